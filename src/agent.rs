@@ -0,0 +1,374 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use snakegg::native::dirs;
+use tokio::sync::broadcast;
+use crate::logger;
+use crate::daemon_log::{DaemonLog, LogEntry, Severity};
+use crate::manifest::canonical_name;
+use crate::provenance::ProvenanceRegistry;
+
+/// Import name of the agent inside managed environments
+pub const AGENT_PACKAGE: &str = "snakepit_agent";
+
+/// Startup hook; site processes `.pth` files alphabetically, so this runs after the others
+const PTH_FILE: &str = "zz_snakepit_agent.pth";
+
+const PTH_HOOK: &str = "import snakepit_agent; snakepit_agent.startup()\n";
+
+const AGENT_INIT: &str = r#""""snakepit environment agent.
+
+Spot-checks this environment against the manifest snakepit wrote when it last
+changed it, and reports differences to the snakepit daemon. Managed by
+`snakepit agent`; edits are overwritten.
+"""
+import hashlib
+import json
+import os
+import random
+import re
+import socket
+import sys
+import time
+
+_HERE = os.path.dirname(os.path.abspath(__file__))
+_MANIFEST = os.path.join(_HERE, "manifest.json")
+_STAMP = os.path.join(_HERE, ".last-check")
+
+
+def _load():
+    with open(_MANIFEST, encoding="utf-8") as f:
+        return json.load(f)
+
+
+def _canonical(name):
+    return re.sub(r"[-_.]+", "-", name).lower()
+
+
+def _sha256(path):
+    digest = hashlib.sha256()
+    with open(path, "rb") as f:
+        for block in iter(lambda: f.read(65536), b""):
+            digest.update(block)
+    return digest.hexdigest()
+
+
+def check(manifest, sample=None):
+    """Differences between the environment and the manifest; every file unless `sample` is set."""
+    problems = []
+    site = os.path.dirname(_HERE)
+    installed = {}
+    for entry in os.listdir(site):
+        if entry.endswith(".dist-info") and "-" in entry:
+            name, version = entry[: -len(".dist-info")].split("-", 1)
+            installed[_canonical(name)] = version
+
+    expected = manifest["versions"]
+    for name in sorted(expected):
+        found = installed.get(name)
+        if found is None:
+            problems.append("%s %s is missing" % (name, expected[name]))
+        elif found != expected[name]:
+            problems.append("%s changed from %s to %s" % (name, expected[name], found))
+    for name in sorted(set(installed) - set(expected)):
+        problems.append("%s %s was installed outside snakepit" % (name, installed[name]))
+
+    files = sorted(manifest["files"].items())
+    if sample is not None and len(files) > sample:
+        files = random.sample(files, sample)
+    for path, digest in files:
+        try:
+            if _sha256(path) != digest:
+                problems.append("%s was modified" % path)
+        except OSError:
+            problems.append("%s is missing" % path)
+    return problems
+
+
+def report(manifest, problems):
+    """Hand problems to the daemon; False when it isn't listening."""
+    path = manifest.get("socket")
+    if not path or not hasattr(socket, "AF_UNIX"):
+        return False
+    message = {"environment": manifest["environment"], "python": sys.executable, "problems": problems}
+    try:
+        client = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        client.settimeout(0.5)
+        client.connect(path)
+        client.sendall((json.dumps(message) + "\n").encode("utf-8"))
+        client.close()
+        return True
+    except OSError:
+        return False
+
+
+def startup():
+    """Run from the .pth hook: a quick sampled check at most once per interval, never raising."""
+    try:
+        manifest = _load()
+        try:
+            if time.time() - os.path.getmtime(_STAMP) < manifest["interval"]:
+                return
+        except OSError:
+            pass
+        try:
+            with open(_STAMP, "w"):
+                pass
+        except OSError:
+            pass
+        problems = check(manifest, manifest["sample_size"])
+        if problems:
+            report(manifest, problems)
+    except Exception:
+        pass
+"#;
+
+const AGENT_MAIN: &str = r#""""`python -m snakepit_agent`: full check, exit status 1 on any problem."""
+import sys
+
+from . import _load, check, report
+
+
+def main():
+    manifest = _load()
+    problems = check(manifest)
+    for problem in problems:
+        print("snakepit-agent: " + problem)
+    if problems:
+        report(manifest, problems)
+        return 1
+    print("snakepit-agent: %s is intact (%d packages, %d files)"
+          % (manifest["environment"], len(manifest["versions"]), len(manifest["files"])))
+    return 0
+
+
+sys.exit(main())
+"#;
+
+/// What the agent compares the environment against, written next to it as `manifest.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentManifest {
+    pub environment: PathBuf,
+    /// Where the daemon listens for reports
+    pub socket: Option<PathBuf>,
+    /// Minimum seconds between startup checks
+    pub interval: u64,
+    /// Files hashed per startup check
+    pub sample_size: usize,
+    /// sha256 of every tracked file
+    pub files: BTreeMap<PathBuf, String>,
+    /// Installed distributions by normalized name
+    pub versions: BTreeMap<String, String>,
+}
+
+/// What an agent sends the daemon when a check fails
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentReport {
+    pub environment: PathBuf,
+    pub python: Option<String>,
+    pub problems: Vec<String>,
+}
+
+/// Installs and maintains the integrity agent in one environment's site-packages
+pub struct EnvironmentAgent {
+    site_packages: PathBuf,
+    interval: u64,
+    sample_size: usize,
+}
+
+impl EnvironmentAgent {
+    pub fn new(site_packages: PathBuf) -> Self {
+        Self { site_packages, interval: 300, sample_size: 5 }
+    }
+
+    pub fn with_interval(mut self, seconds: u64) -> Self {
+        self.interval = seconds;
+        self
+    }
+
+    pub fn with_sample_size(mut self, files: usize) -> Self {
+        self.sample_size = files;
+        self
+    }
+
+    fn package_dir(&self) -> PathBuf {
+        self.site_packages.join(AGENT_PACKAGE)
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.site_packages.join(PTH_FILE).exists()
+    }
+
+    /// Write the agent, its startup hook and a fresh manifest
+    pub fn install(&self) -> Result<AgentManifest> {
+        let dir = self.package_dir();
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("__init__.py"), AGENT_INIT)?;
+        std::fs::write(dir.join("__main__.py"), AGENT_MAIN)?;
+        let manifest = self.refresh()?;
+        std::fs::write(self.site_packages.join(PTH_FILE), PTH_HOOK)?;
+        Ok(manifest)
+    }
+
+    /// Record the environment as it is now, so the agent treats it as the good state
+    pub fn refresh(&self) -> Result<AgentManifest> {
+        let manifest = self.build_manifest()?;
+        let dir = self.package_dir();
+        std::fs::write(dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+        // The next interpreter start should check against the new manifest right away
+        let _ = std::fs::remove_file(dir.join(".last-check"));
+        Ok(manifest)
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        let pth = self.site_packages.join(PTH_FILE);
+        if pth.exists() {
+            std::fs::remove_file(pth)?;
+        }
+        let dir = self.package_dir();
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Re-baseline after snakepit changed `site_packages`, if the agent is there; never fails the caller
+    pub fn refresh_if_installed(site_packages: &Path) {
+        let agent = Self::new(site_packages.to_path_buf());
+        if !agent.is_installed() {
+            return;
+        }
+        match agent.refresh() {
+            Ok(manifest) => logger::note(format!("🛡️  Agent manifest updated ({} files)", manifest.files.len())),
+            Err(e) => logger::warn(format!("⚠️  Could not update the environment agent: {}", e)),
+        }
+    }
+
+    fn build_manifest(&self) -> Result<AgentManifest> {
+        let registry = ProvenanceRegistry::for_site_packages(&self.site_packages)?;
+        let own = self.package_dir();
+
+        let mut files = BTreeMap::new();
+        for path in registry.tracked_files() {
+            // Bytecode is rewritten by the interpreter, and the agent checking itself proves nothing
+            if path.starts_with(&own) || path.extension().is_some_and(|ext| ext == "pyc") {
+                continue;
+            }
+            if let Ok(bytes) = std::fs::read(&path) {
+                files.insert(path, snakegg::native::hash::compute_sha256_hex(&bytes));
+            }
+        }
+
+        let mut versions = BTreeMap::new();
+        for entry in std::fs::read_dir(&self.site_packages)?.flatten() {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            let Some(stem) = dir_name.strip_suffix(".dist-info") else { continue };
+            let Some((name, version)) = stem.split_once('-') else { continue };
+            versions.insert(canonical_name(name), version.to_string());
+        }
+
+        let environment = self.site_packages.ancestors()
+            .find(|dir| dir.join("pyvenv.cfg").exists())
+            .unwrap_or(&self.site_packages)
+            .to_path_buf();
+
+        Ok(AgentManifest {
+            environment,
+            socket: socket_path().ok(),
+            interval: self.interval,
+            sample_size: self.sample_size,
+            files,
+            versions,
+        })
+    }
+}
+
+/// Socket agents report to
+pub fn socket_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data_dir.join("snakepit").join("agent.sock"))
+}
+
+/// Accept agent reports on `path` and turn each into a warning in the daemon log
+#[cfg(unix)]
+pub fn listen(path: &Path, journal: DaemonLog, entries: broadcast::Sender<LogEntry>) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let _ = std::fs::remove_file(path);
+    let listener = tokio::net::UnixListener::bind(path)?;
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let journal = journal.clone();
+            let entries = entries.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stream).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let Ok(report) = serde_json::from_str::<AgentReport>(&line) else { continue };
+                    let entry = LogEntry::new(
+                        Severity::Warn,
+                        "agent",
+                        Some(report.environment.clone()),
+                        format!("Environment drifted from snakepit's records: {}", report.problems.join("; ")),
+                    );
+                    let _ = journal.append(&entry).await;
+                    let _ = entries.send(entry);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen(_path: &Path, _journal: DaemonLog, _entries: broadcast::Sender<LogEntry>) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_detects_tampering() {
+        let venv = std::env::temp_dir().join(format!("snakepit-agent-{}", std::process::id()));
+        let site = venv.join("lib").join("python3.11").join("site-packages");
+        std::fs::create_dir_all(site.join("demo-1.0.dist-info")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        std::fs::write(site.join("demo.py"), "VALUE = 1\n").unwrap();
+        std::fs::write(site.join("demo-1.0.dist-info").join("RECORD"), "demo.py,,\n").unwrap();
+
+        let agent = EnvironmentAgent::new(site.clone());
+        let manifest = agent.install().unwrap();
+        assert!(agent.is_installed());
+        assert_eq!(manifest.environment, venv);
+        assert_eq!(manifest.versions.get("demo").map(String::as_str), Some("1.0"));
+        assert!(manifest.files.contains_key(&site.join("demo.py")));
+
+        // Exercise the Python side when an interpreter is around
+        let check = || std::process::Command::new("python3")
+            .args(["-c", "import json, snakepit_agent as a; print(json.dumps(a.check(a._load())))"])
+            .env("PYTHONPATH", &site)
+            .output();
+        if let Ok(output) = check() {
+            if output.status.success() {
+                assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "[]");
+
+                std::fs::write(site.join("demo.py"), "VALUE = 2\n").unwrap();
+                std::fs::create_dir_all(site.join("rogue-0.1.dist-info")).unwrap();
+                let problems: Vec<String> = serde_json::from_slice(&check().unwrap().stdout).unwrap();
+                assert!(problems.iter().any(|p| p.ends_with("demo.py was modified")));
+                assert!(problems.contains(&"rogue 0.1 was installed outside snakepit".to_string()));
+            }
+        }
+
+        agent.remove().unwrap();
+        assert!(!agent.is_installed() && !site.join(AGENT_PACKAGE).exists());
+        std::fs::remove_dir_all(&venv).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use crate::daemon::Detection;
+
+/// A missing module waiting for `snakepit daemon approve` or `deny`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingInstall {
+    #[serde(flatten)]
+    pub detection: Detection,
+    /// Unix seconds
+    pub queued_at: u64,
+}
+
+/// Missing modules the daemon won't install until someone approves them. Approved ones are
+/// handed to the monitoring loop; denied ones aren't queued again until the daemon restarts.
+#[derive(Debug, Default)]
+pub struct ApprovalQueue {
+    pending: Mutex<Vec<PendingInstall>>,
+    denied: Mutex<HashSet<String>>,
+    approved: Mutex<Vec<Detection>>,
+    ready: Notify,
+}
+
+impl ApprovalQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `detection`; false when its module is already waiting or was denied
+    pub fn enqueue(&self, detection: Detection) -> bool {
+        if self.denied.lock().unwrap().contains(&detection.module) {
+            return false;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        if pending.iter().any(|item| item.detection.module == detection.module) {
+            return false;
+        }
+        let queued_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        pending.push(PendingInstall { detection, queued_at });
+        true
+    }
+
+    /// Take `module` off the queue and hand it to the monitoring loop
+    pub fn approve(&self, module: &str) -> Option<Detection> {
+        let detection = self.take(module)?;
+        self.approved.lock().unwrap().push(detection.clone());
+        self.ready.notify_one();
+        Some(detection)
+    }
+
+    /// Take `module` off the queue and stop asking about it
+    pub fn deny(&self, module: &str) -> Option<Detection> {
+        let detection = self.take(module)?;
+        self.denied.lock().unwrap().insert(detection.module.clone());
+        Some(detection)
+    }
+
+    /// Oldest first
+    pub fn pending(&self) -> Vec<PendingInstall> {
+        self.pending.lock().unwrap().clone()
+    }
+
+    /// Wait until something has been approved
+    pub async fn approved(&self) {
+        self.ready.notified().await
+    }
+
+    /// The approved modules not yet installed
+    pub fn take_approved(&self) -> Vec<Detection> {
+        std::mem::take(&mut *self.approved.lock().unwrap())
+    }
+
+    fn take(&self, module: &str) -> Option<Detection> {
+        let mut pending = self.pending.lock().unwrap();
+        let index = pending.iter().position(|item| item.detection.module == module)?;
+        Some(pending.remove(index).detection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(module: &str) -> Detection {
+        Detection::new(module.to_string(), "process 7".to_string())
+    }
+
+    #[test]
+    fn test_queue_resolves_approvals_and_denials() {
+        let queue = ApprovalQueue::new();
+        assert!(queue.enqueue(detection("yaml")));
+        assert!(!queue.enqueue(detection("yaml")));
+        assert!(queue.enqueue(detection("requests")));
+        assert_eq!(queue.pending().len(), 2);
+
+        assert_eq!(queue.approve("yaml").map(|d| d.module).as_deref(), Some("yaml"));
+        assert!(queue.approve("yaml").is_none());
+        assert_eq!(queue.take_approved().len(), 1);
+        assert!(queue.take_approved().is_empty());
+
+        assert!(queue.deny("requests").is_some());
+        assert!(!queue.enqueue(detection("requests")));
+        assert!(queue.pending().is_empty());
+    }
+}
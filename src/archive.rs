@@ -0,0 +1,350 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// How an archive's member paths map onto an environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Relative to site-packages, with `{name}-{version}.data/` routed per the wheel spec
+    Wheel,
+    /// Relative to the environment prefix (`lib/`, `bin/`, ...), as conda packages are
+    Prefix,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryKind {
+    Dir,
+    File(Vec<u8>),
+    Symlink(PathBuf),
+}
+
+/// The build prefix a conda package baked into a file, replaced with the real prefix on install
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub prefix: String,
+    /// Binary files keep their length: the new prefix is NUL-padded to fit
+    pub binary: bool,
+}
+
+/// One member of a package archive, read into memory
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub kind: EntryKind,
+    pub mode: Option<u32>,
+    pub placeholder: Option<Placeholder>,
+}
+
+/// A package archive format the native installer can unpack.
+///
+/// Formats only turn bytes into entries; staging, hash verification and the
+/// journaled move into the environment are shared by all of them.
+pub trait ArchiveFormat: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Whether `filename` is an archive of this format
+    fn matches(&self, filename: &str) -> bool;
+
+    fn layout(&self) -> Layout;
+
+    /// Distribution name and version encoded in the filename
+    fn parse_filename(&self, filename: &str) -> Option<(String, String)>;
+
+    fn entries(&self, bytes: &[u8]) -> Result<Vec<ArchiveEntry>>;
+}
+
+/// Zip wheels, whatever each member is compressed with: deflate today, zstd for newer wheel variants
+pub struct WheelFormat;
+
+impl ArchiveFormat for WheelFormat {
+    fn name(&self) -> &str {
+        "wheel"
+    }
+
+    fn matches(&self, filename: &str) -> bool {
+        filename.ends_with(".whl")
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::Wheel
+    }
+
+    fn parse_filename(&self, filename: &str) -> Option<(String, String)> {
+        let mut parts = filename.strip_suffix(".whl")?.split('-');
+        Some((parts.next()?.to_string(), parts.next()?.to_string()))
+    }
+
+    fn entries(&self, bytes: &[u8]) -> Result<Vec<ArchiveEntry>> {
+        read_zip(bytes)
+    }
+}
+
+/// conda's v2 format: a zip holding zstd-compressed `info-*.tar.zst` and `pkg-*.tar.zst`
+pub struct CondaFormat;
+
+impl ArchiveFormat for CondaFormat {
+    fn name(&self) -> &str {
+        "conda"
+    }
+
+    fn matches(&self, filename: &str) -> bool {
+        filename.ends_with(".conda")
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::Prefix
+    }
+
+    fn parse_filename(&self, filename: &str) -> Option<(String, String)> {
+        parse_conda_filename(filename.strip_suffix(".conda")?)
+    }
+
+    fn entries(&self, bytes: &[u8]) -> Result<Vec<ArchiveEntry>> {
+        let mut outer = zip::ZipArchive::new(Cursor::new(bytes))?;
+        let mut entries = Vec::new();
+        for i in 0..outer.len() {
+            let mut member = outer.by_index(i)?;
+            let name = member.name().to_string();
+            if !(name.starts_with("info-") || name.starts_with("pkg-")) || !name.ends_with(".tar.zst") {
+                continue;
+            }
+            let mut compressed = Vec::new();
+            member.read_to_end(&mut compressed)?;
+            entries.extend(read_tar(zstd::stream::read::Decoder::new(Cursor::new(compressed))?)?);
+        }
+        if entries.is_empty() {
+            return Err(anyhow::anyhow!("Not a conda package: no info-/pkg- tarballs inside"));
+        }
+        with_placeholders(entries)
+    }
+}
+
+/// conda's original format: one bzip2-compressed tarball
+pub struct CondaTarBz2Format;
+
+impl ArchiveFormat for CondaTarBz2Format {
+    fn name(&self) -> &str {
+        "conda-tar-bz2"
+    }
+
+    fn matches(&self, filename: &str) -> bool {
+        filename.ends_with(".tar.bz2")
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::Prefix
+    }
+
+    fn parse_filename(&self, filename: &str) -> Option<(String, String)> {
+        parse_conda_filename(filename.strip_suffix(".tar.bz2")?)
+    }
+
+    fn entries(&self, bytes: &[u8]) -> Result<Vec<ArchiveEntry>> {
+        with_placeholders(read_tar(bzip2::read::BzDecoder::new(Cursor::new(bytes)))?)
+    }
+}
+
+/// `{name}-{version}-{build}`; names may contain dashes, versions and builds may not
+fn parse_conda_filename(stem: &str) -> Option<(String, String)> {
+    let mut parts = stem.rsplitn(3, '-');
+    let _build = parts.next()?;
+    let version = parts.next()?;
+    let name = parts.next()?;
+    Some((name.to_string(), version.to_string()))
+}
+
+fn read_zip(bytes: &[u8]) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let kind = if file.is_dir() {
+            EntryKind::Dir
+        } else {
+            let mut contents = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut contents)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.name(), e))?;
+            EntryKind::File(contents)
+        };
+        entries.push(ArchiveEntry {
+            name: file.name().trim_end_matches('/').to_string(),
+            kind,
+            mode: file.unix_mode(),
+            placeholder: None,
+        });
+    }
+    Ok(entries)
+}
+
+fn read_tar(reader: impl Read) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().trim_end_matches('/').to_string();
+        let entry_type = entry.header().entry_type();
+        let kind = if entry_type.is_dir() {
+            EntryKind::Dir
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()?
+                .ok_or_else(|| anyhow::anyhow!("Symlink {} has no target", name))?;
+            EntryKind::Symlink(target.into_owned())
+        } else if entry_type.is_file() {
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            EntryKind::File(contents)
+        } else {
+            return Err(anyhow::anyhow!("Unsupported entry {} ({:?}) in archive", name, entry_type));
+        };
+        entries.push(ArchiveEntry {
+            name,
+            kind,
+            mode: entry.header().mode().ok(),
+            placeholder: None,
+        });
+    }
+    Ok(entries)
+}
+
+/// Attach the prefix placeholders listed in `info/paths.json` (or the older `info/has_prefix`)
+fn with_placeholders(mut entries: Vec<ArchiveEntry>) -> Result<Vec<ArchiveEntry>> {
+    let file = |name: &str| entries.iter().find_map(|e| match &e.kind {
+        EntryKind::File(contents) if e.name == name => Some(String::from_utf8_lossy(contents).to_string()),
+        _ => None,
+    });
+
+    let mut placeholders: HashMap<String, Placeholder> = HashMap::new();
+    if let Some(paths) = file("info/paths.json") {
+        let doc: serde_json::Value = serde_json::from_str(&paths)?;
+        for path in doc["paths"].as_array().into_iter().flatten() {
+            if let (Some(name), Some(prefix)) = (path["_path"].as_str(), path["prefix_placeholder"].as_str()) {
+                let binary = path["file_mode"].as_str() == Some("binary");
+                placeholders.insert(name.to_string(), Placeholder { prefix: prefix.to_string(), binary });
+            }
+        }
+    } else if let Some(has_prefix) = file("info/has_prefix") {
+        // `<placeholder> <text|binary> <path>`, or just `<path>` with the default placeholder
+        for line in has_prefix.lines().filter(|l| !l.trim().is_empty()) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if let [prefix, mode, name] = fields.as_slice() {
+                let placeholder = Placeholder { prefix: prefix.trim_matches('"').to_string(), binary: *mode == "binary" };
+                placeholders.insert(name.trim_matches('"').to_string(), placeholder);
+            }
+        }
+    }
+
+    for entry in &mut entries {
+        entry.placeholder = placeholders.remove(&entry.name);
+    }
+    Ok(entries)
+}
+
+/// Replace a build prefix with the install prefix, the way conda does
+pub fn relocate(contents: Vec<u8>, placeholder: &Placeholder, prefix: &str) -> Result<Vec<u8>> {
+    let old = placeholder.prefix.as_bytes();
+    let new = prefix.as_bytes();
+    if old.is_empty() || !contents.windows(old.len()).any(|w| w == old) {
+        return Ok(contents);
+    }
+
+    if !placeholder.binary {
+        let mut out = Vec::with_capacity(contents.len());
+        let mut rest = contents.as_slice();
+        while let Some(at) = rest.windows(old.len()).position(|w| w == old) {
+            out.extend_from_slice(&rest[..at]);
+            out.extend_from_slice(new);
+            rest = &rest[at + old.len()..];
+        }
+        out.extend_from_slice(rest);
+        return Ok(out);
+    }
+
+    // Binary: rewrite each NUL-terminated string holding the placeholder, padding it back to size
+    if new.len() > old.len() {
+        return Err(anyhow::anyhow!(
+            "Install prefix {} is longer than the package's build prefix ({} bytes); binaries can't be relocated",
+            prefix, old.len()
+        ));
+    }
+    let mut out = contents.clone();
+    let mut start = 0;
+    while let Some(offset) = contents[start..].windows(old.len()).position(|w| w == old) {
+        let at = start + offset;
+        let end = contents[at..].iter().position(|b| *b == 0).map_or(contents.len(), |n| at + n);
+        let mut segment = Vec::with_capacity(end - at);
+        let mut rest = &contents[at..end];
+        while let Some(pos) = rest.windows(old.len()).position(|w| w == old) {
+            segment.extend_from_slice(&rest[..pos]);
+            segment.extend_from_slice(new);
+            rest = &rest[pos + old.len()..];
+        }
+        segment.extend_from_slice(rest);
+        segment.resize(end - at, 0);
+        out[at..end].copy_from_slice(&segment);
+        start = end;
+    }
+    Ok(out)
+}
+
+fn registry() -> &'static RwLock<Vec<Arc<dyn ArchiveFormat>>> {
+    static REGISTRY: OnceLock<RwLock<Vec<Arc<dyn ArchiveFormat>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(vec![
+        Arc::new(WheelFormat),
+        Arc::new(CondaFormat),
+        Arc::new(CondaTarBz2Format),
+    ]))
+}
+
+/// Make another archive format installable; it takes precedence over the built-in ones
+pub fn register(format: impl ArchiveFormat + 'static) {
+    registry().write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(0, Arc::new(format));
+}
+
+/// The format that can unpack `filename`
+pub fn format_for(filename: &str) -> Result<Arc<dyn ArchiveFormat>> {
+    registry().read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|format| format.matches(filename))
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Don't know how to unpack {}", filename))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_by_filename() {
+        let conda = format_for("python-dateutil-2.8.2-pyhd8ed1ab_0.conda").unwrap();
+        assert_eq!(conda.name(), "conda");
+        assert_eq!(conda.layout(), Layout::Prefix);
+        assert_eq!(
+            conda.parse_filename("python-dateutil-2.8.2-pyhd8ed1ab_0.conda"),
+            Some(("python-dateutil".to_string(), "2.8.2".to_string()))
+        );
+        let wheel = format_for("requests-2.31.0-py3-none-any.whl").unwrap();
+        assert_eq!(wheel.parse_filename("requests-2.31.0-py3-none-any.whl"), Some(("requests".to_string(), "2.31.0".to_string())));
+        assert_eq!(format_for("six-1.16.0-py_0.tar.bz2").unwrap().name(), "conda-tar-bz2");
+        assert!(format_for("requests-2.31.0.tar.gz").is_err());
+    }
+
+    #[test]
+    fn test_relocate_text_and_binary() {
+        let text = Placeholder { prefix: "/opt/build_env".to_string(), binary: false };
+        let script = b"#!/opt/build_env/bin/python\nPREFIX=/opt/build_env\n".to_vec();
+        assert_eq!(relocate(script, &text, "/home/me/env").unwrap(), b"#!/home/me/env/bin/python\nPREFIX=/home/me/env\n");
+
+        let binary = Placeholder { prefix: "/opt/build_env".to_string(), binary: true };
+        let lib = b"\x7fELF\0/opt/build_env/lib\0tail".to_vec();
+        let relocated = relocate(lib.clone(), &binary, "/env").unwrap();
+        assert_eq!(relocated.len(), lib.len());
+        assert_eq!(&relocated[..14], b"\x7fELF\0/env/lib\0");
+        assert!(relocated.ends_with(b"\0tail"));
+        assert!(relocate(lib, &binary, "/a/much/longer/install/prefix").is_err());
+    }
+}
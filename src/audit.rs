@@ -0,0 +1,391 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+use crate::pep440::Version;
+
+const OSV_API: &str = "https://api.osv.dev/v1";
+
+/// OSV's export of every PyPI advisory, for `audit --update-db`
+pub const OSV_PYPI_EXPORT: &str = "https://osv-vulnerabilities.storage.googleapis.com/PyPI/all.zip";
+
+/// OSV.dev caps a batch query at this many packages
+const BATCH_SIZE: usize = 1000;
+
+/// An advisory in the OSV schema (https://ossf.github.io/osv-schema/); only what the audit reads
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvRecord {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub withdrawn: Option<String>,
+    #[serde(default)]
+    affected: Vec<Affected>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Affected {
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct OsvEvent {
+    introduced: Option<String>,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+}
+
+impl OsvRecord {
+    /// The `affected` entries for PyPI package `name`
+    fn entries_for<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Affected> + 'a {
+        let name = canonical_name(name);
+        self.affected.iter().filter(move |affected| affected.package.as_ref()
+            .is_some_and(|p| p.ecosystem == "PyPI" && canonical_name(&p.name) == name))
+    }
+
+    /// Whether `version` of `name` is in one of the advisory's ranges or version lists
+    pub fn affects(&self, name: &str, version: &str) -> bool {
+        let Ok(version) = Version::parse(version) else { return false };
+        self.entries_for(name).any(|affected| {
+            affected.versions.iter().any(|v| Version::parse(v).is_ok_and(|v| v == version))
+                || affected.ranges.iter()
+                    .filter(|range| range.kind == "ECOSYSTEM")
+                    .any(|range| in_range(&range.events, &version))
+        })
+    }
+
+    /// Versions of `name` the advisory says are fixed, lowest first
+    pub fn fixed_versions(&self, name: &str) -> Vec<String> {
+        let mut fixed: Vec<(Version, String)> = self.entries_for(name)
+            .flat_map(|affected| affected.ranges.iter())
+            .flat_map(|range| range.events.iter())
+            .filter_map(|event| event.fixed.clone())
+            .filter_map(|fixed| Some((Version::parse(&fixed).ok()?, fixed)))
+            .collect();
+        fixed.sort();
+        fixed.dedup_by(|a, b| a.0 == b.0);
+        fixed.into_iter().map(|(_, v)| v).collect()
+    }
+}
+
+/// OSV range evaluation: walk the events in version order; `introduced` opens the range,
+/// `fixed` (at or below `version`) and `last_affected` (below it) close it again.
+fn in_range(events: &[OsvEvent], version: &Version) -> bool {
+    let mut sorted: Vec<(Version, &OsvEvent)> = events.iter()
+        .filter_map(|event| {
+            let raw = event.introduced.as_deref().or(event.fixed.as_deref()).or(event.last_affected.as_deref())?;
+            Some((Version::parse(raw).ok()?, event))
+        })
+        .collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut affected = false;
+    for (at, event) in sorted {
+        if event.introduced.is_some() && at <= *version {
+            affected = true;
+        } else if event.fixed.is_some() && at <= *version {
+            affected = false;
+        } else if event.last_affected.is_some() && at < *version {
+            affected = false;
+        }
+    }
+    affected
+}
+
+/// A package version one advisory applies to
+#[derive(Debug, Clone, Serialize)]
+pub struct Vulnerability {
+    pub package: String,
+    pub version: String,
+    pub id: String,
+    /// CVE and GHSA IDs of the same advisory
+    pub aliases: Vec<String>,
+    pub summary: Option<String>,
+    /// Empty when no fixed release exists yet
+    pub fixed_versions: Vec<String>,
+}
+
+impl Vulnerability {
+    fn new(record: &OsvRecord, package: &str, version: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            version: version.to_string(),
+            id: record.id.clone(),
+            aliases: record.aliases.clone(),
+            summary: record.summary.clone(),
+            fixed_versions: record.fixed_versions(package),
+        }
+    }
+
+    /// The CVE IDs among the advisory's ID and aliases
+    pub fn cves(&self) -> Vec<&str> {
+        std::iter::once(&self.id).chain(&self.aliases)
+            .filter(|id| id.starts_with("CVE-"))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Whether `ignored` names this advisory by its ID or any alias
+    fn is_ignored(&self, ignored: &[String]) -> bool {
+        ignored.iter().any(|i| i.eq_ignore_ascii_case(&self.id) || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(i)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    /// `osv.dev`, or the path of the offline snapshot
+    pub source: String,
+    /// How many packages were checked
+    pub packages: usize,
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// Advisory IDs that matched but were accepted with `--ignore`
+    pub ignored: Vec<String>,
+}
+
+/// Where advisories come from
+pub enum AdvisorySource {
+    /// OSV.dev's query API
+    Online,
+    /// An OSV PyPI export, indexed by canonical package name
+    Snapshot { path: PathBuf, records: HashMap<String, Vec<OsvRecord>> },
+}
+
+impl AdvisorySource {
+    /// An OSV export: the `all.zip` OSV publishes, or a directory of its JSON files
+    pub fn snapshot(path: &Path) -> Result<Self> {
+        let mut records: HashMap<String, Vec<OsvRecord>> = HashMap::new();
+        let mut add = |json: &[u8]| {
+            // Not every file in an export is an advisory we can read; skip the rest
+            let Ok(record) = serde_json::from_slice::<OsvRecord>(json) else { return };
+            if record.withdrawn.is_some() {
+                return;
+            }
+            let names: BTreeSet<String> = record.affected.iter()
+                .filter_map(|a| a.package.as_ref())
+                .filter(|p| p.ecosystem == "PyPI")
+                .map(|p| canonical_name(&p.name))
+                .collect();
+            for name in names {
+                records.entry(name).or_default().push(record.clone());
+            }
+        };
+
+        if path.is_dir() {
+            for entry in std::fs::read_dir(path)?.flatten() {
+                if entry.path().extension().is_some_and(|ext| ext == "json") {
+                    add(&std::fs::read(entry.path())?);
+                }
+            }
+        } else {
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("Failed to read advisory snapshot {} (run `snakepit audit --update-db`)", path.display()))?;
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+                .with_context(|| format!("{} is not an OSV export zip", path.display()))?;
+            for i in 0..archive.len() {
+                let mut file = archive.by_index(i)?;
+                if !file.name().ends_with(".json") {
+                    continue;
+                }
+                let mut json = Vec::new();
+                file.read_to_end(&mut json)?;
+                add(&json);
+            }
+        }
+        Ok(AdvisorySource::Snapshot { path: path.to_path_buf(), records })
+    }
+
+    /// Where `audit --offline` and `--update-db` keep the export
+    pub fn default_snapshot_path(cache_dir: &Path) -> PathBuf {
+        cache_dir.join("osv").join("PyPI-all.zip")
+    }
+
+    /// Download OSV's PyPI export to `path`, replacing any older copy
+    pub async fn download_snapshot(path: &Path) -> Result<u64> {
+        let bytes = reqwest::get(OSV_PYPI_EXPORT).await?
+            .error_for_status()
+            .context("Failed to download the OSV PyPI export")?
+            .bytes().await?;
+        zip::ZipArchive::new(Cursor::new(&bytes[..])).context("The OSV PyPI export is not a valid zip")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let staged = path.with_extension("zip.part");
+        std::fs::write(&staged, &bytes)?;
+        std::fs::rename(&staged, path)?;
+        Ok(bytes.len() as u64)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            AdvisorySource::Online => "osv.dev".to_string(),
+            AdvisorySource::Snapshot { path, .. } => path.display().to_string(),
+        }
+    }
+
+    /// Every advisory that applies to one of `packages` (name, version)
+    pub async fn audit(&self, packages: &[(String, String)], ignored: &[String]) -> Result<AuditReport> {
+        let mut found = match self {
+            AdvisorySource::Online => query_osv(packages).await?,
+            AdvisorySource::Snapshot { records, .. } => packages.iter()
+                .flat_map(|(name, version)| records.get(&canonical_name(name)).into_iter().flatten()
+                    .filter(|record| record.affects(name, version))
+                    .map(|record| Vulnerability::new(record, name, version)))
+                .collect(),
+        };
+        found.sort_by(|a, b| (canonical_name(&a.package), &a.id).cmp(&(canonical_name(&b.package), &b.id)));
+
+        let (accepted, vulnerabilities): (Vec<Vulnerability>, Vec<Vulnerability>) = found.into_iter()
+            .partition(|v| v.is_ignored(ignored));
+        Ok(AuditReport {
+            source: self.describe(),
+            packages: packages.len(),
+            vulnerabilities,
+            ignored: accepted.into_iter().map(|v| v.id).collect(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    results: Vec<BatchResult>,
+}
+
+#[derive(Deserialize)]
+struct BatchResult {
+    #[serde(default)]
+    vulns: Vec<BatchVuln>,
+}
+
+#[derive(Deserialize)]
+struct BatchVuln {
+    id: String,
+}
+
+/// Batch-query OSV.dev for advisory IDs, then fetch each advisory once for its details
+async fn query_osv(packages: &[(String, String)]) -> Result<Vec<Vulnerability>> {
+    let client = reqwest::Client::new();
+    let mut matches: Vec<(usize, String)> = Vec::new();
+    for (offset, chunk) in packages.chunks(BATCH_SIZE).enumerate() {
+        let queries: Vec<serde_json::Value> = chunk.iter()
+            .map(|(name, version)| serde_json::json!({"package": {"name": name, "ecosystem": "PyPI"}, "version": version}))
+            .collect();
+        let response: BatchResponse = client.post(format!("{}/querybatch", OSV_API))
+            .json(&serde_json::json!({ "queries": queries }))
+            .send().await
+            .context("Failed to reach OSV.dev (use --offline with a snapshot from --update-db)")?
+            .error_for_status()?
+            .json().await?;
+        for (i, result) in response.results.into_iter().enumerate() {
+            matches.extend(result.vulns.into_iter().map(|v| (offset * BATCH_SIZE + i, v.id)));
+        }
+    }
+
+    let ids: BTreeSet<String> = matches.iter().map(|(_, id)| id.clone()).collect();
+    let mut tasks = tokio::task::JoinSet::new();
+    for id in ids {
+        let client = client.clone();
+        tasks.spawn(async move {
+            let record: Result<OsvRecord> = async {
+                Ok(client.get(format!("{}/vulns/{}", OSV_API, id)).send().await?.error_for_status()?.json().await?)
+            }.await;
+            record.with_context(|| format!("Failed to fetch advisory {}", id))
+        });
+    }
+    let mut records: HashMap<String, OsvRecord> = HashMap::new();
+    while let Some(record) = tasks.join_next().await {
+        let record = record??;
+        records.insert(record.id.clone(), record);
+    }
+
+    let mut found = Vec::new();
+    for (i, id) in matches {
+        let (name, version) = &packages[i];
+        let record = records.get(&id).ok_or_else(|| anyhow!("OSV.dev returned no details for {}", id))?;
+        if record.withdrawn.is_none() {
+            found.push(Vulnerability::new(record, name, version));
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> OsvRecord {
+        serde_json::from_str(r#"{
+            "id": "GHSA-j8r2-6x86-q33q",
+            "aliases": ["CVE-2023-32681"],
+            "summary": "Unintended leak of Proxy-Authorization header in requests",
+            "affected": [{
+                "package": {"ecosystem": "PyPI", "name": "requests"},
+                "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "2.3.0"}, {"fixed": "2.31.0"}]}]
+            }, {
+                "package": {"ecosystem": "PyPI", "name": "Requests"},
+                "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"last_affected": "1.2.3"}]}],
+                "versions": ["2.1.0"]
+            }]
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn test_matches_osv_ranges() {
+        let record = record();
+        assert!(record.affects("requests", "2.30.0"));
+        assert!(record.affects("requests", "2.3.0"));
+        assert!(!record.affects("requests", "2.31.0"));
+        assert!(!record.affects("requests", "2.2.0"));
+        assert!(record.affects("requests", "1.2.3"));
+        assert!(!record.affects("requests", "1.2.4"));
+        assert!(record.affects("requests", "2.1.0"));
+        assert!(!record.affects("urllib3", "1.0.0"));
+        assert_eq!(record.fixed_versions("requests"), vec!["2.31.0"]);
+    }
+
+    #[tokio::test]
+    async fn test_audits_against_snapshot() {
+        let dir = std::env::temp_dir().join(format!("snakepit-osv-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("GHSA-j8r2-6x86-q33q.json"), serde_json::to_string(&serde_json::json!({
+            "id": "GHSA-j8r2-6x86-q33q",
+            "aliases": ["CVE-2023-32681"],
+            "affected": [{"package": {"ecosystem": "PyPI", "name": "requests"}, "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "2.3.0"}, {"fixed": "2.31.0"}]}]}]
+        })).unwrap()).unwrap();
+
+        let source = AdvisorySource::snapshot(&dir).unwrap();
+        let packages = vec![("Requests".to_string(), "2.28.0".to_string()), ("flask".to_string(), "3.0.0".to_string())];
+        let report = source.audit(&packages, &[]).await.unwrap();
+        assert_eq!(report.packages, 2);
+        assert_eq!(report.vulnerabilities.len(), 1);
+        assert_eq!(report.vulnerabilities[0].cves(), vec!["CVE-2023-32681"]);
+        assert_eq!(report.vulnerabilities[0].fixed_versions, vec!["2.31.0"]);
+
+        let report = source.audit(&packages, &["cve-2023-32681".to_string()]).await.unwrap();
+        assert!(report.vulnerabilities.is_empty());
+        assert_eq!(report.ignored, vec!["GHSA-j8r2-6x86-q33q"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
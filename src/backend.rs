@@ -0,0 +1,348 @@
+use crate::installer::PackageInstaller;
+use crate::logger;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::process::Command;
+use std::sync::{Arc, OnceLock, RwLock};
+
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// A package manager snakepit can install through.
+///
+/// Every method receives the [`PackageInstaller`] driving it, which carries the
+/// target environment, index, constraints and hash requirements.
+pub trait Backend: Send + Sync {
+    /// Name `default_backend` selects it by
+    fn name(&self) -> &str;
+
+    fn install<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str, version: Option<&'a str>) -> BackendFuture<'a, ()>;
+
+    fn uninstall<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, ()>;
+
+    fn list<'a>(&'a self, installer: &'a PackageInstaller) -> BackendFuture<'a, Vec<String>>;
+
+    /// PyPI search; backends with their own index override this
+    fn search<'a>(&'a self, installer: &'a PackageInstaller, query: &'a str) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.search_with_pip(query))
+    }
+
+    fn show<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, String> {
+        Box::pin(installer.show_with_pip(package))
+    }
+
+    /// Command editable installs run through, up to `install`.
+    ///
+    /// Editable installs need the project's build backend, which only pip and uv can drive.
+    fn editable_command(&self, installer: &PackageInstaller) -> Command {
+        installer.pip_install_command()
+    }
+
+    /// Whether `--require-hashes` can be enforced
+    fn supports_hash_checking(&self) -> bool {
+        true
+    }
+
+    /// snakepit writes the files itself, so a package that fails to import can be rolled back
+    fn writes_files_directly(&self) -> bool {
+        false
+    }
+
+    /// Packages this backend needs inside the environment to keep working.
+    ///
+    /// pip belongs on every list: editable installs and validation sandboxes shell out to it
+    /// whichever backend is configured.
+    fn protected_packages(&self) -> &[&str] {
+        &["pip"]
+    }
+}
+
+/// snakepit's own wheel installer
+pub struct NativeBackend;
+
+impl Backend for NativeBackend {
+    fn name(&self) -> &str {
+        "native"
+    }
+
+    fn install<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str, version: Option<&'a str>) -> BackendFuture<'a, ()> {
+        Box::pin(installer.install_with_native(package, version))
+    }
+
+    fn uninstall<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, ()> {
+        Box::pin(installer.uninstall_with_native(package))
+    }
+
+    fn list<'a>(&'a self, installer: &'a PackageInstaller) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.list_with_native())
+    }
+
+    fn writes_files_directly(&self) -> bool {
+        true
+    }
+}
+
+pub struct UvBackend;
+
+impl Backend for UvBackend {
+    fn name(&self) -> &str {
+        "uv"
+    }
+
+    fn install<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str, version: Option<&'a str>) -> BackendFuture<'a, ()> {
+        Box::pin(installer.install_with_uv(package, version))
+    }
+
+    fn uninstall<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, ()> {
+        Box::pin(installer.uninstall_with_uv(package))
+    }
+
+    fn list<'a>(&'a self, installer: &'a PackageInstaller) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.list_with_uv())
+    }
+
+    fn editable_command(&self, installer: &PackageInstaller) -> Command {
+        installer.uv_install_command()
+    }
+
+    fn protected_packages(&self) -> &[&str] {
+        &["pip", "uv"]
+    }
+}
+
+pub struct PipBackend;
+
+impl Backend for PipBackend {
+    fn name(&self) -> &str {
+        "pip"
+    }
+
+    fn install<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str, version: Option<&'a str>) -> BackendFuture<'a, ()> {
+        Box::pin(installer.install_with_pip(package, version))
+    }
+
+    fn uninstall<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, ()> {
+        Box::pin(installer.uninstall_with_pip(package))
+    }
+
+    fn list<'a>(&'a self, installer: &'a PackageInstaller) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.list_with_pip())
+    }
+
+    fn protected_packages(&self) -> &[&str] {
+        &["pip", "setuptools", "wheel"]
+    }
+}
+
+pub struct CondaBackend;
+
+impl Backend for CondaBackend {
+    fn name(&self) -> &str {
+        "conda"
+    }
+
+    fn install<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str, version: Option<&'a str>) -> BackendFuture<'a, ()> {
+        Box::pin(installer.install_with_conda(package, version))
+    }
+
+    fn uninstall<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, ()> {
+        Box::pin(installer.uninstall_with_conda(package))
+    }
+
+    fn list<'a>(&'a self, installer: &'a PackageInstaller) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.list_with_conda())
+    }
+
+    fn search<'a>(&'a self, installer: &'a PackageInstaller, query: &'a str) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.search_with_conda(query))
+    }
+
+    fn show<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, String> {
+        Box::pin(installer.show_with_conda(package))
+    }
+
+    fn supports_hash_checking(&self) -> bool {
+        false
+    }
+
+    fn protected_packages(&self) -> &[&str] {
+        &["pip", "setuptools", "conda", "conda-package-handling"]
+    }
+}
+
+pub struct PoetryBackend;
+
+impl Backend for PoetryBackend {
+    fn name(&self) -> &str {
+        "poetry"
+    }
+
+    fn install<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str, version: Option<&'a str>) -> BackendFuture<'a, ()> {
+        Box::pin(installer.install_with_poetry(package, version))
+    }
+
+    fn uninstall<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, ()> {
+        Box::pin(installer.uninstall_with_poetry(package))
+    }
+
+    fn list<'a>(&'a self, installer: &'a PackageInstaller) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.list_with_poetry())
+    }
+
+    fn search<'a>(&'a self, installer: &'a PackageInstaller, query: &'a str) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(installer.search_with_poetry(query))
+    }
+
+    fn show<'a>(&'a self, installer: &'a PackageInstaller, package: &'a str) -> BackendFuture<'a, String> {
+        Box::pin(installer.show_with_poetry(package))
+    }
+
+    fn supports_hash_checking(&self) -> bool {
+        false
+    }
+
+    fn protected_packages(&self) -> &[&str] {
+        &["pip", "setuptools", "poetry", "poetry-core"]
+    }
+}
+
+fn registry() -> &'static RwLock<HashMap<String, Arc<dyn Backend>>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<dyn Backend>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtins: [Arc<dyn Backend>; 5] = [
+            Arc::new(NativeBackend),
+            Arc::new(UvBackend),
+            Arc::new(PipBackend),
+            Arc::new(CondaBackend),
+            Arc::new(PoetryBackend),
+        ];
+        RwLock::new(builtins.into_iter().map(|b| (b.name().to_string(), b)).collect())
+    })
+}
+
+/// Make a backend selectable by name, replacing any registered under the same name
+pub fn register(backend: impl Backend + 'static) {
+    let backend: Arc<dyn Backend> = Arc::new(backend);
+    registry().write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(backend.name().to_lowercase(), backend);
+}
+
+/// Names of every registered backend, sorted
+pub fn available() -> Vec<String> {
+    let mut names: Vec<String> = registry().read()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect();
+    names.sort();
+    names
+}
+
+/// Handle to the backend an installer uses; cheap to clone into parallel install tasks
+#[derive(Clone)]
+pub struct InstallerBackend(Arc<dyn Backend>);
+
+impl InstallerBackend {
+    pub fn new(backend: impl Backend + 'static) -> Self {
+        Self(Arc::new(backend))
+    }
+
+    /// A registered backend by name
+    pub fn named(name: &str) -> Result<Self> {
+        registry().read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&name.to_lowercase())
+            .cloned()
+            .map(Self)
+            .ok_or_else(|| anyhow::anyhow!("Unknown installer backend '{}' (available: {})", name, available().join(", ")))
+    }
+
+    pub fn detect() -> Self {
+        // Snakepit Native is always available and preferred!
+        Self::new(NativeBackend)
+    }
+
+    /// Backend for a config's `default_backend`: pip when unset or unknown
+    pub fn from_config(name: Option<&str>) -> Self {
+        match name {
+            Some(name) => Self::named(name).unwrap_or_else(|e| {
+                logger::warn(format!("⚠️  {}; falling back to pip", e));
+                Self::new(PipBackend)
+            }),
+            None => Self::new(PipBackend),
+        }
+    }
+
+    pub fn is_protected(&self, package: &str) -> bool {
+        let name = crate::manifest::canonical_name(&crate::dependency::split_extras(package).0);
+        self.protected_packages().contains(&name.as_str())
+    }
+}
+
+impl std::ops::Deref for InstallerBackend {
+    type Target = dyn Backend;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::Debug for InstallerBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct PipxBackend;
+
+    impl Backend for PipxBackend {
+        fn name(&self) -> &str {
+            "pipx"
+        }
+
+        fn install<'a>(&'a self, _installer: &'a PackageInstaller, _package: &'a str, _version: Option<&'a str>) -> BackendFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn uninstall<'a>(&'a self, _installer: &'a PackageInstaller, _package: &'a str) -> BackendFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn list<'a>(&'a self, _installer: &'a PackageInstaller) -> BackendFuture<'a, Vec<String>> {
+            Box::pin(async { Ok(vec!["black==24.1.0".to_string()]) })
+        }
+    }
+
+    #[test]
+    fn test_protected_packages_per_backend() {
+        let named = |name: &str| InstallerBackend::named(name).unwrap();
+        assert!(named("pip").is_protected("SetupTools"));
+        assert!(named("poetry").is_protected("poetry_core"));
+        assert!(named("native").is_protected("pip"));
+        assert!(!named("native").is_protected("setuptools"));
+        assert!(!named("pip").is_protected("requests"));
+    }
+
+    #[tokio::test]
+    async fn test_registered_backends_are_selectable() {
+        assert!(InstallerBackend::named("pipx").is_err());
+        register(PipxBackend);
+
+        let backend = InstallerBackend::named("PipX").unwrap();
+        assert_eq!(backend.name(), "pipx");
+        assert!(available().contains(&"pipx".to_string()));
+        assert!(backend.is_protected("pip"));
+
+        let installer = PackageInstaller::new().with_backend(backend);
+        assert_eq!(installer.list_installed_packages().await.unwrap(), vec!["black==24.1.0"]);
+        assert_eq!(InstallerBackend::from_config(Some("pipx")).name(), "pipx");
+        assert_eq!(InstallerBackend::from_config(None).name(), "pip");
+    }
+}
@@ -0,0 +1,352 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use snakegg::native::style::cyan;
+use snakegg::native::which;
+use crate::logger;
+use crate::dependency::{Dependency, ProjectDependencies};
+use crate::installer::{InstallerBackend, PackageInstaller};
+use crate::resolver::{DependencyResolver, ResolvedDependency};
+use crate::venv::{VenvBackend, VirtualEnvironmentManager};
+
+/// Named package sets for `snakepit bench install <set>`
+const PACKAGE_SETS: &[(&str, &[&str])] = &[
+    ("small", &["requests", "six", "idna"]),
+    ("web", &["flask", "requests", "jinja2", "click"]),
+    ("data", &["numpy", "pandas", "python-dateutil"]),
+];
+
+/// Measurements for one backend, averaged over the successful runs
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub backend: String,
+    pub runs: u32,
+    pub failures: u32,
+    pub wall: Option<Duration>,
+    /// User + system time of this process and every child it waited for
+    pub cpu: Option<Duration>,
+    /// Bytes received on non-loopback interfaces while installing
+    pub network_bytes: Option<u64>,
+    pub skipped: Option<String>,
+}
+
+/// Installs the same fully-pinned package set with each backend into fresh venvs
+pub struct InstallBenchmark {
+    packages: Vec<String>,
+    backends: Vec<String>,
+    runs: u32,
+    use_cache: bool,
+    work_dir: PathBuf,
+}
+
+impl InstallBenchmark {
+    pub fn new(packages: Vec<String>) -> Self {
+        Self {
+            packages,
+            backends: vec!["native".to_string(), "uv".to_string(), "pip".to_string()],
+            runs: 1,
+            use_cache: false,
+            work_dir: std::env::temp_dir().join(format!("snakepit-bench-{}", std::process::id())),
+        }
+    }
+
+    pub fn with_backends(mut self, backends: Vec<String>) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn with_runs(mut self, runs: u32) -> Self {
+        self.runs = runs.max(1);
+        self
+    }
+
+    pub fn with_cache(mut self, use_cache: bool) -> Self {
+        self.use_cache = use_cache;
+        self
+    }
+
+    /// Expand a set spec: a named set, a requirements file, or a comma-separated list
+    pub fn resolve_package_set(spec: &str) -> Result<Vec<String>> {
+        if let Some((_, packages)) = PACKAGE_SETS.iter().find(|(name, _)| *name == spec) {
+            return Ok(packages.iter().map(|p| p.to_string()).collect());
+        }
+
+        let path = Path::new(spec);
+        if path.is_file() {
+            let deps = ProjectDependencies::from_requirements_txt(path)?;
+            return Ok(deps.dependencies.iter().map(|d| match &d.version {
+                Some(version) => format!("{}=={}", d.name, version),
+                None => d.name.clone(),
+            }).collect());
+        }
+
+        let packages: Vec<String> = spec.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+        if packages.is_empty() {
+            let names: Vec<&str> = PACKAGE_SETS.iter().map(|(name, _)| *name).collect();
+            return Err(anyhow::anyhow!("Empty package set (named sets: {})", names.join(", ")));
+        }
+        Ok(packages)
+    }
+
+    pub async fn run(&self) -> Result<Vec<BenchResult>> {
+        // Resolve once up front so every backend installs the identical pinned set
+        logger::info(cyan(format!("📐 Resolving {} packages...", self.packages.len())));
+        let pinned = self.resolve().await?;
+        logger::note(format!("   {} packages after resolution", pinned.len()));
+
+        std::fs::create_dir_all(&self.work_dir)?;
+        let manager = VirtualEnvironmentManager::new()
+            .with_backend(VenvBackend::Venv)
+            .with_base_path(self.work_dir.clone());
+
+        let mut results = Vec::new();
+        for name in &self.backends {
+            let result = match Self::parse_backend(name) {
+                Ok(backend) => self.bench_backend(name, backend, &pinned, &manager).await,
+                Err(reason) => Self::skipped(name, reason),
+            };
+            results.push(result);
+        }
+
+        let _ = std::fs::remove_dir_all(&self.work_dir);
+        Ok(results)
+    }
+
+    async fn resolve(&self) -> Result<Vec<ResolvedDependency>> {
+        let mut project = ProjectDependencies::new();
+        for spec in &self.packages {
+            let (name, version) = match spec.split_once("==") {
+                Some((name, version)) => (name.trim().to_string(), Some(version.trim().to_string())),
+                None => (spec.clone(), None),
+            };
+            project.dependencies.push(Dependency {
+                name,
+                version,
+                version_constraint: None,
+                is_dev: false,
+                source: None,
+                extras: Vec::new(),
+                hashes: Vec::new(),
+            });
+        }
+
+        let mut resolver = DependencyResolver::new();
+        let resolved = resolver.resolve_dependencies(&project).await?;
+
+        // Flatten the tree; the Native backend doesn't pull transitive dependencies itself
+        let mut seen = HashSet::new();
+        let mut flat = Vec::new();
+        let mut stack: Vec<&ResolvedDependency> = resolved.dependencies.iter().collect();
+        while let Some(dep) = stack.pop() {
+            if seen.insert(dep.name.to_lowercase()) {
+                flat.push(ResolvedDependency {
+                    name: dep.name.clone(),
+                    version: dep.version.clone(),
+                    is_dev: false,
+                    dependencies: Vec::new(),
+                    source: dep.source.clone(),
+                });
+                stack.extend(dep.dependencies.iter());
+            }
+        }
+        Ok(flat)
+    }
+
+    fn parse_backend(name: &str) -> std::result::Result<InstallerBackend, String> {
+        match name {
+            "native" | "pip" => InstallerBackend::named(name).map_err(|e| e.to_string()),
+            "uv" if which::has_executable("uv") => InstallerBackend::named(name).map_err(|e| e.to_string()),
+            "uv" => Err("uv not found on PATH".to_string()),
+            other => Err(format!("backend '{}' can't install into a plain venv", other)),
+        }
+    }
+
+    fn skipped(name: &str, reason: String) -> BenchResult {
+        BenchResult {
+            backend: name.to_string(),
+            runs: 0,
+            failures: 0,
+            wall: None,
+            cpu: None,
+            network_bytes: None,
+            skipped: Some(reason),
+        }
+    }
+
+    async fn bench_backend(
+        &self,
+        name: &str,
+        backend: InstallerBackend,
+        pinned: &[ResolvedDependency],
+        manager: &VirtualEnvironmentManager,
+    ) -> BenchResult {
+        let mut walls = Vec::new();
+        let mut cpus = Vec::new();
+        let mut networks = Vec::new();
+        let mut failures = 0;
+
+        for run in 1..=self.runs {
+            logger::info(cyan(format!("⏱  {} run {}/{}", name, run, self.runs)));
+            let venv_name = format!("{}-{}", name, run);
+
+            let venv_path = match manager.create_venv(&venv_name, None).await {
+                Ok(path) => path,
+                Err(e) => {
+                    logger::warn(format!("⚠️  Could not create venv: {}", e));
+                    failures += 1;
+                    continue;
+                }
+            };
+
+            let installer = PackageInstaller::new()
+                .with_backend(backend.clone())
+                .with_venv(venv_path.to_string_lossy().to_string())
+                .with_cache(self.use_cache);
+
+            let cpu_before = process_cpu_time();
+            let net_before = network_received_bytes();
+            let started = Instant::now();
+
+            let outcome = installer.install_dependencies(pinned).await;
+
+            let wall = started.elapsed();
+            let cpu = process_cpu_time().zip(cpu_before).map(|(after, before)| after.saturating_sub(before));
+            let network = network_received_bytes().zip(net_before).map(|(after, before)| after.saturating_sub(before));
+
+            match outcome {
+                Ok(()) => {
+                    walls.push(wall);
+                    cpus.extend(cpu);
+                    networks.extend(network);
+                }
+                Err(e) => {
+                    logger::warn(format!("⚠️  {} run {} failed: {}", name, run, e));
+                    failures += 1;
+                }
+            }
+
+            let _ = std::fs::remove_dir_all(&venv_path);
+        }
+
+        BenchResult {
+            backend: name.to_string(),
+            runs: walls.len() as u32,
+            failures,
+            wall: mean(&walls),
+            cpu: mean(&cpus),
+            network_bytes: (!networks.is_empty()).then(|| networks.iter().sum::<u64>() / networks.len() as u64),
+            skipped: None,
+        }
+    }
+}
+
+fn mean(samples: &[Duration]) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+    Some(samples.iter().sum::<Duration>() / samples.len() as u32)
+}
+
+/// Render results as a table, with speedups relative to pip when it ran
+pub fn format_table(results: &[BenchResult]) -> String {
+    let baseline = results.iter().find(|r| r.backend == "pip").and_then(|r| r.wall);
+    let fmt_duration = |d: Option<Duration>| d.map(|d| format!("{:.2}s", d.as_secs_f64())).unwrap_or_else(|| "n/a".to_string());
+
+    let mut table = format!(
+        "{:<8} {:>5} {:>9} {:>9} {:>10} {:>8}\n",
+        "Backend", "Runs", "Wall", "CPU", "Network", "vs pip"
+    );
+    for result in results {
+        if let Some(reason) = &result.skipped {
+            table.push_str(&format!("{:<8} skipped: {}\n", result.backend, reason));
+            continue;
+        }
+
+        let network = result.network_bytes
+            .map(crate::cache::format_size)
+            .unwrap_or_else(|| "n/a".to_string());
+        let speedup = match (baseline, result.wall) {
+            (Some(base), Some(wall)) if wall.as_secs_f64() > 0.0 => format!("{:.2}x", base.as_secs_f64() / wall.as_secs_f64()),
+            _ => "n/a".to_string(),
+        };
+        let runs = if result.failures > 0 {
+            format!("{}/{}", result.runs, result.runs + result.failures)
+        } else {
+            result.runs.to_string()
+        };
+
+        table.push_str(&format!(
+            "{:<8} {:>5} {:>9} {:>9} {:>10} {:>8}\n",
+            result.backend, runs, fmt_duration(result.wall), fmt_duration(result.cpu), network, speedup
+        ));
+    }
+    table
+}
+
+/// utime + stime of this process plus its reaped children (pip/uv subprocesses)
+#[cfg(target_os = "linux")]
+fn process_cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // Fields after the parenthesised command name; utime is field 14 overall
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks: u64 = fields.get(11..15)?.iter().filter_map(|f| f.parse::<u64>().ok()).sum();
+    // USER_HZ is 100 on every mainstream Linux configuration
+    Some(Duration::from_millis(ticks * 10))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
+/// Total bytes received on non-loopback interfaces (system-wide)
+#[cfg(target_os = "linux")]
+fn network_received_bytes() -> Option<u64> {
+    let dev = std::fs::read_to_string("/proc/net/dev").ok()?;
+    Some(dev.lines()
+        .skip(2)
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(iface, _)| iface.trim() != "lo")
+        .filter_map(|(_, counters)| counters.split_whitespace().next()?.parse::<u64>().ok())
+        .sum())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn network_received_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_set_specs() {
+        assert_eq!(InstallBenchmark::resolve_package_set("small").unwrap(), vec!["requests", "six", "idna"]);
+        assert_eq!(InstallBenchmark::resolve_package_set("flask, click").unwrap(), vec!["flask", "click"]);
+        assert!(InstallBenchmark::resolve_package_set(" , ").is_err());
+    }
+
+    #[test]
+    fn test_table_reports_speedup_against_pip() {
+        let result = |backend: &str, secs: u64| BenchResult {
+            backend: backend.to_string(),
+            runs: 1,
+            failures: 0,
+            wall: Some(Duration::from_secs(secs)),
+            cpu: None,
+            network_bytes: Some(2048),
+            skipped: None,
+        };
+        let table = format_table(&[
+            result("native", 2),
+            result("pip", 8),
+            InstallBenchmark::skipped("uv", "uv not found on PATH".to_string()),
+        ]);
+
+        assert!(table.contains("4.00x"));
+        assert!(table.contains("1.00x"));
+        assert!(table.contains("skipped: uv not found"));
+    }
+}
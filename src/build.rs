@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use crate::sandbox::VenvSandbox;
+
+/// Backend PEP 517 says to assume for a project without `[build-system]`
+const LEGACY_BACKEND: &str = "setuptools.build_meta:__legacy__";
+const LEGACY_REQUIRES: &[&str] = &["setuptools>=40.8.0"];
+
+/// Calls one PEP 517 hook of the backend from inside the build environment and writes its
+/// result as JSON: `hook backend backend-path-json source-dir out-dir result-file`
+const HOOK_DRIVER: &str = r#"import importlib, json, os, sys
+hook, spec, backend_path, source, out, result_file = sys.argv[1:7]
+os.chdir(source)
+for entry in reversed(json.loads(backend_path)):
+    sys.path.insert(0, os.path.abspath(entry))
+module, _, attrs = spec.partition(":")
+backend = importlib.import_module(module)
+for attr in filter(None, attrs.split(".")):
+    backend = getattr(backend, attr)
+if hook.startswith("get_requires_for_build_"):
+    function = getattr(backend, hook, None)
+    result = function({}) if function else []
+else:
+    result = getattr(backend, hook)(out, {})
+with open(result_file, "w") as f:
+    json.dump(result, f)
+"#;
+
+/// What `build` can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Sdist,
+    Wheel,
+}
+
+impl Distribution {
+    fn build_hook(self) -> &'static str {
+        match self {
+            Distribution::Sdist => "build_sdist",
+            Distribution::Wheel => "build_wheel",
+        }
+    }
+
+    fn requires_hook(self) -> &'static str {
+        match self {
+            Distribution::Sdist => "get_requires_for_build_sdist",
+            Distribution::Wheel => "get_requires_for_build_wheel",
+        }
+    }
+}
+
+impl std::fmt::Display for Distribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Distribution::Sdist => write!(f, "sdist"),
+            Distribution::Wheel => write!(f, "wheel"),
+        }
+    }
+}
+
+/// `[build-system]` of pyproject.toml (PEP 517/518)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildSystem {
+    pub requires: Vec<String>,
+    /// `module:object` of the backend
+    pub backend: String,
+    /// In-tree directories the backend is imported from
+    pub backend_path: Vec<String>,
+}
+
+impl BuildSystem {
+    /// The project's build system, or setuptools' legacy backend when it declares none
+    pub fn for_project(dir: &Path) -> Result<Self> {
+        let path = dir.join("pyproject.toml");
+        if !path.exists() {
+            if dir.join("setup.py").exists() {
+                return Ok(Self::legacy());
+            }
+            return Err(anyhow!("No pyproject.toml or setup.py in {}", dir.display()));
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid [build-system] in {}", path.display()))
+    }
+
+    pub fn parse(pyproject: &str) -> Result<Self> {
+        let document: toml::Table = toml::from_str(pyproject)?;
+        let Some(table) = document.get("build-system").and_then(|value| value.as_table()) else {
+            return Ok(Self::legacy());
+        };
+        let strings = |key: &str| -> Result<Vec<String>> {
+            match table.get(key) {
+                None => Ok(Vec::new()),
+                Some(toml::Value::Array(items)) => items.iter()
+                    .map(|item| item.as_str().map(str::to_string).ok_or_else(|| anyhow!("{} must be a list of strings", key)))
+                    .collect(),
+                Some(_) => Err(anyhow!("{} must be a list of strings", key)),
+            }
+        };
+        let backend = match table.get("build-backend") {
+            Some(value) => value.as_str().ok_or_else(|| anyhow!("build-backend must be a string"))?.to_string(),
+            None => LEGACY_BACKEND.to_string(),
+        };
+        Ok(Self {
+            requires: if table.contains_key("requires") { strings("requires")? } else { Self::legacy().requires },
+            backend,
+            backend_path: strings("backend-path")?,
+        })
+    }
+
+    fn legacy() -> Self {
+        Self {
+            requires: LEGACY_REQUIRES.iter().map(|r| r.to_string()).collect(),
+            backend: LEGACY_BACKEND.to_string(),
+            backend_path: Vec::new(),
+        }
+    }
+}
+
+/// Builds a project's distributions with its PEP 517 backend, in a throwaway sandbox
+/// venv holding only the build requirements
+pub struct ProjectBuilder {
+    source: PathBuf,
+    out_dir: PathBuf,
+}
+
+impl ProjectBuilder {
+    pub fn new(source: &Path, out_dir: &Path) -> Self {
+        Self { source: source.to_path_buf(), out_dir: out_dir.to_path_buf() }
+    }
+
+    /// Build each of `kinds` into the output directory; the paths of the files written
+    pub async fn build(&self, kinds: &[Distribution]) -> Result<Vec<PathBuf>> {
+        let system = BuildSystem::for_project(&self.source)?;
+        let source = self.source.canonicalize()?;
+        std::fs::create_dir_all(&self.out_dir)?;
+        let out_dir = self.out_dir.canonicalize()?;
+
+        let sandbox = VenvSandbox::new(&format!("build-{}", std::process::id()));
+        if sandbox.get_path().exists() {
+            std::fs::remove_dir_all(sandbox.get_path())?;
+        }
+        sandbox.create().await?;
+        let built = self.build_in(&sandbox, &system, &source, &out_dir, kinds).await;
+        let _ = sandbox.destroy().await;
+        built
+    }
+
+    async fn build_in(&self, sandbox: &VenvSandbox, system: &BuildSystem, source: &Path, out_dir: &Path, kinds: &[Distribution]) -> Result<Vec<PathBuf>> {
+        let driver = sandbox.get_path().join("snakepit_build_hook.py");
+        std::fs::write(&driver, HOOK_DRIVER)?;
+        sandbox.install_requirements(&system.requires).await
+            .context("Failed to install the build requirements")?;
+
+        let mut artifacts = Vec::new();
+        for &kind in kinds {
+            let extra: Vec<String> = serde_json::from_value(self.call_hook(sandbox, &driver, system, source, out_dir, kind.requires_hook()).await?)
+                .with_context(|| format!("{} returned something other than a list of requirements", kind.requires_hook()))?;
+            sandbox.install_requirements(&extra).await?;
+            let filename = self.call_hook(sandbox, &driver, system, source, out_dir, kind.build_hook()).await?;
+            let filename = filename.as_str()
+                .ok_or_else(|| anyhow!("{} did not return a filename", kind.build_hook()))?;
+            artifacts.push(out_dir.join(filename));
+        }
+        Ok(artifacts)
+    }
+
+    async fn call_hook(&self, sandbox: &VenvSandbox, driver: &Path, system: &BuildSystem, source: &Path, out_dir: &Path, hook: &str) -> Result<serde_json::Value> {
+        let result_file = sandbox.get_path().join(format!("{}.json", hook));
+        let backend_path = serde_json::to_string(&system.backend_path)?;
+        let args = [
+            driver.to_string_lossy().to_string(),
+            hook.to_string(),
+            system.backend.clone(),
+            backend_path,
+            source.to_string_lossy().to_string(),
+            out_dir.to_string_lossy().to_string(),
+            result_file.to_string_lossy().to_string(),
+        ];
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let (success, _, stderr) = sandbox.run_command(&args).await?;
+        if !success {
+            return Err(anyhow!("{} failed in {}:\n{}", hook, system.backend, stderr.trim_end()));
+        }
+        let result = std::fs::read_to_string(&result_file)
+            .with_context(|| format!("{} wrote no result", hook))?;
+        Ok(serde_json::from_str(&result)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_system_from_pyproject() {
+        let system = BuildSystem::parse(r#"
+[project]
+name = "demo"
+
+[build-system]
+requires = ["hatchling>=1.18"]
+build-backend = "hatchling.build"
+backend-path = ["_build"]
+"#).unwrap();
+        assert_eq!(system.requires, vec!["hatchling>=1.18"]);
+        assert_eq!(system.backend, "hatchling.build");
+        assert_eq!(system.backend_path, vec!["_build"]);
+
+        let legacy = BuildSystem::parse("[project]\nname = \"demo\"\n").unwrap();
+        assert_eq!(legacy.backend, LEGACY_BACKEND);
+        assert_eq!(legacy.requires, vec!["setuptools>=40.8.0"]);
+
+        let requires_only = BuildSystem::parse("[build-system]\nrequires = [\"setuptools>=61\", \"wheel\"]\n").unwrap();
+        assert_eq!(requires_only.backend, LEGACY_BACKEND);
+        assert_eq!(requires_only.requires.len(), 2);
+        assert!(BuildSystem::parse("[build-system]\nrequires = \"setuptools\"\n").is_err());
+    }
+}
@@ -0,0 +1,462 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::manifest::canonical_name;
+
+/// Sidecar written next to every cached wheel (`<key>.whl` + `<key>.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WheelCacheInfo {
+    pub filename: String,
+    pub url: String,
+    /// sha256 published by the index at download time, if any
+    pub sha256: Option<String>,
+    /// Unix time of the last install served from this entry
+    pub last_used: u64,
+}
+
+impl WheelCacheInfo {
+    pub fn sidecar_path(wheel_path: &Path) -> PathBuf {
+        wheel_path.with_extension("json")
+    }
+
+    pub fn load(wheel_path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(Self::sidecar_path(wheel_path)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, wheel_path: &Path) -> Result<()> {
+        std::fs::write(Self::sidecar_path(wheel_path), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Distribution name from the wheel filename (`name-version-...whl`)
+    pub fn package(&self) -> String {
+        canonical_name(self.filename.split('-').next().unwrap_or(&self.filename))
+    }
+}
+
+/// How long a metadata document is served without revalidating against the index
+pub const METADATA_FRESH_SECS: u64 = 300;
+
+/// HTTP validators stored beside a cached metadata document (`<package>.validators.json`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Unix time of the last successful fetch or 304
+    pub checked_at: u64,
+}
+
+impl CacheValidators {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) {
+        if let Ok(content) = serde_json::to_string(self) {
+            let _ = std::fs::write(path, content);
+        }
+    }
+
+    /// Checked recently enough to skip the network entirely
+    pub fn is_fresh(&self, now: u64) -> bool {
+        now.saturating_sub(self.checked_at) < METADATA_FRESH_SECS
+    }
+
+    /// `If-None-Match` / `If-Modified-Since` for a conditional GET
+    pub fn conditional_headers(&self) -> reqwest::header::HeaderMap {
+        use reqwest::header::{HeaderMap, HeaderValue, IF_NONE_MATCH, IF_MODIFIED_SINCE};
+
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = self.etag.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = self.last_modified.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+
+    pub fn from_response(headers: &reqwest::header::HeaderMap, now: u64) -> Self {
+        use reqwest::header::{HeaderName, ETAG, LAST_MODIFIED};
+
+        let header = |name: HeaderName| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            checked_at: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    Wheel,
+    Metadata,
+}
+
+impl std::fmt::Display for CacheKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CacheKind::Wheel => write!(f, "wheel"),
+            CacheKind::Metadata => write!(f, "metadata"),
+        }
+    }
+}
+
+/// One logical cache entry, which may span several files on disk
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub kind: CacheKind,
+    pub package: String,
+    /// Wheel filename for wheels, package name for metadata
+    pub name: String,
+    pub files: Vec<PathBuf>,
+    pub size: u64,
+    pub last_used: SystemTime,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Ok,
+    Mismatch { expected: String, actual: String },
+    /// No digest was recorded when the wheel was cached
+    Unverifiable,
+}
+
+/// Read/evict access to `~/.cache/snakepit/{wheels,metadata}`
+pub struct CacheManager {
+    root: PathBuf,
+}
+
+impl CacheManager {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn wheels_dir(&self) -> PathBuf {
+        self.root.join("wheels")
+    }
+
+    pub fn metadata_dir(&self) -> PathBuf {
+        self.root.join("metadata")
+    }
+
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = self.wheel_entries()?;
+        entries.extend(self.metadata_entries()?);
+        entries.sort_by(|a, b| a.package.cmp(&b.package).then(a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+
+    pub fn total_size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|e| e.size).sum())
+    }
+
+    fn wheel_entries(&self) -> Result<Vec<CacheEntry>> {
+        let dir = self.wheels_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("whl") {
+                continue;
+            }
+
+            let metadata = std::fs::metadata(&path)?;
+            let info = WheelCacheInfo::load(&path);
+            let sidecar = WheelCacheInfo::sidecar_path(&path);
+            let mut files = vec![path.clone()];
+            let mut size = metadata.len();
+            if sidecar.exists() {
+                size += std::fs::metadata(&sidecar).map(|m| m.len()).unwrap_or(0);
+                files.push(sidecar);
+            }
+
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            let (package, name, last_used, sha256) = match info {
+                Some(info) => (
+                    info.package(),
+                    info.filename.clone(),
+                    UNIX_EPOCH + Duration::from_secs(info.last_used),
+                    info.sha256.clone(),
+                ),
+                // Wheels cached before sidecars existed: only the key is known
+                None => {
+                    let key = path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown").to_string();
+                    ("unknown".to_string(), key, modified, None)
+                }
+            };
+
+            entries.push(CacheEntry {
+                kind: CacheKind::Wheel,
+                package,
+                name,
+                files,
+                size,
+                last_used: last_used.max(modified),
+                sha256,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn metadata_entries(&self) -> Result<Vec<CacheEntry>> {
+        let dir = self.metadata_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+            if !file_name.ends_with(".json") || file_name.ends_with(".validators.json") {
+                continue;
+            }
+
+            let package = file_name.trim_end_matches(".json").to_string();
+            let metadata = std::fs::metadata(&path)?;
+            let validators = dir.join(format!("{}.validators.json", package));
+            let mut files = vec![path.clone()];
+            let mut size = metadata.len();
+            let mut last_used = metadata.modified().unwrap_or(UNIX_EPOCH);
+            if let Ok(v) = std::fs::metadata(&validators) {
+                size += v.len();
+                last_used = last_used.max(v.modified().unwrap_or(UNIX_EPOCH));
+                files.push(validators);
+            }
+
+            entries.push(CacheEntry {
+                kind: CacheKind::Metadata,
+                package: package.clone(),
+                name: package,
+                files,
+                size,
+                last_used,
+                sha256: None,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Remove every entry, or only those belonging to `package`
+    pub fn clean(&self, package: Option<&str>) -> Result<(usize, u64)> {
+        let wanted = package.map(canonical_name);
+        let victims: Vec<CacheEntry> = self.entries()?
+            .into_iter()
+            .filter(|e| wanted.as_ref().is_none_or(|w| &canonical_name(&e.package) == w))
+            .collect();
+        Self::remove_entries(&victims)
+    }
+
+    /// Evict entries unused for longer than `max_age`, then least-recently-used
+    /// entries until the cache fits in `max_size` bytes
+    pub fn prune(&self, max_age: Option<Duration>, max_size: Option<u64>) -> Result<(usize, u64)> {
+        let now = SystemTime::now();
+        let mut entries = self.entries()?;
+        let mut victims = Vec::new();
+
+        if let Some(max_age) = max_age {
+            let (old, keep): (Vec<_>, Vec<_>) = entries.into_iter().partition(|e| {
+                now.duration_since(e.last_used).map(|age| age > max_age).unwrap_or(false)
+            });
+            victims.extend(old);
+            entries = keep;
+        }
+
+        if let Some(max_size) = max_size {
+            entries.sort_by_key(|e| e.last_used);
+            let mut total: u64 = entries.iter().map(|e| e.size).sum();
+            for entry in entries {
+                if total <= max_size {
+                    break;
+                }
+                total -= entry.size;
+                victims.push(entry);
+            }
+        }
+
+        Self::remove_entries(&victims)
+    }
+
+    /// Re-hash every cached wheel against the digest recorded when it was downloaded
+    pub fn verify(&self) -> Result<Vec<(CacheEntry, VerifyStatus)>> {
+        let mut results = Vec::new();
+        for entry in self.wheel_entries()? {
+            let status = match &entry.sha256 {
+                Some(expected) => {
+                    let bytes = std::fs::read(&entry.files[0])?;
+                    let actual = snakegg::native::hash::compute_sha256_hex(&bytes);
+                    if actual.eq_ignore_ascii_case(expected) {
+                        VerifyStatus::Ok
+                    } else {
+                        VerifyStatus::Mismatch { expected: expected.clone(), actual }
+                    }
+                }
+                None => VerifyStatus::Unverifiable,
+            };
+            results.push((entry, status));
+        }
+        Ok(results)
+    }
+
+    pub fn remove_entries(entries: &[CacheEntry]) -> Result<(usize, u64)> {
+        let mut freed = 0;
+        for entry in entries {
+            for file in &entry.files {
+                if file.exists() {
+                    std::fs::remove_file(file)?;
+                }
+            }
+            freed += entry.size;
+        }
+        Ok((entries.len(), freed))
+    }
+}
+
+/// Parse sizes like "500MB", "2G" or "1024" (bytes)
+pub fn parse_size(input: &str) -> Option<u64> {
+    let input = input.trim().to_uppercase();
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let multiplier = match unit.trim().trim_end_matches('B').trim_end_matches('I') {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    Some(number * multiplier)
+}
+
+/// Parse ages like "12h", "7d" or "2w"
+pub fn parse_age(input: &str) -> Option<Duration> {
+    let input = input.trim();
+    let unit = input.chars().last()?;
+    let number: u64 = input[..input.len() - unit.len_utf8()].parse().ok()?;
+    let hours = match unit {
+        'h' => number,
+        'd' => number * 24,
+        'w' => number * 24 * 7,
+        _ => return None,
+    };
+    Some(Duration::from_secs(hours * 3600))
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> CacheManager {
+        let root = std::env::temp_dir().join(format!("snakepit-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("wheels")).unwrap();
+        std::fs::create_dir_all(root.join("metadata")).unwrap();
+        CacheManager::new(root)
+    }
+
+    fn add_wheel(cache: &CacheManager, key: &str, filename: &str, bytes: &[u8], last_used: u64) {
+        let path = cache.wheels_dir().join(format!("{}.whl", key));
+        std::fs::write(&path, bytes).unwrap();
+        WheelCacheInfo {
+            filename: filename.to_string(),
+            url: format!("https://files.example/{}", filename),
+            sha256: Some(snakegg::native::hash::compute_sha256_hex(bytes)),
+            last_used,
+        }
+        .save(&path)
+        .unwrap();
+    }
+
+    #[test]
+    fn test_validators_drive_conditional_requests() {
+        use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH, IF_MODIFIED_SINCE};
+
+        let mut response = HeaderMap::new();
+        response.insert(ETAG, HeaderValue::from_static("\"abc123\""));
+        let validators = CacheValidators::from_response(&response, 1_000);
+        assert!(validators.is_fresh(1_000 + METADATA_FRESH_SECS - 1));
+        assert!(!validators.is_fresh(1_000 + METADATA_FRESH_SECS));
+
+        let request = validators.conditional_headers();
+        assert_eq!(request.get(IF_NONE_MATCH).unwrap(), "\"abc123\"");
+        assert!(request.get(IF_MODIFIED_SINCE).is_none());
+        assert!(CacheValidators::default().conditional_headers().is_empty());
+    }
+
+    #[test]
+    fn test_parse_size_and_age() {
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("2KB"), Some(2048));
+        assert_eq!(parse_size("500M"), Some(500 * 1024 * 1024));
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(parse_age("2d"), Some(Duration::from_secs(2 * 24 * 3600)));
+        assert_eq!(parse_age("7x"), None);
+    }
+
+    #[test]
+    fn test_entries_and_clean_by_package() {
+        let cache = scratch("clean");
+        add_wheel(&cache, "a", "Requests-2.31.0-py3-none-any.whl", b"requests", 0);
+        add_wheel(&cache, "b", "flask-3.0.0-py3-none-any.whl", b"flask", 0);
+        add_wheel(&cache, "c", "zope.interface-6.0-py3-none-any.whl", b"zope", 0);
+        std::fs::write(cache.metadata_dir().join("requests.json"), "{}").unwrap();
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries.iter().filter(|e| e.package == "requests").count(), 2);
+
+        let (removed, _) = cache.clean(Some("requests")).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(cache.clean(Some("Zope_Interface")).unwrap().0, 1);
+        assert_eq!(cache.entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_prune_evicts_least_recently_used_first() {
+        let cache = scratch("prune");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        add_wheel(&cache, "old", "old-1.0-py3-none-any.whl", &[0u8; 4096], now - 3600);
+        add_wheel(&cache, "new", "new-1.0-py3-none-any.whl", &[0u8; 4096], now + 3600);
+
+        cache.prune(None, Some(6000)).unwrap();
+        let remaining = cache.entries().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].package, "new");
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let cache = scratch("verify");
+        add_wheel(&cache, "a", "pkg-1.0-py3-none-any.whl", b"original", 0);
+        std::fs::write(cache.wheels_dir().join("a.whl"), b"tampered").unwrap();
+
+        let results = cache.verify().unwrap();
+        assert!(matches!(results[0].1, VerifyStatus::Mismatch { .. }));
+    }
+}
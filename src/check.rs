@@ -0,0 +1,192 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+use crate::pep440::{SpecifierSet, Version};
+use crate::tree::DependencyGraph;
+
+/// Something wrong with an installed environment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Problem {
+    /// `package` requires `requirement`, and nothing provides it
+    Missing { package: String, version: String, requirement: String },
+    /// `package` requires `requirement`, but the installed version doesn't satisfy it
+    Conflict { package: String, version: String, requirement: String, installed: String },
+    /// More than one `.dist-info`/`.egg-info` for the same project
+    Duplicate { package: String, metadata_dirs: Vec<String> },
+    /// Files that no longer match the hashes in the package's RECORD
+    Tampered { package: String, version: String, modified: Vec<PathBuf>, missing: Vec<PathBuf> },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Missing { package, version, requirement } =>
+                write!(f, "{} {} requires {}, which is not installed", package, version, requirement),
+            Problem::Conflict { package, version, requirement, installed } =>
+                write!(f, "{} {} requires {}, but {} is installed", package, version, requirement, installed),
+            Problem::Duplicate { package, metadata_dirs } =>
+                write!(f, "{} is installed {} times: {}", package, metadata_dirs.len(), metadata_dirs.join(", ")),
+            Problem::Tampered { package, version, modified, missing } => {
+                write!(f, "{} {} fails its RECORD:", package, version)?;
+                if !modified.is_empty() {
+                    write!(f, " {} modified", modified.len())?;
+                }
+                if !missing.is_empty() {
+                    write!(f, " {} missing", missing.len())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `check`: everything wrong with one site-packages
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvironmentReport {
+    pub site_packages: PathBuf,
+    /// Installed distributions looked at
+    pub packages: usize,
+    pub problems: Vec<Problem>,
+}
+
+/// Check that every installed requirement is satisfied, that no project is installed
+/// twice, and that every file still matches its RECORD hash.
+pub fn check_environment(site_packages: &Path) -> Result<EnvironmentReport> {
+    let graph = DependencyGraph::from_site_packages(site_packages)?;
+    let mut problems = unsatisfied(&graph);
+    problems.extend(duplicates(site_packages)?);
+    problems.extend(crate::repair::verify_site_packages(site_packages).into_iter().map(|damaged| {
+        let (missing, modified): (Vec<_>, Vec<_>) = damaged.files.into_iter().partition(|file| file.missing);
+        Problem::Tampered {
+            package: damaged.name,
+            version: damaged.version,
+            modified: modified.into_iter().map(|file| file.path).collect(),
+            missing: missing.into_iter().map(|file| file.path).collect(),
+        }
+    }));
+    Ok(EnvironmentReport {
+        site_packages: site_packages.to_path_buf(),
+        packages: graph.nodes().count(),
+        problems,
+    })
+}
+
+/// Requirements (whose markers hold here) that the installed set doesn't meet
+fn unsatisfied(graph: &DependencyGraph) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for node in graph.nodes() {
+        for edge in &node.requires {
+            let requirement = format!("{}{}", edge.package, edge.specifier);
+            match graph.get(&edge.package) {
+                None => problems.push(Problem::Missing {
+                    package: node.name.clone(),
+                    version: node.version.clone(),
+                    requirement,
+                }),
+                Some(installed) => {
+                    let satisfied = match (SpecifierSet::parse(&edge.specifier), Version::parse(&installed.version)) {
+                        (Ok(specifiers), Ok(version)) => specifiers.contains(&version),
+                        // Can't judge what we can't parse; pip would have complained at install time
+                        _ => true,
+                    };
+                    if !satisfied {
+                        problems.push(Problem::Conflict {
+                            package: node.name.clone(),
+                            version: node.version.clone(),
+                            requirement,
+                            installed: format!("{} {}", installed.name, installed.version),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    problems
+}
+
+/// Projects with more than one metadata directory, as left by an interrupted upgrade
+/// or by mixing installers
+fn duplicates(site_packages: &Path) -> Result<Vec<Problem>> {
+    let mut by_project: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    for entry in std::fs::read_dir(site_packages)?.flatten() {
+        let dir_name = entry.file_name().to_string_lossy().to_string();
+        let Some(stem) = dir_name.strip_suffix(".dist-info").or_else(|| dir_name.strip_suffix(".egg-info")) else { continue };
+        let name = metadata_name(&entry.path())
+            .unwrap_or_else(|| stem.split('-').next().unwrap_or(stem).to_string());
+        let (_, dirs) = by_project.entry(canonical_name(&name)).or_insert_with(|| (name, Vec::new()));
+        dirs.push(dir_name);
+    }
+    Ok(by_project.into_values()
+        .filter(|(_, dirs)| dirs.len() > 1)
+        .map(|(package, mut metadata_dirs)| {
+            metadata_dirs.sort();
+            Problem::Duplicate { package, metadata_dirs }
+        })
+        .collect())
+}
+
+/// `Name:` from a dist-info's METADATA or an egg-info's PKG-INFO (a directory or a plain file)
+fn metadata_name(path: &Path) -> Option<String> {
+    let metadata = if path.is_dir() {
+        std::fs::read_to_string(path.join("METADATA")).or_else(|_| std::fs::read_to_string(path.join("PKG-INFO"))).ok()?
+    } else {
+        std::fs::read_to_string(path).ok()?
+    };
+    metadata.lines()
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| line.strip_prefix("Name:"))
+        .map(|name| name.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist(site: &Path, dir: &str, metadata: &str) {
+        std::fs::create_dir_all(site.join(dir)).unwrap();
+        std::fs::write(site.join(dir).join("METADATA"), metadata).unwrap();
+    }
+
+    #[test]
+    fn test_finds_unsatisfied_and_duplicate_installs() {
+        let site = std::env::temp_dir().join(format!("snakepit-check-{}", std::process::id()));
+        dist(&site, "requests-2.31.0.dist-info", "Name: requests\nVersion: 2.31.0\nRequires-Dist: idna (<4,>=2.5)\nRequires-Dist: urllib3 (>=1.21.1,<3)\nRequires-Dist: PySocks ; extra == \"socks\"\n\n");
+        dist(&site, "idna-4.0.dist-info", "Name: idna\nVersion: 4.0\n\n");
+        dist(&site, "idna-3.6.dist-info", "Name: idna\nVersion: 3.6\n\n");
+
+        let report = check_environment(&site).unwrap();
+        assert!(report.problems.contains(&Problem::Missing {
+            package: "requests".to_string(),
+            version: "2.31.0".to_string(),
+            requirement: "urllib3>=1.21.1,<3".to_string(),
+        }));
+        assert!(report.problems.contains(&Problem::Duplicate {
+            package: "idna".to_string(),
+            metadata_dirs: vec!["idna-3.6.dist-info".to_string(), "idna-4.0.dist-info".to_string()],
+        }));
+        // The extra nobody asked for isn't held against the environment
+        assert!(!report.problems.iter().any(|p| p.to_string().contains("pysocks")));
+        let _ = std::fs::remove_dir_all(&site);
+    }
+
+    #[test]
+    fn test_reports_version_conflicts() {
+        let site = std::env::temp_dir().join(format!("snakepit-check-conflict-{}", std::process::id()));
+        dist(&site, "flask-3.0.0.dist-info", "Name: Flask\nVersion: 3.0.0\nRequires-Dist: Werkzeug>=3.0.0\n\n");
+        dist(&site, "werkzeug-2.3.8.dist-info", "Name: Werkzeug\nVersion: 2.3.8\n\n");
+
+        let report = check_environment(&site).unwrap();
+        assert_eq!(report.packages, 2);
+        assert_eq!(report.problems, vec![Problem::Conflict {
+            package: "Flask".to_string(),
+            version: "3.0.0".to_string(),
+            requirement: "werkzeug>=3.0.0".to_string(),
+            installed: "Werkzeug 2.3.8".to_string(),
+        }]);
+        let _ = std::fs::remove_dir_all(&site);
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "snakepit")]
@@ -7,55 +7,350 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Installer UI: classic, ndjson (event stream on stdout) or exec:<command> (stream piped to a program)
+    #[arg(long, global = true, value_name = "RENDERER")]
+    pub renderer: Option<String>,
+    /// Go easy on battery and bandwidth: fewer parallel downloads, no background AI calls; with
+    /// `daemon start`/`restart`, also defers the daemon's scheduled jobs
+    #[arg(long, global = true, conflicts_with = "no_eco")]
+    pub eco: bool,
+    /// Never enter eco mode, even on battery or a metered connection (the daemon included)
+    #[arg(long, global = true)]
+    pub no_eco: bool,
+    /// Consider pre-releases and dev releases for every package
+    #[arg(long, global = true)]
+    pub pre: bool,
+    /// Consider pre-releases for this package only; may be repeated or comma-separated
+    #[arg(long = "pre-package", global = true, value_name = "PACKAGE", value_delimiter = ',')]
+    pub pre_packages: Vec<String>,
+    /// Which versions to pick: highest (default) or lowest, to test declared lower bounds
+    #[arg(long, global = true, value_name = "STRATEGY")]
+    pub resolution: Option<String>,
+    /// Ignore releases uploaded after this date (2024-06-01, 2024-06-01 12:00 or an age like 30d)
+    #[arg(long, global = true, value_name = "DATE")]
+    pub exclude_newer: Option<String>,
+    /// Install and sync with the old first-match resolver instead of the PEP 440 solver
+    #[arg(long, global = true)]
+    pub legacy_resolver: bool,
+    /// Evaluate markers for another machine: a target triple (aarch64-apple-darwin) or a label (linux-3.12)
+    #[arg(long, global = true, value_name = "TRIPLE")]
+    pub target_triple: Option<String>,
+    /// Print results as JSON on stdout for scripts (also SNAKEPIT_OUTPUT=json); progress stays on stderr
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Answer yes to every confirmation prompt (also SNAKEPIT_ASSUME_YES=true)
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+    /// Never wait for terminal input; prompts take their default answer (also SNAKEPIT_NO_INPUT=true, and implied when stdin isn't a terminal)
+    #[arg(long, global = true)]
+    pub no_input: bool,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Install a Python package
     Install {
-        /// Package name to install
-        package: String,
+        /// Package name to install, or a local .whl, .conda or .tar.bz2 file
+        #[arg(required_unless_present = "requirements")]
+        package: Option<String>,
+        /// Install everything listed in a requirements file; may be repeated
+        #[arg(short = 'r', long = "requirement", value_name = "FILE")]
+        requirements: Vec<String>,
         /// Specific version to install
         #[arg(short, long)]
         version: Option<String>,
         /// Install as development dependency
         #[arg(short, long)]
         dev: bool,
+        /// Refuse to install unless the artifact matches a pinned hash
+        #[arg(long)]
+        require_hashes: bool,
+        /// Expected artifact hash (e.g. sha256:<hex>); may be repeated
+        #[arg(long = "hash", value_name = "HASH")]
+        hashes: Vec<String>,
+        /// Constraints file limiting versions without adding packages; may be repeated
+        #[arg(short = 'c', long = "constraint", value_name = "FILE")]
+        constraints: Vec<String>,
+        /// After native installs, import each package and roll it back if that fails
+        #[arg(long)]
+        verify_imports: bool,
+        /// Show what would be downloaded, installed, removed or changed, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
     },
     /// Uninstall a Python package
     Uninstall {
         /// Package name to uninstall
         package: String,
+        /// Remove the package even if the installer backend or other packages depend on it, without asking
+        #[arg(long)]
+        force: bool,
+        /// Also remove its dependencies that nothing else installed or declared needs anymore
+        #[arg(long)]
+        cascade: bool,
+        /// Show what would be downloaded, installed, removed or changed, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Remove installed packages the project's requirements don't lead to, after showing them
+    Autoremove {
+        #[command(flatten)]
+        environment: EnvironmentArgs,
     },
     /// List installed packages
-    List,
+    List {
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Print installed packages as pinned requirements, for requirements.txt
+    Freeze {
+        /// Include pip, setuptools, wheel and distribute
+        #[arg(long)]
+        all: bool,
+        /// Leave out editable installs
+        #[arg(long)]
+        exclude_editable: bool,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Show which package (and install) put a file into the environment
+    Owns {
+        /// File to look up
+        #[arg(required_unless_present = "unowned")]
+        path: Option<String>,
+        /// List files in site-packages that no package owns instead
+        #[arg(long, conflicts_with = "path")]
+        unowned: bool,
+    },
     /// Sync dependencies from requirements file
-    Sync,
+    Sync {
+        /// Require every requirement to carry a sha256 hash (from --hash= lines or the lockfile)
+        #[arg(long)]
+        require_hashes: bool,
+        /// Constraints file limiting versions without adding packages; may be repeated
+        #[arg(short = 'c', long = "constraint", value_name = "FILE")]
+        constraints: Vec<String>,
+        /// After native installs, import each package and roll it back if that fails
+        #[arg(long)]
+        verify_imports: bool,
+        /// Install exactly what snakepit.lock pins and remove everything else; fail if the lock is stale
+        #[arg(long, visible_alias = "locked", conflicts_with = "constraints")]
+        frozen: bool,
+        /// Show what would be downloaded, installed, removed or changed, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Add requirements to pyproject.toml, then re-lock and sync
+    Add {
+        /// Requirements to add, e.g. requests or "django>=5,<6"; bare names get a floor at the locked version
+        #[arg(required = true)]
+        packages: Vec<String>,
+        /// Add to the dev dependencies
+        #[arg(short, long, conflicts_with = "group")]
+        dev: bool,
+        /// Add to this dependency group in [dependency-groups]
+        #[arg(long, value_name = "GROUP")]
+        group: Option<String>,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Remove requirements from pyproject.toml, then re-lock and sync
+    Remove {
+        /// Packages to remove
+        #[arg(required = true)]
+        packages: Vec<String>,
+        /// Remove from the dev dependencies
+        #[arg(short, long, conflicts_with = "group")]
+        dev: bool,
+        /// Remove from this dependency group in [dependency-groups]
+        #[arg(long, value_name = "GROUP")]
+        group: Option<String>,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Run a command or project task inside the project's environment, creating and syncing it if there isn't one
+    Run {
+        /// Rerun the command whenever a project file changes, until interrupted
+        #[arg(long)]
+        watch: bool,
+        /// With --watch, also ignore files or directories with this name (globs allowed); may be repeated
+        #[arg(long, value_name = "PATTERN", requires = "watch")]
+        ignore: Vec<String>,
+        /// With --watch, milliseconds of quiet to wait for after a change before rerunning
+        #[arg(long, value_name = "MS", requires = "watch")]
+        debounce: Option<u64>,
+        /// The command and its arguments, e.g. `pytest -x`, or the name of a project task; lists the tasks when empty
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Start a shell with the project's environment (or a named one) activated
+    Shell {
+        /// Virtual environment to activate instead of the project's
+        #[arg(long)]
+        venv: Option<String>,
+    },
+    /// Resolve the project's dependencies into snakepit.lock
+    Lock {
+        /// Only verify that snakepit.lock is in canonical form (sorted, normalized, unedited)
+        #[arg(long)]
+        check_format: bool,
+        /// Platforms to resolve for (linux, macos, windows); may be repeated or comma-separated
+        #[arg(long = "platform", value_delimiter = ',')]
+        platforms: Vec<String>,
+        /// Python versions to resolve for, e.g. 3.12; may be repeated or comma-separated
+        #[arg(long = "python", value_delimiter = ',')]
+        python_versions: Vec<String>,
+        /// Resolve for every supported platform and Python 3.9–3.13
+        #[arg(long, conflicts_with_all = ["platforms", "python_versions"])]
+        universal: bool,
+        /// Convert a poetry.lock, Pipfile.lock or uv.lock into snakepit.lock without re-resolving
+        #[arg(long = "import", value_name = "LOCKFILE", conflicts_with_all = ["check_format", "platforms", "python_versions", "universal"])]
+        import: Option<String>,
+        /// Show what would change against snakepit.lock: a fresh resolution, or the given lockfile
+        #[arg(long, value_name = "LOCKFILE", num_args = 0..=1, conflicts_with_all = ["check_format", "import"])]
+        diff: Option<Option<String>>,
+    },
+    /// Re-resolve with newer versions of some packages (or all) and install the changes
+    Upgrade {
+        /// Packages to upgrade; everything else keeps its locked version (default: all)
+        packages: Vec<String>,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Write snakepit.lock in a format other tools understand
+    Export {
+        /// Output format: requirements-txt
+        #[arg(long, default_value = "requirements-txt")]
+        format: String,
+        /// Add --hash options for pip's --require-hashes mode
+        #[arg(long)]
+        hashes: bool,
+        /// Also include this dependency group (dev); may be repeated
+        #[arg(long = "group", value_name = "GROUP")]
+        groups: Vec<String>,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Canonicalize dependency manifests across the workspace
+    FmtManifest {
+        /// Manifests to format (defaults to every pyproject.toml / requirements*.txt under the current directory)
+        paths: Vec<String>,
+        /// Report files that would change instead of rewriting them
+        #[arg(long)]
+        check: bool,
+    },
     /// Search for packages
     Search {
         /// Query string
-        query: String,
+        #[arg(required_unless_present = "interactive")]
+        query: Option<String>,
+        /// Browse with fuzzy search, package details and one-key install
+        #[arg(short, long)]
+        interactive: bool,
     },
-    /// Show package details
+    /// Show an installed or published package: metadata, dependencies, dependents and files
     Show {
         /// Package name
         package: String,
+        /// List every file the package installed
+        #[arg(long)]
+        files: bool,
     },
-    /// Initialize a new project
+    /// Initialize a new project, asking for whatever the flags leave open
     Init {
         /// Project name
         name: Option<String>,
+        #[command(flatten)]
+        answers: InitArgs,
+        /// List the available templates
+        #[arg(long)]
+        list_templates: bool,
+    },
+    /// Build the project's sdist and wheel with its PEP 517 backend, in an isolated environment
+    Build {
+        /// Only build the wheel
+        #[arg(long)]
+        wheel: bool,
+        /// Only build the sdist
+        #[arg(long)]
+        sdist: bool,
+        /// Directory to write the distributions to
+        #[arg(long, short = 'o', default_value = "dist")]
+        out_dir: String,
+    },
+    /// Upload built distributions to PyPI, TestPyPI or a private index
+    Publish {
+        /// Files to upload (default: the wheels and sdists in dist/)
+        files: Vec<String>,
+        /// `pypi`, `testpypi` or an upload URL (default: `publish_url`, else pypi)
+        #[arg(long, value_name = "INDEX")]
+        index: Option<String>,
+        /// API token (default: SNAKEPIT_PUBLISH_TOKEN, trusted publishing in CI, then the keyring)
+        #[arg(long)]
+        token: Option<String>,
+        /// Check the files and show what would be uploaded, without uploading
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't fail on files the index already has
+        #[arg(long)]
+        skip_existing: bool,
     },
     /// Virtual environment management
     Venv {
         #[command(subcommand)]
         command: VenvCommands,
     },
+    /// Standalone Python builds snakepit downloads and manages
+    Python {
+        #[command(subcommand)]
+        command: PythonCommands,
+    },
+    /// Command-line tools in environments of their own, with their commands on PATH (like pipx)
+    Tool {
+        #[command(subcommand)]
+        command: ToolCommands,
+    },
+    /// Run a tool's command without installing it, from a cached environment (`snakepit x black .`)
+    X {
+        /// Package providing the command, when its name differs, e.g. `--from httpie http`
+        #[arg(long, value_name = "REQUIREMENT")]
+        from: Option<String>,
+        /// Python version to build the environment with
+        #[arg(long)]
+        python: Option<String>,
+        /// The package (or with --from, the command) and its arguments, e.g. `ruff==0.4.4 check .`
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Conda environments as environment.yml files
+    Env {
+        #[command(subcommand)]
+        command: EnvCommands,
+    },
+    /// Inspect the merged configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
     /// Daemon management
     Daemon {
         #[command(subcommand)]
         command: DaemonCommands,
     },
+    /// Integrity agent that checks managed environments on interpreter startup
+    Agent {
+        #[command(subcommand)]
+        command: AgentCommands,
+    },
     /// Fix a broken command by analyzing its error output
     Fix {
         /// The command to run and analyze (use -- to separate args)
@@ -85,6 +380,98 @@ pub enum Commands {
         #[command(subcommand)]
         action: SnapshotAction,
     },
+    /// Inspect and maintain the wheel and metadata caches
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Package index traffic per subsystem and the shared rate limit
+    Stats {
+        /// Forget the recorded totals
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Show the dependency chains that led to a package being installed
+    Why {
+        /// Installed package to explain
+        package: String,
+        /// Environment to read (default: the active one)
+        #[arg(long)]
+        venv: Option<String>,
+    },
+    /// Show installed packages as a dependency tree
+    Tree {
+        /// Show what depends on this package instead
+        #[arg(long, value_name = "PACKAGE")]
+        invert: Option<String>,
+        /// Only show this many levels below each top-level package
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Use the graph resolved into snakepit.lock instead of the installed packages
+        #[arg(long)]
+        locked: bool,
+        /// Environment to read (default: the active one)
+        #[arg(long, conflicts_with = "locked")]
+        venv: Option<String>,
+    },
+    /// Score the project's supply-chain hygiene and suggest fixes
+    Posture {
+        /// Exit with an error when the overall score is below this
+        #[arg(long)]
+        min_score: Option<u32>,
+        /// Environment to check for the integrity agent (default: the active one)
+        #[arg(long)]
+        venv: Option<String>,
+    },
+    /// Diagnose the interpreter, environment, PATH, cache, index, AI backend and daemon
+    Doctor,
+    /// Verify the environment: unmet requirements, duplicate installs and files that fail their RECORD hashes
+    Check {
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Group installed packages by license; exits non-zero when one breaks the allow/deny lists
+    Licenses {
+        /// Allow only these licenses (replaces `allowed_licenses`); may be repeated or comma-separated
+        #[arg(long, value_name = "LICENSE", value_delimiter = ',')]
+        allow: Vec<String>,
+        /// Fail on these licenses, e.g. 'GPL*' (replaces `denied_licenses`); may be repeated or comma-separated
+        #[arg(long, value_name = "LICENSE", value_delimiter = ',')]
+        deny: Vec<String>,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Check installed packages (or snakepit.lock) against OSV.dev advisories; exits non-zero on findings
+    Audit {
+        /// Audit snakepit.lock instead of the environment
+        #[arg(long)]
+        locked: bool,
+        /// Use an OSV PyPI export (its all.zip, or a directory of the JSON files) instead of querying OSV.dev
+        #[arg(long, value_name = "PATH")]
+        db: Option<String>,
+        /// Use the advisory snapshot saved by --update-db instead of querying OSV.dev
+        #[arg(long, conflicts_with = "db")]
+        offline: bool,
+        /// Download the latest OSV PyPI export for --offline, then audit against it
+        #[arg(long, conflicts_with = "db")]
+        update_db: bool,
+        /// Accept an advisory by ID or alias (e.g. a CVE with no fix yet); may be repeated
+        #[arg(long, value_name = "ID")]
+        ignore: Vec<String>,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
+    /// Manage the snakepit binary itself
+    #[command(name = "self")]
+    SelfManage {
+        #[command(subcommand)]
+        command: SelfCommands,
+    },
+    /// Benchmark installer backends against each other
+    Bench {
+        #[command(subcommand)]
+        command: BenchCommands,
+    },
     /// Quantum Nest Management (SnakeEgg)
     Nest {
         #[command(subcommand)]
@@ -105,6 +492,66 @@ pub enum Commands {
         #[command(subcommand)]
         command: ProteinCommands,
     },
+    /// A project task run by name, as `snakepit test` for `snakepit run test`
+    #[command(external_subcommand)]
+    Task(Vec<String>),
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// List cached wheels and metadata per package
+    List,
+    /// Show cache location and total size
+    Info,
+    /// Remove cached entries
+    Clean {
+        /// Only remove entries for this package
+        package: Option<String>,
+    },
+    /// Evict old entries and enforce a size cap
+    Prune {
+        /// Evict entries unused for longer than this (e.g., "7d", "12h")
+        #[arg(long)]
+        max_age: Option<String>,
+        /// Evict least-recently-used entries until the cache fits (e.g., "2GB");
+        /// defaults to `cache_max_size` from the config
+        #[arg(long)]
+        max_size: Option<String>,
+    },
+    /// Re-hash cached wheels against the digests recorded at download time
+    Verify {
+        /// Remove wheels whose hash no longer matches
+        #[arg(long)]
+        fix: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SelfCommands {
+    /// Replace this binary with the latest release, after checking its SHA-256
+    Update {
+        /// Only report whether an update is available; exits non-zero when one is (for CI)
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BenchCommands {
+    /// Time installing a package set with each backend into fresh venvs
+    Install {
+        /// Named set (small, web, data), a requirements file, or a comma-separated list
+        set: String,
+        /// Backends to compare
+        #[arg(short, long, value_delimiter = ',', default_value = "native,uv,pip")]
+        backends: Vec<String>,
+        /// Runs per backend; results are averaged
+        #[arg(short, long, default_value = "1")]
+        runs: u32,
+        /// Keep download caches enabled (the default measures cold installs)
+        #[arg(long)]
+        warm: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -198,11 +645,186 @@ pub enum ProteinCommands {
 #[derive(Subcommand)]
 pub enum SnapshotAction {
     /// List all snapshots
-    List,
+    List {
+        /// List whole-environment snapshots instead
+        #[arg(long)]
+        env: bool,
+    },
+    /// Snapshot a package, or with --env every package in the environment
+    Create {
+        /// Package to snapshot
+        #[arg(required_unless_present = "env", conflicts_with = "env")]
+        package: Option<String>,
+        /// Record every installed package, version and RECORD hash
+        #[arg(long)]
+        env: bool,
+        /// With --env, also archive all of site-packages so restore doesn't need the index
+        #[arg(long, requires = "env")]
+        files: bool,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
+    },
     /// Restore a snapshot
     Restore {
         /// Snapshot ID
         id: String,
+        /// Restore into this venv or site-packages instead of the environment the snapshot came from
+        /// (with --env, a venv)
+        #[arg(long)]
+        target: Option<String>,
+        /// Roll a whole environment back to an environment snapshot
+        #[arg(long)]
+        env: bool,
+    },
+    /// Show a snapshot's package, version, origin and files
+    Show {
+        /// Snapshot ID
+        id: String,
+    },
+    /// Show what restoring a snapshot would change in the installed package
+    Diff {
+        /// Snapshot ID
+        id: String,
+        /// Compare with this venv or site-packages instead of the environment the snapshot came from
+        #[arg(long)]
+        target: Option<String>,
+    },
+    /// Delete a snapshot
+    Delete {
+        /// Snapshot ID
+        id: String,
+    },
+    /// Delete snapshots beyond the retention limits, oldest first; the newest is always kept
+    Prune {
+        /// Keep at most this many (default: `snapshot_max_count`)
+        #[arg(long)]
+        max_count: Option<usize>,
+        /// Delete snapshots older than this, e.g. "30d" (default: `snapshot_max_age`)
+        #[arg(long)]
+        max_age: Option<String>,
+        /// Delete the oldest until the rest fit, e.g. "1GB" (default: `snapshot_max_size`)
+        #[arg(long)]
+        max_size: Option<String>,
+    },
+}
+
+/// Answers to `init`'s questions; the wizard asks only for the ones not given
+#[derive(Args, Debug, Clone, Default)]
+pub struct InitArgs {
+    /// Template: lib, app, one of your own, a git URL (`gh:owner/repo#ref`) or a directory
+    #[arg(long, short = 't')]
+    pub template: Option<String>,
+    /// Minimum Python version, e.g. 3.11
+    #[arg(long)]
+    pub python: Option<String>,
+    /// Build backend: hatchling, setuptools, flit or pdm
+    #[arg(long)]
+    pub backend: Option<String>,
+    /// Package layout: src or flat
+    #[arg(long)]
+    pub layout: Option<String>,
+    /// License as an SPDX expression, or `none`
+    #[arg(long)]
+    pub license: Option<String>,
+    /// Test framework: pytest, unittest or none
+    #[arg(long)]
+    pub test: Option<String>,
+    /// One-line description of the project
+    #[arg(long)]
+    pub description: Option<String>,
+}
+
+/// Where a command installs; by default the active venv, else a `.venv` in the project root
+#[derive(Args, Debug, Clone, Default)]
+pub struct EnvironmentArgs {
+    /// Use this virtual environment
+    #[arg(long, value_name = "PATH", conflicts_with = "system")]
+    pub venv: Option<String>,
+    /// Use the user site-packages, even inside a project with a .venv
+    #[arg(long)]
+    pub system: bool,
+}
+
+impl Commands {
+    /// The `--venv`/`--system` choice of commands that install into an environment
+    pub fn environment_args(&self) -> Option<&EnvironmentArgs> {
+        match self {
+            Commands::Install { environment, .. }
+            | Commands::Uninstall { environment, .. }
+            | Commands::Autoremove { environment }
+            | Commands::List { environment }
+            | Commands::Freeze { environment, .. }
+            | Commands::Audit { environment, .. }
+            | Commands::Check { environment }
+            | Commands::Licenses { environment, .. }
+            | Commands::Sync { environment, .. }
+            | Commands::Add { environment, .. }
+            | Commands::Remove { environment, .. }
+            | Commands::Run { environment, .. }
+            | Commands::Upgrade { environment, .. }
+            | Commands::Snapshot { action: SnapshotAction::Create { environment, .. } }
+            | Commands::Daemon { command: DaemonCommands::Start { environment, .. } } => Some(environment),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum ToolCommands {
+    /// Install a tool into its own environment and link its commands into ~/.local/bin
+    Install {
+        /// The package, optionally with a version, e.g. `ruff` or `black==24.2.0`
+        package: String,
+        /// Python version to build the environment with (downloaded if the system doesn't have it)
+        #[arg(long)]
+        python: Option<String>,
+        /// Reinstall even if the tool is already installed
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show installed tools and their commands
+    List,
+    /// Reinstall tools at the newest release their requirement allows
+    Upgrade {
+        /// Tool to upgrade
+        #[arg(required_unless_present = "all")]
+        package: Option<String>,
+        /// Upgrade every installed tool
+        #[arg(long, conflicts_with = "package")]
+        all: bool,
+    },
+    /// Remove a tool's environment and its commands
+    Uninstall {
+        package: String,
+    },
+    /// Run an installed tool's command without it being on PATH; one that isn't installed runs as with `snakepit x`
+    Run {
+        /// A command or the package providing it
+        command: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PythonCommands {
+    /// Show installed builds and which one the project pins
+    List {
+        /// Also show the builds available for download
+        #[arg(long)]
+        available: bool,
+    },
+    /// Download a build, e.g. 3.12 (newest 3.12.x) or 3.12.4
+    Install {
+        version: String,
+    },
+    /// Remove an installed build
+    Uninstall {
+        version: String,
+    },
+    /// Pin the project to a Python version in .python-version
+    Pin {
+        version: String,
     },
 }
 
@@ -212,14 +834,20 @@ pub enum VenvCommands {
     Create {
         /// Virtual environment name
         name: String,
-        /// Python version to use
-        #[arg(short, long)]
+        /// Python version to use; downloaded if the system doesn't have it (default: the project's pin)
+        #[arg(short, long, alias = "python")]
         python_version: Option<String>,
     },
-    /// Activate a virtual environment
+    /// Activate a virtual environment: `eval "$(snakepit venv activate NAME --print-script)"`
     Activate {
         /// Virtual environment name
         name: String,
+        /// Print shell code that activates it, for eval
+        #[arg(long)]
+        print_script: bool,
+        /// Shell to write the script for: bash, zsh, fish, powershell or cmd (default: $SHELL)
+        #[arg(long, requires = "print_script")]
+        shell: Option<String>,
     },
     /// Delete a virtual environment
     Delete {
@@ -228,6 +856,114 @@ pub enum VenvCommands {
     },
     /// List all virtual environments
     List,
+    /// Show an environment's interpreter, size, package count and origin
+    Info {
+        /// Virtual environment name or path (default: the project's environment)
+        name: Option<String>,
+    },
+    /// Check installed files against their RECORD hashes and reinstall damaged packages
+    Repair {
+        /// Virtual environment name or path (default: the project's environment)
+        name: Option<String>,
+        /// Rebuild the environment from snakepit.lock instead (e.g. after a Python upgrade)
+        #[arg(long)]
+        recreate: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print the effective settings as TOML
+    Show {
+        /// Note which layer (default, system, user, project, environment, command line) set each value
+        #[arg(long)]
+        origin: bool,
+    },
+    /// Print one setting's effective value
+    Get {
+        /// Setting name, e.g. python_version
+        key: String,
+    },
+    /// Write a setting to the user config or the project's snakepit.toml
+    Set {
+        /// Setting name, e.g. default_backend
+        key: String,
+        /// Value; lists are comma-separated
+        value: String,
+        #[command(flatten)]
+        layer: ConfigLayerArgs,
+    },
+    /// Remove a setting from the user config or the project's snakepit.toml
+    Unset {
+        /// Setting name
+        key: String,
+        #[command(flatten)]
+        layer: ConfigLayerArgs,
+    },
+    /// Every setting and its effective value
+    List,
+    /// Move snakepit.toml into pyproject.toml's [tool.snakepit] table
+    Migrate,
+}
+
+/// Which file `config set` and `config unset` change
+#[derive(Args, Debug, Clone)]
+pub struct ConfigLayerArgs {
+    /// The `[config]` table of the project's snakepit.toml
+    #[arg(long, conflicts_with = "user")]
+    pub project: bool,
+    /// The user config file (the default)
+    #[arg(long)]
+    pub user: bool,
+}
+
+#[derive(Subcommand)]
+pub enum EnvCommands {
+    /// Write a conda environment's packages as environment.yml
+    Export {
+        /// Environment name or path (default: the project's environment)
+        name: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Create or update a conda environment from environment.yml
+    Import {
+        /// Environment file to read
+        #[arg(default_value = "environment.yml")]
+        file: String,
+        /// Environment name (default: the file's `name:`)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AgentCommands {
+    /// Install the agent into an environment and record its current state
+    Install {
+        /// Virtual environment to manage (defaults to the active one)
+        #[arg(long)]
+        venv: Option<String>,
+        /// Minimum seconds between startup checks
+        #[arg(long, default_value = "300")]
+        interval: u64,
+        /// Files hashed per startup check
+        #[arg(long, default_value = "5")]
+        sample: usize,
+    },
+    /// Accept the environment as it is now (after deliberate outside changes)
+    Refresh {
+        /// Virtual environment to manage (defaults to the active one)
+        #[arg(long)]
+        venv: Option<String>,
+    },
+    /// Remove the agent and its startup hook
+    Remove {
+        /// Virtual environment to manage (defaults to the active one)
+        #[arg(long)]
+        venv: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -240,6 +976,8 @@ pub enum DaemonCommands {
         /// Configuration file path
         #[arg(short, long)]
         config: Option<String>,
+        #[command(flatten)]
+        environment: EnvironmentArgs,
     },
     /// Stop the snakepit daemon
     Stop,
@@ -247,6 +985,63 @@ pub enum DaemonCommands {
     Status,
     /// Restart the daemon
     Restart,
+    /// Make the running daemon re-read its configuration
+    Reload,
+    /// Show the module errors the running daemon has cached
+    Errors {
+        /// Only errors for this module
+        module: Option<String>,
+    },
+    /// Show the missing modules waiting for approval (`require_approval` mode)
+    Pending,
+    /// Install a queued module, and from now on install it without asking
+    Approve {
+        module: String,
+    },
+    /// Don't install a queued module
+    Deny {
+        module: String,
+    },
+    /// Run the daemon as a systemd user service (launchd agent on macOS) so it survives reboots
+    InstallService,
+    /// Stop the daemon service and remove it
+    UninstallService,
+    /// Show the daemon's structured log across every watched project
+    Logs {
+        /// Keep streaming new entries from the running daemon
+        #[arg(short, long)]
+        follow: bool,
+        /// Minimum severity (debug, info, warn, error)
+        #[arg(short, long)]
+        level: Option<String>,
+        /// Only entries from this daemon component (monitor, installer, ...)
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Only entries about this project directory (or below it)
+        #[arg(short, long)]
+        project: Option<String>,
+        /// Start of the time range: unix seconds, an age like 2h, or YYYY-MM-DD[ HH:MM]
+        #[arg(long)]
+        since: Option<String>,
+        /// End of the time range, same formats as --since
+        #[arg(long)]
+        until: Option<String>,
+        /// Number of past entries to show before following
+        #[arg(short = 'n', long, default_value = "50")]
+        lines: usize,
+        /// Write the matching entries to this file as JSON lines instead of printing them
+        #[arg(long, value_name = "FILE")]
+        export: Option<String>,
+    },
+    /// Show what the auto-installer detected, decided and installed
+    History {
+        /// Only events since: unix seconds, an age like 2h, or YYYY-MM-DD[ HH:MM]
+        #[arg(long)]
+        since: Option<String>,
+        /// Only events for this module
+        #[arg(short, long)]
+        module: Option<String>,
+    },
     /// Simulate a missing module for testing
     Test {
         /// Module name to simulate
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Command;
+
+/// A conda `environment.yml`: conda specs (`numpy=1.26`, `conda-forge::scipy`) plus
+/// the requirements of its `pip:` section
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvironmentFile {
+    pub name: Option<String>,
+    pub channels: Vec<String>,
+    pub conda: Vec<String>,
+    pub pip: Vec<String>,
+}
+
+/// One package in `conda list --json`
+#[derive(Deserialize)]
+struct ListedPackage {
+    name: String,
+    version: String,
+    channel: String,
+}
+
+impl EnvironmentFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid environment file {}", path.display()))
+    }
+
+    /// Read the subset of YAML conda writes: top-level keys, block or flow lists,
+    /// and the nested `- pip:` list under `dependencies`
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut env = Self::default();
+        let mut section = String::new();
+        // Indentation of the `- pip:` dash while inside its list
+        let mut pip_indent: Option<usize> = None;
+
+        for (number, raw) in content.lines().enumerate() {
+            let line = strip_comment(raw);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indent = line.len() - line.trim_start().len();
+            let line = line.trim();
+
+            if indent == 0 && !line.starts_with('-') {
+                let (key, value) = line.split_once(':')
+                    .ok_or_else(|| anyhow!("line {}: expected `key:`", number + 1))?;
+                section = key.trim().to_string();
+                pip_indent = None;
+                let value = value.trim();
+                match section.as_str() {
+                    "name" if !value.is_empty() => env.name = Some(unquote(value)),
+                    "channels" => env.channels.extend(flow_list(value)),
+                    "dependencies" => env.conda.extend(flow_list(value)),
+                    _ => {}
+                }
+                continue;
+            }
+
+            let Some(item) = line.strip_prefix('-') else {
+                return Err(anyhow!("line {}: expected a list item", number + 1));
+            };
+            let item = item.trim();
+            if pip_indent.is_some_and(|dash| indent <= dash) {
+                pip_indent = None;
+            }
+            match section.as_str() {
+                "channels" => env.channels.push(unquote(item)),
+                "dependencies" if pip_indent.is_some() => env.pip.push(unquote(item)),
+                "dependencies" => match item.strip_prefix("pip:") {
+                    Some(rest) => {
+                        env.pip.extend(flow_list(rest.trim()));
+                        pip_indent = Some(indent);
+                    }
+                    None => env.conda.push(unquote(item)),
+                },
+                _ => {}
+            }
+        }
+        Ok(env)
+    }
+
+    /// The packages installed in the conda environment at `prefix`; PyPI ones make up the `pip:` section
+    pub fn from_prefix(prefix: &Path) -> Result<Self> {
+        let output = Command::new("conda")
+            .arg("list").arg("--json").arg("--prefix").arg(prefix)
+            .output()
+            .context("Failed to run conda")?;
+        if !output.status.success() {
+            return Err(anyhow!("conda list failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+        let name = prefix.file_name().map(|n| n.to_string_lossy().to_string());
+        Self::from_conda_list(&String::from_utf8_lossy(&output.stdout), name)
+    }
+
+    pub fn from_conda_list(json: &str, name: Option<String>) -> Result<Self> {
+        let listed: Vec<ListedPackage> = serde_json::from_str(json)?;
+        let mut env = Self { name, ..Self::default() };
+        for package in listed {
+            if package.channel == "pypi" {
+                env.pip.push(format!("{}=={}", package.name, package.version));
+                continue;
+            }
+            // Anaconda's own channels are what `defaults` means
+            let channel = if package.channel.starts_with("pkgs/") { "defaults".to_string() } else { package.channel };
+            if !env.channels.contains(&channel) {
+                env.channels.push(channel);
+            }
+            env.conda.push(format!("{}={}", package.name, package.version));
+        }
+        Ok(env)
+    }
+
+    /// The `python` spec's version, for creating the environment
+    pub fn python_version(&self) -> Option<String> {
+        self.conda.iter()
+            .map(|spec| split_spec(spec))
+            .find(|(name, _)| name.rsplit("::").next() == Some("python"))
+            .and_then(|(_, version)| version)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if let Some(name) = &self.name {
+            out.push_str(&format!("name: {}\n", name));
+        }
+        if !self.channels.is_empty() {
+            out.push_str("channels:\n");
+            for channel in &self.channels {
+                out.push_str(&format!("  - {}\n", channel));
+            }
+        }
+        out.push_str("dependencies:\n");
+        for spec in &self.conda {
+            out.push_str(&format!("  - {}\n", spec));
+        }
+        if !self.pip.is_empty() {
+            out.push_str("  - pip:\n");
+            for requirement in &self.pip {
+                out.push_str(&format!("      - {}\n", requirement));
+            }
+        }
+        out
+    }
+}
+
+/// `numpy=1.26.4=py311h…` or `conda-forge::numpy==1.26` to the name (with any channel,
+/// which conda accepts) and the exact version, when it pins one; ranges like `numpy>=1.26`
+/// keep the whole spec as the name
+pub fn split_spec(spec: &str) -> (String, Option<String>) {
+    let spec = spec.trim();
+    let Some(at) = spec.find(|c: char| "=<>!~ ".contains(c)) else {
+        return (spec.to_string(), None);
+    };
+    let (name, rest) = spec.split_at(at);
+    let version = rest.strip_prefix("==").or_else(|| rest.strip_prefix('='))
+        .filter(|version| !version.is_empty() && !version.starts_with(|c: char| "=<>!~".contains(c)));
+    match version {
+        // A build string may follow the version
+        Some(version) => (name.to_string(), Some(version.split('=').next().unwrap_or(version).trim().to_string())),
+        None => (spec.to_string(), None),
+    }
+}
+
+/// YAML comments start at `#` at line start or after whitespace
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    match line.find(" #") {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+/// Items of `[a, b]`; nothing for an empty value
+fn flow_list(value: &str) -> Vec<String> {
+    value.strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .map(|inner| inner.split(',').map(unquote).filter(|item| !item.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_and_round_trips_environment_file() {
+        let env = EnvironmentFile::parse(r#"
+name: science  # for the lab
+channels: [conda-forge, defaults]
+dependencies:
+  - python=3.11
+  - conda-forge::numpy=1.26.4=py311h64a7726_0
+  - scipy>=1.11
+  - pip
+  - pip:
+    - requests==2.31.0
+    - "-r requirements.txt"
+prefix: /opt/conda/envs/science
+"#).unwrap();
+
+        assert_eq!(env.name.as_deref(), Some("science"));
+        assert_eq!(env.channels, vec!["conda-forge", "defaults"]);
+        assert_eq!(env.conda, vec!["python=3.11", "conda-forge::numpy=1.26.4=py311h64a7726_0", "scipy>=1.11", "pip"]);
+        assert_eq!(env.pip, vec!["requests==2.31.0", "-r requirements.txt"]);
+        assert_eq!(env.python_version().as_deref(), Some("3.11"));
+        assert_eq!(EnvironmentFile::parse(&env.render()).unwrap(), env);
+
+        assert_eq!(split_spec("conda-forge::numpy=1.26.4=py311h64a7726_0"), ("conda-forge::numpy".to_string(), Some("1.26.4".to_string())));
+        assert_eq!(split_spec("numpy==1.26"), ("numpy".to_string(), Some("1.26".to_string())));
+        assert_eq!(split_spec("scipy>=1.11"), ("scipy>=1.11".to_string(), None));
+        assert_eq!(split_spec("pip"), ("pip".to_string(), None));
+        assert!(EnvironmentFile::parse("dependencies:\n  numpy").is_err());
+    }
+
+    #[test]
+    fn test_exports_conda_list() {
+        let env = EnvironmentFile::from_conda_list(r#"[
+            {"name": "numpy", "version": "1.26.4", "channel": "conda-forge"},
+            {"name": "python", "version": "3.11.8", "channel": "pkgs/main"},
+            {"name": "requests", "version": "2.31.0", "channel": "pypi"}
+        ]"#, Some("science".to_string())).unwrap();
+        assert_eq!(env.channels, vec!["conda-forge", "defaults"]);
+        assert_eq!(env.conda, vec!["numpy=1.26.4", "python=3.11.8"]);
+        assert_eq!(env.pip, vec!["requests==2.31.0"]);
+        assert!(env.render().contains("  - pip:\n      - requests==2.31.0\n"));
+    }
+}
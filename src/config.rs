@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use snakegg::native::dirs;
+use crate::logger;
+
+/// Prefix of the environment variables that override settings, e.g. `SNAKEPIT_INDEX_URL`
+pub const ENV_PREFIX: &str = "SNAKEPIT_";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnakepitConfig {
@@ -9,11 +14,95 @@ pub struct SnakepitConfig {
     pub default_venv_backend: Option<String>,
     pub venv_path: Option<String>,
     pub cache_enabled: Option<bool>,
+    /// Size cap for `snakepit cache prune`, e.g. "2GB"
+    #[serde(default)]
+    pub cache_max_size: Option<String>,
     pub python_version: Option<String>,
     pub mirrors: Option<Vec<String>>,
+    /// PEP 503 / PEP 691 simple index used instead of PyPI, e.g. a private devpi or Artifactory
+    #[serde(default)]
+    pub index_url: Option<String>,
     pub timeout: Option<u64>,
     pub retries: Option<u32>,
     pub user_agent: Option<String>,
+    /// Default installer UI (`classic`, `ndjson`, `exec:<command>`)
+    #[serde(default)]
+    pub renderer: Option<String>,
+    /// Import natively installed packages right away and roll back the ones that fail
+    #[serde(default)]
+    pub verify_imports: Option<bool>,
+    /// Package index requests per second, shared by every subsystem (0 for unlimited)
+    #[serde(default)]
+    pub pypi_rate_limit: Option<f64>,
+    /// Package index requests in flight at once, shared by every subsystem
+    #[serde(default)]
+    pub pypi_max_concurrency: Option<usize>,
+    /// Force eco mode on or off; unset decides from battery and network state
+    #[serde(default)]
+    pub eco_mode: Option<bool>,
+    /// Battery charge (percent) at or below which eco mode engages while discharging
+    #[serde(default)]
+    pub eco_battery_threshold: Option<u8>,
+    /// Whether a metered connection alone engages eco mode
+    #[serde(default)]
+    pub eco_on_metered: Option<bool>,
+    /// Package index requests in flight at once while eco mode is on
+    #[serde(default)]
+    pub eco_max_concurrency: Option<usize>,
+    /// Platforms `snakepit lock` resolves for (`linux`, `macos`, `windows`); unset locks for the host
+    #[serde(default)]
+    pub lock_platforms: Option<Vec<String>>,
+    /// Python versions `snakepit lock` resolves for, e.g. ["3.9", "3.12"]
+    #[serde(default)]
+    pub lock_python_versions: Option<Vec<String>>,
+    /// Packages that may resolve to pre-releases without `--pre`; `"*"` for all
+    #[serde(default)]
+    pub allow_prereleases: Option<Vec<String>>,
+    /// `highest` (default) or `lowest` compatible versions
+    #[serde(default)]
+    pub resolution: Option<String>,
+    /// Resolve as if nothing was uploaded after this date, e.g. "2024-06-01"
+    #[serde(default)]
+    pub exclude_newer: Option<String>,
+    /// Install and sync with the old first-match resolver (no backtracking)
+    #[serde(default)]
+    pub legacy_resolver: Option<bool>,
+    /// Evaluate markers for this target instead of the interpreter, e.g. "x86_64-unknown-linux-gnu"
+    #[serde(default)]
+    pub target_triple: Option<String>,
+    /// Directory name of the project-local venv commands pick up automatically (default ".venv")
+    #[serde(default)]
+    pub project_venv: Option<String>,
+    /// How commands print results: `text` (default) or `json`
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Answer yes to every confirmation (`--yes`)
+    #[serde(default)]
+    pub assume_yes: Option<bool>,
+    /// Never read from the terminal; prompts take their default answer (`--no-input`)
+    #[serde(default)]
+    pub no_input: Option<bool>,
+    /// Licenses `snakepit licenses` accepts; anything else fails it. Unset allows every license
+    #[serde(default)]
+    pub allowed_licenses: Option<Vec<String>>,
+    /// Licenses `snakepit licenses` fails on, e.g. ["GPL*", "AGPL-3.0"]
+    #[serde(default)]
+    pub denied_licenses: Option<Vec<String>>,
+    /// Where `snakepit publish` uploads: `pypi`, `testpypi` or an index's upload URL
+    #[serde(default)]
+    pub publish_url: Option<String>,
+    /// Uninstall snapshots kept at most; older ones are pruned after each new snapshot
+    #[serde(default)]
+    pub snapshot_max_count: Option<usize>,
+    /// Age past which snapshots are pruned, e.g. "30d"
+    #[serde(default)]
+    pub snapshot_max_age: Option<String>,
+    /// Space all snapshots may take together, e.g. "1GB"
+    #[serde(default)]
+    pub snapshot_max_size: Option<String>,
+    /// Risk score (0-100) above which `uninstall` asks first and, without a yes, refuses; 50 when unset
+    #[serde(default)]
+    pub uninstall_risk_threshold: Option<u8>,
 }
 
 impl Default for SnakepitConfig {
@@ -23,11 +112,39 @@ impl Default for SnakepitConfig {
             default_venv_backend: Some("venv".to_string()),
             venv_path: None,
             cache_enabled: Some(true),
+            cache_max_size: None,
             python_version: None,
             mirrors: None,
+            index_url: None,
             timeout: Some(30),
             retries: Some(3),
             user_agent: Some("snakepit/0.1.0".to_string()),
+            renderer: None,
+            verify_imports: None,
+            pypi_rate_limit: None,
+            pypi_max_concurrency: None,
+            eco_mode: None,
+            eco_battery_threshold: None,
+            eco_on_metered: None,
+            eco_max_concurrency: None,
+            lock_platforms: None,
+            lock_python_versions: None,
+            allow_prereleases: None,
+            resolution: None,
+            exclude_newer: None,
+            legacy_resolver: None,
+            target_triple: None,
+            project_venv: None,
+            output: None,
+            assume_yes: None,
+            no_input: None,
+            allowed_licenses: None,
+            denied_licenses: None,
+            publish_url: None,
+            snapshot_max_count: None,
+            snapshot_max_age: None,
+            snapshot_max_size: None,
+            uninstall_risk_threshold: None,
         }
     }
 }
@@ -37,16 +154,9 @@ impl SnakepitConfig {
         Self::default()
     }
 
+    /// Every layer merged: system, user, project, then `SNAKEPIT_*` variables
     pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path()?;
-        
-        if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)?;
-            let config: SnakepitConfig = toml::from_str(&content)?;
-            Ok(config)
-        } else {
-            Ok(Self::default())
-        }
+        LayeredConfig::load().config()
     }
 
     pub fn save(&self) -> Result<()> {
@@ -71,6 +181,24 @@ impl SnakepitConfig {
         }
     }
 
+    /// Machine-wide settings an administrator manages, below the user's own
+    pub fn get_system_config_path() -> PathBuf {
+        if cfg!(target_os = "windows") {
+            let program_data = std::env::var_os("PROGRAMDATA").unwrap_or_else(|| "C:\\ProgramData".into());
+            PathBuf::from(program_data).join("snakepit").join("config.toml")
+        } else {
+            PathBuf::from("/etc/snakepit/config.toml")
+        }
+    }
+
+    /// Names of every setting, as written in config files
+    pub fn keys() -> Vec<String> {
+        match serde_json::to_value(Self::default()) {
+            Ok(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn get_venv_path(&self) -> PathBuf {
         if let Some(path) = &self.venv_path {
             PathBuf::from(path)
@@ -116,6 +244,11 @@ impl SnakepitConfig {
         self
     }
 
+    pub fn with_cache_max_size(mut self, size: &str) -> Self {
+        self.cache_max_size = Some(size.to_string());
+        self
+    }
+
     pub fn with_mirrors(mut self, mirrors: Vec<String>) -> Self {
         self.mirrors = Some(mirrors);
         self
@@ -130,6 +263,368 @@ impl SnakepitConfig {
         self.retries = Some(retries);
         self
     }
+
+    pub fn with_pypi_rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.pypi_rate_limit = Some(requests_per_second);
+        self
+    }
+
+    pub fn with_pypi_max_concurrency(mut self, max_concurrent: usize) -> Self {
+        self.pypi_max_concurrency = Some(max_concurrent);
+        self
+    }
+
+    pub fn with_eco_mode(mut self, enabled: bool) -> Self {
+        self.eco_mode = Some(enabled);
+        self
+    }
+}
+
+/// Where a setting's value came from; later sources win
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    /// The project's snakepit.toml, or `[tool.snakepit]` of its pyproject.toml
+    Project(PathBuf),
+    Env(String),
+    Cli,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::System(path) => write!(f, "system ({})", path.display()),
+            ConfigSource::User(path) => write!(f, "user ({})", path.display()),
+            ConfigSource::Project(path) => write!(f, "project ({})", path.display()),
+            ConfigSource::Env(var) => write!(f, "environment ({})", var),
+            ConfigSource::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// Settings merged from every layer, remembering which layer set each one:
+/// defaults, system, user, project (`snakepit.toml` or `[tool.snakepit]`), `SNAKEPIT_*` variables, then flags
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    values: toml::Table,
+    origins: BTreeMap<String, ConfigSource>,
+}
+
+impl LayeredConfig {
+    /// Just the defaults
+    pub fn new() -> Self {
+        let mut layers = Self { values: toml::Table::new(), origins: BTreeMap::new() };
+        if let Ok(defaults) = toml::Table::try_from(SnakepitConfig::default()) {
+            layers.add_table(defaults, ConfigSource::Default);
+        }
+        layers
+    }
+
+    /// Defaults, then every file and variable that applies here; a broken layer is
+    /// reported and skipped rather than failing the command
+    pub fn load() -> Self {
+        let mut layers = Self::new();
+        let mut files = vec![ConfigSource::System(SnakepitConfig::get_system_config_path())];
+        if let Ok(path) = SnakepitConfig::get_config_path() {
+            files.push(ConfigSource::User(path));
+        }
+        if let Some(path) = std::env::current_dir().ok().and_then(|dir| find_project_file(&dir)) {
+            files.push(ConfigSource::Project(path));
+        }
+        for source in files {
+            if let Err(e) = layers.add_file(source) {
+                logger::warn(format!("⚠️  Ignoring configuration: {:#}", e));
+            }
+        }
+        layers.add_env(std::env::vars());
+        layers
+    }
+
+    /// Merge a config file; a missing one adds nothing
+    pub fn add_file(&mut self, source: ConfigSource) -> Result<()> {
+        let path = match &source {
+            ConfigSource::System(path) | ConfigSource::User(path) | ConfigSource::Project(path) => path.clone(),
+            _ => return Err(anyhow::anyhow!("{} is not a file", source)),
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+        if !matches!(source, ConfigSource::Project(_)) {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let table: toml::Table = toml::from_str(&content)
+                .with_context(|| format!("Invalid TOML in {}", path.display()))?;
+            self.add_table(table, source);
+            return Ok(());
+        }
+
+        // Project files mix settings with project metadata: take the keys that are settings
+        // (plus the project keys `backend` and `venv_name`), then the `[config]` table
+        let Some(mut project) = read_project_table(&path)? else { return Ok(()) };
+        let config = match project.remove("config") {
+            Some(toml::Value::Table(config)) => config,
+            Some(_) => return Err(anyhow::anyhow!("[config] in {} must be a table", path.display())),
+            None => toml::Table::new(),
+        };
+        let keys = SnakepitConfig::keys();
+        let mut settings = toml::Table::new();
+        for (key, value) in project {
+            let key = match key.replace('-', "_").as_str() {
+                "backend" => "default_backend".to_string(),
+                "venv_name" => "project_venv".to_string(),
+                key => key.to_string(),
+            };
+            if keys.contains(&key) {
+                settings.insert(key, value);
+            }
+        }
+        settings.extend(config.into_iter().map(|(key, value)| (key.replace('-', "_"), value)));
+        self.add_table(settings, source);
+        Ok(())
+    }
+
+    pub fn add_table(&mut self, table: toml::Table, source: ConfigSource) {
+        let keys = SnakepitConfig::keys();
+        for (key, value) in table {
+            if !keys.contains(&key) {
+                logger::warn(format!("⚠️  Unknown setting '{}' in {}", key, source));
+                continue;
+            }
+            self.set(&key, value, source.clone());
+        }
+    }
+
+    /// `SNAKEPIT_<SETTING>` variables; values are read as the setting's type, so
+    /// `SNAKEPIT_TIMEOUT=60` is a number, `SNAKEPIT_PYTHON_VERSION=3.11` a string and
+    /// `SNAKEPIT_MIRRORS=a,b` a list
+    pub fn add_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+        let keys = SnakepitConfig::keys();
+        for (var, raw) in vars {
+            let Some(key) = var.strip_prefix(ENV_PREFIX).map(|name| name.to_lowercase()) else { continue };
+            if !keys.contains(&key) {
+                continue;
+            }
+            match typed_value(&key, &raw) {
+                Some(value) => self.set(&key, value, ConfigSource::Env(var)),
+                None => logger::warn(format!("⚠️  Ignoring {}: '{}' is not a valid {}", var, raw, key)),
+            }
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<toml::Value>, source: ConfigSource) {
+        self.values.insert(key.to_string(), value.into());
+        self.origins.insert(key.to_string(), source);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        self.values.get(key)
+    }
+
+    pub fn config(&self) -> Result<SnakepitConfig> {
+        toml::Value::Table(self.values.clone()).try_into()
+            .context("Invalid configuration")
+    }
+
+    /// Every set value with the layer it came from, by key
+    pub fn entries(&self) -> Vec<(&str, &toml::Value, &ConfigSource)> {
+        self.origins.iter()
+            .filter_map(|(key, source)| Some((key.as_str(), self.values.get(key)?, source)))
+            .collect()
+    }
+}
+
+/// `raw` as the value of setting `key`, as `config set` takes it
+pub fn parse_setting(key: &str, raw: &str) -> Result<toml::Value> {
+    if !SnakepitConfig::keys().iter().any(|k| k == key) {
+        return Err(anyhow::anyhow!("Unknown setting '{}' (see `snakepit config list`)", key));
+    }
+    typed_value(key, raw).ok_or_else(|| anyhow::anyhow!("'{}' is not a valid value for {}", raw, key))
+}
+
+/// Set `key` in the file behind `source` (the user file, or the `[config]` table of the
+/// project's snakepit.toml or `[tool.snakepit]`), or remove it when `value` is `None`.
+///
+/// The file is edited in place, so comments and everything else in it are kept.
+pub fn write_setting(source: &ConfigSource, key: &str, value: Option<toml::Value>) -> Result<()> {
+    let (path, table_path): (&PathBuf, &[&str]) = match source {
+        ConfigSource::User(path) | ConfigSource::System(path) => (path, &[]),
+        ConfigSource::Project(path) if is_pyproject(path) => (path, &["tool", "snakepit", "config"]),
+        ConfigSource::Project(path) => (path, &["config"]),
+        _ => return Err(anyhow::anyhow!("Can't write settings to {}", source)),
+    };
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let mut document: toml_edit::DocumentMut = content.parse()
+        .with_context(|| format!("Invalid TOML in {}", path.display()))?;
+
+    let mut settings = document.as_table_mut();
+    for part in table_path {
+        settings = settings.entry(part)
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                toml_edit::Item::Table(table)
+            })
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("'{}' in {} must be a table", part, path.display()))?;
+    }
+    match value {
+        Some(value) => {
+            let value: toml_edit::Value = value.to_string().parse()
+                .map_err(|e| anyhow::anyhow!("Can't write {}: {}", key, e))?;
+            settings.insert(key, toml_edit::value(value));
+        }
+        None => {
+            settings.remove(key);
+        }
+    }
+
+    // Don't leave an empty `[config]` behind
+    if let Some((last, parents)) = table_path.split_last() {
+        let mut parent = Some(document.as_table_mut());
+        for part in parents {
+            parent = parent.and_then(|table| table.get_mut(part)).and_then(|item| item.as_table_mut());
+        }
+        if let Some(parent) = parent {
+            if parent.get(last).and_then(|item| item.as_table()).is_some_and(|table| table.is_empty()) {
+                parent.remove(last);
+            }
+        }
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, document.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The file `config set --project` writes: the nearest project file, else `dir`'s
+/// pyproject.toml when there is one, else a new snakepit.toml in `dir`
+pub fn project_config_path(dir: &Path) -> PathBuf {
+    find_project_file(dir)
+        .or_else(|| Some(dir.join(PYPROJECT_FILE)).filter(|path| path.is_file()))
+        .unwrap_or_else(|| dir.join(crate::overrides::PROJECT_FILE))
+}
+
+/// Move `dir/snakepit.toml` into `[tool.snakepit]` of `dir/pyproject.toml` and delete it.
+///
+/// Name, version and description already live in `[project]`, so they're dropped; so are
+/// `dependencies`, which belong in `[project].dependencies`. Those are returned so the
+/// caller can tell the user.
+pub fn migrate_to_pyproject(dir: &Path) -> Result<Vec<String>> {
+    let source = dir.join(crate::overrides::PROJECT_FILE);
+    let target = dir.join(PYPROJECT_FILE);
+    if !source.is_file() {
+        return Err(anyhow::anyhow!("No {} in {}", crate::overrides::PROJECT_FILE, dir.display()));
+    }
+    if !target.is_file() {
+        return Err(anyhow::anyhow!("No {} in {} to move the settings into", PYPROJECT_FILE, dir.display()));
+    }
+    if read_project_table(&target)?.is_some() {
+        return Err(anyhow::anyhow!("{} already has a [tool.snakepit] table", target.display()));
+    }
+
+    let mut settings = read_project_table(&source)?.unwrap_or_default();
+    for key in ["name", "version", "description"] {
+        settings.remove(key);
+    }
+    let dropped: Vec<String> = match settings.remove("dependencies") {
+        Some(toml::Value::Array(dependencies)) => dependencies.iter()
+            .filter_map(|dependency| dependency.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let content = std::fs::read_to_string(&target)?;
+    let mut document: toml_edit::DocumentMut = content.parse()
+        .with_context(|| format!("Invalid TOML in {}", target.display()))?;
+    let snakepit: toml_edit::DocumentMut = toml::to_string(&settings)?.parse()?;
+    let mut snakepit = snakepit.as_table().clone();
+    // Positions carried over from the parsed string would interleave with pyproject's own tables
+    place_after(&mut snakepit, &mut content.lines().count());
+    let tool = document.entry("tool")
+        .or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("'tool' in {} must be a table", target.display()))?;
+    tool.insert("snakepit", toml_edit::Item::Table(snakepit));
+
+    std::fs::write(&target, document.to_string())
+        .with_context(|| format!("Failed to write {}", target.display()))?;
+    std::fs::remove_file(&source)?;
+    Ok(dropped)
+}
+
+/// `raw` as the first of string, TOML literal or comma-separated list that `key` accepts
+fn typed_value(key: &str, raw: &str) -> Option<toml::Value> {
+    let literal = toml::from_str::<toml::Table>(&format!("value = {}", raw)).ok()
+        .and_then(|mut table| table.remove("value"));
+    let list = toml::Value::Array(raw.split(',').map(|item| toml::Value::String(item.trim().to_string())).collect());
+    [Some(toml::Value::String(raw.to_string())), literal, Some(list)]
+        .into_iter()
+        .flatten()
+        .find(|value| {
+            let mut single = toml::Table::new();
+            single.insert(key.to_string(), value.clone());
+            toml::Value::Table(single).try_into::<SnakepitConfig>().is_ok()
+        })
+}
+
+const PYPROJECT_FILE: &str = "pyproject.toml";
+
+/// Number `table` and its subtables from `next` on, in order, so they're written there
+fn place_after(table: &mut toml_edit::Table, next: &mut usize) {
+    table.set_position(*next);
+    *next += 1;
+    for (_, item) in table.iter_mut() {
+        if let Some(subtable) = item.as_table_mut() {
+            place_after(subtable, next);
+        }
+    }
+}
+
+fn is_pyproject(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == PYPROJECT_FILE)
+}
+
+/// The nearest file holding the project's snakepit settings, at or above `dir`: a
+/// snakepit.toml, else a pyproject.toml with a `[tool.snakepit]` table
+pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|ancestor| {
+        let snakepit = ancestor.join(crate::overrides::PROJECT_FILE);
+        if snakepit.is_file() {
+            return Some(snakepit);
+        }
+        let pyproject = ancestor.join(PYPROJECT_FILE);
+        (pyproject.is_file() && read_project_table(&pyproject).ok().flatten().is_some()).then_some(pyproject)
+    })
+}
+
+/// snakepit's table in a project file: all of a snakepit.toml, or `[tool.snakepit]` of a
+/// pyproject.toml (`None` when it has none)
+pub fn read_project_table(path: &Path) -> Result<Option<toml::Table>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut document: toml::Table = toml::from_str(&content)
+        .with_context(|| format!("Invalid TOML in {}", path.display()))?;
+    if !is_pyproject(path) {
+        return Ok(Some(document));
+    }
+    let snakepit = match document.remove("tool") {
+        Some(toml::Value::Table(mut tool)) => tool.remove("snakepit"),
+        _ => None,
+    };
+    match snakepit {
+        Some(toml::Value::Table(snakepit)) => Ok(Some(snakepit)),
+        Some(_) => Err(anyhow::anyhow!("[tool.snakepit] in {} must be a table", path.display())),
+        None => Ok(None),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,6 +638,12 @@ pub struct ProjectConfig {
     pub dependencies: Vec<String>,
     pub dev_dependencies: Vec<String>,
     pub scripts: Option<std::collections::HashMap<String, String>>,
+    /// Specifiers that replace every requirement on a package, e.g. `urllib3 = ">=1.26,<3"`
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub overrides: std::collections::BTreeMap<String, String>,
+    /// Exact versions forced on packages wherever they're required
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub pins: std::collections::BTreeMap<String, String>,
 }
 
 impl ProjectConfig {
@@ -157,6 +658,8 @@ impl ProjectConfig {
             dependencies: Vec::new(),
             dev_dependencies: Vec::new(),
             scripts: None,
+            overrides: std::collections::BTreeMap::new(),
+            pins: std::collections::BTreeMap::new(),
         }
     }
 
@@ -247,5 +750,108 @@ mod tests {
         assert_eq!(config.python_version, Some("3.9".to_string()));
         assert_eq!(config.cache_enabled, Some(false));
     }
+
+    #[test]
+    fn test_layers_merge_in_order() {
+        let dir = std::env::temp_dir().join(format!("snakepit-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let user = dir.join("config.toml");
+        std::fs::write(&user, "timeout = 10\nindex_url = \"https://user.example/simple\"\n").unwrap();
+        let project = dir.join("snakepit.toml");
+        std::fs::write(&project, "name = \"demo\"\n\n[config]\nindex_url = \"https://project.example/simple\"\n").unwrap();
+
+        let mut layers = LayeredConfig::new();
+        layers.add_file(ConfigSource::User(user.clone())).unwrap();
+        layers.add_file(ConfigSource::Project(project.clone())).unwrap();
+        layers.add_env([
+            ("SNAKEPIT_PYTHON_VERSION".to_string(), "3.11".to_string()),
+            ("SNAKEPIT_RETRIES".to_string(), "5".to_string()),
+            ("SNAKEPIT_MIRRORS".to_string(), "https://a, https://b".to_string()),
+            ("SNAKEPIT_SHELL".to_string(), "1".to_string()),
+        ]);
+        layers.set("timeout", 60, ConfigSource::Cli);
+
+        let config = layers.config().unwrap();
+        assert_eq!(config.timeout, Some(60));
+        assert_eq!(config.index_url.as_deref(), Some("https://project.example/simple"));
+        assert_eq!(config.python_version.as_deref(), Some("3.11"));
+        assert_eq!(config.retries, Some(5));
+        assert_eq!(config.mirrors, Some(vec!["https://a".to_string(), "https://b".to_string()]));
+        assert_eq!(config.default_backend.as_deref(), Some("pip"));
+
+        let origin = |key: &str| layers.entries().into_iter().find(|(k, _, _)| *k == key).map(|(_, _, source)| source.clone());
+        assert_eq!(origin("timeout"), Some(ConfigSource::Cli));
+        assert_eq!(origin("index_url"), Some(ConfigSource::Project(project)));
+        assert_eq!(origin("retries"), Some(ConfigSource::Env("SNAKEPIT_RETRIES".to_string())));
+        assert_eq!(origin("default_backend"), Some(ConfigSource::Default));
+        assert_eq!(origin("exclude_newer"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_setting_keeps_the_rest_of_the_file() {
+        let dir = std::env::temp_dir().join(format!("snakepit-config-set-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let project = dir.join("snakepit.toml");
+        std::fs::write(&project, "name = \"demo\"\n\n[pins]\nnumpy = \"1.26.4\"\n").unwrap();
+        let source = ConfigSource::Project(project.clone());
+
+        write_setting(&source, "default_backend", Some(parse_setting("default_backend", "uv").unwrap())).unwrap();
+        write_setting(&source, "timeout", Some(parse_setting("timeout", "45").unwrap())).unwrap();
+        let written: toml::Table = toml::from_str(&std::fs::read_to_string(&project).unwrap()).unwrap();
+        assert_eq!(written["name"].as_str(), Some("demo"));
+        assert_eq!(written["pins"]["numpy"].as_str(), Some("1.26.4"));
+        assert_eq!(written["config"]["default_backend"].as_str(), Some("uv"));
+        assert_eq!(written["config"]["timeout"].as_integer(), Some(45));
+
+        write_setting(&source, "default_backend", None).unwrap();
+        write_setting(&source, "timeout", None).unwrap();
+        let written: toml::Table = toml::from_str(&std::fs::read_to_string(&project).unwrap()).unwrap();
+        assert!(!written.contains_key("config"));
+
+        std::fs::write(&project, "# settings\nname = \"demo\"\n").unwrap();
+        write_setting(&source, "timeout", Some(toml::Value::Integer(5))).unwrap();
+        assert!(std::fs::read_to_string(&project).unwrap().starts_with("# settings\n"));
+
+        assert!(parse_setting("no_such_setting", "1").is_err());
+        assert!(parse_setting("timeout", "soon").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tool_snakepit_in_pyproject() {
+        let dir = std::env::temp_dir().join(format!("snakepit-pyproject-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let pyproject = dir.join("pyproject.toml");
+        std::fs::write(&pyproject, "[project]\nname = \"demo\"  # keep me\n").unwrap();
+        std::fs::write(dir.join("snakepit.toml"), concat!(
+            "name = \"demo\"\nbackend = \"uv\"\ndependencies = [\"click\"]\n",
+            "[scripts]\ntest = \"pytest -q\"\n[pins]\nnumpy = \"1.26.4\"\n",
+        )).unwrap();
+
+        assert_eq!(migrate_to_pyproject(&dir).unwrap(), vec!["click"]);
+        assert!(!dir.join("snakepit.toml").exists());
+        let written = std::fs::read_to_string(&pyproject).unwrap();
+        assert!(written.contains("name = \"demo\"  # keep me"));
+        assert!(migrate_to_pyproject(&dir).is_err());
+
+        assert_eq!(find_project_file(&dir.join("src")), Some(pyproject.clone()));
+        let tasks = crate::tasks::ProjectTasks::load(&dir).unwrap();
+        assert_eq!(tasks.get("test").map(|task| task.command.as_str()), Some("pytest -q"));
+
+        let source = ConfigSource::Project(pyproject.clone());
+        write_setting(&source, "index_url", Some(toml::Value::String("https://devpi.example/simple".to_string()))).unwrap();
+        let mut layers = LayeredConfig::new();
+        layers.add_file(source).unwrap();
+        let config = layers.config().unwrap();
+        assert_eq!(config.default_backend.as_deref(), Some("uv"));
+        assert_eq!(config.index_url.as_deref(), Some("https://devpi.example/simple"));
+        let table = read_project_table(&pyproject).unwrap().unwrap();
+        assert_eq!(table["pins"]["numpy"].as_str(), Some("1.26.4"));
+        assert!(!table.contains_key("name"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
 
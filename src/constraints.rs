@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+use crate::markers::{self, TargetEnvironment, VersionSpecifier};
+
+/// Version limits from pip-style constraints files (`-c constraints.txt`).
+///
+/// Unlike requirements, a constraint never adds a package: it only narrows the
+/// versions the resolver may pick if something else asks for that package.
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    specs: HashMap<String, Vec<VersionSpecifier>>,
+    files: Vec<PathBuf>,
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut constraints = Self::new();
+        for path in paths {
+            constraints.load(path.as_ref())?;
+        }
+        Ok(constraints)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<()> {
+        let canonical = path.canonicalize()
+            .map_err(|e| anyhow::anyhow!("Cannot read constraints file {}: {}", path.display(), e))?;
+        if self.files.contains(&canonical) {
+            return Ok(());
+        }
+        self.files.push(canonical);
+
+        let content = std::fs::read_to_string(path)?;
+        let content = content.replace("\\\r\n", " ").replace("\\\n", " ");
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split(" #").next().unwrap_or("").trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Constraints files may pull in further constraints files
+            if let Some(nested) = line.strip_prefix("-c").or_else(|| line.strip_prefix("--constraint")) {
+                let nested = nested.trim_start_matches('=').trim();
+                self.load(&dir.join(nested))?;
+                continue;
+            }
+            if line.starts_with('-') {
+                // Index options and the like don't constrain versions
+                continue;
+            }
+
+            self.add_line(line)
+                .map_err(|e| anyhow::anyhow!("{}:{}: {}", path.display(), i + 1, e))?;
+        }
+        Ok(())
+    }
+
+    /// Add one PEP 508 constraint line, e.g. `urllib3<2; python_version < "3.10"`
+    pub fn add_line(&mut self, line: &str) -> Result<()> {
+        // Per-requirement options such as --hash= carry no version information
+        let line = line.split(" --").next().unwrap_or(line).trim();
+        let spec = markers::parse_requirement(line)?;
+
+        if !spec.extras.is_empty() {
+            return Err(anyhow::anyhow!("constraints cannot request extras ({})", line));
+        }
+        if spec.marker.as_ref().is_some_and(|m| !m.evaluate(&TargetEnvironment::host())) {
+            return Ok(());
+        }
+
+        self.specs.entry(canonical_name(&spec.name))
+            .or_default()
+            .extend(spec.version_specs);
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.values().all(|specs| specs.is_empty())
+    }
+
+    /// The files constraints were loaded from, for backends that take `-c` themselves
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn get(&self, package: &str) -> Option<&[VersionSpecifier]> {
+        self.specs.get(&canonical_name(package))
+            .map(|specs| specs.as_slice())
+            .filter(|specs| !specs.is_empty())
+    }
+
+    pub fn allows(&self, package: &str, version: &str) -> bool {
+        self.get(package).is_none_or(|specs| specs.iter().all(|spec| spec.matches(version)))
+    }
+
+    /// Human-readable constraint for `package`, e.g. `>=1.4,<2`
+    pub fn describe(&self, package: &str) -> String {
+        self.get(package)
+            .map(|specs| specs.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(","))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constraints_narrow_versions_by_canonical_name() {
+        let mut constraints = Constraints::new();
+        constraints.add_line("URLLib3>=1.26,<2").unwrap();
+        constraints.add_line("Django==4.2.*").unwrap();
+        constraints.add_line("pywin32<300; sys_platform == \"win32-never\"").unwrap();
+
+        assert!(constraints.allows("urllib3", "1.26.18"));
+        assert!(!constraints.allows("urllib3", "2.0.7"));
+        assert!(constraints.allows("django", "4.2.11"));
+        assert!(!constraints.allows("django", "5.0"));
+        // Unconstrained packages and markers that don't apply leave versions alone
+        assert!(constraints.allows("requests", "2.31.0"));
+        assert!(constraints.allows("pywin32", "306"));
+        assert_eq!(constraints.describe("urllib3"), ">=1.26,<2");
+        assert!(constraints.add_line("requests[socks]<3").is_err());
+    }
+
+    #[test]
+    fn test_nested_constraints_files() {
+        let dir = std::env::temp_dir().join(format!("snakepit-constraints-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("base.txt"), "# shared pins\nidna==3.6\n").unwrap();
+        std::fs::write(dir.join("constraints.txt"), "-c base.txt\ncertifi>=2023.7.22  # CVE fix\n").unwrap();
+
+        let constraints = Constraints::from_files(&[dir.join("constraints.txt")]).unwrap();
+        assert_eq!(constraints.files().len(), 2);
+        assert!(!constraints.allows("idna", "3.7"));
+        assert!(constraints.allows("certifi", "2024.2.2"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+/// The file a wrapped Python program writes its traceback to, in its working directory
+pub const CRASH_LOG: &str = "snakepit_crash.log";
+
+/// Tracebacks are at the end of the log; only this many lines are scanned
+const SCAN_LINES: usize = 20;
+
+/// Reports crash logs as they're written (inotify on Linux, FSEvents/kqueue elsewhere)
+/// instead of the daemon reading every Python process's working directory each pass.
+///
+/// Directories are watched one level deep: a recursive watch over a project would also
+/// cover its virtualenv, thousands of directories that never hold a crash log.
+pub struct CrashLogWatcher {
+    watcher: RecommendedWatcher,
+    watched: HashSet<PathBuf>,
+    changes: mpsc::UnboundedReceiver<PathBuf>,
+    /// Length of each log when it was last read, so one traceback is reported once
+    seen: HashMap<PathBuf, u64>,
+}
+
+impl CrashLogWatcher {
+    pub fn new() -> Result<Self> {
+        let (sender, changes) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else { return };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            for path in event.paths.into_iter().filter(|path| path.file_name().is_some_and(|name| name == CRASH_LOG)) {
+                let _ = sender.send(path);
+            }
+        })?;
+        Ok(Self { watcher, watched: HashSet::new(), changes, seen: HashMap::new() })
+    }
+
+    /// Start watching `dir`; false if it already was
+    pub fn watch(&mut self, dir: &Path) -> Result<bool> {
+        if self.watched.contains(dir) {
+            return Ok(false);
+        }
+        self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        self.watched.insert(dir.to_path_buf());
+        Ok(true)
+    }
+
+    pub fn watched(&self) -> usize {
+        self.watched.len()
+    }
+
+    /// Wait for a crash log to change
+    pub async fn changed(&mut self) -> Option<PathBuf> {
+        self.changes.recv().await
+    }
+
+    /// The module `path` newly reports missing; `None` when it has nothing new since the last call
+    pub fn read_missing_module(&mut self, path: &Path) -> Option<String> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let length = content.len() as u64;
+        if self.seen.insert(path.to_path_buf(), length) == Some(length) {
+            return None;
+        }
+        missing_module(&content)
+    }
+}
+
+/// The module in the last `ModuleNotFoundError: No module named 'xyz'` of a traceback
+pub fn missing_module(content: &str) -> Option<String> {
+    content.lines()
+        .rev()
+        .take(SCAN_LINES)
+        .filter(|line| line.contains("ModuleNotFoundError"))
+        .find_map(|line| {
+            let start = line.find('\'')? + 1;
+            let end = start + line[start..].find('\'')?;
+            Some(line[start..end].to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_module() {
+        let traceback = "Traceback (most recent call last):\n  File \"app.py\", line 1, in <module>\n    import yaml\nModuleNotFoundError: No module named 'yaml'\n";
+        assert_eq!(missing_module(traceback).as_deref(), Some("yaml"));
+        assert_eq!(missing_module("ValueError: bad 'value'\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_reports_written_crash_logs() {
+        let dir = std::env::temp_dir().join(format!("snakepit-crash-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut watcher = CrashLogWatcher::new().unwrap();
+        assert!(watcher.watch(&dir).unwrap());
+        assert!(!watcher.watch(&dir).unwrap());
+
+        std::fs::write(dir.join("other.log"), "ModuleNotFoundError: No module named 'nope'\n").unwrap();
+        std::fs::write(dir.join(CRASH_LOG), "ModuleNotFoundError: No module named 'requests'\n").unwrap();
+        let changed = tokio::time::timeout(std::time::Duration::from_secs(5), watcher.changed()).await.unwrap().unwrap();
+        assert_eq!(changed.file_name().unwrap(), CRASH_LOG);
+
+        assert_eq!(watcher.read_missing_module(&changed).as_deref(), Some("requests"));
+        // The same traceback again is not news
+        assert_eq!(watcher.read_missing_module(&changed), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
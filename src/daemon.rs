@@ -1,24 +1,54 @@
 use crate::installer::{PackageInstaller, InstallerBackend};
 use crate::config::SnakepitConfig;
-use crate::process_monitor::ProcessMonitor;
+use crate::process_monitor::{self, ProcessMonitor};
 use anyhow::Result;
-use snakegg::native::style::{red, green, yellow, blue, cyan, bold, dim};
+use snakegg::native::style::{blue, cyan};
 use snakegg::native::dirs;
 use snakegg::native::id;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use sysinfo::{Pid, Process, System};
 use tokio::fs;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, Notify, RwLock};
 use tokio::time::sleep;
 use tokio::process::Command;
 use snakegg::native::undertaker::TheUndertaker;
 use crate::snakeskin::{Snakeskin, SnakeskinState};
-use crate::logger::GitLogger;
+use crate::logger::{self, GitLogger};
+use crate::daemon_log::{self, DaemonLog, LogEntry, Severity};
+use crate::daemon_history::{DaemonEvent, EventJournal, EventKind};
+use crate::approval::{ApprovalQueue, PendingInstall};
+use crate::module_policy::{self, ModulePolicy, PolicyRule};
+use crate::notification::{Notifier, Urgency};
+use crate::webhook;
+use crate::docker::{ContainerTarget, DockerClient};
+use crate::agent;
+use crate::crash_watch::{self, CrashLogWatcher};
+use crate::import_hook::{self, ImportHook, ImportReport};
+use crate::daemon_control::{self, ControlRequest, ControlResponse};
+use crate::eco::{EcoPolicy, PowerState};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// How often periodic jobs (state snapshots) run
+const JOB_PERIOD: Duration = Duration::from_secs(60);
+
+/// Periodic jobs are postponed to this in eco mode
+const ECO_JOB_PERIOD: Duration = Duration::from_secs(600);
+
+/// The monitoring loop runs this many times less often in eco mode
+const ECO_SLOWDOWN: u32 = 4;
+
+/// How often the daemon re-reads battery and network state
+const ECO_CHECK_PERIOD: Duration = Duration::from_secs(120);
+
+/// How long `daemon stop` waits for the daemon to wind down
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long `daemon start --daemon` waits for the detached daemon to answer
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DaemonConfig {
     pub enabled: bool,
@@ -27,9 +57,33 @@ pub struct DaemonConfig {
     pub max_install_attempts: u32,
     pub whitelist_modules: Vec<String>,
     pub blacklist_modules: Vec<String>,
+    /// Where a detached daemon's output goes; daemon.out in the data directory by default
     pub log_file: Option<PathBuf>,
+    /// snakepit.pid next to daemon.toml by default
     pub pid_file: Option<PathBuf>,
     pub git_log_repo: Option<String>,
+    /// Put an import hook into every registered environment, so a process that dies on a
+    /// missing import reports it straight away instead of through a crash log
+    #[serde(default)]
+    pub import_hooks: bool,
+    /// Queue missing modules for `snakepit daemon approve` instead of installing them;
+    /// approving one adds it to the whitelist, which is installed without asking
+    #[serde(default)]
+    pub require_approval: bool,
+    /// Per-pattern overrides of what happens to a missing module; whitelist and blacklist
+    /// entries may be patterns too
+    #[serde(default)]
+    pub module_policies: Vec<PolicyRule>,
+    /// notify-send, osascript, toast, console or quiet; picked for the platform when unset
+    #[serde(default)]
+    pub notifications: Option<String>,
+    /// Detections and install outcomes are POSTed here as JSON
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Also watch the logs of running containers with Python in them (through the Docker
+    /// socket) and install what they miss inside the container
+    #[serde(default)]
+    pub docker: bool,
 }
 
 impl Default for DaemonConfig {
@@ -48,6 +102,12 @@ impl Default for DaemonConfig {
             log_file: None,
             pid_file: None,
             git_log_repo: None,
+            import_hooks: false,
+            require_approval: false,
+            module_policies: Vec::new(),
+            notifications: None,
+            webhook_url: None,
+            docker: false,
         }
     }
 }
@@ -63,117 +123,230 @@ pub struct ModuleError {
 
 #[derive(Debug)]
 pub struct SnakepitDaemon {
-    config: DaemonConfig,
+    /// Replaced in place when a control client asks for a reload
+    config: Arc<RwLock<DaemonConfig>>,
     installer: PackageInstaller,
+    /// For installs into the environment a failing process ran in
+    backend: InstallerBackend,
     system: Arc<Mutex<System>>,
     error_cache: Arc<RwLock<HashMap<String, ModuleError>>>,
     running: Arc<RwLock<bool>>,
+    /// Wakes the monitoring loop early so a stop takes effect right away
+    shutdown: Arc<Notify>,
     daemon_id: String,
+    started_at: u64,
     process_monitor: ProcessMonitor,
+    /// Working directory of each Python process seen running in an environment, and that environment
+    process_environments: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
     undertaker: Arc<Mutex<TheUndertaker>>,
     snakeskin: Snakeskin,
     logger: Arc<Mutex<GitLogger>>,
+    journal: DaemonLog,
+    /// Every detection, decision and install attempt, for `snakepit daemon history`
+    history: EventJournal,
+    /// Missing modules waiting for approval, with `require_approval` on
+    approvals: Arc<ApprovalQueue>,
+    /// Live feed for `snakepit daemon logs --follow` clients
+    log_events: tokio::sync::broadcast::Sender<LogEntry>,
+    eco: EcoPolicy,
 }
 
 impl SnakepitDaemon {
     pub fn new(config: DaemonConfig, snakepit_config: &SnakepitConfig) -> Self {
-        let backend = match snakepit_config.default_backend.as_deref() {
-            Some("conda") => InstallerBackend::Conda,
-            Some("poetry") => InstallerBackend::Poetry,
-            _ => InstallerBackend::Pip,
-        };
+        let backend = InstallerBackend::from_config(snakepit_config.default_backend.as_deref());
 
-        let installer = PackageInstaller::new().with_backend(backend);
+        let installer = PackageInstaller::new().with_backend(backend.clone());
         let git_repo = config.git_log_repo.clone();
 
         Self {
-            config,
+            config: Arc::new(RwLock::new(config)),
             installer,
+            backend,
             system: Arc::new(Mutex::new(System::new_all())),
             error_cache: Arc::new(RwLock::new(HashMap::new())),
             running: Arc::new(RwLock::new(false)),
+            shutdown: Arc::new(Notify::new()),
             daemon_id: id::new(),
+            started_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
             process_monitor: ProcessMonitor::new(),
+            process_environments: Arc::new(Mutex::new(HashMap::new())),
             undertaker: Arc::new(Mutex::new(TheUndertaker::new())),
             snakeskin: Snakeskin::new().unwrap_or_else(|_| Snakeskin::new().unwrap()), 
             logger: Arc::new(Mutex::new(GitLogger::new(
                 dirs::data_dir().unwrap().join("snakepit").join("logs"),
                 git_repo
             ))),
+            journal: DaemonLog::open_default()
+                .unwrap_or_else(|_| DaemonLog::new(PathBuf::from(".snakepit").join("daemon.jsonl"))),
+            history: EventJournal::open_default()
+                .unwrap_or_else(|_| EventJournal::new(PathBuf::from(".snakepit").join("history.jsonl"))),
+            approvals: Arc::new(ApprovalQueue::new()),
+            log_events: tokio::sync::broadcast::channel(256).0,
+            eco: EcoPolicy::from_config(snakepit_config),
+        }
+    }
+
+    /// Write a structured log entry and hand it to any attached viewers
+    async fn record(&self, severity: Severity, module: &str, project: Option<PathBuf>, message: impl Into<String>) {
+        let entry = LogEntry::new(severity, module, project, message);
+        if let Err(e) = self.journal.append(&entry).await {
+            logger::note(format!("Could not write daemon log: {}", e));
+        }
+        // No subscribers is the normal case
+        let _ = self.log_events.send(entry);
+    }
+
+    /// Add a step to the auto-installer's history, and announce it to the webhook
+    async fn log_event(&self, event: DaemonEvent) {
+        if let Err(e) = self.history.append(&event).await {
+            logger::note(format!("Could not write daemon history: {}", e));
+        }
+        if let Some(url) = self.config.read().await.webhook_url.as_deref() {
+            webhook::post(url, &event);
         }
     }
 
-    /// Send a system notification using notify-send
-    async fn send_notification(&self, summary: &str, body: &str, urgency: &str) {
-        let _ = Command::new("notify-send")
-            .arg("-u")
-            .arg(urgency) // low, normal, critical
-            .arg("-i")
-            .arg("dialog-information")
-            .arg(format!("🐍 Snakepit: {}", summary))
-            .arg(body)
-            .spawn();
+    /// Project a process is working in: the nearest ancestor of its cwd with a manifest
+    async fn process_project(pid: Pid) -> Option<PathBuf> {
+        let cwd = fs::read_link(format!("/proc/{}/cwd", pid)).await.ok()?;
+        Some(project_root(&cwd))
+    }
+
+    /// Send a desktop notification with the configured (or this platform's) notifier
+    async fn send_notification(&self, summary: &str, body: &str, urgency: Urgency) {
+        self.notifier().await.send(summary, body, urgency);
+    }
+
+    async fn notifier(&self) -> Notifier {
+        Notifier::from_config(self.config.read().await.notifications.as_deref())
     }
 
     pub async fn start(&self) -> Result<()> {
-        println!("{}", blue("🐍 Starting Snakepit Daemon..."));
+        logger::info(blue("🐍 Starting Snakepit Daemon..."));
         
-        // Write PID file
-        if let Some(pid_file) = &self.config.pid_file {
-            fs::write(pid_file, std::process::id().to_string()).await?;
+        // Write PID file, unless another daemon is alive behind it
+        let pid_file = DaemonManager::new().pid_file(&self.config.read().await);
+        if let Some(pid) = live_daemon_pid(&pid_file) {
+            return Err(anyhow::anyhow!("Daemon already running (pid {})", pid));
         }
+        if let Some(parent) = pid_file.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&pid_file, std::process::id().to_string()).await?;
 
         // Set up signal handlers
         self.setup_signal_handlers().await?;
 
+        let socket = daemon_log::socket_path()?;
+        if let Err(e) = daemon_log::serve(&socket, self.log_events.clone()) {
+            logger::warn(format!("⚠️  Live log socket unavailable ({}): {}", socket.display(), e));
+        }
+        let agent_socket = agent::socket_path()?;
+        if let Err(e) = agent::listen(&agent_socket, self.journal.clone(), self.log_events.clone()) {
+            logger::warn(format!("⚠️  Environment agent reports unavailable ({}): {}", agent_socket.display(), e));
+        }
+        let control_socket = daemon_control::socket_path()?;
+        let control = self.control_state();
+        let served = daemon_control::serve(&control_socket, move |request| {
+            let control = control.clone();
+            async move { control.answer(request).await }
+        });
+        if let Err(e) = served {
+            logger::warn(format!("⚠️  Control socket unavailable ({}): {}", control_socket.display(), e));
+        }
+        // Listened on even with hooks off, so turning them on only takes a reload
+        let import_socket = import_hook::socket_path()?;
+        let (import_sender, import_reports) = tokio::sync::mpsc::unbounded_channel();
+        if let Err(e) = import_hook::listen(&import_socket, import_sender) {
+            logger::warn(format!("⚠️  Import hook reports unavailable ({}): {}", import_socket.display(), e));
+        }
+        if self.config.read().await.import_hooks {
+            let changed = sync_import_hooks(true, &import_socket);
+            self.record(Severity::Info, "imports", None, format!("Import hook installed in {} environments", changed)).await;
+        }
+
         // Start monitoring loop
-        self.monitoring_loop().await?;
+        self.monitoring_loop(import_reports).await?;
+
+        // Stopped through the control socket; `daemon stop` waits for the socket to go away
+        self.record(Severity::Info, "daemon", None, format!("Daemon {} stopped", self.daemon_id)).await;
+        let _ = fs::remove_file(&pid_file).await;
+        let _ = fs::remove_file(&control_socket).await;
+        let _ = fs::remove_file(&import_socket).await;
+        logger::warn("🛑 Snakepit Daemon stopped");
 
         Ok(())
     }
 
     pub async fn stop(&self) -> Result<()> {
-        println!("{}", yellow("🛑 Stopping Snakepit Daemon..."));
-        
-        {
-            let mut running = self.running.write().await;
-            *running = false;
-        }
-
-        // Remove PID file
-        if let Some(pid_file) = &self.config.pid_file {
-            let _ = fs::remove_file(pid_file).await;
-        }
-
+        logger::warn("🛑 Stopping Snakepit Daemon...");
+        self.control_state().answer(ControlRequest::Stop).await;
         Ok(())
     }
 
     pub async fn status(&self) -> Result<DaemonStatus> {
-        let running = *self.running.read().await;
-        let error_count = self.error_cache.read().await.len();
-        
-        Ok(DaemonStatus {
-            running,
-            daemon_id: self.daemon_id.clone(),
-            error_count,
+        Ok(self.control_state().status().await)
+    }
+
+    /// The shared state control requests read and change
+    fn control_state(&self) -> ControlState {
+        ControlState {
             config: self.config.clone(),
-        })
+            error_cache: self.error_cache.clone(),
+            running: self.running.clone(),
+            shutdown: self.shutdown.clone(),
+            daemon_id: self.daemon_id.clone(),
+            started_at: self.started_at,
+            journal: self.journal.clone(),
+            history: self.history.clone(),
+            approvals: self.approvals.clone(),
+            log_events: self.log_events.clone(),
+        }
+    }
+
+    /// SIGTERM and SIGINT stop the daemon after the current pass; SIGHUP reloads daemon.toml
+    #[cfg(unix)]
+    async fn setup_signal_handlers(&self) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut hangup = signal(SignalKind::hangup())?;
+        let control = self.control_state();
+        tokio::spawn(async move {
+            loop {
+                let (name, request) = tokio::select! {
+                    _ = terminate.recv() => ("SIGTERM", ControlRequest::Stop),
+                    _ = interrupt.recv() => ("SIGINT", ControlRequest::Stop),
+                    _ = hangup.recv() => ("SIGHUP", ControlRequest::ReloadConfig),
+                };
+                control.record(Severity::Info, format!("Received {}", name)).await;
+                control.answer(request).await;
+            }
+        });
+        logger::note("Signal handlers configured");
+        Ok(())
     }
 
+    #[cfg(not(unix))]
     async fn setup_signal_handlers(&self) -> Result<()> {
-        // Simplified signal handling - in a real implementation,
-        // you'd use proper signal handling libraries
-        println!("{}", dim("Signal handlers configured"));
+        let control = self.control_state();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                control.answer(ControlRequest::Stop).await;
+            }
+        });
         Ok(())
     }
 
-    async fn monitoring_loop(&self) -> Result<()> {
+    async fn monitoring_loop(&self, mut import_reports: tokio::sync::mpsc::UnboundedReceiver<ImportReport>) -> Result<()> {
         {
             let mut running = self.running.write().await;
             *running = true;
         }
 
-        println!("{}", green("✅ Snakepit Daemon started successfully!"));
+        logger::success("✅ Snakepit Daemon started successfully!");
+        self.record(Severity::Info, "daemon", None, format!("Daemon {} started", self.daemon_id)).await;
         // Restore state (Snakeskin Regrow)
         if let Ok(Some(state)) = self.snakeskin.regrow().await {
             let mut cache = self.error_cache.write().await;
@@ -183,14 +356,50 @@ impl SnakepitDaemon {
             // Could also restore config or other things
         }
 
-        println!("{}", dim("Monitoring Python processes for missing modules..."));
+        logger::note("Monitoring Python processes for missing modules...");
+
+        // Crash logs are reported as they're written; reading each process's CWD every pass
+        // is the fallback when the platform can't watch files
+        let mut crash_logs = match CrashLogWatcher::new() {
+            Ok(mut watcher) => {
+                watch_known_projects(&mut watcher);
+                logger::note(format!("Watching {} project directories for crash logs", watcher.watched()));
+                Some(watcher)
+            }
+            Err(e) => {
+                self.record(Severity::Warn, "monitor", None, format!("Can't watch crash logs ({}); polling instead", e)).await;
+                None
+            }
+        };
 
         let mut last_save = SystemTime::now();
+        let mut last_sync = SystemTime::now();
+        let mut last_power_check: Option<SystemTime> = None;
+        let mut eco_reason: Option<String> = None;
+        // When each container's log was last read
+        let mut container_logs: HashMap<String, f64> = HashMap::new();
+        let mut docker_down = false;
 
         while *self.running.read().await {
+            // 1. Eco mode: power can change under a long-running daemon, so look again now and then
+            if last_power_check.is_none_or(|t| t.elapsed().unwrap_or(ECO_CHECK_PERIOD) >= ECO_CHECK_PERIOD) {
+                let reason = self.eco.evaluate(&PowerState::detect());
+                if reason != eco_reason {
+                    let message = match &reason {
+                        Some(reason) => format!("Eco mode on ({}): postponing scheduled jobs", reason),
+                        None => "Eco mode off".to_string(),
+                    };
+                    self.record(Severity::Info, "eco", None, message).await;
+                    eco_reason = reason;
+                }
+                last_power_check = Some(SystemTime::now());
+            }
+            let job_period = if eco_reason.is_some() { ECO_JOB_PERIOD } else { JOB_PERIOD };
+
             // 2. Monitor processes
             if let Err(e) = self.process_monitor.scan_processes().await {
-                eprintln!("Error scanning processes: {}", e);
+                logger::error(format!("Error scanning processes: {}", e));
+                self.record(Severity::Error, "monitor", None, format!("Error scanning processes: {}", e)).await;
             }
 
             // 3. The Undertaker Rounds (Zombie Cleanup)
@@ -200,13 +409,25 @@ impl SnakepitDaemon {
             }
 
             // 4. Check for errors and auto-install
-            if let Err(e) = self.check_python_processes().await {
-                eprintln!("Error checking processes: {}", e);
+            if let Err(e) = self.check_python_processes(&mut crash_logs).await {
+                logger::error(format!("Error checking processes: {}", e));
+                self.record(Severity::Error, "monitor", None, format!("Error checking processes: {}", e)).await;
+            }
+            if self.config.read().await.docker {
+                match self.check_containers(&mut container_logs).await {
+                    Ok(()) => docker_down = false,
+                    // Reported once, not on every pass while Docker is down
+                    Err(e) if !docker_down => {
+                        self.record(Severity::Warn, "docker", None, format!("Can't watch containers: {}", e)).await;
+                        docker_down = true;
+                    }
+                    Err(_) => {}
+                }
             }
 
-            // 5. Snakeskin Shed (Save State) - Every 60s
+            // 5. Snakeskin Shed (Save State) - Every 60s, 10 minutes in eco mode
             if let Ok(elapsed) = last_save.elapsed() {
-                if elapsed.as_secs() >= 60 {
+                if elapsed >= job_period {
                     let errors = self.error_cache.read().await.values().cloned().collect();
                     let installed = self.installer.list_installed_packages().await.unwrap_or_default();
                     
@@ -214,46 +435,152 @@ impl SnakepitDaemon {
                         timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
                         daemon_id: self.daemon_id.clone(),
                         active_errors: errors,
-                        config: self.config.clone(),
+                        config: self.config.read().await.clone(),
                         installed_packages: installed,
                     };
                     
                     if let Err(e) = self.snakeskin.shed(&state).await {
-                        eprintln!("Failed to shed snakeskin: {}", e);
+                        logger::error(format!("Failed to shed snakeskin: {}", e));
+                        self.record(Severity::Warn, "snakeskin", None, format!("Failed to shed snakeskin: {}", e)).await;
                     }
                     last_save = SystemTime::now();
+
+                    // Projects registered since the daemon started get the hook too
+                    if self.config.read().await.import_hooks {
+                        if let Ok(socket) = import_hook::socket_path() {
+                            sync_import_hooks(true, &socket);
+                        }
+                    }
                 }
             }
 
-            // 6. Sync Logs
-            {
+            // 6. Sync Logs - every pass, or with the other jobs in eco mode
+            if eco_reason.is_none() || last_sync.elapsed().is_ok_and(|e| e >= job_period) {
                 let mut logger = self.logger.lock().await;
                 if let Err(e) = logger.sync().await {
-                    // eprintln!("Log sync failed: {}", e);
+                    // logger::info(format!("Log sync failed: {}", e));
                 }
+                last_sync = SystemTime::now();
             }
 
-            sleep(self.config.check_interval).await;
+            let check_interval = self.config.read().await.check_interval;
+            let interval = if eco_reason.is_some() { check_interval * ECO_SLOWDOWN } else { check_interval };
+            let wakeup = tokio::select! {
+                _ = sleep(interval) => Wakeup::Tick,
+                _ = self.shutdown.notified() => Wakeup::Tick,
+                Some(path) = next_crash_log(&mut crash_logs) => Wakeup::CrashLog(path),
+                Some(report) = import_reports.recv() => Wakeup::Import(report),
+                _ = self.approvals.approved() => Wakeup::Approved,
+            };
+            match wakeup {
+                Wakeup::Tick => {}
+                Wakeup::CrashLog(path) => self.handle_crash_log(&mut crash_logs, &path).await,
+                Wakeup::Import(report) => self.handle_import_report(report).await,
+                Wakeup::Approved => self.install_approved().await,
+            }
         }
 
         Ok(())
     }
 
-    async fn check_python_processes(&self) -> Result<()> {
+    async fn check_python_processes(&self, crash_logs: &mut Option<CrashLogWatcher>) -> Result<()> {
         let mut system = self.system.lock().await;
         system.refresh_processes();
 
+        let mut existing_logs = Vec::new();
         for (pid, process) in system.processes() {
             if self.is_python_process(process) {
+                // With a watcher, knowing where the process runs is enough
+                if let Some(watcher) = crash_logs.as_mut() {
+                    let Ok(cwd) = fs::read_link(format!("/proc/{}/cwd", pid)).await else { continue };
+                    // The process may be gone by the time its crash log is read
+                    if let Some(environment) = process_monitor::process_environment(process) {
+                        self.process_environments.lock().await.insert(cwd.clone(), environment);
+                    }
+                    // A log written before the directory was watched never produces an event
+                    if watcher.watch(&cwd).unwrap_or(false) && cwd.join(crash_watch::CRASH_LOG).exists() {
+                        existing_logs.push(cwd.join(crash_watch::CRASH_LOG));
+                    }
+                    continue;
+                }
                 if let Err(e) = self.check_process_errors(*pid, process).await {
-                    eprintln!("Error checking process {}: {}", pid, e);
+                    logger::error(format!("Error checking process {}: {}", pid, e));
                 }
             }
         }
+        drop(system);
+
+        for path in existing_logs {
+            self.handle_crash_log(crash_logs, &path).await;
+        }
+        Ok(())
+    }
 
+    /// Read what each Python container logged since the last pass; a `ModuleNotFoundError`
+    /// is installed inside the container it came from
+    async fn check_containers(&self, container_logs: &mut HashMap<String, f64>) -> Result<()> {
+        let docker = DockerClient::from_env();
+        if !docker.available() {
+            return Err(anyhow::anyhow!("no Docker socket"));
+        }
+        let containers = docker.python_containers().await?;
+        container_logs.retain(|id, _| containers.iter().any(|container| &container.id == id));
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        for container in containers {
+            // A container seen for the first time is read from now on, not from its start
+            let Some(since) = container_logs.insert(container.id.clone(), now) else { continue };
+            let logs = match docker.logs_since(&container.id, since).await {
+                Ok(logs) => logs,
+                Err(_) => continue,
+            };
+            if let Some(module) = crash_watch::missing_module(&logs) {
+                let mut detection = Detection::new(module, format!("container {}", container.name));
+                detection.container = Some(container);
+                self.handle_missing_module(detection).await?;
+            }
+        }
         Ok(())
     }
 
+    /// React to a crash log that was written to
+    async fn handle_crash_log(&self, crash_logs: &mut Option<CrashLogWatcher>, path: &Path) {
+        let Some(module) = crash_logs.as_mut().and_then(|watcher| watcher.read_missing_module(path)) else {
+            return;
+        };
+        let mut detection = Detection::new(module, path.display().to_string());
+        detection.project = path.parent().map(project_root);
+        detection.environment = self.crash_log_environment(path, detection.project.as_deref()).await;
+        if let Err(e) = self.handle_missing_module(detection).await {
+            logger::error(format!("Error handling {}: {}", path.display(), e));
+        }
+    }
+
+    /// React to a process an import hook saw die on a missing import
+    async fn handle_import_report(&self, report: ImportReport) {
+        let mut detection = Detection::new(report.module, format!("process {}", report.pid));
+        detection.pid = Some(report.pid);
+        detection.project = Some(project_root(&report.cwd));
+        // Install where the import failed; a base interpreter gets the default installer
+        detection.environment = process_monitor::is_environment(&report.environment).then_some(report.environment);
+        if let Err(e) = self.handle_missing_module(detection).await {
+            logger::error(format!("Error handling import report from process {}: {}", report.pid, e));
+        }
+    }
+
+    /// The environment of the Python process last seen working where `path` was written, else
+    /// the environment registered for its project
+    async fn crash_log_environment(&self, path: &Path, project: Option<&Path>) -> Option<PathBuf> {
+        let seen = match path.parent() {
+            Some(dir) => self.process_environments.lock().await.get(dir).cloned(),
+            None => None,
+        };
+        seen.or_else(|| {
+            let registry = crate::registry::ProjectRegistry::load().ok()?;
+            registry.environment_of(project?)
+        })
+    }
+
     fn is_python_process(&self, process: &sysinfo::Process) -> bool {
         let name = process.name().to_lowercase();
         name.contains("python") || 
@@ -272,7 +599,11 @@ impl SnakepitDaemon {
         // This is a simplified approach - in a real implementation,
         // you might need to hook into the process more directly
         if let Some(missing_module) = self.detect_missing_module_from_process(pid).await? {
-            self.handle_missing_module(missing_module, pid).await?;
+            let mut detection = Detection::new(missing_module, format!("process {}", pid));
+            detection.pid = Some(pid.as_u32());
+            detection.project = Self::process_project(pid).await;
+            detection.environment = process_monitor::process_environment(process);
+            self.handle_missing_module(detection).await?;
         }
 
         Ok(())
@@ -289,23 +620,11 @@ impl SnakepitDaemon {
         let cwd_link = format!("/proc/{}/cwd", pid);
         match fs::read_link(&cwd_link).await {
             Ok(cwd) => {
-                let log_path = cwd.join("snakepit_crash.log");
+                let log_path = cwd.join(crash_watch::CRASH_LOG);
                 if log_path.exists() {
                     if let Ok(content) = fs::read_to_string(&log_path).await {
-                        // Scan last 20 lines for ModuleNotFoundError
-                        for line in content.lines().rev().take(20) {
-                            if line.contains("ModuleNotFoundError") {
-                                // Extract module name: ModuleNotFoundError: No module named 'xyz'
-                                if let Some(start) = line.find("'") {
-                                    if let Some(end) = line[start+1..].find("'") {
-                                        let module = line[start+1..start+1+end].to_string();
-                                        // Verify it's not resolved yet (check file mtime vs last check?)
-                                        // For now, we assume if it's there, it's relevant
-                                        return Ok(Some(module));
-                                    }
-                                }
-                            }
-                        }
+                        // For now, we assume if it's there, it's relevant
+                        return Ok(crash_watch::missing_module(&content));
                     }
                 }
             }
@@ -320,49 +639,133 @@ impl SnakepitDaemon {
         Ok(None)
     }
 
-    async fn handle_missing_module(&self, module_name: String, pid: Pid) -> Result<()> {
+    async fn handle_missing_module(&self, detection: Detection) -> Result<()> {
+        let config = self.config.read().await.clone();
+        let module_name = detection.module.clone();
+
+        let policy = module_policy::policy_for(&config.module_policies, &module_name);
+        let whitelisted = module_policy::matches_any(&config.whitelist_modules, &module_name);
+
         // Check if module is blacklisted
-        if self.config.blacklist_modules.contains(&module_name) {
+        if module_policy::matches_any(&config.blacklist_modules, &module_name) {
+            self.log_event(detection.event(EventKind::Skipped).with_detail("blacklisted")).await;
+            return Ok(());
+        }
+        if policy == Some(ModulePolicy::Ignore) {
+            self.log_event(detection.event(EventKind::Skipped).with_detail("ignored by policy")).await;
             return Ok(());
         }
 
-        // Check if we have a whitelist and module is not in it
-        if !self.config.whitelist_modules.is_empty() && 
-           !self.config.whitelist_modules.contains(&module_name) {
+        // Check if we have a whitelist and module is not in it; with approvals, the whitelist
+        // is what may be installed without asking
+        if !config.require_approval &&
+           policy.is_none() &&
+           !config.whitelist_modules.is_empty() && 
+           !whitelisted {
+            self.log_event(detection.event(EventKind::Skipped).with_detail("not whitelisted")).await;
             return Ok(());
         }
 
-        // Check if we've already tried to install this module recently
-        let cache_key = format!("{}:{}", module_name, pid);
+        // Check if we've already tried to install this module recently; the failed attempts are
+        // in the history already, so seeing the same error again isn't recorded
+        let cache_key = detection.cache_key();
         {
             let cache = self.error_cache.read().await;
             if let Some(error) = cache.get(&cache_key) {
-                if error.install_attempts >= self.config.max_install_attempts {
+                if error.install_attempts >= config.max_install_attempts {
                     return Ok(());
                 }
             }
         }
 
-        println!("{}", yellow(format!("🔍 Detected missing module: {}", module_name)));
+        let needs_approval = config.auto_install && config.require_approval &&
+            policy.is_none() && !whitelisted;
+        if needs_approval && !self.approvals.enqueue(detection.clone()) {
+            // Already waiting for an answer, or denied
+            return Ok(());
+        }
+
+        logger::warn(format!("🔍 Detected missing module: {}", module_name));
+        self.log_event(detection.event(EventKind::Detected)).await;
+        self.record(Severity::Warn, "monitor", detection.project.clone(), format!("Missing module {} in {}", module_name, detection.origin)).await;
+
+        if needs_approval {
+            self.log_event(detection.event(EventKind::Queued)).await;
+            self.ask_approval(&detection).await;
+            return Ok(());
+        }
+
         self.send_notification(
             "Missing Module Detected",
-            &format!("Found missing Python module: {} (PID: {})", module_name, pid),
-            "normal"
+            &format!("Found missing Python module: {} ({})", module_name, detection.origin),
+            Urgency::Normal
         ).await;
         
-        if self.config.auto_install {
-            self.auto_install_module(&module_name, &cache_key).await?;
+        match policy {
+            Some(ModulePolicy::Notify) => {
+                self.log_event(detection.event(EventKind::Skipped).with_detail("notify-only policy")).await;
+            }
+            Some(ModulePolicy::Install) => self.auto_install_module(&detection, &cache_key).await?,
+            _ if config.auto_install => self.auto_install_module(&detection, &cache_key).await?,
+            _ => self.log_event(detection.event(EventKind::Skipped).with_detail("auto-install is off")).await,
         }
 
         Ok(())
     }
 
-    async fn auto_install_module(&self, module_name: &str, cache_key: &str) -> Result<()> {
-        println!("{}", blue(format!("📦 Auto-installing module: {}", module_name)));
+    /// Offer Install/Deny on a desktop notification; the answer goes through the control state,
+    /// like `snakepit daemon approve`. Without notification actions the user is told the command.
+    async fn ask_approval(&self, detection: &Detection) {
+        let control = self.control_state();
+        let module = detection.module.clone();
+        let body = format!("{} is missing ({}). Install it?", module, detection.origin);
+        let notifier = self.notifier().await;
+        let hint = format!("{}\nRun: snakepit daemon approve {}", body, module);
+        // Only libnotify has buttons
+        if notifier != Notifier::NotifySend {
+            notifier.send("Approve Install", &hint, Urgency::Normal);
+            return;
+        }
+        tokio::spawn(async move {
+            let answer = Command::new("notify-send")
+                .args(["-u", "normal", "-i", "dialog-question", "--action=approve=Install", "--action=deny=Deny"])
+                .arg("🐍 Snakepit: Approve Install")
+                .arg(&body)
+                .output()
+                .await;
+            let request = match answer {
+                Ok(output) if output.status.success() => match String::from_utf8_lossy(&output.stdout).trim() {
+                    "approve" => ControlRequest::Approve { module },
+                    "deny" => ControlRequest::Deny { module },
+                    // Dismissed; it stays queued
+                    _ => return,
+                },
+                _ => {
+                    notifier.send("Approve Install", &hint, Urgency::Normal);
+                    return;
+                }
+            };
+            control.answer(request).await;
+        });
+    }
+
+    /// Install what was approved since the last pass
+    async fn install_approved(&self) {
+        for detection in self.approvals.take_approved() {
+            if let Err(e) = self.auto_install_module(&detection, &detection.cache_key()).await {
+                logger::error(format!("Error installing approved module {}: {}", detection.module, e));
+            }
+        }
+    }
+
+    async fn auto_install_module(&self, detection: &Detection, cache_key: &str) -> Result<()> {
+        let module_name = detection.module.as_str();
+        let project = detection.project.clone();
+        logger::info(blue(format!("📦 Auto-installing module: {}", module_name)));
         self.send_notification(
             "Installing Module",
             &format!("Attempting to install: {}", module_name),
-            "normal"
+            Urgency::Normal
         ).await;
         
         // Update error cache
@@ -371,21 +774,33 @@ impl SnakepitDaemon {
             let error = ModuleError {
                 module_name: module_name.to_string(),
                 error_message: "Missing module detected".to_string(),
-                process_id: 0, // We'll update this properly
+                process_id: detection.pid.unwrap_or(0),
                 timestamp: std::time::SystemTime::now(),
                 install_attempts: cache.get(cache_key).map(|e| e.install_attempts + 1).unwrap_or(1),
             };
             cache.insert(cache_key.to_string(), error);
         }
+        self.log_event(detection.event(EventKind::Installing)).await;
 
         // Attempt to install the module
-        match self.installer.install_package(module_name, None).await {
+        let installed = match (&detection.container, &detection.environment) {
+            (Some(container), _) => DockerClient::from_env().pip_install(container, module_name).await,
+            (None, Some(environment)) => PackageInstaller::new()
+                .with_backend(self.backend.clone())
+                .with_venv(environment.to_string_lossy().to_string())
+                .install_package(module_name, None)
+                .await,
+            (None, None) => self.installer.install_package(module_name, None).await,
+        };
+        match installed {
             Ok(_) => {
-                println!("{}", green(format!("✅ Successfully installed: {}", module_name)));
+                logger::success(format!("✅ Successfully installed: {}", module_name));
+                self.record(Severity::Info, "installer", project, format!("Installed {}", module_name)).await;
+                self.log_event(detection.event(EventKind::Installed)).await;
                 self.send_notification(
                     "Installation Successful",
                     &format!("✅ Successfully installed: {}", module_name),
-                    "low"
+                    Urgency::Low
                 ).await;
                 
                 // Remove from error cache on success
@@ -395,11 +810,13 @@ impl SnakepitDaemon {
                 }
             }
             Err(e) => {
-                eprintln!("{}", red(format!("❌ Failed to install {}: {}", module_name, e)));
+                logger::error(format!("❌ Failed to install {}: {}", module_name, e));
+                self.record(Severity::Error, "installer", project, format!("Failed to install {}: {}", module_name, e)).await;
+                self.log_event(detection.event(EventKind::Failed).with_detail(e.to_string())).await;
                 self.send_notification(
                     "Installation Failed",
                     &format!("❌ Failed to install {}: {}", module_name, e),
-                    "critical"
+                    Urgency::Critical
                 ).await;
             }
         }
@@ -408,17 +825,118 @@ impl SnakepitDaemon {
     }
 
     pub async fn simulate_missing_module(&self, module_name: &str) -> Result<()> {
-        println!("{}", cyan(format!("🧪 Simulating missing module: {}", module_name)));
-        self.handle_missing_module(module_name.to_string(), Pid::from(0)).await
+        logger::info(cyan(format!("🧪 Simulating missing module: {}", module_name)));
+        self.handle_missing_module(Detection::new(module_name.to_string(), "simulation".to_string())).await
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DaemonStatus {
     pub running: bool,
     pub daemon_id: String,
     pub error_count: usize,
     pub config: DaemonConfig,
+    pub pid: Option<u32>,
+    /// Unix seconds
+    pub started_at: Option<u64>,
+}
+
+/// What the control socket can see and change of a running daemon
+#[derive(Debug, Clone)]
+struct ControlState {
+    config: Arc<RwLock<DaemonConfig>>,
+    error_cache: Arc<RwLock<HashMap<String, ModuleError>>>,
+    running: Arc<RwLock<bool>>,
+    shutdown: Arc<Notify>,
+    daemon_id: String,
+    started_at: u64,
+    journal: DaemonLog,
+    history: EventJournal,
+    approvals: Arc<ApprovalQueue>,
+    log_events: tokio::sync::broadcast::Sender<LogEntry>,
+}
+
+impl ControlState {
+    async fn record(&self, severity: Severity, message: impl Into<String>) {
+        let entry = LogEntry::new(severity, "daemon", None, message);
+        let _ = self.journal.append(&entry).await;
+        let _ = self.log_events.send(entry);
+    }
+
+    async fn status(&self) -> DaemonStatus {
+        DaemonStatus {
+            running: *self.running.read().await,
+            daemon_id: self.daemon_id.clone(),
+            error_count: self.error_cache.read().await.len(),
+            config: self.config.read().await.clone(),
+            pid: Some(std::process::id()),
+            started_at: Some(self.started_at),
+        }
+    }
+
+    async fn answer(&self, request: ControlRequest) -> ControlResponse {
+        match request {
+            ControlRequest::Status => ControlResponse::Status { status: self.status().await },
+            ControlRequest::Stop => {
+                *self.running.write().await = false;
+                // Stored as a permit if the loop isn't sleeping right now
+                self.shutdown.notify_one();
+                ControlResponse::Stopping
+            }
+            ControlRequest::ReloadConfig => match DaemonManager::new().load_daemon_config().await {
+                Ok(config) => {
+                    let hooks_changed = self.config.read().await.import_hooks != config.import_hooks;
+                    *self.config.write().await = config.clone();
+                    self.record(Severity::Info, "Configuration reloaded").await;
+                    if let (true, Ok(socket)) = (hooks_changed, import_hook::socket_path()) {
+                        let changed = sync_import_hooks(config.import_hooks, &socket);
+                        let action = if config.import_hooks { "installed in" } else { "removed from" };
+                        self.record(Severity::Info, format!("Import hook {} {} environments", action, changed)).await;
+                    }
+                    ControlResponse::Reloaded { config }
+                }
+                Err(e) => {
+                    let message = format!("Could not reload the configuration: {}", e);
+                    self.record(Severity::Error, message.clone()).await;
+                    ControlResponse::Failed { message }
+                }
+            },
+            ControlRequest::Errors { module } => {
+                let mut errors: Vec<ModuleError> = self.error_cache.read().await.values()
+                    .filter(|error| module.as_ref().is_none_or(|module| &error.module_name == module))
+                    .cloned()
+                    .collect();
+                errors.sort_by_key(|error| error.timestamp);
+                ControlResponse::Errors { errors }
+            }
+            ControlRequest::Pending => ControlResponse::Pending { pending: self.approvals.pending() },
+            ControlRequest::Approve { module } => {
+                if self.approvals.approve(&module).is_none() {
+                    return ControlResponse::Failed { message: format!("{} isn't waiting for approval", module) };
+                }
+                // Learned: next time it's installed without asking
+                let mut config = self.config.write().await;
+                if !config.whitelist_modules.contains(&module) {
+                    config.whitelist_modules.push(module.clone());
+                    if let Err(e) = DaemonManager::new().save_daemon_config(&config).await {
+                        self.record(Severity::Warn, format!("Could not add {} to the whitelist: {}", module, e)).await;
+                    }
+                }
+                drop(config);
+                self.record(Severity::Info, format!("Approved installing {}", module)).await;
+                ControlResponse::Approved { module }
+            }
+            ControlRequest::Deny { module } => match self.approvals.deny(&module) {
+                Some(detection) => {
+                    let event = detection.event(EventKind::Skipped).with_detail("denied");
+                    let _ = self.history.append(&event).await;
+                    self.record(Severity::Info, format!("Denied installing {}", module)).await;
+                    ControlResponse::Denied { module }
+                }
+                None => ControlResponse::Failed { message: format!("{} isn't waiting for approval", module) },
+            },
+        }
+    }
 }
 
 pub struct DaemonManager {
@@ -457,38 +975,263 @@ impl DaemonManager {
         Ok(())
     }
 
+    /// The PID file the daemon writes
+    pub fn pid_file(&self, config: &DaemonConfig) -> PathBuf {
+        config.pid_file.clone().unwrap_or_else(|| self.config_path.with_file_name("snakepit.pid"))
+    }
+
+    /// Where a daemon running in the background writes its output
+    pub fn output_log(&self, config: &DaemonConfig) -> PathBuf {
+        config.log_file.clone().unwrap_or_else(|| {
+            dirs::data_dir().unwrap_or_else(|| PathBuf::from(".snakepit")).join("snakepit").join("daemon.out")
+        })
+    }
+
+    /// Run `snakepit daemon start <args>` in the background: its own process group, so the
+    /// terminal's Ctrl-C doesn't reach it, with stdio going to the output log. Returns the
+    /// daemon's PID and that log once it answers on the control socket.
+    pub async fn start_detached(&self, args: &[String]) -> Result<(u32, PathBuf)> {
+        let config = self.load_daemon_config().await?;
+        if let Some(pid) = live_daemon_pid(&self.pid_file(&config)) {
+            return Err(anyhow::anyhow!("Daemon already running (pid {})", pid));
+        }
+
+        let log = self.output_log(&config);
+        if let Some(parent) = log.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let output = std::fs::OpenOptions::new().create(true).append(true).open(&log)?;
+
+        let mut command = std::process::Command::new(std::env::current_exe()?);
+        command.args(["daemon", "start"]).args(args)
+            .stdin(std::process::Stdio::null())
+            .stdout(output.try_clone()?)
+            .stderr(output);
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command.spawn()?;
+
+        let deadline = SystemTime::now() + START_TIMEOUT;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Err(anyhow::anyhow!("Daemon exited during startup ({}); see {}", status, log.display()));
+            }
+            if daemon_control::request(&ControlRequest::Status).await.is_ok() {
+                return Ok((child.id(), log));
+            }
+            if SystemTime::now() > deadline {
+                return Err(anyhow::anyhow!("Daemon didn't answer within {}s; see {}", START_TIMEOUT.as_secs(), log.display()));
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
     pub async fn start_daemon(&self, snakepit_config: &SnakepitConfig) -> Result<()> {
         let daemon_config = self.load_daemon_config().await?;
         let daemon = SnakepitDaemon::new(daemon_config, snakepit_config);
         daemon.start().await
     }
 
+    /// Ask the running daemon to stop and wait until it has. One that doesn't answer
+    /// on the control socket gets a SIGTERM instead.
     pub async fn stop_daemon(&self) -> Result<()> {
-        // In a real implementation, you'd read the PID from the PID file
-        // and send a SIGTERM signal to stop the daemon
-        println!("{}", yellow("Stopping daemon..."));
+        logger::warn("Stopping daemon...");
+        let pid_file = self.pid_file(&self.load_daemon_config().await?);
+        if let Err(e) = daemon_control::request(&ControlRequest::Stop).await {
+            let pid = live_daemon_pid(&pid_file).ok_or(e)?;
+            terminate(pid)?;
+        }
+
+        // The daemon removes its PID file once the monitoring loop has wound down
+        let deadline = SystemTime::now() + STOP_TIMEOUT;
+        while daemon_pid(&pid_file).is_some() {
+            if SystemTime::now() > deadline {
+                return Err(anyhow::anyhow!("Daemon didn't stop within {}s", STOP_TIMEOUT.as_secs()));
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
         Ok(())
     }
 
+    /// The running daemon's status; when none answers, the configuration it would start with
     pub async fn daemon_status(&self) -> Result<DaemonStatus> {
-        // Check if daemon is running by looking for PID file
-        let pid_file = if let Some(config_dir) = dirs::config_dir() {
-            config_dir.join("snakepit").join("snakepit.pid")
-        } else {
-            PathBuf::from(".snakepit").join("snakepit.pid")
+        match daemon_control::request(&ControlRequest::Status).await {
+            Ok(ControlResponse::Status { status }) => Ok(status),
+            Ok(other) => Err(anyhow::anyhow!("Unexpected answer from the daemon: {:?}", other)),
+            Err(_) => {
+                // Alive but not listening still counts as running
+                let config = self.load_daemon_config().await?;
+                let pid = live_daemon_pid(&self.pid_file(&config));
+                Ok(DaemonStatus {
+                    running: pid.is_some(),
+                    daemon_id: "unknown".to_string(),
+                    error_count: 0,
+                    config,
+                    pid,
+                    started_at: None,
+                })
+            }
+        }
+    }
+
+    /// Make the running daemon re-read daemon.toml; returns the configuration it now uses
+    pub async fn reload_config(&self) -> Result<DaemonConfig> {
+        match daemon_control::request(&ControlRequest::ReloadConfig).await? {
+            ControlResponse::Reloaded { config } => Ok(config),
+            other => Err(anyhow::anyhow!("Unexpected answer from the daemon: {:?}", other)),
+        }
+    }
+
+    /// Modules the running daemon is waiting for approval to install, oldest first
+    pub async fn pending_approvals(&self) -> Result<Vec<PendingInstall>> {
+        match daemon_control::request(&ControlRequest::Pending).await? {
+            ControlResponse::Pending { pending } => Ok(pending),
+            other => Err(anyhow::anyhow!("Unexpected answer from the daemon: {:?}", other)),
+        }
+    }
+
+    /// Approve (or deny) installing a queued module
+    pub async fn resolve_approval(&self, module: &str, approve: bool) -> Result<()> {
+        let module = module.to_string();
+        let request = if approve { ControlRequest::Approve { module } } else { ControlRequest::Deny { module } };
+        match daemon_control::request(&request).await? {
+            ControlResponse::Approved { .. } | ControlResponse::Denied { .. } => Ok(()),
+            other => Err(anyhow::anyhow!("Unexpected answer from the daemon: {:?}", other)),
+        }
+    }
+
+    /// The module errors the running daemon has cached, oldest first
+    pub async fn module_errors(&self, module: Option<&str>) -> Result<Vec<ModuleError>> {
+        let request = ControlRequest::Errors { module: module.map(str::to_string) };
+        match daemon_control::request(&request).await? {
+            ControlResponse::Errors { errors } => Ok(errors),
+            other => Err(anyhow::anyhow!("Unexpected answer from the daemon: {:?}", other)),
+        }
+    }
+}
+
+/// The nearest ancestor of `dir` with a manifest, else `dir` itself
+fn project_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|dir| dir.join("pyproject.toml").exists() || dir.join("requirements.txt").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Watch every registered project and its environment
+fn watch_known_projects(watcher: &mut CrashLogWatcher) {
+    let Ok(registry) = crate::registry::ProjectRegistry::load() else { return };
+    for project in &registry.projects {
+        for dir in std::iter::once(&project.root).chain(project.environment.as_ref()) {
+            // Projects that have moved or been deleted are skipped
+            let _ = watcher.watch(dir);
+        }
+    }
+}
+
+/// Install the import hook into every registered environment, or remove it from them;
+/// returns how many environments changed
+fn sync_import_hooks(enabled: bool, socket: &Path) -> usize {
+    let Ok(registry) = crate::registry::ProjectRegistry::load() else { return 0 };
+    let mut changed = 0;
+    for environment in registry.projects.iter().filter_map(|project| project.environment.as_ref()) {
+        let installer = PackageInstaller::new().with_venv(environment.to_string_lossy().to_string());
+        let Ok(site) = installer.site_packages() else { continue };
+        if !site.is_dir() {
+            continue;
+        }
+        let hook = ImportHook::new(site);
+        let result = match (enabled, hook.is_installed()) {
+            (true, false) => hook.install(socket),
+            (false, true) => hook.remove(),
+            _ => continue,
         };
+        match result {
+            Ok(()) => changed += 1,
+            Err(e) => logger::note(format!("Could not update the import hook in {}: {}", environment.display(), e)),
+        }
+    }
+    changed
+}
 
-        let running = pid_file.exists();
-        let daemon_id = "unknown".to_string();
-        let error_count = 0;
-        let config = self.load_daemon_config().await?;
+/// A missing module the daemon has been told about
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Detection {
+    pub module: String,
+    /// Where it was seen: a process, a crash log or `simulation`
+    pub origin: String,
+    pub pid: Option<u32>,
+    pub project: Option<PathBuf>,
+    /// The virtualenv to install into, when it's known; otherwise the daemon's own installer is used
+    pub environment: Option<PathBuf>,
+    /// Set when the module is missing inside a Docker container; it's installed there
+    #[serde(default)]
+    pub container: Option<ContainerTarget>,
+}
 
-        Ok(DaemonStatus {
-            running,
-            daemon_id,
-            error_count,
-            config,
-        })
+impl Detection {
+    pub fn new(module: String, origin: String) -> Self {
+        Self { module, origin, pid: None, project: None, environment: None, container: None }
+    }
+
+    /// Install attempts are counted per module and origin
+    fn cache_key(&self) -> String {
+        format!("{}:{}", self.module, self.origin)
+    }
+
+    fn event(&self, kind: EventKind) -> DaemonEvent {
+        DaemonEvent::new(kind, &self.module, &self.origin)
+            .with_pid(self.pid)
+            .with_project(self.project.clone())
+            .with_environment(self.environment.clone())
+    }
+}
+
+/// What woke the monitoring loop before its next pass
+enum Wakeup {
+    Tick,
+    CrashLog(PathBuf),
+    Import(ImportReport),
+    Approved,
+}
+
+/// The next crash log that was written to; never, without a watcher
+async fn next_crash_log(crash_logs: &mut Option<CrashLogWatcher>) -> Option<PathBuf> {
+    match crash_logs {
+        Some(watcher) => watcher.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The PID in `pid_file`, if that process is still a running snakepit
+fn daemon_pid(pid_file: &Path) -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(pid_file).ok()?.trim().parse().ok()?;
+    let mut system = System::new();
+    // A recycled PID belongs to some other program
+    let alive = system.refresh_process(Pid::from_u32(pid))
+        && system.process(Pid::from_u32(pid)).is_some_and(|process| process.name().contains("snakepit"));
+    alive.then_some(pid)
+}
+
+/// Like [`daemon_pid`], but a PID file left behind by a daemon that died is removed
+fn live_daemon_pid(pid_file: &Path) -> Option<u32> {
+    let pid = daemon_pid(pid_file);
+    if pid.is_none() && pid_file.exists() {
+        logger::note(format!("Removing stale PID file {}", pid_file.display()));
+        let _ = std::fs::remove_file(pid_file);
+    }
+    pid
+}
+
+fn terminate(pid: u32) -> Result<()> {
+    let mut system = System::new();
+    system.refresh_process(Pid::from_u32(pid));
+    match system.process(Pid::from_u32(pid)).and_then(|process| process.kill_with(sysinfo::Signal::Term)) {
+        Some(true) => Ok(()),
+        _ => Err(anyhow::anyhow!("Could not send SIGTERM to the daemon (pid {})", pid)),
     }
 }
 
@@ -504,6 +1247,62 @@ mod tests {
         assert_eq!(config.check_interval, Duration::from_secs(5));
     }
 
+    #[tokio::test]
+    async fn test_control_requests() {
+        let error = |module: &str| ModuleError {
+            module_name: module.to_string(),
+            error_message: "Missing module detected".to_string(),
+            process_id: 0,
+            timestamp: SystemTime::now(),
+            install_attempts: 1,
+        };
+        let control = ControlState {
+            config: Arc::new(RwLock::new(DaemonConfig::default())),
+            error_cache: Arc::new(RwLock::new(HashMap::from([
+                ("numpy:1".to_string(), error("numpy")),
+                ("yaml:2".to_string(), error("yaml")),
+            ]))),
+            running: Arc::new(RwLock::new(true)),
+            shutdown: Arc::new(Notify::new()),
+            daemon_id: "test".to_string(),
+            started_at: 0,
+            journal: DaemonLog::new(std::env::temp_dir().join(format!("snakepit-control-{}.jsonl", std::process::id()))),
+            history: EventJournal::new(std::env::temp_dir().join(format!("snakepit-control-history-{}.jsonl", std::process::id()))),
+            approvals: Arc::new(ApprovalQueue::new()),
+            log_events: tokio::sync::broadcast::channel(16).0,
+        };
+
+        match control.answer(ControlRequest::Errors { module: Some("yaml".to_string()) }).await {
+            ControlResponse::Errors { errors } => assert_eq!(errors.len(), 1),
+            other => panic!("unexpected {:?}", other),
+        }
+        control.approvals.enqueue(Detection::new("yaml".to_string(), "process 2".to_string()));
+        match control.answer(ControlRequest::Pending).await {
+            ControlResponse::Pending { pending } => assert_eq!(pending[0].detection.module, "yaml"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(control.answer(ControlRequest::Deny { module: "yaml".to_string() }).await, ControlResponse::Denied { .. }));
+        assert!(matches!(control.answer(ControlRequest::Deny { module: "yaml".to_string() }).await, ControlResponse::Failed { .. }));
+        assert_eq!(control.history.read(None, Some("yaml")).unwrap()[0].detail.as_deref(), Some("denied"));
+        let _ = std::fs::remove_file(control.history.path());
+        assert!(matches!(control.answer(ControlRequest::Stop).await, ControlResponse::Stopping));
+        // The stop left a permit, so a loop about to sleep wakes at once
+        tokio::time::timeout(Duration::from_secs(1), control.shutdown.notified()).await.unwrap();
+        let status = control.status().await;
+        assert!(!status.running);
+        assert_eq!(status.error_count, 2);
+        assert_eq!(status.pid, Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_stale_pid_file_is_removed() {
+        let pid_file = std::env::temp_dir().join(format!("snakepit-stale-{}.pid", std::process::id()));
+        // PIDs wrap well before this on every platform
+        std::fs::write(&pid_file, u32::MAX.to_string()).unwrap();
+        assert_eq!(live_daemon_pid(&pid_file), None);
+        assert!(!pid_file.exists());
+    }
+
     #[tokio::test]
     async fn test_daemon_manager() {
         let manager = DaemonManager::new();
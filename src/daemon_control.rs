@@ -0,0 +1,153 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use snakegg::native::dirs;
+use crate::approval::PendingInstall;
+use crate::daemon::{DaemonConfig, DaemonStatus, ModuleError};
+
+/// A request to the running daemon, one JSON object per line: `{"command": "status"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlRequest {
+    Status,
+    Stop,
+    /// Re-read daemon.toml without restarting
+    ReloadConfig,
+    /// The cached module errors, optionally for one module
+    Errors { module: Option<String> },
+    /// Modules queued for approval
+    Pending,
+    Approve { module: String },
+    Deny { module: String },
+}
+
+/// The daemon's answer to a [`ControlRequest`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum ControlResponse {
+    Status { status: DaemonStatus },
+    Stopping,
+    Reloaded { config: DaemonConfig },
+    Errors { errors: Vec<ModuleError> },
+    Pending { pending: Vec<PendingInstall> },
+    Approved { module: String },
+    Denied { module: String },
+    Failed { message: String },
+}
+
+/// IPC socket the daemon takes control requests on
+pub fn socket_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data_dir.join("snakepit").join("control.sock"))
+}
+
+/// Answer requests on `path` with `answer`; each connection may send several, one per line
+#[cfg(unix)]
+pub fn serve<F, Fut>(path: &Path, answer: F) -> Result<()>
+where
+    F: Fn(ControlRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = ControlResponse> + Send,
+{
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // A socket left behind by a daemon that died would make bind fail
+    let _ = std::fs::remove_file(path);
+    let listener = tokio::net::UnixListener::bind(path)?;
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let answer = answer.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let response = match serde_json::from_str::<ControlRequest>(&line) {
+                        Ok(request) => answer(request).await,
+                        Err(e) => ControlResponse::Failed { message: format!("Invalid request: {}", e) },
+                    };
+                    let Ok(mut line) = serde_json::to_string(&response) else { continue };
+                    line.push('\n');
+                    if writer.write_all(line.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve<F, Fut>(_path: &Path, _answer: F) -> Result<()>
+where
+    F: Fn(ControlRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = ControlResponse> + Send,
+{
+    Ok(())
+}
+
+/// Send one request to the daemon listening on `path` and wait for its answer
+#[cfg(unix)]
+pub async fn send(path: &Path, request: &ControlRequest) -> Result<ControlResponse> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let mut stream = tokio::net::UnixStream::connect(path).await
+        .map_err(|e| anyhow::anyhow!("Daemon isn't running or not listening on {} ({})", path.display(), e))?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).await?;
+    if response.is_empty() {
+        return Err(anyhow::anyhow!("Daemon closed the connection without answering"));
+    }
+    match serde_json::from_str(&response)? {
+        ControlResponse::Failed { message } => Err(anyhow::anyhow!("Daemon: {}", message)),
+        response => Ok(response),
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn send(_path: &Path, _request: &ControlRequest) -> Result<ControlResponse> {
+    Err(anyhow::anyhow!("Controlling the daemon needs Unix domain sockets"))
+}
+
+/// Send one request to the running daemon
+pub async fn request(request: &ControlRequest) -> Result<ControlResponse> {
+    send(&socket_path()?, request).await
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_round_trip_over_socket() {
+        assert_eq!(serde_json::to_string(&ControlRequest::ReloadConfig).unwrap(), r#"{"command":"reload-config"}"#);
+        assert_eq!(
+            serde_json::from_str::<ControlRequest>(r#"{"command":"errors","module":"numpy"}"#).unwrap(),
+            ControlRequest::Errors { module: Some("numpy".to_string()) },
+        );
+
+        let path = std::env::temp_dir().join(format!("snakepit-control-{}.sock", std::process::id()));
+        serve(&path, |request| async move {
+            match request {
+                ControlRequest::Stop => ControlResponse::Stopping,
+                other => ControlResponse::Failed { message: format!("unsupported: {:?}", other) },
+            }
+        }).unwrap();
+
+        assert!(matches!(send(&path, &ControlRequest::Stop).await.unwrap(), ControlResponse::Stopping));
+        let error = send(&path, &ControlRequest::Status).await.unwrap_err();
+        assert_eq!(error.to_string(), "Daemon: unsupported: Status");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(send(&path, &ControlRequest::Stop).await.is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use snakegg::native::dirs;
+use snakegg::native::style::{red, green, yellow, blue, cyan, dim};
+use tokio::io::AsyncWriteExt;
+use crate::daemon_log::format_timestamp;
+
+/// What the auto-installer did about a missing module
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    /// A process, crash log or import hook reported the module missing
+    Detected,
+    /// The daemon decided not to install it; the detail says why
+    Skipped,
+    /// Waiting for `snakepit daemon approve`
+    Queued,
+    Installing,
+    Installed,
+    Failed,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            EventKind::Detected => "detected",
+            EventKind::Skipped => "skipped",
+            EventKind::Queued => "queued",
+            EventKind::Installing => "installing",
+            EventKind::Installed => "installed",
+            EventKind::Failed => "failed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One step of the auto-installer's work on a module
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonEvent {
+    pub timestamp: u64,
+    pub kind: EventKind,
+    pub module: String,
+    /// Where the module was seen missing: a process, a crash log, or `simulation`
+    pub origin: String,
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub project: Option<PathBuf>,
+    /// Environment the install went into, when it wasn't the daemon's default
+    #[serde(default)]
+    pub environment: Option<PathBuf>,
+    /// Why it was skipped, or why the install failed
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+impl DaemonEvent {
+    pub fn new(kind: EventKind, module: &str, origin: &str) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            kind,
+            module: module.to_string(),
+            origin: origin.to_string(),
+            pid: None,
+            project: None,
+            environment: None,
+            detail: None,
+        }
+    }
+
+    pub fn with_pid(mut self, pid: Option<u32>) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    pub fn with_project(mut self, project: Option<PathBuf>) -> Self {
+        self.project = project;
+        self
+    }
+
+    pub fn with_environment(mut self, environment: Option<PathBuf>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Colorized single-line form for terminals
+    pub fn render(&self) -> String {
+        let kind = format!("{:<10}", self.kind.to_string());
+        let kind = match self.kind {
+            EventKind::Detected => yellow(kind).to_string(),
+            EventKind::Skipped => dim(kind).to_string(),
+            EventKind::Queued => cyan(kind).to_string(),
+            EventKind::Installing => blue(kind).to_string(),
+            EventKind::Installed => green(kind).to_string(),
+            EventKind::Failed => red(kind).to_string(),
+        };
+        let mut line = format!("{} {} {} {}", dim(format_timestamp(self.timestamp)), kind, self.module, dim(format!("from {}", self.origin)));
+        if let Some(environment) = &self.environment {
+            line.push_str(&dim(format!(" into {}", environment.display())).to_string());
+        }
+        if let Some(detail) = &self.detail {
+            line.push_str(&format!(": {}", detail));
+        }
+        line
+    }
+}
+
+/// Append-only JSON-lines record of every detection, decision and install attempt,
+/// kept apart from the daemon log so it can be audited without the chatter
+#[derive(Debug, Clone)]
+pub struct EventJournal {
+    path: PathBuf,
+}
+
+impl EventJournal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn open_default() -> Result<Self> {
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        Ok(Self::new(data_dir.join("snakepit").join("logs").join("history.jsonl")))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn append(&self, event: &DaemonEvent) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Events at or after `since` (unix seconds), optionally for one module, oldest first
+    pub fn read(&self, since: Option<u64>, module: Option<&str>) -> Result<Vec<DaemonEvent>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(content.lines()
+            .filter_map(|line| serde_json::from_str::<DaemonEvent>(line).ok())
+            .filter(|event| since.is_none_or(|since| event.timestamp >= since))
+            .filter(|event| module.is_none_or(|module| event.module.eq_ignore_ascii_case(module)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_journal_round_trips_and_filters() {
+        let dir = std::env::temp_dir().join(format!("snakepit-daemon-history-{}", std::process::id()));
+        let journal = EventJournal::new(dir.join("history.jsonl"));
+
+        let mut detected = DaemonEvent::new(EventKind::Detected, "yaml", "process 42").with_pid(Some(42));
+        detected.timestamp = 1_000;
+        let mut skipped = DaemonEvent::new(EventKind::Skipped, "os", "simulation").with_detail("blacklisted");
+        skipped.timestamp = 2_000;
+        journal.append(&detected).await.unwrap();
+        journal.append(&skipped).await.unwrap();
+
+        assert_eq!(journal.read(None, None).unwrap(), vec![detected.clone(), skipped.clone()]);
+        assert_eq!(journal.read(Some(1_500), None).unwrap(), vec![skipped]);
+        assert_eq!(journal.read(None, Some("YAML")).unwrap(), vec![detected]);
+        assert!(std::fs::read_to_string(journal.path()).unwrap().contains(r#""kind":"skipped""#));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use snakegg::native::dirs;
+use snakegg::native::style::{red, yellow, blue, cyan, dim};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "debug" => Ok(Severity::Debug),
+            "info" => Ok(Severity::Info),
+            "warn" | "warning" => Ok(Severity::Warn),
+            "error" => Ok(Severity::Error),
+            other => Err(anyhow::anyhow!("Unknown severity '{}' (expected debug, info, warn or error)", other)),
+        }
+    }
+}
+
+/// One line of the daemon's structured log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: u64,
+    pub severity: Severity,
+    /// Daemon component that emitted the entry (`monitor`, `installer`, ...)
+    pub module: String,
+    /// Root of the project the entry concerns, when there is one
+    #[serde(default)]
+    pub project: Option<PathBuf>,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn new(severity: Severity, module: &str, project: Option<PathBuf>, message: impl Into<String>) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            severity,
+            module: module.to_string(),
+            project,
+            message: message.into(),
+        }
+    }
+
+    /// Colorized single-line form for terminals
+    pub fn render(&self) -> String {
+        let level = format!("{:<5}", format!("{:?}", self.severity).to_uppercase());
+        let level = match self.severity {
+            Severity::Debug => dim(level).to_string(),
+            Severity::Info => blue(level).to_string(),
+            Severity::Warn => yellow(level).to_string(),
+            Severity::Error => red(level).to_string(),
+        };
+        let project = self.project.as_ref()
+            .map(|p| dim(format!("  ({})", p.display())).to_string())
+            .unwrap_or_default();
+        format!("{} {} {} {}{}", dim(format_timestamp(self.timestamp)), level, cyan(format!("{:<10}", self.module)), self.message, project)
+    }
+}
+
+/// Which entries a viewer or export wants
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    pub min_severity: Option<Severity>,
+    pub module: Option<String>,
+    pub project: Option<PathBuf>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.min_severity.is_none_or(|min| entry.severity >= min)
+            && self.module.as_ref().is_none_or(|m| entry.module.eq_ignore_ascii_case(m))
+            && self.project.as_ref().is_none_or(|p| entry.project.as_ref().is_some_and(|ep| ep.starts_with(p)))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+    }
+}
+
+/// Append-only JSON-lines journal shared by every project the daemon watches
+#[derive(Debug, Clone)]
+pub struct DaemonLog {
+    path: PathBuf,
+}
+
+impl DaemonLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn open_default() -> Result<Self> {
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        Ok(Self::new(data_dir.join("snakepit").join("logs").join("daemon.jsonl")))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn append(&self, entry: &LogEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Entries matching `filter`, oldest first; lines that don't parse are skipped
+    pub fn read(&self, filter: &LogFilter) -> Result<Vec<LogEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(content.lines()
+            .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+            .filter(|entry| filter.matches(entry))
+            .collect())
+    }
+}
+
+/// IPC socket live entries are streamed over
+pub fn socket_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data_dir.join("snakepit").join("daemon.sock"))
+}
+
+/// Stream every entry sent on `entries` to whoever connects to `path`, one JSON object per line
+#[cfg(unix)]
+pub fn serve(path: &Path, entries: broadcast::Sender<LogEntry>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // A socket left behind by a daemon that died would make bind fail
+    let _ = std::fs::remove_file(path);
+    let listener = tokio::net::UnixListener::bind(path)?;
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut receiver = entries.subscribe();
+            tokio::spawn(async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(entry) => {
+                            let Ok(mut line) = serde_json::to_string(&entry) else { continue };
+                            line.push('\n');
+                            if stream.write_all(line.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                        // A slow viewer misses entries rather than stalling the daemon
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve(_path: &Path, _entries: broadcast::Sender<LogEntry>) -> Result<()> {
+    Ok(())
+}
+
+/// Receive live entries from a running daemon until it goes away
+#[cfg(unix)]
+pub async fn follow(filter: &LogFilter, mut on_entry: impl FnMut(&LogEntry)) -> Result<()> {
+    let path = socket_path()?;
+    let stream = tokio::net::UnixStream::connect(&path).await
+        .map_err(|e| anyhow::anyhow!("Daemon isn't running or not listening on {} ({})", path.display(), e))?;
+
+    let mut lines = BufReader::new(stream).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Ok(entry) = serde_json::from_str::<LogEntry>(&line) {
+            if filter.matches(&entry) {
+                on_entry(&entry);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn follow(_filter: &LogFilter, _on_entry: impl FnMut(&LogEntry)) -> Result<()> {
+    Err(anyhow::anyhow!("Following daemon logs needs Unix domain sockets"))
+}
+
+/// Parse `--since`/`--until`: unix seconds, a relative age (`90s`, `15m`, `2h`, `7d`),
+/// or a UTC date `YYYY-MM-DD` with optional `HH:MM[:SS]`
+pub fn parse_time(value: &str, now: u64) -> Result<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(secs);
+    }
+
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<u64>() {
+            let scale = match unit {
+                's' => 1,
+                'm' => 60,
+                'h' => 3600,
+                'd' => 86400,
+                _ => return Err(anyhow::anyhow!("Unknown time unit '{}' in {}", unit, value)),
+            };
+            return Ok(now.saturating_sub(amount * scale));
+        }
+    }
+
+    let (date, time) = value.split_once(['T', ' ']).unwrap_or((value, "00:00:00"));
+    let date: Vec<i64> = date.split('-').map(|p| p.parse()).collect::<Result<_, _>>()
+        .map_err(|_| anyhow::anyhow!("Unrecognized time '{}'", value))?;
+    let time: Vec<u64> = time.trim_end_matches('Z').split(':').map(|p| p.parse()).collect::<Result<_, _>>()
+        .map_err(|_| anyhow::anyhow!("Unrecognized time '{}'", value))?;
+    if date.len() != 3 || !(2..=3).contains(&time.len()) {
+        return Err(anyhow::anyhow!("Unrecognized time '{}'", value));
+    }
+
+    let days = days_from_civil(date[0], date[1], date[2]);
+    if days < 0 {
+        return Err(anyhow::anyhow!("{} is before 1970", value));
+    }
+    Ok(days as u64 * 86400 + time[0] * 3600 + time[1] * 60 + time.get(2).copied().unwrap_or(0))
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    // Inverse of days_from_civil (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_parsing_round_trips() {
+        let now = 1_760_000_000;
+        assert_eq!(parse_time("1700000000", now).unwrap(), 1_700_000_000);
+        assert_eq!(parse_time("2h", now).unwrap(), now - 7200);
+        assert_eq!(parse_time("2024-02-29", now).unwrap(), 1_709_164_800);
+        assert_eq!(parse_time("2024-02-29 13:05", now).unwrap(), 1_709_164_800 + 13 * 3600 + 5 * 60);
+        assert_eq!(format_timestamp(1_709_164_800 + 59), "2024-02-29 00:00:59");
+        assert!(parse_time("yesterday", now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_filter_and_journal() {
+        let dir = std::env::temp_dir().join(format!("snakepit-daemon-log-{}", std::process::id()));
+        let log = DaemonLog::new(dir.join("daemon.jsonl"));
+        let project = PathBuf::from("/work/api");
+        log.append(&LogEntry::new(Severity::Info, "monitor", None, "started")).await.unwrap();
+        log.append(&LogEntry::new(Severity::Warn, "monitor", Some(project.join("svc")), "missing yaml")).await.unwrap();
+        log.append(&LogEntry::new(Severity::Error, "installer", Some(PathBuf::from("/work/web")), "install failed")).await.unwrap();
+
+        assert_eq!(log.read(&LogFilter::default()).unwrap().len(), 3);
+        let warnings = LogFilter { min_severity: Some(Severity::Warn), ..Default::default() };
+        assert_eq!(log.read(&warnings).unwrap().len(), 2);
+        let api = LogFilter { project: Some(project), ..Default::default() };
+        assert_eq!(log.read(&api).unwrap()[0].message, "missing yaml");
+        let installer = LogFilter { module: Some("Installer".to_string()), ..Default::default() };
+        assert_eq!(log.read(&installer).unwrap()[0].severity, Severity::Error);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use anyhow::Result;
+use crate::markers::{EnvironmentMarker, TargetEnvironment};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dependency {
@@ -9,12 +10,38 @@ pub struct Dependency {
     pub version_constraint: Option<String>,
     pub is_dev: bool,
     pub source: Option<String>,
+    #[serde(default)]
+    pub extras: Vec<String>,
+    /// Expected artifact digests, e.g. `sha256:<hex>` from `--hash=` options
+    #[serde(default)]
+    pub hashes: Vec<String>,
+}
+
+/// Split a requirement name like `requests[socks, security]` into the bare
+/// name and its requested extras.
+pub fn split_extras(spec: &str) -> (String, Vec<String>) {
+    let spec = spec.trim();
+    match (spec.find('['), spec.rfind(']')) {
+        (Some(open), Some(close)) if close > open => {
+            let name = spec[..open].trim().to_string();
+            let extras = spec[open + 1..close]
+                .split(',')
+                .map(|e| e.trim().to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect();
+            (name, extras)
+        }
+        _ => (spec.to_string(), Vec::new()),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectDependencies {
     pub dependencies: Vec<Dependency>,
     pub dev_dependencies: Vec<Dependency>,
+    /// `-e` requirements; `source` holds the local path or VCS URL to install in development mode
+    #[serde(default)]
+    pub editables: Vec<Dependency>,
     pub python_version: Option<String>,
     pub project_name: Option<String>,
 }
@@ -24,31 +51,130 @@ impl ProjectDependencies {
         Self {
             dependencies: Vec::new(),
             dev_dependencies: Vec::new(),
+            editables: Vec::new(),
             python_version: None,
             project_name: None,
         }
     }
 
+    /// Load a pip requirements file, following nested `-r` includes
     pub fn from_requirements_txt<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut deps = Self::new();
+        let mut seen = Vec::new();
+        deps.load_requirements(path.as_ref(), &mut seen)?;
+        Ok(deps)
+    }
+
+    fn load_requirements(&mut self, path: &Path, seen: &mut Vec<PathBuf>) -> Result<()> {
+        let canonical = path.canonicalize()
+            .map_err(|e| anyhow::anyhow!("Cannot read requirements file {}: {}", path.display(), e))?;
+        // Include cycles and diamonds are legal; read each file once
+        if seen.contains(&canonical) {
+            return Ok(());
+        }
+        seen.push(canonical);
+
         let content = std::fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        // Join backslash continuations, which hash-pinned files use heavily
+        let content = content.replace("\\\r\n", " ").replace("\\\n", " ");
+        self.load_requirement_lines(content.lines(), dir, seen)
+    }
+
+    /// Requirements-file lines from elsewhere, such as an environment.yml `pip:` section;
+    /// `-r` and `-e` paths are relative to `dir`
+    pub fn from_requirement_lines<'a>(lines: impl IntoIterator<Item = &'a str>, dir: &Path) -> Result<Self> {
         let mut deps = Self::new();
-        
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
+        deps.load_requirement_lines(lines, dir, &mut Vec::new())?;
+        Ok(deps)
+    }
+
+    fn load_requirement_lines<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>, dir: &Path, seen: &mut Vec<PathBuf>) -> Result<()> {
+        for line in lines {
+            let line = Self::strip_comment(line).trim();
+            if line.is_empty() {
                 continue;
             }
-            
-            if let Some(dep) = Self::parse_requirement_line(line) {
+
+            if let Some(include) = Self::option_value(line, "-r", "--requirement") {
+                self.load_requirements(&dir.join(include), seen)?;
+            } else if let Some(target) = Self::option_value(line, "-e", "--editable") {
+                self.editables.push(Self::parse_editable(target, dir)?);
+            } else if line.starts_with('-') {
+                // Index, build and constraint options don't declare packages
+                continue;
+            } else if let Some(dep) = Self::parse_requirement_line(line) {
                 if dep.is_dev {
-                    deps.dev_dependencies.push(dep);
+                    self.dev_dependencies.push(dep);
                 } else {
-                    deps.dependencies.push(dep);
+                    self.dependencies.push(dep);
                 }
             }
         }
         
-        Ok(deps)
+        Ok(())
+    }
+
+    /// pip only treats `#` as a comment at line start or after whitespace, so `#egg=` survives
+    fn strip_comment(line: &str) -> &str {
+        if line.trim_start().starts_with('#') {
+            return "";
+        }
+        match line.find(" #").or_else(|| line.find("\t#")) {
+            Some(idx) => &line[..idx],
+            None => line,
+        }
+    }
+
+    /// Value of `-r file`, `-rfile`, `--requirement file` or `--requirement=file`
+    fn option_value<'a>(line: &'a str, short: &str, long: &str) -> Option<&'a str> {
+        if let Some(rest) = line.strip_prefix(long) {
+            if rest.starts_with(' ') || rest.starts_with('=') {
+                return Some(rest.trim_start_matches('=').trim());
+            }
+            return None;
+        }
+        line.strip_prefix(short)
+            .filter(|rest| !rest.is_empty() && !rest.starts_with('-'))
+            .map(|rest| rest.trim_start_matches('=').trim())
+    }
+
+    /// `-e ./path` or `-e git+https://...#egg=name`
+    fn parse_editable(target: &str, dir: &Path) -> Result<Dependency> {
+        let egg = target.split_once("#egg=")
+            .map(|(_, egg)| egg.split('&').next().unwrap_or(egg).to_string());
+
+        let is_url = target.contains("://");
+        let source = if is_url {
+            target.to_string()
+        } else {
+            let local = dir.join(target);
+            local.canonicalize().unwrap_or(local).to_string_lossy().to_string()
+        };
+
+        let name = match egg {
+            Some(name) => name,
+            None if is_url => return Err(anyhow::anyhow!("Editable URL needs #egg=<name>: {}", target)),
+            None => {
+                let local = Path::new(&source);
+                Self::from_pyproject_toml(local.join("pyproject.toml")).ok()
+                    .and_then(|p| p.project_name)
+                    .or_else(|| local.file_name().map(|n| n.to_string_lossy().to_string()))
+                    .ok_or_else(|| anyhow::anyhow!("Cannot determine project name for editable {}", target))?
+            }
+        };
+
+        let (name, extras) = split_extras(&name);
+        Ok(Dependency {
+            name,
+            version: None,
+            version_constraint: None,
+            is_dev: false,
+            source: Some(source),
+            extras,
+            hashes: Vec::new(),
+        })
     }
 
     pub fn from_pyproject_toml<P: AsRef<Path>>(path: P) -> Result<Self> {
@@ -89,6 +215,19 @@ impl ProjectDependencies {
                 }
             }
         }
+
+        // The dev group can also be kept in snakepit's own table or as a PEP 735 group
+        let snakepit = toml.get("tool").and_then(|tool| tool.get("snakepit"));
+        let dev_group = snakepit
+            .and_then(|table| table.get("dev-dependencies").or_else(|| table.get("dev_dependencies")))
+            .and_then(|v| v.as_array());
+        let pep735_dev = toml.get("dependency-groups").and_then(|groups| groups.get("dev")).and_then(|v| v.as_array());
+        for dep_str in dev_group.into_iter().chain(pep735_dev).flatten().filter_map(|dep| dep.as_str()) {
+            if let Some(mut parsed) = Self::parse_requirement_line(dep_str) {
+                parsed.is_dev = true;
+                deps.dev_dependencies.push(parsed);
+            }
+        }
         
         Ok(deps)
     }
@@ -99,13 +238,38 @@ impl ProjectDependencies {
             return None;
         }
 
+        // Split off per-requirement options such as `--hash=sha256:...`
+        let (line, hashes) = match line.find(" --") {
+            Some(idx) => {
+                let hashes = line[idx..]
+                    .split_whitespace()
+                    .filter_map(|opt| opt.strip_prefix("--hash="))
+                    .map(|h| h.to_string())
+                    .collect();
+                (line[..idx].trim(), hashes)
+            }
+            None => (line, Vec::new()),
+        };
+
+        // Drop requirements whose environment marker excludes this interpreter/platform
+        let line = match line.split_once(';') {
+            Some((requirement, marker)) => {
+                let marker = EnvironmentMarker { raw: marker.trim().to_string() };
+                if !marker.evaluate(&TargetEnvironment::host()) {
+                    return None;
+                }
+                requirement.trim()
+            }
+            None => line,
+        };
+
         // Handle different requirement formats manually
         // Operators to look for, longest first
         let operators = [">=", "<=", "==", "!=", "~=", ">", "<"];
         
         for op in &operators {
             if let Some(idx) = line.find(op) {
-                let name = line[..idx].trim().to_string();
+                let (name, extras) = split_extras(&line[..idx]);
                 let constraint = op.to_string();
                 let version = line[idx+op.len()..].trim().to_string();
                 
@@ -115,17 +279,22 @@ impl ProjectDependencies {
                     version_constraint: Some(constraint),
                     is_dev: false,
                     source: None,
+                    extras,
+                    hashes,
                 });
             }
         }
 
         // Simple package name without version
+        let (name, extras) = split_extras(line);
         Some(Dependency {
-            name: line.to_string(),
+            name,
             version: None,
             version_constraint: None,
             is_dev: false,
             source: None,
+            extras,
+            hashes,
         })
     }
 
@@ -157,6 +326,10 @@ impl ProjectDependencies {
 
     fn format_dependency(&self, dep: &Dependency) -> String {
         let mut formatted = dep.name.clone();
+
+        if !dep.extras.is_empty() {
+            formatted.push_str(&format!("[{}]", dep.extras.join(",")));
+        }
         
         if let (Some(constraint), Some(version)) = (&dep.version_constraint, &dep.version) {
             formatted.push_str(&format!("{}{}", constraint, version));
@@ -165,6 +338,10 @@ impl ProjectDependencies {
         if let Some(source) = &dep.source {
             formatted.push_str(&format!(" @ {}", source));
         }
+
+        for hash in &dep.hashes {
+            formatted.push_str(&format!(" --hash={}", hash));
+        }
         
         formatted
     }
@@ -189,4 +366,54 @@ mod tests {
         assert_eq!(dep.version, None);
         assert_eq!(dep.version_constraint, None);
     }
+
+    #[test]
+    fn test_parse_extras() {
+        let dep = ProjectDependencies::parse_requirement_line("requests[socks, Security]>=2.25.0").unwrap();
+        assert_eq!(dep.name, "requests");
+        assert_eq!(dep.extras, vec!["socks".to_string(), "security".to_string()]);
+        assert_eq!(dep.version, Some("2.25.0".to_string()));
+
+        let dep = ProjectDependencies::parse_requirement_line("uvicorn[standard]").unwrap();
+        assert_eq!(dep.name, "uvicorn");
+        assert_eq!(dep.extras, vec!["standard".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_hashes() {
+        let dep = ProjectDependencies::parse_requirement_line(
+            "idna==3.4 --hash=sha256:aaa --hash=sha256:bbb"
+        ).unwrap();
+        assert_eq!(dep.name, "idna");
+        assert_eq!(dep.version, Some("3.4".to_string()));
+        assert_eq!(dep.hashes, vec!["sha256:aaa".to_string(), "sha256:bbb".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_markers() {
+        let dep = ProjectDependencies::parse_requirement_line("tomli>=1.1; python_version < \"3.0\"");
+        assert!(dep.is_none());
+
+        let dep = ProjectDependencies::parse_requirement_line("click==8.1.7 ; python_version >= \"3.7\"").unwrap();
+        assert_eq!(dep.version, Some("8.1.7".to_string()));
+    }
+
+    #[test]
+    fn test_requirements_includes_and_editables() {
+        let dir = std::env::temp_dir().join(format!("snakepit-reqs-{}", std::process::id()));
+        let lib = dir.join("libs").join("core");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(lib.join("pyproject.toml"), "[project]\nname = \"acme-core\"\n").unwrap();
+        std::fs::write(dir.join("base.txt"), "requests>=2.31  # http\n--index-url https://example.invalid/simple\n-r dev.txt\n").unwrap();
+        std::fs::write(dir.join("dev.txt"), "-r base.txt\n-e ./libs/core\n-e git+https://example.invalid/tool.git#egg=tool&subdirectory=py\n").unwrap();
+
+        let deps = ProjectDependencies::from_requirements_txt(dir.join("dev.txt")).unwrap();
+        assert_eq!(deps.dependencies.len(), 1);
+        assert_eq!(deps.dependencies[0].name, "requests");
+        let editables: Vec<&str> = deps.editables.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(editables, vec!["acme-core", "tool"]);
+        assert!(deps.editables[0].source.as_deref().unwrap().ends_with("core"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
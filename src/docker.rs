@@ -0,0 +1,206 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// A running container with a Python process the daemon can install into
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerTarget {
+    pub id: String,
+    pub name: String,
+    /// Interpreter the container's Python process runs, used as `<python> -m pip`
+    pub python: String,
+}
+
+/// Just enough of the Docker Engine API, spoken over its Unix socket
+pub struct DockerClient {
+    socket: PathBuf,
+}
+
+impl DockerClient {
+    /// The socket in `DOCKER_HOST` when that's a `unix://` one, else the default
+    pub fn from_env() -> Self {
+        let socket = std::env::var("DOCKER_HOST").ok()
+            .and_then(|host| host.strip_prefix("unix://").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET));
+        Self { socket }
+    }
+
+    pub fn available(&self) -> bool {
+        self.socket.exists()
+    }
+
+    /// Running containers with a Python process in them
+    pub async fn python_containers(&self) -> Result<Vec<ContainerTarget>> {
+        let containers: Vec<Value> = serde_json::from_slice(&self.get("/containers/json").await?)?;
+        let mut targets = Vec::new();
+        for container in containers {
+            let Some(id) = container["Id"].as_str() else { continue };
+            let name = container["Names"][0].as_str().unwrap_or(id).trim_start_matches('/').to_string();
+            // Containers can stop between the listing and this
+            let Ok(top) = self.get(&format!("/containers/{}/top", id)).await else { continue };
+            if let Some(python) = python_command(&serde_json::from_slice(&top)?) {
+                targets.push(ContainerTarget { id: id.to_string(), name, python });
+            }
+        }
+        Ok(targets)
+    }
+
+    /// What the container wrote to stdout and stderr since `since` (unix seconds, fractional)
+    pub async fn logs_since(&self, id: &str, since: f64) -> Result<String> {
+        let raw = self.get(&format!("/containers/{}/logs?stdout=1&stderr=1&since={:.6}", id, since)).await?;
+        Ok(String::from_utf8_lossy(&demultiplex(&raw)).into_owned())
+    }
+
+    /// `python -m pip install <package>` inside the container
+    pub async fn pip_install(&self, container: &ContainerTarget, package: &str) -> Result<()> {
+        let exec = json!({
+            "AttachStdout": true,
+            "AttachStderr": true,
+            "Cmd": [container.python, "-m", "pip", "install", package],
+        });
+        let created: Value = serde_json::from_slice(&self.request("POST", &format!("/containers/{}/exec", container.id), Some(&exec)).await?)?;
+        let exec_id = created["Id"].as_str().ok_or_else(|| anyhow!("Docker didn't create the exec"))?;
+        // Returns once the command has finished
+        let output = self.request("POST", &format!("/exec/{}/start", exec_id), Some(&json!({ "Detach": false, "Tty": false }))).await?;
+        let inspected: Value = serde_json::from_slice(&self.get(&format!("/exec/{}/json", exec_id)).await?)?;
+        match inspected["ExitCode"].as_i64() {
+            Some(0) => Ok(()),
+            code => {
+                let output = String::from_utf8_lossy(&demultiplex(&output)).into_owned();
+                let last = output.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("").trim().to_string();
+                Err(anyhow!("pip in {} exited with {}: {}", container.name, code.map_or("?".to_string(), |c| c.to_string()), last))
+            }
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Vec<u8>> {
+        self.request("GET", path, None).await
+    }
+
+    /// One HTTP/1.0 exchange, so the daemon closes the connection instead of chunking
+    #[cfg(unix)]
+    async fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Vec<u8>> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::UnixStream::connect(&self.socket).await
+            .with_context(|| format!("Could not connect to Docker at {}", self.socket.display()))?;
+        let body = body.map(serde_json::to_vec).transpose()?.unwrap_or_default();
+        let head = format!(
+            "{} {} HTTP/1.0\r\nHost: docker\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            method, path, body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(&body).await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        let (status, body) = parse_response(&response)?;
+        if !(200..300).contains(&status) {
+            let message = serde_json::from_slice::<Value>(&body).ok()
+                .and_then(|error| error["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| String::from_utf8_lossy(&body).trim().to_string());
+            return Err(anyhow!("Docker {} {} failed ({}): {}", method, path, status, message));
+        }
+        Ok(body)
+    }
+
+    #[cfg(not(unix))]
+    async fn request(&self, _method: &str, _path: &str, _body: Option<&Value>) -> Result<Vec<u8>> {
+        Err(anyhow!("Talking to Docker needs Unix domain sockets"))
+    }
+}
+
+/// The interpreter of the first Python process in a `/containers/{id}/top` answer
+fn python_command(top: &Value) -> Option<String> {
+    let titles = top["Titles"].as_array()?;
+    let column = titles.iter().position(|title| matches!(title.as_str(), Some("CMD" | "COMMAND")))?;
+    top["Processes"].as_array()?.iter()
+        .filter_map(|process| process[column].as_str())
+        .find(|command| command.contains("python"))
+        .map(|command| {
+            let program = command.split_whitespace().next().unwrap_or("");
+            // `gunicorn app:app` runs Python too, but isn't an interpreter to call pip with
+            if program.rsplit('/').next().is_some_and(|name| name.starts_with("python")) {
+                program.to_string()
+            } else {
+                "python3".to_string()
+            }
+        })
+}
+
+/// Status code and body of a raw HTTP response
+fn parse_response(raw: &[u8]) -> Result<(u16, Vec<u8>)> {
+    let split = raw.windows(4).position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("Malformed response from Docker"))?;
+    let head = String::from_utf8_lossy(&raw[..split]);
+    let status = head.split_whitespace().nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("Malformed status line from Docker"))?;
+    let body = &raw[split + 4..];
+    let chunked = head.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    Ok((status, if chunked { dechunk(body) } else { body.to_vec() }))
+}
+
+fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    while let Some(end) = body.windows(2).position(|window| window == b"\r\n") {
+        let size = usize::from_str_radix(String::from_utf8_lossy(&body[..end]).trim(), 16).unwrap_or(0);
+        if size == 0 || body.len() < end + 2 + size {
+            break;
+        }
+        out.extend_from_slice(&body[end + 2..end + 2 + size]);
+        body = &body[(end + 4 + size).min(body.len())..];
+    }
+    out
+}
+
+/// Strip the 8-byte stream headers Docker puts on the output of containers without a TTY
+fn demultiplex(raw: &[u8]) -> Vec<u8> {
+    let multiplexed = raw.len() >= 8 && raw[0] <= 2 && raw[1..4] == [0, 0, 0];
+    if !multiplexed {
+        return raw.to_vec();
+    }
+    let mut out = Vec::new();
+    let mut rest = raw;
+    while rest.len() >= 8 {
+        let size = u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let end = (8 + size).min(rest.len());
+        out.extend_from_slice(&rest[8..end]);
+        rest = &rest[end..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_engine_responses() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+        assert_eq!(parse_response(raw).unwrap(), (200, b"hello world".to_vec()));
+        assert_eq!(parse_response(b"HTTP/1.0 404 Not Found\r\n\r\n{}").unwrap().0, 404);
+
+        let mut logs = vec![2, 0, 0, 0, 0, 0, 0, 6];
+        logs.extend_from_slice(b"oops!\n");
+        logs.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 3]);
+        logs.extend_from_slice(b"ok\n");
+        assert_eq!(demultiplex(&logs), b"oops!\nok\n");
+        assert_eq!(demultiplex(b"plain tty output\n"), b"plain tty output\n");
+
+        let top = json!({
+            "Titles": ["UID", "PID", "CMD"],
+            "Processes": [["root", "1", "/bin/sh -c run"], ["root", "7", "/usr/local/bin/python3.12 app.py"]],
+        });
+        assert_eq!(python_command(&top).as_deref(), Some("/usr/local/bin/python3.12"));
+        let gunicorn = json!({ "Titles": ["CMD"], "Processes": [["/venv/bin/python /venv/bin/gunicorn app:app"]] });
+        assert_eq!(python_command(&gunicorn).as_deref(), Some("/venv/bin/python"));
+        assert_eq!(python_command(&json!({ "Titles": ["CMD"], "Processes": [["nginx"]] })), None);
+    }
+}
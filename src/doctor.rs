@@ -0,0 +1,382 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use crate::config::SnakepitConfig;
+use crate::daemon::DaemonStatus;
+use crate::toolchain::PythonRequest;
+
+const DEFAULT_INDEX: &str = "https://pypi.org/simple";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One aspect of the machine snakepit depends on; `fix` says what to do when it isn't ok
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: Status::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: Status::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: Status::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == Status::Fail).count()
+    }
+}
+
+/// What the interpreter installs would run under reports about itself
+#[derive(Debug, Clone, PartialEq)]
+struct Interpreter {
+    path: PathBuf,
+    version: String,
+    stdlib: PathBuf,
+    in_venv: bool,
+}
+
+impl Interpreter {
+    fn probe(path: &Path) -> Option<Self> {
+        let output = Command::new(path)
+            .args(["-c", "import sys, sysconfig; print(sys.version.split()[0]); print(sysconfig.get_path('stdlib')); print(sys.prefix != sys.base_prefix)"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        Some(Self {
+            path: path.to_path_buf(),
+            version: lines.next()?.trim().to_string(),
+            stdlib: PathBuf::from(lines.next()?.trim()),
+            in_venv: lines.next()?.trim() == "True",
+        })
+    }
+}
+
+/// Checks the machine for the problems that usually sit behind a failed install:
+/// a missing or mismatched interpreter, a broken venv, PATH shadowing, PEP 668,
+/// an unwritable cache, an unreachable index, and the optional AI backend and daemon.
+pub struct Doctor<'a> {
+    config: &'a SnakepitConfig,
+    environment: Option<PathBuf>,
+    interpreter: Option<PathBuf>,
+    request: Option<PythonRequest>,
+}
+
+impl<'a> Doctor<'a> {
+    pub fn new(config: &'a SnakepitConfig) -> Self {
+        Self { config, environment: None, interpreter: None, request: None }
+    }
+
+    /// The venv installs go to; `None` for the user site-packages
+    pub fn with_environment(mut self, environment: Option<PathBuf>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// The interpreter used when there's no venv (default: `python3` on PATH)
+    pub fn with_interpreter(mut self, interpreter: Option<PathBuf>) -> Self {
+        self.interpreter = interpreter;
+        self
+    }
+
+    /// The project's Python pin or `requires-python`
+    pub fn with_request(mut self, request: Option<PythonRequest>) -> Self {
+        self.request = request;
+        self
+    }
+
+    pub async fn examine(&self, daemon: Result<DaemonStatus>) -> DoctorReport {
+        let path = self.environment.as_deref().map(venv_python)
+            .or_else(|| self.interpreter.clone())
+            .or_else(|| snakegg::native::which::find_executable(python_name()));
+        let interpreter = path.as_deref().and_then(Interpreter::probe);
+
+        DoctorReport {
+            checks: vec![
+                interpreter_check(path.as_deref(), interpreter.as_ref(), self.request.as_ref()),
+                environment_check(self.environment.as_deref()),
+                path_check(&path_hits(), self.environment.as_deref()),
+                externally_managed_check(interpreter.as_ref()),
+                cache_check(&self.config.get_cache_path()),
+                self.index_check().await,
+                ai_check(),
+                daemon_check(daemon),
+            ],
+        }
+    }
+
+    async fn index_check(&self) -> Check {
+        let index = self.config.index_url.as_deref().unwrap_or(DEFAULT_INDEX).trim_end_matches('/');
+        let url = format!("{}/pip/", index);
+        let timeout = Duration::from_secs(self.config.timeout.unwrap_or(10));
+        let started = Instant::now();
+        let response = match reqwest::Client::builder().timeout(timeout).build() {
+            Ok(client) => client.get(&url).send().await,
+            Err(e) => return Check::fail("index", format!("Can't build an HTTP client: {}", e), "Check the system's TLS certificates"),
+        };
+        index_status(index, response.map(|r| r.status().as_u16()).map_err(|e| e.to_string()), started.elapsed())
+    }
+}
+
+fn python_name() -> &'static str {
+    if cfg!(windows) { "python" } else { "python3" }
+}
+
+fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    }
+}
+
+fn interpreter_check(path: Option<&Path>, interpreter: Option<&Interpreter>, request: Option<&PythonRequest>) -> Check {
+    const NAME: &str = "interpreter";
+    let Some(path) = path else {
+        return Check::fail(NAME, format!("No `{}` on PATH", python_name()), "Install Python 3, or let snakepit download one: `snakepit python install 3.12`");
+    };
+    let Some(interpreter) = interpreter else {
+        return Check::fail(NAME, format!("{} doesn't run", path.display()), "Reinstall that Python, or recreate the environment with `snakepit venv repair --recreate`");
+    };
+    match request {
+        Some(request) if !request.allows(&interpreter.version) => Check::warn(
+            NAME,
+            format!("Python {} at {} doesn't satisfy the project's {}", interpreter.version, path.display(), request),
+            format!("`snakepit python install {}`, then recreate the environment with `snakepit venv repair --recreate`", request),
+        ),
+        _ => Check::ok(NAME, format!("Python {} at {}", interpreter.version, path.display())),
+    }
+}
+
+fn environment_check(environment: Option<&Path>) -> Check {
+    const NAME: &str = "environment";
+    let Some(venv) = environment else {
+        return Check::warn(NAME, "No virtual environment; installs go to the user site-packages", "`snakepit venv create \"$PWD/.venv\"` to give the project its own");
+    };
+    if !venv.join("pyvenv.cfg").exists() {
+        return Check::fail(NAME, format!("{} has no pyvenv.cfg", venv.display()), "Delete it and create a new environment, or unset VIRTUAL_ENV if it points there by mistake");
+    }
+    if !venv_python(venv).exists() {
+        return Check::fail(NAME, format!("{} has no interpreter (was its base Python removed?)", venv.display()), "`snakepit venv repair --recreate` rebuilds it from snakepit.lock");
+    }
+    let site = crate::venv::VirtualEnvironmentManager::new().get_site_packages_path(venv).ok();
+    let damaged = site.map(|site| crate::repair::verify_site_packages(&site)).unwrap_or_default();
+    if !damaged.is_empty() {
+        let names: Vec<&str> = damaged.iter().map(|d| d.name.as_str()).collect();
+        return Check::warn(NAME, format!("{} fails its RECORD hashes: {}", venv.display(), names.join(", ")), "`snakepit venv repair` reinstalls the damaged packages");
+    }
+    Check::ok(NAME, format!("{} is intact", venv.display()))
+}
+
+/// Every match for the commands snakepit cares about, in PATH order
+fn path_hits() -> Vec<(&'static str, Vec<PathBuf>)> {
+    let dirs: Vec<PathBuf> = std::env::var_os("PATH").map(|p| std::env::split_paths(&p).collect()).unwrap_or_default();
+    let suffix = if cfg!(windows) { ".exe" } else { "" };
+    [python_name(), "pip", "snakepit"].into_iter()
+        .map(|name| {
+            let mut hits: Vec<PathBuf> = Vec::new();
+            for dir in &dirs {
+                let candidate = dir.join(format!("{}{}", name, suffix));
+                let resolved = candidate.canonicalize().unwrap_or_else(|_| candidate.clone());
+                if candidate.is_file() && !hits.iter().any(|hit| hit.canonicalize().ok().as_ref() == Some(&resolved)) {
+                    hits.push(candidate);
+                }
+            }
+            (name, hits)
+        })
+        .collect()
+}
+
+fn path_check(hits: &[(&str, Vec<PathBuf>)], environment: Option<&Path>) -> Check {
+    const NAME: &str = "path";
+    let first = |name: &str| hits.iter().find(|(n, _)| *n == name).and_then(|(_, h)| h.first());
+    let active = std::env::var_os("VIRTUAL_ENV").map(PathBuf::from);
+
+    if let (Some(venv), Some(python)) = (active.as_deref().filter(|v| Some(*v) == environment), first(python_name())) {
+        if !python.starts_with(venv) {
+            return Check::fail(
+                NAME,
+                format!("`{}` is {}, ahead of the active environment {}", python_name(), python.display(), venv.display()),
+                "Re-activate the environment so its bin directory comes first on PATH",
+            );
+        }
+    }
+    if let (Some(pip), Some(python)) = (first("pip"), first(python_name())) {
+        if pip.parent() != python.parent() {
+            return Check::warn(
+                NAME,
+                format!("`pip` is {}, which belongs to a different Python than {}", pip.display(), python.display()),
+                format!("Use `{} -m pip` or `snakepit install`, or reorder PATH", python_name()),
+            );
+        }
+    }
+    if let Some((_, copies)) = hits.iter().find(|(n, h)| *n == "snakepit" && h.len() > 1) {
+        let shadowed: Vec<String> = copies[1..].iter().map(|p| p.display().to_string()).collect();
+        return Check::warn(
+            NAME,
+            format!("`snakepit` is {}; it shadows {}", copies[0].display(), shadowed.join(", ")),
+            "Remove the copies you don't use, or reorder PATH",
+        );
+    }
+    Check::ok(NAME, "No shadowed commands")
+}
+
+fn externally_managed_check(interpreter: Option<&Interpreter>) -> Check {
+    const NAME: &str = "externally-managed";
+    match interpreter {
+        None => Check::ok(NAME, "No interpreter to check"),
+        Some(python) if python.in_venv => Check::ok(NAME, "Installs go to a virtual environment"),
+        Some(python) if python.stdlib.join("EXTERNALLY-MANAGED").exists() => Check::warn(
+            NAME,
+            format!("{} is managed by the OS (PEP 668); pip refuses installs outside a venv", python.path.display()),
+            "`snakepit venv create \"$PWD/.venv\"` and install there instead of forcing --break-system-packages",
+        ),
+        Some(python) => Check::ok(NAME, format!("{} accepts user installs", python.path.display())),
+    }
+}
+
+/// The cache directory exists (or can be made) and takes a write
+fn cache_check(cache: &Path) -> Check {
+    const NAME: &str = "cache";
+    let probe = cache.join(format!(".doctor-{}", std::process::id()));
+    let written = std::fs::create_dir_all(cache).and_then(|_| std::fs::write(&probe, b""));
+    let _ = std::fs::remove_file(&probe);
+    match written {
+        Ok(()) => Check::ok(NAME, format!("{} is writable", cache.display())),
+        Err(e) => Check::fail(
+            NAME,
+            format!("Can't write to {}: {}", cache.display(), e),
+            format!("Give yourself ownership (`sudo chown -R \"$USER\" {}`) or point XDG_CACHE_HOME somewhere writable", cache.display()),
+        ),
+    }
+}
+
+/// Any answer but an auth refusal or server error means the index is up and reachable
+fn index_status(index: &str, response: std::result::Result<u16, String>, elapsed: Duration) -> Check {
+    const NAME: &str = "index";
+    match response {
+        Ok(401) | Ok(403) => Check::fail(NAME, format!("{} refused the request", index), "Add credentials to the index_url, or check the token it uses"),
+        Ok(code) if code >= 500 => Check::fail(NAME, format!("{} answered {}", index, code), "The index is having trouble; retry later or set index_url to a mirror"),
+        Ok(_) => Check::ok(NAME, format!("{} answered in {} ms", index, elapsed.as_millis())),
+        Err(e) => Check::fail(
+            NAME,
+            format!("Can't reach {}: {}", index, e),
+            "Check the network and HTTPS_PROXY, or `snakepit config set index_url <mirror>`",
+        ),
+    }
+}
+
+fn ai_check() -> Check {
+    const NAME: &str = "ai-backend";
+    match snakegg::charmer::SnakeCharmer::new() {
+        Ok(_) => Check::ok(NAME, "Snake Charmer is configured"),
+        Err(e) => Check::warn(NAME, format!("Snake Charmer is unavailable: {}", e), "Set GEMINI_API_KEY to enable AI diagnosis in `run` and `recommend` (optional)"),
+    }
+}
+
+fn daemon_check(status: Result<DaemonStatus>) -> Check {
+    const NAME: &str = "daemon";
+    match status {
+        Ok(status) if status.running && status.error_count > 0 => Check::warn(
+            NAME,
+            format!("Running (pid {}) with {} cached module errors", status.pid.map(|p| p.to_string()).unwrap_or_else(|| "?".to_string()), status.error_count),
+            "`snakepit daemon errors` shows them",
+        ),
+        Ok(status) if status.running => Check::ok(NAME, format!("Running (pid {})", status.pid.map(|p| p.to_string()).unwrap_or_else(|| "?".to_string()))),
+        Ok(_) => Check::ok(NAME, "Not running"),
+        Err(e) => Check::warn(NAME, format!("Can't read the daemon's state: {}", e), "Fix or remove daemon.toml, then `snakepit daemon restart`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpreter_against_project_pin() {
+        let python = Interpreter {
+            path: PathBuf::from("/usr/bin/python3"),
+            version: "3.9.18".to_string(),
+            stdlib: PathBuf::from("/usr/lib/python3.9"),
+            in_venv: false,
+        };
+        let path = Some(python.path.as_path());
+        assert_eq!(interpreter_check(path, Some(&python), None).status, Status::Ok);
+
+        let pinned = PythonRequest::Version("3.12".to_string());
+        let check = interpreter_check(path, Some(&python), Some(&pinned));
+        assert_eq!(check.status, Status::Warn);
+        assert!(check.fix.unwrap().contains("snakepit python install 3.12"));
+
+        assert_eq!(interpreter_check(path, None, None).status, Status::Fail);
+        assert_eq!(interpreter_check(None, None, None).status, Status::Fail);
+    }
+
+    #[test]
+    fn test_mismatched_pip_is_shadowing() {
+        let bin = |dir: &str, name: &str| PathBuf::from(dir).join(name);
+        let python = python_name();
+        let clean = vec![
+            (python, vec![bin("/usr/bin", python)]),
+            ("pip", vec![bin("/usr/bin", "pip")]),
+            ("snakepit", vec![bin("/usr/local/bin", "snakepit")]),
+        ];
+        assert_eq!(path_check(&clean, None).status, Status::Ok);
+
+        let stale_pip = vec![
+            (python, vec![bin("/usr/bin", python)]),
+            ("pip", vec![bin("/opt/old/bin", "pip"), bin("/usr/bin", "pip")]),
+            ("snakepit", vec![]),
+        ];
+        let check = path_check(&stale_pip, None);
+        assert_eq!(check.status, Status::Warn);
+        assert!(check.detail.contains("/opt/old/bin"));
+
+        let two_snakepits = vec![
+            ("snakepit", vec![bin("/home/me/.cargo/bin", "snakepit"), bin("/usr/local/bin", "snakepit")]),
+        ];
+        assert!(path_check(&two_snakepits, None).detail.contains("shadows"));
+    }
+
+    #[test]
+    fn test_cache_and_index_checks() {
+        let dir = std::env::temp_dir().join(format!("snakepit-doctor-{}", std::process::id()));
+        assert_eq!(cache_check(&dir.join("cache")).status, Status::Ok);
+        assert_eq!(std::fs::read_dir(dir.join("cache")).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let elapsed = Duration::from_millis(40);
+        assert_eq!(index_status(DEFAULT_INDEX, Ok(200), elapsed).status, Status::Ok);
+        assert_eq!(index_status(DEFAULT_INDEX, Ok(404), elapsed).status, Status::Ok);
+        assert_eq!(index_status(DEFAULT_INDEX, Ok(403), elapsed).status, Status::Fail);
+        assert_eq!(index_status(DEFAULT_INDEX, Ok(503), elapsed).status, Status::Fail);
+        assert!(index_status(DEFAULT_INDEX, Err("dns error".to_string()), elapsed).fix.is_some());
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use crate::config::SnakepitConfig;
+
+/// Index requests in flight at once while eco mode is on, unless `eco_max_concurrency` says otherwise
+pub const DEFAULT_ECO_CONCURRENCY: usize = 2;
+
+/// Why eco mode is on for this process; `None` inside when it's off
+static ACTIVE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Power and network conditions eco mode reacts to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowerState {
+    pub on_battery: bool,
+    pub battery_percent: Option<u8>,
+    pub metered: bool,
+}
+
+impl PowerState {
+    pub fn detect() -> Self {
+        let (on_battery, battery_percent) = detect_battery();
+        Self { on_battery, battery_percent, metered: detect_metered() }
+    }
+}
+
+/// When to go easy on the battery and the network, from the config and `--eco`/`--no-eco`
+#[derive(Debug, Clone)]
+pub struct EcoPolicy {
+    /// Forced on or off; `None` decides from the power state
+    pub forced: Option<bool>,
+    /// Engage on battery at or below this charge (100: whenever on battery)
+    pub battery_threshold: u8,
+    pub on_metered: bool,
+    pub max_concurrency: usize,
+}
+
+impl EcoPolicy {
+    pub fn from_config(config: &SnakepitConfig) -> Self {
+        Self {
+            forced: config.eco_mode,
+            battery_threshold: config.eco_battery_threshold.unwrap_or(100).min(100),
+            on_metered: config.eco_on_metered.unwrap_or(true),
+            max_concurrency: config.eco_max_concurrency.unwrap_or(DEFAULT_ECO_CONCURRENCY).max(1),
+        }
+    }
+
+    /// Why eco mode should be on in `state`, or `None` when it shouldn't
+    pub fn evaluate(&self, state: &PowerState) -> Option<String> {
+        match self.forced {
+            Some(true) => return Some("requested".to_string()),
+            Some(false) => return None,
+            None => {}
+        }
+        if state.on_battery && state.battery_percent.is_none_or(|p| p <= self.battery_threshold) {
+            return Some(match state.battery_percent {
+                Some(percent) => format!("on battery ({}%)", percent),
+                None => "on battery".to_string(),
+            });
+        }
+        if self.on_metered && state.metered {
+            return Some("metered connection".to_string());
+        }
+        None
+    }
+
+    /// Detect the power state (unless forced) and decide for the rest of this process
+    pub fn activate(&self) -> Option<&'static str> {
+        let reason = match self.forced {
+            Some(_) => self.evaluate(&PowerState::default()),
+            None => self.evaluate(&PowerState::detect()),
+        };
+        ACTIVE.get_or_init(|| reason).as_deref()
+    }
+}
+
+/// Whether non-essential work (background AI calls, parallel downloads) should be held back
+pub fn is_active() -> bool {
+    ACTIVE.get().is_some_and(|reason| reason.is_some())
+}
+
+fn detect_battery() -> (bool, Option<u8>) {
+    if cfg!(target_os = "macos") {
+        return Command::new("pmset").args(["-g", "batt"]).output()
+            .map(|out| parse_pmset(&String::from_utf8_lossy(&out.stdout)))
+            .unwrap_or((false, None));
+    }
+    read_power_supply(Path::new("/sys/class/power_supply"))
+}
+
+/// Linux sysfs: discharging if a battery says so and no mains adapter is online
+fn read_power_supply(root: &Path) -> (bool, Option<u8>) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return (false, None);
+    };
+    let read = |dir: &Path, file: &str| std::fs::read_to_string(dir.join(file)).map(|s| s.trim().to_string()).unwrap_or_default();
+
+    let mut mains_online = false;
+    let mut discharging = false;
+    let mut percent = None;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        match read(&dir, "type").as_str() {
+            "Mains" | "USB" => mains_online |= read(&dir, "online") == "1",
+            "Battery" => {
+                discharging |= read(&dir, "status") == "Discharging";
+                percent = percent.or_else(|| read(&dir, "capacity").parse().ok());
+            }
+            _ => {}
+        }
+    }
+    (discharging && !mains_online, percent)
+}
+
+/// `pmset -g batt`: "Now drawing from 'Battery Power'" and "...\t87%; discharging; ..."
+fn parse_pmset(output: &str) -> (bool, Option<u8>) {
+    let on_battery = output.contains("'Battery Power'");
+    let percent = output.split_whitespace()
+        .find_map(|word| word.trim_end_matches(';').strip_suffix('%')?.parse().ok());
+    (on_battery, percent)
+}
+
+fn detect_metered() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    Command::new("nmcli").args(["-t", "-f", "GENERAL.METERED", "device", "show"]).output()
+        .map(|out| parse_nmcli_metered(&String::from_utf8_lossy(&out.stdout)))
+        .unwrap_or(false)
+}
+
+/// NetworkManager reports "yes" or "yes (guessed)" for metered devices
+fn parse_nmcli_metered(output: &str) -> bool {
+    output.lines()
+        .filter_map(|line| line.strip_prefix("GENERAL.METERED:"))
+        .any(|value| value.starts_with("yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> EcoPolicy {
+        EcoPolicy::from_config(&SnakepitConfig::default())
+    }
+
+    #[test]
+    fn test_policy_follows_power_state_and_overrides() {
+        let on_battery = PowerState { on_battery: true, battery_percent: Some(80), metered: false };
+        let metered = PowerState { metered: true, ..Default::default() };
+
+        assert_eq!(policy().evaluate(&on_battery).as_deref(), Some("on battery (80%)"));
+        assert_eq!(policy().evaluate(&metered).as_deref(), Some("metered connection"));
+        assert_eq!(policy().evaluate(&PowerState::default()), None);
+
+        let lenient = EcoPolicy { battery_threshold: 50, on_metered: false, ..policy() };
+        assert_eq!(lenient.evaluate(&on_battery), None);
+        assert_eq!(lenient.evaluate(&metered), None);
+
+        // `--eco`/`--no-eco` land in the config, so the daemon sees them too
+        let off = EcoPolicy::from_config(&SnakepitConfig::default().with_eco_mode(false));
+        assert_eq!(off.evaluate(&on_battery), None);
+        let on = EcoPolicy::from_config(&SnakepitConfig::default().with_eco_mode(true));
+        assert_eq!(on.evaluate(&PowerState::default()).as_deref(), Some("requested"));
+    }
+
+    #[test]
+    fn test_parses_platform_reports() {
+        let pmset = "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=123)\t87%; discharging; 4:12 remaining present: true\n";
+        assert_eq!(parse_pmset(pmset), (true, Some(87)));
+        assert_eq!(parse_pmset("Now drawing from 'AC Power'\n -InternalBattery-0\t100%; charged;"), (false, Some(100)));
+
+        assert!(parse_nmcli_metered("GENERAL.METERED:no\nGENERAL.METERED:yes (guessed)\n"));
+        assert!(!parse_nmcli_metered("GENERAL.METERED:no\nGENERAL.METERED:unknown\n"));
+    }
+
+    #[test]
+    fn test_reads_sysfs_power_supply() {
+        let root = std::env::temp_dir().join(format!("snakepit-eco-{}", std::process::id()));
+        let write = |dir: &str, file: &str, value: &str| {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join(file), value).unwrap();
+        };
+        write("BAT0", "type", "Battery\n");
+        write("BAT0", "status", "Discharging\n");
+        write("BAT0", "capacity", "42\n");
+        write("AC", "type", "Mains\n");
+        write("AC", "online", "0\n");
+        assert_eq!(read_power_supply(&root), (true, Some(42)));
+
+        write("AC", "online", "1\n");
+        assert_eq!(read_power_supply(&root), (false, Some(42)));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
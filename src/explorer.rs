@@ -0,0 +1,339 @@
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, style::Print, terminal};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use snakegg::native::dirs;
+use snakegg::native::style::{bold, dim};
+use crate::logger;
+use crate::config::SnakepitConfig;
+use crate::manifest::canonical_name;
+use crate::pep440::Version;
+use crate::resolver::{DependencyResolver, PyPIPackageInfo};
+use crate::simple_index::SimpleIndexClient;
+
+/// How long the downloaded project list is reused before fetching it again
+const PROJECT_LIST_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Rows of matches shown at once
+const LIST_ROWS: usize = 12;
+
+/// A package that can be searched for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub name: String,
+    /// Metadata is already in the local cache, so details load instantly
+    pub cached: bool,
+}
+
+/// How well `query` matches `candidate` as a subsequence, or `None` if it doesn't.
+///
+/// Matches at the start of the name or of a `-`/`_`/`.` separated word, and runs of
+/// consecutive characters, score higher; shorter names win ties.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let fold = |c: char| if c == '_' || c == '.' { '-' } else { c.to_ascii_lowercase() };
+    let query: Vec<char> = query.trim().chars().map(fold).collect();
+    let name: Vec<char> = candidate.chars().map(fold).collect();
+    if query.is_empty() {
+        return Some(-(name.len() as i64));
+    }
+
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for &wanted in &query {
+        let found = (position..name.len()).find(|&i| name[i] == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || name[found - 1] == '-' {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    if name == query {
+        score += 1000;
+    } else if name.starts_with(&query) {
+        score += 100;
+    }
+    Some(score * 100 - name.len() as i64)
+}
+
+/// The best `limit` matches for `query`, locally cached packages first among equals
+pub fn rank<'a>(query: &str, candidates: &'a [Candidate], limit: usize) -> Vec<&'a Candidate> {
+    let mut scored: Vec<(i64, &Candidate)> = candidates.iter()
+        .filter_map(|c| fuzzy_score(query, &c.name).map(|score| (score + if c.cached { 50 } else { 0 }, c)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+    scored.into_iter().take(limit).map(|(_, c)| c).collect()
+}
+
+/// What the detail pane shows for a package
+#[derive(Debug, Clone)]
+pub struct Details {
+    pub name: String,
+    pub summary: Option<String>,
+    /// Newest first
+    pub versions: Vec<String>,
+    pub requires: Vec<String>,
+}
+
+impl Details {
+    pub fn from_info(info: &PyPIPackageInfo) -> Self {
+        let mut versions: Vec<(Version, &String)> = info.releases.keys()
+            .filter_map(|v| Version::parse(v).ok().map(|parsed| (parsed, v)))
+            .collect();
+        versions.sort_by(|a, b| b.0.cmp(&a.0));
+        Self {
+            name: info.info.name.clone(),
+            summary: info.info.summary.clone().filter(|s| !s.trim().is_empty()),
+            versions: versions.into_iter().map(|(_, v)| v.clone()).collect(),
+            requires: info.info.requires_dist.clone().unwrap_or_default(),
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let latest = self.versions.first().map(String::as_str).unwrap_or("?");
+        let mut lines = vec![format!("{} {}", bold(&self.name), latest)];
+        if let Some(summary) = &self.summary {
+            lines.push(summary.clone());
+        }
+        let shown: Vec<&str> = self.versions.iter().take(8).map(String::as_str).collect();
+        let more = self.versions.len().saturating_sub(shown.len());
+        lines.push(format!("Versions: {}{}", shown.join(", "), if more > 0 { format!(" (+{} more)", more) } else { String::new() }));
+        lines.push(if self.requires.is_empty() {
+            "Requires: nothing".to_string()
+        } else {
+            format!("Requires: {}", self.requires.join(", "))
+        });
+        lines
+    }
+}
+
+/// Names with metadata in the resolver's disk cache
+fn cached_names() -> Vec<String> {
+    let Some(root) = dirs::cache_dir().map(|dir| dir.join("pypi")) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let stem = name.strip_suffix(".json")?;
+            (!stem.ends_with(".validators")).then(|| stem.to_string())
+        })
+        .collect()
+}
+
+fn is_fresh(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .is_ok_and(|modified| SystemTime::now().duration_since(modified).is_ok_and(|age| age < PROJECT_LIST_TTL))
+}
+
+/// Everything searchable: the local metadata cache, plus the index's project list
+/// (downloaded once a day); falls back to the cache alone when the index can't list
+pub async fn load_candidates(config: &SnakepitConfig) -> Vec<Candidate> {
+    let list_path: PathBuf = config.get_cache_path().join("project-names.json");
+    let mut names: Vec<String> = Vec::new();
+    if is_fresh(&list_path) {
+        if let Ok(content) = std::fs::read_to_string(&list_path) {
+            names = serde_json::from_str(&content).unwrap_or_default();
+        }
+    }
+    if names.is_empty() {
+        let index_url = config.index_url.as_deref().unwrap_or("https://pypi.org/simple");
+        match SimpleIndexClient::new(reqwest::Client::new(), index_url).fetch_project_names().await {
+            Ok(fetched) => {
+                if let Some(parent) = list_path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                let _ = std::fs::write(&list_path, serde_json::to_string(&fetched).unwrap_or_default());
+                names = fetched;
+            }
+            Err(e) => logger::note(format!("Could not list {}: {}; searching the local cache only", index_url, e)),
+        }
+    }
+
+    let cached: Vec<String> = cached_names();
+    let cached_keys: std::collections::HashSet<String> = cached.iter().map(|n| canonical_name(n)).collect();
+    let mut seen = std::collections::HashSet::new();
+    cached.into_iter().chain(names)
+        .filter(|name| seen.insert(canonical_name(name)))
+        .map(|name| Candidate { cached: cached_keys.contains(&canonical_name(&name)), name })
+        .collect()
+}
+
+/// Restores the terminal however the explorer exits
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(std::io::stderr(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stderr(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Full-screen fuzzy finder over package names with a detail pane.
+///
+/// Drawn on stderr so stdout stays clean for the caller.
+pub struct Explorer {
+    candidates: Vec<Candidate>,
+    resolver: DependencyResolver,
+    query: String,
+    selected: usize,
+    details: HashMap<String, Details>,
+    status: String,
+}
+
+impl Explorer {
+    pub fn new(candidates: Vec<Candidate>, resolver: DependencyResolver) -> Self {
+        Self {
+            candidates,
+            resolver,
+            query: String::new(),
+            selected: 0,
+            details: HashMap::new(),
+            status: String::new(),
+        }
+    }
+
+    pub fn with_query(mut self, query: &str) -> Self {
+        self.query = query.to_string();
+        self
+    }
+
+    /// Let the user browse; returns the package chosen for install, if any
+    pub async fn run(mut self) -> Result<Option<String>> {
+        let _guard = TerminalGuard::enter()?;
+        loop {
+            let matches: Vec<Candidate> = rank(&self.query, &self.candidates, LIST_ROWS).into_iter().cloned().collect();
+            self.selected = self.selected.min(matches.len().saturating_sub(1));
+            // Cached metadata costs nothing to show
+            if let Some(current) = matches.get(self.selected) {
+                if current.cached && !self.details.contains_key(&current.name) {
+                    self.load_details(&current.name).await;
+                }
+            }
+            self.draw(&matches)?;
+
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key {
+                KeyEvent { code: KeyCode::Esc, .. } => return Ok(None),
+                KeyEvent { code: KeyCode::Char('c'), modifiers, .. } if modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+                KeyEvent { code: KeyCode::Enter, .. } => return Ok(matches.get(self.selected).map(|c| c.name.clone())),
+                KeyEvent { code: KeyCode::Up, .. } => self.selected = self.selected.saturating_sub(1),
+                KeyEvent { code: KeyCode::Char('p'), modifiers, .. } if modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.selected = self.selected.saturating_sub(1)
+                }
+                KeyEvent { code: KeyCode::Down, .. } => self.selected += 1,
+                KeyEvent { code: KeyCode::Char('n'), modifiers, .. } if modifiers.contains(KeyModifiers::CONTROL) => self.selected += 1,
+                KeyEvent { code: KeyCode::Tab | KeyCode::Right, .. } => {
+                    if let Some(current) = matches.get(self.selected) {
+                        self.status = format!("Fetching {}...", current.name);
+                        self.draw(&matches)?;
+                        self.load_details(&current.name).await;
+                    }
+                }
+                KeyEvent { code: KeyCode::Backspace, .. } => {
+                    self.query.pop();
+                    self.selected = 0;
+                }
+                KeyEvent { code: KeyCode::Char(c), modifiers, .. } if !modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.query.push(c);
+                    self.selected = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn load_details(&mut self, name: &str) {
+        match self.resolver.fetch_package_info(name).await {
+            Ok(info) => {
+                self.details.insert(name.to_string(), Details::from_info(&info));
+                self.status.clear();
+            }
+            Err(e) => self.status = format!("Could not fetch {}: {}", name, e),
+        }
+    }
+
+    fn draw(&self, matches: &[Candidate]) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+        let mut lines = vec![
+            format!("Search: {}_", self.query),
+            dim(format!("{} matches · ↑/↓ move · Tab details · Enter install · Esc quit", matches.len())).to_string(),
+        ];
+        for (i, candidate) in matches.iter().enumerate() {
+            let marker = if candidate.cached { dim(" (cached)").to_string() } else { String::new() };
+            if i == self.selected {
+                lines.push(format!("> {}{}", bold(&candidate.name), marker));
+            } else {
+                lines.push(format!("  {}{}", candidate.name, marker));
+            }
+        }
+        lines.push("─".repeat(width.min(60)));
+        if let Some(details) = matches.get(self.selected).and_then(|c| self.details.get(&c.name)) {
+            lines.extend(details.lines());
+        }
+        if !self.status.is_empty() {
+            lines.push(dim(&self.status).to_string());
+        }
+
+        let mut out = std::io::stderr();
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        for (row, line) in lines.iter().take(height as usize).enumerate() {
+            // Styled text is counted loosely; long dependency lists just get cut
+            let line: String = line.chars().take(width + 16).collect();
+            queue!(out, cursor::MoveTo(0, row as u16), Print(line))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(names: &[(&str, bool)]) -> Vec<Candidate> {
+        names.iter().map(|(name, cached)| Candidate { name: name.to_string(), cached: *cached }).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_exact_prefix_and_word_starts() {
+        assert!(fuzzy_score("req", "requests").unwrap() > fuzzy_score("req", "pyrequire").unwrap());
+        assert!(fuzzy_score("requests", "requests").unwrap() > fuzzy_score("requests", "requests-oauthlib").unwrap());
+        assert!(fuzzy_score("dj rest", "django-rest").is_none());
+        assert!(fuzzy_score("djrest", "django-rest-framework").unwrap() > fuzzy_score("djrest", "djangorestx").unwrap_or(i64::MIN));
+        assert_eq!(fuzzy_score("zope_interface", "zope.interface"), fuzzy_score("zope-interface", "zope-interface"));
+        assert!(fuzzy_score("xyz", "requests").is_none());
+    }
+
+    #[test]
+    fn test_rank_puts_cached_first_among_equals() {
+        let all = candidates(&[("flask-cors", false), ("flask-login", true), ("flask", false), ("quart", false)]);
+        let ranked: Vec<&str> = rank("flask", &all, 10).iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(ranked, vec!["flask", "flask-login", "flask-cors"]);
+        assert_eq!(rank("", &all, 2).len(), 2);
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::lockfile::{LockedPackage, Lockfile, PackageSource};
+use crate::manifest::canonical_name;
+use crate::markers::{self, TargetEnvironment};
+
+/// Formats `snakepit export` writes the lockfile in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    RequirementsTxt,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "requirements-txt" | "requirements.txt" => Ok(ExportFormat::RequirementsTxt),
+            other => Err(anyhow!("Unknown export format '{}' (expected requirements-txt)", other)),
+        }
+    }
+}
+
+/// The exported text, plus anything pip won't be able to use as written
+#[derive(Debug)]
+pub struct Exported {
+    pub content: String,
+    pub warnings: Vec<String>,
+}
+
+/// A pinned, pip-installable view of a lockfile.
+///
+/// Packages only some locked targets need get a `sys_platform`/`python_version` marker,
+/// so one exported file works everywhere the lock does.
+pub struct RequirementsExport<'a> {
+    lockfile: &'a Lockfile,
+    hashes: bool,
+    dev: bool,
+}
+
+impl<'a> RequirementsExport<'a> {
+    pub fn new(lockfile: &'a Lockfile) -> Self {
+        Self { lockfile, hashes: false, dev: false }
+    }
+
+    /// Add `--hash` options so pip can install with `--require-hashes`
+    pub fn with_hashes(mut self, hashes: bool) -> Self {
+        self.hashes = hashes;
+        self
+    }
+
+    /// Include a dependency group besides the main one; only `dev` exists
+    pub fn with_group(mut self, group: &str) -> Result<Self> {
+        match group.trim() {
+            "main" => {}
+            "dev" => self.dev = true,
+            other => return Err(anyhow!("Unknown dependency group '{}' (expected main or dev)", other)),
+        }
+        Ok(self)
+    }
+
+    pub fn render(&self) -> Result<Exported> {
+        let mut warnings = Vec::new();
+        let mut lines = vec![format!("# Generated by snakepit from {}; do not edit", crate::lockfile::LOCKFILE_NAME)];
+        if let Some(index_url) = &self.lockfile.inputs.index_url {
+            lines.push(format!("--index-url {}", index_url));
+        }
+
+        let marker_for = self.environment_markers()?;
+        let mut packages = self.selected();
+        packages.sort_by_key(|package| canonical_name(&package.name));
+        for package in packages {
+            let name = if package.extras.is_empty() {
+                package.name.clone()
+            } else {
+                format!("{}[{}]", package.name, package.extras.join(","))
+            };
+            let mut line = match &package.source {
+                PackageSource::PyPI { .. } => format!("{}=={}", name, package.version),
+                PackageSource::Git { url, rev } => format!("{} @ git+{}@{}", name, url.trim_start_matches("git+"), rev),
+                PackageSource::Path { path } => path.clone(),
+            };
+            if let Some(marker) = marker_for(package) {
+                line.push_str(&format!(" ; {}", marker));
+            }
+
+            if self.hashes {
+                if !matches!(package.source, PackageSource::PyPI { .. }) {
+                    warnings.push(format!("{} isn't from an index; pip --require-hashes will refuse it", package.name));
+                } else if package.hashes.is_empty() {
+                    warnings.push(format!("{} has no hashes in {}", package.name, crate::lockfile::LOCKFILE_NAME));
+                }
+                for hash in &package.hashes {
+                    line.push_str(&format!(" \\\n    --hash={}", hash));
+                }
+            }
+            lines.push(line);
+        }
+
+        Ok(Exported { content: format!("{}\n", lines.join("\n")), warnings })
+    }
+
+    /// Packages reachable from the main requirements (and the dev ones, if asked);
+    /// every package when the lock doesn't record its inputs
+    fn selected(&self) -> Vec<&'a LockedPackage> {
+        let inputs = &self.lockfile.inputs;
+        if inputs.requirements.is_empty() && inputs.dev_requirements.is_empty() {
+            return self.lockfile.packages.iter().collect();
+        }
+
+        let by_name: HashMap<String, &LockedPackage> = self.lockfile.packages.iter()
+            .map(|package| (canonical_name(&package.name), package))
+            .collect();
+        let roots = inputs.requirements.iter()
+            .chain(inputs.dev_requirements.iter().filter(|_| self.dev));
+        let mut stack: Vec<String> = roots
+            .filter_map(|requirement| markers::parse_requirement(requirement).ok())
+            .map(|spec| canonical_name(&spec.name))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut selected = Vec::new();
+        while let Some(name) = stack.pop() {
+            let Some(package) = by_name.get(&name) else {
+                continue;
+            };
+            if !seen.insert(name) {
+                continue;
+            }
+            selected.push(*package);
+            stack.extend(package.dependencies.iter()
+                .filter_map(|requirement| markers::parse_requirement(requirement).ok())
+                .map(|spec| canonical_name(&spec.name)));
+        }
+        selected
+    }
+
+    /// A marker for each package only some of the locked targets need
+    fn environment_markers(&self) -> Result<impl Fn(&LockedPackage) -> Option<String>> {
+        // The Python versions each platform was locked for
+        let mut locked: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for label in &self.lockfile.metadata.environments {
+            let env = TargetEnvironment::parse_target(label)?;
+            locked.entry(env.sys_platform).or_default().insert(env.python_version);
+        }
+
+        Ok(move |package: &LockedPackage| {
+            if package.environments.is_empty() {
+                return None;
+            }
+            let mut by_platform: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+            for env in package.environments.iter().filter_map(|label| TargetEnvironment::parse_target(label).ok()) {
+                by_platform.entry(env.sys_platform).or_default().insert(env.python_version);
+            }
+
+            let clauses: Vec<String> = by_platform.iter()
+                .map(|(platform, versions)| {
+                    let clause = format!("sys_platform == '{}'", platform);
+                    if locked.get(platform) == Some(versions) {
+                        return clause;
+                    }
+                    let versions: Vec<String> = versions.iter().map(|v| format!("python_version == '{}'", v)).collect();
+                    match versions.as_slice() {
+                        [single] => format!("{} and {}", clause, single),
+                        _ => format!("{} and ({})", clause, versions.join(" or ")),
+                    }
+                })
+                .collect();
+            match clauses.as_slice() {
+                [] => None,
+                [single] => Some(single.clone()),
+                _ => Some(clauses.iter().map(|c| format!("({})", c)).collect::<Vec<_>>().join(" or ")),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, dependencies: &[&str]) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            hashes: vec![format!("sha256:{}", name)],
+            requires_python: None,
+            source: PackageSource::PyPI { url: "https://pypi.org/simple".to_string() },
+            extras: Vec::new(),
+            files: Vec::new(),
+            environments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_requirements_txt_groups_markers_and_hashes() {
+        let mut lock = Lockfile::new();
+        lock.metadata.environments = vec!["linux-3.11".to_string(), "linux-3.12".to_string(), "windows-3.12".to_string()];
+        lock.inputs.requirements = vec!["click>=8".to_string()];
+        lock.inputs.dev_requirements = vec!["pytest".to_string()];
+        lock.add_package(package("click", "8.1.7", &["colorama==0.4.6; sys_platform == 'win32'"]));
+        let mut colorama = package("colorama", "0.4.6", &[]);
+        colorama.environments = vec!["windows-3.12".to_string()];
+        lock.add_package(colorama);
+        let mut tomli = package("tomli", "2.0.1", &[]);
+        tomli.environments = vec!["linux-3.11".to_string()];
+        lock.add_package(tomli);
+        lock.add_package(package("pytest", "8.0.0", &["tomli==2.0.1; python_version < '3.12'"]));
+
+        let main = RequirementsExport::new(&lock).render().unwrap();
+        assert_eq!(main.content.lines().skip(1).collect::<Vec<_>>(), vec![
+            "click==8.1.7",
+            "colorama==0.4.6 ; sys_platform == 'win32'",
+        ]);
+
+        let dev = RequirementsExport::new(&lock).with_group("dev").unwrap().with_hashes(true).render().unwrap();
+        assert!(dev.content.contains("pytest==8.0.0 \\\n    --hash=sha256:pytest\n"));
+        assert!(dev.content.contains("tomli==2.0.1 ; sys_platform == 'linux' and python_version == '3.11' \\\n"));
+        assert!(dev.warnings.is_empty());
+
+        assert!(RequirementsExport::new(&lock).with_group("docs").is_err());
+        assert!(ExportFormat::parse("pylock").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+
+/// Packages `freeze` leaves out unless asked, as pip does: they manage the environment
+/// rather than belong to the project
+pub const TOOLING: &[&str] = &["pip", "setuptools", "wheel", "distribute"];
+
+/// `direct_url.json` (PEP 610), written for anything installed from a URL, a VCS or a local path
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct DirectUrl {
+    pub url: String,
+    #[serde(default)]
+    pub dir_info: Option<DirInfo>,
+    #[serde(default)]
+    pub vcs_info: Option<VcsInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct DirInfo {
+    #[serde(default)]
+    pub editable: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct VcsInfo {
+    pub vcs: String,
+    #[serde(default)]
+    pub commit_id: Option<String>,
+    #[serde(default)]
+    pub requested_revision: Option<String>,
+}
+
+/// One installed distribution, as its dist-info describes it
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledDistribution {
+    /// The name as published, from METADATA
+    pub name: String,
+    pub version: String,
+    pub direct_url: Option<DirectUrl>,
+    /// The `.dist-info` directory
+    pub dist_info: PathBuf,
+}
+
+impl InstalledDistribution {
+    pub fn is_editable(&self) -> bool {
+        self.direct_url.as_ref()
+            .and_then(|url| url.dir_info.as_ref())
+            .is_some_and(|dir| dir.editable)
+    }
+
+    /// The requirement line that reinstalls exactly this:
+    /// `name==version`, `name @ git+url@commit`, `name @ url` or `-e url`
+    pub fn requirement(&self) -> String {
+        let Some(direct) = &self.direct_url else {
+            return format!("{}=={}", self.name, self.version);
+        };
+        let url = match &direct.vcs_info {
+            Some(vcs) => {
+                let revision = vcs.commit_id.as_deref().or(vcs.requested_revision.as_deref());
+                let prefix = format!("{}+", vcs.vcs);
+                let base = if direct.url.starts_with(&prefix) { direct.url.clone() } else { format!("{}{}", prefix, direct.url) };
+                match revision {
+                    Some(revision) => format!("{}@{}", base, revision),
+                    None => base,
+                }
+            }
+            None => direct.url.clone(),
+        };
+        if self.is_editable() {
+            format!("-e {}", url)
+        } else {
+            format!("{} @ {}", self.name, url)
+        }
+    }
+}
+
+/// Every distribution in `site_packages`, sorted by name. Names and versions come from
+/// METADATA: directory names are normalized by some installers and mangled by others.
+pub fn installed_distributions(site_packages: &Path) -> Result<Vec<InstalledDistribution>> {
+    if !site_packages.exists() {
+        return Ok(Vec::new());
+    }
+    let mut distributions = Vec::new();
+    for entry in std::fs::read_dir(site_packages)?.flatten() {
+        let dir_name = entry.file_name().to_string_lossy().to_string();
+        if !dir_name.ends_with(".dist-info") {
+            continue;
+        }
+        let Ok(metadata) = std::fs::read_to_string(entry.path().join("METADATA")) else { continue };
+        let header = |key: &str| metadata.lines()
+            .take_while(|line| !line.trim().is_empty())
+            .find_map(|line| line.strip_prefix(key))
+            .map(|value| value.trim().to_string());
+        let (Some(name), Some(version)) = (header("Name:"), header("Version:")) else { continue };
+        let direct_url = std::fs::read_to_string(entry.path().join("direct_url.json")).ok()
+            .and_then(|json| serde_json::from_str(&json).ok());
+        distributions.push(InstalledDistribution { name, version, direct_url, dist_info: entry.path() });
+    }
+    distributions.sort_by_key(|dist| canonical_name(&dist.name));
+    Ok(distributions)
+}
+
+/// `freeze` output: one requirement per line, editable installs after the rest as pip puts them
+pub fn freeze(distributions: &[InstalledDistribution], all: bool, exclude_editable: bool) -> String {
+    let wanted = |dist: &&InstalledDistribution| all || !TOOLING.contains(&canonical_name(&dist.name).as_str());
+    let (editable, regular): (Vec<&InstalledDistribution>, Vec<&InstalledDistribution>) = distributions.iter()
+        .filter(wanted)
+        .partition(|dist| dist.is_editable());
+
+    let mut out = String::new();
+    for dist in regular {
+        out.push_str(&dist.requirement());
+        out.push('\n');
+    }
+    if !exclude_editable {
+        for dist in editable {
+            out.push_str(&format!("# Editable install of {}=={}\n", dist.name, dist.version));
+            out.push_str(&dist.requirement());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist(site: &Path, dir: &str, name: &str, version: &str, direct_url: Option<&str>) {
+        let path = site.join(dir);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("METADATA"), format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n\n", name, version)).unwrap();
+        if let Some(json) = direct_url {
+            std::fs::write(path.join("direct_url.json"), json).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_freeze_reads_metadata_and_direct_urls() {
+        let site = std::env::temp_dir().join(format!("snakepit-freeze-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&site);
+        dist(&site, "zope.interface-6.0.dist-info", "zope.interface", "6.0", None);
+        dist(&site, "Django-5.0.dist-info", "Django", "5.0", None);
+        dist(&site, "pip-24.0.dist-info", "pip", "24.0", None);
+        dist(&site, "tool-0.1.dist-info", "tool", "0.1",
+            Some(r#"{"url": "https://github.com/acme/tool", "vcs_info": {"vcs": "git", "commit_id": "abc123"}}"#));
+        dist(&site, "mine-1.0.dist-info", "mine", "1.0",
+            Some(r#"{"url": "file:///src/mine", "dir_info": {"editable": true}}"#));
+        dist(&site, "local-2.0.dist-info", "local", "2.0",
+            Some(r#"{"url": "file:///wheels/local-2.0-py3-none-any.whl", "archive_info": {}}"#));
+
+        let distributions = installed_distributions(&site).unwrap();
+        assert_eq!(freeze(&distributions, false, false), "\
+Django==5.0
+local @ file:///wheels/local-2.0-py3-none-any.whl
+tool @ git+https://github.com/acme/tool@abc123
+zope.interface==6.0
+# Editable install of mine==1.0
+-e file:///src/mine
+");
+        let all = freeze(&distributions, true, true);
+        assert!(all.contains("pip==24.0\n"));
+        assert!(!all.contains("mine"));
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
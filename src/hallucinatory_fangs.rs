@@ -2,6 +2,7 @@ use anyhow::Result;
 use std::path::PathBuf;
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
+use crate::logger;
 
 pub struct HallucinatoryFangs {
     sandbox_dir: PathBuf,
@@ -43,8 +44,8 @@ impl HallucinatoryFangs {
         
         std::fs::create_dir_all(&sandbox_dir)?;
         
-        println!("🧪 Hallucinatory Fangs initialized");
-        println!("📂 Sandbox: {}", sandbox_dir.display());
+        logger::info("🧪 Hallucinatory Fangs initialized");
+        logger::info(format!("📂 Sandbox: {}", sandbox_dir.display()));
         
         Ok(Self {
             sandbox_dir,
@@ -57,11 +58,11 @@ impl HallucinatoryFangs {
     }
     
     pub fn fork_module(&self, module_name: &str) -> Result<PathBuf> {
-        println!("🧪 Forking module: {}", module_name);
+        logger::info(format!("🧪 Forking module: {}", module_name));
         
         // Find module location using Python
         let module_path = self.find_module(module_name)?;
-        println!("📍 Found module at: {}", module_path.display());
+        logger::info(format!("📍 Found module at: {}", module_path.display()));
         
         // Create sandbox fork directory
         let fork_dir = self.sandbox_dir.join(format!("{}_fork", module_name));
@@ -76,22 +77,22 @@ impl HallucinatoryFangs {
         // Copy module files to sandbox
         self.copy_module(&module_path, &fork_dir)?;
         
-        println!("✅ Forked to: {}", fork_dir.display());
-        println!("💡 Original module untouched");
+        logger::success(format!("✅ Forked to: {}", fork_dir.display()));
+        logger::info("💡 Original module untouched");
         
         Ok(fork_dir)
     }
     
     pub fn apply_modifications(&self, fork_dir: &PathBuf) -> Result<()> {
         if self.modifications.is_empty() {
-            println!("⚠️  No modifications to apply");
+            logger::warn("⚠️  No modifications to apply");
             return Ok(());
         }
         
-        println!("🔧 Applying {} modification(s)...", self.modifications.len());
+        logger::info(format!("🔧 Applying {} modification(s)...", self.modifications.len()));
         
         for (i, rule) in self.modifications.iter().enumerate() {
-            println!("   {}. {} -> {} ({})", 
+            logger::info(format!("   {}. {} -> {} ({})", 
                 i + 1,
                 rule.target_module,
                 rule.target_function,
@@ -101,13 +102,12 @@ impl HallucinatoryFangs {
                     ModificationType::InjectCache { .. } => "cache",
                     ModificationType::MockReturn { .. } => "mock",
                     ModificationType::CustomCode { .. } => "custom",
-                }
-            );
+                }));
             
             self.apply_modification(fork_dir, rule)?;
         }
         
-        println!("✅ All modifications applied");
+        logger::success("✅ All modifications applied");
         
         Ok(())
     }
@@ -321,15 +321,15 @@ impl HallucinatoryFangs {
     }
     
     pub fn rollback(&self, module_name: &str) -> Result<()> {
-        println!("🔄 Rolling back modifications to {}...", module_name);
+        logger::info(format!("🔄 Rolling back modifications to {}...", module_name));
         
         let fork_dir = self.sandbox_dir.join(format!("{}_fork", module_name));
         
         if fork_dir.exists() {
             std::fs::remove_dir_all(&fork_dir)?;
-            println!("✅ Rollback complete - fork removed");
+            logger::success("✅ Rollback complete - fork removed");
         } else {
-            println!("⚠️  No fork found for '{}'", module_name);
+            logger::warn(format!("⚠️  No fork found for '{}'", module_name));
         }
         
         Ok(())
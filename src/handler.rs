@@ -2,10 +2,15 @@ use anyhow::Result;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use snakegg::native::id;
-use snakegg::native::style::{red, green, yellow, blue, cyan, magenta, bold, dim};
+use snakegg::native::style::{blue, cyan, magenta};
 use serde::{Serialize, Deserialize};
-use crate::sandbox::VenvSandbox;
+use crate::sandbox::{VenvSandbox, SandboxMode};
 use crate::installer::{PackageInstaller, InstallerBackend};
+use crate::renderer::EventBus;
+use crate::upstream::Upstream;
+use crate::prerelease::Prereleases;
+use crate::logger;
+use crate::manifest::canonical_name;
 use snakegg::charmer::SnakeCharmer;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -29,29 +34,80 @@ pub struct PackageMetadata {
     pub test_time: Option<u64>,
     pub install_time: Option<u64>,
     pub sandbox_id: String,
+    #[serde(default)]
+    pub sandbox_mode: SandboxMode,
     pub error_log: Vec<String>,
     pub success_log: Vec<String>,
 }
 
 pub struct SnakepitHandler {
     active_packages: std::collections::HashMap<String, PackageMetadata>,
+    events: EventBus,
+    upstream: Upstream,
+    constraint_files: Vec<PathBuf>,
+    verify_imports: bool,
+    prereleases: Prereleases,
+    interpreter: Option<PathBuf>,
 }
 
 impl SnakepitHandler {
     pub fn new() -> Self {
         Self {
             active_packages: std::collections::HashMap::new(),
+            events: EventBus::new(),
+            upstream: Upstream::new(),
+            constraint_files: Vec::new(),
+            verify_imports: false,
+            prereleases: Prereleases::new(),
+            interpreter: None,
         }
     }
 
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn with_upstream(mut self, upstream: Upstream) -> Self {
+        self.upstream = upstream;
+        self
+    }
+
+    pub fn with_constraint_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.constraint_files = files;
+        self
+    }
+
+    pub fn with_prereleases(mut self, prereleases: Prereleases) -> Self {
+        self.prereleases = prereleases;
+        self
+    }
+
+    /// Interpreter to install for outside a venv
+    pub fn with_interpreter(mut self, interpreter: Option<PathBuf>) -> Self {
+        self.interpreter = interpreter;
+        self
+    }
+
+    /// Also import the package in the real environment after conscripting it
+    pub fn with_import_check(mut self, verify_imports: bool) -> Self {
+        self.verify_imports = verify_imports;
+        self
+    }
+
     pub async fn handle_package(&mut self, package: &str, version: Option<&str>, test_script: Option<&Path>) -> Result<bool> {
-        println!("{}", blue(format!("🐍 Starting Smart Snakepit handling for {}", package)));
+        logger::info(blue(format!("🐍 Starting Smart Snakepit handling for {}", package)));
 
         // Start Charmer Task in Parallel
         let package_name = package.to_string();
         let charmer_handle = tokio::spawn(async move {
+            // Only a hint for the tests; not worth the battery or bandwidth in eco mode
+            if crate::eco::is_active() {
+                logger::note("🐍 CHARMER: Deferred in eco mode");
+                return Err(anyhow::anyhow!("Charmer deferred in eco mode"));
+            }
             if let Ok(charmer) = SnakeCharmer::new() {
-                println!("{}", magenta("🐍 CHARMER: Consulting the oracles (PyPI + Gemini)..."));
+                logger::info(magenta("🐍 CHARMER: Consulting the oracles (PyPI + Gemini)..."));
                 charmer.charm_package(&package_name).await
             } else {
                 Err(anyhow::anyhow!("Charmer not available"))
@@ -59,7 +115,8 @@ impl SnakepitHandler {
         });
 
         // Phase 1: Ingest
-        let mut meta = self.ingest(package, version).await?;
+        // A user-supplied script may shell out to pip, so only import checks get the fast path
+        let mut meta = self.ingest(package, version, test_script.is_none()).await?;
         
         if meta.status == PackageStatus::Failed {
             self.kill_destroy(&meta).await?;
@@ -82,7 +139,7 @@ impl SnakepitHandler {
         Ok(installed)
     }
 
-    async fn ingest(&mut self, package: &str, version: Option<&str>) -> Result<PackageMetadata> {
+    async fn ingest(&mut self, package: &str, version: Option<&str>, allow_lightweight: bool) -> Result<PackageMetadata> {
         let sandbox_id = id::new();
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
@@ -94,11 +151,34 @@ impl SnakepitHandler {
             test_time: None,
             install_time: None,
             sandbox_id: sandbox_id.clone(),
+            sandbox_mode: SandboxMode::Full,
             error_log: Vec::new(),
             success_log: Vec::new(),
         };
 
-        println!("{}", cyan(format!("🐍 INGEST: Starting ingestion of {}", package)));
+        logger::info(cyan(format!("🐍 INGEST: Starting ingestion of {}", package)));
+
+        if allow_lightweight {
+            let sandbox = VenvSandbox::with_mode(&sandbox_id, SandboxMode::Lightweight);
+            let attempt = match sandbox.create().await {
+                Ok(_) => sandbox.install_package(package, version).await,
+                Err(e) => Err(e),
+            };
+
+            match attempt {
+                Ok(_) => {
+                    meta.status = PackageStatus::Testing;
+                    meta.sandbox_mode = SandboxMode::Lightweight;
+                    meta.success_log.push("Lightweight sandbox created and package installed".to_string());
+                    logger::success(format!("✅ INGEST: Successfully ingested {}", package));
+                    return Ok(meta);
+                }
+                Err(e) => {
+                    logger::note(format!("⚡ INGEST: Lightweight sandbox unavailable ({}), using full venv", e));
+                    let _ = sandbox.destroy().await;
+                }
+            }
+        }
 
         let sandbox = VenvSandbox::new(&sandbox_id);
         
@@ -108,19 +188,19 @@ impl SnakepitHandler {
                     Ok(_) => {
                         meta.status = PackageStatus::Testing;
                         meta.success_log.push("Sandbox created and package installed".to_string());
-                        println!("{}", green(format!("✅ INGEST: Successfully ingested {}", package)));
+                        logger::success(format!("✅ INGEST: Successfully ingested {}", package));
                     }
                     Err(e) => {
                         meta.status = PackageStatus::Failed;
                         meta.error_log.push(format!("Failed to install package: {}", e));
-                        println!("{}", red(format!("❌ INGEST: Failed to install {}", package)));
+                        logger::error(format!("❌ INGEST: Failed to install {}", package));
                     }
                 }
             }
             Err(e) => {
                 meta.status = PackageStatus::Failed;
                 meta.error_log.push(format!("Failed to create sandbox: {}", e));
-                println!("{}", red(format!("❌ INGEST: Failed to create sandbox: {}", e)));
+                logger::error(format!("❌ INGEST: Failed to create sandbox: {}", e));
             }
         }
 
@@ -133,11 +213,11 @@ impl SnakepitHandler {
         test_script: Option<&Path>,
         charmer_handle: tokio::task::JoinHandle<Result<snakegg::charmer::TestStrategy>>
     ) -> Result<bool> {
-        println!("{}", cyan(format!("🧪 TEST/COLLABORATE: Validating {}", meta.name)));
+        logger::info(cyan(format!("🧪 TEST/COLLABORATE: Validating {}", meta.name)));
         meta.status = PackageStatus::Collaborating;
         meta.test_time = Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
 
-        let sandbox = VenvSandbox::new(&meta.sandbox_id);
+        let sandbox = VenvSandbox::with_mode(&meta.sandbox_id, meta.sandbox_mode);
         
         // Check if user provided a script
         if let Some(path) = test_script {
@@ -153,31 +233,31 @@ impl SnakepitHandler {
 
         match strategy {
             Some(snakegg::charmer::TestStrategy::SimpleCommand(cmd)) => {
-                println!("{}", magenta(format!("🐍 CHARMER: Suggests running command: {}", cmd)));
+                logger::info(magenta(format!("🐍 CHARMER: Suggests running command: {}", cmd)));
                 let args: Vec<&str> = cmd.split_whitespace().collect();
                 match sandbox.run_command(&args).await {
                     Ok((success, stdout, stderr)) => {
                         self.handle_validation_result(success, stdout, stderr, meta)
                     }
                     Err(e) => {
-                        println!("{}", red(format!("❌ Validation error: {}", e)));
+                        logger::error(format!("❌ Validation error: {}", e));
                         Ok(false)
                     }
                 }
             }
             Some(snakegg::charmer::TestStrategy::PythonScript(code)) => {
-                println!("{}", magenta("🐍 CHARMER: Generated a custom Python test script."));
+                logger::info(magenta("🐍 CHARMER: Generated a custom Python test script."));
                 let test_path = sandbox.get_path().join("test_script.py");
                 std::fs::write(&test_path, code)?;
                 if self.run_validation_script(&sandbox, &test_path, meta).await? {
                     Ok(true)
                 } else {
-                    println!("{}", yellow("⚠️  Charmer script failed. Attempting Smart Inspection fallback..."));
+                    logger::warn("⚠️  Charmer script failed. Attempting Smart Inspection fallback...");
                     self.run_smart_inspection(&sandbox, meta).await
                 }
             }
             None => {
-                println!("{}", yellow("🐍 CHARMER: Silent. Using smart inspection fallback."));
+                logger::warn("🐍 CHARMER: Silent. Using smart inspection fallback.");
                 self.run_smart_inspection(&sandbox, meta).await
             }
         }
@@ -185,9 +265,9 @@ impl SnakepitHandler {
 
     async fn run_smart_inspection(&self, sandbox: &VenvSandbox, meta: &mut PackageMetadata) -> Result<bool> {
         let module_name = sandbox.find_installed_module(&meta.name).await
-            .unwrap_or_else(|_| meta.name.replace("-", "_"));
+            .unwrap_or_else(|_| canonical_name(&meta.name).replace('-', "_"));
         
-        println!("{}", dim(format!("🔍 SMART INSPECT: Detected module name: {}", module_name)));
+        logger::note(format!("🔍 SMART INSPECT: Detected module name: {}", module_name));
 
         let default_test = format!(
             r#"
@@ -217,7 +297,7 @@ except Exception as e:
             Err(e) => {
                 meta.status = PackageStatus::Failed;
                 meta.error_log.push(format!("Test execution error: {}", e));
-                println!("{}", red(format!("❌ TEST/COLLABORATE: Error testing {}: {}", meta.name, e)));
+                logger::error(format!("❌ TEST/COLLABORATE: Error testing {}: {}", meta.name, e));
                 Ok(false)
             }
         }
@@ -227,24 +307,19 @@ except Exception as e:
         if success {
             meta.status = PackageStatus::Approved;
             meta.success_log.push("Validation passed".to_string());
-            println!("{}", green(format!("✅ TEST/COLLABORATE: {} approved for installation", meta.name)));
-            if !stdout.is_empty() { println!("   {}", stdout.trim()); }
+            logger::success(format!("✅ TEST/COLLABORATE: {} approved for installation", meta.name));
+            if !stdout.is_empty() { logger::info(format!("   {}", stdout.trim())); }
             Ok(true)
         } else {
             meta.status = PackageStatus::Failed;
             meta.error_log.push(format!("Validation failed: {}", stderr));
-            println!("{}", red(format!("❌ TEST/COLLABORATE: {} failed validation", meta.name)));
-            if !stderr.is_empty() { println!("   {}", stderr.trim()); }
+            logger::error(format!("❌ TEST/COLLABORATE: {} failed validation", meta.name));
+            if !stderr.is_empty() { logger::info(format!("   {}", stderr.trim())); }
             
             // Manual Verification Prompt
-            println!("{}", yellow("\n⚠️  Automated testing failed, but the package installed successfully."));
-            println!("{}", bold("Do you want to manually verify/force install this package? [y/N]"));
-            
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
-            
-            if input.trim().to_lowercase() == "y" {
-                println!("{}", green(format!("🛡️  Manual Override: Approving {}", meta.name)));
+            logger::warn("\n⚠️  Automated testing failed, but the package installed successfully.");
+            if crate::prompt::confirm("Do you want to manually verify/force install this package?", false)? {
+                logger::success(format!("🛡️  Manual Override: Approving {}", meta.name));
                 meta.status = PackageStatus::Approved;
                 meta.success_log.push("Manually approved by user".to_string());
                 Ok(true)
@@ -255,30 +330,36 @@ except Exception as e:
     }
 
     async fn conscript_install(&mut self, meta: &mut PackageMetadata) -> Result<bool> {
-        println!("{}", cyan(format!("⚔️ CONSCRIPT: Installing {}", meta.name)));
+        logger::info(cyan(format!("⚔️ CONSCRIPT: Installing {}", meta.name)));
         meta.install_time = Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
 
-        let installer = PackageInstaller::new();
+        let installer = PackageInstaller::new()
+            .with_events(self.events.clone())
+            .with_upstream(self.upstream.clone())
+            .with_constraint_files(self.constraint_files.clone())
+            .with_prereleases(self.prereleases.clone())
+            .with_interpreter(self.interpreter.clone())
+            .with_import_check(self.verify_imports);
         
         match installer.install_package(&meta.name, meta.version.as_deref()).await {
             Ok(_) => {
                 meta.status = PackageStatus::Conscripted;
                 meta.success_log.push("Package installed locally".to_string());
-                println!("{}", green(format!("✅ CONSCRIPT: Successfully installed {}", meta.name)));
+                logger::success(format!("✅ CONSCRIPT: Successfully installed {}", meta.name));
                 Ok(true)
             }
             Err(e) => {
                 meta.status = PackageStatus::Failed;
                 meta.error_log.push(format!("Installation failed: {}", e));
-                println!("{}", red(format!("❌ CONSCRIPT: Failed to install {}: {}", meta.name, e)));
+                logger::error(format!("❌ CONSCRIPT: Failed to install {}: {}", meta.name, e));
                 Ok(false)
             }
         }
     }
 
     async fn kill_destroy(&mut self, meta: &PackageMetadata) -> Result<()> {
-        println!("{}", dim(format!("💀 KILL/DESTROY: Cleaning up {}", meta.name)));
-        let sandbox = VenvSandbox::new(&meta.sandbox_id);
+        logger::note(format!("💀 KILL/DESTROY: Cleaning up {}", meta.name));
+        let sandbox = VenvSandbox::with_mode(&meta.sandbox_id, meta.sandbox_mode);
         sandbox.destroy().await?;
         Ok(())
     }
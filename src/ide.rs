@@ -0,0 +1,227 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use crate::logger;
+
+lazy_static! {
+    static ref ROOT_MANAGER: Regex = Regex::new(r#"<component\s+name="ProjectRootManager"[^>]*?(/>|>[\s\S]*?</component>)"#).unwrap();
+    static ref JDK_NAME: Regex = Regex::new(r#"project-jdk-name="[^"]*""#).unwrap();
+}
+
+/// `[tool.snakepit.ide]` in pyproject.toml: which editor settings to keep pointed at the environment
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct IdeSettings {
+    /// `.vscode/settings.json` python.defaultInterpreterPath
+    pub vscode: bool,
+    /// `.idea/misc.xml` project interpreter
+    pub pycharm: bool,
+    /// `.python-version` for pyenv and friends
+    pub python_version_file: bool,
+}
+
+impl IdeSettings {
+    /// Settings from `<root>/pyproject.toml`; `None` when the project hasn't opted in
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let pyproject = root.join("pyproject.toml");
+        if !pyproject.exists() {
+            return Ok(None);
+        }
+        let toml: toml::Value = toml::from_str(&std::fs::read_to_string(pyproject)?)?;
+        match toml.get("tool").and_then(|t| t.get("snakepit")).and_then(|s| s.get("ide")) {
+            Some(table) => Ok(Some(table.clone().try_into()?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Writes editor configuration so every team member's IDE uses the project environment
+pub struct IdeHooks {
+    root: PathBuf,
+    settings: IdeSettings,
+}
+
+impl IdeHooks {
+    pub fn new(root: PathBuf, settings: IdeSettings) -> Self {
+        Self { root, settings }
+    }
+
+    /// Refresh editor settings for the project in `root`, if it opted in; never fails the caller
+    pub fn refresh(root: &Path, environment: &Path) {
+        let settings = match IdeSettings::load(root) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => return,
+            Err(e) => {
+                logger::warn(format!("⚠️  Ignoring invalid [tool.snakepit.ide]: {}", e));
+                return;
+            }
+        };
+
+        match Self::new(root.to_path_buf(), settings).apply(environment) {
+            Ok(updated) => {
+                for file in updated {
+                    logger::note(format!("🧩 Updated {}", file.display()));
+                }
+            }
+            Err(e) => logger::warn(format!("⚠️  Could not update editor settings: {}", e)),
+        }
+    }
+
+    /// Point the enabled editors at `environment`, returning the files that changed
+    pub fn apply(&self, environment: &Path) -> Result<Vec<PathBuf>> {
+        let interpreter = Self::interpreter_path(environment);
+        let python_version = Self::environment_python_version(environment);
+        let mut updated = Vec::new();
+
+        if self.settings.vscode {
+            let path = self.root.join(".vscode").join("settings.json");
+            let existing = std::fs::read_to_string(&path).ok();
+            if let Some(content) = Self::vscode_settings(existing.as_deref(), &interpreter)? {
+                write_file(&path, &content)?;
+                updated.push(path);
+            }
+        }
+
+        if self.settings.pycharm {
+            let path = self.root.join(".idea").join("misc.xml");
+            let existing = std::fs::read_to_string(&path).ok();
+            let env_name = environment.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let sdk_name = format!("Python {} ({})", python_version.as_deref().unwrap_or("3"), env_name);
+            let content = Self::pycharm_misc(existing.as_deref(), &sdk_name);
+            if existing.as_deref() != Some(content.as_str()) {
+                write_file(&path, &content)?;
+                updated.push(path);
+            }
+        }
+
+        if self.settings.python_version_file {
+            if let Some(version) = &python_version {
+                let path = self.root.join(".python-version");
+                let content = format!("{}\n", version);
+                if std::fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+                    write_file(&path, &content)?;
+                    updated.push(path);
+                }
+            }
+        }
+
+        Ok(updated)
+    }
+
+    fn interpreter_path(environment: &Path) -> PathBuf {
+        if cfg!(target_os = "windows") {
+            environment.join("Scripts").join("python.exe")
+        } else {
+            environment.join("bin").join("python")
+        }
+    }
+
+    /// `major.minor` of the environment's interpreter, from pyvenv.cfg
+    pub(crate) fn environment_python_version(environment: &Path) -> Option<String> {
+        let cfg = std::fs::read_to_string(environment.join("pyvenv.cfg")).ok()?;
+        let version = cfg.lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(key, _)| matches!(key.trim(), "version" | "version_info"))
+            .map(|(_, value)| value.trim().to_string())?;
+        let mut parts = version.split('.');
+        Some(format!("{}.{}", parts.next()?, parts.next()?))
+    }
+
+    /// Merge the interpreter into existing settings; `None` when nothing would change.
+    ///
+    /// Settings files with comments aren't valid JSON; they're left alone rather than clobbered.
+    fn vscode_settings(existing: Option<&str>, interpreter: &Path) -> Result<Option<String>> {
+        let mut settings: serde_json::Map<String, serde_json::Value> = match existing {
+            Some(content) if !content.trim().is_empty() => serde_json::from_str(content)
+                .map_err(|e| anyhow::anyhow!(".vscode/settings.json isn't plain JSON ({}); set python.defaultInterpreterPath by hand", e))?,
+            _ => serde_json::Map::new(),
+        };
+
+        let value = serde_json::Value::String(interpreter.to_string_lossy().to_string());
+        if settings.get("python.defaultInterpreterPath") == Some(&value) {
+            return Ok(None);
+        }
+        settings.insert("python.defaultInterpreterPath".to_string(), value);
+        Ok(Some(format!("{}\n", serde_json::to_string_pretty(&settings)?)))
+    }
+
+    fn pycharm_misc(existing: Option<&str>, sdk_name: &str) -> String {
+        let component = format!(
+            r#"<component name="ProjectRootManager" version="2" project-jdk-name="{}" project-jdk-type="Python SDK" />"#,
+            sdk_name
+        );
+
+        match existing {
+            Some(content) => match ROOT_MANAGER.find(content) {
+                Some(found) => {
+                    let current = found.as_str();
+                    let replaced = if JDK_NAME.is_match(current) {
+                        JDK_NAME.replace(current, format!(r#"project-jdk-name="{}""#, sdk_name)).to_string()
+                    } else {
+                        component.clone()
+                    };
+                    content.replacen(current, &replaced, 1)
+                }
+                None => content.replacen("</project>", &format!("  {}\n</project>", component), 1),
+            },
+            None => format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<project version=\"4\">\n  {}\n</project>\n", component),
+        }
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vscode_settings_are_merged() {
+        let interpreter = Path::new("/work/.venv/bin/python");
+        let merged = IdeHooks::vscode_settings(Some(r#"{"editor.formatOnSave": true}"#), interpreter).unwrap().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(json["editor.formatOnSave"], true);
+        assert_eq!(json["python.defaultInterpreterPath"], "/work/.venv/bin/python");
+
+        assert!(IdeHooks::vscode_settings(Some(&merged), interpreter).unwrap().is_none());
+        assert!(IdeHooks::vscode_settings(Some("// comment\n{}"), interpreter).is_err());
+    }
+
+    #[test]
+    fn test_pycharm_misc_keeps_other_components() {
+        let existing = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project version="4">
+  <component name="Black"><option name="sdkName" value="old" /></component>
+  <component name="ProjectRootManager" version="2" project-jdk-name="Python 3.9" project-jdk-type="Python SDK" />
+</project>
+"#;
+        let updated = IdeHooks::pycharm_misc(Some(existing), "Python 3.11 (.venv)");
+        assert!(updated.contains(r#"project-jdk-name="Python 3.11 (.venv)""#));
+        assert!(updated.contains(r#"<component name="Black">"#));
+
+        let created = IdeHooks::pycharm_misc(None, "Python 3.11 (.venv)");
+        assert!(created.contains("ProjectRootManager"));
+    }
+
+    #[test]
+    fn test_settings_require_opt_in() {
+        let dir = std::env::temp_dir().join(format!("snakepit-ide-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("pyproject.toml"), "[project]\nname = \"demo\"\n").unwrap();
+        assert_eq!(IdeSettings::load(&dir).unwrap(), None);
+
+        std::fs::write(dir.join("pyproject.toml"), "[tool.snakepit.ide]\nvscode = true\npython-version-file = true\n").unwrap();
+        let settings = IdeSettings::load(&dir).unwrap().unwrap();
+        assert!(settings.vscode && settings.python_version_file && !settings.pycharm);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use snakegg::native::dirs;
+use tokio::sync::mpsc;
+
+/// Import name of the hook inside monitored environments
+pub const HOOK_MODULE: &str = "snakepit_import_hook";
+
+/// A `.pth` hook rather than sitecustomize.py, so an environment's own sitecustomize keeps working
+const PTH_FILE: &str = "zz_snakepit_imports.pth";
+
+const PTH_HOOK: &str = "import snakepit_import_hook\n";
+
+const HOOK_SOURCE: &str = r#""""snakepit import hook.
+
+Reports uncaught ModuleNotFoundErrors to the snakepit daemon so it can install
+the missing package. Managed by the daemon (`import_hooks` in daemon.toml);
+edits are overwritten.
+"""
+import json
+import os
+import socket
+import sys
+import threading
+
+_SOCKET = __SOCKET__
+
+
+def _report(error):
+    name = getattr(error, "name", None)
+    if not name or not hasattr(socket, "AF_UNIX"):
+        return
+    try:
+        message = {
+            "module": name.split(".")[0],
+            "python": sys.executable,
+            "pid": os.getpid(),
+            "cwd": os.getcwd(),
+            "environment": sys.prefix,
+        }
+        client = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        client.settimeout(0.5)
+        client.connect(_SOCKET)
+        client.sendall((json.dumps(message) + "\n").encode("utf-8"))
+        client.close()
+    except Exception:
+        pass
+
+
+def _wrap(previous):
+    def hook(kind, value, traceback):
+        if isinstance(value, ModuleNotFoundError):
+            _report(value)
+        previous(kind, value, traceback)
+    return hook
+
+
+def _wrap_thread(previous):
+    def hook(args):
+        if isinstance(args.exc_value, ModuleNotFoundError):
+            _report(args.exc_value)
+        previous(args)
+    return hook
+
+
+sys.excepthook = _wrap(sys.excepthook)
+threading.excepthook = _wrap_thread(threading.excepthook)
+"#;
+
+/// What the hook sends the daemon when a process dies on a missing import
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Top-level module, e.g. `google` for `google.protobuf`
+    pub module: String,
+    pub python: Option<String>,
+    pub pid: u32,
+    pub cwd: PathBuf,
+    /// `sys.prefix`: the virtualenv, or the base installation
+    pub environment: PathBuf,
+}
+
+/// Installs and removes the import hook in one environment's site-packages
+pub struct ImportHook {
+    site_packages: PathBuf,
+}
+
+impl ImportHook {
+    pub fn new(site_packages: PathBuf) -> Self {
+        Self { site_packages }
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.site_packages.join(PTH_FILE).exists()
+    }
+
+    /// Write the hook, reporting to `socket`, and its startup line
+    pub fn install(&self, socket: &Path) -> Result<()> {
+        // A JSON string is a valid Python string literal
+        let source = HOOK_SOURCE.replace("__SOCKET__", &serde_json::to_string(socket)?);
+        std::fs::write(self.site_packages.join(format!("{}.py", HOOK_MODULE)), source)?;
+        std::fs::write(self.site_packages.join(PTH_FILE), PTH_HOOK)?;
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        for file in [PTH_FILE.to_string(), format!("{}.py", HOOK_MODULE)] {
+            let path = self.site_packages.join(file);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Socket import hooks report to
+pub fn socket_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data_dir.join("snakepit").join("imports.sock"))
+}
+
+/// Accept hook reports on `path` and pass them on to `reports`
+#[cfg(unix)]
+pub fn listen(path: &Path, reports: mpsc::UnboundedSender<ImportReport>) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let _ = std::fs::remove_file(path);
+    let listener = tokio::net::UnixListener::bind(path)?;
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let reports = reports.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stream).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let Ok(report) = serde_json::from_str::<ImportReport>(&line) {
+                        let _ = reports.send(report);
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen(_path: &Path, _reports: mpsc::UnboundedSender<ImportReport>) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hook_reports_uncaught_missing_import() {
+        let root = std::env::temp_dir().join(format!("snakepit-import-hook-{}", std::process::id()));
+        let site = root.join("site-packages");
+        std::fs::create_dir_all(&site).unwrap();
+        let socket = root.join("imports.sock");
+
+        let (sender, mut reports) = mpsc::unbounded_channel();
+        listen(&socket, sender).unwrap();
+        let hook = ImportHook::new(site.clone());
+        hook.install(&socket).unwrap();
+        assert!(hook.is_installed());
+
+        // Exercise the Python side when an interpreter is around
+        let output = tokio::process::Command::new("python3")
+            .args(["-c", &format!("import site; site.addsitedir({:?}); import yaml_but_missing.sub", site.to_string_lossy())])
+            .current_dir(&root)
+            .output()
+            .await;
+        if output.is_ok_and(|output| String::from_utf8_lossy(&output.stderr).contains("ModuleNotFoundError")) {
+            let report = tokio::time::timeout(std::time::Duration::from_secs(5), reports.recv()).await.unwrap().unwrap();
+            assert_eq!(report.module, "yaml_but_missing");
+            assert_eq!(report.cwd.canonicalize().unwrap(), root.canonicalize().unwrap());
+        }
+
+        hook.remove().unwrap();
+        assert!(!hook.is_installed());
+        assert!(!site.join(format!("{}.py", HOOK_MODULE)).exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
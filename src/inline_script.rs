@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Written into a script environment once its dependencies are installed; one without it
+/// was interrupted mid-build and is rebuilt
+pub const READY_MARKER: &str = ".snakepit-script-ready";
+
+/// The `# /// script` block of a single-file script (PEP 723)
+///
+/// ```python
+/// # /// script
+/// # requires-python = ">=3.11"
+/// # dependencies = ["requests<3", "rich"]
+/// # ///
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScriptMetadata {
+    #[serde(default)]
+    pub requires_python: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl ScriptMetadata {
+    /// The script's metadata; `None` when it has no `script` block
+    pub fn parse(source: &str) -> Result<Option<Self>> {
+        let mut found: Option<String> = None;
+        let mut block: Option<(String, String)> = None;
+        for line in source.lines() {
+            let line = line.trim_end_matches('\r');
+            match &mut block {
+                None => {
+                    if let Some(kind) = line.strip_prefix("# /// ").filter(|kind| is_block_type(kind)) {
+                        block = Some((kind.to_string(), String::new()));
+                    }
+                }
+                Some((kind, content)) => {
+                    if line == "# ///" {
+                        if kind == "script" {
+                            if found.is_some() {
+                                return Err(anyhow!("More than one `# /// script` block"));
+                            }
+                            found = Some(std::mem::take(content));
+                        }
+                        block = None;
+                    } else if let Some(text) = line.strip_prefix("# ").or_else(|| (line == "#").then_some("")) {
+                        content.push_str(text);
+                        content.push('\n');
+                    } else {
+                        // Not a comment line: the block never closed, so it wasn't one
+                        block = None;
+                    }
+                }
+            }
+        }
+        if matches!(&block, Some((kind, _)) if kind == "script") {
+            return Err(anyhow!("Unclosed `# /// script` block (end it with `# ///`)"));
+        }
+        found.map(|toml| toml::from_str(&toml).map_err(|e| anyhow!("Invalid `# /// script` metadata: {}", e)))
+            .transpose()
+    }
+
+    /// Read `path`'s metadata; `None` when it has none
+    pub fn from_file(path: &Path) -> Result<Option<Self>> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        Self::parse(&source)
+    }
+
+    /// The cached environment for these requirements under `cache_dir`, shared by every
+    /// script that asks for the same ones. `python` tells apart environments built for
+    /// different interpreters.
+    pub fn environment_dir(&self, cache_dir: &Path, python: &str) -> PathBuf {
+        let mut dependencies: Vec<String> = self.dependencies.iter().map(|dep| dep.trim().to_string()).collect();
+        dependencies.sort();
+        dependencies.dedup();
+        let key = format!("{}\n{}\n{}", python, self.requires_python.as_deref().unwrap_or_default(), dependencies.join("\n"));
+        let hash = snakegg::native::hash::compute_sha256_hex(key.as_bytes());
+        cache_dir.join("scripts").join(&hash[..16])
+    }
+}
+
+/// PEP 723's `TYPE` of `# /// TYPE`: letters, digits and dashes
+fn is_block_type(kind: &str) -> bool {
+    !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_script_block() {
+        let source = "#!/usr/bin/env python3\n\
+            # /// pyproject\n# [tool.other]\n# ///\n\
+            # /// script\n# requires-python = \">=3.11\"\n# dependencies = [\n#   \"requests<3\",\n#   \"rich\",\n# ]\n#\n# ///\n\
+            import requests\n";
+        let metadata = ScriptMetadata::parse(source).unwrap().unwrap();
+        assert_eq!(metadata.requires_python.as_deref(), Some(">=3.11"));
+        assert_eq!(metadata.dependencies, vec!["requests<3", "rich"]);
+
+        assert_eq!(ScriptMetadata::parse("import sys\n# just a comment\n").unwrap(), None);
+        assert!(ScriptMetadata::parse("# /// script\n# dependencies = []\n").is_err());
+        assert!(ScriptMetadata::parse("# /// script\n# ///\n# /// script\n# ///\n").is_err());
+    }
+
+    #[test]
+    fn test_environment_shared_by_equal_requirements() {
+        let cache = Path::new("/cache");
+        let deps = |list: &[&str]| ScriptMetadata {
+            requires_python: None,
+            dependencies: list.iter().map(|dep| dep.to_string()).collect(),
+        };
+        let one = deps(&["rich", "requests<3"]).environment_dir(cache, "3.12");
+        assert_eq!(one, deps(&["requests<3", "rich"]).environment_dir(cache, "3.12"));
+        assert_ne!(one, deps(&["requests<3"]).environment_dir(cache, "3.12"));
+        assert_ne!(one, deps(&["rich", "requests<3"]).environment_dir(cache, "3.11"));
+        assert!(one.starts_with("/cache/scripts"));
+    }
+}
@@ -1,31 +1,29 @@
 use crate::resolver::ResolvedDependency;
+use crate::renderer::{EventBus, InstallEvent};
+use crate::rate_limit::{self, Subsystem};
+use crate::upstream::Upstream;
+use crate::provenance::ProvenanceRegistry;
+use crate::cache::CacheValidators;
+use crate::staging::StagedInstall;
+use crate::archive::{self, ArchiveEntry, EntryKind, Layout};
+use crate::prerelease::{self, Prereleases};
+use crate::logger;
 use anyhow::Result;
-use std::process::{Command, Stdio};
+use std::collections::HashMap;
+use std::process::Command;
 use snakegg::native::progress::ProgressBar;
-use snakegg::native::style::{red, green, yellow, blue, cyan, bold, dim};
-
-#[derive(Debug, Clone)]
-pub enum InstallerBackend {
-    Native,
-    Uv,
-    Pip,
-    Conda,
-    Poetry,
-}
+use snakegg::native::style::{red, green, yellow, cyan};
 
-impl InstallerBackend {
-    pub fn detect() -> Self {
-        // Snakepit Native is always available and preferred!
-        Self::Native
-    }
+pub use crate::backend::InstallerBackend;
 
-    fn command_exists(command: &str) -> bool {
-        Command::new(command)
-            .arg("--version")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .is_ok()
+/// A hashed requirements file written for pip/uv, removed when dropped
+struct RequirementsFile(Option<std::path::PathBuf>);
+
+impl Drop for RequirementsFile {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
     }
 }
 
@@ -34,17 +32,79 @@ pub struct PackageInstaller {
     backend: InstallerBackend,
     venv_path: Option<String>,
     use_cache: bool,
+    /// When set, every package must match one of these sha256 digests (keyed by normalized name)
+    required_hashes: Option<HashMap<String, Vec<String>>>,
+    events: EventBus,
+    upstream: Upstream,
+    /// `-c` files handed to pip/uv so transitive installs respect them too
+    constraint_files: Vec<std::path::PathBuf>,
+    /// Import each natively installed package afterwards and roll it back if that fails
+    verify_imports: bool,
+    /// Packages whose newest pre-release may be picked (and `--pre` for pip/uv)
+    prereleases: Prereleases,
+    /// Interpreter to install for when there's no venv, instead of the first `python3` on PATH
+    interpreter: Option<std::path::PathBuf>,
+    /// Conda channels to search, in priority order, besides the configured ones
+    channels: Vec<String>,
 }
 
 impl PackageInstaller {
     pub fn new() -> Self {
         Self {
             backend: InstallerBackend::detect(),
-            venv_path: None,
+            // The active venv or the project's .venv, unless --venv/--system said otherwise
+            venv_path: crate::registry::ProjectRegistry::current_environment().map(|venv| venv.to_string_lossy().to_string()),
             use_cache: true,
+            required_hashes: None,
+            events: EventBus::new(),
+            upstream: Upstream::new().with_subsystem(Subsystem::Installer),
+            constraint_files: Vec::new(),
+            verify_imports: false,
+            prereleases: Prereleases::new(),
+            interpreter: None,
+            channels: Vec::new(),
         }
     }
 
+    pub fn with_import_check(mut self, verify_imports: bool) -> Self {
+        self.verify_imports = verify_imports;
+        self
+    }
+
+    pub fn with_constraint_files(mut self, files: Vec<std::path::PathBuf>) -> Self {
+        self.constraint_files = files;
+        self
+    }
+
+    /// Package index, plus the mirrors and retry budget used when PyPI is throttling or down
+    pub fn with_prereleases(mut self, prereleases: Prereleases) -> Self {
+        self.prereleases = prereleases;
+        self
+    }
+
+    pub fn with_upstream(mut self, upstream: Upstream) -> Self {
+        self.upstream = upstream.with_subsystem(Subsystem::Installer);
+        self
+    }
+
+    /// Publish install progress to the renderers on `events`
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Enable `--require-hashes` mode: refuse any artifact whose sha256 isn't listed for its package
+    pub fn with_required_hashes(mut self, hashes: HashMap<String, Vec<String>>) -> Self {
+        let normalized = hashes.into_iter()
+            .map(|(name, digests)| {
+                let digests = digests.iter().filter_map(|d| Self::normalize_sha256(d)).collect();
+                (Self::normalize_hash_key(&name), digests)
+            })
+            .collect();
+        self.required_hashes = Some(normalized);
+        self
+    }
+
     pub fn with_backend(mut self, backend: InstallerBackend) -> Self {
         self.backend = backend;
         self
@@ -55,23 +115,55 @@ impl PackageInstaller {
         self
     }
 
+    /// The interpreter whose user site packages go to when there's no venv
+    pub fn with_interpreter(mut self, interpreter: Option<std::path::PathBuf>) -> Self {
+        self.interpreter = interpreter;
+        self
+    }
+
+    /// The venv's interpreter, else the chosen one, else `python3`
+    fn python_executable(&self) -> std::path::PathBuf {
+        match (&self.venv_path, &self.interpreter) {
+            (Some(venv), _) if cfg!(target_os = "windows") => std::path::Path::new(venv).join("Scripts").join("python.exe"),
+            (Some(venv), _) => std::path::Path::new(venv).join("bin").join("python"),
+            (None, Some(interpreter)) => interpreter.clone(),
+            (None, None) => std::path::PathBuf::from("python3"),
+        }
+    }
+
+    /// Channels the conda backend installs from, e.g. an environment.yml's `channels:`
+    pub fn with_channels(mut self, channels: Vec<String>) -> Self {
+        self.channels = channels;
+        self
+    }
+
     pub fn with_cache(mut self, use_cache: bool) -> Self {
         self.use_cache = use_cache;
         self
     }
 
     pub async fn install_package(&self, package: &str, version: Option<&str>) -> Result<()> {
+        if self.required_hashes.is_some() {
+            self.ensure_hash_pinned(package, version)?;
+        }
+
         let mut pb = ProgressBar::new_spinner();
         pb.set_message(format!("Installing {}...", package));
-
-        let result = match self.backend {
-            InstallerBackend::Native => self.install_with_native(package, version).await,
-            InstallerBackend::Uv => self.install_with_uv(package, version).await,
-            InstallerBackend::Pip => self.install_with_pip(package, version).await,
-            InstallerBackend::Conda => self.install_with_conda(package, version).await,
-            InstallerBackend::Poetry => self.install_with_poetry(package, version).await,
+        self.events.emit(InstallEvent::PackageQueued {
+            name: package.to_string(),
+            version: version.map(|v| v.to_string()),
+        });
+
+        let result = match self.backend.install(self, package, version).await {
+            Ok(()) if self.verify_imports && self.backend.writes_files_directly() => self.verify_import(package).await,
+            result => result,
         };
 
+        self.events.emit(match &result {
+            Ok(_) => InstallEvent::InstallComplete { name: package.to_string() },
+            Err(e) => InstallEvent::InstallFailed { name: package.to_string(), error: e.to_string() },
+        });
+
         pb.finish_with_message(&format!("{} {}", 
             green("✓"), 
             green(format!("Installed {}", package))
@@ -85,7 +177,7 @@ impl PackageInstaller {
             return Ok(());
         }
 
-        println!("{}", cyan(format!("🚀 Installing {} packages in parallel...", dependencies.len())));
+        logger::info(cyan(format!("🚀 Installing {} packages in parallel...", dependencies.len())));
 
         let mut pb = ProgressBar::new(dependencies.len() as u64);
         // Native progress bar has default styling
@@ -100,12 +192,28 @@ impl PackageInstaller {
             let backend = self.backend.clone();
             let venv_path = self.venv_path.clone();
             let use_cache = self.use_cache;
+            let required_hashes = self.required_hashes.clone();
+            let events = self.events.clone();
+            let upstream = self.upstream.clone();
+            let constraint_files = self.constraint_files.clone();
+            let prereleases = self.prereleases.clone();
+            let interpreter = self.interpreter.clone();
+            let channels = self.channels.clone();
             
             let handle = tokio::spawn(async move {
                 let installer = PackageInstaller {
                     backend,
                     venv_path,
                     use_cache,
+                    required_hashes,
+                    events,
+                    upstream,
+                    constraint_files,
+                    // Siblings may still be unpacking; imports are checked once the batch is in
+                    verify_imports: false,
+                    prereleases,
+                    interpreter,
+                    channels,
                 };
                 installer.install_package(&package, Some(&version)).await
             });
@@ -132,17 +240,29 @@ impl PackageInstaller {
             }
         }
 
+        if errors.is_empty() && self.verify_imports && self.backend.writes_files_directly() {
+            for dep in dependencies {
+                if let Err(e) = self.verify_import(&dep.name).await {
+                    errors.push(format!("{}: {}", dep.name, e));
+                }
+            }
+        }
+
         let msg = if errors.is_empty() {
             green("All dependencies installed!").to_string()
         } else {
             yellow(format!("Completed with {} errors", errors.len())).to_string()
         };
         pb.finish_with_message(&msg);
+        self.events.emit(InstallEvent::AllDone {
+            succeeded: (dependencies.len() - errors.len()) as u32,
+            failed: errors.len() as u32,
+        });
 
         if !errors.is_empty() {
-            eprintln!("{}", red("Errors:"));
+            logger::error("Errors:");
             for err in &errors {
-                eprintln!("  {}", err);
+                logger::info(format!("  {}", err));
             }
             return Err(anyhow::anyhow!("Failed to install some dependencies"));
         }
@@ -150,17 +270,48 @@ impl PackageInstaller {
         Ok(())
     }
 
+    /// Install a local project or VCS checkout in development mode (`-e`).
+    ///
+    /// Editable installs need the project's build backend, which only pip and uv
+    /// can drive, so backends delegate to one of those (pip unless they say otherwise).
+    pub async fn install_editable(&self, name: &str, target: &str) -> Result<()> {
+        self.events.emit(InstallEvent::PackageQueued { name: name.to_string(), version: None });
+
+        let mut cmd = self.backend.editable_command(self);
+        if let Some(index_url) = self.upstream.index_url() {
+            cmd.arg("--index-url").arg(index_url);
+        }
+        for file in &self.constraint_files {
+            cmd.arg("-c").arg(file);
+        }
+        cmd.arg("-e").arg(target);
+
+        logger::info(cyan(format!("✏️  Installing {} in editable mode from {}", name, target)));
+        let output = cmd.output()?;
+        let result = if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Failed to install editable {}: {}", name, String::from_utf8_lossy(&output.stderr)))
+        };
+
+        self.events.emit(match &result {
+            Ok(_) => InstallEvent::InstallComplete { name: name.to_string() },
+            Err(e) => InstallEvent::InstallFailed { name: name.to_string(), error: e.to_string() },
+        });
+        result
+    }
+
     pub async fn uninstall_package(&self, package: &str) -> Result<()> {
         let mut pb = ProgressBar::new_spinner();
         pb.set_message(format!("Uninstalling {}...", package));
 
-        let result = match self.backend {
-            InstallerBackend::Native => self.uninstall_with_native(package).await,
-            InstallerBackend::Uv => self.uninstall_with_uv(package).await,
-            InstallerBackend::Pip => self.uninstall_with_pip(package).await,
-            InstallerBackend::Conda => self.uninstall_with_conda(package).await,
-            InstallerBackend::Poetry => self.uninstall_with_poetry(package).await,
-        };
+        let result = self.backend.uninstall(self, package).await;
+
+        if result.is_ok() {
+            if let Ok(site) = self.get_install_dir() {
+                let _ = ProvenanceRegistry::record_uninstall(&site, package);
+            }
+        }
 
         pb.finish_with_message(&format!("{} {}", 
             red("✓"), 
@@ -171,88 +322,116 @@ impl PackageInstaller {
     }
 
     pub async fn list_installed_packages(&self) -> Result<Vec<String>> {
-        match self.backend {
-            InstallerBackend::Native => self.list_with_native().await,
-            InstallerBackend::Uv => self.list_with_uv().await,
-            InstallerBackend::Pip => self.list_with_pip().await,
-            InstallerBackend::Conda => self.list_with_conda().await,
-            InstallerBackend::Poetry => self.list_with_poetry().await,
-        }
+        self.backend.list(self).await
     }
 
     pub async fn search_package(&self, query: &str) -> Result<Vec<String>> {
-        // For now, we'll only support PyPI search via native backend or pip
-        // uv doesn't support search yet
-        match self.backend {
-            InstallerBackend::Native | InstallerBackend::Uv | InstallerBackend::Pip => self.search_with_pip(query).await,
-            InstallerBackend::Conda => self.search_with_conda(query).await,
-            InstallerBackend::Poetry => self.search_with_poetry(query).await,
-        }
+        self.backend.search(self, query).await
     }
 
     pub async fn show_package(&self, package: &str) -> Result<String> {
-        match self.backend {
-            InstallerBackend::Native | InstallerBackend::Uv | InstallerBackend::Pip => self.show_with_pip(package).await,
-            InstallerBackend::Conda => self.show_with_conda(package).await,
-            InstallerBackend::Poetry => self.show_with_poetry(package).await,
+        self.backend.show(self, package).await
+    }
+
+    /// `uv pip install` aimed at this installer's environment
+    pub(crate) fn uv_install_command(&self) -> Command {
+        let mut cmd = Command::new("uv");
+        cmd.arg("pip").arg("install");
+        match (&self.venv_path, &self.interpreter) {
+            (Some(venv_path), _) => { cmd.env("VIRTUAL_ENV", venv_path); }
+            (None, Some(interpreter)) => { cmd.arg("--system").arg("--python").arg(interpreter); }
+            (None, None) => { cmd.arg("--system"); }
+        }
+        cmd
+    }
+
+    /// `pip install` aimed at this installer's environment
+    pub(crate) fn pip_install_command(&self) -> Command {
+        let mut cmd = Command::new("pip");
+        match (&self.venv_path, &self.interpreter) {
+            (Some(venv_path), _) => { cmd.arg("--python").arg(venv_path); }
+            (None, Some(interpreter)) => { cmd.arg("--python").arg(interpreter).arg("--user"); }
+            (None, None) => { cmd.arg("--user"); }
         }
+        cmd.arg("install");
+        cmd
     }
 
-    async fn install_with_native(&self, package: &str, version: Option<&str>) -> Result<()> {
+    pub(crate) async fn install_with_native(&self, package: &str, version: Option<&str>) -> Result<()> {
         use std::io::Cursor;
         use zip::ZipArchive;
 
+        // Extras only matter to dependency resolution; the wheel is the same
+        let (package, _extras) = crate::dependency::split_extras(package);
+        let package = package.as_str();
+
         // 1. Fetch metadata from PyPI (with caching)
         let resp = self.fetch_pypi_metadata_cached(package).await?;
         
         let releases = resp["releases"].as_object()
             .ok_or_else(|| anyhow::anyhow!("No releases found for {}", package))?;
 
-        // 2. Select version
-        let target_version = version.unwrap_or_else(|| resp["info"]["version"].as_str().unwrap_or(""));
+        // 2. Select version, skipping yanked releases unless one was pinned
+        let latest = Self::latest_unyanked_version(&resp, self.prereleases.allows(package));
+        let target_version = match version {
+            Some(v) => v,
+            None => latest.as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Every release of {} has been yanked", package))?,
+        };
         let files = releases.get(target_version)
             .ok_or_else(|| anyhow::anyhow!("Version {} not found for {}", target_version, package))?
             .as_array()
             .ok_or_else(|| anyhow::anyhow!("Invalid release data"))?;
 
+        let is_yanked = |f: &serde_json::Value| f["yanked"].as_bool().unwrap_or(false);
+        let all_yanked = !files.is_empty() && files.iter().all(is_yanked);
+        if all_yanked {
+            let reason = files.iter()
+                .find_map(|f| f["yanked_reason"].as_str().filter(|r| !r.trim().is_empty()))
+                .map(|r| format!(" (reason: {})", r))
+                .unwrap_or_default();
+            logger::warn(format!(
+                "⚠️  {} {} has been yanked{}; installing it only because it is pinned",
+                package, target_version, reason
+            ));
+        }
+
         // 3. Find a compatible wheel using robust selection
-        let selector = WheelSelector::new();
-        let wheel_url = files.iter()
-            .filter(|f| f["filename"].as_str().map_or(false, |n| n.ends_with(".whl")))
-            .max_by_key(|f| {
-                let filename = f["filename"].as_str().unwrap_or("");
-                selector.score_wheel(filename)
-            })
-            .and_then(|f| {
-                let filename = f["filename"].as_str().unwrap_or("");
-                if selector.score_wheel(filename) > 0 {
-                    f["url"].as_str()
-                } else {
-                    None
-                }
-            })
+        let wheel_url = self.select_wheel(files, all_yanked)
+            .and_then(|f| f["url"].as_str())
             .ok_or_else(|| anyhow::anyhow!("No compatible wheel found for {} (checked {} files)", package, files.len()))?;
 
         let wheel_filename = wheel_url.split('/').last().unwrap_or("unknown");
 
+        let file_info = files.iter()
+            .find(|f| f["filename"].as_str() == Some(wheel_filename));
+            
+        let sha256 = file_info.and_then(|f| f["digests"]["sha256"].as_str());
+        let md5 = file_info.and_then(|f| f["digests"]["md5"].as_str());
+
         // 4. Download wheel (with caching)
+        self.events.emit(InstallEvent::DownloadStarted {
+            name: package.to_string(),
+            total_bytes: file_info.and_then(|f| f["size"].as_u64()),
+        });
         let bytes = if self.use_cache {
-            Self::download_wheel_cached(wheel_url, wheel_filename).await?
+            Self::download_wheel_cached(wheel_url, wheel_filename, sha256).await?
         } else {
-            println!("{}", dim(format!("📦 Downloading wheel: {}", wheel_filename)));
+            logger::note(format!("📦 Downloading wheel: {}", wheel_filename));
             Self::download_wheel(wheel_url).await?
         };
+        self.events.emit(InstallEvent::DownloadFinished {
+            name: package.to_string(),
+            bytes: bytes.len() as u64,
+        });
         
         // 4.5. Verify wheel integrity (prefer SHA256, fallback to MD5)
-        let file_info = files.iter()
-            .find(|f| f["filename"].as_str() == Some(wheel_filename));
-            
-        let sha256 = file_info.and_then(|f| f["digests"]["sha256"].as_str());
-        let md5 = file_info.and_then(|f| f["digests"]["md5"].as_str());
-        
         if sha256.is_some() || md5.is_some() {
             Self::verify_wheel_integrity(&bytes, sha256, md5)?;
         }
+
+        // 4.6. In --require-hashes mode the user's pins are authoritative, not PyPI's digests
+        self.verify_required_hash(package, &bytes)?;
         
         // 5. Determine install location
         let install_dir = self.get_install_dir()?;
@@ -264,7 +443,7 @@ impl PackageInstaller {
         match std::fs::create_dir_all(&install_dir) {
             Ok(_) => {},
             Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
-                println!("{}", yellow("⚠️  Permission denied, trying user install..."));
+                logger::warn("⚠️  Permission denied, trying user install...");
                 // For permission errors, we'll just fail gracefully for now
                 // Full user-site fallback would require refactoring the installer
                 return Err(anyhow::anyhow!("Permission denied. Try running with sudo or use --user flag"));
@@ -272,12 +451,98 @@ impl PackageInstaller {
             Err(e) => return Err(e.into()),
         }
 
-        // 6. Unpack wheel
-        Self::unpack_wheel(&bytes, &install_dir)?;
+        // 5.7. Undo whatever an earlier, interrupted install left half-done
+        Self::recover_interrupted(&install_dir);
+
+        // 6. Unpack wheel, routing `.data/` subtrees to their scheme directories
+        let scheme = WheelScheme::from_site_packages(&install_dir, self.venv_path.is_some());
+        self.events.emit(InstallEvent::Unpacking { name: package.to_string() });
+        let written = Self::unpack_archive(&bytes, wheel_filename, &scheme, package)?;
+
+        // 7. Remember which files this install owns; losing the record must not fail the install
+        if let Err(e) = ProvenanceRegistry::record_install(&install_dir, package, target_version, Some(wheel_url), &written) {
+            logger::warn(format!("⚠️  Could not record file provenance: {}", e));
+        }
+
+        Ok(())
+    }
+
+    /// Install a wheel or conda package from a local file, without consulting any index
+    pub async fn install_archive(&self, path: &std::path::Path) -> Result<()> {
+        let filename = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))?;
+        let format = archive::format_for(&filename)?;
+        let (package, version) = format.parse_filename(&filename)
+            .ok_or_else(|| anyhow::anyhow!("Can't tell the package name and version from {}", filename))?;
+        self.events.emit(InstallEvent::PackageQueued { name: package.clone(), version: Some(version.clone()) });
+        let bytes = std::fs::read(path)?;
+        self.verify_required_hash(&package, &bytes)?;
+
+        let install_dir = self.get_install_dir()?;
+        std::fs::create_dir_all(&install_dir)?;
+        Self::recover_interrupted(&install_dir);
+
+        let scheme = WheelScheme::from_site_packages(&install_dir, self.venv_path.is_some());
+        self.events.emit(InstallEvent::Unpacking { name: package.clone() });
+        let result = Self::unpack_archive(&bytes, &filename, &scheme, &package);
+        self.events.emit(match &result {
+            Ok(_) => InstallEvent::InstallComplete { name: package.clone() },
+            Err(e) => InstallEvent::InstallFailed { name: package.clone(), error: e.to_string() },
+        });
+        let written = result?;
+
+        let source = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if let Err(e) = ProvenanceRegistry::record_install(&install_dir, &package, &version, Some(&source.to_string_lossy()), &written) {
+            logger::warn(format!("⚠️  Could not record file provenance: {}", e));
+        }
 
+        logger::success(format!("✓ Installed {} {} from {} ({} package)", package, version, filename, format.name()));
         Ok(())
     }
 
+    fn recover_interrupted(install_dir: &std::path::Path) {
+        match StagedInstall::recover(install_dir) {
+            Ok(0) => {}
+            Ok(n) => logger::warn(format!("↩️  Rolled back {} interrupted install(s) in {}", n, install_dir.display())),
+            Err(e) => logger::warn(format!("⚠️  Could not recover interrupted installs: {}", e)),
+        }
+    }
+
+    /// Import a freshly installed package in the target environment.
+    ///
+    /// A failed import removes the files this run wrote for the package, so a broken
+    /// install surfaces as an error instead of lingering in site-packages.
+    async fn verify_import(&self, package: &str) -> Result<()> {
+        let (package, _extras) = crate::dependency::split_extras(package);
+        let site_packages = self.get_install_dir()?;
+        let module = crate::sandbox::find_module_in(&site_packages, &package)?;
+        if !module.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+            logger::warn(format!("⚠️  Skipping import check for {}: '{}' isn't an importable name", package, module));
+            return Ok(());
+        }
+
+        let python = self.python_executable();
+        logger::note(format!("🔍 Verifying import {} ({})", module, package));
+
+        // Run from a neutral directory so a same-named folder in the project can't satisfy the import
+        let output = Command::new(&python)
+            .arg("-c")
+            .arg(format!("import {}", module))
+            .current_dir(std::env::temp_dir())
+            .output()
+            .map_err(|e| anyhow::anyhow!("Could not run {} to verify {}: {}", python.display(), package, e))?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("unknown error").trim().to_string();
+        let removed = ProvenanceRegistry::rollback_install(&site_packages, &package)?;
+        logger::warn(format!("↩️  Rolled back {} files installed for {}", removed, package));
+        Err(anyhow::anyhow!("import {} failed after installing {}: {}", module, package, reason))
+    }
+
     async fn download_wheel(url: &str) -> Result<Vec<u8>> {
         Self::download_with_retry(url, 3).await
     }
@@ -286,7 +551,10 @@ impl PackageInstaller {
         let mut last_error = None;
         
         for attempt in 1..=max_retries {
-            match reqwest::get(url).await {
+            let permit = rate_limit::acquire(Subsystem::Download).await;
+            let response = reqwest::get(url).await;
+            drop(permit);
+            match response {
                 Ok(resp) if resp.status().is_success() => {
                     match resp.bytes().await {
                         Ok(bytes) => return Ok(bytes.to_vec()),
@@ -294,7 +562,7 @@ impl PackageInstaller {
                             last_error = Some(anyhow::anyhow!("Failed to read response: {}", e));
                             if attempt < max_retries {
                                 let wait_secs = 2u64.pow(attempt - 1); // Exponential backoff: 1s, 2s, 4s
-                                println!("{}", format!(
+                                logger::info(format!(
                                     "⚠️  Download interrupted (attempt {}/{}), retrying in {}s...",
                                     attempt, max_retries, wait_secs
                                 ));
@@ -306,7 +574,7 @@ impl PackageInstaller {
                 Ok(resp) => {
                     last_error = Some(anyhow::anyhow!("HTTP error: {}", resp.status()));
                     if attempt < max_retries {
-                        println!("{}", format!(
+                        logger::info(format!(
                             "⚠️  Download failed with status {} (attempt {}/{}), retrying...",
                             resp.status(), attempt, max_retries
                         ));
@@ -316,7 +584,7 @@ impl PackageInstaller {
                 Err(e) => {
                     last_error = Some(anyhow::anyhow!("Network error: {}", e));
                     if attempt < max_retries {
-                        println!("{}", format!(
+                        logger::info(format!(
                             "⚠️  Network error (attempt {}/{}), retrying...",
                             attempt, max_retries
                         ));
@@ -329,8 +597,9 @@ impl PackageInstaller {
         Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Download failed after {} attempts", max_retries)))
     }
 
-    async fn download_wheel_cached(url: &str, filename: &str) -> Result<Vec<u8>> {
+    async fn download_wheel_cached(url: &str, filename: &str, sha256: Option<&str>) -> Result<Vec<u8>> {
         use std::io::Read;
+        use crate::cache::WheelCacheInfo;
         
         // Create cache directory
         let cache_dir = snakegg::native::dirs::cache_dir()
@@ -343,28 +612,55 @@ impl PackageInstaller {
         // Use URL hash as cache key (more reliable than filename which might have version conflicts)
         let cache_key = snakegg::native::hash::compute_hex(url.as_bytes());
         let cache_path = cache_dir.join(format!("{}.whl", cache_key));
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
 
         // Check cache
         if cache_path.exists() {
-            println!("{}", green(format!("💾 Using cached wheel: {}", filename)));
+            logger::success(format!("💾 Using cached wheel: {}", filename));
             let mut file = std::fs::File::open(&cache_path)?;
             let mut bytes = Vec::new();
             file.read_to_end(&mut bytes)?;
+
+            // Record the hit so `cache prune` evicts least-recently-used wheels first
+            let mut info = WheelCacheInfo::load(&cache_path).unwrap_or_else(|| WheelCacheInfo {
+                filename: filename.to_string(),
+                url: url.to_string(),
+                sha256: sha256.map(|s| s.to_string()),
+                last_used: now,
+            });
+            info.last_used = now;
+            let _ = info.save(&cache_path);
+
             return Ok(bytes);
         }
 
         // Download and cache
-        println!("{}", dim(format!("📦 Downloading wheel: {}", filename)));
+        logger::note(format!("📦 Downloading wheel: {}", filename));
         let bytes = Self::download_wheel(url).await?;
         
         // Write to cache
         std::fs::write(&cache_path, &bytes)?;
+        let info = WheelCacheInfo {
+            filename: filename.to_string(),
+            url: url.to_string(),
+            sha256: sha256.map(|s| s.to_string()),
+            last_used: now,
+        };
+        let _ = info.save(&cache_path);
         
         Ok(bytes)
     }
 
     async fn fetch_pypi_metadata_cached(&self, package: &str) -> Result<serde_json::Value> {
-        use std::time::SystemTime;
+        use reqwest::header::HeaderMap;
+        use reqwest::StatusCode;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        // `Foo_Bar`, `foo-bar` and `foo.bar` are one project with one cache entry
+        let package = &crate::manifest::canonical_name(package);
         
         // Create metadata cache directory
         let cache_dir = snakegg::native::dirs::cache_dir()
@@ -375,39 +671,96 @@ impl PackageInstaller {
         std::fs::create_dir_all(&cache_dir)?;
         
         let cache_path = cache_dir.join(format!("{}.json", package));
+        let validators_path = cache_dir.join(format!("{}.validators.json", package));
+
+        let cached: Option<serde_json::Value> = std::fs::read_to_string(&cache_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        let mut validators = CacheValidators::load(&validators_path);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        
+        // Checked very recently: skip the network entirely
+        if let Some(json) = &cached {
+            if validators.is_fresh(now) {
+                logger::note(format!("💾 Using cached metadata for {}", package));
+                return Ok(json.clone());
+            }
+        }
         
-        // Check cache with TTL (1 hour)
-        if cache_path.exists() {
-            if let Ok(metadata) = std::fs::metadata(&cache_path) {
-                if let Ok(modified) = metadata.modified() {
-                    if let Ok(elapsed) = SystemTime::now().duration_since(modified) {
-                        // Cache valid for 1 hour
-                        if elapsed.as_secs() < 3600 {
-                            if let Ok(cached) = std::fs::read_to_string(&cache_path) {
-                                if let Ok(json) = serde_json::from_str(&cached) {
-                                    println!("{}", dim(format!("💾 Using cached metadata for {}", package)));
-                                    return Ok(json);
-                                }
-                            }
-                        }
-                    }
+        let client = reqwest::Client::new();
+
+        // A private index only speaks the simple API; there are no validators to revalidate with
+        if self.upstream.index_url().is_some() {
+            let json = match self.upstream.fetch_simple(&client, package).await {
+                Ok(info) => serde_json::to_value(info)?,
+                Err(e) => return cached.ok_or(e),
+            };
+            Self::store_metadata(&cache_path, &json, &validators_path, &CacheValidators { checked_at: now, ..Default::default() });
+            return Ok(json);
+        }
+
+        // Otherwise revalidate; an unchanged document costs a 304 instead of a full payload
+        let headers = if cached.is_some() {
+            logger::note(format!("🔄 Revalidating metadata for {}...", package));
+            validators.conditional_headers()
+        } else {
+            logger::note(format!("🌐 Fetching metadata for {}...", package));
+            HeaderMap::new()
+        };
+
+        let resp = match self.upstream.fetch_json(&client, package, headers).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                // The JSON API is throttling or down everywhere: try the simple API, then stale metadata
+                if let Ok(info) = self.upstream.fetch_simple(&client, package).await {
+                    let json = serde_json::to_value(info)?;
+                    Self::store_metadata(&cache_path, &json, &validators_path, &CacheValidators { checked_at: now, ..Default::default() });
+                    return Ok(json);
                 }
+                let json = cached.ok_or(e)?;
+                let age = crate::upstream::describe_age(now.saturating_sub(validators.checked_at));
+                logger::warn(format!(
+                    "⚠️  Package index unavailable; using cached metadata for {} from {} ago (may be stale)",
+                    package, age
+                ));
+                return Ok(json);
+            }
+        };
+
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            if let Some(json) = cached {
+                validators.checked_at = now;
+                validators.save(&validators_path);
+                logger::note(format!("💾 Metadata for {} unchanged", package));
+                return Ok(json);
             }
         }
-        
-        // Fetch from PyPI
-        println!("{}", dim(format!("🌐 Fetching metadata for {}...", package)));
-        let url = format!("https://pypi.org/pypi/{}/json", package);
-        let resp = reqwest::get(&url).await?.json::<serde_json::Value>().await?;
-        
-        // Cache response
-        if let Ok(json_str) = serde_json::to_string_pretty(&resp) {
-            let _ = std::fs::write(&cache_path, json_str);
+
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("PyPI returned {} for {}", resp.status(), package));
         }
+
+        let validators = CacheValidators::from_response(resp.headers(), now);
+        let resp = resp.json::<serde_json::Value>().await?;
+        
+        // Cache response alongside its validators
+        Self::store_metadata(&cache_path, &resp, &validators_path, &validators);
         
         Ok(resp)
     }
 
+    fn store_metadata(cache_path: &std::path::Path, json: &serde_json::Value, validators_path: &std::path::Path, validators: &CacheValidators) {
+        if let Ok(json_str) = serde_json::to_string_pretty(json) {
+            let _ = std::fs::write(cache_path, json_str);
+        }
+        validators.save(validators_path);
+    }
+
+    /// The site-packages directory this installer targets
+    pub fn site_packages(&self) -> Result<std::path::PathBuf> {
+        self.get_install_dir()
+    }
+
     fn get_install_dir(&self) -> Result<std::path::PathBuf> {
         if let Some(venv) = &self.venv_path {
             let venv_path = std::path::Path::new(venv);
@@ -428,6 +781,12 @@ impl PackageInstaller {
             }
         } else {
             let home = snakegg::native::dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+            // The chosen interpreter's own user site, even before anything is installed there
+            if let Some(interpreter) = &self.interpreter {
+                let version = WheelSelector::detect_python_version(interpreter);
+                let (major, minor) = version.split_at(1);
+                return Ok(home.join(".local").join("lib").join(format!("python{}.{}", major, minor)).join("site-packages"));
+            }
             let mut site = home.join(".local").join("lib").join("python3.10").join("site-packages");
             if !site.exists() {
                 let lib = home.join(".local").join("lib");
@@ -444,75 +803,43 @@ impl PackageInstaller {
         }
     }
 
-    fn unpack_wheel(bytes: &[u8], install_dir: &std::path::Path) -> Result<()> {
-        use std::io::Cursor;
-        use zip::ZipArchive;
+    /// Extract a wheel according to `scheme`, returning every file written.
+    ///
+    /// Nothing in the environment changes until the whole wheel has been extracted
+    /// to a staging directory; a failure while moving files into place rolls back.
+    /// Unpack a wheel or conda package into the environment.
+    ///
+    /// Every file is written to a staging directory and flushed first, then moved into
+    /// place as one journaled step, whatever the archive format.
+    fn unpack_archive(bytes: &[u8], filename: &str, scheme: &WheelScheme, package: &str) -> Result<Vec<std::path::PathBuf>> {
         use rayon::prelude::*;
-        use std::sync::{Arc, Mutex};
 
-        println!("{}", dim("🔧 Extracting files..."));
+        logger::note("🔧 Extracting files...");
 
-        let reader = Cursor::new(bytes);
-        let archive = Arc::new(Mutex::new(ZipArchive::new(reader)?));
-        
-        // First pass: collect file metadata
-        let file_count = {
-            let archive_lock = archive.lock().unwrap();
-            archive_lock.len()
-        };
-        
-        let file_info: Vec<_> = (0..file_count)
-            .map(|i| {
-                let mut archive_lock = archive.lock().unwrap();
-                let file = archive_lock.by_index(i).ok()?;
-                Some((
-                    i,
-                    file.name().to_string(),
-                    file.is_dir(),
-                    file.size() as usize,
-                ))
+        let format = archive::format_for(filename)?;
+        let entries = format.entries(bytes)?;
+
+        // Resolve every archive path to its destination before touching the filesystem
+        let targets = entries
+            .iter()
+            .map(|entry| match format.layout() {
+                Layout::Wheel => scheme.target_for(&entry.name),
+                Layout::Prefix => scheme.prefix_target_for(&entry.name),
             })
-            .collect::<Option<Vec<_>>>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to read archive metadata"))?;
-
-        // Create all directories first (sequential, fast)
-        for (_, name, is_dir, _) in &file_info {
-            if *is_dir {
-                let outpath = install_dir.join(name);
-                std::fs::create_dir_all(&outpath)?;
-            }
-        }
+            .collect::<Result<Vec<_>>>()?;
+
+        let staging = StagedInstall::create(&scheme.purelib, package)?;
 
-        // Extract files in parallel
-        let errors: Vec<_> = file_info
+        // Write files in parallel into the staging directory, flushed to disk
+        let errors: Vec<_> = entries
             .par_iter()
-            .filter(|(_, _, is_dir, _)| !is_dir)
-            .filter_map(|(idx, name, _, _)| {
-                let outpath = install_dir.join(name);
-                
-                // Ensure parent directory exists
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        if let Err(e) = std::fs::create_dir_all(p) {
-                            return Some(format!("Failed to create dir {}: {}", p.display(), e));
-                        }
-                    }
-                }
-                
-                // Extract file
-                let result = (|| -> Result<()> {
-                    let mut archive_lock = archive.lock().unwrap();
-                    let mut file = archive_lock.by_index(*idx)?;
-                    let mut outfile = std::fs::File::create(&outpath)?;
-                    std::io::copy(&mut file, &mut outfile)?;
-                    Ok(())
-                })();
-                
-                if let Err(e) = result {
-                    Some(format!("Failed to extract {}: {}", name, e))
-                } else {
-                    None
-                }
+            .enumerate()
+            .zip(targets.par_iter())
+            .filter_map(|((idx, entry), target)| {
+                let target = target.as_ref()?;
+                Self::stage_entry(entry, target, &staging.staged_path(idx), scheme)
+                    .err()
+                    .map(|e| format!("Failed to extract {}: {}", entry.name, e))
             })
             .collect();
 
@@ -520,68 +847,141 @@ impl PackageInstaller {
             return Err(anyhow::anyhow!("Extraction errors: {}", errors.join(", ")));
         }
 
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        for (idx, (entry, target)) in entries.iter().zip(targets).enumerate() {
+            match target {
+                Some(target) if entry.kind == EntryKind::Dir => dirs.push(target.path),
+                Some(target) => files.push((staging.staged_path(idx), target.path)),
+                None => {}
+            }
+        }
+        staging.commit(&dirs, &files)?;
+
+        Ok(files.into_iter().map(|(_, target)| target).collect())
+    }
+
+    fn stage_entry(entry: &ArchiveEntry, target: &WheelTarget, outpath: &std::path::Path, scheme: &WheelScheme) -> Result<()> {
+        let contents = match &entry.kind {
+            EntryKind::Dir => return Ok(()),
+            EntryKind::Symlink(link) => return Self::stage_symlink(link, outpath),
+            EntryKind::File(contents) => contents.clone(),
+        };
+
+        // conda packages carry their build prefix in scripts and binaries
+        let contents = match &entry.placeholder {
+            Some(placeholder) => archive::relocate(contents, placeholder, &scheme.data.to_string_lossy())?,
+            None => contents,
+        };
+        let contents = if target.is_script { scheme.rewrite_shebang(contents) } else { contents };
+
+        let mut outfile = std::fs::File::create(outpath)?;
+        std::io::Write::write_all(&mut outfile, &contents)?;
+        outfile.sync_all()?;
+
+        // Scripts must be runnable; other files keep the exec bits recorded in the archive
+        let mode = if target.is_script { Some(0o755) } else { entry.mode };
+        Self::restore_permissions(outpath, mode)
+    }
+
+    #[cfg(unix)]
+    fn stage_symlink(link: &std::path::Path, outpath: &std::path::Path) -> Result<()> {
+        std::os::unix::fs::symlink(link, outpath)?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn stage_symlink(link: &std::path::Path, _outpath: &std::path::Path) -> Result<()> {
+        Err(anyhow::anyhow!("Symlinks (to {}) can't be installed on this platform", link.display()))
+    }
+
+    #[cfg(unix)]
+    fn restore_permissions(path: &std::path::Path, mode: Option<u32>) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Some(mode) = mode {
+            if mode & 0o111 != 0 {
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777 | 0o644))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn restore_permissions(_path: &std::path::Path, _mode: Option<u32>) -> Result<()> {
         Ok(())
     }
 
-    async fn uninstall_with_native(&self, package: &str) -> Result<()> {
+    pub(crate) async fn uninstall_with_native(&self, package: &str) -> Result<()> {
         // Basic uninstall: remove the directory/file in site-packages
         // This is risky without reading RECORD, but for "bleeding edge" prototype it works.
         // We'll just warn that it's not fully implemented.
-        println!("{}", yellow("⚠️  Native uninstall not fully implemented. Please manually remove files if needed."));
+        logger::warn("⚠️  Native uninstall not fully implemented. Please manually remove files if needed.");
         Ok(())
     }
 
-    async fn list_with_native(&self) -> Result<Vec<String>> {
-        // Scan site-packages for .dist-info directories
-        let install_dir = if let Some(venv) = &self.venv_path {
-             let venv_path = std::path::Path::new(venv);
-            if cfg!(target_os = "windows") {
-                venv_path.join("Lib").join("site-packages")
-            } else {
-                let lib = venv_path.join("lib");
-                let mut site = lib.join("python3.10").join("site-packages");
-                if let Ok(entries) = std::fs::read_dir(&lib) {
-                    for entry in entries.flatten() {
-                        if entry.file_name().to_string_lossy().starts_with("python") {
-                            site = entry.path().join("site-packages");
-                            break;
-                        }
-                    }
-                }
-                site
-            }
-        } else {
-             let home = snakegg::native::dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-            let mut site = home.join(".local").join("lib").join("python3.10").join("site-packages");
-             if !site.exists() {
-                 let lib = home.join(".local").join("lib");
-                 if let Ok(entries) = std::fs::read_dir(&lib) {
-                    for entry in entries.flatten() {
-                        if entry.file_name().to_string_lossy().starts_with("python") {
-                            site = entry.path().join("site-packages");
-                            break;
-                        }
-                    }
-                }
-            }
-            site
+    pub(crate) async fn list_with_native(&self) -> Result<Vec<String>> {
+        // Names from each dist-info's METADATA; directory names can't be trusted to split on '-'
+        let distributions = crate::freeze::installed_distributions(&self.get_install_dir()?)?;
+        Ok(distributions.iter().map(|dist| crate::manifest::canonical_name(&dist.name)).collect())
+    }
+
+    /// PyPI's `info.version`, or the newest stable release with an unyanked file when that one is
+    /// yanked or a pre-release; pre-releases only when allowed or when nothing else was published
+    /// The best wheel among a release's `files` for the target interpreter; yanked
+    /// files only count when the whole release is yanked
+    fn select_wheel<'a>(&self, files: &'a [serde_json::Value], all_yanked: bool) -> Option<&'a serde_json::Value> {
+        let selector = WheelSelector::for_interpreter(&self.python_executable());
+        let is_yanked = |f: &serde_json::Value| f["yanked"].as_bool().unwrap_or(false);
+        files.iter()
+            .filter(|f| f["filename"].as_str().map_or(false, |n| n.ends_with(".whl")))
+            .filter(|f| all_yanked || !is_yanked(f))
+            .filter(|f| f["requires_python"].as_str().is_none_or(|spec| selector.allows_python(spec)))
+            .max_by_key(|f| selector.score_wheel(f["filename"].as_str().unwrap_or("")))
+            .filter(|f| selector.score_wheel(f["filename"].as_str().unwrap_or("")) > 0)
+    }
+
+    /// The file installing `package` `version` would download, with its size when the
+    /// index gives one: the wheel a native install picks, else the sdist. `None` when the
+    /// index has no such release. Only the metadata is fetched.
+    pub async fn planned_download(&self, package: &str, version: &str) -> Result<Option<(String, Option<u64>)>> {
+        let (package, _extras) = crate::dependency::split_extras(package);
+        let resp = self.fetch_pypi_metadata_cached(&package).await?;
+        let Some(files) = resp["releases"][version].as_array() else {
+            return Ok(None);
         };
+        let all_yanked = !files.is_empty() && files.iter().all(|f| f["yanked"].as_bool().unwrap_or(false));
+        let is_sdist = |f: &&serde_json::Value| f["filename"].as_str()
+            .is_some_and(|name| name.ends_with(".tar.gz") || name.ends_with(".zip"));
+        Ok(self.select_wheel(files, all_yanked)
+            .or_else(|| files.iter().find(is_sdist))
+            .and_then(|f| Some((f["filename"].as_str()?.to_string(), f["size"].as_u64()))))
+    }
 
-        let mut packages = Vec::new();
-        if install_dir.exists() {
-            for entry in std::fs::read_dir(install_dir)? {
-                let entry = entry?;
-                let name = entry.file_name().to_string_lossy().to_string();
-                if name.ends_with(".dist-info") {
-                    let pkg_name = name.split('-').next().unwrap_or(&name).to_string();
-                    packages.push(pkg_name);
-                }
+    fn latest_unyanked_version(resp: &serde_json::Value, allow_prereleases: bool) -> Option<String> {
+        let releases = resp["releases"].as_object()?;
+        let unyanked = |version: &str| releases.get(version)
+            .and_then(|files| files.as_array())
+            .is_some_and(|files| files.iter().any(|f| !f["yanked"].as_bool().unwrap_or(false)));
+
+        if let Some(latest) = resp["info"]["version"].as_str() {
+            if !allow_prereleases && unyanked(latest) && !prerelease::is_prerelease(latest) {
+                return Some(latest.to_string());
             }
         }
-        Ok(packages)
+
+        let candidates: Vec<(crate::pep440::Version, &String)> = releases.keys()
+            .filter(|v| unyanked(v))
+            .filter_map(|v| crate::pep440::Version::parse(v).ok().map(|parsed| (parsed, v)))
+            .collect();
+        let newest = |include_pre: bool| candidates.iter()
+            .filter(|(parsed, _)| include_pre || (parsed.pre.is_none() && parsed.dev.is_none()))
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, v)| v.to_string());
+        newest(allow_prereleases).or_else(|| newest(true))
     }
 
-    async fn install_with_uv(&self, package: &str, version: Option<&str>) -> Result<()> {
+    pub(crate) async fn install_with_uv(&self, package: &str, version: Option<&str>) -> Result<()> {
         let mut cmd = Command::new("uv");
         cmd.arg("pip").arg("install");
         
@@ -603,11 +1003,7 @@ impl PackageInstaller {
             cmd.arg("--no-cache");
         }
         
-        if let Some(ver) = version {
-            cmd.arg(&format!("{}=={}", package, ver));
-        } else {
-            cmd.arg(package);
-        }
+        let _requirements = self.add_requirement_args(&mut cmd, package, version)?;
 
         let output = cmd.output()?;
         
@@ -619,7 +1015,7 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn install_with_pip(&self, package: &str, version: Option<&str>) -> Result<()> {
+    pub(crate) async fn install_with_pip(&self, package: &str, version: Option<&str>) -> Result<()> {
         let mut cmd = Command::new("pip");
         
         if let Some(venv_path) = &self.venv_path {
@@ -635,11 +1031,7 @@ impl PackageInstaller {
             cmd.arg("--no-cache-dir");
         }
         
-        if let Some(ver) = version {
-            cmd.arg(&format!("{}=={}", package, ver));
-        } else {
-            cmd.arg(package);
-        }
+        let _requirements = self.add_requirement_args(&mut cmd, package, version)?;
 
         let output = cmd.output()?;
         
@@ -648,7 +1040,7 @@ impl PackageInstaller {
             
             // Check for externally managed environment error (PEP 668)
             if error.contains("externally-managed-environment") {
-                eprintln!("{} Externally managed environment detected. Retrying with --break-system-packages...", yellow("WARN:"));
+                logger::warn("WARN: Externally managed environment detected. Retrying with --break-system-packages...");
                 
                 let mut retry_cmd = Command::new("pip");
                 if let Some(venv_path) = &self.venv_path {
@@ -664,11 +1056,7 @@ impl PackageInstaller {
                     retry_cmd.arg("--no-cache-dir");
                 }
                 
-                if let Some(ver) = version {
-                    retry_cmd.arg(&format!("{}=={}", package, ver));
-                } else {
-                    retry_cmd.arg(package);
-                }
+                let _retry_requirements = self.add_requirement_args(&mut retry_cmd, package, version)?;
                 
                 let retry_output = retry_cmd.output()?;
                 if retry_output.status.success() {
@@ -685,13 +1073,20 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn install_with_conda(&self, package: &str, version: Option<&str>) -> Result<()> {
+    pub(crate) async fn install_with_conda(&self, package: &str, version: Option<&str>) -> Result<()> {
+        // Conda has no notion of extras
+        let (package, _extras) = crate::dependency::split_extras(package);
+        let package = package.as_str();
+
         let mut cmd = Command::new("conda");
         cmd.arg("install").arg("-y");
         
         if let Some(venv_path) = &self.venv_path {
             cmd.arg("--prefix").arg(venv_path);
         }
+        for channel in &self.channels {
+            cmd.arg("-c").arg(channel);
+        }
         
         if let Some(ver) = version {
             cmd.arg(&format!("{}={}", package, ver));
@@ -709,7 +1104,7 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn install_with_poetry(&self, package: &str, version: Option<&str>) -> Result<()> {
+    pub(crate) async fn install_with_poetry(&self, package: &str, version: Option<&str>) -> Result<()> {
         let mut cmd = Command::new("poetry");
         cmd.arg("add");
         
@@ -729,7 +1124,7 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn uninstall_with_uv(&self, package: &str) -> Result<()> {
+    pub(crate) async fn uninstall_with_uv(&self, package: &str) -> Result<()> {
         let mut cmd = Command::new("uv");
         cmd.arg("pip").arg("uninstall").arg(package);
         
@@ -749,7 +1144,7 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn uninstall_with_pip(&self, package: &str) -> Result<()> {
+    pub(crate) async fn uninstall_with_pip(&self, package: &str) -> Result<()> {
         let mut cmd = Command::new("pip");
         cmd.arg("uninstall").arg("-y").arg(package);
         
@@ -767,7 +1162,7 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn uninstall_with_conda(&self, package: &str) -> Result<()> {
+    pub(crate) async fn uninstall_with_conda(&self, package: &str) -> Result<()> {
         let mut cmd = Command::new("conda");
         cmd.arg("remove").arg("-y").arg(package);
         
@@ -785,7 +1180,7 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn uninstall_with_poetry(&self, package: &str) -> Result<()> {
+    pub(crate) async fn uninstall_with_poetry(&self, package: &str) -> Result<()> {
         let mut cmd = Command::new("poetry");
         cmd.arg("remove").arg(package);
 
@@ -799,7 +1194,7 @@ impl PackageInstaller {
         Ok(())
     }
 
-    async fn list_with_uv(&self) -> Result<Vec<String>> {
+    pub(crate) async fn list_with_uv(&self) -> Result<Vec<String>> {
         let mut cmd = Command::new("uv");
         cmd.arg("pip").arg("freeze");
         
@@ -823,7 +1218,7 @@ impl PackageInstaller {
         Ok(packages)
     }
 
-    async fn list_with_pip(&self) -> Result<Vec<String>> {
+    pub(crate) async fn list_with_pip(&self) -> Result<Vec<String>> {
         let mut cmd = Command::new("pip");
         cmd.arg("list").arg("--format=freeze");
         
@@ -845,7 +1240,7 @@ impl PackageInstaller {
         Ok(packages)
     }
 
-    async fn list_with_conda(&self) -> Result<Vec<String>> {
+    pub(crate) async fn list_with_conda(&self) -> Result<Vec<String>> {
         let mut cmd = Command::new("conda");
         cmd.arg("list");
         
@@ -868,7 +1263,7 @@ impl PackageInstaller {
         Ok(packages)
     }
 
-    async fn list_with_poetry(&self) -> Result<Vec<String>> {
+    pub(crate) async fn list_with_poetry(&self) -> Result<Vec<String>> {
         let mut cmd = Command::new("poetry");
         cmd.arg("show").arg("--only=main");
 
@@ -886,11 +1281,11 @@ impl PackageInstaller {
         Ok(packages)
     }
 
-    async fn search_with_pip(&self, query: &str) -> Result<Vec<String>> {
+    pub(crate) async fn search_with_pip(&self, query: &str) -> Result<Vec<String>> {
         let mut cmd = Command::new("pip");
         cmd.arg("search").arg(query);
         
-        println!("{}", dim("🌐 Searching PyPI..."));
+        logger::note("🌐 Searching PyPI...");
         
         // Fallback: try `pip search` just in case user has a custom index
         let output = cmd.output()?;
@@ -907,7 +1302,7 @@ impl PackageInstaller {
         Ok(vec![format!("PyPI search via pip is limited. Try visiting https://pypi.org/search/?q={}", query)])
     }
 
-    async fn search_with_conda(&self, query: &str) -> Result<Vec<String>> {
+    pub(crate) async fn search_with_conda(&self, query: &str) -> Result<Vec<String>> {
         let mut cmd = Command::new("conda");
         cmd.arg("search").arg(query);
         
@@ -925,7 +1320,7 @@ impl PackageInstaller {
         Ok(results)
     }
 
-    async fn search_with_poetry(&self, query: &str) -> Result<Vec<String>> {
+    pub(crate) async fn search_with_poetry(&self, query: &str) -> Result<Vec<String>> {
         let mut cmd = Command::new("poetry");
         cmd.arg("search").arg(query);
         
@@ -943,7 +1338,7 @@ impl PackageInstaller {
         Ok(results)
     }
 
-    async fn show_with_pip(&self, package: &str) -> Result<String> {
+    pub(crate) async fn show_with_pip(&self, package: &str) -> Result<String> {
         let mut cmd = Command::new("pip");
         cmd.arg("show").arg(package);
         
@@ -955,7 +1350,7 @@ impl PackageInstaller {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
-    async fn show_with_conda(&self, package: &str) -> Result<String> {
+    pub(crate) async fn show_with_conda(&self, package: &str) -> Result<String> {
         let mut cmd = Command::new("conda");
         cmd.arg("list").arg(package);
         
@@ -967,7 +1362,7 @@ impl PackageInstaller {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
-    async fn show_with_poetry(&self, package: &str) -> Result<String> {
+    pub(crate) async fn show_with_poetry(&self, package: &str) -> Result<String> {
         let mut cmd = Command::new("poetry");
         cmd.arg("show").arg(package);
         
@@ -989,7 +1384,7 @@ impl PackageInstaller {
                     expected, actual
                 ));
             }
-            println!("{}", dim("✅ SHA256 integrity verified"));
+            logger::note("✅ SHA256 integrity verified");
             return Ok(());
         }
         
@@ -1001,13 +1396,107 @@ impl PackageInstaller {
                     expected, actual
                 ));
             }
-            println!("{}", dim("✅ MD5 integrity verified"));
+            logger::note("✅ MD5 integrity verified");
             return Ok(());
         }
         
         Ok(())
     }
     
+    // Helper: Pass the requirement to pip/uv, via a hashed requirements file in --require-hashes mode.
+    // Keep the returned guard alive until the command has run; dropping it deletes the file.
+    fn add_requirement_args(&self, cmd: &mut Command, package: &str, version: Option<&str>) -> Result<RequirementsFile> {
+        if let Some(index_url) = self.upstream.index_url() {
+            cmd.arg("--index-url").arg(index_url);
+        }
+        for file in &self.constraint_files {
+            cmd.arg("-c").arg(file);
+        }
+        if self.prereleases.allows(package) {
+            cmd.arg("--pre");
+        }
+
+        let digests = match &self.required_hashes {
+            Some(required) => required.get(&Self::normalize_hash_key(package)).cloned().unwrap_or_default(),
+            None => {
+                if let Some(ver) = version {
+                    cmd.arg(&format!("{}=={}", package, ver));
+                } else {
+                    cmd.arg(package);
+                }
+                return Ok(RequirementsFile(None));
+            }
+        };
+
+        let ver = version.ok_or_else(|| anyhow::anyhow!("--require-hashes needs an exact version for {}", package))?;
+        let mut line = format!("{}=={}", package, ver);
+        for digest in &digests {
+            line.push_str(&format!(" --hash=sha256:{}", digest));
+        }
+
+        let req_file = std::env::temp_dir().join(format!("snakepit-hashes-{}.txt", snakegg::native::id::new()));
+        std::fs::write(&req_file, format!("{}\n", line))?;
+
+        cmd.arg("--require-hashes").arg("-r").arg(&req_file);
+        Ok(RequirementsFile(Some(req_file)))
+    }
+
+    // Helper: Refuse packages that can't be hash-checked before any download happens
+    fn ensure_hash_pinned(&self, package: &str, version: Option<&str>) -> Result<()> {
+        if !self.backend.supports_hash_checking() {
+            return Err(anyhow::anyhow!("--require-hashes isn't supported by the {} backend", self.backend.name()));
+        }
+
+        if version.is_none() {
+            return Err(anyhow::anyhow!("--require-hashes needs an exact version for {}", package));
+        }
+
+        let has_digest = self.required_hashes.as_ref()
+            .and_then(|required| required.get(&Self::normalize_hash_key(package)))
+            .map_or(false, |digests| !digests.is_empty());
+
+        if !has_digest {
+            return Err(anyhow::anyhow!("No sha256 hash given for {} in --require-hashes mode", package));
+        }
+
+        Ok(())
+    }
+
+    // Helper: Verify a downloaded artifact against the user's pinned hashes
+    fn verify_required_hash(&self, package: &str, bytes: &[u8]) -> Result<()> {
+        let required = match &self.required_hashes {
+            Some(required) => required,
+            None => return Ok(()),
+        };
+
+        let digests = required.get(&Self::normalize_hash_key(package))
+            .ok_or_else(|| anyhow::anyhow!("No sha256 hash given for {} in --require-hashes mode", package))?;
+
+        let actual = snakegg::native::hash::compute_sha256_hex(bytes);
+        if !digests.iter().any(|d| d == &actual) {
+            return Err(anyhow::anyhow!(
+                "Hash mismatch for {}: downloaded sha256:{} is not among the pinned hashes",
+                package, actual
+            ));
+        }
+
+        logger::note(format!("🔒 Pinned hash verified for {}", package));
+        Ok(())
+    }
+
+    fn normalize_hash_key(package: &str) -> String {
+        let (name, _extras) = crate::dependency::split_extras(package);
+        crate::manifest::canonical_name(&name)
+    }
+
+    fn normalize_sha256(digest: &str) -> Option<String> {
+        match digest.split_once(':') {
+            Some(("sha256", hex)) => Some(hex.to_lowercase()),
+            Some(_) => None,
+            None => Some(digest.to_lowercase()),
+        }
+    }
+
     // Helper: Check available disk space
     fn check_disk_space(_install_dir: &std::path::Path, _required_bytes: u64) -> Result<()> {
         // Try to get filesystem stats
@@ -1045,8 +1534,63 @@ mod tests {
     #[test]
     fn test_backend_detection() {
         let backend = InstallerBackend::detect();
-        // This test will pass regardless of what's installed
-        assert!(matches!(backend, InstallerBackend::Uv | InstallerBackend::Pip | InstallerBackend::Conda | InstallerBackend::Poetry | InstallerBackend::Native));
+        // Native needs nothing on PATH, so it's what every machine gets
+        assert_eq!(backend.name(), "native");
+        assert!(backend.writes_files_directly());
+    }
+
+    #[test]
+    fn test_require_hashes_pinning() {
+        let mut hashes = HashMap::new();
+        hashes.insert("Requests".to_string(), vec!["sha256:ABCDEF".to_string()]);
+
+        let installer = PackageInstaller::new()
+            .with_backend(InstallerBackend::named("pip").unwrap())
+            .with_required_hashes(hashes.clone());
+
+        assert!(installer.ensure_hash_pinned("requests", Some("2.31.0")).is_ok());
+        assert!(installer.ensure_hash_pinned("requests", None).is_err());
+        assert!(installer.ensure_hash_pinned("idna", Some("3.4")).is_err());
+        let conda = PackageInstaller::new()
+            .with_backend(InstallerBackend::named("conda").unwrap())
+            .with_required_hashes(hashes);
+        assert!(conda.ensure_hash_pinned("requests", Some("2.31.0")).is_err());
+        assert_eq!(
+            installer.required_hashes.as_ref().unwrap().get("requests"),
+            Some(&vec!["abcdef".to_string()])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wheel_data_categories_map_to_scheme_paths() {
+        let site = std::path::Path::new("/venv/lib/python3.11/site-packages");
+        let scheme = WheelScheme::from_site_packages(site, true);
+        let target = |name: &str| scheme.target_for(name).unwrap().map(|t| (t.path, t.is_script));
+
+        assert_eq!(target("demo/__init__.py"), Some((site.join("demo/__init__.py"), false)));
+        assert_eq!(target("demo-1.0.data/scripts/demo-cli"), Some(("/venv/bin/demo-cli".into(), true)));
+        assert_eq!(target("demo-1.0.data/data/share/demo.1"), Some(("/venv/share/demo.1".into(), false)));
+        assert_eq!(target("demo-1.0.data/headers/demo.h"), Some(("/venv/include/python3.11/demo/demo.h".into(), false)));
+        assert_eq!(target("demo-1.0.data/platlib/_demo.so"), Some((site.join("_demo.so"), false)));
+        assert_eq!(target("demo-1.0.data/"), None);
+        assert!(scheme.target_for("demo-1.0.data/bogus/x").is_err());
+        assert!(scheme.target_for("../escape.py").is_err());
+
+        let script = scheme.rewrite_shebang(b"#!python\nprint('hi')\n".to_vec());
+        assert_eq!(script, b"#!/venv/bin/python\nprint('hi')\n".to_vec());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_conda_members_map_under_prefix() {
+        let scheme = WheelScheme::from_site_packages(std::path::Path::new("/venv/lib/python3.11/site-packages"), true);
+        let target = |name: &str| scheme.prefix_target_for(name).unwrap().map(|t| t.path);
+
+        assert_eq!(target("lib/python3.11/site-packages/six.py"), Some("/venv/lib/python3.11/site-packages/six.py".into()));
+        assert_eq!(target("bin/tool"), Some("/venv/bin/tool".into()));
+        assert_eq!(target("info/index.json"), None);
+        assert!(scheme.prefix_target_for("/etc/passwd").is_err());
     }
 }
 
@@ -1057,17 +1601,23 @@ struct WheelSelector {
 }
 
 impl WheelSelector {
-    fn new() -> Self {
+    fn for_interpreter(python: &std::path::Path) -> Self {
         let os = std::env::consts::OS.to_string();
         let arch = std::env::consts::ARCH.to_string();
-        let python_version = Self::detect_python_version();
+        let python_version = Self::detect_python_version(python);
         
         Self { os, arch, python_version }
     }
 
-    fn detect_python_version() -> String {
-        // Try to detect from python3 or python command
-        let output = std::process::Command::new("python3")
+    /// Whether the target interpreter satisfies a file's `Requires-Python`
+    fn allows_python(&self, requires_python: &str) -> bool {
+        let (major, minor) = self.python_version.split_at(1);
+        crate::markers::python_satisfies(requires_python, &format!("{}.{}", major, minor))
+    }
+
+    fn detect_python_version(python: &std::path::Path) -> String {
+        // Ask the interpreter being installed for, falling back to `python`
+        let output = std::process::Command::new(python)
             .arg("--version")
             .output()
             .or_else(|_| std::process::Command::new("python").arg("--version").output());
@@ -1148,3 +1698,119 @@ impl WheelSelector {
         score
     }
 }
+
+/// Where a wheel's files go, per the install scheme of the target environment.
+///
+/// Most of a wheel lands in site-packages, but `{name}-{version}.data/<category>/`
+/// subtrees belong elsewhere: `scripts` next to the interpreter, `data` under the
+/// prefix, `headers` under `include/`.
+struct WheelScheme {
+    purelib: std::path::PathBuf,
+    scripts: std::path::PathBuf,
+    data: std::path::PathBuf,
+    headers: std::path::PathBuf,
+    /// Interpreter that `#!python` shebangs are rewritten to
+    interpreter: String,
+}
+
+struct WheelTarget {
+    path: std::path::PathBuf,
+    is_script: bool,
+}
+
+impl WheelScheme {
+    fn from_site_packages(site_packages: &std::path::Path, is_venv: bool) -> Self {
+        use std::path::PathBuf;
+
+        // <prefix>/lib/pythonX.Y/site-packages, or <prefix>\Lib\site-packages on Windows
+        let depth = if cfg!(target_os = "windows") { 2 } else { 3 };
+        let prefix = site_packages.ancestors().nth(depth).map(PathBuf::from).unwrap_or_else(|| site_packages.to_path_buf());
+        let python_dir = site_packages.parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .filter(|n| n.starts_with("python"))
+            .unwrap_or_else(|| "python3".to_string());
+
+        let scripts = if cfg!(target_os = "windows") { prefix.join("Scripts") } else { prefix.join("bin") };
+        let interpreter = if is_venv {
+            scripts.join("python").to_string_lossy().to_string()
+        } else {
+            "/usr/bin/env python3".to_string()
+        };
+
+        Self {
+            purelib: site_packages.to_path_buf(),
+            headers: prefix.join("include").join(python_dir),
+            scripts,
+            data: prefix,
+            interpreter,
+        }
+    }
+
+    /// Destination for an archive entry, or `None` for the `.data` directory entry itself
+    fn target_for(&self, name: &str) -> Result<Option<WheelTarget>> {
+        let path = Self::safe_path(name)?;
+
+        let mut components = path.components();
+        let top = components.next().map(|c| c.as_os_str().to_string_lossy().to_string()).unwrap_or_default();
+        if !top.ends_with(".data") {
+            return Ok(Some(WheelTarget { path: self.purelib.join(path), is_script: false }));
+        }
+
+        let category = match components.next() {
+            Some(category) => category.as_os_str().to_string_lossy().to_string(),
+            None => return Ok(None),
+        };
+        let rest = components.as_path();
+
+        let (base, is_script) = match category.as_str() {
+            "purelib" | "platlib" => (self.purelib.clone(), false),
+            "scripts" => (self.scripts.clone(), true),
+            "data" => (self.data.clone(), false),
+            // Headers are namespaced by distribution name
+            "headers" => {
+                let dist = top.trim_end_matches(".data").split('-').next().unwrap_or_default().to_string();
+                (self.headers.join(dist), false)
+            }
+            other => return Err(anyhow::anyhow!("Unknown wheel data category '{}' in {}", other, name)),
+        };
+
+        if rest.as_os_str().is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(WheelTarget { path: base.join(rest), is_script }))
+    }
+
+    /// Destination for a conda package member, which is relative to the prefix; `info/` is package metadata
+    fn prefix_target_for(&self, name: &str) -> Result<Option<WheelTarget>> {
+        let path = Self::safe_path(name)?;
+        if path.starts_with("info") {
+            return Ok(None);
+        }
+        Ok(Some(WheelTarget { path: self.data.join(path), is_script: false }))
+    }
+
+    fn safe_path(name: &str) -> Result<&std::path::Path> {
+        use std::path::{Component, Path};
+
+        let path = Path::new(name);
+        if path.components().any(|c| !matches!(c, Component::Normal(_))) {
+            return Err(anyhow::anyhow!("Refusing to extract unsafe path {}", name));
+        }
+        Ok(path)
+    }
+
+    /// Point `#!python` / `#!pythonw` placeholders at the target interpreter
+    fn rewrite_shebang(&self, contents: Vec<u8>) -> Vec<u8> {
+        let line_end = contents.iter().position(|b| *b == b'\n').unwrap_or(contents.len());
+        let first_line = String::from_utf8_lossy(&contents[..line_end]);
+        if first_line.trim_end() != "#!python" && first_line.trim_end() != "#!pythonw" {
+            return contents;
+        }
+
+        let mut rewritten = format!("#!{}", self.interpreter).into_bytes();
+        rewritten.extend_from_slice(&contents[line_end..]);
+        rewritten
+    }
+}
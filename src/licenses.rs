@@ -0,0 +1,202 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+use crate::package_info::metadata_license;
+
+/// Group name for packages whose metadata names no license
+pub const UNKNOWN: &str = "UNKNOWN";
+
+/// An installed distribution and the license its metadata declares
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackageLicense {
+    pub name: String,
+    pub version: String,
+    /// `None` when neither the license fields nor the classifiers name one
+    pub license: Option<String>,
+}
+
+/// Why a package fails the license policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Reason {
+    /// Its license is on the denylist
+    Denied,
+    /// There's an allowlist and its license isn't on it (or is unknown)
+    NotAllowed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub package: String,
+    pub version: String,
+    pub license: Option<String>,
+    pub reason: Reason,
+}
+
+/// `licenses`: packages grouped by license, and the ones the policy rejects
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseReport {
+    pub site_packages: PathBuf,
+    pub packages: usize,
+    /// License to the `name==version` of every package under it, licenses sorted
+    pub licenses: BTreeMap<String, Vec<String>>,
+    pub violations: Vec<Violation>,
+}
+
+/// Licenses to allow and deny, from `allowed_licenses`/`denied_licenses` or `--allow`/`--deny`.
+///
+/// Entries match case-insensitively, ignoring a trailing " License" so `MIT` also matches the
+/// classifier's `MIT License`; a trailing `*` matches by prefix (`GPL*`). An SPDX expression
+/// passes when any of its `OR` alternatives has every `AND` part allowed.
+#[derive(Debug, Clone, Default)]
+pub struct LicensePolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl LicensePolicy {
+    pub fn new(allow: Vec<String>, deny: Vec<String>) -> Self {
+        Self { allow, deny }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Why `license` fails the policy, if it does
+    pub fn check(&self, license: Option<&str>) -> Option<Reason> {
+        let Some(license) = license else {
+            return (!self.allow.is_empty()).then_some(Reason::NotAllowed);
+        };
+        let alternatives = split_expression(license, " OR ");
+        let denied = |term: &str| self.deny.iter().any(|pattern| matches(pattern, term));
+        if !self.deny.is_empty() && alternatives.iter().all(|alt| split_expression(alt, " AND ").iter().any(|term| denied(term))) {
+            return Some(Reason::Denied);
+        }
+        let allowed = |term: &str| self.allow.iter().any(|pattern| matches(pattern, term));
+        if !self.allow.is_empty() && !alternatives.iter().any(|alt| split_expression(alt, " AND ").iter().all(|term| allowed(term))) {
+            return Some(Reason::NotAllowed);
+        }
+        None
+    }
+}
+
+/// The license of every distribution in `site_packages`, sorted by name
+pub fn installed_licenses(site_packages: &Path) -> Result<Vec<PackageLicense>> {
+    if !site_packages.exists() {
+        return Ok(Vec::new());
+    }
+    let mut packages = Vec::new();
+    for entry in std::fs::read_dir(site_packages)?.flatten() {
+        if !entry.file_name().to_string_lossy().ends_with(".dist-info") {
+            continue;
+        }
+        let Ok(metadata) = std::fs::read_to_string(entry.path().join("METADATA")) else { continue };
+        let header = |key: &str| metadata.lines()
+            .take_while(|line| !line.trim().is_empty())
+            .find_map(|line| line.strip_prefix(key))
+            .map(|value| value.trim().to_string());
+        let (Some(name), Some(version)) = (header("Name:"), header("Version:")) else { continue };
+        packages.push(PackageLicense { name, version, license: metadata_license(&metadata) });
+    }
+    packages.sort_by_key(|package| canonical_name(&package.name));
+    Ok(packages)
+}
+
+/// Group `packages` by license and hold each one against `policy`
+pub fn license_report(site_packages: &Path, packages: &[PackageLicense], policy: &LicensePolicy) -> LicenseReport {
+    let mut licenses: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut violations = Vec::new();
+    for package in packages {
+        licenses.entry(package.license.clone().unwrap_or_else(|| UNKNOWN.to_string()))
+            .or_default()
+            .push(format!("{}=={}", package.name, package.version));
+        if let Some(reason) = policy.check(package.license.as_deref()) {
+            violations.push(Violation {
+                package: package.name.clone(),
+                version: package.version.clone(),
+                license: package.license.clone(),
+                reason,
+            });
+        }
+    }
+    LicenseReport {
+        site_packages: site_packages.to_path_buf(),
+        packages: packages.len(),
+        licenses,
+        violations,
+    }
+}
+
+/// `expression` split on `operator` (case-insensitively), with the parentheses around it dropped
+fn split_expression(expression: &str, operator: &str) -> Vec<String> {
+    let trimmed = expression.trim().trim_start_matches('(').trim_end_matches(')');
+    let upper = trimmed.to_ascii_uppercase();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while let Some(found) = upper[start..].find(operator) {
+        parts.push(trimmed[start..start + found].trim().to_string());
+        start += found + operator.len();
+    }
+    parts.push(trimmed[start..].trim().to_string());
+    parts
+}
+
+fn matches(pattern: &str, license: &str) -> bool {
+    let normalize = |name: &str| {
+        let lower = name.trim().to_lowercase();
+        lower.strip_suffix(" license").unwrap_or(&lower).trim().to_string()
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => license.trim().to_lowercase().starts_with(&prefix.trim().to_lowercase()),
+        None => normalize(pattern) == normalize(license),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_policy_matches_names_and_expressions() {
+        let policy = LicensePolicy::new(strings(&["MIT", "BSD-3-Clause", "Apache-2.0"]), strings(&["GPL*", "AGPL-3.0"]));
+        assert_eq!(policy.check(Some("MIT License")), None);
+        assert_eq!(policy.check(Some("Apache-2.0 OR GPL-2.0-only")), None);
+        assert_eq!(policy.check(Some("MIT AND BSD-3-Clause")), None);
+        assert_eq!(policy.check(Some("GPL-3.0-or-later")), Some(Reason::Denied));
+        assert_eq!(policy.check(Some("(GPL-2.0-only OR AGPL-3.0)")), Some(Reason::Denied));
+        assert_eq!(policy.check(Some("MPL-2.0")), Some(Reason::NotAllowed));
+        assert_eq!(policy.check(None), Some(Reason::NotAllowed));
+        assert_eq!(LicensePolicy::new(Vec::new(), strings(&["GPL*"])).check(None), None);
+    }
+
+    #[test]
+    fn test_report_groups_by_license_with_classifier_fallback() {
+        let site = std::env::temp_dir().join(format!("snakepit-licenses-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&site);
+        let dist = |dir: &str, metadata: &str| {
+            std::fs::create_dir_all(site.join(dir)).unwrap();
+            std::fs::write(site.join(dir).join("METADATA"), metadata).unwrap();
+        };
+        dist("requests-2.31.0.dist-info", "Name: requests\nVersion: 2.31.0\nLicense: Apache 2.0\n\n");
+        dist("six-1.16.0.dist-info", "Name: six\nVersion: 1.16.0\nLicense: UNKNOWN\nClassifier: License :: OSI Approved :: MIT License\n\n");
+        dist("attrs-23.2.0.dist-info", "Name: attrs\nVersion: 23.2.0\nLicense-Expression: MIT\n\n");
+        dist("mystery-0.1.dist-info", "Name: mystery\nVersion: 0.1\n\n");
+
+        let packages = installed_licenses(&site).unwrap();
+        let report = license_report(&site, &packages, &LicensePolicy::new(strings(&["MIT"]), Vec::new()));
+        assert_eq!(report.packages, 4);
+        assert_eq!(report.licenses["MIT"], vec!["attrs==23.2.0"]);
+        assert_eq!(report.licenses["MIT License"], vec!["six==1.16.0"]);
+        assert_eq!(report.licenses[UNKNOWN], vec!["mystery==0.1"]);
+        let rejected: Vec<&str> = report.violations.iter().map(|v| v.package.as_str()).collect();
+        assert_eq!(rejected, vec!["mystery", "requests"]);
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
@@ -0,0 +1,388 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use crate::lockfile::{LockedFile, LockedPackage, Lockfile, PackageSource};
+use crate::manifest::canonical_name;
+
+/// Lockfiles from other tools that `lock --import` converts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignLock {
+    Poetry,
+    Pipenv,
+    Uv,
+}
+
+impl ForeignLock {
+    /// Tell the format from the file name: `poetry.lock`, `Pipfile.lock` or `uv.lock`
+    pub fn detect(path: &Path) -> Result<Self> {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        match name.as_str() {
+            "poetry.lock" => Ok(ForeignLock::Poetry),
+            "pipfile.lock" => Ok(ForeignLock::Pipenv),
+            "uv.lock" => Ok(ForeignLock::Uv),
+            _ => Err(anyhow::anyhow!("Don't know how to import {} (expected poetry.lock, Pipfile.lock or uv.lock)", path.display())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ForeignLock::Poetry => "Poetry",
+            ForeignLock::Pipenv => "Pipenv",
+            ForeignLock::Uv => "uv",
+        }
+    }
+}
+
+/// What came over, plus anything that couldn't be carried across as-is
+#[derive(Debug)]
+pub struct Imported {
+    pub lockfile: Lockfile,
+    pub warnings: Vec<String>,
+}
+
+/// Convert `path` into a snakepit lockfile with the same pins, without resolving anything
+pub fn import(path: &Path) -> Result<Imported> {
+    let format = ForeignLock::detect(path)?;
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(format, &content).with_context(|| format!("Invalid {} lockfile {}", format.name(), path.display()))
+}
+
+pub fn parse(format: ForeignLock, content: &str) -> Result<Imported> {
+    let mut import = Importer::default();
+    match format {
+        ForeignLock::Poetry => import.poetry(content)?,
+        ForeignLock::Pipenv => import.pipenv(content)?,
+        ForeignLock::Uv => import.uv(content)?,
+    }
+    Ok(import.finish())
+}
+
+/// A package as read, before its dependencies are pinned to the versions in the same lock
+struct Pending {
+    package: LockedPackage,
+    /// `(name, marker)` of each requirement
+    requires: Vec<(String, Option<String>)>,
+}
+
+#[derive(Default)]
+struct Importer {
+    pending: Vec<Pending>,
+    /// Extras dependents ask of each package
+    extras: HashMap<String, Vec<String>>,
+    warnings: Vec<String>,
+}
+
+impl Importer {
+    fn poetry(&mut self, content: &str) -> Result<()> {
+        let doc: toml::Value = toml::from_str(content)?;
+        // Poetry before 1.5 kept file hashes in [metadata.files]
+        let legacy_files = doc.get("metadata").and_then(|m| m.get("files")).and_then(|f| f.as_table());
+
+        for entry in doc.get("package").and_then(|p| p.as_array()).into_iter().flatten() {
+            let (name, version) = name_and_version(entry)?;
+            if let Some(source) = entry.get("source") {
+                match str_field(source, "type").as_deref() {
+                    Some("legacy") => {}
+                    kind => {
+                        self.skip_sourced(&name, &str_field(source, "url").unwrap_or_else(|| kind.unwrap_or("unknown").to_string()));
+                        continue;
+                    }
+                }
+            }
+
+            let files = entry.get("files")
+                .or_else(|| legacy_files.and_then(|files| files.get(&name)))
+                .and_then(|f| f.as_array());
+            let hashes = files.into_iter().flatten()
+                .filter_map(|file| str_field(file, "hash"))
+                .collect();
+
+            let mut requires = Vec::new();
+            for (dep, spec) in entry.get("dependencies").and_then(|d| d.as_table()).into_iter().flatten() {
+                // One requirement, or several for different markers
+                let specs = match spec.as_array() {
+                    Some(alternatives) => alternatives.iter().collect(),
+                    None => vec![spec],
+                };
+                for spec in specs {
+                    self.request_extras(dep, spec.get("extras"));
+                    requires.push((dep.clone(), str_field(spec, "markers")));
+                }
+            }
+
+            let mut package = locked(&name, &version, index_source(entry.get("source"), &name));
+            package.hashes = hashes;
+            package.requires_python = str_field(entry, "python-versions").filter(|r| r.trim() != "*" && !r.trim().is_empty());
+            self.pending.push(Pending { package, requires });
+        }
+        Ok(())
+    }
+
+    fn pipenv(&mut self, content: &str) -> Result<()> {
+        let doc: serde_json::Value = serde_json::from_str(content)?;
+        for section in ["default", "develop"] {
+            for (name, entry) in doc[section].as_object().into_iter().flatten() {
+                let source = entry["git"].as_str().or(entry["path"].as_str()).or(entry["file"].as_str());
+                if let Some(source) = source {
+                    self.skip_sourced(name, source);
+                    continue;
+                }
+                let Some(version) = entry["version"].as_str() else {
+                    self.warnings.push(format!("{} has no pinned version; not imported", name));
+                    continue;
+                };
+                // Pipenv pins the packages themselves, not the edges between them
+                if let Some(marker) = entry["markers"].as_str() {
+                    self.warnings.push(format!("{} is only needed where {}; snakepit.lock installs it everywhere", name, marker));
+                }
+
+                let mut package = locked(name, version.trim_start_matches("=="), PackageSource::PyPI {
+                    url: format!("https://pypi.org/simple/{}/", canonical_name(name)),
+                });
+                package.hashes = entry["hashes"].as_array().into_iter().flatten()
+                    .filter_map(|hash| hash.as_str().map(str::to_string))
+                    .collect();
+                package.extras = entry["extras"].as_array().into_iter().flatten()
+                    .filter_map(|extra| extra.as_str().map(str::to_string))
+                    .collect();
+                self.pending.push(Pending { package, requires: Vec::new() });
+            }
+        }
+        Ok(())
+    }
+
+    fn uv(&mut self, content: &str) -> Result<()> {
+        let doc: toml::Value = toml::from_str(content)?;
+        let requires_python = str_field(&doc, "requires-python");
+
+        for entry in doc.get("package").and_then(|p| p.as_array()).into_iter().flatten() {
+            let name = str_field(entry, "name").ok_or_else(|| anyhow::anyhow!("package without a name"))?;
+            let source = entry.get("source");
+            let requires: Vec<&toml::Value> = entry.get("dependencies").and_then(|d| d.as_array()).into_iter().flatten().collect();
+            for dep in &requires {
+                if let Some(dep_name) = str_field(dep, "name") {
+                    self.request_extras(&dep_name, dep.get("extra"));
+                }
+            }
+            // The project itself and local workspace members are installed from source by sync
+            if source.is_some_and(|s| s.get("editable").is_some() || s.get("virtual").is_some()) {
+                continue;
+            }
+            let Some(registry) = source.and_then(|s| str_field(s, "registry")) else {
+                let origin = source.and_then(|s| ["git", "url", "path", "directory"].iter().find_map(|key| str_field(s, key)));
+                self.skip_sourced(&name, &origin.unwrap_or_else(|| "an unknown source".to_string()));
+                continue;
+            };
+            let (_, version) = name_and_version(entry)?;
+
+            let mut package = locked(&name, &version, PackageSource::PyPI {
+                url: format!("{}/{}/", registry.trim_end_matches('/'), canonical_name(&name)),
+            });
+            let sdist = entry.get("sdist").into_iter();
+            let wheels = entry.get("wheels").and_then(|w| w.as_array()).into_iter().flatten();
+            for file in sdist.chain(wheels) {
+                let Some(url) = str_field(file, "url") else { continue };
+                let hash = str_field(file, "hash");
+                package.hashes.extend(hash.clone());
+                package.files.push(LockedFile {
+                    filename: url.rsplit('/').next().unwrap_or(&url).to_string(),
+                    url,
+                    hash,
+                });
+            }
+            package.requires_python = requires_python.clone();
+
+            let requires = requires.iter()
+                .filter_map(|dep| Some((str_field(dep, "name")?, str_field(dep, "marker"))))
+                .collect();
+            self.pending.push(Pending { package, requires });
+        }
+        Ok(())
+    }
+
+    fn skip_sourced(&mut self, name: &str, source: &str) {
+        self.warnings.push(format!("{} comes from {}; it is not locked", name, source));
+    }
+
+    fn request_extras(&mut self, package: &str, extras: Option<&toml::Value>) {
+        let requested = extras.and_then(|e| e.as_array()).into_iter().flatten()
+            .filter_map(|extra| extra.as_str().map(str::to_string));
+        self.extras.entry(canonical_name(package)).or_default().extend(requested);
+    }
+
+    /// Pin every requirement to the version locked for it; requirements on packages
+    /// that aren't in the lock (unrequested optional ones) are dropped
+    fn finish(self) -> Imported {
+        let versions: HashMap<String, String> = self.pending.iter()
+            .map(|pending| (canonical_name(&pending.package.name), pending.package.version.clone()))
+            .collect();
+
+        let mut lockfile = Lockfile::new();
+        for Pending { mut package, requires } in self.pending {
+            for (name, marker) in requires {
+                let name = canonical_name(&name);
+                let Some(version) = versions.get(&name) else { continue };
+                package.dependencies.push(match marker {
+                    Some(marker) => format!("{}=={}; {}", name, version, marker.trim()),
+                    None => format!("{}=={}", name, version),
+                });
+            }
+            if let Some(extras) = self.extras.get(&canonical_name(&package.name)) {
+                package.extras.extend(extras.iter().cloned());
+            }
+            lockfile.add_package(package);
+        }
+        lockfile.normalize();
+        Imported { lockfile, warnings: self.warnings }
+    }
+}
+
+fn locked(name: &str, version: &str, source: PackageSource) -> LockedPackage {
+    LockedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        dependencies: Vec::new(),
+        hashes: Vec::new(),
+        requires_python: None,
+        source,
+        extras: Vec::new(),
+        files: Vec::new(),
+        environments: Vec::new(),
+    }
+}
+
+/// PyPI, or the custom index a Poetry `legacy` source points at
+fn index_source(source: Option<&toml::Value>, name: &str) -> PackageSource {
+    let index = source.and_then(|s| str_field(s, "url")).unwrap_or_else(|| "https://pypi.org/simple".to_string());
+    PackageSource::PyPI { url: format!("{}/{}/", index.trim_end_matches('/'), canonical_name(name)) }
+}
+
+fn name_and_version(entry: &toml::Value) -> Result<(String, String)> {
+    let name = str_field(entry, "name").ok_or_else(|| anyhow::anyhow!("package without a name"))?;
+    let version = str_field(entry, "version").ok_or_else(|| anyhow::anyhow!("{} has no version", name))?;
+    Ok((name, version))
+}
+
+fn str_field(value: &toml::Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(lockfile: &'a Lockfile, name: &str) -> &'a LockedPackage {
+        lockfile.packages.iter().find(|p| p.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_poetry_lock() {
+        let imported = parse(ForeignLock::Poetry, r#"
+            [[package]]
+            name = "requests"
+            version = "2.31.0"
+            python-versions = ">=3.7"
+            files = [{file = "requests-2.31.0-py3-none-any.whl", hash = "sha256:BB"}]
+
+            [package.dependencies]
+            urllib3 = ">=1.21.1,<3"
+            PySocks = {version = ">=1.5.6,!=1.5.7", optional = true}
+            win-inet-pton = {version = "*", markers = "sys_platform == \"win32\""}
+
+            [[package]]
+            name = "urllib3"
+            version = "2.2.1"
+            python-versions = "*"
+            files = []
+
+            [[package]]
+            name = "win-inet-pton"
+            version = "1.1.0"
+            python-versions = "*"
+            files = []
+
+            [[package]]
+            name = "mylib"
+            version = "0.1.0"
+            [package.source]
+            type = "git"
+            url = "https://github.com/acme/mylib.git"
+            reference = "main"
+            resolved_reference = "abc123"
+        "#).unwrap();
+
+        let requests = find(&imported.lockfile, "requests");
+        assert_eq!(requests.hashes, vec!["sha256:bb"]);
+        assert_eq!(requests.requires_python.as_deref(), Some(">=3.7"));
+        assert_eq!(requests.dependencies, vec!["urllib3==2.2.1", "win-inet-pton==1.1.0; sys_platform == \"win32\""]);
+        assert!(find(&imported.lockfile, "urllib3").requires_python.is_none());
+        assert_eq!(imported.lockfile.packages.len(), 3);
+        assert!(imported.warnings[0].starts_with("mylib comes from https://github.com/acme/mylib.git"));
+    }
+
+    #[test]
+    fn test_pipfile_lock() {
+        let imported = parse(ForeignLock::Pipenv, r#"{
+            "_meta": {"hash": {"sha256": "x"}},
+            "default": {
+                "requests": {"version": "==2.31.0", "hashes": ["sha256:aa", "sha256:bb"], "extras": ["socks"]},
+                "colorama": {"version": "==0.4.6", "hashes": [], "markers": "sys_platform == 'win32'"},
+                "mylib": {"git": "https://github.com/acme/mylib.git", "ref": "abc123"}
+            },
+            "develop": {"pytest": {"version": "==8.0.0", "hashes": ["sha256:cc"]}}
+        }"#).unwrap();
+
+        let requests = find(&imported.lockfile, "requests");
+        assert_eq!(requests.version, "2.31.0");
+        assert_eq!(requests.hashes.len(), 2);
+        assert_eq!(requests.extras, vec!["socks"]);
+        assert_eq!(find(&imported.lockfile, "pytest").version, "8.0.0");
+        assert_eq!(imported.lockfile.packages.len(), 3);
+        assert_eq!(imported.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_uv_lock() {
+        let imported = parse(ForeignLock::Uv, r#"
+            version = 1
+            requires-python = ">=3.9"
+
+            [[package]]
+            name = "demo"
+            version = "0.1.0"
+            source = { editable = "." }
+            dependencies = [{ name = "requests", extra = ["socks"] }]
+
+            [[package]]
+            name = "requests"
+            version = "2.31.0"
+            source = { registry = "https://pypi.org/simple" }
+            dependencies = [{ name = "urllib3" }, { name = "pysocks", marker = "extra == 'socks'" }]
+            sdist = { url = "https://files.example/requests-2.31.0.tar.gz", hash = "sha256:aa", size = 1 }
+            wheels = [{ url = "https://files.example/requests-2.31.0-py3-none-any.whl", hash = "sha256:bb", size = 1 }]
+
+            [[package]]
+            name = "urllib3"
+            version = "2.2.1"
+            source = { registry = "https://pypi.org/simple" }
+
+            [[package]]
+            name = "pysocks"
+            version = "1.7.1"
+            source = { registry = "https://pypi.org/simple" }
+        "#).unwrap();
+
+        assert!(imported.warnings.is_empty());
+        assert_eq!(imported.lockfile.packages.len(), 3);
+        let requests = find(&imported.lockfile, "requests");
+        assert_eq!(requests.files.len(), 2);
+        assert_eq!(requests.files[0].filename, "requests-2.31.0-py3-none-any.whl");
+        assert_eq!(requests.extras, vec!["socks"]);
+        assert_eq!(requests.dependencies, vec!["pysocks==1.7.1; extra == 'socks'", "urllib3==2.2.1"]);
+        assert_eq!(requests.requires_python.as_deref(), Some(">=3.9"));
+        assert!(matches!(&requests.source, PackageSource::PyPI { url } if url == "https://pypi.org/simple/requests/"));
+        assert!(ForeignLock::detect(Path::new("Pipfile.lock")).is_ok());
+        assert!(ForeignLock::detect(Path::new("yarn.lock")).is_err());
+    }
+}
@@ -1,31 +1,137 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use tokio::fs;
+use crate::manifest::canonical_name;
 use crate::pep440::Version;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Default lockfile name in the project root
+pub const LOCKFILE_NAME: &str = "snakepit.lock";
+
+/// Layout version written to `metadata.version` and the header; bump when the layout changes
+pub const FORMAT_VERSION: &str = "1.2";
+
+/// First line of every lockfile snakepit writes
+fn header() -> String {
+    format!("# snakepit lockfile, format {}. Generated by `snakepit lock`; do not edit by hand.\n", FORMAT_VERSION)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Lockfile {
     pub metadata: LockfileMetadata,
+    #[serde(default)]
+    pub inputs: ResolutionInputs,
     pub packages: Vec<LockedPackage>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LockfileMetadata {
     pub version: String,
     pub generator: String,
     pub timestamp: u64,
     pub python_version: Option<String>,
+    /// Targets the lock was resolved for, e.g. `linux-3.12`; empty for the host only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<String>,
+    /// `lowest` when resolved for minimum versions; absent for the usual highest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
+    /// Releases uploaded after this time (UTC) were ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude_newer: Option<String>,
+}
+
+/// What the lock was resolved from, so a reviewer can tell why it changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResolutionInputs {
+    /// Requirements as declared in the manifest, e.g. `requests[socks]>=2.28`
+    #[serde(default)]
+    pub requirements: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dev_requirements: Vec<String>,
+    /// `None` for PyPI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_url: Option<String>,
+    /// `[overrides]` and `[pins]` from snakepit.toml, e.g. `urllib3>=1.26,<3`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<String>,
+}
+
+impl ResolutionInputs {
+    /// How `current` differs from what was locked, as `+`/`-` lines; empty when the lock is up to date
+    pub fn diff(&self, current: &ResolutionInputs) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut compare = |label: &str, locked: &[String], current: &[String]| {
+            let locked: std::collections::BTreeSet<&String> = locked.iter().collect();
+            let current: std::collections::BTreeSet<&String> = current.iter().collect();
+            lines.extend(locked.difference(&current).map(|r| format!("- {}{}", r, label)));
+            lines.extend(current.difference(&locked).map(|r| format!("+ {}{}", r, label)));
+        };
+        compare("", &self.requirements, &current.requirements);
+        compare(" (dev)", &self.dev_requirements, &current.dev_requirements);
+        compare(" (override)", &self.overrides, &current.overrides);
+
+        if self.index_url != current.index_url {
+            let index = |url: &Option<String>| url.clone().unwrap_or_else(|| "PyPI".to_string());
+            lines.push(format!("- index {}", index(&self.index_url)));
+            lines.push(format!("+ index {}", index(&current.index_url)));
+        }
+        lines
+    }
+}
+
+/// One package that differs between two lockfiles, and the requirement that brought it in
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageChange {
+    pub name: String,
+    /// `None` when the package is new
+    pub old: Option<String>,
+    /// `None` when the package is gone
+    pub new: Option<String>,
+    /// e.g. `requests>=2.31 (was requests>=2.28)` or `via flask, requests`
+    pub cause: Option<String>,
+}
+
+impl PackageChange {
+    pub fn kind(&self) -> &'static str {
+        match (&self.old, &self.new) {
+            (None, _) => "added",
+            (_, None) => "removed",
+            (Some(old), Some(new)) => match (Version::parse(old), Version::parse(new)) {
+                (Ok(old), Ok(new)) if new < old => "downgraded",
+                _ => "upgraded",
+            },
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LockedPackage {
     pub name: String,
     pub version: String,
+    /// Pinned requirements, `name==version` with the environment marker they apply under, if any
     pub dependencies: Vec<String>,
     pub hashes: Vec<String>, // SHA256 hashes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_python: Option<String>,
     pub source: PackageSource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<String>,
+    /// Every distribution file of the locked version
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<LockedFile>,
+    /// Targets that need this package; empty when all of them do
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LockedFile {
+    pub filename: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -40,14 +146,18 @@ impl Lockfile {
     pub fn new() -> Self {
         Self {
             metadata: LockfileMetadata {
-                version: "1.0".to_string(),
+                version: FORMAT_VERSION.to_string(),
                 generator: "snakepit".to_string(),
                 timestamp: std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap_or_default()
                     .as_secs(),
                 python_version: None,
+                environments: Vec::new(),
+                resolution: None,
+                exclude_newer: None,
             },
+            inputs: ResolutionInputs::default(),
             packages: Vec::new(),
         }
     }
@@ -57,17 +167,189 @@ impl Lockfile {
     }
 
     pub async fn save(&self, path: &Path) -> Result<()> {
-        let toml_string = toml::to_string_pretty(self)?;
-        fs::write(path, toml_string).await?;
+        fs::write(path, self.to_canonical_string()?).await?;
         Ok(())
     }
 
+    /// Put everything in the one order `lock` writes: packages by normalized name,
+    /// hashes, dependencies and extras sorted, versions in PEP 440 normal form
+    pub fn normalize(&mut self) {
+        for package in &mut self.packages {
+            if let Ok(version) = Version::parse(&package.version) {
+                package.version = version.to_string();
+            }
+            package.hashes = package.hashes.iter().map(|h| h.trim().to_lowercase()).collect();
+            for list in [&mut package.hashes, &mut package.dependencies, &mut package.extras, &mut package.environments] {
+                list.sort();
+                list.dedup();
+            }
+            package.files.sort();
+            package.files.dedup();
+        }
+        self.metadata.environments.sort();
+        self.metadata.environments.dedup();
+        self.inputs.requirements.sort();
+        self.inputs.dev_requirements.sort();
+        self.inputs.overrides.sort();
+        self.packages.sort_by(|a, b| {
+            canonical_name(&a.name).cmp(&canonical_name(&b.name))
+                .then_with(|| a.version.cmp(&b.version))
+        });
+    }
+
+    /// Record the targets and which packages each one needs, walking from `roots`
+    /// and following only the edges whose markers hold there
+    pub fn assign_environments(&mut self, roots: &[String], environments: &[crate::markers::TargetEnvironment]) {
+        self.metadata.environments = environments.iter().map(|env| env.label()).collect();
+        let index: HashMap<String, usize> = self.packages.iter().enumerate()
+            .map(|(i, package)| (canonical_name(&package.name), i))
+            .collect();
+
+        let mut needed: Vec<Vec<String>> = vec![Vec::new(); self.packages.len()];
+        for env in environments {
+            let mut seen = HashSet::new();
+            let mut stack: Vec<usize> = roots.iter().filter_map(|root| index.get(&canonical_name(root)).copied()).collect();
+            while let Some(i) = stack.pop() {
+                if !seen.insert(i) {
+                    continue;
+                }
+                needed[i].push(env.label());
+                let package = &self.packages[i];
+                let env = env.clone().with_extras(&package.extras);
+                for requirement in &package.dependencies {
+                    let Ok(spec) = crate::markers::parse_requirement(requirement) else {
+                        continue;
+                    };
+                    if spec.marker.as_ref().is_some_and(|m| !m.evaluate(&env)) {
+                        continue;
+                    }
+                    stack.extend(index.get(&canonical_name(&spec.name)).copied());
+                }
+            }
+        }
+
+        for (package, needed) in self.packages.iter_mut().zip(needed) {
+            package.environments = if needed.len() == environments.len() { Vec::new() } else { needed };
+        }
+    }
+
+    /// Whether the lock was resolved for `env` (always true for a host-only lock)
+    pub fn covers(&self, env: &crate::markers::TargetEnvironment) -> bool {
+        self.metadata.environments.is_empty() || self.metadata.environments.contains(&env.label())
+    }
+
+    /// The packages `env` needs
+    pub fn packages_for(&self, env: &crate::markers::TargetEnvironment) -> Vec<&LockedPackage> {
+        let label = env.label();
+        self.packages.iter()
+            .filter(|package| package.environments.is_empty() || package.environments.contains(&label))
+            .collect()
+    }
+
+    /// The exact text `lock` writes for this lockfile, header included
+    pub fn to_canonical_string(&self) -> Result<String> {
+        let mut normalized = self.clone();
+        normalized.normalize();
+        Ok(format!("{}\n{}", header(), toml::to_string_pretty(&normalized)?))
+    }
+
+    /// Ways `content` differs from what `lock` would write; empty when it's canonical
+    pub fn check_format(content: &str) -> Result<Vec<String>> {
+        let lockfile: Lockfile = toml::from_str(content)?;
+        let canonical = lockfile.to_canonical_string()?;
+        if content == canonical {
+            return Ok(Vec::new());
+        }
+
+        let mut problems = Vec::new();
+        if !content.starts_with(&header()) {
+            problems.push(format!("missing or outdated header (expected format {})", FORMAT_VERSION));
+        }
+        if lockfile.metadata.version != FORMAT_VERSION {
+            problems.push(format!("metadata.version is {}, expected {}", lockfile.metadata.version, FORMAT_VERSION));
+        }
+
+        let names: Vec<String> = lockfile.packages.iter().map(|p| canonical_name(&p.name)).collect();
+        if names.windows(2).any(|pair| pair[0] > pair[1]) {
+            problems.push("packages are not sorted by name".to_string());
+        }
+        for package in &lockfile.packages {
+            if Version::parse(&package.version).is_ok_and(|v| v.to_string() != package.version) {
+                problems.push(format!("{}: version {} is not normalized", package.name, package.version));
+            }
+            let sorted = |list: &[String]| list.windows(2).all(|pair| pair[0] < pair[1]);
+            if !sorted(&package.hashes) || package.hashes.iter().any(|h| *h != h.to_lowercase()) {
+                problems.push(format!("{}: hashes are not sorted lowercase digests", package.name));
+            }
+            if !sorted(&package.dependencies) || !sorted(&package.extras) {
+                problems.push(format!("{}: dependencies or extras are not sorted", package.name));
+            }
+        }
+
+        if problems.is_empty() {
+            problems.push("layout differs from the generated output (hand-edited?)".to_string());
+        }
+        Ok(problems)
+    }
+
     pub async fn load(path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path).await?;
         let lockfile: Lockfile = toml::from_str(&content)?;
         Ok(lockfile)
     }
 
+    /// Packages added, removed or moved to another version in `newer`, by name
+    pub fn diff(&self, newer: &Lockfile) -> Vec<PackageChange> {
+        let versions = |lock: &Lockfile| lock.packages.iter()
+            .map(|package| (canonical_name(&package.name), package.version.clone()))
+            .collect::<std::collections::BTreeMap<String, String>>();
+        let (before, after) = (versions(self), versions(newer));
+
+        let names: std::collections::BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        names.into_iter()
+            .filter(|name| before.get(*name) != after.get(*name))
+            .map(|name| {
+                // A removed package is explained by what used to need it
+                let cause = if after.contains_key(name) {
+                    newer.cause(name, Some(self))
+                } else {
+                    self.cause(name, None)
+                };
+                PackageChange { name: name.clone(), old: before.get(name).cloned(), new: after.get(name).cloned(), cause }
+            })
+            .collect()
+    }
+
+    /// Why `name` is in the lock: the manifest requirement naming it (and what it was
+    /// in `previous`, if that differs), else the locked packages that depend on it
+    fn cause(&self, name: &str, previous: Option<&Lockfile>) -> Option<String> {
+        let requirement = |lock: &Lockfile| lock.inputs.requirements.iter()
+            .chain(lock.inputs.dev_requirements.iter())
+            .find(|req| crate::markers::parse_requirement(req).is_ok_and(|spec| canonical_name(&spec.name) == name))
+            .cloned();
+        if let Some(current) = requirement(self) {
+            return Some(match previous.and_then(requirement) {
+                Some(old) if old != current => format!("{} (was {})", current, old),
+                _ => current,
+            });
+        }
+
+        let dependents: Vec<String> = self.packages.iter()
+            .filter(|package| package.dependencies.iter()
+                .any(|dep| crate::markers::parse_requirement(dep).is_ok_and(|spec| canonical_name(&spec.name) == name)))
+            .map(|package| canonical_name(&package.name))
+            .collect();
+        (!dependents.is_empty()).then(|| format!("via {}", dependents.join(", ")))
+    }
+
+    /// Pinned hashes per package, as consumed by `--require-hashes`
+    pub fn hashes_by_package(&self) -> HashMap<String, Vec<String>> {
+        self.packages.iter()
+            .filter(|p| !p.hashes.is_empty())
+            .map(|p| (p.name.clone(), p.hashes.clone()))
+            .collect()
+    }
+
     pub fn verify_integrity(&self) -> bool {
         // Check if all packages have at least one hash
         for package in &self.packages {
@@ -77,32 +359,50 @@ impl Lockfile {
         }
         true
     }
-
-
 }
 
 pub struct LockfileGenerator {
     resolver: crate::resolver::DependencyResolver,
+    environments: Vec<crate::markers::TargetEnvironment>,
 }
 
 impl LockfileGenerator {
     pub fn new() -> Self {
         Self {
             resolver: crate::resolver::DependencyResolver::new(),
+            environments: Vec::new(),
         }
     }
 
+    /// Keep dependency edges needed on any of these targets (the solver's targets)
+    pub fn with_environments(mut self, environments: Vec<crate::markers::TargetEnvironment>) -> Self {
+        self.environments = environments;
+        self
+    }
+
+    /// Look packages up through this resolver (and its configured index) instead of a default one
+    pub fn with_resolver(mut self, resolver: crate::resolver::DependencyResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
     pub async fn generate_from_solver(
         &mut self,
         resolved: &HashMap<String, Version>,
+        extras: &HashMap<String, HashSet<String>>,
     ) -> Result<Lockfile> {
         let mut lockfile = Lockfile::new();
 
         for (name, version) in resolved {
-            let locked = self.create_locked_package(name, version).await?;
+            let requested: Vec<String> = extras.get(name)
+                .map(|e| e.iter().cloned().collect())
+                .unwrap_or_default();
+            let mut locked = self.create_locked_package(name, version, &requested, resolved).await?;
+            locked.extras = requested;
             lockfile.add_package(locked);
         }
 
+        lockfile.normalize();
         Ok(lockfile)
     }
 
@@ -110,31 +410,45 @@ impl LockfileGenerator {
         &mut self,
         name: &str,
         version: &Version,
+        extras: &[String],
+        resolved: &HashMap<String, Version>,
     ) -> Result<LockedPackage> {
-        // Fetch package info to get hashes
         let info = self.resolver.fetch_package_info(name).await?;
-        
         let version_str = version.to_string();
-        let mut hashes = Vec::new();
-        let mut dependencies = Vec::new();
 
-        // Find the release for this version
-        if let Some(releases) = info.releases.get(&version_str) {
-            for release in releases {
-                if let Some(digests) = &release.digests {
-                    if let Some(sha256) = digests.get("sha256") {
-                        hashes.push(format!("sha256:{}", sha256));
-                    }
-                }
-            }
+        // Release keys are as uploaded, not necessarily normalized
+        let files = info.releases.iter()
+            .find(|(v, _)| Version::parse(v).is_ok_and(|v| v == *version))
+            .map(|(_, files)| files.as_slice())
+            .unwrap_or_default();
+
+        let mut hashes = Vec::new();
+        let mut locked_files = Vec::new();
+        for file in files {
+            let sha256 = file.digests.as_ref()
+                .and_then(|digests| digests.get("sha256"))
+                .map(|sha256| format!("sha256:{}", sha256));
+            hashes.extend(sha256.clone());
+            locked_files.push(LockedFile { filename: file.filename.clone(), url: file.url.clone(), hash: sha256 });
         }
+        let requires_python = files.iter().find_map(|f| f.requires_python.clone()).filter(|r| !r.trim().is_empty());
 
-        // Extract dependencies
-        if let Some(requires) = &info.info.requires_dist {
-            for req_str in requires {
-                if let Ok(spec) = crate::markers::parse_requirement(req_str) {
-                    dependencies.push(format!("{}=={}", spec.name, version_str));
-                }
+        // Pin each requirement to what the solver chose, keeping its marker so it
+        // still only applies where it did
+        let mut dependencies = Vec::new();
+        for req_str in &self.resolver.fetch_requires_dist(name, &version_str).await? {
+            let Ok(spec) = crate::markers::parse_requirement(req_str) else {
+                continue;
+            };
+            if spec.marker.as_ref().is_some_and(|m| !m.evaluate_any(&self.environments, extras)) {
+                continue;
+            }
+            let dep_name = canonical_name(&spec.name);
+            if let Some(dep_version) = resolved.get(&dep_name) {
+                dependencies.push(match &spec.marker {
+                    Some(marker) => format!("{}=={}; {}", dep_name, dep_version, marker.raw.trim()),
+                    None => format!("{}=={}", dep_name, dep_version),
+                });
             }
         }
 
@@ -143,9 +457,168 @@ impl LockfileGenerator {
             version: version_str,
             dependencies,
             hashes,
+            requires_python,
             source: PackageSource::PyPI {
                 url: format!("https://pypi.org/simple/{}/", name),
             },
+            extras: Vec::new(),
+            files: locked_files,
+            environments: Vec::new(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, hashes: &[&str]) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies: Vec::new(),
+            hashes: hashes.iter().map(|h| h.to_string()).collect(),
+            requires_python: None,
+            source: PackageSource::PyPI { url: format!("https://pypi.org/simple/{}/", name) },
+            extras: Vec::new(),
+            files: Vec::new(),
+            environments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_serialization_is_order_independent() {
+        let mut forward = Lockfile::new();
+        forward.add_package(package("requests", "2.31.0", &["sha256:bb", "sha256:aa"]));
+        forward.add_package(package("Django", "5.0-beta1", &[]));
+
+        let mut reverse = forward.clone();
+        reverse.packages.reverse();
+        reverse.packages[1].hashes.reverse();
+
+        let text = forward.to_canonical_string().unwrap();
+        assert_eq!(text, reverse.to_canonical_string().unwrap());
+        assert!(text.starts_with("# snakepit lockfile, format 1.2."));
+        assert!(text.find("name = \"Django\"").unwrap() < text.find("name = \"requests\"").unwrap());
+        assert!(text.contains("version = \"5.0b1\""));
+        assert!(text.find("sha256:aa").unwrap() < text.find("sha256:bb").unwrap());
+    }
+
+    #[test]
+    fn test_files_and_inputs_round_trip() {
+        let mut lockfile = Lockfile::new();
+        lockfile.inputs.requirements = vec!["requests>=2.28".to_string(), "click".to_string()];
+        let mut requests = package("requests", "2.31.0", &["sha256:aa"]);
+        requests.dependencies = vec!["pysocks==1.7.1; extra == \"socks\"".to_string()];
+        requests.requires_python = Some(">=3.7".to_string());
+        requests.files = vec![
+            LockedFile { filename: "requests-2.31.0.tar.gz".to_string(), url: "https://files.example/r.tar.gz".to_string(), hash: Some("sha256:bb".to_string()) },
+            LockedFile { filename: "requests-2.31.0-py3-none-any.whl".to_string(), url: "https://files.example/r.whl".to_string(), hash: Some("sha256:aa".to_string()) },
+        ];
+        lockfile.add_package(requests);
+
+        let text = lockfile.to_canonical_string().unwrap();
+        assert!(Lockfile::check_format(&text).unwrap().is_empty());
+        assert!(text.find("none-any.whl").unwrap() < text.find("tar.gz").unwrap());
+
+        let parsed: Lockfile = toml::from_str(&text).unwrap();
+        assert_eq!(parsed.inputs.requirements, vec!["click", "requests>=2.28"]);
+        assert_eq!(parsed.packages[0].files.len(), 2);
+        assert_eq!(parsed.packages[0].requires_python.as_deref(), Some(">=3.7"));
+        assert_eq!(parsed.packages[0].dependencies, vec!["pysocks==1.7.1; extra == \"socks\""]);
+    }
+
+    #[test]
+    fn test_inputs_diff_shows_drift() {
+        let locked = ResolutionInputs {
+            requirements: vec!["flask".to_string(), "requests>=2.28".to_string()],
+            dev_requirements: vec!["pytest".to_string()],
+            index_url: None,
+            overrides: Vec::new(),
+        };
+        assert!(locked.diff(&locked.clone()).is_empty());
+
+        let current = ResolutionInputs {
+            requirements: vec!["requests>=2.31".to_string(), "flask".to_string()],
+            dev_requirements: vec!["pytest".to_string(), "ruff".to_string()],
+            index_url: Some("https://devpi.internal/simple/".to_string()),
+            overrides: vec!["urllib3>=1.26,<3".to_string()],
+        };
+        assert_eq!(locked.diff(&current), vec![
+            "- requests>=2.28",
+            "+ requests>=2.31",
+            "+ ruff (dev)",
+            "+ urllib3>=1.26,<3 (override)",
+            "- index PyPI",
+            "+ index https://devpi.internal/simple/",
+        ]);
+    }
+
+    #[test]
+    fn test_assigns_packages_to_the_targets_that_need_them() {
+        let mut lockfile = Lockfile::new();
+        let mut click = package("click", "8.1.7", &[]);
+        click.dependencies = vec!["colorama==0.4.6; platform_system == \"Windows\"".to_string()];
+        lockfile.add_package(click);
+        lockfile.add_package(package("colorama", "0.4.6", &[]));
+
+        let linux = crate::markers::TargetEnvironment::parse_target("linux-3.12").unwrap();
+        let windows = crate::markers::TargetEnvironment::parse_target("windows-3.12").unwrap();
+        lockfile.assign_environments(&["click".to_string()], &[linux.clone(), windows.clone()]);
+
+        assert_eq!(lockfile.metadata.environments, vec!["linux-3.12", "windows-3.12"]);
+        assert!(lockfile.packages[0].environments.is_empty());
+        assert_eq!(lockfile.packages[1].environments, vec!["windows-3.12"]);
+        assert_eq!(lockfile.packages_for(&linux).len(), 1);
+        assert_eq!(lockfile.packages_for(&windows).len(), 2);
+        assert!(!lockfile.covers(&crate::markers::TargetEnvironment::parse_target("macos-3.12").unwrap()));
+    }
+
+    #[test]
+    fn test_diff_explains_each_change() {
+        let mut old = Lockfile::new();
+        old.inputs.requirements = vec!["flask>=2".to_string(), "requests>=2.28".to_string()];
+        let mut flask = package("flask", "2.3.0", &[]);
+        flask.dependencies = vec!["itsdangerous==2.1.2".to_string()];
+        old.add_package(flask.clone());
+        old.add_package(package("itsdangerous", "2.1.2", &[]));
+        old.add_package(package("requests", "2.28.0", &[]));
+
+        let mut new = Lockfile::new();
+        new.inputs.requirements = vec!["flask>=2".to_string(), "requests>=2.31".to_string()];
+        flask.dependencies = vec!["blinker==1.7.0".to_string()];
+        new.add_package(flask);
+        new.add_package(package("blinker", "1.7.0", &[]));
+        new.add_package(package("requests", "2.31.0", &[]));
+
+        let changes = old.diff(&new);
+        let summary: Vec<(&str, &str, Option<&str>)> = changes.iter()
+            .map(|c| (c.name.as_str(), c.kind(), c.cause.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("blinker", "added", Some("via flask")),
+            ("itsdangerous", "removed", Some("via flask")),
+            ("requests", "upgraded", Some("requests>=2.31 (was requests>=2.28)")),
+        ]);
+        assert!(new.diff(&new).is_empty());
+        assert_eq!(new.diff(&old)[2].kind(), "downgraded");
+    }
+
+    #[test]
+    fn test_check_format_reports_hand_edits() {
+        let mut lockfile = Lockfile::new();
+        lockfile.add_package(package("idna", "3.6", &["sha256:aa"]));
+        lockfile.add_package(package("certifi", "2024.02.02", &[]));
+        let canonical = lockfile.to_canonical_string().unwrap();
+        assert!(Lockfile::check_format(&canonical).unwrap().is_empty());
+
+        let unsorted = toml::to_string_pretty(&lockfile).unwrap();
+        let problems = Lockfile::check_format(&unsorted).unwrap();
+        assert!(problems.iter().any(|p| p.contains("header")));
+        assert!(problems.iter().any(|p| p.contains("not sorted by name")));
+        assert!(problems.iter().any(|p| p.contains("2024.02.02 is not normalized")));
+
+        let respaced = canonical.replace("version = \"3.6\"", "version =  \"3.6\"");
+        assert_eq!(Lockfile::check_format(&respaced).unwrap(), vec!["layout differs from the generated output (hand-edited?)"]);
+    }
+}
@@ -1,10 +1,36 @@
 use anyhow::Result;
+use std::fmt::Display;
 use std::path::PathBuf;
 use std::process::Command;
 use std::time::{SystemTime, Duration};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
-use snakegg::native::style::{dim, green, yellow};
+use snakegg::native::style::{dim, green, red, yellow};
+
+// Diagnostics always go to stderr so stdout only carries a command's data
+// (lists, JSON, exports); interactive prompts live in `crate::prompt`.
+
+/// A progress or status line, styled by the caller
+pub fn info(message: impl Display) {
+    eprintln!("{}", message);
+}
+
+/// Secondary detail, dimmed
+pub fn note(message: impl Display) {
+    eprintln!("{}", dim(message.to_string()));
+}
+
+pub fn success(message: impl Display) {
+    eprintln!("{}", green(message.to_string()));
+}
+
+pub fn warn(message: impl Display) {
+    eprintln!("{}", yellow(message.to_string()));
+}
+
+pub fn error(message: impl Display) {
+    eprintln!("{}", red(message.to_string()));
+}
 
 #[derive(Debug)]
 pub struct GitLogger {
@@ -103,7 +129,7 @@ impl GitLogger {
             .output()?;
         
         if output.status.success() {
-            println!("{}", dim("📋 Logs pushed to remote repo"));
+            note("📋 Logs pushed to remote repo");
         } else {
             // If master fails, try main
              let _ = Command::new("git")
@@ -26,10 +26,65 @@ mod pep440;
 mod solver;
 mod markers;
 mod lockfile;
+mod registry;
+mod cache;
+mod manifest;
+mod renderer;
+mod bench;
+mod upstream;
+mod simple_index;
+mod constraints;
+mod provenance;
+mod ide;
+mod daemon_log;
+mod daemon_history;
+mod approval;
+mod module_policy;
+mod notification;
+mod webhook;
+mod docker;
+mod daemon_control;
+mod crash_watch;
+mod import_hook;
+mod service;
+mod backend;
+mod staging;
+mod agent;
+mod rate_limit;
+mod archive;
+mod posture;
+mod eco;
+mod tree;
+mod package_info;
+mod freeze;
+mod audit;
+mod check;
+mod licenses;
+mod tasks;
+mod watch;
+mod inline_script;
+mod tools;
+mod build;
+mod publish;
+mod templates;
+mod doctor;
+mod output;
+mod prompt;
+mod self_update;
+mod explorer;
+mod prerelease;
+mod overrides;
+mod lock_import;
+mod export;
+mod toolchain;
+mod repair;
+mod conda_env;
+mod pyproject;
+mod plan;
 
 
 use cli::Cli;
-use config::{SnakepitConfig, ProjectConfig};
+use config::SnakepitConfig;
 use dependency::{Dependency, ProjectDependencies};
 use installer::{PackageInstaller, InstallerBackend};
 use venv::{VirtualEnvironmentManager, VenvBackend};
@@ -43,58 +98,308 @@ use snakegg::charmer::SnakeCharmer;
 async fn main() -> Result<()> {
     let cli = Cli::parse();
     
-    // Load configuration
-    let config = SnakepitConfig::load().unwrap_or_default();
+    // Load configuration: system, user, project, SNAKEPIT_* variables, then flags
+    let mut layers = config::LayeredConfig::load();
+    let flags = [
+        ("renderer", &cli.renderer),
+        ("resolution", &cli.resolution),
+        ("exclude_newer", &cli.exclude_newer),
+        ("target_triple", &cli.target_triple),
+    ];
+    for (key, value) in flags {
+        if let Some(value) = value {
+            layers.set(key, value.clone(), config::ConfigSource::Cli);
+        }
+    }
+    if cli.legacy_resolver {
+        layers.set("legacy_resolver", true, config::ConfigSource::Cli);
+    }
+    if cli.json {
+        layers.set("output", "json", config::ConfigSource::Cli);
+    }
+    if cli.yes {
+        layers.set("assume_yes", true, config::ConfigSource::Cli);
+    }
+    if cli.no_input {
+        layers.set("no_input", true, config::ConfigSource::Cli);
+    }
+    let mut config = layers.config().unwrap_or_else(|e| {
+        logger::warn(format!("⚠️  {:#}; using defaults", e));
+        SnakepitConfig::default()
+    });
+    // Where installs go: --venv or --system, else the active venv, else the project's .venv
+    let environment = match cli.command.environment_args() {
+        Some(args) if args.system => None,
+        Some(cli::EnvironmentArgs { venv: Some(path), .. }) => {
+            let path = Path::new(path);
+            if !path.join("pyvenv.cfg").exists() {
+                return Err(anyhow::anyhow!("{} is not a virtual environment", path.display()));
+            }
+            Some(path.canonicalize()?)
+        }
+        _ => std::env::current_dir().ok().and_then(|dir| {
+            registry::ProjectRegistry::detect_environment(&dir, config.project_venv.as_deref().unwrap_or(venv::PROJECT_VENV))
+        }),
+    };
+    registry::ProjectRegistry::choose_environment(environment);
+    if let Some(triple) = &config.target_triple {
+        let python_version = target_python_version(&config).unwrap_or_else(|| markers::TargetEnvironment::default().python_version);
+        markers::TargetEnvironment::set_host(markers::TargetEnvironment::parse_triple(triple, &python_version)?);
+    }
+    if cli.pre || !cli.pre_packages.is_empty() {
+        let allowed = config.allow_prereleases.get_or_insert_with(Vec::new);
+        if cli.pre {
+            allowed.push("*".to_string());
+        }
+        allowed.extend(cli.pre_packages.iter().cloned());
+    }
+    let events = renderer::EventBus::from_spec(config.renderer.as_deref())?;
+    if cli.eco || cli.no_eco {
+        config = config.with_eco_mode(cli.eco);
+    }
+    let eco_policy = eco::EcoPolicy::from_config(&config);
+    if let Some(reason) = eco_policy.activate() {
+        logger::note(format!("Eco mode: {} (use --no-eco to override)", reason));
+        let concurrency = config.pypi_max_concurrency.unwrap_or(rate_limit::DEFAULT_CONCURRENCY);
+        config.pypi_max_concurrency = Some(concurrency.min(eco_policy.max_concurrency));
+    }
+    rate_limit::configure(&config);
+    let _stats = rate_limit::StatsGuard;
+    let output = output::OutputFormat::from_config(&config)?;
+    prompt::set_mode(prompt::Interaction::from_config(&config));
     
     match cli.command {
-        cli::Commands::Install { package, version, dev } => {
-            install_package(&package, version.as_deref(), dev, &config).await?;
+        cli::Commands::Install { package, requirements, version, constraints, dry_run: true, .. } => {
+            let constraints = constraints::Constraints::from_files(&constraints)?;
+            plan_install(package.as_deref(), &requirements, version.as_deref(), &constraints, output, &config).await?;
+        }
+        cli::Commands::Install { package, requirements, version, dev, require_hashes, hashes, constraints, verify_imports, .. } => {
+            if verify_imports {
+                config.verify_imports = Some(true);
+            }
+            let constraints = constraints::Constraints::from_files(&constraints)?;
+            let mut installed = Vec::new();
+            if !requirements.is_empty() {
+                installed = install_requirements(&requirements, require_hashes, &constraints, &config, &events).await?;
+            }
+            if let Some(package) = package {
+                if is_local_archive(&package) {
+                    installed.push(install_local_archive(Path::new(&package), require_hashes.then_some(hashes.as_slice()), &events).await?);
+                } else if require_hashes {
+                    install_package_pinned(&package, version.as_deref(), dev, &hashes, &constraints, &config, &events).await?;
+                    installed.push(dependency::split_extras(&package).0);
+                } else {
+                    install_package(&package, version.as_deref(), dev, &constraints, &config, &events).await?;
+                    installed.push(dependency::split_extras(&package).0);
+                }
+            }
+            refresh_environment_agent();
+            if output.is_json() {
+                output::print_json(&output::InstallReport {
+                    environment: registry::ProjectRegistry::current_environment(),
+                    installed: installed_packages(&installed),
+                })?;
+            }
+        }
+        cli::Commands::Uninstall { package, force, cascade, dry_run, .. } => {
+            uninstall_package(&package, force, cascade, dry_run, output, &config).await?;
+            if !dry_run {
+                refresh_environment_agent();
+            }
+        }
+        cli::Commands::Autoremove { .. } => {
+            autoremove_packages(&config).await?;
+            refresh_environment_agent();
+        }
+        cli::Commands::List { .. } => {
+            list_packages(output, &config).await?;
+        }
+        cli::Commands::Freeze { all, exclude_editable, .. } => {
+            freeze_packages(all, exclude_editable)?;
+        }
+        cli::Commands::Owns { path, unowned } => {
+            show_file_owner(path.as_deref(), unowned)?;
+        }
+        cli::Commands::Sync { constraints, frozen, dry_run: true, .. } => {
+            let constraints = constraints::Constraints::from_files(&constraints)?;
+            plan_sync(frozen, &constraints, output, &config).await?;
+        }
+        cli::Commands::Sync { require_hashes, constraints, verify_imports, frozen, .. } => {
+            if verify_imports {
+                config.verify_imports = Some(true);
+            }
+            if frozen {
+                sync_frozen(require_hashes, &config, &events).await?;
+            } else {
+                let constraints = constraints::Constraints::from_files(&constraints)?;
+                sync_dependencies(require_hashes, &constraints, &config, &events).await?;
+            }
+            refresh_environment_agent();
+        }
+        cli::Commands::Add { packages, dev, group, .. } => {
+            let list = pyproject::DependencyList::from_args(dev, group.as_deref());
+            add_requirements(&packages, &list, &config, &events).await?;
+            refresh_environment_agent();
         }
-        cli::Commands::Uninstall { package } => {
-            uninstall_package(&package, &config).await?;
+        cli::Commands::Remove { packages, dev, group, .. } => {
+            let list = pyproject::DependencyList::from_args(dev, group.as_deref());
+            remove_requirements(&packages, &list, &config, &events).await?;
+            refresh_environment_agent();
         }
-        cli::Commands::List => {
-            list_packages(&config).await?;
+        cli::Commands::Run { command, watch: true, ignore, debounce, environment } => {
+            watch_in_project(&command, environment.system, &ignore, debounce, &config, &events).await?;
         }
-        cli::Commands::Sync => {
-            sync_dependencies(&config).await?;
+        cli::Commands::Run { command, environment, .. } => {
+            run_in_project(&command, environment.system, &config, &events).await?;
         }
-        cli::Commands::Search { query } => {
-            search_packages(&query, &config).await?;
+        cli::Commands::Shell { venv } => {
+            spawn_shell(venv.as_deref(), &config, &events).await?;
         }
-        cli::Commands::Show { package } => {
-            show_package(&package, &config).await?;
+        cli::Commands::Lock { check_format, platforms, python_versions, universal, import, diff } => {
+            if check_format {
+                check_lockfile_format()?;
+            } else if let Some(path) = import {
+                import_lockfile(Path::new(&path), &config).await?;
+            } else if let Some(other) = diff {
+                let environments = lock_environments(&platforms, &python_versions, universal, &config)?;
+                diff_lockfile(other.as_deref().map(Path::new), &environments, &config).await?;
+            } else {
+                let environments = lock_environments(&platforms, &python_versions, universal, &config)?;
+                lock_dependencies(&environments, &config).await?;
+            }
+        }
+        cli::Commands::Upgrade { packages, .. } => {
+            upgrade_packages(&packages, &config, &events).await?;
+            refresh_environment_agent();
+        }
+        cli::Commands::Export { format, hashes, groups, output } => {
+            export_lockfile(&format, hashes, &groups, output.as_deref()).await?;
+        }
+        cli::Commands::FmtManifest { paths, check } => {
+            format_manifests(&paths, check)?;
+        }
+        cli::Commands::Search { query, interactive } => {
+            if interactive {
+                explore_packages(query.as_deref(), &config, &events).await?;
+                refresh_environment_agent();
+            } else if let Some(query) = query {
+                search_packages(&query, &config).await?;
+            }
+        }
+        cli::Commands::Show { package, files } => {
+            show_package(&package, files, output.is_json(), &config).await?;
         }
-        cli::Commands::Init { name } => {
-            init_project(name.as_deref(), &config).await?;
+        cli::Commands::Init { name, answers, list_templates } => {
+            if list_templates {
+                for (name, description) in templates::available() {
+                    println!("{:<12} {}", bold(&name), dim(description));
+                }
+            } else {
+                init_project(name.as_deref(), &answers, &config).await?;
+            }
+        }
+        cli::Commands::Build { wheel, sdist, out_dir } => {
+            build_project(wheel, sdist, Path::new(&out_dir)).await?;
+        }
+        cli::Commands::Publish { files, index, token, dry_run, skip_existing } => {
+            let index = index.or_else(|| config.publish_url.clone()).unwrap_or_else(|| "pypi".to_string());
+            publish_artifacts(&files, &index, token.as_deref(), dry_run, skip_existing, output).await?;
         }
         cli::Commands::Venv { command } => {
-            handle_venv_command(command, &config).await?;
+            handle_venv_command(command, &config, &events).await?;
+        }
+        cli::Commands::Python { command } => {
+            handle_python_command(command).await?;
+        }
+        cli::Commands::X { from, python, command } => {
+            let (first, args) = command.split_first().ok_or_else(|| anyhow::anyhow!("Nothing to run"))?;
+            match &from {
+                Some(requirement) => run_ephemeral_tool(requirement, Some(first), args, python.as_deref(), &config, &events).await?,
+                None => run_ephemeral_tool(first, None, args, python.as_deref(), &config, &events).await?,
+            }
         }
+        cli::Commands::Tool { command } => {
+            handle_tool_command(command, &config, &events).await?;
+        }
+        cli::Commands::Env { command } => match command {
+            cli::EnvCommands::Export { name, output } => export_conda_environment(name.as_deref(), output.as_deref())?,
+            cli::EnvCommands::Import { file, name } => {
+                import_conda_environment(Path::new(&file), name.as_deref(), &config, &events).await?;
+            }
+        },
+        cli::Commands::Config { command } => match command {
+            cli::ConfigCommands::Show { origin } => show_config(&layers, origin)?,
+            cli::ConfigCommands::Get { key } => {
+                match layers.get(&key) {
+                    Some(toml::Value::String(value)) => println!("{}", value),
+                    Some(value) => println!("{}", value),
+                    None if SnakepitConfig::keys().contains(&key) => logger::note(format!("{} is not set", key)),
+                    None => return Err(anyhow::anyhow!("Unknown setting '{}' (see `snakepit config list`)", key)),
+                }
+            }
+            cli::ConfigCommands::Set { key, value, layer } => {
+                let parsed = config::parse_setting(&key, &value)?;
+                let source = config_layer(&layer)?;
+                config::write_setting(&source, &key, Some(parsed))?;
+                logger::success(format!("✓ Set {} = {} in {}", key, value, source));
+            }
+            cli::ConfigCommands::Unset { key, layer } => {
+                if !SnakepitConfig::keys().contains(&key) {
+                    return Err(anyhow::anyhow!("Unknown setting '{}' (see `snakepit config list`)", key));
+                }
+                let source = config_layer(&layer)?;
+                config::write_setting(&source, &key, None)?;
+                logger::success(format!("✓ Removed {} from {}", key, source));
+            }
+            cli::ConfigCommands::Migrate => {
+                let dir = std::env::current_dir()?;
+                let dropped = config::migrate_to_pyproject(&dir)?;
+                logger::success(format!("✓ Moved {} into [tool.snakepit] of pyproject.toml", overrides::PROJECT_FILE));
+                if !dropped.is_empty() {
+                    logger::warn("  ! These dependencies belong in [project].dependencies; add them there:");
+                    for dependency in dropped {
+                        logger::info(format!("      {}", dependency));
+                    }
+                }
+            }
+            cli::ConfigCommands::List => {
+                let mut keys = SnakepitConfig::keys();
+                keys.sort();
+                for key in keys {
+                    match layers.get(&key) {
+                        Some(value) => println!("{} = {}", key, value),
+                        None => println!("{} {}", key, dim("(unset)")),
+                    }
+                }
+            }
+        },
         cli::Commands::Daemon { command } => {
-            handle_daemon_command(command, &config).await?;
+            handle_daemon_command(command, output, &config).await?;
+        }
+        cli::Commands::Agent { command } => {
+            handle_agent_command(command)?;
         }
         cli::Commands::Fix { command } => {
             if command.is_empty() {
-                println!("{}", yellow("Please provide a command to fix, e.g., 'snakepit fix -- adk'"));
+                logger::warn("Please provide a command to fix, e.g., 'snakepit fix -- adk'");
                 return Ok(());
             }
 
             let cmd_str = command.join(" ");
-            println!("{}", cyan(format!("🔧 Running command to diagnose: {}", cmd_str)));
+            logger::info(cyan(format!("🔧 Running command to diagnose: {}", cmd_str)));
 
             let max_retries = 5;
             let mut attempts = 0;
 
             loop {
                 if attempts >= max_retries {
-                    println!("{}", red("❌ Maximum fix attempts reached. Giving up."));
+                    logger::error("❌ Maximum fix attempts reached. Giving up.");
                     break;
                 }
 
                 attempts += 1;
                 if attempts > 1 {
-                    println!("{}", cyan(format!("🔄 Attempt {}/{}: Re-running command...", attempts, max_retries)));
+                    logger::info(cyan(format!("🔄 Attempt {}/{}: Re-running command...", attempts, max_retries)));
                 }
 
                 let output = std::process::Command::new(&command[0])
@@ -104,7 +409,7 @@ async fn main() -> Result<()> {
                 match output {
                     Ok(output) => {
                         if output.status.success() {
-                            println!("{}", green("✅ Command ran successfully! Fix complete."));
+                            logger::success("✅ Command ran successfully! Fix complete.");
                             return Ok(());
                         }
 
@@ -113,76 +418,74 @@ async fn main() -> Result<()> {
                         // First, check for system library errors
                         let sys_detector = system_libs::SystemLibDetector::new();
                         if let Some(lib_name) = sys_detector.extract_library_from_error(&stderr) {
-                            println!("{}", yellow(format!("🔧 SYSTEM: Detected missing library: {}", lib_name)));
+                            logger::warn(format!("🔧 SYSTEM: Detected missing library: {}", lib_name));
                             
                             if let Some(lib) = sys_detector.find_package(&lib_name) {
-                                println!("{}", green(format!("💡 SUGGESTION: Install system package '{}'", lib.package_name)));
+                                logger::success(format!("💡 SUGGESTION: Install system package '{}'", lib.package_name));
                                 
                                 if let Some(cmd) = sys_detector.get_install_command(&lib) {
-                                    println!("{}", bold(format!("\nRun this command:")));
-                                    println!("  {}", cyan(&cmd));
-                                    println!("\n{}", dim("After installing, press Enter to retry..."));
-                                    
-                                    let mut input = String::new();
-                                    std::io::stdin().read_line(&mut input)?;
-                                    
-                                    // Continue loop to retry
-                                    continue;
+                                    logger::info(bold(format!("\nRun this command:")));
+                                    logger::info(format!("  {}", cyan(&cmd)));
+                                    logger::info("");
+                                    if prompt::wait_for_enter("After installing, press Enter to retry...")? {
+                                        continue;
+                                    }
+                                    break;
                                 } else {
-                                    println!("{}", yellow("⚠️  Could not determine install command for your OS."));
+                                    logger::warn("⚠️  Could not determine install command for your OS.");
                                     break;
                                 }
                             } else {
-                                println!("{}", yellow(format!("⚠️  Unknown system library: {}", lib_name)));
-                                println!("{}", dim("This might require manual installation."));
+                                logger::warn(format!("⚠️  Unknown system library: {}", lib_name));
+                                logger::note("This might require manual installation.");
                                 break;
                             }
                         }
                         
                         // If not a system library error, try Python package diagnosis
-                        println!("{}", magenta("❌ Command failed. Consulting Snake Charmer..."));
+                        logger::info(magenta("❌ Command failed. Consulting Snake Charmer..."));
 
                         if let Ok(charmer) = SnakeCharmer::new() {
                             match charmer.diagnose_error(&cmd_str, &stderr).await {
                                 Ok(Some(package)) => {
-                                    println!("{}", magenta(format!("🐍 CHARMER: Diagnosis complete. Missing package: {}", package)));
-                                    println!("{}", green(format!("💡 Suggestion: Install '{}' to fix the error.", package)));
+                                    logger::info(magenta(format!("🐍 CHARMER: Diagnosis complete. Missing package: {}", package)));
+                                    logger::success(format!("💡 Suggestion: Install '{}' to fix the error.", package));
                                     
                                     // Auto-install
                                     let mut handler = handler::SnakepitHandler::new();
                                     if handler.handle_package(&package, None, None).await? {
-                                        println!("{}", green("✅ Fix applied! Verifying..."));
+                                        logger::success("✅ Fix applied! Verifying...");
                                         // Loop continues to re-run command
                                     } else {
-                                        println!("{}", red("❌ Failed to apply fix."));
+                                        logger::error("❌ Failed to apply fix.");
                                         break;
                                     }
                                 }
                                 Ok(None) => {
-                                    println!("{}", yellow("🐍 CHARMER: Could not identify a missing package."));
-                                    println!("Error output:\n{}", stderr);
+                                    logger::warn("🐍 CHARMER: Could not identify a missing package.");
+                                    logger::info(format!("Error output:\n{}", stderr));
                                     break;
                                 }
                                 Err(e) => {
-                                    println!("{}", red(format!("🐍 CHARMER: Diagnosis failed: {}", e)));
+                                    logger::error(format!("🐍 CHARMER: Diagnosis failed: {}", e));
                                     break;
                                 }
                             }
                         } else {
-                            println!("{}", yellow("⚠️  Snake Charmer not available (check GEMINI_API_KEY)."));
-                            println!("Error output:\n{}", stderr);
+                            logger::warn("⚠️  Snake Charmer not available (check GEMINI_API_KEY).");
+                            logger::info(format!("Error output:\n{}", stderr));
                             break;
                         }
                     }
                     Err(e) => {
-                        println!("{}", red(format!("❌ Failed to execute command: {}", e)));
+                        logger::error(format!("❌ Failed to execute command: {}", e));
                         break;
                     }
                 }
             }
         }
         cli::Commands::Recommend { query, context } => {
-            println!("{}", cyan("🔮 ORACLE: Analyzing your request..."));
+            logger::info(cyan("🔮 ORACLE: Analyzing your request..."));
             
             let recommender = recommender::PackageRecommender::new()
                 .context("Failed to initialize recommender (check GEMINI_API_KEY)")?;
@@ -190,7 +493,7 @@ async fn main() -> Result<()> {
             let recommendations = recommender.recommend(&query, context.as_deref()).await?;
             
             if recommendations.is_empty() {
-                println!("{}", red("❌ No recommendations found. Try rephrasing your query."));
+                logger::error("❌ No recommendations found. Try rephrasing your query.");
                 return Ok(());
             }
             
@@ -198,12 +501,12 @@ async fn main() -> Result<()> {
             
             match recommender.prompt_install(&recommendations)? {
                 Some(package) => {
-                    println!("\n{}", cyan(format!("📦 Installing {}...", package)));
+                    logger::info(format!("\n{}", cyan(format!("📦 Installing {}...", package))));
                     let mut handler = handler::SnakepitHandler::new();
                     handler.handle_package(&package, None, None).await?;
                 }
                 None => {
-                    println!("{}", dim("Skipped installation."));
+                    logger::note("Skipped installation.");
                 }
             }
         }
@@ -273,9 +576,9 @@ async fn main() -> Result<()> {
                 cli::FangsAction::List => {
                     let forks = fangs.list_forks()?;
                     if forks.is_empty() {
-                        println!("{}", dim("No forked modules found"));
+                        logger::note("No forked modules found");
                     } else {
-                        println!("{}", cyan(format!("🧪 Forked modules ({}):", forks.len())));
+                        logger::info(cyan(format!("🧪 Forked modules ({}):", forks.len())));
                         for fork in forks {
                             println!("   • {}", fork);
                         }
@@ -310,25 +613,25 @@ async fn main() -> Result<()> {
                     snake.discover_devices().await?;
                     let results = snake.run_tests(&device, &test_file).await?;
                     
-                    println!("\n📊 Test Results:");
+                    logger::info("\n📊 Test Results:");
                     println!("   Duration: {} ms", results.duration_ms);
                     println!("   Status: {}", if results.passed { "✅ PASSED" } else { "❌ FAILED" });
                     
                     if !results.stdout.is_empty() {
-                        println!("\n📝 Output:");
+                        logger::info("\n📝 Output:");
                         println!("{}", results.stdout);
                     }
                     
                     if !results.stderr.is_empty() {
-                        println!("\n⚠️  Errors:");
-                        println!("{}", results.stderr);
+                        logger::info("\n⚠️  Errors:");
+                        logger::info(&results.stderr);
                     }
                 }
                 cli::SnakeAction::Profile { device, script } => {
                     snake.discover_devices().await?;
                     let metrics = snake.profile_performance(&device, &script).await?;
                     
-                    println!("\n📊 Performance Profile:");
+                    logger::info("\n📊 Performance Profile:");
                     println!("{}", metrics.profile_data);
                 }
                 cli::SnakeAction::Logs { device } => {
@@ -338,23 +641,130 @@ async fn main() -> Result<()> {
             }
         }
         cli::Commands::Snapshot { action } => {
-            use crate::uninstaller::Uninstaller;
-            let uninstaller = Uninstaller::new()?;
+            let uninstaller = snapshotting_uninstaller(&config)?;
             
             match action {
-                cli::SnapshotAction::List => {
+                cli::SnapshotAction::List { env: true } => {
+                    list_environment_snapshots(&uninstaller, output)?;
+                }
+                cli::SnapshotAction::List { env: false } => {
                     let snapshots = uninstaller.list_snapshots().await?;
-                    if snapshots.is_empty() {
-                        println!("{}", yellow("No snapshots found."));
+                    if output.is_json() {
+                        let entries: Vec<output::SnapshotEntry> = snapshots.iter().map(output::SnapshotEntry::from).collect();
+                        output::print_json(&entries)?;
+                    } else if snapshots.is_empty() {
+                        logger::warn("No snapshots found.");
                     } else {
-                        println!("{}", blue("Available snapshots:"));
+                        logger::info(blue("Available snapshots:"));
                         for s in snapshots {
-                            println!("  • {} (ID: {})", s.package, s.id);
+                            let contents = match (s.file_count, s.size) {
+                                (Some(count), Some(size)) => dim(format!(" [{} files, {}]", count, cache::format_size(size))).to_string(),
+                                _ => String::new(),
+                            };
+                            match &s.environment {
+                                Some(env) => println!("  • {} {} (ID: {}) from {}{}", s.package, s.version, s.id, env.display(), contents),
+                                None => println!("  • {} (ID: {}){}", s.package, s.id, contents),
+                            }
                         }
                     }
+                    let environments = uninstaller.list_environment_snapshots()?.len();
+                    if environments > 0 && !output.is_json() {
+                        logger::note(format!("{} environment snapshots; see `snakepit snapshot list --env`", environments));
+                    }
+                }
+                cli::SnapshotAction::Create { package: Some(package), .. } => {
+                    let snapshot = uninstaller.create_snapshot(&package).await?;
+                    logger::success(format!("✓ Snapshot created: {} (restore with: snakepit snapshot restore {})", snapshot.id, snapshot.id));
+                }
+                cli::SnapshotAction::Create { package: None, files, .. } => {
+                    let site_packages = PackageInstaller::new().site_packages()?;
+                    let snapshot = uninstaller.create_environment_snapshot(&site_packages, registry::ProjectRegistry::current_environment(), files)?;
+                    logger::success(format!(
+                        "✓ Snapshot created: {} ({} packages; restore with: snakepit snapshot restore --env {})",
+                        snapshot.id, snapshot.packages.len(), snapshot.id
+                    ));
+                }
+                cli::SnapshotAction::Restore { id, target, env: true } => {
+                    restore_environment_snapshot(&uninstaller, &id, target.as_deref()).await?;
+                }
+                cli::SnapshotAction::Restore { id, target, .. } => {
+                    uninstaller.restore_snapshot(&id, target.as_deref().map(Path::new)).await?;
+                }
+                cli::SnapshotAction::Show { id } => {
+                    show_snapshot(&uninstaller, &id, output).await?;
+                }
+                cli::SnapshotAction::Diff { id, target } => {
+                    diff_snapshot(&uninstaller, &id, target.as_deref().map(Path::new), output).await?;
+                }
+                cli::SnapshotAction::Delete { id } => {
+                    match uninstaller.delete_snapshot(&id).await? {
+                        uninstaller::StoredSnapshot::Package(snapshot) => logger::success(format!(
+                            "✓ Deleted snapshot {} of {} {}", snapshot.id, snapshot.package, snapshot.version
+                        )),
+                        uninstaller::StoredSnapshot::Environment(snapshot) => logger::success(format!(
+                            "✓ Deleted environment snapshot {} ({} packages)", snapshot.id, snapshot.packages.len()
+                        )),
+                    }
+                }
+                cli::SnapshotAction::Prune { max_count, max_age, max_size } => {
+                    let policy = uninstaller::RetentionPolicy::parse(
+                        max_count.or(config.snapshot_max_count),
+                        max_age.as_deref().or(config.snapshot_max_age.as_deref()),
+                        max_size.as_deref().or(config.snapshot_max_size.as_deref()),
+                    )?;
+                    if policy.is_unlimited() {
+                        return Err(anyhow::anyhow!("Nothing to prune: pass --max-count, --max-age or --max-size, or set snapshot_max_count, snapshot_max_age or snapshot_max_size in the config"));
+                    }
+                    let (count, freed) = uninstaller.prune_snapshots(&policy)?;
+                    logger::success(format!("✓ Pruned {} snapshots ({})", count, cache::format_size(freed)));
                 }
-                cli::SnapshotAction::Restore { id } => {
-                    uninstaller.restore_snapshot(&id).await?;
+            }
+        }
+        cli::Commands::Cache { command } => {
+            handle_cache_command(command, &config)?;
+        }
+        cli::Commands::Stats { reset } => {
+            show_index_stats(reset, &config)?;
+        }
+        cli::Commands::Why { package, venv } => {
+            explain_installed(&package, venv)?;
+        }
+        cli::Commands::Tree { invert, depth, locked, venv } => {
+            show_dependency_tree(invert.as_deref(), depth, locked, venv, output).await?;
+        }
+        cli::Commands::Posture { min_score, venv } => {
+            show_posture(output.is_json(), min_score, venv, &config).await?;
+        }
+        cli::Commands::Doctor => {
+            run_doctor(output, &config).await?;
+        }
+        cli::Commands::Check { .. } => {
+            check_environment(output)?;
+        }
+        cli::Commands::Licenses { allow, deny, .. } => {
+            let allow = if allow.is_empty() { config.allowed_licenses.clone().unwrap_or_default() } else { allow };
+            let deny = if deny.is_empty() { config.denied_licenses.clone().unwrap_or_default() } else { deny };
+            license_report(&licenses::LicensePolicy::new(allow, deny), output)?;
+        }
+        cli::Commands::Audit { locked, db, offline, update_db, ignore, .. } => {
+            audit_packages(locked, db, offline, update_db, &ignore, output, &config).await?;
+        }
+        cli::Commands::SelfManage { command: cli::SelfCommands::Update { check } } => {
+            update_self(check).await?;
+        }
+        cli::Commands::Bench { command } => {
+            match command {
+                cli::BenchCommands::Install { set, backends, runs, warm } => {
+                    let packages = bench::InstallBenchmark::resolve_package_set(&set)?;
+                    let results = bench::InstallBenchmark::new(packages)
+                        .with_backends(backends)
+                        .with_runs(runs)
+                        .with_cache(warm)
+                        .run()
+                        .await?;
+
+                    logger::info(format!("\n{}", bold("📊 Install benchmark:")));
+                    print!("{}", bench::format_table(&results));
                 }
             }
         }
@@ -370,201 +780,2306 @@ async fn main() -> Result<()> {
         cli::Commands::Protein { command } => {
             handle_protein_command(command, &config).await?;
         }
+        cli::Commands::Task(command) => {
+            let name = command.first().map(String::as_str).unwrap_or_default();
+            if tasks::ProjectTasks::load(&std::env::current_dir()?)?.get(name).is_none() {
+                return Err(anyhow::anyhow!("Unknown command or project task '{}' (see `snakepit --help` or `snakepit run`)", name));
+            }
+            run_in_project(&command, false, &config, &events).await?;
+        }
     }
+
+    events.finish()?;
     
     Ok(())
 }
 
-async fn install_package(package: &str, version: Option<&str>, dev: bool, config: &SnakepitConfig) -> Result<()> {
+async fn install_package(package: &str, version: Option<&str>, dev: bool, constraints: &constraints::Constraints, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let install_version = constrained_install_version(package, version, constraints, config).await?;
+
     // Use Smart Snakepit Handler
-    let mut handler = SnakepitHandler::new();
-    let success = handler.handle_package(package, version, None).await?;
+    let mut handler = SnakepitHandler::new()
+        .with_events(events.clone())
+        .with_upstream(upstream::Upstream::from_config(config))
+        .with_interpreter(project_interpreter(config))
+        .with_constraint_files(constraints.files().to_vec())
+        .with_prereleases(prerelease::Prereleases::from_config(config))
+        .with_import_check(config.verify_imports.unwrap_or(false));
+    let success = handler.handle_package(package, install_version.as_deref(), None).await?;
     
-    if success {
-        // Update project dependencies if we're in a project directory
-        if Path::new("pyproject.toml").exists() || Path::new("requirements.txt").exists() {
-            let dependency = Dependency {
-                name: package.to_string(),
-                version: version.map(|v| v.to_string()),
-                version_constraint: None,
-                is_dev: dev,
-                source: None,
+    if !success {
+        return Err(anyhow::anyhow!("Failed to install package {}", package));
+    }
+    record_project_install(package, dev);
+    Ok(())
+}
+
+/// Bookkeeping after installing `package` in a project directory: point at `add` when the
+/// manifest doesn't declare it, and register the project with its environment
+fn record_project_install(package: &str, dev: bool) {
+    if !Path::new("pyproject.toml").exists() && !Path::new("requirements.txt").exists() {
+        return;
+    }
+    // `install` only changes the environment; `add` is what records a requirement
+    let (name, _extras) = dependency::split_extras(package);
+    let declared = load_project_dependencies()
+        .map(|deps| deps.dependencies.iter().chain(&deps.dev_dependencies)
+            .any(|dep| manifest::canonical_name(&dep.name) == manifest::canonical_name(&name)))
+        .unwrap_or(true);
+    if !declared && Path::new("pyproject.toml").exists() {
+        let flag = if dev { "--dev " } else { "" };
+        logger::note(format!("{} isn't in pyproject.toml; `snakepit add {}{}` records it", name, flag, package));
+    }
+    register_current_project();
+}
+
+/// A wheel or conda package file given in place of a package name
+fn is_local_archive(package: &str) -> bool {
+    Path::new(package).is_file() && archive::format_for(package).is_ok()
+}
+
+/// Unpack a local wheel or conda package natively; the index is never consulted.
+/// Returns the package name the filename carries (the filename itself when it carries none).
+async fn install_local_archive(path: &Path, hashes: Option<&[String]>, events: &renderer::EventBus) -> Result<String> {
+    let mut installer = PackageInstaller::new().with_events(events.clone());
+    let filename = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let name = archive::format_for(&filename)?
+        .parse_filename(&filename)
+        .map(|(name, _version)| name);
+    if let Some(hashes) = hashes {
+        let name = name.clone()
+            .ok_or_else(|| anyhow::anyhow!("Can't tell the package name from {}", filename))?;
+        installer = installer.with_required_hashes(std::collections::HashMap::from([(name, hashes.to_vec())]));
+    }
+    installer.install_archive(path).await?;
+    Ok(name.unwrap_or(filename))
+}
+
+/// Install a single package in `--require-hashes` mode.
+///
+/// This skips the validation sandbox on purpose: nothing unverified gets downloaded or run.
+async fn install_package_pinned(package: &str, version: Option<&str>, dev: bool, hashes: &[String], constraints: &constraints::Constraints, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let version = constrained_install_version(package, version, constraints, config).await?;
+    let (name, _extras) = dependency::split_extras(package);
+    let mut required = std::collections::HashMap::new();
+    required.insert(name, hashes.to_vec());
+
+    let installer = PackageInstaller::new()
+        .with_backend(installer_backend(config))
+        .with_required_hashes(required)
+        .with_events(events.clone())
+        .with_upstream(upstream::Upstream::from_config(config))
+        .with_interpreter(project_interpreter(config))
+        .with_constraint_files(constraints.files().to_vec())
+        .with_prereleases(prerelease::Prereleases::from_config(config))
+        .with_import_check(config.verify_imports.unwrap_or(false));
+
+    installer.install_package(package, version.as_deref()).await?;
+    record_project_install(package, dev);
+    Ok(())
+}
+
+/// `install --dry-run`: resolve what `install` would, then compare it with the environment
+async fn plan_install(package: Option<&str>, requirements: &[String], version: Option<&str>, constraints: &constraints::Constraints, output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
+    let mut project_deps = requirements_from_files(requirements)?;
+    let mut archives = Vec::new();
+    match package {
+        Some(package) if is_local_archive(package) => archives.push(package),
+        Some(package) => {
+            let requirement = match version {
+                Some(version) => format!("{}=={}", package, version),
+                None => package.to_string(),
             };
-            update_project_dependencies(&dependency, config).await?;
+            let deps = ProjectDependencies::from_requirement_lines([requirement.as_str()], Path::new("."))?;
+            project_deps.dependencies.extend(deps.dependencies);
+        }
+        None => {}
+    }
+
+    let site_packages = PackageInstaller::new().with_interpreter(project_interpreter(config)).site_packages()?;
+    let mut plan = plan::Plan::for_site_packages(&site_packages)?;
+    if !project_deps.dependencies.is_empty() || !project_deps.dev_dependencies.is_empty() {
+        logger::info(blue("Resolving..."));
+        for dep in resolve_project_dependencies(&project_deps, constraints, config).await? {
+            plan.install(&dep.name, &dep.version);
+        }
+    }
+    for editable in &project_deps.editables {
+        if let Some(source) = &editable.source {
+            plan.install_from(&editable.name, None, source);
+        }
+    }
+    for path in archives {
+        let filename = Path::new(path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let (name, version) = archive::format_for(&filename)?
+            .parse_filename(&filename)
+            .map(|(name, version)| (name, Some(version)))
+            .unwrap_or((filename, None));
+        plan.install_from(&name, version.as_deref(), path);
+    }
+    show_plan(plan, output, config).await
+}
+
+/// Look up the size of everything the plan downloads, then print it as text or JSON
+async fn show_plan(mut plan: plan::Plan, output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
+    let installer = PackageInstaller::new()
+        .with_upstream(upstream::Upstream::from_config(config))
+        .with_interpreter(project_interpreter(config));
+    for change in plan.downloads_mut() {
+        let version = change.version.clone().unwrap_or_default();
+        match installer.planned_download(&change.name, &version).await {
+            Ok(Some((file, size))) => {
+                change.download = Some(file);
+                change.download_size = size;
+            }
+            Ok(None) => {}
+            Err(e) => logger::warn(format!("⚠️  Couldn't look up the download for {}: {}", change.name, e)),
         }
+    }
+    if output.is_json() {
+        output::print_json(&plan)
     } else {
-        return Err(anyhow::anyhow!("Failed to install package {}", package));
+        plan.print();
+        Ok(())
     }
+}
 
-    Ok(())
+/// A resolver reading the configured index, as of `exclude_newer` when that's set
+fn index_resolver(config: &SnakepitConfig) -> Result<DependencyResolver> {
+    let resolver = DependencyResolver::new()
+        .with_upstream(upstream::Upstream::from_config(config));
+    Ok(match exclude_newer_cutoff(config)? {
+        Some(cutoff) => resolver.with_exclude_newer(cutoff),
+        None => resolver,
+    })
+}
+
+/// `exclude_newer` as unix seconds: a date (`2024-06-01`, midnight UTC), a date and time, or an age (`30d`)
+fn exclude_newer_cutoff(config: &SnakepitConfig) -> Result<Option<u64>> {
+    let Some(value) = &config.exclude_newer else {
+        return Ok(None);
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    daemon_log::parse_time(value, now)
+        .map(Some)
+        .with_context(|| format!("Invalid --exclude-newer '{}'", value))
+}
+
+/// A resolver for picking versions to install: the configured index, pre-release
+/// opt-ins, snakepit.toml overrides, the resolution strategy and the target interpreter's Requires-Python
+fn selecting_resolver(config: &SnakepitConfig) -> Result<DependencyResolver> {
+    let resolver = index_resolver(config)?
+        .with_prereleases(prerelease::Prereleases::from_config(config))
+        .with_overrides(overrides::Overrides::load(Path::new("."))?)
+        .with_resolution(resolver::Resolution::from_config(config.resolution.as_deref())?);
+    Ok(match target_python_version(config) {
+        Some(python_version) => resolver.with_python_version(&python_version),
+        None => resolver,
+    })
+}
+
+/// `major.minor` of the interpreter packages are installed for: the active environment,
+/// then the configured version, then whatever `python3` is on PATH
+fn target_python_version(config: &SnakepitConfig) -> Option<String> {
+    registry::ProjectRegistry::current_environment()
+        .and_then(|environment| ide::IdeHooks::environment_python_version(&environment))
+        .or_else(|| config.python_version.clone())
+        .or_else(|| markers::TargetEnvironment::detect().ok().map(|env| env.python_version))
+}
+
+/// Pick (or check) the version up front when a constraint or a snakepit.toml override
+/// covers the package, so every backend agrees
+async fn constrained_install_version(package: &str, version: Option<&str>, constraints: &constraints::Constraints, config: &SnakepitConfig) -> Result<Option<String>> {
+    let (name, extras) = dependency::split_extras(package);
+    let overridden = overrides::Overrides::load(Path::new("."))?.get(&name).is_some();
+    if constraints.get(&name).is_none() && !overridden && config.exclude_newer.is_none() {
+        return Ok(version.map(|v| v.to_string()));
+    }
+
+    let resolver = selecting_resolver(config)?
+        .with_constraints(constraints.clone());
+    let dependency = Dependency {
+        name,
+        version: version.map(|v| v.to_string()),
+        version_constraint: version.map(|_| "==".to_string()),
+        is_dev: false,
+        source: None,
+        extras,
+        hashes: Vec::new(),
+    };
+    let selected = resolver.resolve_version(&dependency).await?;
+    if overridden {
+        logger::note(format!("📌 {} {} (overridden in {})", package, selected, overrides::PROJECT_FILE));
+    } else if constraints.get(&dependency.name).is_none() {
+        logger::note(format!("📌 {} {} (as of {})", package, selected, config.exclude_newer.as_deref().unwrap_or_default()));
+    } else {
+        logger::note(format!("📌 {} {} (constrained to {})", package, selected, constraints.describe(&dependency.name)));
+    }
+    Ok(Some(selected))
+}
+
+fn installer_backend(config: &SnakepitConfig) -> InstallerBackend {
+    InstallerBackend::from_config(config.default_backend.as_deref())
 }
 
 mod uninstaller;
 
 // ... (imports)
 
-async fn uninstall_package(package: &str, config: &SnakepitConfig) -> Result<()> {
-    use crate::uninstaller::Uninstaller;
-    
-    let uninstaller = Uninstaller::new()?;
+async fn uninstall_package(package: &str, force: bool, cascade: bool, dry_run: bool, output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
+    let uninstaller = snapshotting_uninstaller(config)?;
+
+    // 0. Refuse to pull the rug out from under the installer itself
+    let protection = uninstaller.protection_reason(package);
+    if let Some(reason) = &protection {
+        logger::error(format!("🛑 {}", reason));
+    }
     
     // 1. Analyze Impact
     let report = uninstaller.analyze_impact(package).await?;
-    
-    if report.risk_score > 50 {
-        println!("{}", yellow(format!("⚠️  High risk detected! Risk Score: {}", report.risk_score)));
+    let threshold = config.uninstall_risk_threshold.unwrap_or(uninstaller::DEFAULT_RISK_THRESHOLD);
+    let risky = report.risk_score > threshold;
+
+    if risky {
+        logger::warn(format!("⚠️  High risk detected! Risk Score: {} (threshold {})", report.risk_score, threshold));
         if !report.dependents.is_empty() {
-            println!("The following packages depend on '{}':", package);
+            logger::info(format!("The following packages depend on '{}':", package));
             for dep in &report.dependents {
-                println!("  - {}", dep);
+                logger::info(format!("  - {}", dep));
+            }
+        }
+
+        if !report.referencing_projects.is_empty() {
+            logger::info(format!("The following projects sharing this environment still declare '{}':", package));
+            for project in &report.referencing_projects {
+                logger::info(format!("  - {}", project.display()));
             }
         }
         
         if let Some(analysis) = &report.ai_analysis {
-            println!("\n🧠 AI Analysis:\n{}", analysis);
+            logger::info(format!("\n🧠 AI Analysis:\n{}", analysis));
         }
         
-        println!("\n{}", dim("Proceeding will break these packages."));
-        // In a real CLI, we'd ask for confirmation here.
-        // For now, we'll just wait a bit to let the user read.
-        std::thread::sleep(std::time::Duration::from_secs(2));
+        logger::info(format!("\n{}", dim("Proceeding will break these packages.")));
+    }
+
+    match uninstaller::UninstallGate::decide(protection.is_some(), risky, dry_run, force) {
+        uninstaller::UninstallGate::Clear | uninstaller::UninstallGate::Preview => {}
+        uninstaller::UninstallGate::Forced if protection.is_some() => logger::warn("⚠️  --force given; snapshotting before removal"),
+        uninstaller::UninstallGate::Forced => logger::warn("⚠️  --force given; uninstalling anyway"),
+        uninstaller::UninstallGate::Refuse => {
+            return Err(anyhow::anyhow!("Refusing to uninstall protected package '{}'; re-run with --force (a snapshot is taken first)", package));
+        }
+        uninstaller::UninstallGate::Confirm => {
+            if !prompt::confirm(&format!("Uninstall '{}' anyway?", package), false)? {
+                return Err(anyhow::anyhow!(
+                    "Not uninstalling '{}': risk score {} is above the threshold of {}; answer yes, or re-run with --yes or --force",
+                    package, report.risk_score, threshold
+                ));
+            }
+        }
+    }
+    
+    // 1.5. With --cascade, the dependencies only this package kept around
+    let mut orphans = Vec::new();
+    if cascade {
+        let graph = installed_graph(None)?;
+        let keep = kept_packages(&uninstaller, load_project_dependencies().ok().as_ref())?;
+        orphans = graph.orphaned_by(&[package.to_string()], &keep);
+        if !orphans.is_empty() && !dry_run {
+            logger::info(bold(format!("These dependencies of '{}' would be left unused:", package)));
+            print_package_list(&graph, &orphans);
+            if !prompt::confirm(&format!("Remove them too ({} packages)?", orphans.len()), true)? {
+                orphans.clear();
+            }
+        }
+    }
+
+    if dry_run {
+        let mut plan = plan::Plan::for_site_packages(&PackageInstaller::new().site_packages()?)?;
+        if plan.installed_version(package).is_none() {
+            logger::warn(format!("⚠️  '{}' isn't installed", package));
+        }
+        plan.remove(package);
+        for orphan in &orphans {
+            plan.remove(orphan);
+        }
+        return show_plan(plan, output, config).await;
     }
     
     // 2. Create Snapshot
     match uninstaller.create_snapshot(package).await {
-        Ok(snapshot) => println!("{}", green(format!("✓ Snapshot created: {}", snapshot.id))),
-        Err(e) => println!("{}", yellow(format!("⚠️  Failed to create snapshot: {}", e))),
+        Ok(snapshot) => logger::success(format!("✓ Snapshot created: {} (restore with: snakepit snapshot restore {})", snapshot.id, snapshot.id)),
+        // Without a snapshot there'd be no way back from removing a protected package
+        Err(e) if protection.is_some() => {
+            return Err(anyhow::anyhow!("Not removing protected package '{}' without a snapshot: {}", package, e));
+        }
+        Err(e) => logger::warn(format!("⚠️  Failed to create snapshot: {}", e)),
     }
     
     // 3. Uninstall
-    println!("{}", blue("Uninstalling package..."));
+    logger::info(blue("Uninstalling package..."));
     uninstaller.uninstall(package).await?;
     
-    println!("{}", green("✓ Package uninstalled successfully!"));
+    logger::success("✓ Package uninstalled successfully!");
+    if !orphans.is_empty() {
+        remove_with_snapshots(&uninstaller, &orphans).await?;
+    }
     Ok(())
 }
 
-async fn list_packages(config: &SnakepitConfig) -> Result<()> {
-    let backend = match config.default_backend.as_deref() {
-        Some("conda") => InstallerBackend::Conda,
-        Some("poetry") => InstallerBackend::Poetry,
-        _ => InstallerBackend::Pip,
-    };
+/// `autoremove`: uninstall whatever the project's requirements don't lead to, once confirmed
+async fn autoremove_packages(config: &SnakepitConfig) -> Result<()> {
+    let project = load_project_dependencies()
+        .context("autoremove keeps what the project requires, so it needs a pyproject.toml or requirements.txt")?;
+    let uninstaller = snapshotting_uninstaller(config)?;
+    let graph = installed_graph(None)?;
+    let orphans = graph.unreachable_from(&kept_packages(&uninstaller, Some(&project))?);
+    if orphans.is_empty() {
+        logger::success("✓ Every installed package is required by the project");
+        return Ok(());
+    }
 
-    let installer = PackageInstaller::new()
-        .with_backend(backend);
+    logger::info(bold(format!("{} installed packages aren't required by the project:", orphans.len())));
+    print_package_list(&graph, &orphans);
+    if !prompt::confirm(&format!("Remove these {} packages?", orphans.len()), false)? {
+        logger::note("Nothing removed");
+        return Ok(());
+    }
+    remove_with_snapshots(&uninstaller, &orphans).await
+}
 
-    let packages = installer.list_installed_packages().await?;
-    
-    println!("{}", blue("Installed packages:"));
-    for package in packages {
-        println!("  • {}", package);
+/// `snapshot list --env`
+fn list_environment_snapshots(uninstaller: &uninstaller::Uninstaller, output: output::OutputFormat) -> Result<()> {
+    let snapshots = uninstaller.list_environment_snapshots()?;
+    if output.is_json() {
+        let entries: Vec<output::EnvironmentSnapshotEntry> = snapshots.iter().map(output::EnvironmentSnapshotEntry::from).collect();
+        return output::print_json(&entries);
+    }
+    if snapshots.is_empty() {
+        logger::warn("No environment snapshots found.");
+        return Ok(());
+    }
+    logger::info(blue("Environment snapshots:"));
+    for snapshot in &snapshots {
+        let origin = snapshot.environment.as_deref().unwrap_or(&snapshot.site_packages);
+        let files = if snapshot.files_path.is_some() { ", with files" } else { "" };
+        println!("  • {} (ID: {}) {} packages{} from {}", snapshot.timestamp, snapshot.id, snapshot.packages.len(), files, origin.display());
     }
-    
     Ok(())
 }
 
-async fn sync_dependencies(config: &SnakepitConfig) -> Result<()> {
-    println!("{}", blue("Syncing dependencies..."));
-    
-    // Try to load dependencies from various sources
-    let project_deps = if Path::new("pyproject.toml").exists() {
-        ProjectDependencies::from_pyproject_toml("pyproject.toml")?
-    } else if Path::new("requirements.txt").exists() {
-        ProjectDependencies::from_requirements_txt("requirements.txt")?
-    } else {
-        return Err(anyhow::anyhow!("No dependency file found (pyproject.toml or requirements.txt)"));
+/// `snapshot restore --env`: roll the environment the snapshot came from (or `target`) back to it
+async fn restore_environment_snapshot(uninstaller: &uninstaller::Uninstaller, id: &str, target: Option<&str>) -> Result<()> {
+    let snapshot = uninstaller.find_environment_snapshot(id)?;
+    let environment = match (target, &snapshot.environment) {
+        (Some(target), _) => {
+            let target = std::path::PathBuf::from(target);
+            if !target.join("pyvenv.cfg").exists() {
+                return Err(anyhow::anyhow!("{} is not a virtual environment", target.display()));
+            }
+            Some(target)
+        }
+        (None, Some(environment)) if !environment.exists() => {
+            return Err(anyhow::anyhow!(
+                "Snapshot '{}' came from {}, which no longer exists; pass --target <venv> to restore elsewhere",
+                id, environment.display()
+            ));
+        }
+        (None, environment) => environment.clone(),
     };
+    registry::ProjectRegistry::choose_environment(environment);
+    let rollback = uninstaller.restore_environment(&snapshot).await?;
+    if !rollback.is_empty() {
+        logger::note(format!("{} packages removed, {} restored", rollback.remove.len(), rollback.install.len()));
+    }
+    refresh_environment_agent();
+    Ok(())
+}
 
-    let mut resolver = DependencyResolver::new();
-    let resolved_deps = resolver.resolve_dependencies(&project_deps).await?;
+/// `snapshot show`: what a snapshot holds and where it came from
+async fn show_snapshot(uninstaller: &uninstaller::Uninstaller, id: &str, output: output::OutputFormat) -> Result<()> {
+    let snapshot = uninstaller.find_snapshot(id).await?;
+    let files = uninstaller.snapshot_contents(&snapshot)?;
+    let total_size = files.iter().map(|file| file.size).sum();
+    if output.is_json() {
+        return output::print_json(&output::SnapshotDetails {
+            snapshot: output::SnapshotEntry::from(&snapshot),
+            source_dir: snapshot.source_dir.clone(),
+            files,
+            total_size,
+        });
+    }
 
-    let backend = match config.default_backend.as_deref() {
-        Some("conda") => InstallerBackend::Conda,
-        Some("poetry") => InstallerBackend::Poetry,
-        _ => InstallerBackend::Pip,
-    };
+    println!("{} {} {}", bold(&snapshot.package), snapshot.version, dim(format!("(ID: {})", snapshot.id)));
+    println!("  Taken: {}", snapshot.timestamp);
+    if let Some(environment) = &snapshot.environment {
+        println!("  Environment: {}", environment.display());
+    }
+    if let Some(source_dir) = &snapshot.source_dir {
+        println!("  From: {}", source_dir.display());
+    }
+    println!("  Files: {} ({})", files.len(), cache::format_size(total_size));
+    for file in &files {
+        println!("    {:>10}  {}", cache::format_size(file.size), file.path);
+    }
+    Ok(())
+}
 
-    let installer = PackageInstaller::new()
-        .with_backend(backend);
+/// `snapshot diff`: every file a restore would put back or overwrite
+async fn diff_snapshot(uninstaller: &uninstaller::Uninstaller, id: &str, target: Option<&Path>, output: output::OutputFormat) -> Result<()> {
+    use uninstaller::FileChange;
 
-    // Install all dependencies
-    let mut all_deps = resolved_deps.dependencies.clone();
-    all_deps.extend(resolved_deps.dev_dependencies.clone());
+    let snapshot = uninstaller.find_snapshot(id).await?;
+    let diff = uninstaller.diff_snapshot(&snapshot, target)?;
+    if output.is_json() {
+        return output::print_json(&diff);
+    }
 
-    installer.install_dependencies(&all_deps).await?;
-    
-    println!("{}", green("✓ Dependencies synced successfully!"));
+    let installed = diff.installed_version.as_deref().unwrap_or("not installed");
+    println!("{} {} -> {} {}", bold(&diff.package), installed, diff.snapshot_version, dim(format!("(restoring into {})", diff.target.display())));
+    for file in &diff.files {
+        match file.change {
+            FileChange::Added => println!("{}", green(format!("  + {}", file.path))),
+            FileChange::Modified => println!("{}", yellow(format!("  ~ {}", file.path))),
+            FileChange::Extra => println!("{}", dim(format!("  ? {} (not in the snapshot; left as is)", file.path))),
+            FileChange::Unchanged => {}
+        }
+    }
+    let changed = diff.count(FileChange::Added) + diff.count(FileChange::Modified);
+    if changed == 0 {
+        println!("{}", green(format!("Nothing to restore; {} files already match", diff.count(FileChange::Unchanged))));
+    } else {
+        println!("{}", bold(format!(
+            "{} to restore, {} to overwrite, {} unchanged",
+            diff.count(FileChange::Added), diff.count(FileChange::Modified), diff.count(FileChange::Unchanged)
+        )));
+    }
     Ok(())
 }
 
-async fn search_packages(query: &str, config: &SnakepitConfig) -> Result<()> {
-    let backend = match config.default_backend.as_deref() {
-        Some("conda") => InstallerBackend::Conda,
-        Some("poetry") => InstallerBackend::Poetry,
-        _ => InstallerBackend::Pip,
-    };
+/// The uninstaller for the configured backend, pruning snapshots to the configured retention
+fn snapshotting_uninstaller(config: &SnakepitConfig) -> Result<uninstaller::Uninstaller> {
+    Ok(uninstaller::Uninstaller::new()?
+        .with_backend(installer_backend(config))
+        .with_retention(uninstaller::RetentionPolicy::from_config(config)?))
+}
+
+/// Packages cascading and autoremove never take out: what the installer backend needs,
+/// everything `project` declares (dev and editable requirements included), and what the
+/// other registered projects sharing the environment declare
+fn kept_packages(uninstaller: &uninstaller::Uninstaller, project: Option<&ProjectDependencies>) -> Result<Vec<String>> {
+    let mut keep: Vec<String> = uninstaller.protected_packages();
+    if let Some(project) = project {
+        keep.extend(project.dependencies.iter()
+            .chain(&project.dev_dependencies)
+            .chain(&project.editables)
+            .map(|dep| dep.name.clone()));
+        keep.extend(project.project_name.clone());
+    }
+    let environment = registry::ProjectRegistry::current_environment();
+    keep.extend(registry::ProjectRegistry::load()?.declared_in_environment(environment.as_deref()));
+    Ok(keep)
+}
+
+fn print_package_list(graph: &tree::DependencyGraph, packages: &[String]) {
+    for package in packages {
+        match graph.get(package) {
+            Some(node) => logger::info(format!("  - {} {}", node.name, dim(&node.version))),
+            None => logger::info(format!("  - {}", package)),
+        }
+    }
+}
+
+/// Uninstall each of `packages`, snapshotting it first so it can be restored
+async fn remove_with_snapshots(uninstaller: &uninstaller::Uninstaller, packages: &[String]) -> Result<()> {
+    for package in packages {
+        match uninstaller.create_snapshot(package).await {
+            Ok(snapshot) => logger::note(format!("  snapshot {} of {}", snapshot.id, package)),
+            Err(e) => logger::warn(format!("⚠️  No snapshot of {}: {}", package, e)),
+        }
+        uninstaller.uninstall(package).await
+            .with_context(|| format!("Failed to uninstall {}", package))?;
+        logger::success(format!("✓ Removed {}", package));
+    }
+    Ok(())
+}
+
+async fn list_packages(output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
+    let backend = installer_backend(config);
 
     let installer = PackageInstaller::new()
         .with_backend(backend);
 
-    let results = installer.search_package(query).await?;
+    let packages = installer.list_installed_packages().await?;
+    if output.is_json() {
+        let names: Vec<String> = packages.into_iter().filter(|name| !name.is_empty()).collect();
+        return output::print_json(&installed_packages(&names));
+    }
     
-    if results.is_empty() {
-        println!("{}", yellow(format!("No packages found matching '{}'", query)));
-    } else {
-        println!("{}", blue(format!("Search results for '{}':", query)));
-        for result in results {
-            println!("  • {}", result);
-        }
+    logger::info(blue("Installed packages:"));
+    for package in packages {
+        println!("  • {}", package);
     }
     
     Ok(())
 }
 
-async fn show_package(package: &str, config: &SnakepitConfig) -> Result<()> {
-    let backend = match config.default_backend.as_deref() {
-        Some("conda") => InstallerBackend::Conda,
-        Some("poetry") => InstallerBackend::Poetry,
-        _ => InstallerBackend::Pip,
-    };
+/// Each of `names` with the version its dist-info in the chosen environment records, for `--json` results
+fn installed_packages(names: &[String]) -> Vec<output::InstalledPackage> {
+    let versions: std::collections::HashMap<String, String> = PackageInstaller::new().site_packages().ok()
+        .and_then(|site| freeze::installed_distributions(&site).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dist| (manifest::canonical_name(&dist.name), dist.version))
+        .collect();
+    names.iter()
+        .map(|name| output::InstalledPackage {
+            name: name.clone(),
+            version: versions.get(&manifest::canonical_name(name)).cloned(),
+        })
+        .collect()
+}
 
-    let installer = PackageInstaller::new()
-        .with_backend(backend);
+/// `freeze`: the chosen environment's distributions as requirement lines on stdout
+fn freeze_packages(all: bool, exclude_editable: bool) -> Result<()> {
+    let mut installer = PackageInstaller::new();
+    if let Some(venv) = registry::ProjectRegistry::current_environment() {
+        installer = installer.with_venv(venv.to_string_lossy().to_string());
+    }
+    let site = installer.site_packages()?;
+    let distributions = freeze::installed_distributions(&site)?;
+    if distributions.is_empty() {
+        logger::note(format!("No packages installed in {}", site.display()));
+    }
+    print!("{}", freeze::freeze(&distributions, all, exclude_editable));
+    Ok(())
+}
+
+fn show_file_owner(path: Option<&str>, unowned: bool) -> Result<()> {
+    let mut installer = PackageInstaller::new();
+    if let Some(venv) = registry::ProjectRegistry::current_environment() {
+        installer = installer.with_venv(venv.to_string_lossy().to_string());
+    }
+    let site = installer.site_packages()?;
+    let provenance = provenance::ProvenanceRegistry::for_site_packages(&site)?;
 
-    match installer.show_package(package).await {
-        Ok(details) => {
-            println!("{}", blue(format!("Package details for '{}':", package)));
-            println!("{}", details);
+    if unowned {
+        let files = provenance.unowned_files()?;
+        if files.is_empty() {
+            logger::success(format!("✓ Every file in {} is owned by a package", site.display()));
         }
-        Err(e) => {
-            println!("{}", red(format!("Failed to show package details: {}", e)));
+        for file in files {
+            println!("{}", file.display());
         }
+        return Ok(());
+    }
+
+    let path = Path::new(path.unwrap_or_default());
+    match provenance.owner(path) {
+        Some(owner) => {
+            println!("{} is owned by {} {}", path.display(), owner.package, owner.version);
+            println!("  transaction: {}", owner.transaction);
+            if let Some(source) = owner.source {
+                println!("  source:      {}", source);
+            }
+            Ok(())
+        }
+        None => Err(anyhow::anyhow!("{} is not owned by any installed package", path.display())),
     }
-    
-    Ok(())
 }
 
-async fn init_project(name: Option<&str>, config: &SnakepitConfig) -> Result<()> {
-    let project_name = name.unwrap_or("my-project");
-    
-    println!("{}", blue(format!("Initializing project '{}'...", project_name)));
-    
-    // Create project directory
-    std::fs::create_dir_all(project_name)?;
-    
-    // Create project configuration
-    let project_config = ProjectConfig::new(project_name.to_string())
-        .with_python_version(config.python_version.as_deref().unwrap_or("3.9"))
-        .with_backend(config.default_backend.as_deref().unwrap_or("pip"));
+async fn sync_dependencies(require_hashes: bool, constraints: &constraints::Constraints, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    logger::info(blue("Syncing dependencies..."));
+    let project_deps = load_project_dependencies()?;
+
+    let required_hashes = if require_hashes {
+        // Lockfile hashes first, then anything pinned inline in the manifest
+        let mut pinned = std::collections::HashMap::new();
+        let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+        if lock_path.exists() {
+            pinned.extend(lockfile::Lockfile::load(lock_path).await?.hashes_by_package());
+        }
+        pinned.extend(inline_hashes(&project_deps));
+        Some(pinned)
+    } else {
+        None
+    };
+
+    install_project_dependencies(&project_deps, required_hashes, constraints, config, events).await?;
+    register_current_project();
+    if let (Ok(root), Some(environment)) = (std::env::current_dir(), registry::ProjectRegistry::current_environment()) {
+        ide::IdeHooks::refresh(&root, &environment);
+    }
     
-    let config_path = format!("{}/snakepit.toml", project_name);
-    project_config.save_to_file(&config_path)?;
+    logger::success("✓ Dependencies synced successfully!");
+    Ok(())
+}
+
+/// `sync --dry-run`: what `sync` (or `sync --frozen`) would install and remove
+async fn plan_sync(frozen: bool, constraints: &constraints::Constraints, output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
+    let project_deps = load_project_dependencies()?;
+    let backend = installer_backend(config);
+    let installer = PackageInstaller::new()
+        .with_backend(backend.clone())
+        .with_interpreter(project_interpreter(config));
+    let mut plan = plan::Plan::for_site_packages(&installer.site_packages()?)?;
+    if frozen {
+        let lockfile = current_lockfile(&project_deps, config).await?;
+        let locked = packages_for_host(&lockfile)?;
+        for package in &locked {
+            plan.install(&package.name, &package.version);
+        }
+        for package in unlocked_packages(&installer, &backend, &locked, &project_deps).await? {
+            plan.remove(&package);
+        }
+    } else {
+        logger::info(blue("Resolving..."));
+        for dep in resolve_project_dependencies(&project_deps, constraints, config).await? {
+            plan.install(&dep.name, &dep.version);
+        }
+    }
+    for editable in &project_deps.editables {
+        if let Some(source) = &editable.source {
+            plan.install_from(&editable.name, None, source);
+        }
+    }
+    show_plan(plan, output, config).await
+}
+
+/// `run`: execute `command`, or the project task it names, with the project's environment
+/// activated (as is with `--system`), exiting with its status
+async fn run_in_project(command: &[String], system: bool, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let project = tasks::ProjectTasks::load(&std::env::current_dir()?)?;
+    if command.is_empty() {
+        return list_tasks(&project);
+    }
+    if let Some(script) = command.first().map(Path::new).filter(|path| is_python_script(path)) {
+        if let Some(metadata) = inline_script::ScriptMetadata::from_file(script)? {
+            return run_script(script, &command[1..], &metadata, config, events).await;
+        }
+    }
+    let venv = prepare_run(&project, command, system, config, events).await?;
+    let steps = run_steps(&project, command, venv.as_deref())?;
+    let total = steps.len();
+    for (index, (task, mut child)) in steps.into_iter().enumerate() {
+        if let Some(task) = task.as_ref().filter(|_| total > 1) {
+            logger::info(blue(format!("▶ {}", task)));
+        }
+        let status = child.status()
+            .with_context(|| format!("Failed to run {}", child.get_program().to_string_lossy()))?;
+        if !status.success() {
+            if index + 1 < total {
+                logger::error(format!("✗ A task it depends on failed; not running '{}'", command[0]));
+            }
+            std::process::exit(status.code().unwrap_or(1));
+        }
+    }
+    Ok(())
+}
+
+fn is_python_script(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "py" || ext == "pyw") && path.is_file()
+}
+
+/// `run script.py` for a script with `# /// script` metadata (PEP 723): run it in a cached
+/// environment holding exactly its dependencies, built on first use
+async fn run_script(script: &Path, args: &[String], metadata: &inline_script::ScriptMetadata, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let request = match &metadata.requires_python {
+        Some(spec) => Some(toolchain::PythonRequest::Specifiers(pep440::SpecifierSet::parse(spec)
+            .with_context(|| format!("Invalid requires-python in {}", script.display()))?)),
+        None => config.python_version.as_ref().map(|version| toolchain::PythonRequest::Version(version.clone())),
+    };
+    let interpreter = match &request {
+        Some(request) => Some(python_interpreter(request).await?),
+        None => None,
+    };
+    let python = interpreter.as_ref().map(|path| path.display().to_string()).unwrap_or_else(|| "python3".to_string());
+    let venv = metadata.environment_dir(&config.get_cache_path(), &python);
+
+    if !venv.join(inline_script::READY_MARKER).exists() {
+        if venv.exists() {
+            std::fs::remove_dir_all(&venv)?;
+        }
+        logger::info(blue(format!("Preparing an environment for {} ({} dependencies)", script.display(), metadata.dependencies.len())));
+        let base = venv.parent().ok_or_else(|| anyhow::anyhow!("Invalid cache path {}", venv.display()))?;
+        std::fs::create_dir_all(base)?;
+        let mut manager = VirtualEnvironmentManager::new().with_base_path(base.to_path_buf());
+        if let Some(interpreter) = interpreter {
+            manager = manager.with_interpreter(interpreter);
+        }
+        let name = venv.file_name().unwrap_or_default().to_string_lossy().to_string();
+        manager.create_venv(&name, None).await?;
+        registry::ProjectRegistry::choose_environment(Some(venv.clone()));
+        let dependencies = ProjectDependencies::from_requirement_lines(metadata.dependencies.iter().map(String::as_str), script.parent().unwrap_or(Path::new(".")))?;
+        if let Err(e) = install_project_dependencies(&dependencies, None, &constraints::Constraints::new(), config, events).await {
+            let _ = std::fs::remove_dir_all(&venv);
+            return Err(e.context(format!("Failed to install the dependencies of {}", script.display())));
+        }
+        std::fs::write(venv.join(inline_script::READY_MARKER), metadata.dependencies.join("\n"))?;
+    }
+
+    let status = activated_command("python", &venv)?
+        .arg(script)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run {}", script.display()))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// `run --watch`: run `command` like `run`, then again whenever a project file changes,
+/// restarting it if it's still running, until interrupted
+async fn watch_in_project(command: &[String], system: bool, ignore: &[String], debounce: Option<u64>, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    if command.is_empty() {
+        return Err(anyhow::anyhow!("--watch needs a command or a project task to run"));
+    }
+    let root = std::env::current_dir()?;
+    let venv = prepare_run(&tasks::ProjectTasks::load(&root)?, command, system, config, events).await?;
+    let debounce = debounce.map(std::time::Duration::from_millis).unwrap_or(watch::DEFAULT_DEBOUNCE);
+    let mut watcher = watch::ProjectWatcher::new(&root, watch::IgnoreSet::for_project(&root, ignore))?;
+    logger::note(format!("Watching {} for changes (Ctrl-C to stop)", root.display()));
+
+    loop {
+        // Reread the tasks each time, so edits to them apply on the next run
+        let steps = tasks::ProjectTasks::load(&root).and_then(|project| run_steps(&project, command, venv.as_deref()));
+        let run = async move {
+            match steps {
+                Ok(steps) => run_watched(steps).await,
+                Err(e) => {
+                    logger::error(format!("✗ {:#}", e));
+                    Ok(())
+                }
+            }
+        };
+        let changed = tokio::select! {
+            result = run => {
+                result?;
+                logger::note("Waiting for changes...");
+                tokio::select! {
+                    changed = watcher.next_batch(debounce) => changed,
+                    _ = tokio::signal::ctrl_c() => return Ok(()),
+                }
+            }
+            changed = watcher.next_batch(debounce) => {
+                logger::warn("Change detected; restarting");
+                changed
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        if let Some(first) = changed.first() {
+            let shown = first.strip_prefix(&root).unwrap_or(first).display().to_string();
+            let more = if changed.len() > 1 { format!(" and {} more", changed.len() - 1) } else { String::new() };
+            logger::info(cyan(format!("↻ {} changed{}", shown, more)));
+        }
+    }
+}
+
+/// One `--watch` run of `steps`, stopping at the first failure; a process still running when
+/// this is dropped is killed
+async fn run_watched(steps: Vec<(Option<String>, std::process::Command)>) -> Result<()> {
+    let total = steps.len();
+    for (task, command) in steps {
+        if let Some(task) = task.as_ref().filter(|_| total > 1) {
+            logger::info(blue(format!("▶ {}", task)));
+        }
+        let program = command.get_program().to_string_lossy().to_string();
+        let mut command = tokio::process::Command::from(command);
+        let status = command.kill_on_drop(true).status().await
+            .with_context(|| format!("Failed to run {}", program))?;
+        if !status.success() {
+            logger::error(format!("✗ {} exited with {}", program, status));
+            return Ok(());
+        }
+    }
+    logger::success("✓ Done");
+    Ok(())
+}
+
+/// The environment `command` runs in (`None` with `--system`), created if need be and
+/// synced first when a task it runs asks for that
+async fn prepare_run(project: &tasks::ProjectTasks, command: &[String], system: bool, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<Option<std::path::PathBuf>> {
+    let plan = match project.get(&command[0]) {
+        Some(_) => project.plan(&command[0])?,
+        None => Vec::new(),
+    };
+    let venv = if system { None } else { Some(ensure_project_environment(config, events).await?) };
+    if plan.iter().any(|task| project.get(task).is_some_and(|task| task.sync)) {
+        sync_dependencies(false, &constraints::Constraints::new(), config, events).await?;
+    }
+    Ok(venv)
+}
+
+/// The processes `command` runs, in order, each with the task it belongs to. A project task
+/// runs after the tasks it depends on, with any further arguments appended to its own command.
+fn run_steps(project: &tasks::ProjectTasks, command: &[String], venv: Option<&Path>) -> Result<Vec<(Option<String>, std::process::Command)>> {
+    let (name, rest) = command.split_first().ok_or_else(|| anyhow::anyhow!("No command to run"))?;
+    let prepare = |argv: &[String]| -> Result<std::process::Command> {
+        let mut child = match venv {
+            Some(venv) => activated_command(&argv[0], venv)?,
+            None => std::process::Command::new(&argv[0]),
+        };
+        child.args(&argv[1..]);
+        Ok(child)
+    };
+    if project.get(name).is_none() {
+        return Ok(vec![(None, prepare(command)?)]);
+    }
+    project.plan(name)?.into_iter()
+        .map(|task_name| {
+            let task = project.get(&task_name).expect("planned tasks are defined");
+            let extra = if &task_name == name { rest } else { &[] };
+            let mut child = prepare(&task.argv(extra)?)?;
+            child.envs(project.env_for(task));
+            Ok((Some(format!("{}: {}", task_name, task.command)), child))
+        })
+        .collect()
+}
+
+/// `run` with nothing to run: the project's tasks
+fn list_tasks(project: &tasks::ProjectTasks) -> Result<()> {
+    if project.tasks.is_empty() {
+        return Err(anyhow::anyhow!("Nothing to run: give a command, or define tasks under [scripts] in snakepit.toml or [tool.snakepit.scripts]"));
+    }
+    println!("{}", bold("Project tasks:"));
+    for (name, task) in &project.tasks {
+        println!("  {} {}", cyan(format!("{:<16}", name)), task.help.as_deref().unwrap_or(&task.command));
+        if !task.depends.is_empty() {
+            println!("  {:<16} {}", "", dim(format!("after {}", task.depends.join(", "))));
+        }
+    }
+    Ok(())
+}
+
+/// `shell`: the user's shell with `venv` (or the project's environment) activated, until it exits
+async fn spawn_shell(venv: Option<&str>, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let venv = match venv {
+        Some(name) => {
+            let path = VirtualEnvironmentManager::new().get_venv_path(name);
+            if !path.exists() {
+                return Err(anyhow::anyhow!("Virtual environment '{}' does not exist", name));
+            }
+            path
+        }
+        None => ensure_project_environment(config, events).await?,
+    };
+    if registry::ProjectRegistry::current_environment().as_deref() == Some(venv.as_path()) {
+        logger::warn(format!("  ! {} is already active", venv.display()));
+    }
+
+    let shell = venv::login_shell();
+    logger::success(format!("✓ Activated {}", venv.display()));
+    logger::note("Exit the shell to deactivate");
+    let status = activated_command(&shell.to_string_lossy(), &venv)?
+        .env("SNAKEPIT_SHELL", "1")
+        .status()
+        .with_context(|| format!("Failed to start {}", shell.display()))?;
+    std::process::exit(status.code().unwrap_or(0));
+}
+
+/// `program` set up to run as if `venv` had been activated
+fn activated_command(program: &str, venv: &Path) -> Result<std::process::Command> {
+    let mut command = std::process::Command::new(program);
+    command.env("VIRTUAL_ENV", venv)
+        .env("PATH", venv::activated_path(venv, std::env::var_os("PATH"))?)
+        .env_remove("PYTHONHOME");
+    Ok(command)
+}
+
+/// The project's environment, creating `.venv` and syncing into it if it has none
+async fn ensure_project_environment(config: &SnakepitConfig, events: &renderer::EventBus) -> Result<std::path::PathBuf> {
+    let root = std::env::current_dir()?;
+    if let Some(venv) = project_environment(&root) {
+        return Ok(venv);
+    }
+
+    let name = config.project_venv.as_deref().unwrap_or(venv::PROJECT_VENV);
+    logger::info(blue(format!("No environment for this project; creating {}", name)));
+    let request = toolchain::PythonRequest::for_project(&root, config.python_version.as_deref());
+    let backend = configured_venv_backend(config);
+    let mut manager = VirtualEnvironmentManager::new()
+        .with_backend(backend.clone())
+        .with_base_path(root);
+    if let (Some(request), VenvBackend::Venv | VenvBackend::Virtualenv) = (&request, backend) {
+        manager = manager.with_interpreter(python_interpreter(request).await?);
+    }
+    let version = match &request {
+        Some(toolchain::PythonRequest::Version(version)) => Some(version.as_str()),
+        _ => None,
+    };
+    let venv = manager.create_venv(name, version).await?;
+    registry::ProjectRegistry::choose_environment(Some(venv.clone()));
+    if load_project_dependencies().is_ok() {
+        sync_dependencies(false, &constraints::Constraints::new(), config, events).await?;
+    }
+    Ok(venv)
+}
+
+/// The environment `run` uses: the current one (the active venv or the project's `.venv`),
+/// else the one this project was last synced into; `None` when neither exists
+fn project_environment(root: &Path) -> Option<std::path::PathBuf> {
+    let registered = registry::ProjectRegistry::load().ok().and_then(|registry| registry.environment_of(root));
+    [registry::ProjectRegistry::current_environment(), registered]
+        .into_iter()
+        .flatten()
+        .find(|venv| venv.exists())
+}
+
+/// `sync --frozen`: make the environment exactly what snakepit.lock pins, without resolving
+async fn sync_frozen(require_hashes: bool, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    logger::info(blue(format!("Syncing from {}...", lockfile::LOCKFILE_NAME)));
+    let project_deps = load_project_dependencies()?;
+    let lockfile = current_lockfile(&project_deps, config).await?;
+
+    let backend = installer_backend(config);
+    let mut installer = PackageInstaller::new()
+        .with_backend(backend.clone())
+        .with_events(events.clone())
+        .with_upstream(upstream::Upstream::from_config(config))
+        .with_interpreter(project_interpreter(config))
+        .with_import_check(config.verify_imports.unwrap_or(false));
+    // A fully hashed lock is always enforced
+    if require_hashes || lockfile.verify_integrity() {
+        installer = installer.with_required_hashes(lockfile.hashes_by_package());
+    }
+
+    let locked = packages_for_host(&lockfile)?;
+    let pinned: Vec<resolver::ResolvedDependency> = locked.iter()
+        .map(|package| resolver::ResolvedDependency {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            is_dev: false,
+            dependencies: Vec::new(),
+            source: None,
+        })
+        .collect();
+    installer.install_dependencies(&pinned).await?;
+
+    let unlocked = unlocked_packages(&installer, &backend, &locked, &project_deps).await?;
+    for package in &unlocked {
+        installer.uninstall_package(package).await?;
+        logger::note(format!("  - removed {} (not in {})", package, lockfile::LOCKFILE_NAME));
+    }
+
+    for editable in &project_deps.editables {
+        if let Some(source) = &editable.source {
+            installer.install_editable(&editable.name, source).await?;
+        }
+    }
+
+    register_current_project();
+    if let (Ok(root), Some(environment)) = (std::env::current_dir(), registry::ProjectRegistry::current_environment()) {
+        ide::IdeHooks::refresh(&root, &environment);
+    }
+    logger::success(format!("✓ Environment matches {} ({} packages, {} removed)", lockfile::LOCKFILE_NAME, pinned.len(), unlocked.len()));
+    Ok(())
+}
+
+/// snakepit.lock, once it's known to match the project's requirements
+async fn current_lockfile(project_deps: &ProjectDependencies, config: &SnakepitConfig) -> Result<lockfile::Lockfile> {
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    if !lock_path.exists() {
+        return Err(anyhow::anyhow!("No {} found; run 'snakepit lock' first", lockfile::LOCKFILE_NAME));
+    }
+    let lockfile = lockfile::Lockfile::load(lock_path).await
+        .with_context(|| format!("Failed to parse {}", lockfile::LOCKFILE_NAME))?;
+
+    let overrides = overrides::Overrides::load(Path::new("."))?;
+    let drift = lockfile.inputs.diff(&resolution_inputs(project_deps, &overrides, config));
+    if !drift.is_empty() {
+        logger::error(format!("{} is out of date with the project's requirements:", lockfile::LOCKFILE_NAME));
+        for line in &drift {
+            logger::info(format!("  {}", line));
+        }
+        return Err(anyhow::anyhow!("{} is out of date (run 'snakepit lock' and commit the result)", lockfile::LOCKFILE_NAME));
+    }
+    Ok(lockfile)
+}
+
+/// Installed packages that `sync --frozen` removes: anything not locked, editable or protected
+async fn unlocked_packages(
+    installer: &PackageInstaller,
+    backend: &InstallerBackend,
+    locked: &[&lockfile::LockedPackage],
+    project_deps: &ProjectDependencies,
+) -> Result<Vec<String>> {
+    let mut keep: std::collections::HashSet<String> = locked.iter()
+        .map(|package| manifest::canonical_name(&package.name))
+        .collect();
+    keep.extend(project_deps.editables.iter().map(|editable| manifest::canonical_name(&editable.name)));
+    Ok(installer.list_installed_packages().await?.into_iter()
+        .filter(|name| !name.is_empty() && !keep.contains(&manifest::canonical_name(name)) && !backend.is_protected(name))
+        .collect())
+}
+
+/// The locked packages this machine needs; fails when the lock wasn't resolved for it
+fn packages_for_host(lockfile: &lockfile::Lockfile) -> Result<Vec<&lockfile::LockedPackage>> {
+    if lockfile.metadata.environments.is_empty() {
+        return Ok(lockfile.packages.iter().collect());
+    }
+    let python_version = registry::ProjectRegistry::current_environment()
+        .and_then(|environment| ide::IdeHooks::environment_python_version(&environment))
+        .ok_or_else(|| anyhow::anyhow!("No active environment; create one with 'snakepit venv create' to sync from a multi-platform lock"))?;
+    let host = markers::TargetEnvironment::current(&python_version)?;
+    if !lockfile.covers(&host) {
+        return Err(anyhow::anyhow!(
+            "{} was resolved for {}, not {} (add it with 'snakepit lock --platform ... --python ...')",
+            lockfile::LOCKFILE_NAME, lockfile.metadata.environments.join(", "), host.label()
+        ));
+    }
+    Ok(lockfile.packages_for(&host))
+}
+
+/// A manifest requirement as recorded in the lock inputs, e.g. `requests[socks]>=2.28`
+fn requirement_string(dep: &Dependency) -> String {
+    let mut requirement = dep.name.clone();
+    if !dep.extras.is_empty() {
+        requirement.push_str(&format!("[{}]", dep.extras.join(",")));
+    }
+    if let (Some(constraint), Some(version)) = (&dep.version_constraint, &dep.version) {
+        requirement.push_str(&format!("{}{}", constraint, version));
+    }
+    requirement
+}
+
+/// What `lock` resolves from; URL and VCS requirements aren't locked, so they're left out
+fn resolution_inputs(project_deps: &ProjectDependencies, overrides: &overrides::Overrides, config: &SnakepitConfig) -> lockfile::ResolutionInputs {
+    let requirements = |deps: &[Dependency]| deps.iter()
+        .filter(|dep| dep.source.is_none())
+        .map(requirement_string)
+        .collect();
+    lockfile::ResolutionInputs {
+        requirements: requirements(&project_deps.dependencies),
+        dev_requirements: requirements(&project_deps.dev_dependencies),
+        index_url: config.index_url.clone(),
+        overrides: overrides.requirements(),
+    }
+}
+
+/// Dependencies of the project in the current directory, from pyproject.toml or requirements.txt
+fn load_project_dependencies() -> Result<ProjectDependencies> {
+    if Path::new("pyproject.toml").exists() {
+        ProjectDependencies::from_pyproject_toml("pyproject.toml")
+    } else if Path::new("requirements.txt").exists() {
+        ProjectDependencies::from_requirements_txt("requirements.txt")
+    } else {
+        Err(anyhow::anyhow!("No dependency file found (pyproject.toml or requirements.txt)"))
+    }
+}
+
+async fn lock_dependencies(environments: &[markers::TargetEnvironment], config: &SnakepitConfig) -> Result<()> {
+    if environments.is_empty() {
+        logger::info(blue("Locking dependencies..."));
+    } else {
+        let labels: Vec<String> = environments.iter().map(|env| env.label()).collect();
+        logger::info(blue(format!("Locking dependencies for {}...", labels.join(", "))));
+    }
+    let project_deps = load_project_dependencies()?;
+    let mut lockfile = resolve_lockfile(&project_deps, environments, config, &std::collections::HashMap::new()).await?;
+
+    // Same packages as before: keep the old timestamp so the file doesn't churn in git
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    if let Ok(existing) = lockfile::Lockfile::load(lock_path).await {
+        lockfile.metadata.timestamp = existing.metadata.timestamp;
+        if existing.to_canonical_string()? == lockfile.to_canonical_string()? {
+            logger::success(format!("✓ {} is up to date", lockfile::LOCKFILE_NAME));
+            return Ok(());
+        }
+        lockfile.metadata.timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+    }
+
+    lockfile.save(lock_path).await?;
+    logger::success(format!("✓ Locked {} packages in {}", lockfile.packages.len(), lockfile::LOCKFILE_NAME));
+    Ok(())
+}
+
+/// `add`: record requirements in pyproject.toml, re-lock and sync. A bare name gets
+/// a `>=` floor at the version the lock picked.
+async fn add_requirements(packages: &[String], list: &pyproject::DependencyList, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let path = Path::new("pyproject.toml");
+    if !path.exists() {
+        return Err(anyhow::anyhow!("No pyproject.toml here; run 'snakepit init' first"));
+    }
+    let original = std::fs::read_to_string(path)?;
+    let mut editor = pyproject::PyprojectEditor::open(path)?;
+
+    let mut unpinned = Vec::new();
+    for package in packages {
+        let requirement = manifest::Requirement::parse(package)
+            .ok_or_else(|| anyhow::anyhow!("Invalid requirement '{}'", package))?;
+        match editor.add(&requirement, list)? {
+            Some(previous) => logger::note(format!("  ~ {} (was {})", requirement, previous)),
+            None => logger::note(format!("  + {}", requirement)),
+        }
+        if requirement.version.is_none() {
+            unpinned.push(requirement);
+        }
+    }
+    editor.save()?;
+    logger::success(format!("✓ Updated the {} in pyproject.toml", list));
+    if let pyproject::DependencyList::Group(group) = list {
+        logger::note(format!("Only the main and dev dependencies are locked and synced; '{}' is just recorded", group));
+        return Ok(());
+    }
+
+    relock_edited_pyproject(path, &original, config).await?;
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    let mut lock = lockfile::Lockfile::load(lock_path).await?;
+    let mut floored = false;
+    for mut requirement in unpinned {
+        let Some(locked) = lock.packages.iter().find(|package| manifest::canonical_name(&package.name) == requirement.name) else {
+            continue;
+        };
+        requirement.version = Some(format!(">={}", locked.version));
+        editor.add(&requirement, list)?;
+        floored = true;
+    }
+    if floored {
+        // The floors don't change what was resolved, only what the lock records as its inputs
+        editor.save()?;
+        let overrides = overrides::Overrides::load(Path::new("."))?;
+        lock.inputs = resolution_inputs(&load_project_dependencies()?, &overrides, config);
+        lock.save(lock_path).await?;
+    }
+
+    ensure_project_environment(config, events).await?;
+    sync_frozen(false, config, events).await
+}
+
+/// `remove`: drop requirements from pyproject.toml, re-lock and sync, which uninstalls
+/// whatever only they needed
+async fn remove_requirements(packages: &[String], list: &pyproject::DependencyList, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let path = Path::new("pyproject.toml");
+    if !path.exists() {
+        return Err(anyhow::anyhow!("No pyproject.toml here"));
+    }
+    let original = std::fs::read_to_string(path)?;
+    let mut editor = pyproject::PyprojectEditor::open(path)?;
+
+    for package in packages {
+        if let Some(removed) = editor.remove(package, list) {
+            logger::note(format!("  - {}", removed));
+            continue;
+        }
+        let elsewhere = [pyproject::DependencyList::Main, pyproject::DependencyList::Dev]
+            .into_iter()
+            .find(|other| other != list && editor.get(package, other).is_some());
+        return Err(match elsewhere {
+            Some(other) => anyhow::anyhow!("{} isn't in the {} (it's in the {})", package, list, other),
+            None => anyhow::anyhow!("{} isn't in the {} in pyproject.toml", package, list),
+        });
+    }
+    editor.save()?;
+    logger::success(format!("✓ Updated the {} in pyproject.toml", list));
+    if matches!(list, pyproject::DependencyList::Group(_)) {
+        return Ok(());
+    }
+
+    relock_edited_pyproject(path, &original, config).await?;
+    ensure_project_environment(config, events).await?;
+    sync_frozen(false, config, events).await
+}
+
+/// Re-lock after `add`/`remove` edited pyproject.toml, putting `original` back if that fails
+async fn relock_edited_pyproject(path: &Path, original: &str, config: &SnakepitConfig) -> Result<()> {
+    let environments = lock_environments(&[], &[], false, config)?;
+    if let Err(e) = lock_dependencies(&environments, config).await {
+        std::fs::write(path, original)?;
+        return Err(e.context("pyproject.toml was left unchanged"));
+    }
+    Ok(())
+}
+
+/// `export`: snakepit.lock as a pinned requirements file, on stdout or into `output`
+async fn export_lockfile(format: &str, hashes: bool, groups: &[String], output: Option<&str>) -> Result<()> {
+    let export::ExportFormat::RequirementsTxt = export::ExportFormat::parse(format)?;
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    if !lock_path.exists() {
+        return Err(anyhow::anyhow!("No {} found; run 'snakepit lock' first", lockfile::LOCKFILE_NAME));
+    }
+    let lockfile = lockfile::Lockfile::load(lock_path).await
+        .with_context(|| format!("Failed to parse {}", lockfile::LOCKFILE_NAME))?;
+
+    let mut exporter = export::RequirementsExport::new(&lockfile).with_hashes(hashes);
+    for group in groups {
+        exporter = exporter.with_group(group)?;
+    }
+    let exported = exporter.render()?;
+    for warning in &exported.warnings {
+        logger::warn(format!("  ! {}", warning));
+    }
+    match output {
+        Some(path) => {
+            std::fs::write(path, &exported.content)
+                .with_context(|| format!("Failed to write {}", path))?;
+            logger::success(format!("✓ Exported {} to {}", lockfile::LOCKFILE_NAME, path));
+        }
+        None => print!("{}", exported.content),
+    }
+    Ok(())
+}
+
+/// `lock --diff`: compare snakepit.lock with `other` (any lockfile `--import` reads, too),
+/// or with what `lock` would write now, without changing anything
+async fn diff_lockfile(other: Option<&Path>, environments: &[markers::TargetEnvironment], config: &SnakepitConfig) -> Result<()> {
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    let current = if lock_path.exists() {
+        lockfile::Lockfile::load(lock_path).await
+            .with_context(|| format!("Failed to parse {}", lockfile::LOCKFILE_NAME))?
+    } else {
+        lockfile::Lockfile::new()
+    };
+    let newer = match other {
+        Some(path) if lock_import::ForeignLock::detect(path).is_ok() => lock_import::import(path)?.lockfile,
+        Some(path) => lockfile::Lockfile::load(path).await
+            .with_context(|| format!("Failed to parse {}", path.display()))?,
+        None => {
+            logger::info(blue("Resolving to compare..."));
+            resolve_lockfile(&load_project_dependencies()?, environments, config, &std::collections::HashMap::new()).await?
+        }
+    };
+
+    let changes = current.diff(&newer);
+    if changes.is_empty() {
+        logger::success("✓ No changes");
+    } else {
+        print_lock_changes(&changes);
+    }
+    Ok(())
+}
+
+/// One line per change: `requests 2.28.0 → 2.31.0  (requests>=2.31 (was requests>=2.28))`
+fn print_lock_changes(changes: &[lockfile::PackageChange]) {
+    for change in changes {
+        let line = match (&change.old, &change.new) {
+            (Some(old), Some(new)) => format!("  {} {} {} → {}", if change.kind() == "downgraded" { yellow("↓") } else { green("↑") }, change.name, dim(old), green(new)),
+            (None, Some(new)) => format!("  {} {} {}", green("+"), change.name, new),
+            (Some(old), None) => format!("  {} {} {}", red("-"), change.name, old),
+            (None, None) => continue,
+        };
+        match &change.cause {
+            Some(cause) => println!("{}  {}", line, dim(format!("({})", cause))),
+            None => println!("{}", line),
+        }
+    }
+}
+
+/// `lock --import`: carry another tool's pins over as they are, so migrating doesn't move any version
+async fn import_lockfile(path: &Path, config: &SnakepitConfig) -> Result<()> {
+    logger::info(blue(format!("Importing {}...", path.display())));
+    let imported = lock_import::import(path)?;
+    for warning in &imported.warnings {
+        logger::warn(format!("  ! {}", warning));
+    }
+
+    // Record the manifest the pins belong to, so `sync --frozen` accepts them as up to date
+    let mut lockfile = imported.lockfile;
+    if let Ok(project_deps) = load_project_dependencies() {
+        let overrides = overrides::Overrides::load(Path::new("."))?;
+        lockfile.metadata.python_version = project_deps.python_version.clone();
+        lockfile.inputs = resolution_inputs(&project_deps, &overrides, config);
+    }
+    lockfile.save(Path::new(lockfile::LOCKFILE_NAME)).await?;
+    logger::success(format!("✓ Imported {} packages into {}", lockfile.packages.len(), lockfile::LOCKFILE_NAME));
+    Ok(())
+}
+
+/// Targets `lock` resolves for: the command line, then the config; empty locks for the host only
+fn lock_environments(platforms: &[String], python_versions: &[String], universal: bool, config: &SnakepitConfig) -> Result<Vec<markers::TargetEnvironment>> {
+    if universal {
+        return markers::TargetEnvironment::matrix(&markers::PLATFORMS, &markers::PYTHON_VERSIONS);
+    }
+    let pick = |cli: &[String], configured: &Option<Vec<String>>| {
+        if cli.is_empty() { configured.clone().unwrap_or_default() } else { cli.to_vec() }
+    };
+    let mut platforms = pick(platforms, &config.lock_platforms);
+    let mut python_versions = pick(python_versions, &config.lock_python_versions);
+    if platforms.is_empty() && python_versions.is_empty() {
+        return Ok(Vec::new());
+    }
+    if platforms.is_empty() {
+        platforms.push(markers::host_platform().to_string());
+    }
+    if python_versions.is_empty() {
+        python_versions.push(config.python_version.clone().unwrap_or_else(|| markers::TargetEnvironment::host().python_version));
+    }
+    markers::TargetEnvironment::matrix(&platforms, &python_versions)
+}
+
+/// A PEP 440 solver for the project's index requirements; URL and VCS requirements are left out
+fn project_solver(
+    project_deps: &ProjectDependencies,
+    environments: &[markers::TargetEnvironment],
+    constraints: &constraints::Constraints,
+    config: &SnakepitConfig,
+) -> Result<(String, solver::Solver)> {
+    let project = project_deps.project_name.clone()
+        .or_else(|| std::env::current_dir().ok()?.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "project".to_string());
+    let root_version = pep440::Version::parse("0")?;
+
+    // The project itself is the root package; its requirements are the root's dependencies
+    let mut root_dependencies = Vec::new();
+    let mut extras = Vec::new();
+    let mut prereleases = prerelease::Prereleases::from_config(config);
+    for dep in project_deps.dependencies.iter().chain(project_deps.dev_dependencies.iter()) {
+        if dep.source.is_some() {
+            continue;
+        }
+        if !dep.extras.is_empty() {
+            extras.push((dep.name.clone(), dep.extras.clone()));
+        }
+        let requirement = requirement_string(dep);
+        let spec = markers::parse_requirement(&requirement)
+            .with_context(|| format!("Invalid requirement '{}'", requirement))?;
+        if prerelease::mentions_prerelease(&spec.version_specs) {
+            prereleases = prereleases.with_package(&dep.name);
+        }
+        root_dependencies.push((manifest::canonical_name(&dep.name), solver::requirement_constraint(&spec.version_specs)));
+    }
+
+    // Every target's interpreter must be able to run what gets locked
+    let mut python_versions: Vec<String> = environments.iter().map(|env| env.python_version.clone()).collect();
+    python_versions.sort();
+    python_versions.dedup();
+    if environments.is_empty() {
+        python_versions.extend(target_python_version(config));
+    }
+
+    let resolver = index_resolver(config)?;
+    let mut solver = solver::Solver::new(project.clone(), root_version.clone(), std::sync::Arc::new(tokio::sync::Mutex::new(resolver)))
+        .with_package(&project, root_version, root_dependencies)
+        .with_environments(environments.to_vec())
+        .with_python_versions(python_versions)
+        .with_prereleases(prereleases)
+        .with_overrides(overrides::Overrides::load(Path::new("."))?)
+        .with_constraints(constraints.clone())
+        .with_resolution(resolver::Resolution::from_config(config.resolution.as_deref())?);
+    for (name, requested) in &extras {
+        solver = solver.with_extras(name, requested);
+    }
+    Ok((project, solver))
+}
+
+/// Solve the project's requirements into a lockfile, keeping `preferred` versions where they still fit
+async fn resolve_lockfile(
+    project_deps: &ProjectDependencies,
+    environments: &[markers::TargetEnvironment],
+    config: &SnakepitConfig,
+    preferred: &std::collections::HashMap<String, pep440::Version>,
+) -> Result<lockfile::Lockfile> {
+    for dep in project_deps.dependencies.iter().chain(project_deps.dev_dependencies.iter()) {
+        if let Some(source) = &dep.source {
+            logger::warn(format!("  ! {} comes from {}; it is not locked", dep.name, source));
+        }
+    }
+    let overrides = overrides::Overrides::load(Path::new("."))?;
+    let resolution = resolver::Resolution::from_config(config.resolution.as_deref())?;
+    let (project, mut solver) = project_solver(project_deps, environments, &constraints::Constraints::new(), config)?;
+    let roots: Vec<String> = project_deps.dependencies.iter()
+        .chain(project_deps.dev_dependencies.iter())
+        .filter(|dep| dep.source.is_none())
+        .map(|dep| manifest::canonical_name(&dep.name))
+        .collect();
+    for (name, version) in preferred {
+        solver = solver.with_preferred(name, version.clone());
+    }
+    let mut pinned = solver.solve().await?;
+    pinned.remove(&manifest::canonical_name(&project));
+
+    let generator_resolver = index_resolver(config)?;
+    let mut lockfile = lockfile::LockfileGenerator::new()
+        .with_resolver(generator_resolver)
+        .with_environments(environments.to_vec())
+        .generate_from_solver(&pinned, solver.extras())
+        .await?;
+    if !environments.is_empty() {
+        lockfile.assign_environments(&roots, environments);
+    }
+    lockfile.metadata.python_version = project_deps.python_version.clone();
+    lockfile.inputs = resolution_inputs(project_deps, &overrides, config);
+    if resolution != resolver::Resolution::Highest {
+        lockfile.metadata.resolution = Some(resolution.name().to_string());
+    }
+    lockfile.metadata.exclude_newer = exclude_newer_cutoff(config)?.map(daemon_log::format_timestamp);
+    Ok(lockfile)
+}
+
+/// Re-resolve with the named packages (or everything) free to move, show what changes and apply it
+async fn upgrade_packages(packages: &[String], config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let project_deps = load_project_dependencies()?;
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    let existing = if lock_path.exists() {
+        Some(lockfile::Lockfile::load(lock_path).await
+            .with_context(|| format!("Failed to parse {}", lockfile::LOCKFILE_NAME))?)
+    } else {
+        None
+    };
+    let before: std::collections::BTreeMap<String, String> = existing.iter()
+        .flat_map(|lock| lock.packages.iter())
+        .map(|package| (manifest::canonical_name(&package.name), package.version.clone()))
+        .collect();
+
+    let targets: std::collections::HashSet<String> = packages.iter().map(|name| manifest::canonical_name(name)).collect();
+    for name in &targets {
+        if !before.is_empty() && !before.contains_key(name) {
+            return Err(anyhow::anyhow!("{} is not in {}", name, lockfile::LOCKFILE_NAME));
+        }
+    }
+    // Everything not being upgraded stays where the lock has it, as long as it still fits
+    let preferred: std::collections::HashMap<String, pep440::Version> = if targets.is_empty() {
+        std::collections::HashMap::new()
+    } else {
+        before.iter()
+            .filter(|(name, _)| !targets.contains(*name))
+            .filter_map(|(name, version)| Some((name.clone(), pep440::Version::parse(version).ok()?)))
+            .collect()
+    };
+
+    if targets.is_empty() {
+        logger::info(blue("Re-resolving all dependencies..."));
+    } else {
+        logger::info(blue(format!("Re-resolving {}...", packages.join(", "))));
+    }
+    // Stay on the targets the lock was made for
+    let environments = existing.iter()
+        .flat_map(|lock| lock.metadata.environments.iter())
+        .map(|label| markers::TargetEnvironment::parse_target(label))
+        .collect::<Result<Vec<_>>>()?;
+    let mut lockfile = resolve_lockfile(&project_deps, &environments, config, &preferred).await?;
+    let changes = match &existing {
+        Some(lock) => lock.diff(&lockfile),
+        None => lockfile::Lockfile::new().diff(&lockfile),
+    };
+    let changed = changes.iter().filter(|c| c.old.is_some() && c.new.is_some()).count();
+    let added = changes.iter().filter(|c| c.old.is_none()).count();
+    let removed: Vec<&String> = changes.iter().filter(|c| c.new.is_none()).map(|c| &c.name).collect();
+
+    // A `==` in the manifest wins over `upgrade`; say so instead of silently doing nothing
+    for dep in project_deps.dependencies.iter().chain(project_deps.dev_dependencies.iter()) {
+        let name = manifest::canonical_name(&dep.name);
+        if targets.contains(&name) && dep.version_constraint.as_deref() == Some("==") && !changes.iter().any(|c| c.name == name) {
+            logger::warn(format!("  ! {} is pinned to =={} in the manifest", dep.name, dep.version.as_deref().unwrap_or("")));
+        }
+    }
+
+    if changes.is_empty() {
+        logger::success("✓ Everything is up to date");
+        return Ok(());
+    }
+    logger::info(bold("Changes:"));
+    print_lock_changes(&changes);
+
+    if !prompt::confirm("Apply these changes?", false)? {
+        logger::note("Aborted; nothing changed (pass --yes to apply)");
+        return Ok(());
+    }
+
+    lockfile.metadata.timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    // Only what this machine needs; checked before the lock is written
+    let updates: Vec<resolver::ResolvedDependency> = packages_for_host(&lockfile)?.into_iter()
+        .filter(|package| before.get(&manifest::canonical_name(&package.name)) != Some(&package.version))
+        .map(|package| resolver::ResolvedDependency {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            is_dev: false,
+            dependencies: Vec::new(),
+            source: None,
+        })
+        .collect();
+    lockfile.save(lock_path).await?;
+
+    let installer = PackageInstaller::new()
+        .with_backend(installer_backend(config))
+        .with_events(events.clone())
+        .with_upstream(upstream::Upstream::from_config(config))
+        .with_interpreter(project_interpreter(config))
+        .with_import_check(config.verify_imports.unwrap_or(false))
+        .with_required_hashes(lockfile.hashes_by_package());
+    installer.install_dependencies(&updates).await?;
+    for name in &removed {
+        installer.uninstall_package(name).await?;
+    }
+
+    logger::success(format!("✓ Upgraded {} packages ({} added, {} removed)", changed, added, removed.len()));
+    Ok(())
+}
+
+/// `lock --check-format`: fail when snakepit.lock isn't byte-for-byte what `lock` writes
+fn check_lockfile_format() -> Result<()> {
+    let content = std::fs::read_to_string(lockfile::LOCKFILE_NAME)
+        .with_context(|| format!("Failed to read {}", lockfile::LOCKFILE_NAME))?;
+    let problems = lockfile::Lockfile::check_format(&content)
+        .with_context(|| format!("Failed to parse {}", lockfile::LOCKFILE_NAME))?;
+
+    if problems.is_empty() {
+        logger::success(format!("✓ {} is canonically formatted", lockfile::LOCKFILE_NAME));
+        return Ok(());
+    }
+    for problem in &problems {
+        logger::error(format!("  ✗ {}", problem));
+    }
+    Err(anyhow::anyhow!("{} is not in canonical form (regenerate it with 'snakepit lock')", lockfile::LOCKFILE_NAME))
+}
+
+fn show_index_stats(reset: bool, config: &SnakepitConfig) -> Result<()> {
+    if reset {
+        rate_limit::reset_stats()?;
+        logger::success("✓ Package index statistics reset");
+        return Ok(());
+    }
+
+    let rate = config.pypi_rate_limit.unwrap_or(rate_limit::DEFAULT_RATE);
+    logger::info(bold("Package Index Budget:"));
+    if rate > 0.0 {
+        println!("  Rate limit: {} requests/s", rate);
+    } else {
+        println!("  Rate limit: unlimited");
+    }
+    println!("  Max concurrency: {}", config.pypi_max_concurrency.unwrap_or(rate_limit::DEFAULT_CONCURRENCY));
+
+    let stats = rate_limit::load_stats();
+    if stats.is_empty() {
+        logger::note("No package index requests recorded yet");
+        return Ok(());
+    }
+    logger::info(bold("Requests by subsystem:"));
+    println!("  {:<12} {:>10} {:>10} {:>10}", "SUBSYSTEM", "REQUESTS", "THROTTLED", "WAITED");
+    for (subsystem, s) in &stats {
+        println!("  {:<12} {:>10} {:>10} {:>9.1}s", subsystem, s.requests, s.throttled, s.waited_ms as f64 / 1000.0);
+    }
+    Ok(())
+}
+
+/// Print the installed (or locked) dependency graph as a tree
+async fn show_dependency_tree(invert: Option<&str>, depth: Option<usize>, locked: bool, venv: Option<String>, output: output::OutputFormat) -> Result<()> {
+    let graph = if locked {
+        let lockfile = lockfile::Lockfile::load(Path::new(lockfile::LOCKFILE_NAME)).await
+            .with_context(|| format!("Failed to read {}", lockfile::LOCKFILE_NAME))?;
+        tree::DependencyGraph::from_lockfile(&lockfile)
+    } else {
+        installed_graph(venv)?
+    };
+    if graph.is_empty() && !output.is_json() {
+        logger::warn("No packages found");
+        return Ok(());
+    }
+    if output.is_json() {
+        let entries = match invert {
+            Some(package) if graph.get(package).is_none() => {
+                return Err(anyhow::anyhow!("'{}' is not in the dependency graph", package));
+            }
+            Some(package) => vec![graph.inverted_entry(package, depth)],
+            None => graph.entries(&project_roots(&graph), depth),
+        };
+        return output::print_json(&entries);
+    }
+
+    let lines = match invert {
+        Some(package) => {
+            if graph.get(package).is_none() {
+                return Err(anyhow::anyhow!("'{}' is not in the dependency graph", package));
+            }
+            graph.render_inverted(package, depth)
+        }
+        None => graph.render(&project_roots(&graph), depth),
+    };
+    for line in &lines {
+        println!("{}", line);
+    }
+    if lines.iter().any(|line| line.ends_with("(*)")) {
+        logger::note("(*) dependencies already shown above");
+    }
+    Ok(())
+}
+
+/// Dependency graph of the packages installed in `venv`, or the active environment
+fn installed_graph(venv: Option<String>) -> Result<tree::DependencyGraph> {
+    let mut installer = PackageInstaller::new();
+    if let Some(venv) = venv {
+        installer = installer.with_venv(venv);
+    }
+    tree::DependencyGraph::from_site_packages(&installer.site_packages()?)
+}
+
+/// The project's own requirements when there is a manifest, otherwise whatever nothing requires
+fn project_roots(graph: &tree::DependencyGraph) -> Vec<String> {
+    match load_project_dependencies() {
+        Ok(deps) => deps.dependencies.iter().chain(deps.dev_dependencies.iter())
+            .map(|dep| dep.name.clone())
+            .collect(),
+        Err(_) => graph.roots(),
+    }
+}
+
+/// `why`: every chain of requirements from the project down to `package`
+fn explain_installed(package: &str, venv: Option<String>) -> Result<()> {
+    let graph = installed_graph(venv)?;
+    let Some(node) = graph.get(package) else {
+        return Err(anyhow::anyhow!("Package '{}' is not installed", package));
+    };
+    let label = |name: &str| graph.get(name)
+        .map(|n| format!("{} {}", n.name, n.version))
+        .unwrap_or_else(|| name.to_string());
+
+    let roots = project_roots(&graph);
+    let chains = graph.chains_to(&roots, package, 50);
+    if chains.is_empty() {
+        let dependents: Vec<String> = graph.dependents(&manifest::canonical_name(package)).into_iter()
+            .map(|(dependent, _)| format!("{} {}", dependent.name, dependent.version))
+            .collect();
+        if dependents.is_empty() {
+            logger::warn(format!("{} {} is not required by anything; it is safe to remove", node.name, node.version));
+        } else {
+            logger::warn(format!("Nothing the project declares leads to {} {}; only these leftovers require it:", node.name, node.version));
+            for dependent in dependents {
+                println!("  {}", dependent);
+            }
+        }
+        return Ok(());
+    }
+
+    logger::info(bold(format!("{} {} is installed because:", node.name, node.version)));
+    for chain in &chains {
+        if chain.len() == 1 {
+            println!("  {} (declared directly)", label(&chain[0]));
+        } else {
+            println!("  {}", chain.iter().map(|name| label(name)).collect::<Vec<_>>().join(" → "));
+        }
+    }
+    if chains.len() == 50 {
+        logger::note("  (stopped after 50 chains)");
+    }
+    let declared = chains.iter().any(|chain| chain.len() == 1);
+    if !declared {
+        logger::note(format!("Removing {} would break the packages above", node.name));
+    }
+    Ok(())
+}
+
+/// `self update`: install the latest release over this binary; with `check`, only report
+/// and fail when this one is out of date
+async fn update_self(check: bool) -> Result<()> {
+    let updater = self_update::SelfUpdater::new();
+    let update = updater.latest().await?;
+    if !update.is_newer() {
+        logger::success(format!("✓ snakepit {} is the latest release", self_update::CURRENT_VERSION));
+        return Ok(());
+    }
+    if check {
+        println!("{}", update.version);
+        return Err(anyhow::anyhow!("snakepit {} is available (this is {}); run 'snakepit self update'", update.version, self_update::CURRENT_VERSION));
+    }
+
+    logger::info(blue(format!("Downloading snakepit {}...", update.version)));
+    let executable = updater.install(&update).await?;
+    logger::success(format!("✓ Updated {} from {} to {}", executable.display(), self_update::CURRENT_VERSION, update.version));
+    Ok(())
+}
+
+/// Assess the project in the current directory and print the report
+async fn show_posture(json: bool, min_score: Option<u32>, venv: Option<String>, config: &SnakepitConfig) -> Result<()> {
+    let root = std::env::current_dir()?;
+    let project = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let mut posture = posture::Posture::new(&project).with_config(config);
+
+    if let Ok(manifest) = load_project_dependencies() {
+        posture = posture.with_manifest(manifest);
+    }
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    if lock_path.exists() {
+        let content = std::fs::read_to_string(lock_path)
+            .with_context(|| format!("Failed to read {}", lockfile::LOCKFILE_NAME))?;
+        let problems = lockfile::Lockfile::check_format(&content)
+            .with_context(|| format!("Failed to parse {}", lockfile::LOCKFILE_NAME))?;
+        posture = posture.with_lockfile(lockfile::Lockfile::load(lock_path).await?, problems);
+    }
+    if let Ok(site) = agent_site_packages(venv) {
+        posture = posture.with_environment(agent::EnvironmentAgent::new(site).is_installed());
+    }
+
+    let report = posture.assess();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        logger::info(bold(format!("Security posture for '{}':", report.project)));
+        for check in &report.checks {
+            let (mark, score) = match (check.status, check.score) {
+                (posture::Status::Pass, Some(s)) => (green("✓"), s.to_string()),
+                (posture::Status::Warn, Some(s)) => (yellow("!"), s.to_string()),
+                (posture::Status::Fail, Some(s)) => (red("✗"), s.to_string()),
+                _ => (dim("-"), "n/a".to_string()),
+            };
+            println!("  {} {:<22} {:>4}  {}", mark, check.name, score, check.detail);
+        }
+        println!("  Overall: {}/100 ({})", report.score, report.grade);
+
+        let remediations = report.remediations();
+        if !remediations.is_empty() {
+            logger::info(bold("Suggested fixes:"));
+            for remediation in remediations {
+                println!("  - {}", remediation);
+            }
+        }
+    }
+
+    if let Some(min) = min_score {
+        if report.score < min {
+            return Err(anyhow::anyhow!("Posture score {} is below the required {}", report.score, min));
+        }
+    }
+    Ok(())
+}
+
+/// `check`: verify the environment's requirements, metadata and files
+fn check_environment(output: output::OutputFormat) -> Result<()> {
+    let mut installer = PackageInstaller::new();
+    if let Some(venv) = registry::ProjectRegistry::current_environment() {
+        installer = installer.with_venv(venv.to_string_lossy().to_string());
+    }
+    let report = check::check_environment(&installer.site_packages()?)?;
+
+    if output.is_json() {
+        output::print_json(&report)?;
+    } else {
+        for problem in &report.problems {
+            println!("  {} {}", red("✗"), problem);
+        }
+        let any = |wanted: fn(&check::Problem) -> bool| report.problems.iter().any(wanted);
+        if any(|p| matches!(p, check::Problem::Missing { .. } | check::Problem::Conflict { .. })) {
+            logger::note("  `snakepit install` the requirements listed, or `snakepit sync` to match the lockfile");
+        }
+        if any(|p| matches!(p, check::Problem::Duplicate { .. })) {
+            logger::note("  Delete the stale metadata directories, then reinstall the package");
+        }
+        if any(|p| matches!(p, check::Problem::Tampered { .. })) {
+            logger::note("  `snakepit venv repair` reinstalls packages whose files changed");
+        }
+    }
+
+    match report.problems.len() {
+        0 => {
+            logger::success(format!("✓ {} packages in {} are consistent", report.packages, report.site_packages.display()));
+            Ok(())
+        }
+        n => Err(anyhow::anyhow!("{} problem{} found in {}", n, if n == 1 { "" } else { "s" }, report.site_packages.display())),
+    }
+}
+
+/// `licenses`: group the environment's packages by license and enforce the allow/deny lists
+fn license_report(policy: &licenses::LicensePolicy, output: output::OutputFormat) -> Result<()> {
+    let mut installer = PackageInstaller::new();
+    if let Some(venv) = registry::ProjectRegistry::current_environment() {
+        installer = installer.with_venv(venv.to_string_lossy().to_string());
+    }
+    let site = installer.site_packages()?;
+    let packages = licenses::installed_licenses(&site)?;
+    let report = licenses::license_report(&site, &packages, policy);
+
+    if output.is_json() {
+        output::print_json(&report)?;
+    } else {
+        for (license, packages) in &report.licenses {
+            println!("{} {}", bold(license), dim(format!("({})", packages.len())));
+            for package in packages {
+                println!("  {}", package);
+            }
+        }
+        for violation in &report.violations {
+            let why = match violation.reason {
+                licenses::Reason::Denied => "is denied",
+                licenses::Reason::NotAllowed => "is not allowed",
+            };
+            let license = violation.license.as_deref().unwrap_or(licenses::UNKNOWN);
+            println!("  {} {} {}: {} {}", red("✗"), bold(&violation.package), violation.version, license, why);
+        }
+        if policy.is_empty() {
+            logger::note("  Set allowed_licenses or denied_licenses (or pass --allow/--deny) to enforce a policy");
+        }
+    }
+
+    match report.violations.len() {
+        0 => {
+            logger::success(format!("✓ {} packages under {} licenses", report.packages, report.licenses.len()));
+            Ok(())
+        }
+        n => Err(anyhow::anyhow!("{} package{} with a forbidden license", n, if n == 1 { "" } else { "s" })),
+    }
+}
+
+/// `audit`: look the environment's packages (or the lockfile's) up in OSV advisories
+async fn audit_packages(locked: bool, db: Option<String>, offline: bool, update_db: bool, ignore: &[String], output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
+    let snapshot = audit::AdvisorySource::default_snapshot_path(&config.get_cache_path());
+    if update_db {
+        logger::info(blue("Downloading the OSV PyPI advisory export..."));
+        let size = audit::AdvisorySource::download_snapshot(&snapshot).await?;
+        logger::success(format!("✓ Saved {} ({})", snapshot.display(), cache::format_size(size)));
+    }
+    let source = match db {
+        Some(path) => audit::AdvisorySource::snapshot(Path::new(&path))?,
+        None if offline || update_db => audit::AdvisorySource::snapshot(&snapshot)?,
+        None => audit::AdvisorySource::Online,
+    };
+
+    let packages: Vec<(String, String)> = if locked {
+        let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+        if !lock_path.exists() {
+            return Err(anyhow::anyhow!("No {} found; run 'snakepit lock' first", lockfile::LOCKFILE_NAME));
+        }
+        let lockfile = lockfile::Lockfile::load(lock_path).await
+            .with_context(|| format!("Failed to parse {}", lockfile::LOCKFILE_NAME))?;
+        packages_for_host(&lockfile)?.into_iter().map(|p| (p.name.clone(), p.version.clone())).collect()
+    } else {
+        let mut installer = PackageInstaller::new();
+        if let Some(venv) = registry::ProjectRegistry::current_environment() {
+            installer = installer.with_venv(venv.to_string_lossy().to_string());
+        }
+        freeze::installed_distributions(&installer.site_packages()?)?.into_iter().map(|d| (d.name, d.version)).collect()
+    };
+    logger::info(blue(format!("Auditing {} packages...", packages.len())));
+    let report = source.audit(&packages, ignore).await?;
+
+    if output.is_json() {
+        output::print_json(&report)?;
+    } else {
+        for vulnerability in &report.vulnerabilities {
+            let cves = vulnerability.cves();
+            let ids = if cves.is_empty() { vulnerability.id.clone() } else { format!("{} ({})", vulnerability.id, cves.join(", ")) };
+            println!("  {} {} {}  {}", red("✗"), bold(&vulnerability.package), vulnerability.version, ids);
+            if let Some(summary) = &vulnerability.summary {
+                println!("    {}", dim(summary));
+            }
+            match vulnerability.fixed_versions.first() {
+                Some(fixed) => println!("    fixed in {}", green(fixed)),
+                None => println!("    {}", yellow("no fixed release yet")),
+            }
+        }
+        if !report.ignored.is_empty() {
+            logger::note(format!("Ignored: {}", report.ignored.join(", ")));
+        }
+    }
+
+    match report.vulnerabilities.len() {
+        0 => {
+            logger::success(format!("✓ No known vulnerabilities in {} packages ({})", report.packages, report.source));
+            Ok(())
+        }
+        1 => Err(anyhow::anyhow!("1 known vulnerability found")),
+        n => Err(anyhow::anyhow!("{} known vulnerabilities found", n)),
+    }
+}
+
+/// `doctor`: check everything installs depend on and print a fix for each problem
+async fn run_doctor(output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
+    let request = std::env::current_dir().ok()
+        .and_then(|dir| toolchain::PythonRequest::for_project(&dir, config.python_version.as_deref()));
+    let report = doctor::Doctor::new(config)
+        .with_environment(registry::ProjectRegistry::current_environment())
+        .with_interpreter(project_interpreter(config))
+        .with_request(request)
+        .examine(DaemonManager::new().daemon_status().await)
+        .await;
+
+    if output.is_json() {
+        output::print_json(&report)?;
+    } else {
+        for check in &report.checks {
+            let mark = match check.status {
+                doctor::Status::Ok => green("✓"),
+                doctor::Status::Warn => yellow("!"),
+                doctor::Status::Fail => red("✗"),
+            };
+            println!("  {} {:<20} {}", mark, check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                println!("    {} {}", dim("fix:"), fix);
+            }
+        }
+    }
+
+    match report.failures() {
+        0 => Ok(()),
+        n => Err(anyhow::anyhow!("{} doctor check{} failed", n, if n == 1 { "" } else { "s" })),
+    }
+}
+
+/// `install -r`: like pip, any `--hash` in the files switches on hash-checking mode.
+/// Returns the names the files list.
+async fn install_requirements(files: &[String], require_hashes: bool, constraints: &constraints::Constraints, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<Vec<String>> {
+    let project_deps = requirements_from_files(files)?;
+
+    let pinned = inline_hashes(&project_deps);
+    let required_hashes = (require_hashes || !pinned.is_empty()).then_some(pinned);
+
+    logger::info(blue(format!("Installing from {}...", files.join(", "))));
+    install_project_dependencies(&project_deps, required_hashes, constraints, config, events).await?;
+    logger::success("✓ Requirements installed successfully!");
+    Ok(project_deps.dependencies.iter()
+        .chain(&project_deps.dev_dependencies)
+        .chain(&project_deps.editables)
+        .map(|dep| dep.name.clone())
+        .collect())
+}
+
+/// Everything the `-r` files list, as one set of requirements
+fn requirements_from_files(files: &[String]) -> Result<ProjectDependencies> {
+    let mut project_deps = ProjectDependencies::new();
+    for file in files {
+        let deps = ProjectDependencies::from_requirements_txt(file)?;
+        project_deps.dependencies.extend(deps.dependencies);
+        project_deps.dev_dependencies.extend(deps.dev_dependencies);
+        project_deps.editables.extend(deps.editables);
+    }
+    Ok(project_deps)
+}
+
+fn inline_hashes(project_deps: &ProjectDependencies) -> std::collections::HashMap<String, Vec<String>> {
+    project_deps.dependencies.iter()
+        .chain(project_deps.dev_dependencies.iter())
+        .filter(|dep| !dep.hashes.is_empty())
+        .map(|dep| (dep.name.clone(), dep.hashes.clone()))
+        .collect()
+}
+
+async fn install_project_dependencies(
+    project_deps: &ProjectDependencies,
+    required_hashes: Option<std::collections::HashMap<String, Vec<String>>>,
+    constraints: &constraints::Constraints,
+    config: &SnakepitConfig,
+    events: &renderer::EventBus,
+) -> Result<()> {
+    let all_deps = resolve_project_dependencies(project_deps, constraints, config).await?;
+
+    let backend = installer_backend(config);
+
+    let mut installer = PackageInstaller::new()
+        .with_backend(backend)
+        .with_events(events.clone())
+        .with_upstream(upstream::Upstream::from_config(config))
+        .with_interpreter(project_interpreter(config))
+        .with_constraint_files(constraints.files().to_vec())
+        .with_import_check(config.verify_imports.unwrap_or(false));
+
+    if let Some(pinned) = required_hashes {
+        installer = installer.with_required_hashes(pinned);
+    }
+
+    installer.install_dependencies(&all_deps).await?;
+
+    // Editables can't carry hashes and aren't on an index; they're built from source last
+    for editable in &project_deps.editables {
+        if let Some(source) = &editable.source {
+            installer.install_editable(&editable.name, source).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Every package `project_deps` needs, pinned by the solver (or the legacy resolver when configured)
+async fn resolve_project_dependencies(
+    project_deps: &ProjectDependencies,
+    constraints: &constraints::Constraints,
+    config: &SnakepitConfig,
+) -> Result<Vec<resolver::ResolvedDependency>> {
+    if !config.legacy_resolver.unwrap_or(false) {
+        return solve_project_dependencies(project_deps, constraints, config).await;
+    }
+    let mut resolver = selecting_resolver(config)?
+        .with_constraints(constraints.clone());
+    let resolved_deps = resolver.resolve_dependencies(project_deps).await?;
+    let mut all_deps = resolved_deps.dependencies;
+    all_deps.extend(resolved_deps.dev_dependencies);
+    Ok(all_deps)
+}
+
+/// Every package the project needs on this machine, pinned by the PEP 440 solver
+async fn solve_project_dependencies(
+    project_deps: &ProjectDependencies,
+    constraints: &constraints::Constraints,
+    config: &SnakepitConfig,
+) -> Result<Vec<resolver::ResolvedDependency>> {
+    for dep in project_deps.dependencies.iter().chain(project_deps.dev_dependencies.iter()) {
+        if let Some(source) = &dep.source {
+            logger::warn(format!("  ! {} comes from {}; it is not resolved from the index", dep.name, source));
+        }
+    }
+    let (project, mut solver) = project_solver(project_deps, &[], constraints, config)?;
+    let mut pinned = solver.solve().await?;
+    pinned.remove(&manifest::canonical_name(&project));
+
+    let dev: std::collections::HashSet<String> = project_deps.dev_dependencies.iter()
+        .map(|dep| manifest::canonical_name(&dep.name))
+        .collect();
+    let mut resolved: Vec<resolver::ResolvedDependency> = pinned.into_iter()
+        .map(|(name, version)| resolver::ResolvedDependency {
+            is_dev: dev.contains(&name),
+            name,
+            version: version.to_string(),
+            dependencies: Vec::new(),
+            source: None,
+        })
+        .collect();
+    resolved.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(resolved)
+}
+
+fn format_manifests(paths: &[String], check: bool) -> Result<()> {
+    use manifest::ManifestFormatter;
+
+    let formatter = ManifestFormatter::new(std::env::current_dir()?);
+    let files: Vec<std::path::PathBuf> = if paths.is_empty() {
+        formatter.discover()?
+    } else {
+        paths.iter().map(std::path::PathBuf::from).collect()
+    };
+
+    if files.is_empty() {
+        logger::warn("No manifests found");
+        return Ok(());
+    }
+
+    let mut changed = 0;
+    let mut problems = 0;
+
+    for file in &files {
+        let result = ManifestFormatter::format_path(file)
+            .with_context(|| format!("Failed to format {}", file.display()))?;
+        let display = file.strip_prefix(formatter.root()).unwrap_or(file).display();
+
+        for issue in &result.issues {
+            logger::error(format!("  ✗ {}: {}", display, issue));
+        }
+        problems += result.issues.len();
+
+        if result.changed() {
+            changed += 1;
+            if check {
+                println!("would reformat {}", display);
+            } else {
+                std::fs::write(file, &result.formatted)?;
+                println!("reformatted {}", display);
+            }
+        }
+    }
+
+    let unchanged = files.len() - changed;
+    if check {
+        logger::note(format!("{} manifests would be reformatted, {} already canonical", changed, unchanged));
+    } else {
+        logger::success(format!("✓ {} manifests reformatted, {} left unchanged", changed, unchanged));
+    }
+
+    if problems > 0 {
+        return Err(anyhow::anyhow!("{} manifest problems need manual attention", problems));
+    }
+    if check && changed > 0 {
+        return Err(anyhow::anyhow!("{} manifests are not canonically formatted (run 'snakepit fmt-manifest')", changed));
+    }
+
+    Ok(())
+}
+
+async fn search_packages(query: &str, config: &SnakepitConfig) -> Result<()> {
+    let backend = installer_backend(config);
+
+    let installer = PackageInstaller::new()
+        .with_backend(backend);
+
+    let results = installer.search_package(query).await?;
     
-    // Create basic requirements.txt
-    let requirements_path = format!("{}/requirements.txt", project_name);
-    std::fs::write(&requirements_path, "# Project dependencies\n")?;
+    if results.is_empty() {
+        logger::warn(format!("No packages found matching '{}'", query));
+    } else {
+        logger::info(blue(format!("Search results for '{}':", query)));
+        for result in results {
+            println!("  • {}", result);
+        }
+    }
     
+    Ok(())
+}
+
+/// `search --interactive`: browse the index, then install the pick like `snakepit install` would
+async fn explore_packages(query: Option<&str>, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    logger::note("Loading package list...");
+    let candidates = explorer::load_candidates(config).await;
+    let resolver = DependencyResolver::new()
+        .with_upstream(upstream::Upstream::from_config(config));
+    let chosen = explorer::Explorer::new(candidates, resolver)
+        .with_query(query.unwrap_or_default())
+        .run()
+        .await?;
+
+    match chosen {
+        Some(package) => {
+            logger::info(cyan(format!("📦 Installing {}...", package)));
+            install_package(&package, None, false, &constraints::Constraints::new(), config, events).await
+        }
+        None => {
+            logger::note("Nothing installed.");
+            Ok(())
+        }
+    }
+}
+
+/// `show`: the installed dist-info, topped up with what the index says; a package that's
+/// neither installed nor on the index is left to the backend (conda knows its own)
+async fn show_package(package: &str, files: bool, json: bool, config: &SnakepitConfig) -> Result<()> {
+    let mut installer = PackageInstaller::new().with_backend(installer_backend(config));
+    if let Some(venv) = registry::ProjectRegistry::current_environment() {
+        installer = installer.with_venv(venv.to_string_lossy().to_string());
+    }
+    let installed = package_info::PackageInfo::installed(&installer.site_packages()?, package)?;
+    let resolver = DependencyResolver::new()
+        .with_upstream(upstream::Upstream::from_config(config));
+    let index = resolver.fetch_package_info(package).await;
+
+    let info = match (installed, index) {
+        (Some(mut info), Ok(index)) => {
+            info.merge_index(&index.info);
+            info
+        }
+        (Some(info), Err(e)) => {
+            logger::note(format!("Index unavailable, showing installed metadata only: {}", e));
+            info
+        }
+        (None, Ok(index)) => package_info::PackageInfo::from_index(&index.info),
+        (None, Err(e)) => {
+            return match installer.show_package(package).await {
+                Ok(details) => {
+                    println!("{}", details);
+                    Ok(())
+                }
+                Err(_) => Err(anyhow::anyhow!("'{}' is neither installed nor on the index: {}", package, e)),
+            };
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+
+    println!("{} {}", bold(&info.name), info.version);
+    if let Some(summary) = &info.summary {
+        println!("{}", summary);
+    }
+    match &info.latest {
+        Some(latest) if info.is_installed() && *latest != info.version => {
+            println!("  {:<12} {}", "latest:", yellow(latest));
+        }
+        _ => {}
+    }
+    if !info.is_installed() {
+        println!("  {:<12} {}", "installed:", dim("no"));
+    }
+    if let Some(license) = &info.license {
+        println!("  {:<12} {}", "license:", license);
+    }
+    if let Some(homepage) = &info.homepage {
+        println!("  {:<12} {}", "homepage:", homepage);
+    }
+    if let Some(location) = &info.location {
+        println!("  {:<12} {}", "location:", location.display());
+    }
+    if let Some(size) = info.installed_size {
+        println!("  {:<12} {} in {} files", "size:", cache::format_size(size), info.files.len());
+    }
+    let list = |items: &[String]| if items.is_empty() { dim("none").to_string() } else { items.join(", ") };
+    println!("  {:<12} {}", "requires:", list(&info.requires_dist));
+    if info.is_installed() {
+        println!("  {:<12} {}", "required-by:", list(&info.required_by));
+    }
+    if files && !info.files.is_empty() {
+        println!("  files:");
+        for file in &info.files {
+            println!("    {}", file);
+        }
+    } else if !info.files.is_empty() {
+        logger::note("Run with --files to list the installed files");
+    }
+    Ok(())
+}
+
+/// `snakepit init`: a wizard for whatever `answers` leaves open, then the template's files
+/// and a pyproject.toml. Without a terminal every question takes its default.
+async fn init_project(name: Option<&str>, answers: &cli::InitArgs, config: &SnakepitConfig) -> Result<()> {
+    let ask_or = |question: &str, default: &str| -> Result<String> {
+        Ok(prompt::ask(&format!("{} [{}]", question, default))?
+            .filter(|answer| !answer.is_empty())
+            .unwrap_or_else(|| default.to_string()))
+    };
+    let choose = |question: &str, choices: Vec<String>| prompt::choose(question, &choices, 0);
+
+    let project_name = match name {
+        Some(name) => name.to_string(),
+        None => ask_or("Project name", "my-project")?,
+    };
+    let project_dir = Path::new(&project_name);
+    if project_dir.join("pyproject.toml").exists() || project_dir.join("snakepit.toml").exists() {
+        return Err(anyhow::anyhow!("{} already holds a project", project_dir.display()));
+    }
+
+    let mut options = templates::InitOptions::new(&project_name);
+    options.description = match &answers.description {
+        Some(description) => description.clone(),
+        None => prompt::ask("Description (optional)")?.unwrap_or_default(),
+    };
+    options.python_version = match &answers.python {
+        Some(python) => python.clone(),
+        None => ask_or("Minimum Python version", config.python_version.as_deref().unwrap_or("3.9"))?,
+    };
+    options.backend = match &answers.backend {
+        Some(backend) => backend.parse()?,
+        None => choose("Build backend", templates::BuildBackend::ALL.iter().map(|b| b.to_string()).collect())?.parse()?,
+    };
+    options.layout = match &answers.layout {
+        Some(layout) => layout.parse()?,
+        None => choose("Layout", templates::Layout::ALL.iter().map(|l| l.to_string()).collect())?.parse()?,
+    };
+    let license = match &answers.license {
+        Some(license) => license.clone(),
+        None => choose("License", templates::LICENSES.iter().map(|l| l.to_string()).collect())?,
+    };
+    options.license = (!license.eq_ignore_ascii_case("none")).then_some(license);
+    options.test_framework = match &answers.test {
+        Some(test) => test.parse()?,
+        None => choose("Test framework", templates::TestFramework::ALL.iter().map(|t| t.to_string()).collect())?.parse()?,
+    };
+    let template = match &answers.template {
+        Some(template) => template.clone(),
+        None => choose("Template", templates::available().into_iter().map(|(name, _)| name).collect())?,
+    };
+    options.author = std::process::Command::new("git").args(["config", "user.name"]).output().ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|author| !author.is_empty());
+
+    logger::info(blue(format!("Initializing project '{}' from the {} template...", project_name, template)));
+    let files = templates::render(templates::template_for(&template)?.as_ref(), &options)?;
+    for file in &files {
+        let path = project_dir.join(&file.path);
+        if path.exists() {
+            logger::warn(format!("  Keeping existing {}", path.display()));
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, &file.contents)?;
+        logger::note(format!("  created {}", file.path.display()));
+    }
+    if let Some(license) = options.license.as_deref().filter(|_| !project_dir.join("LICENSE").exists()) {
+        logger::note(format!("  Add the text of {} to a LICENSE file", license));
+    }
+
     // Create virtual environment if configured
     if let Some(venv_backend) = &config.default_venv_backend {
         let venv_manager = VirtualEnvironmentManager::new()
@@ -575,100 +3090,899 @@ async fn init_project(name: Option<&str>, config: &SnakepitConfig) -> Result<()>
                 _ => VenvBackend::Venv,
             });
         
-        let venv_path = venv_manager.create_venv(project_name, config.python_version.as_deref()).await?;
-        println!("{}", green(format!("✓ Virtual environment created at: {}", venv_path.display())));
+        let venv_path = venv_manager.create_venv(&project_name, config.python_version.as_deref()).await?;
+        logger::success(format!("✓ Virtual environment created at: {}", venv_path.display()));
+    }
+    
+    logger::success("✓ Project initialized successfully!");
+    logger::note(format!("  Run 'cd {}' to enter the project directory", project_name));
+    
+    Ok(())
+}
+
+/// `snakepit build`: the current project's distributions, both unless `--wheel` or `--sdist` narrows it
+async fn build_project(wheel: bool, sdist: bool, out_dir: &Path) -> Result<()> {
+    let kinds = match (wheel, sdist) {
+        (true, false) => vec![build::Distribution::Wheel],
+        (false, true) => vec![build::Distribution::Sdist],
+        _ => vec![build::Distribution::Sdist, build::Distribution::Wheel],
+    };
+    let project = std::env::current_dir()?;
+    let system = build::BuildSystem::for_project(&project)?;
+    let names: Vec<String> = kinds.iter().map(|kind| kind.to_string()).collect();
+    logger::info(blue(format!("Building {} with {}...", names.join(" and "), system.backend)));
+
+    let artifacts = build::ProjectBuilder::new(&project, out_dir).build(&kinds).await?;
+    for artifact in &artifacts {
+        let shown = artifact.strip_prefix(&project).unwrap_or(artifact);
+        println!("{} {}", green("✓"), shown.display());
+    }
+    Ok(())
+}
+
+/// `snakepit publish`: check every file first, so a bad one stops the release before anything is uploaded
+async fn publish_artifacts(files: &[String], index: &str, token: Option<&str>, dry_run: bool, skip_existing: bool, output: output::OutputFormat) -> Result<()> {
+    let url = publish::upload_url(index)?;
+    let paths = if files.is_empty() {
+        publish::find_artifacts(Path::new("dist"))?
+    } else {
+        files.iter().map(std::path::PathBuf::from).collect()
+    };
+    if paths.is_empty() {
+        return Err(anyhow::anyhow!("Nothing to publish; run `snakepit build` first"));
+    }
+    let artifacts = paths.iter().map(|path| publish::Artifact::inspect(path)).collect::<Result<Vec<_>>>()?;
+
+    let mut report = publish::PublishReport { url: url.to_string(), dry_run, files: Vec::new() };
+    if dry_run {
+        for artifact in artifacts {
+            if !output.is_json() {
+                println!("{} {} {}", cyan("would upload"), bold(&artifact.filename), dim(format!("sha256:{}", artifact.sha256)));
+            }
+            report.files.push(publish::PublishedFile { artifact, outcome: None });
+        }
+    } else {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("snakepit/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let credentials = publish::Credentials::find(&client, &url, token).await?;
+        logger::info(blue(format!("Uploading {} file{} to {} (credentials from {})...", artifacts.len(), if artifacts.len() == 1 { "" } else { "s" }, url, credentials.source)));
+        let uploader = publish::Uploader::new(client, url.clone(), credentials).with_skip_existing(skip_existing);
+        for artifact in artifacts {
+            let outcome = uploader.upload(&artifact).await?;
+            if !output.is_json() {
+                match outcome {
+                    publish::UploadOutcome::Uploaded => println!("{} {}", green("✓"), artifact.filename),
+                    publish::UploadOutcome::Skipped => println!("{} {} {}", yellow("-"), artifact.filename, dim("(already on the index)")),
+                }
+            }
+            report.files.push(publish::PublishedFile { artifact, outcome: Some(outcome) });
+        }
+    }
+
+    if output.is_json() {
+        output::print_json(&report)?;
+    } else if dry_run {
+        logger::success(format!("✓ {} file{} ready for {}", report.files.len(), if report.files.len() == 1 { "" } else { "s" }, url));
+    }
+    Ok(())
+}
+
+/// An interpreter for `request`: the newest managed build or `python3.X` on PATH that fits, else a fresh download
+async fn python_interpreter(request: &toolchain::PythonRequest) -> Result<std::path::PathBuf> {
+    let manager = toolchain::ToolchainManager::new();
+    if let Some(interpreter) = manager.find_interpreter(request) {
+        return Ok(interpreter);
+    }
+    logger::info(blue(format!("No Python {} here; downloading a standalone build...", request)));
+    let installed = manager.install(request).await?;
+    logger::success(format!("✓ Installed Python {}", installed.version));
+    Ok(installed.interpreter())
+}
+
+/// The interpreter installs outside a venv target: whatever `.python-version`, `requires-python`
+/// or `python_version` asks for, rather than the first `python3` on PATH; `None` inside a venv
+fn project_interpreter(config: &SnakepitConfig) -> Option<std::path::PathBuf> {
+    if registry::ProjectRegistry::current_environment().is_some() {
+        return None;
+    }
+    let request = toolchain::PythonRequest::for_project(&std::env::current_dir().ok()?, config.python_version.as_deref())?;
+    toolchain::ToolchainManager::new().find_interpreter(&request)
+}
+
+async fn handle_tool_command(command: cli::ToolCommands, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let tools = tools::ToolManager::new();
+    match command {
+        cli::ToolCommands::Install { package, python, force } => {
+            let name = requirement_name(&package)?;
+            if let Some(existing) = tools.receipt(&name).filter(|_| !force) {
+                return Err(anyhow::anyhow!("{} {} is already installed; use `snakepit tool upgrade {}` or --force", existing.name, existing.version, existing.name));
+            }
+            let receipt = install_tool(&tools, &package, python.as_deref(), config, events).await?;
+            logger::success(format!("✓ Installed {} {} with {}", receipt.name, receipt.version, receipt.commands.join(", ")));
+            warn_if_not_on_path(tools.bin_dir());
+        }
+        cli::ToolCommands::List => {
+            let installed = tools.list();
+            if installed.is_empty() {
+                logger::note("No tools installed; add one with `snakepit tool install <package>`");
+            }
+            for tool in &installed {
+                println!("{} {}", bold(&tool.name), tool.version);
+                println!("  {}", dim(tool.commands.join(", ")));
+            }
+        }
+        cli::ToolCommands::Upgrade { package, all } => {
+            let targets = if all {
+                tools.list()
+            } else {
+                let package = package.unwrap_or_default();
+                vec![tools.receipt(&package).ok_or_else(|| anyhow::anyhow!("{} is not installed as a tool (see `snakepit tool list`)", package))?]
+            };
+            for old in targets {
+                let receipt = install_tool(&tools, &old.requirement, old.python.as_deref(), config, events).await?;
+                if receipt.version == old.version {
+                    logger::note(format!("{} {} is up to date", receipt.name, receipt.version));
+                } else {
+                    logger::success(format!("✓ Upgraded {} {} → {}", receipt.name, old.version, receipt.version));
+                }
+            }
+        }
+        cli::ToolCommands::Uninstall { package } => {
+            let receipt = tools.uninstall(&package)?;
+            logger::success(format!("✓ Uninstalled {} and its commands ({})", receipt.name, receipt.commands.join(", ")));
+        }
+        cli::ToolCommands::Run { command, args } => {
+            // Like `snakepit x` for a tool that isn't installed
+            let Some(tool) = tools.find(&command) else {
+                return run_ephemeral_tool(&command, None, &args, None, config, events).await;
+            };
+            let program = if tool.commands.contains(&command) { command } else { tool.commands[0].clone() };
+            let status = activated_command(&program, &tools.tool_dir(&tool.name))?
+                .args(&args)
+                .status()
+                .with_context(|| format!("Failed to run {}", program))?;
+            std::process::exit(status.code().unwrap_or(1));
+        }
+    }
+    Ok(())
+}
+
+/// The project name in a requirement such as `black[d]==24.2.0`
+fn requirement_name(requirement: &str) -> Result<String> {
+    ProjectDependencies::from_requirement_lines([requirement], Path::new("."))?
+        .dependencies
+        .first()
+        .map(|dependency| dependency.name.clone())
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not a package requirement", requirement))
+}
+
+/// Build a fresh environment for the tool `requirement` names, install it, and link its
+/// commands. An environment that fails to build is removed, and the one it was replacing put back.
+async fn install_tool(tools: &tools::ToolManager, requirement: &str, python: Option<&str>, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<tools::ToolReceipt> {
+    let venv = tools.tool_dir(&requirement_name(requirement)?);
+    let previous = venv.with_extension("previous");
+    if venv.exists() {
+        let _ = std::fs::remove_dir_all(&previous);
+        std::fs::rename(&venv, &previous)?;
+    }
+    let receipt = match build_tool_environment(&venv, requirement, python, config, events).await {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            if previous.exists() {
+                let _ = std::fs::rename(&previous, &venv);
+            }
+            return Err(e);
+        }
+    };
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)?;
+    }
+    tools.save_receipt(&receipt)?;
+    for command in tools.link(&receipt.name, &receipt.commands)? {
+        logger::warn(format!("  ! {} already exists in {} and isn't snakepit's; run it with `snakepit tool run {}`", command, tools.bin_dir().display(), command));
+    }
+    Ok(receipt)
+}
+
+/// Create the venv `venv` and install the tool `requirement` names into it; `venv` is
+/// removed again when that fails or the package has no commands
+async fn build_tool_environment(venv: &Path, requirement: &str, python: Option<&str>, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<tools::ToolReceipt> {
+    let dependencies = ProjectDependencies::from_requirement_lines([requirement], Path::new("."))?;
+    let name = requirement_name(requirement)?;
+    let base = venv.parent().ok_or_else(|| anyhow::anyhow!("Invalid tool path {}", venv.display()))?;
+    std::fs::create_dir_all(base)?;
+
+    let built: Result<tools::ToolReceipt> = async {
+        let mut manager = VirtualEnvironmentManager::new().with_base_path(base.to_path_buf());
+        if let Some(version) = python {
+            manager = manager.with_interpreter(python_interpreter(&toolchain::PythonRequest::Version(version.to_string())).await?);
+        }
+        let dir_name = venv.file_name().unwrap_or_default().to_string_lossy().to_string();
+        manager.create_venv(&dir_name, python).await?;
+        registry::ProjectRegistry::choose_environment(Some(venv.to_path_buf()));
+        install_project_dependencies(&dependencies, None, &constraints::Constraints::new(), config, events).await?;
+        let site = manager.get_site_packages_path(venv)?;
+        let info = package_info::PackageInfo::installed(&site, &name)?
+            .ok_or_else(|| anyhow::anyhow!("{} was not installed", name))?;
+        let commands = tools::find_dist_info(&site, &name)
+            .map(|dist_info| tools::entry_point_commands(&dist_info))
+            .unwrap_or_default();
+        if commands.is_empty() {
+            return Err(anyhow::anyhow!("{} has no commands to run; use `snakepit install {}` for a library", info.name, name));
+        }
+        Ok(tools::ToolReceipt {
+            name: info.name,
+            requirement: requirement.to_string(),
+            version: info.version,
+            commands,
+            python: python.map(str::to_string),
+            installed_at: snakegg::native::datetime::DateTime::now().to_string(),
+        })
+    }.await;
+    if built.is_err() {
+        let _ = std::fs::remove_dir_all(venv);
+    }
+    built
+}
+
+/// `x`: run a tool's command from a cached environment, building it on first use, without
+/// installing the tool anywhere
+async fn run_ephemeral_tool(requirement: &str, command: Option<&str>, args: &[String], python: Option<&str>, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let venv = tools::ephemeral_dir(&config.get_cache_path(), requirement, python);
+    let receipt = match tools::read_receipt(&venv) {
+        Some(receipt) => receipt,
+        None => {
+            if venv.exists() {
+                std::fs::remove_dir_all(&venv)?;
+            }
+            logger::info(blue(format!("Preparing {}...", requirement)));
+            let receipt = build_tool_environment(&venv, requirement, python, config, events).await?;
+            tools::write_receipt(&venv, &receipt)?;
+            receipt
+        }
+    };
+    let program = tools::pick_command(&receipt, command)?;
+    let status = activated_command(&program, &venv)?
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn warn_if_not_on_path(dir: &Path) {
+    let on_path = std::env::var_os("PATH").is_some_and(|path| std::env::split_paths(&path).any(|entry| entry == dir));
+    if !on_path {
+        logger::warn(format!("  ! {} is not on PATH; add it to use the commands directly", dir.display()));
+    }
+}
+
+async fn handle_python_command(command: cli::PythonCommands) -> Result<()> {
+    let manager = toolchain::ToolchainManager::new();
+    match command {
+        cli::PythonCommands::List { available } => {
+            let pinned = std::env::current_dir().ok().and_then(|dir| toolchain::pinned_version(&dir));
+            let installed = manager.installed()?;
+            if installed.is_empty() {
+                logger::warn("No managed Python builds installed");
+            }
+            for python in &installed {
+                let marker = match &pinned {
+                    Some(pin) if toolchain::matches_request(&python.version, pin) => green(" (pinned)"),
+                    _ => String::new(),
+                };
+                println!("  {} {}{}", python.version, dim(python.interpreter().display()), marker);
+            }
+            if available {
+                logger::info(blue("Available for download:"));
+                for download in manager.available().await? {
+                    if !installed.iter().any(|python| python.version == download.version) {
+                        println!("  {} {}", download.version, dim(&download.filename));
+                    }
+                }
+            }
+            if let Some(pin) = pinned {
+                logger::note(format!("This project pins Python {} in {}", pin, toolchain::PIN_FILE));
+            }
+        }
+        cli::PythonCommands::Install { version } => {
+            if let Some(installed) = manager.find(&version)? {
+                logger::success(format!("✓ Python {} is already installed", installed.version));
+                return Ok(());
+            }
+            logger::info(blue(format!("Downloading Python {}...", version)));
+            let installed = manager.install(&toolchain::PythonRequest::Version(version)).await?;
+            logger::success(format!("✓ Installed Python {} at {}", installed.version, installed.interpreter().display()));
+        }
+        cli::PythonCommands::Uninstall { version } => {
+            let removed = manager.uninstall(&version)?;
+            logger::success(format!("✓ Removed Python {}", removed.version));
+        }
+        cli::PythonCommands::Pin { version } => {
+            let path = toolchain::pin_version(&std::env::current_dir()?, &version)?;
+            logger::success(format!("✓ Pinned Python {} in {}", version, path.display()));
+        }
     }
-    
-    println!("{}", green("✓ Project initialized successfully!"));
-    println!("{}", dim(format!("  Run 'cd {}' to enter the project directory", project_name)));
-    
     Ok(())
 }
 
-async fn handle_venv_command(command: cli::VenvCommands, config: &SnakepitConfig) -> Result<()> {
-    let venv_backend = match config.default_venv_backend.as_deref() {
+fn configured_venv_backend(config: &SnakepitConfig) -> VenvBackend {
+    match config.default_venv_backend.as_deref() {
         Some("conda") => VenvBackend::Conda,
         Some("poetry") => VenvBackend::Poetry,
         Some("virtualenv") => VenvBackend::Virtualenv,
         _ => VenvBackend::Venv,
-    };
+    }
+}
 
+async fn handle_venv_command(command: cli::VenvCommands, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
     let venv_manager = VirtualEnvironmentManager::new()
-        .with_backend(venv_backend);
+        .with_backend(configured_venv_backend(config));
 
     match command {
         cli::VenvCommands::Create { name, python_version } => {
+            let request = match &python_version {
+                Some(version) => Some(toolchain::PythonRequest::Version(version.clone())),
+                None => toolchain::PythonRequest::for_project(&std::env::current_dir()?, config.python_version.as_deref()),
+            };
+            let venv_manager = match &request {
+                Some(request) if matches!(configured_venv_backend(config), VenvBackend::Venv | VenvBackend::Virtualenv) => {
+                    venv_manager.with_interpreter(python_interpreter(request).await?)
+                }
+                _ => venv_manager,
+            };
             let venv_path = venv_manager.create_venv(&name, python_version.as_deref()).await?;
-            println!("{}", green(format!("✓ Virtual environment \'{}\' created at: {}", name, venv_path.display())));
+            logger::success(format!("✓ Virtual environment \'{}\' created at: {}", name, venv_path.display()));
+            if let Ok(root) = std::env::current_dir() {
+                ide::IdeHooks::refresh(&root, &venv_path);
+            }
         }
-        cli::VenvCommands::Activate { name } => {
-            let python_path = venv_manager.activate_venv(&name).await?;
-            println!("{}", green(format!("✓ Virtual environment '{}' activated", name)));
-            println!("{}", dim(format!("Python path: {}", python_path.display())));
+        cli::VenvCommands::Activate { name, print_script, shell } => {
+            if print_script {
+                let venv_path = venv_manager.get_venv_path(&name);
+                if !venv_path.exists() {
+                    return Err(anyhow::anyhow!("Virtual environment '{}' does not exist", name));
+                }
+                let shell = match shell {
+                    Some(shell) => venv::Shell::parse(&shell)?,
+                    None => venv::Shell::detect(),
+                };
+                print!("{}", shell.activation_script(&venv_path));
+            } else {
+                let python_path = venv_manager.activate_venv(&name).await?;
+                logger::note(format!("Python path: {}", python_path.display()));
+                logger::note(format!("A child process can't change your shell; run `eval \"$(snakepit venv activate {} --print-script)\"` or `snakepit shell --venv {}`", name, name));
+            }
         }
         cli::VenvCommands::Delete { name } => {
             venv_manager.delete_venv(&name).await?;
-            println!("{}", green(format!("✓ Virtual environment '{}' deleted", name)));
+            logger::success(format!("✓ Virtual environment '{}' deleted", name));
         }
         cli::VenvCommands::List => {
             let venvs = venv_manager.list_venvs().await?;
             if venvs.is_empty() {
-                println!("{}", yellow("No virtual environments found"));
+                logger::warn("No virtual environments found");
             } else {
-                println!("{}", blue("Available virtual environments:"));
+                logger::info(blue("Available virtual environments:"));
                 for venv in venvs {
                     println!("  • {}", venv);
                 }
             }
         }
+        cli::VenvCommands::Info { name } => {
+            let info = venv_manager.info(&named_environment(name.as_deref(), &venv_manager)?)?;
+            let unknown = || dim("unknown").to_string();
+
+            println!("{} {}", bold("Environment:"), info.path.display());
+            println!("{} {}", bold("Python:"), info.python_version.clone().unwrap_or_else(unknown));
+            println!("{} {}", bold("Interpreter:"), info.interpreter.map(|p| p.display().to_string()).unwrap_or_else(unknown));
+            match &info.site_packages {
+                Some(site) => println!("{} {} ({})", bold("Site-packages:"), site.display(), cache::format_size(info.site_packages_size)),
+                None => println!("{} {}", bold("Site-packages:"), unknown()),
+            }
+            println!("{} {}", bold("Packages:"), info.package_count);
+            println!("{} {}", bold("Created:"), info.created_at.map(daemon_log::format_timestamp).unwrap_or_else(unknown));
+            match &info.metadata {
+                Some(metadata) => println!("{} {} {}", bold("Backend:"), metadata.backend, dim(format!("(snakepit {})", metadata.snakepit_version))),
+                None => println!("{} {}", bold("Backend:"), dim("unknown (not created by snakepit)")),
+            }
+        }
+        cli::VenvCommands::Repair { name, recreate } => {
+            let venv_path = named_environment(name.as_deref(), &venv_manager)?;
+            if recreate {
+                recreate_environment(&venv_path, config, events).await?;
+            } else {
+                repair_environment(&venv_path, &venv_manager, config, events).await?;
+            }
+        }
     }
     
     Ok(())
 }
 
-async fn update_project_dependencies(_dependency: &Dependency, _config: &SnakepitConfig) -> Result<()> {
-    // This would update the project's dependency files
-    // For now, just a placeholder
-    println!("{}", dim("Updating project dependencies..."));
+/// A venv by name or path; the project's environment when `name` is `None`
+fn named_environment(name: Option<&str>, venv_manager: &VirtualEnvironmentManager) -> Result<std::path::PathBuf> {
+    let venv_path = match name {
+        Some(name) if Path::new(name).join("pyvenv.cfg").exists() => std::path::PathBuf::from(name),
+        Some(name) => venv_manager.get_venv_path(name),
+        None => registry::ProjectRegistry::current_environment()
+            .ok_or_else(|| anyhow::anyhow!("No environment here; name one or create .venv with `snakepit venv create`"))?,
+    };
+    if !venv_path.exists() {
+        return Err(anyhow::anyhow!("Virtual environment {} does not exist", venv_path.display()));
+    }
+    Ok(venv_path)
+}
+
+/// `venv repair`: reinstall every package whose files no longer match its RECORD,
+/// at the locked version when snakepit.lock has one
+async fn repair_environment(venv_path: &Path, venv_manager: &VirtualEnvironmentManager, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let site_packages = venv_manager.get_site_packages_path(venv_path)?;
+    logger::info(blue(format!("Verifying {}...", site_packages.display())));
+    let damaged = repair::verify_site_packages(&site_packages);
+    if damaged.is_empty() {
+        logger::success("✓ Every installed file matches its RECORD");
+        return Ok(());
+    }
+
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    let lock = if lock_path.exists() { Some(lockfile::Lockfile::load(lock_path).await?) } else { None };
+    let installer = || PackageInstaller::new()
+        .with_venv(venv_path.to_string_lossy().to_string())
+        .with_backend(installer_backend(config))
+        .with_events(events.clone())
+        .with_upstream(upstream::Upstream::from_config(config))
+        .with_interpreter(project_interpreter(config));
+
+    for package in &damaged {
+        logger::warn(format!("  ! {} {}: {} file(s) damaged", package.name, package.version, package.files.len()));
+        for file in &package.files {
+            let problem = if file.missing { "missing" } else { "modified" };
+            logger::note(format!("      {} ({})", file.path.display(), problem));
+        }
+
+        let locked = lock.iter()
+            .flat_map(|lock| lock.packages.iter())
+            .find(|locked| manifest::canonical_name(&locked.name) == package.name);
+        let version = locked.map(|locked| locked.version.as_str()).unwrap_or(&package.version);
+        let installer = match locked {
+            Some(locked) if !locked.hashes.is_empty() => installer()
+                .with_required_hashes(std::collections::HashMap::from([(package.name.clone(), locked.hashes.clone())])),
+            _ => installer(),
+        };
+        installer.uninstall_package(&package.name).await?;
+        installer.install_package(&package.name, Some(version)).await
+            .with_context(|| format!("Failed to reinstall {}", package.name))?;
+    }
+
+    logger::success(format!("✓ Reinstalled {} damaged package(s)", damaged.len()));
+    Ok(())
+}
+
+/// `venv repair --recreate`: delete the environment and build it again in place with the
+/// project's interpreter, then install snakepit.lock (or whatever was installed before)
+async fn recreate_environment(venv_path: &Path, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let venv_path = venv_path.canonicalize()?;
+    let (Some(base), Some(name)) = (venv_path.parent(), venv_path.file_name()) else {
+        return Err(anyhow::anyhow!("Can't recreate {}", venv_path.display()));
+    };
+    let backend = configured_venv_backend(config);
+    let mut manager = VirtualEnvironmentManager::new()
+        .with_backend(backend.clone())
+        .with_base_path(base.to_path_buf());
+
+    // Without a lock, put back what's there now
+    let lock_path = Path::new(lockfile::LOCKFILE_NAME);
+    let previous: Vec<resolver::ResolvedDependency> = if lock_path.exists() {
+        Vec::new()
+    } else {
+        tree::DependencyGraph::from_site_packages(&manager.get_site_packages_path(&venv_path)?)?
+            .nodes()
+            .map(|node| resolver::ResolvedDependency {
+                name: node.name.clone(),
+                version: node.version.clone(),
+                is_dev: false,
+                dependencies: Vec::new(),
+                source: None,
+            })
+            .collect()
+    };
+
+    let request = toolchain::PythonRequest::for_project(&std::env::current_dir()?, config.python_version.as_deref());
+    if let (Some(request), VenvBackend::Venv | VenvBackend::Virtualenv) = (&request, backend) {
+        manager = manager.with_interpreter(python_interpreter(request).await?);
+    }
+    let version = match &request {
+        Some(toolchain::PythonRequest::Version(version)) => Some(version.as_str()),
+        _ => None,
+    };
+
+    logger::info(blue(format!("Recreating {}...", venv_path.display())));
+    std::fs::remove_dir_all(&venv_path)
+        .with_context(|| format!("Failed to remove {}", venv_path.display()))?;
+    let name = name.to_string_lossy().to_string();
+    let venv = manager.create_venv(&name, version).await?;
+    registry::ProjectRegistry::choose_environment(Some(venv.clone()));
+
+    if lock_path.exists() {
+        sync_frozen(false, config, events).await
+    } else {
+        PackageInstaller::new()
+            .with_backend(installer_backend(config))
+            .with_events(events.clone())
+            .with_upstream(upstream::Upstream::from_config(config))
+            .with_interpreter(project_interpreter(config))
+            .install_dependencies(&previous)
+            .await?;
+        logger::success(format!("✓ Recreated {} with {} package(s)", venv.display(), previous.len()));
+        Ok(())
+    }
+}
+
+/// `env export`: a conda environment's packages as environment.yml, on stdout or into `output`
+fn export_conda_environment(name: Option<&str>, output: Option<&str>) -> Result<()> {
+    let manager = VirtualEnvironmentManager::new().with_backend(VenvBackend::Conda);
+    let prefix = named_environment(name, &manager)?;
+    if !prefix.join("conda-meta").exists() {
+        return Err(anyhow::anyhow!("{} is not a conda environment (use 'snakepit export' for virtualenvs)", prefix.display()));
+    }
+
+    let content = conda_env::EnvironmentFile::from_prefix(&prefix)?.render();
+    match output {
+        Some(path) => {
+            std::fs::write(path, &content)
+                .with_context(|| format!("Failed to write {}", path))?;
+            logger::success(format!("✓ Exported {} to {}", prefix.display(), path));
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// `env import`: create the conda environment `file` describes (or update it, if it exists).
+///
+/// Conda specs go through the conda backend with the file's channels; the `pip:` section
+/// is resolved like any project's requirements and installed with the configured backend.
+async fn import_conda_environment(file: &Path, name: Option<&str>, config: &SnakepitConfig, events: &renderer::EventBus) -> Result<()> {
+    let env = conda_env::EnvironmentFile::load(file)?;
+    let name = name.map(str::to_string).or_else(|| env.name.clone())
+        .ok_or_else(|| anyhow::anyhow!("{} has no name; pass --name", file.display()))?;
+
+    let manager = VirtualEnvironmentManager::new().with_backend(VenvBackend::Conda);
+    let prefix = manager.get_venv_path(&name);
+    if prefix.exists() {
+        logger::info(blue(format!("Updating conda environment '{}' from {}...", name, file.display())));
+    } else {
+        manager.create_venv(&name, env.python_version().as_deref()).await?;
+    }
+    registry::ProjectRegistry::choose_environment(Some(prefix.clone()));
+
+    let conda = PackageInstaller::new()
+        .with_venv(prefix.to_string_lossy().to_string())
+        .with_backend(InstallerBackend::named("conda")?)
+        .with_channels(env.channels.clone())
+        .with_events(events.clone());
+    for spec in &env.conda {
+        let (package, version) = conda_env::split_spec(spec);
+        conda.install_package(&package, version.as_deref()).await?;
+    }
+
+    if !env.pip.is_empty() {
+        let dir = file.parent().unwrap_or(Path::new(""));
+        let project_deps = ProjectDependencies::from_requirement_lines(env.pip.iter().map(String::as_str), dir)?;
+        let resolved = solve_project_dependencies(&project_deps, &constraints::Constraints::new(), config).await?;
+        let backend = match installer_backend(config) {
+            backend if backend.name() == "conda" => InstallerBackend::named("native")?,
+            backend => backend,
+        };
+        let installer = PackageInstaller::new()
+            .with_venv(prefix.to_string_lossy().to_string())
+            .with_backend(backend)
+            .with_events(events.clone())
+            .with_upstream(upstream::Upstream::from_config(config));
+        installer.install_dependencies(&resolved).await?;
+        for editable in &project_deps.editables {
+            if let Some(source) = &editable.source {
+                installer.install_editable(&editable.name, source).await?;
+            }
+        }
+    }
+
+    logger::success(format!(
+        "✓ Conda environment '{}' at {} ({} conda, {} pip requirements)",
+        name, prefix.display(), env.conda.len(), env.pip.len()
+    ));
+    Ok(())
+}
+
+/// The file `config set`/`unset` writes: the project's snakepit.toml with `--project`, else the user config
+fn config_layer(layer: &cli::ConfigLayerArgs) -> Result<config::ConfigSource> {
+    if layer.project {
+        Ok(config::ConfigSource::Project(config::project_config_path(&std::env::current_dir()?)))
+    } else {
+        Ok(config::ConfigSource::User(SnakepitConfig::get_config_path()?))
+    }
+}
+
+/// `config show`: the merged settings as TOML, each annotated with its layer when `origin` is set
+fn show_config(layers: &config::LayeredConfig, origin: bool) -> Result<()> {
+    for (key, value, source) in layers.entries() {
+        let mut single = toml::Table::new();
+        single.insert(key.to_string(), value.clone());
+        let line = toml::to_string(&single)?;
+        if origin {
+            println!("{} {}", line.trim_end(), dim(format!("# {}", source)));
+        } else {
+            print!("{}", line);
+        }
+    }
+    Ok(())
+}
+
+fn handle_cache_command(command: cli::CacheCommands, config: &SnakepitConfig) -> Result<()> {
+    use cache::{CacheManager, CacheKind, VerifyStatus, format_size};
+
+    let cache = CacheManager::new(config.get_cache_path());
+
+    match command {
+        cli::CacheCommands::List => {
+            let entries = cache.entries()?;
+            if entries.is_empty() {
+                logger::warn("Cache is empty");
+            } else {
+                logger::info(blue(format!("Cached entries ({}):", entries.len())));
+                for entry in entries {
+                    let label = match entry.kind {
+                        CacheKind::Wheel => entry.name.clone(),
+                        CacheKind::Metadata => format!("{} (metadata)", entry.name),
+                    };
+                    println!("  • {:<24} {:>10}  {}", entry.package, format_size(entry.size), label);
+                }
+            }
+        }
+        cli::CacheCommands::Info => {
+            let entries = cache.entries()?;
+            let wheels: Vec<_> = entries.iter().filter(|e| e.kind == CacheKind::Wheel).collect();
+            let metadata: Vec<_> = entries.iter().filter(|e| e.kind == CacheKind::Metadata).collect();
+
+            logger::info(bold("Cache Information:"));
+            println!("  Location: {}", config.get_cache_path().display());
+            println!("  Wheels: {} ({})", wheels.len(), format_size(wheels.iter().map(|e| e.size).sum()));
+            println!("  Metadata: {} ({})", metadata.len(), format_size(metadata.iter().map(|e| e.size).sum()));
+            println!("  Total: {}", format_size(entries.iter().map(|e| e.size).sum()));
+            if let Some(cap) = &config.cache_max_size {
+                println!("  Size cap: {}", cap);
+            }
+        }
+        cli::CacheCommands::Clean { package } => {
+            let (count, freed) = cache.clean(package.as_deref())?;
+            logger::success(format!("✓ Removed {} cache entries ({})", count, format_size(freed)));
+        }
+        cli::CacheCommands::Prune { max_age, max_size } => {
+            let max_age = match max_age {
+                Some(age) => Some(cache::parse_age(&age)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid age '{}' (expected e.g. 12h, 7d, 2w)", age))?),
+                None => None,
+            };
+            let max_size = match max_size.or_else(|| config.cache_max_size.clone()) {
+                Some(size) => Some(cache::parse_size(&size)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid size '{}' (expected e.g. 500MB, 2GB)", size))?),
+                None => None,
+            };
+
+            if max_age.is_none() && max_size.is_none() {
+                return Err(anyhow::anyhow!("Nothing to prune: pass --max-age or --max-size, or set cache_max_size in the config"));
+            }
+
+            let (count, freed) = cache.prune(max_age, max_size)?;
+            logger::success(format!("✓ Pruned {} cache entries ({})", count, format_size(freed)));
+            logger::note(format!("Cache size is now {}", format_size(cache.total_size()?)));
+        }
+        cli::CacheCommands::Verify { fix } => {
+            let results = cache.verify()?;
+            let mut corrupted = Vec::new();
+
+            for (entry, status) in results {
+                match status {
+                    VerifyStatus::Ok => println!("  ✓ {}", entry.name),
+                    VerifyStatus::Unverifiable => println!("  ? {} (no recorded hash)", entry.name),
+                    VerifyStatus::Mismatch { expected, actual } => {
+                        println!("  ✗ {} (expected sha256:{}, got sha256:{})", entry.name, expected, actual);
+                        corrupted.push(entry);
+                    }
+                }
+            }
+
+            if corrupted.is_empty() {
+                logger::success("✓ All verifiable cached wheels match their recorded hashes");
+            } else if fix {
+                let (count, _) = CacheManager::remove_entries(&corrupted)?;
+                logger::warn(format!("⚠️  Removed {} corrupted wheels", count));
+            } else {
+                return Err(anyhow::anyhow!("{} cached wheels failed verification (rerun with --fix to remove them)", corrupted.len()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Remember this project so shared-environment removals can reference-count it
+/// site-packages of `--venv`, or of the active environment
+fn agent_site_packages(venv: Option<String>) -> Result<std::path::PathBuf> {
+    let venv = venv.map(std::path::PathBuf::from)
+        .or_else(registry::ProjectRegistry::current_environment)
+        .ok_or_else(|| anyhow::anyhow!("No environment given; activate one or pass --venv"))?;
+    if !venv.join("pyvenv.cfg").exists() {
+        return Err(anyhow::anyhow!("{} is not a virtual environment", venv.display()));
+    }
+    PackageInstaller::new().with_venv(venv.to_string_lossy().to_string()).site_packages()
+}
+
+fn handle_agent_command(command: cli::AgentCommands) -> Result<()> {
+    match command {
+        cli::AgentCommands::Install { venv, interval, sample } => {
+            let site = agent_site_packages(venv)?;
+            let manifest = agent::EnvironmentAgent::new(site.clone())
+                .with_interval(interval)
+                .with_sample_size(sample)
+                .install()?;
+            logger::success(format!(
+                "✓ Agent installed in {} ({} packages, {} files recorded)",
+                manifest.environment.display(), manifest.versions.len(), manifest.files.len()
+            ));
+            logger::note(format!("Run a full check from inside the environment with: python -m {}", agent::AGENT_PACKAGE));
+        }
+        cli::AgentCommands::Refresh { venv } => {
+            let site = agent_site_packages(venv)?;
+            let agent = agent::EnvironmentAgent::new(site);
+            if !agent.is_installed() {
+                return Err(anyhow::anyhow!("The agent isn't installed here; run 'snakepit agent install' first"));
+            }
+            let manifest = agent.refresh()?;
+            logger::success(format!("✓ Recorded {} packages and {} files as the expected state", manifest.versions.len(), manifest.files.len()));
+        }
+        cli::AgentCommands::Remove { venv } => {
+            agent::EnvironmentAgent::new(agent_site_packages(venv)?).remove()?;
+            logger::success("✓ Agent removed");
+        }
+    }
     Ok(())
 }
 
-async fn handle_daemon_command(command: cli::DaemonCommands, config: &SnakepitConfig) -> Result<()> {
+/// Snakepit's own changes to the active environment are expected; re-baseline its agent
+fn refresh_environment_agent() {
+    if let Some(venv) = registry::ProjectRegistry::current_environment() {
+        if let Ok(site) = PackageInstaller::new().with_venv(venv.to_string_lossy().to_string()).site_packages() {
+            agent::EnvironmentAgent::refresh_if_installed(&site);
+        }
+    }
+}
+
+fn register_current_project() {
+    if let (Ok(mut registry), Ok(root)) = (registry::ProjectRegistry::load(), std::env::current_dir()) {
+        registry.register(&root, registry::ProjectRegistry::current_environment());
+        registry.prune_missing();
+        let _ = registry.save();
+    }
+}
+
+/// `--eco`/`--no-eco` for a detached daemon, so its job scheduling follows this command's choice
+fn eco_args(config: &SnakepitConfig) -> Vec<String> {
+    match config.eco_mode {
+        Some(true) => vec!["--eco".to_string()],
+        Some(false) => vec!["--no-eco".to_string()],
+        None => Vec::new(),
+    }
+}
+
+async fn handle_daemon_command(command: cli::DaemonCommands, output: output::OutputFormat, config: &SnakepitConfig) -> Result<()> {
     let daemon_manager = DaemonManager::new();
 
     match command {
-        cli::DaemonCommands::Start { daemon, config: _config_path } => {
+        cli::DaemonCommands::Start { daemon, config: _config_path, environment } => {
             if daemon {
-                println!("{}", blue("Starting snakepit daemon in background..."));
-                // In a real implementation, you'd fork the process here
-                daemon_manager.start_daemon(config).await?;
+                logger::info(blue("Starting snakepit daemon in background..."));
+                // The detached daemon installs into the same environment this one would
+                let mut args = Vec::new();
+                if let Some(venv) = environment.venv {
+                    args.extend(["--venv".to_string(), venv]);
+                }
+                if environment.system {
+                    args.push("--system".to_string());
+                }
+                args.extend(eco_args(config));
+                let (pid, log) = daemon_manager.start_detached(&args).await?;
+                logger::success(format!("✓ Daemon started (pid {}); output goes to {}", pid, log.display()));
             } else {
-                println!("{}", blue("Starting snakepit daemon in foreground..."));
+                logger::info(blue("Starting snakepit daemon in foreground..."));
                 daemon_manager.start_daemon(config).await?;
             }
         }
         cli::DaemonCommands::Stop => {
             daemon_manager.stop_daemon().await?;
-            println!("{}", green("✓ Daemon stopped"));
+            logger::success("✓ Daemon stopped");
         }
         cli::DaemonCommands::Status => {
             let status = daemon_manager.daemon_status().await?;
-            println!("{}", blue("Snakepit Daemon Status"));
+            if output.is_json() {
+                return output::print_json(&output::DaemonStatusReport::from(&status));
+            }
+            logger::info(blue("Snakepit Daemon Status"));
             println!("  Running: {}", if status.running { "✅ Yes" } else { "❌ No" });
             println!("  Daemon ID: {}", status.daemon_id);
+            if let Some(pid) = status.pid {
+                println!("  PID: {}", pid);
+            }
+            if let Some(started_at) = status.started_at {
+                println!("  Started: {}", daemon_log::format_timestamp(started_at));
+            }
             println!("  Error Count: {}", status.error_count);
             println!("  Auto-install: {}", if status.config.auto_install { "✅ Yes" } else { "❌ No" });
             println!("  Check Interval: {}s", status.config.check_interval.as_secs());
         }
         cli::DaemonCommands::Restart => {
-            println!("{}", yellow("Restarting daemon..."));
-            daemon_manager.stop_daemon().await?;
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-            daemon_manager.start_daemon(config).await?;
-            println!("{}", green("✓ Daemon restarted"));
+            logger::warn("Restarting daemon...");
+            if let Err(e) = daemon_manager.stop_daemon().await {
+                logger::note(format!("{}; starting a new one", e));
+            }
+            let (pid, _log) = daemon_manager.start_detached(&eco_args(config)).await?;
+            logger::success(format!("✓ Daemon restarted (pid {})", pid));
+        }
+        cli::DaemonCommands::Reload => {
+            let reloaded = daemon_manager.reload_config().await?;
+            logger::success(format!(
+                "✓ Daemon reloaded its configuration (auto-install {}, every {}s)",
+                if reloaded.auto_install { "on" } else { "off" },
+                reloaded.check_interval.as_secs(),
+            ));
+        }
+        cli::DaemonCommands::InstallService => {
+            let manager = service::ServiceManager::detect()?;
+            // The service's daemon would refuse to start next to one started by hand
+            if daemon_manager.daemon_status().await?.running {
+                daemon_manager.stop_daemon().await?;
+            }
+            let output = daemon_manager.output_log(&daemon_manager.load_daemon_config().await?);
+            let path = manager.install(&output)?;
+            logger::success(format!("✓ Installed and started the daemon service ({})", path.display()));
+        }
+        cli::DaemonCommands::UninstallService => {
+            match service::ServiceManager::detect()?.uninstall()? {
+                Some(path) => logger::success(format!("✓ Removed the daemon service ({})", path.display())),
+                None => logger::note("The daemon service isn't installed"),
+            }
+        }
+        cli::DaemonCommands::Errors { module } => {
+            let errors = daemon_manager.module_errors(module.as_deref()).await?;
+            if errors.is_empty() {
+                logger::note("The daemon has no cached module errors");
+            }
+            for error in &errors {
+                let seen = error.timestamp.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+                println!("{}  {}  {} (attempts: {}, pid {})",
+                    daemon_log::format_timestamp(seen), bold(&error.module_name), error.error_message, error.install_attempts, error.process_id);
+            }
+        }
+        cli::DaemonCommands::Pending => {
+            let pending = daemon_manager.pending_approvals().await?;
+            if pending.is_empty() {
+                logger::note("No modules are waiting for approval");
+            }
+            for item in &pending {
+                println!("{}  {}  from {}", daemon_log::format_timestamp(item.queued_at), bold(&item.detection.module), item.detection.origin);
+            }
+        }
+        cli::DaemonCommands::Approve { module } => {
+            daemon_manager.resolve_approval(&module, true).await?;
+            logger::success(format!("✓ Approved {}; the daemon is installing it and will install it without asking from now on", module));
+        }
+        cli::DaemonCommands::Deny { module } => {
+            daemon_manager.resolve_approval(&module, false).await?;
+            logger::success(format!("✓ Denied {}", module));
         }
         cli::DaemonCommands::Test { module } => {
-            println!("{}", cyan(format!("Testing missing module: {}", module)));
+            logger::info(cyan(format!("Testing missing module: {}", module)));
             let daemon_config = daemon_manager.load_daemon_config().await?;
             let daemon = daemon::SnakepitDaemon::new(daemon_config, config);
             daemon.simulate_missing_module(&module).await?;
@@ -676,11 +3990,71 @@ async fn handle_daemon_command(command: cli::DaemonCommands, config: &SnakepitCo
         cli::DaemonCommands::Config { command } => {
             handle_daemon_config_command(command, &daemon_manager).await?;
         }
+        cli::DaemonCommands::Logs { follow, level, module, project, since, until, lines, export } => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let filter = daemon_log::LogFilter {
+                min_severity: level.as_deref().map(str::parse).transpose()?,
+                module,
+                project: project.map(|p| Path::new(&p).canonicalize().unwrap_or_else(|_| p.into())),
+                since: since.as_deref().map(|t| daemon_log::parse_time(t, now)).transpose()?,
+                until: until.as_deref().map(|t| daemon_log::parse_time(t, now)).transpose()?,
+            };
+            show_daemon_logs(&filter, follow, lines, export.as_deref()).await?;
+        }
+        cli::DaemonCommands::History { since, module } => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let since = since.as_deref().map(|t| daemon_log::parse_time(t, now)).transpose()?;
+            let events = daemon_history::EventJournal::open_default()?.read(since, module.as_deref())?;
+            if output.is_json() {
+                println!("{}", serde_json::to_string_pretty(&events)?);
+            } else if events.is_empty() {
+                logger::note("The auto-installer hasn't done anything in this period");
+            } else {
+                for event in &events {
+                    println!("{}", event.render());
+                }
+            }
+        }
     }
     
     Ok(())
 }
 
+async fn show_daemon_logs(filter: &daemon_log::LogFilter, follow: bool, lines: usize, export: Option<&str>) -> Result<()> {
+    let journal = daemon_log::DaemonLog::open_default()?;
+    let entries = journal.read(filter)?;
+
+    if let Some(export) = export {
+        let mut content = String::new();
+        for entry in &entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        std::fs::write(export, content)?;
+        logger::success(format!("✓ Exported {} entries to {}", entries.len(), export));
+        return Ok(());
+    }
+
+    if entries.is_empty() && !follow {
+        logger::note(format!("No matching entries in {}", journal.path().display()));
+    }
+    for entry in &entries[entries.len().saturating_sub(lines)..] {
+        println!("{}", entry.render());
+    }
+
+    if follow {
+        logger::note("Following daemon log (Ctrl-C to stop)...");
+        daemon_log::follow(filter, |entry| println!("{}", entry.render())).await?;
+    }
+    Ok(())
+}
+
 async fn handle_daemon_config_command(command: cli::DaemonConfigCommands, daemon_manager: &DaemonManager) -> Result<()> {
     match command {
         cli::DaemonConfigCommands::Set { key, value } => {
@@ -700,28 +4074,87 @@ async fn handle_daemon_config_command(command: cli::DaemonConfigCommands, daemon
                         config.max_install_attempts = attempts;
                     }
                 }
+                "import_hooks" => {
+                    config.import_hooks = value.parse().unwrap_or(false);
+                }
+                "require_approval" => {
+                    config.require_approval = value.parse().unwrap_or(false);
+                }
+                "notifications" => {
+                    notification::Notifier::named(&value)?;
+                    config.notifications = (value != "auto").then(|| value.clone());
+                }
+                "docker" => {
+                    config.docker = value.parse().unwrap_or(false);
+                }
+                "webhook_url" => {
+                    // An empty URL turns the webhook off
+                    if !value.is_empty() {
+                        webhook::validate_url(&value)?;
+                    }
+                    config.webhook_url = (!value.is_empty()).then(|| value.clone());
+                }
+                "whitelist_modules" | "blacklist_modules" => {
+                    // Comma-separated names, globs or regexes
+                    let patterns: Vec<String> = value.split(',')
+                        .map(str::trim)
+                        .filter(|pattern| !pattern.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    for pattern in &patterns {
+                        module_policy::ModulePattern::parse(pattern)?;
+                    }
+                    if key == "whitelist_modules" {
+                        config.whitelist_modules = patterns;
+                    } else {
+                        config.blacklist_modules = patterns;
+                    }
+                }
+                key if key.starts_with("policy.") => {
+                    // policy.<pattern> install|notify|ignore, or `default` to drop the rule
+                    let pattern = &key["policy.".len()..];
+                    module_policy::ModulePattern::parse(pattern)?;
+                    config.module_policies.retain(|rule| rule.pattern != pattern);
+                    if value != "default" {
+                        config.module_policies.push(module_policy::PolicyRule {
+                            pattern: pattern.to_string(),
+                            policy: value.parse()?,
+                        });
+                    }
+                }
                 _ => {
-                    println!("{}", red(format!("Unknown configuration key: {}", key)));
+                    logger::error(format!("Unknown configuration key: {}", key));
                     return Ok(());
                 }
             }
             
             daemon_manager.save_daemon_config(&config).await?;
-            println!("{}", green(format!("✓ Set {} = {}", key, value)));
+            logger::success(format!("✓ Set {} = {}", key, value));
         }
         cli::DaemonConfigCommands::Show => {
             let config = daemon_manager.load_daemon_config().await?;
-            println!("{}", blue("Daemon Configuration:"));
+            logger::info(blue("Daemon Configuration:"));
             println!("  Auto-install: {}", config.auto_install);
             println!("  Check interval: {}s", config.check_interval.as_secs());
             println!("  Max install attempts: {}", config.max_install_attempts);
+            println!("  Import hooks: {}", config.import_hooks);
+            println!("  Require approval: {}", config.require_approval);
+            println!("  Notifications: {}", config.notifications.as_deref().unwrap_or("auto"));
+            println!("  Webhook: {}", config.webhook_url.as_deref().unwrap_or("none"));
+            println!("  Docker containers: {}", config.docker);
             println!("  Whitelist modules: {:?}", config.whitelist_modules);
             println!("  Blacklist modules: {:?}", config.blacklist_modules);
+            if !config.module_policies.is_empty() {
+                println!("  Module policies:");
+                for rule in &config.module_policies {
+                    println!("    {} = {}", rule.pattern, rule.policy);
+                }
+            }
         }
         cli::DaemonConfigCommands::Reset => {
             let default_config = DaemonConfig::default();
             daemon_manager.save_daemon_config(&default_config).await?;
-            println!("{}", green("✓ Configuration reset to defaults"));
+            logger::success("✓ Configuration reset to defaults");
         }
     }
     
@@ -737,13 +4170,13 @@ async fn handle_nest_command(command: cli::NestCommands, _config: &SnakepitConfi
     
     match command {
         cli::NestCommands::Init => {
-            println!("{}", blue("Initializing Quantum Nest..."));
+            logger::info(blue("Initializing Quantum Nest..."));
             if nest_root.exists() {
-                println!("{}", yellow("Nest already exists."));
+                logger::warn("Nest already exists.");
                 return Ok(());
             }
             std::fs::create_dir_all(&nest_root)?;
-            println!("{}", green(format!("✓ Nest initialized at {}", nest_root.display())));
+            logger::success(format!("✓ Nest initialized at {}", nest_root.display()));
             
             // Initialize git repo if not exists
             if !current_dir.join(".git").exists() {
@@ -751,14 +4184,14 @@ async fn handle_nest_command(command: cli::NestCommands, _config: &SnakepitConfi
                     .arg("init")
                     .current_dir(&current_dir)
                     .output()?;
-                println!("{}", green("✓ Git repository initialized"));
+                logger::success("✓ Git repository initialized");
             }
         }
         cli::NestCommands::Status => {
             let mut nest = QuantumNest::new(nest_root.clone(), "origin".to_string());
             nest.load_state().await?;
             
-            println!("{}", blue("Quantum Nest Status"));
+            logger::info(blue("Quantum Nest Status"));
             println!("  Root: {}", nest_root.display());
             println!("  Total Shells: {}", nest.shells.len());
             
@@ -787,9 +4220,9 @@ async fn handle_nest_command(command: cli::NestCommands, _config: &SnakepitConfi
             let evaporated = nest.vacuum().await?;
             
             if evaporated.is_empty() {
-                println!("{}", yellow("No idle eggs found to evaporate."));
+                logger::warn("No idle eggs found to evaporate.");
             } else {
-                println!("{}", green(format!("✓ Evaporated {} eggs to ether:", evaporated.len())));
+                logger::success(format!("✓ Evaporated {} eggs to ether:", evaporated.len()));
                 for egg in evaporated {
                     println!("  💨 {}", egg);
                 }
@@ -801,14 +4234,14 @@ async fn handle_nest_command(command: cli::NestCommands, _config: &SnakepitConfi
             nest.load_state().await?;
             nest.checkpoint().await?;
             nest.save_state().await?;
-            println!("{}", green("✓ All manifested eggs committed to ether (git)"));
+            logger::success("✓ All manifested eggs committed to ether (git)");
         }
         cli::NestCommands::Observe { name } => {
             let mut nest = QuantumNest::new(nest_root.clone(), "origin".to_string());
             nest.load_state().await?;
             let path = nest.observe(&name).await?;
             nest.save_state().await?;
-            println!("{}", green(format!("👁️  Observed egg '{}' at {}", name, path.display())));
+            logger::success(format!("👁️  Observed egg '{}' at {}", name, path.display()));
         }
     }
     Ok(())
@@ -838,7 +4271,7 @@ async fn handle_egg_command(command: cli::EggCommands, _config: &SnakepitConfig)
             
             let species_enum = snakegg::Species::from_str(&species)?;
             
-            println!("{}", blue(format!("Laying new {} egg: {}...", r#type, name)));
+            logger::info(blue(format!("Laying new {} egg: {}...", r#type, name)));
             
             let dna = DNA {
                 identity: Identity {
@@ -862,7 +4295,7 @@ async fn handle_egg_command(command: cli::EggCommands, _config: &SnakepitConfig)
             }
             
             nest_lock.lay_egg(dna, "default").await?;
-            println!("{}", green(format!("✓ Egg '{}' created in nest", name)));
+            logger::success(format!("✓ Egg '{}' created in nest", name));
         }
         cli::EggCommands::Evolve { name, watch } => {
             let mut mother = Mother::new(charmer.clone(), nest.clone());
@@ -876,7 +4309,7 @@ async fn handle_egg_command(command: cli::EggCommands, _config: &SnakepitConfig)
             // We need to load the DNA to create the Embryo
             let dna_path = organic_path.join(format!("{}.dna", name));
             if !dna_path.exists() {
-                println!("{}", red(format!("Egg '{}' not found in default clutch", name)));
+                logger::error(format!("Egg '{}' not found in default clutch", name));
                 return Ok(());
             }
             
@@ -886,15 +4319,15 @@ async fn handle_egg_command(command: cli::EggCommands, _config: &SnakepitConfig)
             let mut embryo = snakegg::Embryo::new(dna, organic_path, EggType::Organic);
             
             if watch {
-                println!("{}", blue(format!("Watching egg '{}' for evolution...", name)));
+                logger::info(blue(format!("Watching egg '{}' for evolution...", name)));
                 loop {
                     mother.evolve_code(&mut embryo).await?;
                     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
                 }
             } else {
-                println!("{}", blue(format!("Evolving egg '{}'...", name)));
+                logger::info(blue(format!("Evolving egg '{}'...", name)));
                 mother.evolve_code(&mut embryo).await?;
-                println!("{}", green("✓ Evolution cycle complete"));
+                logger::success("✓ Evolution cycle complete");
             }
         }
         cli::EggCommands::Status { name } => {
@@ -905,14 +4338,14 @@ async fn handle_egg_command(command: cli::EggCommands, _config: &SnakepitConfig)
             let dna_path = organic_path.join(format!("{}.dna", name));
             
             if !dna_path.exists() {
-                println!("{}", red(format!("Egg '{}' not found", name)));
+                logger::error(format!("Egg '{}' not found", name));
                 return Ok(());
             }
             
             let dna = DNA::load(&dna_path).await?;
             let embryo = snakegg::Embryo::new(dna, organic_path, EggType::Organic);
             
-            println!("{}", blue(format!("Egg Status: {}", name)));
+            logger::info(blue(format!("Egg Status: {}", name)));
             println!("  Stage: {:?}", embryo.current_stage.milestone);
             println!("  Age: {} cycles", embryo.gestation_log.len());
             println!("  Health: {:.2}", embryo.fitness_score);
@@ -924,11 +4357,11 @@ async fn handle_egg_command(command: cli::EggCommands, _config: &SnakepitConfig)
         cli::EggCommands::List => {
             let nest_lock = nest.lock().await;
             if !nest_root.exists() {
-                println!("{}", yellow("No nest found. Run 'snakepit nest init' first."));
+                logger::warn("No nest found. Run 'snakepit nest init' first.");
                 return Ok(());
             }
             
-            println!("{}", blue("Eggs in nest (default clutch):"));
+            logger::info(blue("Eggs in nest (default clutch):"));
             let eggs = nest_lock.list_eggs("default").await?;
             for egg in eggs {
                 println!("  🥚 {}", egg);
@@ -945,19 +4378,19 @@ async fn handle_clutch_command(command: cli::ClutchCommands, _config: &SnakepitC
         cli::ClutchCommands::Create { name } => {
             let _clutch = Clutch::new(name.clone());
             // Save clutch state (simplified)
-            println!("{}", green(format!("✓ Clutch '{}' created", name)));
+            logger::success(format!("✓ Clutch '{}' created", name));
         }
         cli::ClutchCommands::Add { name, eggs } => {
-            println!("{}", green(format!("✓ Added {} eggs to clutch '{}'", eggs.len(), name)));
+            logger::success(format!("✓ Added {} eggs to clutch '{}'", eggs.len(), name));
         }
         cli::ClutchCommands::ThermalCycle { name } => {
-            println!("{}", blue(format!("Running thermal cycle for clutch '{}'...", name)));
+            logger::info(blue(format!("Running thermal cycle for clutch '{}'...", name)));
             // Simulate thermal cycle
-            println!("{}", green("✓ Heat shared between 3 eggs"));
-            println!("  🔥 api_handler (85°C) → 🌡️ auth_service (42°C)");
+            logger::success("✓ Heat shared between 3 eggs");
+            logger::info("  🔥 api_handler (85°C) → 🌡️ auth_service (42°C)");
         }
         cli::ClutchCommands::Status { name } => {
-            println!("{}", blue(format!("Clutch Status: {}", name)));
+            logger::info(blue(format!("Clutch Status: {}", name)));
             println!("  Temperature: 65°C");
             println!("  Eggs: 0");
         }
@@ -968,14 +4401,14 @@ async fn handle_clutch_command(command: cli::ClutchCommands, _config: &SnakepitC
 async fn handle_protein_command(command: cli::ProteinCommands, _config: &SnakepitConfig) -> Result<()> {
     match command {
         cli::ProteinCommands::List => {
-            println!("{}", blue("Available Proteins:"));
+            logger::info(blue("Available Proteins:"));
             println!("  🧬 auth_flow_v1");
             println!("  🧬 db_connection_pool");
             println!("  🧬 error_handler_retry");
         }
         cli::ProteinCommands::Extract { egg } => {
-            println!("{}", blue(format!("Extracting proteins from egg '{}'...", egg)));
-            println!("{}", green("✓ Extracted 2 proteins"));
+            logger::info(blue(format!("Extracting proteins from egg '{}'...", egg)));
+            logger::success("✓ Extracted 2 proteins");
         }
     }
     Ok(())
@@ -0,0 +1,807 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+lazy_static::lazy_static! {
+    static ref KEY_ARRAY: regex::Regex = regex::Regex::new(r#"^\s*("[^"]*"|'[^']*'|[A-Za-z0-9_\-]+)\s*=\s*\["#).unwrap();
+}
+
+/// Directories never searched for workspace manifests
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "__pycache__", "site-packages", "build", "dist"];
+
+/// PEP 503 name normalization: lowercase, runs of `-_.` collapse to `-`
+pub fn canonical_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut pending_separator = false;
+    for c in name.trim().chars() {
+        if c == '-' || c == '_' || c == '.' {
+            pending_separator = true;
+        } else {
+            if pending_separator && !out.is_empty() {
+                out.push('-');
+            }
+            pending_separator = false;
+            out.push(c.to_ascii_lowercase());
+        }
+    }
+    out
+}
+
+/// A PEP 508 requirement split into the parts fmt-manifest normalizes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    pub name: String,
+    pub extras: Vec<String>,
+    /// Version specifier (`>=1.0,<2`) or direct reference (`@ https://...`)
+    pub version: Option<String>,
+    pub marker: Option<String>,
+}
+
+impl Requirement {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        let (head, marker) = match spec.split_once(';') {
+            Some((head, marker)) => (head.trim(), Some(marker.split_whitespace().collect::<Vec<_>>().join(" "))),
+            None => (spec, None),
+        };
+
+        let name_end = head
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+            .unwrap_or(head.len());
+        let name = canonical_name(&head[..name_end]);
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut rest = head[name_end..].trim_start();
+        let mut extras = Vec::new();
+        if rest.starts_with('[') {
+            let close = rest.find(']')?;
+            extras = rest[1..close].split(',').map(canonical_name).filter(|e| !e.is_empty()).collect();
+            extras.sort();
+            extras.dedup();
+            rest = rest[close + 1..].trim_start();
+        }
+
+        let version = if let Some(url) = rest.strip_prefix('@') {
+            let url = url.trim();
+            if url.is_empty() {
+                return None;
+            }
+            Some(format!("@ {}", url))
+        } else if rest.is_empty() {
+            None
+        } else {
+            // Legacy `name (>=1.0)` form is accepted and unwrapped
+            let rest = rest.trim_start_matches('(').trim_end_matches(')');
+            let clauses: Vec<String> = rest
+                .split(',')
+                .map(|c| c.split_whitespace().collect::<String>())
+                .filter(|c| !c.is_empty())
+                .collect();
+            let is_operator = |c: char| "<>=!~".contains(c);
+            if clauses.is_empty() || clauses.iter().any(|c| !c.starts_with(is_operator)) {
+                return None;
+            }
+            Some(clauses.join(","))
+        };
+
+        Some(Self {
+            name,
+            extras,
+            version,
+            marker: marker.filter(|m| !m.is_empty()),
+        })
+    }
+
+    fn is_direct_reference(&self) -> bool {
+        self.version.as_deref().is_some_and(|v| v.starts_with('@'))
+    }
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.extras.is_empty() {
+            write!(f, "[{}]", self.extras.join(","))?;
+        }
+        match &self.version {
+            Some(reference) if self.is_direct_reference() => write!(f, " {}", reference)?,
+            Some(specifier) => write!(f, "{}", specifier)?,
+            None => {}
+        }
+        if let Some(marker) = &self.marker {
+            // A direct reference needs whitespace before `;` or it is read as part of the URL
+            let separator = if self.is_direct_reference() { " ; " } else { "; " };
+            write!(f, "{}{}", separator, marker)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of formatting one manifest file
+#[derive(Debug)]
+pub struct FormattedManifest {
+    pub path: PathBuf,
+    pub original: String,
+    pub formatted: String,
+    /// Problems that need a human: unparseable entries, conflicting pins, broken references
+    pub issues: Vec<String>,
+}
+
+impl FormattedManifest {
+    pub fn changed(&self) -> bool {
+        self.original != self.formatted
+    }
+}
+
+/// Canonicalizes `pyproject.toml` and `requirements*.txt` files across a workspace.
+///
+/// Only dependency arrays and requirement lines are rewritten; everything else
+/// in a file (tables, comments, options, whitespace) is copied through as-is.
+pub struct ManifestFormatter {
+    root: PathBuf,
+}
+
+impl ManifestFormatter {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Every manifest under the workspace root, skipping hidden dirs and virtualenvs
+    pub fn discover(&self) -> Result<Vec<PathBuf>> {
+        let mut manifests = Vec::new();
+        let mut pending = vec![self.root.clone()];
+
+        while let Some(dir) = pending.pop() {
+            if dir.join("pyvenv.cfg").exists() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+                if path.is_dir() {
+                    if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                        pending.push(path);
+                    }
+                } else if Self::is_manifest(&path) {
+                    manifests.push(path);
+                }
+            }
+        }
+
+        manifests.sort();
+        Ok(manifests)
+    }
+
+    pub fn is_manifest(path: &Path) -> bool {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        name == "pyproject.toml" || (name.starts_with("requirements") && name.ends_with(".txt"))
+    }
+
+    pub fn format_path(path: &Path) -> Result<FormattedManifest> {
+        let original = std::fs::read_to_string(path)?;
+        let mut issues = Vec::new();
+
+        let formatted = if path.file_name().and_then(|n| n.to_str()) == Some("pyproject.toml") {
+            format_pyproject(&original, &mut issues)?
+        } else {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            format_requirements_txt(&original, dir, &mut issues)
+        };
+
+        Ok(FormattedManifest {
+            path: path.to_path_buf(),
+            original,
+            formatted,
+            issues,
+        })
+    }
+}
+
+struct RequirementLine {
+    requirement: Requirement,
+    text: String,
+}
+
+/// Normalize a requirements file: each run of requirement lines between
+/// comments, blank lines and options is sorted and deduplicated in place
+pub fn format_requirements_txt(content: &str, dir: &Path, issues: &mut Vec<String>) -> String {
+    let mut output: Vec<String> = Vec::new();
+    let mut block: Vec<RequirementLine> = Vec::new();
+    let mut pins: HashMap<(String, Option<String>), String> = HashMap::new();
+
+    // Group physical lines into logical entries so `\` continuations move together
+    let mut entries: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for line in content.lines() {
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+        if !line.trim_end().ends_with('\\') {
+            entries.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        entries.push(current);
+    }
+
+    for entry in entries {
+        let trimmed = entry.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('-') {
+            flush_requirement_block(&mut block, &mut output);
+            if trimmed.starts_with('-') {
+                check_requirement_option(trimmed, dir, issues);
+            }
+            output.push(entry);
+            continue;
+        }
+
+        let (first_line, continuation) = match entry.split_once('\n') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (entry.as_str(), None),
+        };
+
+        // The requirement ends where options (` --hash=...`), a comment or a continuation begins
+        let spec_end = [" -", "\t-", " #", "\t#", " \\", "\\"]
+            .iter()
+            .filter_map(|pattern| first_line.find(pattern))
+            .min()
+            .unwrap_or(first_line.len());
+        let spec = first_line[..spec_end].trim_end();
+        let spec_end = spec.len();
+
+        let requirement = match Requirement::parse(spec) {
+            Some(requirement) => requirement,
+            None => {
+                issues.push(format!("could not parse requirement '{}'", spec.trim()));
+                flush_requirement_block(&mut block, &mut output);
+                output.push(entry);
+                continue;
+            }
+        };
+
+        let mut text = format!("{}{}", requirement, &first_line[spec_end..]);
+        if let Some(rest) = continuation {
+            text.push('\n');
+            text.push_str(rest);
+        }
+
+        record_pin(&requirement, &mut pins, issues);
+        block.push(RequirementLine { requirement, text });
+    }
+    flush_requirement_block(&mut block, &mut output);
+
+    let mut formatted = output.join("\n");
+    if content.ends_with('\n') {
+        formatted.push('\n');
+    }
+    formatted
+}
+
+fn flush_requirement_block(block: &mut Vec<RequirementLine>, output: &mut Vec<String>) {
+    block.sort_by(|a, b| a.requirement.name.cmp(&b.requirement.name).then(a.text.cmp(&b.text)));
+    block.dedup_by(|later, earlier| later.text == earlier.text);
+    output.extend(block.drain(..).map(|line| line.text));
+}
+
+fn check_requirement_option(option: &str, dir: &Path, issues: &mut Vec<String>) {
+    let mut parts = option.split_whitespace();
+    let flag = parts.next().unwrap_or_default();
+    let (flag, inline_value) = match flag.split_once('=') {
+        Some((flag, value)) => (flag, Some(value)),
+        None => (flag, None),
+    };
+
+    if matches!(flag, "-r" | "--requirement" | "-c" | "--constraint") {
+        if let Some(target) = inline_value.or_else(|| parts.next()) {
+            if !target.contains("://") && !dir.join(target).exists() {
+                issues.push(format!("'{}' references missing file {}", option, target));
+            }
+        }
+    }
+}
+
+/// Flag the same package pinned two different ways under the same marker
+fn record_pin(requirement: &Requirement, pins: &mut HashMap<(String, Option<String>), String>, issues: &mut Vec<String>) {
+    let key = (requirement.name.clone(), requirement.marker.clone());
+    let version = requirement.version.clone().unwrap_or_default();
+    match pins.get(&key) {
+        Some(existing) if existing != &version => {
+            issues.push(format!(
+                "conflicting entries for {}: '{}' vs '{}'",
+                requirement.name,
+                if existing.is_empty() { "*" } else { existing },
+                if version.is_empty() { "*" } else { &version },
+            ));
+        }
+        Some(_) => {}
+        None => {
+            pins.insert(key, version);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ArrayValue {
+    Str { text: String, quote: char },
+    /// Inline tables such as `{ include-group = "test" }`, kept verbatim
+    Raw(String),
+}
+
+#[derive(Debug, Clone)]
+struct ArrayItem {
+    value: ArrayValue,
+    leading_comments: Vec<String>,
+    trailing_comment: Option<String>,
+}
+
+/// Groups and extras declared in a pyproject, used to validate references
+struct DeclaredGroups {
+    project_name: Option<String>,
+    extras: HashSet<String>,
+    groups: HashSet<String>,
+}
+
+/// Normalize the dependency arrays of a pyproject.toml:
+/// `[project] dependencies`, `[project.optional-dependencies]` and `[dependency-groups]`
+pub fn format_pyproject(content: &str, issues: &mut Vec<String>) -> Result<String> {
+    let document: toml::Value = toml::from_str(content)?;
+    let declared = DeclaredGroups {
+        project_name: document.get("project").and_then(|p| p.get("name")).and_then(|n| n.as_str()).map(canonical_name),
+        extras: table_keys(document.get("project").and_then(|p| p.get("optional-dependencies"))),
+        groups: table_keys(document.get("dependency-groups")),
+    };
+
+    let mut output = String::with_capacity(content.len());
+    let mut table = String::new();
+    let mut pos = 0;
+
+    while pos < content.len() {
+        let line_end = content[pos..].find('\n').map(|i| pos + i + 1).unwrap_or(content.len());
+        let line = &content[pos..line_end];
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('[') {
+            table = parse_table_header(trimmed);
+            output.push_str(line);
+            pos = line_end;
+            continue;
+        }
+
+        if let Some(captures) = KEY_ARRAY.captures(line) {
+            let open = pos + captures.get(0).unwrap().end() - 1;
+            if let Some(close) = find_closing_bracket(content, open) {
+                let key = captures[1].trim_matches(|c| c == '"' || c == '\'').to_string();
+                output.push_str(&content[pos..open]);
+
+                if let Some(context) = dependency_array_context(&table, &key) {
+                    let array = &content[open..=close];
+                    match format_dependency_array(array, &context, &declared, issues) {
+                        Some(formatted) => output.push_str(&formatted),
+                        None => {
+                            issues.push(format!("{}: array is too complex to normalize, left unchanged", context));
+                            output.push_str(array);
+                        }
+                    }
+                } else {
+                    output.push_str(&content[open..=close]);
+                }
+
+                pos = close + 1;
+                continue;
+            }
+        }
+
+        // Copy multi-line strings through untouched so their contents aren't read as headers
+        if let Some(delimiter) = ["\"\"\"", "'''"].iter().find(|d| line.matches(**d).count() == 1) {
+            let after = pos + line.find(delimiter).unwrap() + delimiter.len();
+            let end = content[after..].find(delimiter).map(|i| after + i + delimiter.len()).unwrap_or(content.len());
+            let end = content[end..].find('\n').map(|i| end + i + 1).unwrap_or(content.len());
+            output.push_str(&content[pos..end]);
+            pos = end;
+            continue;
+        }
+
+        output.push_str(line);
+        pos = line_end;
+    }
+
+    Ok(output)
+}
+
+fn table_keys(table: Option<&toml::Value>) -> HashSet<String> {
+    table
+        .and_then(|t| t.as_table())
+        .map(|t| t.keys().map(|k| canonical_name(k)).collect())
+        .unwrap_or_default()
+}
+
+fn parse_table_header(line: &str) -> String {
+    let inner = line.trim_start_matches('[');
+    let inner = inner.split(']').next().unwrap_or_default();
+    inner.chars().filter(|c| !c.is_whitespace() && *c != '"' && *c != '\'').collect()
+}
+
+fn dependency_array_context(table: &str, key: &str) -> Option<String> {
+    match table {
+        "project" if key == "dependencies" => Some("[project] dependencies".to_string()),
+        "project.optional-dependencies" => Some(format!("[project.optional-dependencies] {}", key)),
+        "dependency-groups" => Some(format!("[dependency-groups] {}", key)),
+        _ => None,
+    }
+}
+
+/// Index of the `]` matching the `[` at `open`, skipping strings and comments
+fn find_closing_bracket(content: &str, open: usize) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut depth = 0;
+    let mut i = open;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            b'"' | b'\'' => i = skip_string(bytes, i)?,
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Index of the closing quote of the string starting at `start`
+fn skip_string(bytes: &[u8], start: usize) -> Option<usize> {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if quote == b'"' && bytes[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if bytes[i] == quote {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Split an array literal into items with their attached comments.
+/// Returns `None` for anything beyond strings, inline tables and comments.
+fn parse_array(array: &str) -> Option<(Vec<ArrayItem>, Vec<String>)> {
+    let bytes = array.as_bytes();
+    let body_end = array.len() - 1;
+    let mut items: Vec<ArrayItem> = Vec::new();
+    let mut pending_comments = Vec::new();
+    let mut newline_since_item = true;
+    let mut i = 1;
+
+    while i < body_end {
+        match bytes[i] {
+            b'\n' => newline_since_item = true,
+            b' ' | b'\t' | b'\r' | b',' => {}
+            b'#' => {
+                let end = array[i..body_end].find('\n').map(|e| i + e).unwrap_or(body_end);
+                let comment = array[i..end].trim_end().to_string();
+                match items.last_mut() {
+                    Some(last) if !newline_since_item && pending_comments.is_empty() => last.trailing_comment = Some(comment),
+                    _ => pending_comments.push(comment),
+                }
+                i = end;
+                continue;
+            }
+            b'"' | b'\'' => {
+                if array[i..].starts_with("\"\"\"") || array[i..].starts_with("'''") {
+                    return None;
+                }
+                let end = skip_string(bytes, i)?;
+                let raw = &array[i + 1..end];
+                let text = if bytes[i] == b'"' {
+                    // Only the escapes a requirement can plausibly contain are understood
+                    if raw.replace("\\\\", "").replace("\\\"", "").contains('\\') {
+                        return None;
+                    }
+                    raw.replace("\\\"", "\"").replace("\\\\", "\\")
+                } else {
+                    raw.to_string()
+                };
+                items.push(ArrayItem {
+                    value: ArrayValue::Str { text, quote: bytes[i] as char },
+                    leading_comments: std::mem::take(&mut pending_comments),
+                    trailing_comment: None,
+                });
+                newline_since_item = false;
+                i = end;
+            }
+            b'{' => {
+                let mut depth = 0;
+                let mut end = i;
+                while end < body_end {
+                    match bytes[end] {
+                        b'{' => depth += 1,
+                        b'}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        b'"' | b'\'' => end = skip_string(bytes, end)?,
+                        _ => {}
+                    }
+                    end += 1;
+                }
+                if end >= body_end {
+                    return None;
+                }
+                items.push(ArrayItem {
+                    value: ArrayValue::Raw(array[i..=end].to_string()),
+                    leading_comments: std::mem::take(&mut pending_comments),
+                    trailing_comment: None,
+                });
+                newline_since_item = false;
+                i = end;
+            }
+            _ => return None,
+        }
+        i += 1;
+    }
+
+    Some((items, pending_comments))
+}
+
+fn format_dependency_array(array: &str, context: &str, declared: &DeclaredGroups, issues: &mut Vec<String>) -> Option<String> {
+    let (items, tail_comments) = parse_array(array)?;
+    if items.is_empty() {
+        return Some(array.to_string());
+    }
+
+    let mut includes = Vec::new();
+    let mut requirements: Vec<(Requirement, ArrayItem)> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut pins = HashMap::new();
+
+    for mut item in items {
+        let text = match &item.value {
+            ArrayValue::Raw(raw) => {
+                check_include_group(raw, context, declared, issues);
+                includes.push(item);
+                continue;
+            }
+            ArrayValue::Str { text, .. } => text.clone(),
+        };
+
+        let requirement = match Requirement::parse(&text) {
+            Some(requirement) => requirement,
+            None => {
+                issues.push(format!("{}: could not parse requirement '{}'", context, text));
+                return None;
+            }
+        };
+
+        if declared.project_name.as_deref() == Some(requirement.name.as_str()) {
+            for extra in &requirement.extras {
+                if !declared.extras.contains(extra) {
+                    issues.push(format!("{}: '{}' references undefined extra '{}'", context, text, extra));
+                }
+            }
+        }
+
+        let normalized = requirement.to_string();
+        if let Some(&index) = seen.get(&normalized) {
+            // Keep the first occurrence, but don't lose comments attached to the duplicate
+            let (_, kept) = &mut requirements[index];
+            kept.leading_comments.extend(item.leading_comments);
+            if kept.trailing_comment.is_none() {
+                kept.trailing_comment = item.trailing_comment;
+            }
+            continue;
+        }
+
+        record_pin(&requirement, &mut pins, issues);
+        if let ArrayValue::Str { quote, .. } = item.value {
+            item.value = ArrayValue::Str { text: normalized.clone(), quote };
+        }
+        seen.insert(normalized, requirements.len());
+        requirements.push((requirement, item));
+    }
+
+    requirements.sort_by(|(a, a_item), (b, b_item)| {
+        a.name.cmp(&b.name).then_with(|| render_value(&a_item.value).cmp(&render_value(&b_item.value)))
+    });
+
+    let ordered: Vec<ArrayItem> = includes
+        .into_iter()
+        .chain(requirements.into_iter().map(|(_, item)| item))
+        .collect();
+
+    if !array.contains('\n') {
+        let values: Vec<String> = ordered.iter().map(|item| render_value(&item.value)).collect();
+        return Some(format!("[{}]", values.join(", ")));
+    }
+
+    // Multi-line arrays keep their indentation: one item per line, trailing commas
+    let closing_indent = array[..array.len() - 1]
+        .rsplit('\n')
+        .next()
+        .filter(|tail| tail.chars().all(char::is_whitespace))
+        .unwrap_or("")
+        .to_string();
+    let item_indent = array
+        .lines()
+        .skip(1)
+        .find(|line| !line.trim().is_empty() && !line.trim().starts_with(']'))
+        .map(|line| line[..line.len() - line.trim_start().len()].to_string())
+        .unwrap_or_else(|| format!("{}    ", closing_indent));
+
+    let mut formatted = String::from("[\n");
+    for item in &ordered {
+        for comment in &item.leading_comments {
+            formatted.push_str(&format!("{}{}\n", item_indent, comment));
+        }
+        formatted.push_str(&format!("{}{},", item_indent, render_value(&item.value)));
+        if let Some(comment) = &item.trailing_comment {
+            formatted.push_str(&format!("  {}", comment));
+        }
+        formatted.push('\n');
+    }
+    for comment in &tail_comments {
+        formatted.push_str(&format!("{}{}\n", item_indent, comment));
+    }
+    formatted.push_str(&closing_indent);
+    formatted.push(']');
+
+    Some(formatted)
+}
+
+fn check_include_group(raw: &str, context: &str, declared: &DeclaredGroups, issues: &mut Vec<String>) {
+    let table: toml::Value = match toml::from_str(&format!("entry = {}", raw)) {
+        Ok(value) => value,
+        Err(_) => {
+            issues.push(format!("{}: invalid entry {}", context, raw));
+            return;
+        }
+    };
+
+    if let Some(group) = table.get("entry").and_then(|e| e.get("include-group")).and_then(|g| g.as_str()) {
+        if !declared.groups.contains(&canonical_name(group)) {
+            issues.push(format!("{}: includes undefined group '{}'", context, group));
+        }
+    }
+}
+
+fn render_value(value: &ArrayValue) -> String {
+    match value {
+        ArrayValue::Raw(raw) => raw.clone(),
+        ArrayValue::Str { text, quote: '\'' } if !text.contains('\'') => format!("'{}'", text),
+        ArrayValue::Str { text, .. } => format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirement_normalization() {
+        let normalize = |s: &str| Requirement::parse(s).map(|r| r.to_string());
+
+        assert_eq!(normalize("Requests >= 2.0 , < 3"), Some("requests>=2.0,<3".to_string()));
+        assert_eq!(normalize("Flask_SQLAlchemy[Async, asyncio]"), Some("flask-sqlalchemy[async,asyncio]".to_string()));
+        assert_eq!(normalize("zope.interface (>=5.0)"), Some("zope-interface>=5.0".to_string()));
+        assert_eq!(
+            normalize("tomli>=1.1;python_version  <  '3.11'"),
+            Some("tomli>=1.1; python_version < '3.11'".to_string())
+        );
+        assert_eq!(
+            normalize("pkg@ https://example.com/pkg.whl"),
+            Some("pkg @ https://example.com/pkg.whl".to_string())
+        );
+        assert_eq!(normalize("not a requirement"), None);
+    }
+
+    #[test]
+    fn test_requirements_txt_sorts_blocks_and_keeps_layout() {
+        let content = "\
+# web
+Flask==3.0.0
+requests >= 2.0  # http
+flask==3.0.0
+
+-r requirements-base.txt
+Django==4.2 \\
+    --hash=sha256:abc
+";
+        let dir = std::env::temp_dir();
+        let mut issues = Vec::new();
+        let formatted = format_requirements_txt(content, &dir, &mut issues);
+
+        assert_eq!(formatted, "\
+# web
+flask==3.0.0
+requests>=2.0  # http
+
+-r requirements-base.txt
+django==4.2 \\
+    --hash=sha256:abc
+");
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("requirements-base.txt"));
+    }
+
+    #[test]
+    fn test_requirements_txt_reports_conflicts() {
+        let mut issues = Vec::new();
+        format_requirements_txt("requests==2.0\nRequests==2.1\n", Path::new("."), &mut issues);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("conflicting"));
+    }
+
+    #[test]
+    fn test_pyproject_only_touches_dependency_arrays() {
+        let content = r#"[project]
+name = "demo"
+classifiers = ["B", "A"]
+dependencies = [
+  # networking
+  "Requests >= 2.0",
+  "click",  # cli
+  "click",
+]
+
+[project.optional-dependencies]
+test = ['pytest', "Demo[Extra]"]
+
+[dependency-groups]
+lint = ["ruff"]
+dev = [{ include-group = "lint" }, "mypy", { include-group = "docs" }]
+"#;
+        let mut issues = Vec::new();
+        let formatted = format_pyproject(content, &mut issues).unwrap();
+
+        assert_eq!(formatted, r#"[project]
+name = "demo"
+classifiers = ["B", "A"]
+dependencies = [
+  "click",  # cli
+  # networking
+  "requests>=2.0",
+]
+
+[project.optional-dependencies]
+test = ["demo[extra]", 'pytest']
+
+[dependency-groups]
+lint = ["ruff"]
+dev = [{ include-group = "lint" }, { include-group = "docs" }, "mypy"]
+"#);
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().any(|i| i.contains("undefined extra 'extra'")));
+        assert!(issues.iter().any(|i| i.contains("undefined group 'docs'")));
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent() {
+        let content = "[project]\nname = \"x\"\ndependencies = [\n    \"B>=1\",\n    \"a\",\n]\n";
+        let mut issues = Vec::new();
+        let once = format_pyproject(content, &mut issues).unwrap();
+        let twice = format_pyproject(&once, &mut issues).unwrap();
+        assert_eq!(once, twice);
+        assert!(issues.is_empty());
+    }
+}
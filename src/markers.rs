@@ -2,6 +2,9 @@ use anyhow::{Result, anyhow};
 use regex::Regex;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use crate::pep440::{Operator, Specifier, Version};
 
 lazy_static! {
     // Simplified PEP 508 regex pattern
@@ -41,47 +44,263 @@ impl EnvironmentMarker {
         // Full implementation would need a proper expression parser
         
         let marker = &self.raw;
+
+        // Extras gate the whole requirement: `extra == "socks"` only applies when requested
+        if marker.contains("extra") {
+            if let Some(required) = extract_string_requirement(marker, "extra") {
+                let required = required.to_lowercase();
+                if !env.extras.iter().any(|e| e.to_lowercase() == required) {
+                    return false;
+                }
+            }
+        }
         
         // Check for common patterns
         if marker.contains("python_version") {
             if let Some(required) = extract_version_requirement(marker, "python_version") {
-                return compare_versions(&env.python_version, &required.0, &required.1);
+                if !compare_versions(&env.python_version, &required.0, &required.1) {
+                    return false;
+                }
             }
         }
-        
-        if marker.contains("sys_platform") {
-            if let Some(required) = extract_string_requirement(marker, "sys_platform") {
-                return env.sys_platform == required;
+
+        if marker.contains("python_full_version") {
+            if let Some(required) = extract_version_requirement(marker, "python_full_version") {
+                if !compare_versions(&env.python_full_version, &required.0, &required.1) {
+                    return false;
+                }
             }
         }
-        
-        if marker.contains("platform_system") {
-            if let Some(required) = extract_string_requirement(marker, "platform_system") {
-                return env.platform_system == required;
+
+        let strings = [
+            ("sys_platform", &env.sys_platform),
+            ("platform_system", &env.platform_system),
+            ("platform_machine", &env.platform_machine),
+            ("os_name", &env.os_name),
+            ("implementation_name", &env.implementation_name),
+        ];
+        for (key, actual) in strings {
+            if marker.contains(key) {
+                if let Some(required) = extract_string_requirement(marker, key) {
+                    if *actual != required {
+                        return false;
+                    }
+                }
             }
         }
         
         // Default to true if we can't parse
         true
     }
+    /// Whether the marker holds in at least one of `environments` (the host when empty),
+    /// with `extras` requested; a universal lock keeps every edge some target needs
+    pub fn evaluate_any(&self, environments: &[TargetEnvironment], extras: &[String]) -> bool {
+        if environments.is_empty() {
+            return self.evaluate(&TargetEnvironment::host().with_extras(extras));
+        }
+        environments.iter().any(|env| self.evaluate(&env.clone().with_extras(extras)))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct TargetEnvironment {
     pub python_version: String,
+    pub python_full_version: String,
     pub sys_platform: String,
     pub platform_system: String,
     pub platform_machine: String,
+    pub os_name: String,
+    pub implementation_name: String,
+    /// Extras requested for the package whose requirements are being evaluated
+    pub extras: Vec<String>,
 }
 
 impl Default for TargetEnvironment {
     fn default() -> Self {
         Self {
             python_version: "3.11".to_string(),
+            python_full_version: "3.11.0".to_string(),
             sys_platform: std::env::consts::OS.to_string(),
             platform_system: std::env::consts::OS.to_string(),
             platform_machine: std::env::consts::ARCH.to_string(),
+            os_name: if cfg!(windows) { "nt" } else { "posix" }.to_string(),
+            implementation_name: "cpython".to_string(),
+            extras: Vec::new(),
+        }
+    }
+}
+
+/// Marker values requirements are evaluated against in this process, decided once
+static HOST: OnceLock<TargetEnvironment> = OnceLock::new();
+
+/// Prints the interpreter's marker values, one `key=value` per line
+const INTROSPECT_SCRIPT: &str = "import os, platform, sys
+print('python_version=%d.%d' % sys.version_info[:2])
+print('python_full_version=' + platform.python_version())
+print('sys_platform=' + sys.platform)
+print('platform_system=' + platform.system())
+print('platform_machine=' + platform.machine())
+print('os_name=' + os.name)
+print('implementation_name=' + sys.implementation.name)";
+
+/// Platforms a lock can target, as written in `--platform` and `linux-3.12` labels
+pub const PLATFORMS: [&str; 3] = ["linux", "macos", "windows"];
+
+/// This machine's entry in [`PLATFORMS`]
+pub fn host_platform() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "macos",
+        "windows" => "windows",
+        _ => "linux",
+    }
+}
+
+/// Python versions `lock --universal` resolves for
+pub const PYTHON_VERSIONS: [&str; 5] = ["3.9", "3.10", "3.11", "3.12", "3.13"];
+
+impl TargetEnvironment {
+    /// The marker values of a platform/Python combination, e.g. `("macos", "3.12")`
+    pub fn for_target(platform: &str, python_version: &str) -> Result<Self> {
+        let (sys_platform, platform_system, platform_machine) = match platform.to_lowercase().as_str() {
+            "linux" => ("linux", "Linux", "x86_64"),
+            "macos" | "darwin" => ("darwin", "Darwin", "arm64"),
+            "windows" | "win32" => ("win32", "Windows", "AMD64"),
+            other => return Err(anyhow!("Unknown platform '{}' (expected one of: {})", other, PLATFORMS.join(", "))),
+        };
+        let mut parts = python_version.trim().split('.');
+        let (Some(major), Some(minor)) = (parts.next().filter(|p| p.parse::<u32>().is_ok()), parts.next().filter(|p| p.parse::<u32>().is_ok())) else {
+            return Err(anyhow!("Invalid Python version '{}' (expected e.g. 3.12)", python_version));
+        };
+        Ok(Self {
+            python_version: format!("{}.{}", major, minor),
+            python_full_version: format!("{}.{}.0", major, minor),
+            sys_platform: sys_platform.to_string(),
+            platform_system: platform_system.to_string(),
+            platform_machine: platform_machine.to_string(),
+            os_name: if sys_platform == "win32" { "nt" } else { "posix" }.to_string(),
+            implementation_name: "cpython".to_string(),
+            extras: Vec::new(),
+        })
+    }
+
+    /// A Rust-style target triple (`aarch64-unknown-linux-gnu`, `x86_64-pc-windows-msvc`)
+    /// or a lock label (`macos-3.12`); a triple takes its Python version from `python_version`
+    pub fn parse_triple(triple: &str, python_version: &str) -> Result<Self> {
+        let parts: Vec<&str> = triple.trim().split('-').collect();
+        if parts.len() == 2 {
+            return Self::parse_target(triple);
+        }
+        let platform = if parts.contains(&"linux") {
+            "linux"
+        } else if parts.contains(&"darwin") || parts.contains(&"apple") {
+            "macos"
+        } else if parts.contains(&"windows") {
+            "windows"
+        } else {
+            return Err(anyhow!("Unknown target '{}' (expected e.g. x86_64-unknown-linux-gnu or linux-3.12)", triple));
+        };
+        let mut env = Self::for_target(platform, python_version)?;
+        env.platform_machine = match (platform, parts[0]) {
+            ("macos", "aarch64") => "arm64".to_string(),
+            ("windows", "x86_64") => "AMD64".to_string(),
+            ("windows", "aarch64") => "ARM64".to_string(),
+            (_, arch) => arch.to_string(),
+        };
+        Ok(env)
+    }
+
+    /// Marker values for the interpreter packages go to: the one set with [`Self::set_host`],
+    /// else the active virtualenv's (or `python3`), asked once; built-in guesses if that fails
+    pub fn host() -> Self {
+        HOST.get_or_init(|| Self::detect().unwrap_or_default()).clone()
+    }
+
+    /// Evaluate markers for `target` instead of asking the interpreter; the first call wins
+    pub fn set_host(target: Self) {
+        let _ = HOST.set(target);
+    }
+
+    /// Ask the active virtualenv's interpreter, or `python3` outside one
+    pub fn detect() -> Result<Self> {
+        let python = match std::env::var_os("VIRTUAL_ENV") {
+            Some(venv) if cfg!(windows) => Path::new(&venv).join("Scripts").join("python.exe"),
+            Some(venv) => Path::new(&venv).join("bin").join("python"),
+            None => Path::new(if cfg!(windows) { "python" } else { "python3" }).to_path_buf(),
+        };
+        Self::from_interpreter(&python)
+    }
+
+    pub fn from_interpreter(python: &Path) -> Result<Self> {
+        let output = std::process::Command::new(python)
+            .args(["-c", INTROSPECT_SCRIPT])
+            .output()
+            .map_err(|e| anyhow!("Failed to run {}: {}", python.display(), e))?;
+        if !output.status.success() {
+            return Err(anyhow!("{} failed: {}", python.display(), String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Self::parse_report(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Read the `key=value` lines printed by [`INTROSPECT_SCRIPT`]
+    fn parse_report(report: &str) -> Result<Self> {
+        let values: HashMap<&str, &str> = report.lines()
+            .filter_map(|line| line.trim().split_once('='))
+            .collect();
+        let value = |key: &str| values.get(key)
+            .map(|v| v.to_string())
+            .ok_or_else(|| anyhow!("Interpreter didn't report {}", key));
+        Ok(Self {
+            python_version: value("python_version")?,
+            python_full_version: value("python_full_version")?,
+            sys_platform: value("sys_platform")?,
+            platform_system: value("platform_system")?,
+            platform_machine: value("platform_machine")?,
+            os_name: value("os_name")?,
+            implementation_name: value("implementation_name")?,
+            extras: Vec::new(),
+        })
+    }
+
+    /// Parse a label such as `linux-3.12`
+    pub fn parse_target(label: &str) -> Result<Self> {
+        let (platform, python_version) = label.trim().rsplit_once('-')
+            .ok_or_else(|| anyhow!("Invalid target '{}' (expected e.g. linux-3.12)", label))?;
+        Self::for_target(platform, python_version.trim_start_matches("py"))
+    }
+
+    /// Every combination of `platforms` and `python_versions`
+    pub fn matrix<P: AsRef<str>, V: AsRef<str>>(platforms: &[P], python_versions: &[V]) -> Result<Vec<Self>> {
+        let mut environments = Vec::new();
+        for platform in platforms {
+            for python_version in python_versions {
+                environments.push(Self::for_target(platform.as_ref(), python_version.as_ref())?);
+            }
         }
+        Ok(environments)
+    }
+
+    /// The host platform with the given interpreter version
+    pub fn current(python_version: &str) -> Result<Self> {
+        Self::for_target(host_platform(), python_version)
+    }
+
+    /// Short name used in lockfiles and messages, e.g. `macos-3.12`
+    pub fn label(&self) -> String {
+        let platform = match self.sys_platform.as_str() {
+            "darwin" | "macos" => "macos",
+            "win32" | "windows" => "windows",
+            other => other,
+        };
+        format!("{}-{}", platform, self.python_version)
+    }
+
+    pub fn with_extras<I, S>(mut self, extras: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.extras = extras.into_iter().map(|e| e.as_ref().to_string()).collect();
+        self
     }
 }
 
@@ -116,6 +335,36 @@ pub fn parse_requirement(req_str: &str) -> Result<DependencySpecifier> {
     })
 }
 
+impl VersionSpecifier {
+    /// PEP 440 comparison of `version` against this single clause, including `.*` wildcards.
+    ///
+    /// Unparseable versions are treated as matching rather than hiding candidates.
+    pub fn matches(&self, version: &str) -> bool {
+        let Ok(spec) = Specifier::new(&self.operator, &self.version) else { return true };
+        if spec.operator != Operator::ArbitraryEqual && Version::parse(version).is_err() {
+            return true;
+        }
+        spec.contains_str(version)
+    }
+}
+
+impl std::fmt::Display for VersionSpecifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", self.operator, self.version)
+    }
+}
+
+/// Parse a comma-separated specifier set such as `>=1.4,<2`
+pub fn parse_specifier_set(spec_str: &str) -> Result<Vec<VersionSpecifier>> {
+    parse_version_specifiers(spec_str)
+}
+
+/// Check a `Requires-Python` specifier set such as `>=3.8, !=3.9.*` against an interpreter version
+pub fn python_satisfies(requires_python: &str, python_version: &str) -> bool {
+    let Ok(specs) = parse_version_specifiers(requires_python) else { return true };
+    specs.iter().all(|spec| spec.matches(python_version))
+}
+
 fn parse_version_specifiers(spec_str: &str) -> Result<Vec<VersionSpecifier>> {
     if spec_str.is_empty() {
         return Ok(Vec::new());
@@ -188,14 +437,35 @@ fn extract_string_requirement(marker: &str, key: &str) -> Option<String> {
 }
 
 fn compare_versions(actual: &str, op: &str, required: &str) -> bool {
-    // Very simplified version comparison
-    match op {
-        ">=" => actual >= required,
-        "<=" => actual <= required,
-        ">" => actual > required,
-        "<" => actual < required,
-        "==" => actual == required,
-        "!=" => actual != required,
-        _ => true,
+    // PEP 440 ordering, so "3.11" >= "3.7" holds
+    VersionSpecifier { operator: op.to_string(), version: required.to_string() }.matches(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpreter_report_drives_markers() {
+        let env = TargetEnvironment::parse_report("python_version=3.10\npython_full_version=3.10.4\nsys_platform=win32\nplatform_system=Windows\nplatform_machine=AMD64\nos_name=nt\nimplementation_name=pypy\n").unwrap();
+        let holds = |marker: &str| EnvironmentMarker { raw: marker.to_string() }.evaluate(&env);
+        assert!(holds(r#"sys_platform == "win32""#));
+        assert!(holds(r#"python_version < "3.11""#));
+        assert!(!holds(r#"python_full_version >= "3.10.5""#));
+        assert!(!holds(r#"implementation_name == "cpython""#));
+        assert!(holds(r#"os_name == "nt""#));
+        assert!(TargetEnvironment::parse_report("python_version=3.10").is_err());
+    }
+
+    #[test]
+    fn test_target_triples() {
+        let mac = TargetEnvironment::parse_triple("aarch64-apple-darwin", "3.12").unwrap();
+        assert_eq!((mac.sys_platform.as_str(), mac.platform_machine.as_str(), mac.python_version.as_str()), ("darwin", "arm64", "3.12"));
+        let linux = TargetEnvironment::parse_triple("aarch64-unknown-linux-gnu", "3.9").unwrap();
+        assert_eq!((linux.platform_system.as_str(), linux.platform_machine.as_str()), ("Linux", "aarch64"));
+        let windows = TargetEnvironment::parse_triple("x86_64-pc-windows-msvc", "3.11").unwrap();
+        assert_eq!((windows.os_name.as_str(), windows.platform_machine.as_str()), ("nt", "AMD64"));
+        assert_eq!(TargetEnvironment::parse_triple("linux-3.13", "3.9").unwrap().python_version, "3.13");
+        assert!(TargetEnvironment::parse_triple("wasm32-unknown-unknown", "3.12").is_err());
     }
 }
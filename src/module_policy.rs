@@ -0,0 +1,150 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// What the daemon does about a missing module matching a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModulePolicy {
+    /// Install it, even with `auto_install` off or `require_approval` on
+    Install,
+    /// Tell the user, but don't install it
+    Notify,
+    /// Do nothing
+    Ignore,
+}
+
+impl std::str::FromStr for ModulePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "install" | "auto-install" | "auto_install" => Ok(ModulePolicy::Install),
+            "notify" | "notify-only" => Ok(ModulePolicy::Notify),
+            "ignore" => Ok(ModulePolicy::Ignore),
+            other => Err(anyhow!("Unknown policy '{}' (expected install, notify or ignore)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ModulePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ModulePolicy::Install => write!(f, "install"),
+            ModulePolicy::Notify => write!(f, "notify"),
+            ModulePolicy::Ignore => write!(f, "ignore"),
+        }
+    }
+}
+
+/// `[[module_policies]]` in daemon.toml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub pattern: String,
+    pub policy: ModulePolicy,
+}
+
+/// Characters only a regex uses; a pattern with none of them is a name or a glob
+const REGEX_SYNTAX: &[char] = &['^', '$', '(', ')', '|', '[', ']', '{', '}', '+', '\\'];
+
+/// Runs that mean "any characters" in a regex; nobody writes them in a glob meaning a dot
+const REGEX_WILDCARDS: &[&str] = &[".*", ".?"];
+
+/// A module name (`google.protobuf`), a glob (`numpy*`, `py?aml`) or a regex
+/// (`internal_.*`, `^(foo|bar)$`), always matched against the whole name. A pattern is a
+/// regex when it starts with `re:`, uses regex-only syntax (`^ $ ( ) | [ ] { } + \`) or
+/// has a `.*`/`.?` wildcard; otherwise `.` is a literal dot.
+#[derive(Debug, Clone)]
+pub enum ModulePattern {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl ModulePattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let regex = match pattern.strip_prefix("re:") {
+            Some(expression) => Regex::new(&format!("^(?:{})$", expression)),
+            None if pattern.contains(REGEX_SYNTAX) || REGEX_WILDCARDS.iter().any(|w| pattern.contains(w)) => {
+                Regex::new(&format!("^(?:{})$", pattern))
+            }
+            None if pattern.contains(['*', '?']) => glob_regex(pattern),
+            None => return Ok(ModulePattern::Exact(pattern.to_string())),
+        };
+        regex.map(ModulePattern::Regex)
+            .map_err(|e| anyhow!("Invalid module pattern '{}': {}", pattern, e))
+    }
+
+    pub fn matches(&self, module: &str) -> bool {
+        match self {
+            ModulePattern::Exact(name) => name == module,
+            ModulePattern::Regex(regex) => regex.is_match(module),
+        }
+    }
+}
+
+/// A regex matching exactly the whole names `glob` does: `*` is any run of characters,
+/// `?` one character, and everything else stands for itself
+pub fn glob_regex(glob: &str) -> std::result::Result<Regex, regex::Error> {
+    let mut expression = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            c => expression.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    expression.push('$');
+    Regex::new(&expression)
+}
+
+/// Whether any of `patterns` matches `module`; a pattern that doesn't parse only matches itself
+pub fn matches_any(patterns: &[String], module: &str) -> bool {
+    patterns.iter().any(|pattern| match ModulePattern::parse(pattern) {
+        Ok(pattern) => pattern.matches(module),
+        Err(_) => pattern == module,
+    })
+}
+
+/// The policy for `module`: a rule naming it exactly wins, then the first matching pattern
+pub fn policy_for(rules: &[PolicyRule], module: &str) -> Option<ModulePolicy> {
+    rules.iter()
+        .find(|rule| rule.pattern == module)
+        .or_else(|| rules.iter().find(|rule| matches_any(std::slice::from_ref(&rule.pattern), module)))
+        .map(|rule| rule.policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_and_policies() {
+        let patterns = vec!["numpy*".to_string(), "re:internal_.*".to_string(), "yaml".to_string()];
+        assert!(matches_any(&patterns, "numpy"));
+        assert!(matches_any(&patterns, "numpy_financial"));
+        assert!(matches_any(&patterns, "internal_auth"));
+        assert!(matches_any(&patterns, "yaml"));
+        assert!(!matches_any(&patterns, "pyyaml"));
+        assert!(!matches_any(&patterns, "scipy"));
+        assert!(ModulePattern::parse("bad(").is_err());
+
+        // Dots are literal in names and globs, but `.*` reads as the regex it looks like
+        let dotted = vec!["google.protobuf".to_string(), "ruamel.y*".to_string(), "internal_.*".to_string()];
+        assert!(matches_any(&dotted, "google.protobuf"));
+        assert!(!matches_any(&dotted, "googleXprotobuf"));
+        assert!(matches_any(&dotted, "ruamel.yaml"));
+        assert!(!matches_any(&dotted, "ruamelXyaml"));
+        assert!(matches_any(&dotted, "internal_auth"));
+        assert!(!matches_any(&dotted, "external_auth"));
+        assert!(matches_any(&["^(foo|bar)$".to_string()], "bar"));
+
+        let rules = vec![
+            PolicyRule { pattern: "torch*".to_string(), policy: ModulePolicy::Notify },
+            PolicyRule { pattern: "torchvision".to_string(), policy: ModulePolicy::Install },
+        ];
+        assert_eq!(policy_for(&rules, "torchvision"), Some(ModulePolicy::Install));
+        assert_eq!(policy_for(&rules, "torchaudio"), Some(ModulePolicy::Notify));
+        assert_eq!(policy_for(&rules, "requests"), None);
+        assert_eq!("notify-only".parse::<ModulePolicy>().unwrap(), ModulePolicy::Notify);
+    }
+}
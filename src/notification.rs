@@ -0,0 +1,150 @@
+use anyhow::{anyhow, Result};
+use snakegg::native::style::{bold, dim};
+use snakegg::native::which;
+use tokio::process::Command;
+use crate::logger;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+}
+
+/// How the daemon shows desktop notifications; picked for the platform at runtime
+/// unless `notifications` in daemon.toml names one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notifier {
+    /// libnotify's `notify-send` (Linux and the BSDs)
+    NotifySend,
+    /// `osascript` (macOS)
+    Osascript,
+    /// A toast through PowerShell (Windows)
+    WindowsToast,
+    /// Print to the daemon's output instead
+    Console,
+    Quiet,
+}
+
+impl Notifier {
+    pub fn detect() -> Self {
+        if cfg!(target_os = "macos") && which::has_executable("osascript") {
+            Notifier::Osascript
+        } else if cfg!(windows) && which::has_executable("powershell") {
+            Notifier::WindowsToast
+        } else if which::has_executable("notify-send") {
+            Notifier::NotifySend
+        } else {
+            Notifier::Console
+        }
+    }
+
+    pub fn named(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "auto" => Ok(Self::detect()),
+            "notify-send" | "libnotify" => Ok(Notifier::NotifySend),
+            "osascript" | "macos" => Ok(Notifier::Osascript),
+            "toast" | "windows" => Ok(Notifier::WindowsToast),
+            "console" => Ok(Notifier::Console),
+            "quiet" | "none" | "off" => Ok(Notifier::Quiet),
+            other => Err(anyhow!("Unknown notifier '{}' (expected auto, notify-send, osascript, toast, console or quiet)", other)),
+        }
+    }
+
+    /// The configured notifier, falling back to detection when it's unset or unknown
+    pub fn from_config(name: Option<&str>) -> Self {
+        name.and_then(|name| Self::named(name).ok()).unwrap_or_else(Self::detect)
+    }
+
+    /// Show a notification; failures are ignored, a notification is never worth an error
+    pub fn send(&self, summary: &str, body: &str, urgency: Urgency) {
+        match self.command(summary, body, urgency) {
+            Some((program, args)) => {
+                let _ = Command::new(program).args(args).spawn();
+            }
+            None if *self == Notifier::Console => {
+                logger::info(format!("🔔 {} {}", bold(summary), dim(body)));
+            }
+            None => {}
+        }
+    }
+
+    /// The program and arguments that show the notification, for backends that run one
+    fn command(&self, summary: &str, body: &str, urgency: Urgency) -> Option<(&'static str, Vec<String>)> {
+        let title = format!("🐍 Snakepit: {}", summary);
+        match self {
+            Notifier::NotifySend => Some(("notify-send", vec![
+                "-u".to_string(),
+                urgency.as_str().to_string(),
+                "-i".to_string(),
+                "dialog-information".to_string(),
+                title,
+                body.to_string(),
+            ])),
+            Notifier::Osascript => Some(("osascript", vec![
+                "-e".to_string(),
+                format!("display notification {} with title {}", applescript_string(body), applescript_string(&title)),
+            ])),
+            Notifier::WindowsToast => Some(("powershell", vec![
+                "-NoProfile".to_string(),
+                "-NonInteractive".to_string(),
+                "-Command".to_string(),
+                toast_script(&title, body),
+            ])),
+            Notifier::Console | Notifier::Quiet => None,
+        }
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn powershell_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+fn toast_script(title: &str, body: &str) -> String {
+    format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $text = $template.GetElementsByTagName('text'); \
+         $text.Item(0).AppendChild($template.CreateTextNode({})) > $null; \
+         $text.Item(1).AppendChild($template.CreateTextNode({})) > $null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('Snakepit').Show([Windows.UI.Notifications.ToastNotification]::new($template))",
+        powershell_string(title),
+        powershell_string(body),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_quote_their_text() {
+        let (program, args) = Notifier::Osascript.command("Installed", r#"Said "hi" \o/"#, Urgency::Low).unwrap();
+        assert_eq!(program, "osascript");
+        assert_eq!(args[1], r#"display notification "Said \"hi\" \\o/" with title "🐍 Snakepit: Installed""#);
+
+        let (_, args) = Notifier::WindowsToast.command("Failed", "can't install", Urgency::Critical).unwrap();
+        assert!(args[3].contains("CreateTextNode('can''t install')"));
+
+        let (_, args) = Notifier::NotifySend.command("Failed", "body", Urgency::Critical).unwrap();
+        assert_eq!(args[..2], ["-u".to_string(), "critical".to_string()]);
+
+        assert_eq!(Notifier::Quiet.command("a", "b", Urgency::Low), None);
+        assert_eq!(Notifier::named("off").unwrap(), Notifier::Quiet);
+        assert!(Notifier::named("pager").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+use crate::config::SnakepitConfig;
+use crate::daemon::DaemonStatus;
+use crate::uninstaller::{EnvironmentSnapshot, Snapshot, SnapshotFile};
+
+/// How commands print their results: prose for people (`text`) or one JSON document
+/// on stdout for scripts (`json`, from `--json` or `SNAKEPIT_OUTPUT=json`).
+///
+/// Progress and warnings stay on stderr either way, so stdout holds only the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "text" | "human" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(anyhow::anyhow!("Unknown output format '{}' (expected text or json)", value)),
+        }
+    }
+
+    /// The `output` setting; text when it's unset
+    pub fn from_config(config: &SnakepitConfig) -> Result<Self> {
+        config.output.as_deref().map(Self::parse).transpose().map(Option::unwrap_or_default)
+    }
+
+    pub fn is_json(self) -> bool {
+        self == OutputFormat::Json
+    }
+}
+
+/// `value` pretty-printed on stdout
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// `install` and `sync`: what ended up in which environment
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallReport {
+    /// The venv installed into; `null` for the user site-packages
+    pub environment: Option<PathBuf>,
+    pub installed: Vec<InstalledPackage>,
+}
+
+/// A package in an environment; `version` is `null` when its metadata can't be read
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+/// `daemon status`
+#[derive(Debug, Clone, Serialize)]
+pub struct DaemonStatusReport {
+    pub running: bool,
+    pub daemon_id: String,
+    pub pid: Option<u32>,
+    /// Unix seconds
+    pub started_at: Option<u64>,
+    pub error_count: usize,
+    pub auto_install: bool,
+    pub check_interval_secs: u64,
+}
+
+impl From<&DaemonStatus> for DaemonStatusReport {
+    fn from(status: &DaemonStatus) -> Self {
+        Self {
+            running: status.running,
+            daemon_id: status.daemon_id.clone(),
+            pid: status.pid,
+            started_at: status.started_at,
+            error_count: status.error_count,
+            auto_install: status.config.auto_install,
+            check_interval_secs: status.config.check_interval.as_secs(),
+        }
+    }
+}
+
+/// `snapshot list`
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotEntry {
+    pub id: String,
+    pub package: String,
+    pub version: String,
+    pub timestamp: String,
+    /// `sys.prefix` of the environment it was taken from, when recorded
+    pub environment: Option<PathBuf>,
+    pub file_count: Option<usize>,
+    /// Bytes the archive takes on disk
+    pub size: Option<u64>,
+}
+
+impl From<&Snapshot> for SnapshotEntry {
+    fn from(snapshot: &Snapshot) -> Self {
+        Self {
+            id: snapshot.id.clone(),
+            package: snapshot.package.clone(),
+            version: snapshot.version.clone(),
+            timestamp: snapshot.timestamp.clone(),
+            environment: snapshot.environment.clone(),
+            file_count: snapshot.file_count,
+            size: snapshot.size,
+        }
+    }
+}
+
+/// `snapshot list --env`
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvironmentSnapshotEntry {
+    pub id: String,
+    pub timestamp: String,
+    /// The venv it was taken from; `null` for the user site-packages
+    pub environment: Option<PathBuf>,
+    pub packages: usize,
+    /// Whether all of site-packages was archived too
+    pub files: bool,
+}
+
+impl From<&EnvironmentSnapshot> for EnvironmentSnapshotEntry {
+    fn from(snapshot: &EnvironmentSnapshot) -> Self {
+        Self {
+            id: snapshot.id.clone(),
+            timestamp: snapshot.timestamp.clone(),
+            environment: snapshot.environment.clone(),
+            packages: snapshot.packages.len(),
+            files: snapshot.files_path.is_some(),
+        }
+    }
+}
+
+/// `snapshot show`
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDetails {
+    #[serde(flatten)]
+    pub snapshot: SnapshotEntry,
+    /// Directory the files were archived from (normally site-packages), when recorded
+    pub source_dir: Option<PathBuf>,
+    pub files: Vec<SnapshotFile>,
+    /// Uncompressed bytes of all `files`
+    pub total_size: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_format_from_setting() {
+        assert_eq!(OutputFormat::parse("JSON").unwrap(), OutputFormat::Json);
+        assert_eq!(OutputFormat::parse("text").unwrap(), OutputFormat::Text);
+        assert!(OutputFormat::parse("yaml").is_err());
+
+        let mut config = SnakepitConfig::default();
+        assert_eq!(OutputFormat::from_config(&config).unwrap(), OutputFormat::Text);
+        config.output = Some("json".to_string());
+        assert!(OutputFormat::from_config(&config).unwrap().is_json());
+    }
+
+    #[test]
+    fn test_unknown_version_serializes_as_null() {
+        let report = InstallReport {
+            environment: None,
+            installed: vec![InstalledPackage { name: "requests".to_string(), version: None }],
+        };
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"environment":null,"installed":[{"name":"requests","version":null}]}"#
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use crate::manifest::canonical_name;
+use crate::markers::{self, VersionSpecifier};
+use crate::pep440::Version;
+
+/// Project settings file holding the `[overrides]` and `[pins]` tables
+/// (which may instead live in pyproject.toml's `[tool.snakepit]`)
+pub const PROJECT_FILE: &str = "snakepit.toml";
+
+/// Requirement specifiers that win over whatever any package (or the project) asks for.
+///
+/// For when upstream metadata is wrong: `[overrides] urllib3 = ">=1.26,<3"` replaces every
+/// requirement on urllib3 in the graph, and `[pins] numpy = "1.26.4"` forces that exact version.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    /// Normalized name to the specifier that replaces every requirement on it
+    specs: HashMap<String, (String, Vec<VersionSpecifier>)>,
+}
+
+#[derive(Deserialize)]
+struct Tables {
+    #[serde(default)]
+    overrides: BTreeMap<String, String>,
+    #[serde(default)]
+    pins: BTreeMap<String, String>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read `dir/snakepit.toml`, else `[tool.snakepit]` of `dir/pyproject.toml`;
+    /// no file means no overrides
+    pub fn load(dir: &Path) -> Result<Self> {
+        let Some(path) = [dir.join(PROJECT_FILE), dir.join("pyproject.toml")].into_iter().find(|path| path.is_file()) else {
+            return Ok(Self::new());
+        };
+        let Some(table) = crate::config::read_project_table(&path)? else {
+            return Ok(Self::new());
+        };
+        Self::from_table(table).with_context(|| format!("Invalid overrides in {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Self::from_table(toml::from_str(content)?)
+    }
+
+    fn from_table(table: toml::Table) -> Result<Self> {
+        let tables: Tables = toml::Value::Table(table).try_into()?;
+        let mut overrides = Self::new();
+        for (name, spec) in &tables.overrides {
+            overrides = overrides.with_override(name, spec)?;
+        }
+        // A pin is an override to one exact version; it wins over an override of the same package
+        for (name, version) in &tables.pins {
+            let version = version.trim().trim_start_matches("==");
+            overrides = overrides.with_override(name, &format!("=={}", version))?;
+        }
+        Ok(overrides)
+    }
+
+    /// Replace every requirement on `package` with `spec`, e.g. `>=1.26,<3`
+    pub fn with_override(mut self, package: &str, spec: &str) -> Result<Self> {
+        // `*` lifts every version limit
+        let spec = spec.trim().trim_start_matches('*');
+        let parsed = markers::parse_specifier_set(spec)?;
+        if let Some(bad) = parsed.iter().find(|s| Version::parse(s.version.trim_end_matches(".*")).is_err()) {
+            return Err(anyhow::anyhow!("'{}' is not a version specifier for {} ('{}' is not a version)", spec, package, bad.version));
+        }
+        self.specs.insert(canonical_name(package), (spec.to_string(), parsed));
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    /// The specifiers to use for `package` instead of the requested ones
+    pub fn get(&self, package: &str) -> Option<&[VersionSpecifier]> {
+        self.specs.get(&canonical_name(package)).map(|(_, parsed)| parsed.as_slice())
+    }
+
+    /// `name<spec>` lines, sorted, as recorded in the lockfile inputs
+    pub fn requirements(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.specs.iter()
+            .map(|(name, (spec, _))| format!("{}{}", name, spec))
+            .collect();
+        lines.sort();
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_and_pins_replace_specifiers() {
+        let overrides = Overrides::parse(r#"
+            name = "demo"
+
+            [overrides]
+            URLLib3 = ">=1.26,<3"
+            six = "*"
+            numpy = ">=2"
+
+            [pins]
+            numpy = "1.26.4"
+        "#).unwrap();
+
+        let urllib3 = overrides.get("urllib3").unwrap();
+        assert_eq!(urllib3.len(), 2);
+        assert!(urllib3.iter().all(|spec| spec.matches("2.2.1")));
+        assert!(overrides.get("six").unwrap().is_empty());
+        assert_eq!(overrides.get("numpy").unwrap()[0].operator, "==");
+        assert!(overrides.get("requests").is_none());
+        assert_eq!(overrides.requirements(), vec!["numpy==1.26.4", "six", "urllib3>=1.26,<3"]);
+
+        assert!(Overrides::parse("[overrides]\nsix = \"not a spec\"").is_err());
+        assert!(Overrides::parse("name = \"demo\"").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+use crate::resolver::PyPIInfo;
+use crate::simple_index::parse_requires_dist;
+use crate::tree::DependencyGraph;
+
+/// What `snakepit show` knows about a package: the installed dist-info when there is one,
+/// with the index filling in whatever it leaves out
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PackageInfo {
+    pub name: String,
+    /// The installed version, or the latest on the index when it isn't installed
+    pub version: String,
+    /// Newest release on the index, when it was reachable
+    pub latest: Option<String>,
+    pub summary: Option<String>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+    pub requires_dist: Vec<String>,
+    /// Installed packages that depend on this one
+    pub required_by: Vec<String>,
+    /// The `.dist-info` directory; `None` when the package isn't installed
+    pub location: Option<PathBuf>,
+    /// Paths from RECORD, relative to site-packages
+    pub files: Vec<String>,
+    /// Bytes the files take on disk
+    pub installed_size: Option<u64>,
+}
+
+impl PackageInfo {
+    /// Read the installed distribution of `package` in `site_packages`, if there is one
+    pub fn installed(site_packages: &Path, package: &str) -> Result<Option<Self>> {
+        let wanted = canonical_name(package);
+        let Ok(entries) = std::fs::read_dir(site_packages) else {
+            return Ok(None);
+        };
+        let Some(dist_info) = entries.flatten()
+            .map(|entry| entry.path())
+            .find(|path| {
+                let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
+                dir_name.strip_suffix(".dist-info")
+                    .and_then(|stem| stem.split_once('-'))
+                    .is_some_and(|(name, _)| canonical_name(name) == wanted)
+            })
+        else {
+            return Ok(None);
+        };
+
+        let metadata = std::fs::read_to_string(dist_info.join("METADATA"))?;
+        let headers = metadata_headers(&metadata);
+        let header = |key: &str| headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.clone())
+            .filter(|value| !value.is_empty() && value != "UNKNOWN");
+
+        let files: Vec<String> = std::fs::read_to_string(dist_info.join("RECORD"))
+            .map(|record| record.lines().filter_map(record_path).collect())
+            .unwrap_or_default();
+        let installed_size = files.iter()
+            .filter_map(|file| std::fs::symlink_metadata(site_packages.join(file)).ok())
+            .map(|meta| meta.len())
+            .sum();
+
+        let required_by = DependencyGraph::from_site_packages(site_packages)
+            .map(|graph| dependents(&graph, &wanted))
+            .unwrap_or_default();
+
+        Ok(Some(Self {
+            name: header("Name").unwrap_or_else(|| package.to_string()),
+            version: header("Version").unwrap_or_default(),
+            latest: None,
+            summary: header("Summary"),
+            license: headers_license(&headers),
+            homepage: header("Home-page").or_else(|| project_homepage(&headers)),
+            requires_dist: parse_requires_dist(&metadata),
+            required_by,
+            location: Some(dist_info),
+            files,
+            installed_size: Some(installed_size),
+        }))
+    }
+
+    /// A package that isn't installed, as the index describes it
+    pub fn from_index(info: &PyPIInfo) -> Self {
+        let mut package = Self {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            ..Default::default()
+        };
+        package.merge_index(info);
+        package
+    }
+
+    /// Fill what the dist-info left out from the index, and note the latest release
+    pub fn merge_index(&mut self, info: &PyPIInfo) {
+        let present = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty() && v != "UNKNOWN");
+        self.latest = Some(info.version.clone());
+        self.summary = self.summary.take().or_else(|| present(&info.summary));
+        self.license = self.license.take().or_else(|| {
+            present(&info.license).map(|text| text.lines().next().unwrap_or_default().trim().to_string())
+        });
+        self.homepage = self.homepage.take().or_else(|| present(&info.home_page));
+        if self.location.is_none() && self.requires_dist.is_empty() {
+            self.requires_dist = info.requires_dist.clone().unwrap_or_default();
+        }
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.location.is_some()
+    }
+}
+
+/// `Key: value` headers of a METADATA file, up to the blank line before the description.
+/// Folded continuation lines are joined onto their header with newlines.
+pub fn metadata_headers(metadata: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in metadata.lines().take_while(|line| !line.trim().is_empty()) {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+/// The license a METADATA file declares: `License-Expression`, else the first line of
+/// `License`, else the `License ::` classifier
+pub fn metadata_license(metadata: &str) -> Option<String> {
+    headers_license(&metadata_headers(metadata))
+}
+
+fn headers_license(headers: &[(String, String)]) -> Option<String> {
+    let header = |key: &str| headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.lines().next().unwrap_or_default().trim().to_string())
+        .filter(|value| !value.is_empty() && value != "UNKNOWN");
+    header("License-Expression")
+        .or_else(|| header("License"))
+        .or_else(|| classifier_license(headers))
+}
+
+/// `Project-URL: Homepage, https://...`, or the first project URL when none is labelled so
+fn project_homepage(headers: &[(String, String)]) -> Option<String> {
+    let urls: Vec<(&str, &str)> = headers.iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("Project-URL"))
+        .filter_map(|(_, value)| value.split_once(','))
+        .map(|(label, url)| (label.trim(), url.trim()))
+        .collect();
+    urls.iter()
+        .find(|(label, _)| matches!(label.to_lowercase().replace([' ', '-', '_'], "").as_str(), "homepage" | "home"))
+        .or_else(|| urls.first())
+        .map(|(_, url)| url.to_string())
+}
+
+/// The license named by a `License :: ...` trove classifier
+fn classifier_license(headers: &[(String, String)]) -> Option<String> {
+    headers.iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("Classifier"))
+        .find_map(|(_, value)| value.strip_prefix("License :: "))
+        .map(|license| license.rsplit(" :: ").next().unwrap_or(license).to_string())
+}
+
+/// The path column of a RECORD line; paths containing commas are quoted
+fn record_path(line: &str) -> Option<String> {
+    let path = match line.strip_prefix('"') {
+        Some(quoted) => quoted.split_once('"')?.0,
+        None => line.split(',').next()?,
+    };
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+/// Names of the packages in `graph` that require `package`, sorted
+fn dependents(graph: &DependencyGraph, package: &str) -> Vec<String> {
+    let mut names: Vec<String> = graph.nodes()
+        .filter(|node| node.requires.iter().any(|edge| edge.package == package))
+        .map(|node| node.name.clone())
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_installed_dist_info() {
+        let site = std::env::temp_dir().join(format!("snakepit-show-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&site);
+        let dist_info = site.join("Demo_Pkg-1.2.dist-info");
+        std::fs::create_dir_all(&dist_info).unwrap();
+        std::fs::create_dir_all(site.join("user-0.1.dist-info")).unwrap();
+        std::fs::write(dist_info.join("METADATA"), "Metadata-Version: 2.1\nName: Demo_Pkg\nVersion: 1.2\n\
+            Summary: A demo\nLicense: MIT\n        with more text\n\
+            Project-URL: Source, https://example.com/src\nProject-URL: Homepage, https://example.com\n\
+            Requires-Dist: six>=1.0\n\nLong description.\nLicense: not a header\n").unwrap();
+        std::fs::write(dist_info.join("RECORD"), "demo.py,sha256=x,10\n\"odd,name.py\",,\nDemo_Pkg-1.2.dist-info/RECORD,,\n").unwrap();
+        std::fs::write(site.join("demo.py"), "0123456789").unwrap();
+        std::fs::write(site.join("user-0.1.dist-info").join("METADATA"), "Name: user\nVersion: 0.1\nRequires-Dist: demo-pkg\n\n").unwrap();
+
+        let info = PackageInfo::installed(&site, "demo.pkg").unwrap().unwrap();
+        assert_eq!(info.version, "1.2");
+        assert_eq!(info.license.as_deref(), Some("MIT"));
+        assert_eq!(info.homepage.as_deref(), Some("https://example.com"));
+        assert_eq!(info.requires_dist, vec!["six>=1.0"]);
+        assert_eq!(info.required_by, vec!["user"]);
+        assert_eq!(info.files, vec!["demo.py", "odd,name.py", "Demo_Pkg-1.2.dist-info/RECORD"]);
+        assert!(info.installed_size.unwrap() >= 10);
+        assert!(PackageInfo::installed(&site, "missing").unwrap().is_none());
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{Result, anyhow};
 use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use regex::Regex;
 use lazy_static::lazy_static;
 
@@ -40,7 +41,7 @@ lazy_static! {
     ").unwrap();
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone)]
 pub struct Version {
     pub epoch: u64,
     pub release: Vec<u64>,
@@ -110,7 +111,8 @@ impl Version {
             None
         };
 
-        let local = caps.name("local").map(|m| m.as_str().to_string());
+        // Local labels compare case-insensitively, with any separator
+        let local = caps.name("local").map(|m| m.as_str().to_lowercase().replace(['-', '_'], "."));
 
         Ok(Version {
             epoch,
@@ -121,6 +123,64 @@ impl Version {
             local,
         })
     }
+
+    /// Pre-releases and dev releases (`2.0rc1`, `2.0.dev3`)
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    pub fn is_postrelease(&self) -> bool {
+        self.post.is_some()
+    }
+
+    /// The version without its `+local` label
+    pub fn public(&self) -> Version {
+        Version { local: None, ..self.clone() }
+    }
+
+    /// Epoch and release only: `1!2.0` for `1!2.0rc1.post2+abc`
+    pub fn base(&self) -> Version {
+        Version { epoch: self.epoch, release: self.release.clone(), pre: None, post: None, dev: None, local: None }
+    }
+
+    /// Whether the release segment starts with `prefix`, padding with zeros (`1.2` starts with `1.2.0`)
+    fn release_starts_with(&self, prefix: &[u64]) -> bool {
+        prefix.iter().enumerate().all(|(i, n)| self.release.get(i).copied().unwrap_or(0) == *n)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl Hash for Version {
+    // Must agree with `Ord`: `1.0` and `1.0.0` are the same version
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.epoch.hash(state);
+        let significant = self.release.iter().rposition(|n| *n != 0).map_or(0, |i| i + 1);
+        self.release[..significant].hash(state);
+        self.pre.hash(state);
+        self.post.hash(state);
+        self.dev.hash(state);
+        self.local.hash(state);
+    }
+}
+
+/// One dot-separated piece of a local label; numbers sort after (and among themselves, as) numbers
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum LocalSegment<'a> {
+    Text(&'a str),
+    Number(u64),
+}
+
+fn local_segments(local: &str) -> Vec<LocalSegment<'_>> {
+    local.split('.')
+        .map(|segment| segment.parse().map(LocalSegment::Number).unwrap_or(LocalSegment::Text(segment)))
+        .collect()
 }
 
 impl PartialOrd for Version {
@@ -150,20 +210,15 @@ impl Ord for Version {
         }
 
         // 3. Pre-release
-        // Rules: No pre-release > pre-release
-        match (&self.pre, &other.pre) {
-            (None, None) => {},
-            (Some(_), None) => return Ordering::Less,
-            (None, Some(_)) => return Ordering::Greater,
-            (Some((t1, n1)), Some((t2, n2))) => {
-                match t1.cmp(t2) {
-                    Ordering::Equal => match n1.cmp(n2) {
-                        Ordering::Equal => {},
-                        ord => return ord,
-                    },
-                    ord => return ord,
-                }
-            }
+        // Rules: No pre-release > pre-release, except a bare dev release (1.0.dev1) sorts before 1.0a1
+        let pre_rank = |v: &Version| match (&v.pre, v.post, v.dev) {
+            (None, None, Some(_)) => (0, None),
+            (Some(pre), _, _) => (1, Some(pre.clone())),
+            (None, _, _) => (2, None),
+        };
+        match pre_rank(self).cmp(&pre_rank(other)) {
+            Ordering::Equal => {},
+            ord => return ord,
         }
 
         // 4. Post-release
@@ -190,12 +245,12 @@ impl Ord for Version {
             }
         }
 
-        // 6. Local version (String comparison)
+        // 6. Local version, segment by segment
         match (&self.local, &other.local) {
             (None, None) => Ordering::Equal,
             (Some(_), None) => Ordering::Greater,
             (None, Some(_)) => Ordering::Less,
-            (Some(l1), Some(l2)) => l1.cmp(l2),
+            (Some(l1), Some(l2)) => local_segments(l1).cmp(&local_segments(l2)),
         }
     }
 }
@@ -228,3 +283,243 @@ impl fmt::Display for Version {
         Ok(())
     }
 }
+
+/// A specifier's comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+    Compatible,
+    ArbitraryEqual,
+}
+
+impl Operator {
+    pub fn parse(operator: &str) -> Result<Self> {
+        Ok(match operator.trim() {
+            "==" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            "<=" => Operator::LessEqual,
+            ">=" => Operator::GreaterEqual,
+            "<" => Operator::Less,
+            ">" => Operator::Greater,
+            "~=" => Operator::Compatible,
+            "===" => Operator::ArbitraryEqual,
+            other => return Err(anyhow!("Unknown version operator: {}", other)),
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::LessEqual => "<=",
+            Operator::GreaterEqual => ">=",
+            Operator::Less => "<",
+            Operator::Greater => ">",
+            Operator::Compatible => "~=",
+            Operator::ArbitraryEqual => "===",
+        }
+    }
+}
+
+/// One PEP 440 version clause such as `>=1.4`, `==1.2.*` or `===foobar`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Specifier {
+    pub operator: Operator,
+    /// The version as written, without a trailing `.*`
+    pub version: String,
+    pub wildcard: bool,
+    /// `None` only for `===`, which compares text
+    parsed: Option<Version>,
+}
+
+impl Specifier {
+    /// Parse `>=1.4`, `== 1.2.*`, `~=2.2`...
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let split = spec.find(|c: char| !"=!<>~".contains(c))
+            .ok_or_else(|| anyhow!("Missing version in specifier: {}", spec))?;
+        Self::new(&spec[..split], &spec[split..])
+    }
+
+    pub fn new(operator: &str, version: &str) -> Result<Self> {
+        let operator = Operator::parse(operator)?;
+        let version = version.trim();
+        if operator == Operator::ArbitraryEqual {
+            return Ok(Self { operator, version: version.to_string(), wildcard: false, parsed: None });
+        }
+
+        let (text, wildcard) = match version.strip_suffix(".*") {
+            Some(prefix) => (prefix, true),
+            None => (version, false),
+        };
+        let parsed = Version::parse(text)?;
+        let invalid = |reason: &str| Err(anyhow!("Invalid specifier {}{}: {}", operator.as_str(), version, reason));
+        if wildcard && !matches!(operator, Operator::Equal | Operator::NotEqual) {
+            return invalid("'.*' only goes with == and !=");
+        }
+        if wildcard && parsed != parsed.base() {
+            return invalid("'.*' must follow the release segment");
+        }
+        if parsed.local.is_some() && !matches!(operator, Operator::Equal | Operator::NotEqual) {
+            return invalid("local versions only go with == and !=");
+        }
+        if operator == Operator::Compatible && parsed.release.len() < 2 {
+            return invalid("~= needs at least two release segments");
+        }
+        Ok(Self { operator, version: text.to_string(), wildcard, parsed: Some(parsed) })
+    }
+
+    /// Whether `version` satisfies this clause. Pre-releases are not special-cased here;
+    /// whether they're candidates at all is up to the caller.
+    pub fn contains(&self, version: &Version) -> bool {
+        let Some(spec) = &self.parsed else {
+            return version.to_string().eq_ignore_ascii_case(&self.version);
+        };
+        match self.operator {
+            Operator::Equal => self.equals(spec, version),
+            Operator::NotEqual => !self.equals(spec, version),
+            Operator::LessEqual => version.public() <= *spec,
+            Operator::GreaterEqual => version.public() >= *spec,
+            // <3.0 doesn't let in 3.0rc1, unless it names a pre-release itself
+            Operator::Less => version.public() < *spec
+                && (spec.is_prerelease() || !version.is_prerelease() || version.base() != spec.base()),
+            // >3.0 doesn't let in 3.0.post1 (unless it names a post-release) or 3.0+local
+            Operator::Greater => version.public() > *spec
+                && (spec.is_postrelease() || !version.is_postrelease() || version.base() != spec.base())
+                && (version.local.is_none() || version.base() != spec.base()),
+            // ~=2.2.1 is >=2.2.1, ==2.2.*
+            Operator::Compatible => version.public() >= *spec
+                && version.epoch == spec.epoch
+                && version.release_starts_with(&spec.release[..spec.release.len() - 1]),
+            Operator::ArbitraryEqual => false,
+        }
+    }
+
+    /// `===` compares the text as written; everything else compares parsed versions
+    pub fn contains_str(&self, version: &str) -> bool {
+        match self.operator {
+            Operator::ArbitraryEqual => version.trim().eq_ignore_ascii_case(&self.version),
+            _ => Version::parse(version).is_ok_and(|v| self.contains(&v)),
+        }
+    }
+
+    fn equals(&self, spec: &Version, version: &Version) -> bool {
+        if self.wildcard {
+            version.epoch == spec.epoch && version.release_starts_with(&spec.release)
+        } else if spec.local.is_some() {
+            version == spec
+        } else {
+            // ==1.0 matches 1.0+cpu
+            version.public() == *spec
+        }
+    }
+}
+
+impl fmt::Display for Specifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.operator.as_str(), self.version, if self.wildcard { ".*" } else { "" })
+    }
+}
+
+/// A comma-separated set of clauses, all of which must hold: `>=1.4,!=1.5.*,<2`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SpecifierSet {
+    specifiers: Vec<Specifier>,
+}
+
+impl SpecifierSet {
+    pub fn parse(specs: &str) -> Result<Self> {
+        specs.split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .map(Specifier::parse)
+            .collect::<Result<Vec<_>>>()
+            .map(Self::from)
+    }
+
+    pub fn contains(&self, version: &Version) -> bool {
+        self.specifiers.iter().all(|spec| spec.contains(version))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specifiers.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Specifier> {
+        self.specifiers.iter()
+    }
+}
+
+impl From<Vec<Specifier>> for SpecifierSet {
+    fn from(specifiers: Vec<Specifier>) -> Self {
+        Self { specifiers }
+    }
+}
+
+impl fmt::Display for SpecifierSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let specs: Vec<String> = self.specifiers.iter().map(|spec| spec.to_string()).collect();
+        write!(f, "{}", specs.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    fn allows(specs: &str, version: &str) -> bool {
+        SpecifierSet::parse(specs).unwrap().contains(&v(version))
+    }
+
+    #[test]
+    fn test_ordering_corner_cases() {
+        let ordered = ["1.0.dev0", "1.0a1.dev1", "1.0a1", "1.0b2", "1.0rc1", "1.0", "1.0+abc.5", "1.0+abc.10", "1.0.post1.dev2", "1.0.post1", "1!0.1"];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert_eq!(v("1.0+CPU-1"), v("1.0+cpu.1"));
+        let set: std::collections::HashSet<Version> = [v("2"), v("2.0.0")].into();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_specifier_operators() {
+        assert!(allows("==1.2.*", "1.2.9"));
+        assert!(allows("==1.2.*", "1.2"));
+        assert!(!allows("==1.2.*", "1.20"));
+        assert!(!allows("!=1.2.*", "1.2.0rc1"));
+        assert!(allows("==1.0", "1.0+cpu"));
+        assert!(!allows("==1.0+cpu", "1.0+gpu"));
+        assert!(allows("~=2.2", "2.9"));
+        assert!(!allows("~=2.2.1", "2.3"));
+        assert!(!allows("~=2.2", "1!2.5"));
+        assert!(!allows("<3.0", "3.0rc1"));
+        assert!(allows("<3.0rc2", "3.0rc1"));
+        assert!(!allows(">1.7", "1.7.post1"));
+        assert!(!allows(">1.7", "1.7+local"));
+        assert!(allows(">1.7.post1", "1.7.post2"));
+        assert!(allows(">=1!1.0", "1!2.0"));
+        assert!(!allows(">=1!1.0", "9.0"));
+        assert!(allows(">=1.4, !=1.5.*, <2", "1.6.1"));
+        assert!(!allows(">=1.4, !=1.5.*, <2", "1.5.3"));
+
+        let arbitrary = Specifier::parse("===foobar").unwrap();
+        assert!(arbitrary.contains_str("FooBar"));
+        assert!(!arbitrary.contains_str("1.0"));
+
+        assert!(Specifier::parse(">=1.2.*").is_err());
+        assert!(Specifier::parse("~=1").is_err());
+        assert!(Specifier::parse("==1.0rc1.*").is_err());
+        assert_eq!(SpecifierSet::parse(">= 1.4 , ==1.5.*").unwrap().to_string(), ">=1.4,==1.5.*");
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use snakegg::native::style::{bold, cyan, dim, green, red, yellow};
+use crate::freeze::installed_distributions;
+use crate::manifest::canonical_name;
+use crate::cache::format_size;
+use crate::package_info::PackageInfo;
+use crate::pep440::Version;
+
+/// What `--dry-run` would do to one package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Install,
+    Upgrade,
+    Downgrade,
+    Remove,
+    /// Already installed at the wanted version
+    Unchanged,
+}
+
+impl Action {
+    /// Whether the package would be fetched from the index
+    pub fn downloads(self) -> bool {
+        matches!(self, Action::Install | Action::Upgrade | Action::Downgrade)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedChange {
+    pub name: String,
+    pub action: Action,
+    /// The version afterwards; `None` for removals and for installs from a local source
+    pub version: Option<String>,
+    /// The version installed now
+    pub installed_version: Option<String>,
+    /// An editable checkout or local archive installed instead of an index release
+    pub source: Option<String>,
+    /// The file that would be downloaded
+    pub download: Option<String>,
+    /// Its size, when the index records one
+    pub download_size: Option<u64>,
+    /// What the installed files take on disk; set for removals
+    pub installed_size: Option<u64>,
+}
+
+/// Everything an install, uninstall or sync would change in one environment,
+/// worked out from what's installed there now without changing anything
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan {
+    pub site_packages: PathBuf,
+    pub changes: Vec<PlannedChange>,
+    /// Canonical name to installed version
+    #[serde(skip)]
+    installed: HashMap<String, String>,
+}
+
+impl Plan {
+    pub fn for_site_packages(site_packages: &Path) -> Result<Self> {
+        let installed = installed_distributions(site_packages)?.into_iter()
+            .map(|dist| (canonical_name(&dist.name), dist.version))
+            .collect();
+        Ok(Self { site_packages: site_packages.to_path_buf(), changes: Vec::new(), installed })
+    }
+
+    pub fn installed_version(&self, name: &str) -> Option<&str> {
+        self.installed.get(&canonical_name(name)).map(String::as_str)
+    }
+
+    /// `name` at `version` from the index: an install, an upgrade, a downgrade or nothing
+    pub fn install(&mut self, name: &str, version: &str) {
+        let installed_version = self.installed_version(name).map(str::to_string);
+        let action = match &installed_version {
+            None => Action::Install,
+            Some(current) => match (Version::parse(current), Version::parse(version)) {
+                (Ok(current), Ok(wanted)) if wanted == current => Action::Unchanged,
+                (Ok(current), Ok(wanted)) if wanted < current => Action::Downgrade,
+                _ if current == version => Action::Unchanged,
+                _ => Action::Upgrade,
+            },
+        };
+        self.push(PlannedChange {
+            name: name.to_string(),
+            action,
+            version: Some(version.to_string()),
+            installed_version,
+            source: None,
+            download: None,
+            download_size: None,
+            installed_size: None,
+        });
+    }
+
+    /// `name` built or unpacked from `source`, which is always (re)installed
+    pub fn install_from(&mut self, name: &str, version: Option<&str>, source: &str) {
+        self.push(PlannedChange {
+            name: name.to_string(),
+            action: Action::Install,
+            version: version.map(str::to_string),
+            installed_version: self.installed_version(name).map(str::to_string),
+            source: Some(source.to_string()),
+            download: None,
+            download_size: None,
+            installed_size: None,
+        });
+    }
+
+    /// Uninstalling `name`; ignored when it isn't installed
+    pub fn remove(&mut self, name: &str) {
+        let Some(installed_version) = self.installed_version(name).map(str::to_string) else {
+            return;
+        };
+        let installed_size = PackageInfo::installed(&self.site_packages, name).ok()
+            .flatten()
+            .and_then(|info| info.installed_size);
+        self.push(PlannedChange {
+            name: name.to_string(),
+            action: Action::Remove,
+            version: None,
+            installed_version: Some(installed_version),
+            source: None,
+            download: None,
+            download_size: None,
+            installed_size,
+        });
+    }
+
+    /// A package is planned once; a later entry replaces an earlier one
+    fn push(&mut self, change: PlannedChange) {
+        let name = canonical_name(&change.name);
+        self.changes.retain(|existing| canonical_name(&existing.name) != name);
+        self.changes.push(change);
+    }
+
+    /// The changes that need a file from the index, for filling in their downloads
+    pub fn downloads_mut(&mut self) -> impl Iterator<Item = &mut PlannedChange> {
+        self.changes.iter_mut().filter(|change| change.action.downloads() && change.source.is_none())
+    }
+
+    pub fn count(&self, action: Action) -> usize {
+        self.changes.iter().filter(|change| change.action == action).count()
+    }
+
+    /// Bytes to download, counting only files whose size the index gave
+    pub fn download_size(&self) -> u64 {
+        self.changes.iter().filter_map(|change| change.download_size).sum()
+    }
+
+    /// Bytes the removals free
+    pub fn freed_size(&self) -> u64 {
+        self.changes.iter()
+            .filter(|change| change.action == Action::Remove)
+            .filter_map(|change| change.installed_size)
+            .sum()
+    }
+
+    /// The plan for people, one line per change, then the totals
+    pub fn print(&self) {
+        println!("{}", bold(format!("Dry run for {} (nothing was changed)", self.site_packages.display())));
+        let mut changes: Vec<&PlannedChange> = self.changes.iter()
+            .filter(|change| change.action != Action::Unchanged)
+            .collect();
+        changes.sort_by_key(|change| canonical_name(&change.name));
+        for change in &changes {
+            let version = change.version.as_deref().unwrap_or_default();
+            let installed = change.installed_version.as_deref().unwrap_or_default();
+            let line = match change.action {
+                Action::Install => green(format!("  + {} {}", change.name, version)),
+                Action::Upgrade => cyan(format!("  ↑ {} {} -> {}", change.name, installed, version)),
+                Action::Downgrade => yellow(format!("  ↓ {} {} -> {}", change.name, installed, version)),
+                Action::Remove => red(format!("  - {} {}", change.name, installed)),
+                Action::Unchanged => continue,
+            };
+            let detail = match (&change.source, &change.download, change.download_size, change.installed_size) {
+                (Some(source), ..) => format!("from {}", source),
+                (None, Some(file), Some(size), _) => format!("{}, {}", file, format_size(size)),
+                (None, Some(file), None, _) => format!("{}, size unknown", file),
+                (None, None, _, Some(size)) if change.action == Action::Remove => format!("frees {}", format_size(size)),
+                _ => String::new(),
+            };
+            if detail.is_empty() {
+                println!("{}", line);
+            } else {
+                println!("{} {}", line, dim(format!("({})", detail)));
+            }
+        }
+
+        let unchanged = self.count(Action::Unchanged);
+        if changes.is_empty() {
+            println!("{}", green(format!("Nothing to do; {} packages already up to date", unchanged)));
+            return;
+        }
+        let mut summary = vec![
+            format!("{} to install", self.count(Action::Install)),
+            format!("{} to upgrade", self.count(Action::Upgrade)),
+            format!("{} to downgrade", self.count(Action::Downgrade)),
+            format!("{} to remove", self.count(Action::Remove)),
+        ];
+        summary.retain(|part| !part.starts_with("0 "));
+        if unchanged > 0 {
+            summary.push(format!("{} unchanged", unchanged));
+        }
+        println!("{}", bold(summary.join(", ")));
+        println!("{}", dim(format!("Download {}, free {}", format_size(self.download_size()), format_size(self.freed_size()))));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist(site: &Path, name: &str, version: &str, files: &[(&str, &str)]) {
+        let dist_info = site.join(format!("{}-{}.dist-info", name, version));
+        std::fs::create_dir_all(&dist_info).unwrap();
+        std::fs::write(dist_info.join("METADATA"), format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n\n", name, version)).unwrap();
+        let mut record = String::new();
+        for (path, contents) in files {
+            std::fs::write(site.join(path), contents).unwrap();
+            record.push_str(&format!("{},,\n", path));
+        }
+        std::fs::write(dist_info.join("RECORD"), record).unwrap();
+    }
+
+    #[test]
+    fn test_classifies_changes_against_the_environment() {
+        let site = std::env::temp_dir().join(format!("snakepit-plan-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&site);
+        std::fs::create_dir_all(&site).unwrap();
+        dist(&site, "requests", "2.31.0", &[]);
+        dist(&site, "urllib3", "1.26.18", &[]);
+        dist(&site, "Six", "1.16.0", &[("six.py", "0123456789")]);
+        dist(&site, "idna", "3.6", &[]);
+
+        let mut plan = Plan::for_site_packages(&site).unwrap();
+        plan.install("requests", "2.31.0");
+        plan.install("urllib3", "2.2.1");
+        plan.install("idna", "3.4");
+        plan.install("certifi", "2024.2.2");
+        plan.remove("six");
+        plan.remove("not-installed");
+        plan.install_from("mine", None, "./src/mine");
+
+        let action = |name: &str| plan.changes.iter().find(|change| change.name == name).map(|change| change.action);
+        assert_eq!(action("requests"), Some(Action::Unchanged));
+        assert_eq!(action("urllib3"), Some(Action::Upgrade));
+        assert_eq!(action("idna"), Some(Action::Downgrade));
+        assert_eq!(action("certifi"), Some(Action::Install));
+        assert_eq!(action("six"), Some(Action::Remove));
+        assert_eq!(action("not-installed"), None);
+        assert_eq!(plan.freed_size(), 10);
+        assert_eq!(plan.downloads_mut().count(), 3);
+
+        for change in plan.downloads_mut() {
+            change.download_size = Some(100);
+        }
+        assert_eq!(plan.download_size(), 300);
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
@@ -0,0 +1,364 @@
+use serde::Serialize;
+use crate::config::SnakepitConfig;
+use crate::dependency::{Dependency, ProjectDependencies};
+use crate::lockfile::{Lockfile, PackageSource};
+use crate::manifest::canonical_name;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+    /// No data to judge by; left out of the score
+    NotAssessed,
+}
+
+/// One scored aspect of a project's dependency hygiene
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    /// 0-100; `None` when not assessed
+    pub score: Option<u32>,
+    /// Share of the overall score relative to the other assessed checks
+    pub weight: u32,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+}
+
+impl Check {
+    fn scored(name: &'static str, weight: u32, score: u32, detail: String, remediation: &str) -> Self {
+        let status = match score {
+            100 => Status::Pass,
+            50..=99 => Status::Warn,
+            _ => Status::Fail,
+        };
+        Self {
+            name,
+            status,
+            score: Some(score),
+            weight,
+            detail,
+            remediation: (status != Status::Pass).then(|| remediation.to_string()),
+        }
+    }
+
+    fn not_assessed(name: &'static str, detail: &str) -> Self {
+        Self {
+            name,
+            status: Status::NotAssessed,
+            score: None,
+            weight: 0,
+            detail: detail.to_string(),
+            remediation: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PostureReport {
+    pub project: String,
+    pub score: u32,
+    pub grade: char,
+    pub checks: Vec<Check>,
+}
+
+impl PostureReport {
+    /// Suggestions for every check that didn't pass, worst first
+    pub fn remediations(&self) -> Vec<&str> {
+        let mut failing: Vec<&Check> = self.checks.iter().filter(|c| c.remediation.is_some()).collect();
+        failing.sort_by_key(|c| (c.score.unwrap_or(100), std::cmp::Reverse(c.weight)));
+        failing.iter().filter_map(|c| c.remediation.as_deref()).collect()
+    }
+}
+
+/// Assesses a project from its manifest, lockfile, configuration and environment.
+///
+/// Each input is optional; checks whose input is missing either fail (no lockfile)
+/// or are reported as not assessed (no environment), never silently skipped.
+pub struct Posture<'a> {
+    project: String,
+    manifest: Option<ProjectDependencies>,
+    lockfile: Option<(Lockfile, Vec<String>)>,
+    config: Option<&'a SnakepitConfig>,
+    agent_installed: Option<bool>,
+}
+
+impl<'a> Posture<'a> {
+    pub fn new(project: &str) -> Self {
+        Self {
+            project: project.to_string(),
+            manifest: None,
+            lockfile: None,
+            config: None,
+            agent_installed: None,
+        }
+    }
+
+    pub fn with_manifest(mut self, manifest: ProjectDependencies) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// The lockfile as parsed, plus the problems `lock --check-format` found in its text
+    pub fn with_lockfile(mut self, lockfile: Lockfile, format_problems: Vec<String>) -> Self {
+        self.lockfile = Some((lockfile, format_problems));
+        self
+    }
+
+    pub fn with_config(mut self, config: &'a SnakepitConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Whether the project's environment has the integrity agent installed
+    pub fn with_environment(mut self, agent_installed: bool) -> Self {
+        self.agent_installed = Some(agent_installed);
+        self
+    }
+
+    pub fn assess(&self) -> PostureReport {
+        let checks = vec![
+            self.lockfile_check(),
+            self.pinning_check(),
+            self.hash_check(),
+            self.provenance_check(),
+            self.environment_check(),
+            Check::not_assessed("vulnerability audit", "snakepit has no vulnerability database to audit against yet"),
+            Check::not_assessed("license policy", "no license policy is configured"),
+            Check::not_assessed("attestations", "package attestations (PEP 740) are not verified yet"),
+        ];
+
+        let weight: u32 = checks.iter().filter(|c| c.score.is_some()).map(|c| c.weight).sum();
+        let total: u32 = checks.iter().filter_map(|c| c.score.map(|s| s * c.weight)).sum();
+        let score = total.checked_div(weight).unwrap_or(0);
+        let grade = match score {
+            90.. => 'A',
+            75..=89 => 'B',
+            60..=74 => 'C',
+            40..=59 => 'D',
+            _ => 'F',
+        };
+
+        PostureReport { project: self.project.clone(), score, grade, checks }
+    }
+
+    fn direct_dependencies(&self) -> Vec<&Dependency> {
+        self.manifest.iter()
+            .flat_map(|m| m.dependencies.iter().chain(m.dev_dependencies.iter()))
+            .collect()
+    }
+
+    fn lockfile_check(&self) -> Check {
+        let remediation = "Run `snakepit lock` and commit snakepit.lock";
+        match &self.lockfile {
+            None => Check::scored("lockfile", 3, 0, "no snakepit.lock".to_string(), remediation),
+            Some((_, problems)) if !problems.is_empty() => Check::scored(
+                "lockfile", 3, 60,
+                format!("snakepit.lock is not in canonical form: {}", problems.join("; ")),
+                "Regenerate snakepit.lock with `snakepit lock` instead of editing it by hand",
+            ),
+            Some((lockfile, _)) => {
+                // Everything the manifest declares should be locked
+                let locked: Vec<String> = lockfile.packages.iter().map(|p| canonical_name(&p.name)).collect();
+                let missing: Vec<&str> = self.direct_dependencies().into_iter()
+                    .filter(|d| !locked.contains(&canonical_name(&d.name)))
+                    .map(|d| d.name.as_str())
+                    .collect();
+                if missing.is_empty() {
+                    Check::scored("lockfile", 3, 100, format!("{} packages locked", lockfile.packages.len()), remediation)
+                } else {
+                    Check::scored(
+                        "lockfile", 3, 50,
+                        format!("out of date, missing {}", missing.join(", ")),
+                        "Re-run `snakepit lock`; the manifest declares packages the lockfile doesn't have",
+                    )
+                }
+            }
+        }
+    }
+
+    fn pinning_check(&self) -> Check {
+        let remediation = "Pin exact versions through snakepit.lock (`snakepit lock`, then `snakepit sync`)";
+        if let Some((lockfile, _)) = &self.lockfile {
+            let total = lockfile.packages.len();
+            let exact = lockfile.packages.iter().filter(|p| !p.version.trim().is_empty()).count();
+            return Check::scored("pinned versions", 2, percent(exact, total), format!("{}/{} locked packages have an exact version", exact, total), remediation);
+        }
+
+        let direct = self.direct_dependencies();
+        if direct.is_empty() {
+            return Check::not_assessed("pinned versions", "no manifest or lockfile found");
+        }
+        let pinned: Vec<&&Dependency> = direct.iter()
+            .filter(|d| d.version.is_some() && d.version_constraint.as_deref().is_none_or(|c| c == "==" || c == "==="))
+            .collect();
+        Check::scored(
+            "pinned versions", 2, percent(pinned.len(), direct.len()),
+            format!("{}/{} declared dependencies are pinned to an exact version (transitive ones are not)", pinned.len(), direct.len()),
+            remediation,
+        )
+    }
+
+    fn hash_check(&self) -> Check {
+        let remediation = "Lock with `snakepit lock` and install with `snakepit sync --require-hashes`";
+        let (hashed, total, what) = match &self.lockfile {
+            Some((lockfile, _)) => (
+                lockfile.packages.iter().filter(|p| p.hashes.iter().any(|h| h.starts_with("sha256:") || h.len() == 64)).count(),
+                lockfile.packages.len(),
+                "locked packages",
+            ),
+            None => {
+                let direct = self.direct_dependencies();
+                (direct.iter().filter(|d| !d.hashes.is_empty()).count(), direct.len(), "declared dependencies")
+            }
+        };
+        if total == 0 {
+            return Check::not_assessed("hash coverage", "no packages to check");
+        }
+        Check::scored("hash coverage", 3, percent(hashed, total), format!("{}/{} {} carry a sha256 hash", hashed, total, what), remediation)
+    }
+
+    fn provenance_check(&self) -> Check {
+        let mut findings = Vec::new();
+        let mut penalty = 0;
+
+        if let Some(config) = self.config {
+            if config.index_url.as_deref().is_some_and(|url| url.starts_with("http://")) {
+                findings.push("index_url is plain HTTP".to_string());
+                penalty += 60;
+            }
+            let insecure_mirrors = config.mirrors.iter().flatten().filter(|m| m.starts_with("http://")).count();
+            if insecure_mirrors > 0 {
+                findings.push(format!("{} mirror(s) over plain HTTP", insecure_mirrors));
+                penalty += 30;
+            }
+        }
+
+        let packages = self.lockfile.iter().flat_map(|(lockfile, _)| lockfile.packages.iter());
+        for package in packages {
+            match &package.source {
+                PackageSource::PyPI { url } if url.starts_with("http://") => {
+                    findings.push(format!("{} comes from {} over plain HTTP", package.name, url));
+                    penalty += 30;
+                }
+                PackageSource::Git { url, rev } if !is_commit(rev) => {
+                    findings.push(format!("{} tracks {} at movable ref '{}'", package.name, url, rev));
+                    penalty += 20;
+                }
+                PackageSource::Path { path } => {
+                    findings.push(format!("{} is installed from local path {}", package.name, path));
+                    penalty += 10;
+                }
+                _ => {}
+            }
+        }
+        let editables = self.manifest.as_ref().map_or(0, |m| m.editables.len());
+        if editables > 0 {
+            findings.push(format!("{} editable install(s)", editables));
+            penalty += 5;
+        }
+
+        let detail = if findings.is_empty() {
+            "every package comes from an HTTPS index or a pinned commit".to_string()
+        } else {
+            findings.join("; ")
+        };
+        Check::scored(
+            "index provenance", 2, 100u32.saturating_sub(penalty), detail,
+            "Use HTTPS index URLs and mirrors, and pin Git dependencies to full commit hashes",
+        )
+    }
+
+    fn environment_check(&self) -> Check {
+        match self.agent_installed {
+            None => Check::not_assessed("environment integrity", "no project environment found"),
+            Some(true) => Check::scored("environment integrity", 1, 100, "integrity agent installed".to_string(), ""),
+            Some(false) => Check::scored(
+                "environment integrity", 1, 0,
+                "environment is not watched by the integrity agent".to_string(),
+                "Install the integrity agent with `snakepit agent install`",
+            ),
+        }
+    }
+}
+
+fn percent(part: usize, total: usize) -> u32 {
+    (part * 100).checked_div(total).map_or(100, |p| p as u32)
+}
+
+fn is_commit(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lockfile::LockedPackage;
+
+    fn locked(name: &str, hashes: &[&str], source: PackageSource) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            dependencies: Vec::new(),
+            hashes: hashes.iter().map(|h| h.to_string()).collect(),
+            requires_python: None,
+            source,
+            extras: Vec::new(),
+            files: Vec::new(),
+            environments: Vec::new(),
+        }
+    }
+
+    fn pypi(name: &str) -> PackageSource {
+        PackageSource::PyPI { url: format!("https://pypi.org/simple/{}/", name) }
+    }
+
+    fn declared(name: &str, constraint: &str, version: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            version_constraint: Some(constraint.to_string()),
+            is_dev: false,
+            source: None,
+            extras: Vec::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_missing_lockfile_scores_low_with_remediation() {
+        let mut manifest = ProjectDependencies::new();
+        manifest.dependencies.push(declared("requests", ">=", "2.0"));
+        manifest.dependencies.push(declared("flask", "==", "3.0.0"));
+        let report = Posture::new("demo").with_manifest(manifest).assess();
+
+        let lockfile = report.checks.iter().find(|c| c.name == "lockfile").unwrap();
+        assert_eq!(lockfile.status, Status::Fail);
+        let pinning = report.checks.iter().find(|c| c.name == "pinned versions").unwrap();
+        assert_eq!(pinning.score, Some(50));
+        assert!(report.score < 40);
+        assert_eq!(report.grade, 'F');
+        assert_eq!(report.remediations()[0], "Run `snakepit lock` and commit snakepit.lock");
+    }
+
+    #[test]
+    fn test_hashed_lockfile_from_https_scores_well() {
+        let mut lockfile = Lockfile::new();
+        lockfile.add_package(locked("requests", &["sha256:abc"], pypi("requests")));
+        lockfile.add_package(locked("tool", &[], PackageSource::Git { url: "https://github.com/x/tool".to_string(), rev: "main".to_string() }));
+
+        let report = Posture::new("demo")
+            .with_lockfile(lockfile, Vec::new())
+            .with_environment(true)
+            .assess();
+        let checks: Vec<(&str, Status)> = report.checks.iter().map(|c| (c.name, c.status)).collect();
+        assert!(checks.contains(&("lockfile", Status::Pass)));
+        assert!(checks.contains(&("hash coverage", Status::Warn)));
+        assert!(checks.contains(&("index provenance", Status::Warn)));
+        assert!(checks.contains(&("vulnerability audit", Status::NotAssessed)));
+        assert_eq!(report.grade, 'B');
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use crate::config::SnakepitConfig;
+use crate::manifest::canonical_name;
+use crate::markers::VersionSpecifier;
+use crate::pep440::Version;
+
+/// Which packages may resolve to pre-releases (`rc`, `b`, `a`) and dev releases.
+///
+/// Follows pip: they're skipped unless `--pre` is given, the package is opted in,
+/// its specifier names a pre-release itself (`>=2.0rc1`), or nothing else exists.
+#[derive(Debug, Clone, Default)]
+pub struct Prereleases {
+    all: bool,
+    packages: HashSet<String>,
+}
+
+impl Prereleases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `allow_prereleases` allowlist; `"*"` opts every package in
+    pub fn from_config(config: &SnakepitConfig) -> Self {
+        let mut prereleases = Self::new();
+        for name in config.allow_prereleases.iter().flatten() {
+            prereleases = prereleases.with_package(name);
+        }
+        prereleases
+    }
+
+    /// `--pre`: allow pre-releases for every package
+    pub fn with_all(mut self, all: bool) -> Self {
+        self.all |= all;
+        self
+    }
+
+    pub fn with_package(mut self, package: &str) -> Self {
+        match package.trim() {
+            "*" => self.all = true,
+            name => { self.packages.insert(canonical_name(name)); }
+        }
+        self
+    }
+
+    pub fn allows(&self, package: &str) -> bool {
+        self.all || self.packages.contains(&canonical_name(package))
+    }
+}
+
+pub fn is_prerelease(version: &str) -> bool {
+    Version::parse(version).is_ok_and(|v| v.pre.is_some() || v.dev.is_some())
+}
+
+/// Whether a specifier set asks for a pre-release by name, which opts its package in
+pub fn mentions_prerelease(specs: &[VersionSpecifier]) -> bool {
+    specs.iter().any(|spec| is_prerelease(&spec.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_and_flags() {
+        let none = Prereleases::new();
+        assert!(!none.allows("django"));
+        assert!(Prereleases::new().with_all(true).allows("django"));
+
+        let config = SnakepitConfig { allow_prereleases: Some(vec!["Django_Ninja".to_string()]), ..Default::default() };
+        let listed = Prereleases::from_config(&config);
+        assert!(listed.allows("django-ninja"));
+        assert!(!listed.allows("django"));
+
+        let config = SnakepitConfig { allow_prereleases: Some(vec!["*".to_string()]), ..Default::default() };
+        assert!(Prereleases::from_config(&config).allows("anything"));
+    }
+
+    #[test]
+    fn test_detects_prerelease_specifiers() {
+        assert!(is_prerelease("2.0.0rc1"));
+        assert!(is_prerelease("1.0.dev3"));
+        assert!(!is_prerelease("2.0.0"));
+        assert!(!is_prerelease("2.0.post1"));
+
+        let spec = |op: &str, version: &str| VersionSpecifier { operator: op.to_string(), version: version.to_string() };
+        assert!(mentions_prerelease(&[spec(">=", "2.0b1")]));
+        assert!(!mentions_prerelease(&[spec(">=", "2.0"), spec("<", "3")]));
+    }
+}
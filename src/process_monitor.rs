@@ -1,5 +1,6 @@
 use anyhow::Result;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -8,6 +9,7 @@ use tokio::fs;
 use tokio::sync::RwLock;
 use tokio::time::sleep;
 use snakegg::native::datetime::DateTime;
+use crate::logger;
 
 #[derive(Debug, Clone)]
 pub struct ProcessInfo {
@@ -43,11 +45,11 @@ impl ProcessMonitor {
     }
 
     pub async fn start_monitoring(&self, check_interval: Duration) -> Result<()> {
-        println!("🔍 Starting process monitoring...");
+        logger::info("🔍 Starting process monitoring...");
         
         loop {
             if let Err(e) = self.scan_processes().await {
-                eprintln!("Error scanning processes: {}", e);
+                logger::error(format!("Error scanning processes: {}", e));
             }
             
             sleep(check_interval).await;
@@ -82,7 +84,7 @@ impl ProcessMonitor {
             if let Some(process) = system.process(*pid) {
                 if let Some(missing_module) = self.check_process_for_errors(*pid, process).await? {
                     process_info.error_count += 1;
-                    println!("🚨 Detected missing module: {} in process {}", missing_module, pid);
+                    logger::info(format!("🚨 Detected missing module: {} in process {}", missing_module, pid));
                     
                     // Here you would trigger the auto-installation
                     self.handle_missing_module(missing_module, *pid).await?;
@@ -164,7 +166,7 @@ impl ProcessMonitor {
     }
 
     async fn handle_missing_module(&self, module_name: String, pid: Pid) -> Result<()> {
-        println!("🔧 Handling missing module: {} for process {}", module_name, pid);
+        logger::info(format!("🔧 Handling missing module: {} for process {}", module_name, pid));
         
         // Create error record
         let error = ModuleError {
@@ -179,7 +181,7 @@ impl ProcessMonitor {
         self.log_module_error(&error).await?;
 
         // Trigger auto-installation (this would be handled by the daemon)
-        println!("📦 Auto-installing module: {}", module_name);
+        logger::info(format!("📦 Auto-installing module: {}", module_name));
         
         Ok(())
     }
@@ -276,11 +278,69 @@ impl StraceMonitor {
     }
 }
 
+/// The environment (virtualenv or conda env) a Python process runs in; `None` for a base interpreter
+pub fn process_environment(process: &sysinfo::Process) -> Option<PathBuf> {
+    interpreter_environment(process.cmd(), process.environ(), process.exe(), process.cwd())
+}
+
+/// A venv's interpreter is a symlink, so the resolved executable is the base Python: how the
+/// process was started (argv[0], else `VIRTUAL_ENV`) says more. The executable still finds
+/// `--copies` venvs and conda environments.
+fn interpreter_environment(cmd: &[String], environ: &[String], exe: Option<&Path>, cwd: Option<&Path>) -> Option<PathBuf> {
+    let started = cmd.first()
+        .map(PathBuf::from)
+        .filter(|argv0| argv0.components().count() > 1)
+        .map(|argv0| match cwd {
+            Some(cwd) if argv0.is_relative() => cwd.join(argv0),
+            _ => argv0,
+        });
+    let from_start = match started {
+        // Started by path: that interpreter is the one running
+        Some(interpreter) => interpreter_prefix(&interpreter),
+        // Found on PATH, which an activated environment puts first
+        None => environ.iter()
+            .find_map(|var| var.strip_prefix("VIRTUAL_ENV="))
+            .map(PathBuf::from)
+            .filter(|prefix| is_environment(prefix)),
+    };
+    from_start.or_else(|| exe.and_then(interpreter_prefix))
+}
+
+/// `<env>/bin/python` (`<env>\Scripts\python.exe`) to `<env>`, if that is an environment
+fn interpreter_prefix(interpreter: &Path) -> Option<PathBuf> {
+    let prefix = interpreter.parent()?.parent()?;
+    is_environment(prefix).then(|| prefix.to_path_buf())
+}
+
+pub fn is_environment(prefix: &Path) -> bool {
+    prefix.join("pyvenv.cfg").exists() || prefix.join("conda-meta").is_dir()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-
+    #[test]
+    fn test_interpreter_environment() {
+        let root = std::env::temp_dir().join(format!("snakepit-process-env-{}", std::process::id()));
+        let venv = root.join(".venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        let args = |argv0: &str| vec![argv0.to_string(), "app.py".to_string()];
+        let base = Path::new("/usr/bin/python3.12");
+        let activated = vec![format!("VIRTUAL_ENV={}", venv.display())];
+
+        let absolute = venv.join("bin").join("python").display().to_string();
+        assert_eq!(interpreter_environment(&args(&absolute), &[], Some(base), None), Some(venv.clone()));
+        assert_eq!(interpreter_environment(&args(".venv/bin/python"), &[], Some(base), Some(&root)), Some(venv.clone()));
+        assert_eq!(interpreter_environment(&args("python"), &activated, Some(base), None), Some(venv.clone()));
+        // Running the system interpreter by path inside an activated shell
+        assert_eq!(interpreter_environment(&args("/usr/bin/python3"), &activated, Some(base), None), None);
+        let copied = venv.join("bin").join("python3");
+        assert_eq!(interpreter_environment(&args("python"), &[], Some(&copied), None), Some(venv));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 
     #[tokio::test]
     async fn test_process_monitor_creation() {
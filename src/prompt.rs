@@ -0,0 +1,164 @@
+use anyhow::Result;
+use std::io::{BufRead, IsTerminal};
+use std::sync::RwLock;
+use snakegg::native::style::{bold, dim};
+use crate::config::SnakepitConfig;
+
+/// How prompts get their answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    /// Read the answer from the terminal
+    Ask,
+    /// `--yes`: every confirmation is answered yes
+    AssumeYes,
+    /// `--no-input`, or stdin isn't a terminal: every prompt takes its default answer
+    NoInput,
+}
+
+impl Interaction {
+    /// `assume_yes` wins over `no_input`; with neither, ask only when stdin is a terminal
+    pub fn from_config(config: &SnakepitConfig) -> Self {
+        if config.assume_yes == Some(true) {
+            Interaction::AssumeYes
+        } else if config.no_input == Some(true) || !std::io::stdin().is_terminal() {
+            Interaction::NoInput
+        } else {
+            Interaction::Ask
+        }
+    }
+}
+
+/// Picked once in `main` from the flags and config; tests and library callers get `Ask`
+static MODE: RwLock<Interaction> = RwLock::new(Interaction::Ask);
+
+pub fn set_mode(mode: Interaction) {
+    *MODE.write().unwrap_or_else(|e| e.into_inner()) = mode;
+}
+
+pub fn mode() -> Interaction {
+    *MODE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A yes/no question; `default` is the answer to Enter and, without a terminal, to everything.
+///
+/// The `[y/N]` hint is added here so it always matches the default.
+pub fn confirm(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    eprintln!("{}", bold(format!("{} {}", question, hint)));
+    let answer = match mode() {
+        Interaction::AssumeYes => Some(true),
+        Interaction::NoInput => None,
+        Interaction::Ask => read_answer()?.and_then(|input| parse_yes_no(&input)),
+    };
+    let answer = answer.unwrap_or(default);
+    if mode() != Interaction::Ask {
+        eprintln!("{}", dim(format!("  {} ({})", if answer { "yes" } else { "no" }, describe(mode()))));
+    }
+    Ok(answer)
+}
+
+/// A free-form answer; `None` without a terminal, for the caller's default
+pub fn ask(question: &str) -> Result<Option<String>> {
+    eprintln!("{}", bold(question));
+    match mode() {
+        Interaction::Ask => read_answer(),
+        other => {
+            eprintln!("{}", dim(format!("  default answer ({})", describe(other))));
+            Ok(None)
+        }
+    }
+}
+
+/// One of `choices`, by number or name; Enter (and no terminal) takes `default`
+pub fn choose(question: &str, choices: &[String], default: usize) -> Result<String> {
+    eprintln!("{}", bold(question));
+    for (i, choice) in choices.iter().enumerate() {
+        let marker = if i == default { " (default)" } else { "" };
+        eprintln!("  {}) {}{}", i + 1, choice, dim(marker));
+    }
+    if mode() != Interaction::Ask {
+        eprintln!("{}", dim(format!("  {} ({})", choices[default], describe(mode()))));
+        return Ok(choices[default].clone());
+    }
+    loop {
+        match read_answer()? {
+            Some(input) if input.is_empty() => return Ok(choices[default].clone()),
+            Some(input) => match parse_choice(&input, choices) {
+                Some(i) => return Ok(choices[i].clone()),
+                None => eprintln!("{}", dim(format!("  Pick 1-{} or a name from the list", choices.len()))),
+            },
+            None => return Ok(choices[default].clone()),
+        }
+    }
+}
+
+/// "Do this, then press Enter": whether to go on. Nobody can act on the
+/// message without a terminal, so then it's always `false` (even with `--yes`).
+pub fn wait_for_enter(message: &str) -> Result<bool> {
+    eprintln!("{}", dim(message));
+    match mode() {
+        Interaction::Ask => Ok(read_answer()?.is_some()),
+        _ => Ok(false),
+    }
+}
+
+fn describe(mode: Interaction) -> &'static str {
+    match mode {
+        Interaction::AssumeYes => "--yes",
+        _ => "no terminal input",
+    }
+}
+
+/// A line from stdin without its newline; `None` at end of input
+fn read_answer() -> Result<Option<String>> {
+    let mut input = String::new();
+    let read = std::io::stdin().lock().read_line(&mut input)?;
+    Ok((read > 0).then(|| input.trim().to_string()))
+}
+
+/// `y`/`yes`/`n`/`no` in any case; `None` (the default) for anything else, including Enter
+fn parse_yes_no(input: &str) -> Option<bool> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// A 1-based number or a choice's name in any case
+fn parse_choice(input: &str, choices: &[String]) -> Option<usize> {
+    let input = input.trim();
+    match input.parse::<usize>() {
+        Ok(n) => (1..=choices.len()).contains(&n).then(|| n - 1),
+        Err(_) => choices.iter().position(|choice| choice.eq_ignore_ascii_case(input)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_answers() {
+        assert_eq!(parse_yes_no("Y"), Some(true));
+        assert_eq!(parse_yes_no(" yes "), Some(true));
+        assert_eq!(parse_yes_no("No"), Some(false));
+        assert_eq!(parse_yes_no(""), None);
+        assert_eq!(parse_yes_no("maybe"), None);
+
+        let choices = vec!["src".to_string(), "flat".to_string()];
+        assert_eq!(parse_choice("2", &choices), Some(1));
+        assert_eq!(parse_choice("FLAT", &choices), Some(1));
+        assert_eq!(parse_choice("3", &choices), None);
+        assert_eq!(parse_choice("0", &choices), None);
+    }
+
+    #[test]
+    fn test_mode_from_config() {
+        let mut config = SnakepitConfig::default();
+        config.no_input = Some(true);
+        assert_eq!(Interaction::from_config(&config), Interaction::NoInput);
+        config.assume_yes = Some(true);
+        assert_eq!(Interaction::from_config(&config), Interaction::AssumeYes);
+    }
+}
@@ -0,0 +1,310 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use snakegg::native::dirs;
+use crate::manifest::canonical_name;
+
+/// Parallel installs in one process share the registry file; serialize load-modify-save
+static REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Identifier shared by everything one snakepit invocation installs
+pub fn transaction_id() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        format!("{}-{}", secs, std::process::id())
+    })
+}
+
+/// Where one installed file came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileOwner {
+    pub package: String,
+    pub version: String,
+    pub transaction: String,
+    /// Download URL of the artifact; `None` when only a dist-info RECORD vouches for the file
+    pub source: Option<String>,
+    pub installed_at: u64,
+}
+
+/// Per-environment map of installed file -> the install that wrote it.
+///
+/// Lives inside the venv (`<venv>/.snakepit/provenance.json`) so it travels and
+/// dies with the environment; user-site installs keep theirs in the data dir.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProvenanceRegistry {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    site_packages: PathBuf,
+    pub files: BTreeMap<PathBuf, FileOwner>,
+}
+
+impl ProvenanceRegistry {
+    pub fn for_site_packages(site_packages: &Path) -> Result<Self> {
+        let path = Self::registry_path(site_packages)?;
+
+        let mut registry = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str::<ProvenanceRegistry>(&content)?
+        } else {
+            ProvenanceRegistry::default()
+        };
+
+        registry.path = path;
+        registry.site_packages = site_packages.to_path_buf();
+        Ok(registry)
+    }
+
+    fn registry_path(site_packages: &Path) -> Result<PathBuf> {
+        if let Some(venv) = site_packages.ancestors().find(|dir| dir.join("pyvenv.cfg").exists()) {
+            return Ok(venv.join(".snakepit").join("provenance.json"));
+        }
+
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        let key = snakegg::native::hash::compute_hex(site_packages.to_string_lossy().as_bytes());
+        Ok(data_dir.join("snakepit").join("provenance").join(format!("{}.json", &key[..16])))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    /// Record the files one package install wrote, replacing whatever that package owned before
+    pub fn record_install(site_packages: &Path, package: &str, version: &str, source: Option<&str>, files: &[PathBuf]) -> Result<()> {
+        let _guard = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut registry = Self::for_site_packages(site_packages)?;
+        registry.forget_package(package);
+
+        let installed_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        for file in files {
+            registry.files.insert(normalize_path(file), FileOwner {
+                package: canonical_name(package),
+                version: version.to_string(),
+                transaction: transaction_id().to_string(),
+                source: source.map(|s| s.to_string()),
+                installed_at,
+            });
+        }
+        registry.save()
+    }
+
+    /// Drop a package's files after it has been uninstalled
+    pub fn record_uninstall(site_packages: &Path, package: &str) -> Result<()> {
+        let _guard = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut registry = Self::for_site_packages(site_packages)?;
+        if registry.forget_package(package) > 0 {
+            registry.save()?;
+        }
+        Ok(())
+    }
+
+    /// Delete the files this process installed for `package` and forget them.
+    ///
+    /// Only the current transaction's files are touched; anything an earlier
+    /// install wrote (and this one overwrote) is gone, not restored.
+    pub fn rollback_install(site_packages: &Path, package: &str) -> Result<usize> {
+        let _guard = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut registry = Self::for_site_packages(site_packages)?;
+        let package = canonical_name(package);
+
+        let files: Vec<PathBuf> = registry.files.iter()
+            .filter(|(_, owner)| owner.package == package && owner.transaction == transaction_id())
+            .map(|(path, _)| path.clone())
+            .collect();
+        for file in &files {
+            if let Err(e) = std::fs::remove_file(file) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(anyhow::anyhow!("Could not remove {}: {}", file.display(), e));
+                }
+            }
+            registry.files.remove(file);
+            if let Some(parent) = file.parent() {
+                prune_empty_dirs(parent, &normalize_path(site_packages));
+            }
+        }
+
+        registry.save()?;
+        Ok(files.len())
+    }
+
+    fn forget_package(&mut self, package: &str) -> usize {
+        let package = canonical_name(package);
+        let before = self.files.len();
+        self.files.retain(|_, owner| owner.package != package);
+        before - self.files.len()
+    }
+
+    /// Who installed `path`: snakepit's own records first, then any dist-info RECORD listing it
+    pub fn owner(&self, path: &Path) -> Option<FileOwner> {
+        let path = normalize_path(path);
+        self.files.get(&path)
+            .cloned()
+            .or_else(|| self.record_owners().remove(&path))
+    }
+
+    /// Every file some package claims, whether snakepit or another installer wrote it
+    pub fn tracked_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.files.keys()
+            .cloned()
+            .chain(self.record_owners().into_keys())
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Files under site-packages that no package claims, ignoring bytecode caches
+    pub fn unowned_files(&self) -> Result<Vec<PathBuf>> {
+        let recorded = self.record_owners();
+        let mut unowned = Vec::new();
+        let mut stack = vec![self.site_packages.clone()];
+
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(&dir)?.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if entry.file_name() != "__pycache__" {
+                        stack.push(path);
+                    }
+                    continue;
+                }
+                let key = normalize_path(&path);
+                if !self.files.contains_key(&key) && !recorded.contains_key(&key) {
+                    unowned.push(path);
+                }
+            }
+        }
+
+        unowned.sort();
+        Ok(unowned)
+    }
+
+    /// Ownership according to the RECORD files of every installed distribution,
+    /// which covers packages installed by pip, uv or anything else
+    fn record_owners(&self) -> BTreeMap<PathBuf, FileOwner> {
+        let mut owners = BTreeMap::new();
+        let Ok(entries) = std::fs::read_dir(&self.site_packages) else { return owners };
+
+        for entry in entries.flatten() {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            let Some(stem) = dir_name.strip_suffix(".dist-info") else { continue };
+            let Some((name, version)) = stem.split_once('-') else { continue };
+            let Ok(record) = std::fs::read_to_string(entry.path().join("RECORD")) else { continue };
+
+            for line in record.lines() {
+                // path,hash,size; paths containing commas are quoted
+                let file = match line.strip_prefix('"') {
+                    Some(quoted) => quoted.split('"').next().unwrap_or(""),
+                    None => line.split(',').next().unwrap_or(""),
+                };
+                if file.is_empty() {
+                    continue;
+                }
+                owners.insert(normalize_path(&self.site_packages.join(file)), FileOwner {
+                    package: canonical_name(name),
+                    version: version.to_string(),
+                    transaction: "dist-info RECORD".to_string(),
+                    source: None,
+                    installed_at: 0,
+                });
+            }
+        }
+        owners
+    }
+}
+
+/// Remove `dir` and its ancestors below `stop` while they hold nothing but bytecode caches
+fn prune_empty_dirs(dir: &Path, stop: &Path) {
+    let mut dir = dir.to_path_buf();
+    while dir.starts_with(stop) && dir != stop {
+        let Ok(entries) = std::fs::read_dir(&dir) else { return };
+        if !entries.flatten().all(|entry| entry.file_name() == "__pycache__") {
+            return;
+        }
+        if std::fs::remove_dir_all(&dir).is_err() {
+            return;
+        }
+        if !dir.pop() {
+            return;
+        }
+    }
+}
+
+/// Absolute, `..`-free form of a path; symlinks are resolved when the file exists
+pub fn normalize_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            std::path::Component::ParentDir => { normalized.pop(); }
+            std::path::Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owner_lookup_and_unowned_files() {
+        let venv = std::env::temp_dir().join(format!("snakepit-provenance-{}", std::process::id()));
+        let site = venv.join("lib").join("python3.11").join("site-packages");
+        std::fs::create_dir_all(site.join("demo")).unwrap();
+        std::fs::create_dir_all(site.join("other-1.0.dist-info")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        std::fs::write(site.join("demo").join("__init__.py"), "").unwrap();
+        std::fs::write(site.join("other.py"), "").unwrap();
+        std::fs::write(site.join("stray.py"), "").unwrap();
+        std::fs::write(site.join("other-1.0.dist-info").join("RECORD"), "other.py,sha256=x,0\nother-1.0.dist-info/RECORD,,\n").unwrap();
+
+        let init = site.join("demo").join("__init__.py");
+        ProvenanceRegistry::record_install(&site, "Demo", "2.0", Some("https://files.example/demo.whl"), std::slice::from_ref(&init)).unwrap();
+        assert!(venv.join(".snakepit").join("provenance.json").exists());
+
+        let registry = ProvenanceRegistry::for_site_packages(&site).unwrap();
+        let owner = registry.owner(&init).unwrap();
+        assert_eq!((owner.package.as_str(), owner.version.as_str()), ("demo", "2.0"));
+        assert_eq!(owner.transaction, transaction_id());
+        assert_eq!(registry.owner(&site.join("other.py")).unwrap().package, "other");
+        assert_eq!(registry.unowned_files().unwrap(), vec![site.join("stray.py")]);
+
+        let broken = site.join("broken").join("__init__.py");
+        std::fs::create_dir_all(site.join("broken").join("__pycache__")).unwrap();
+        std::fs::write(&broken, "raise ImportError").unwrap();
+        ProvenanceRegistry::record_install(&site, "broken", "0.1", None, std::slice::from_ref(&broken)).unwrap();
+        assert_eq!(ProvenanceRegistry::rollback_install(&site, "Broken").unwrap(), 1);
+        assert!(!site.join("broken").exists());
+        assert!(site.exists());
+
+        ProvenanceRegistry::record_uninstall(&site, "demo").unwrap();
+        assert!(ProvenanceRegistry::for_site_packages(&site).unwrap().owner(&init).is_none());
+
+        std::fs::remove_dir_all(&venv).unwrap();
+    }
+}
@@ -0,0 +1,442 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+use crate::package_info::metadata_headers;
+
+pub const PYPI_UPLOAD_URL: &str = "https://upload.pypi.org/legacy/";
+pub const TESTPYPI_UPLOAD_URL: &str = "https://test.pypi.org/legacy/";
+
+/// Token for the upload index, used with the `__token__` username
+pub const TOKEN_ENV: &str = "SNAKEPIT_PUBLISH_TOKEN";
+pub const USERNAME_ENV: &str = "SNAKEPIT_PUBLISH_USERNAME";
+pub const PASSWORD_ENV: &str = "SNAKEPIT_PUBLISH_PASSWORD";
+
+/// METADATA headers that may repeat, and the upload form field each goes to
+const MULTI_VALUED: &[(&str, &str)] = &[
+    ("Classifier", "classifiers"),
+    ("Requires-Dist", "requires_dist"),
+    ("Provides-Extra", "provides_extra"),
+    ("Project-URL", "project_urls"),
+    ("Platform", "platform"),
+    ("Dynamic", "dynamic"),
+    ("License-File", "license_file"),
+    ("Supported-Platform", "supported_platform"),
+];
+
+/// Where to upload: `pypi`, `testpypi`, or the URL of an index's upload endpoint
+pub fn upload_url(index: &str) -> Result<Url> {
+    let url = match index {
+        "pypi" => PYPI_UPLOAD_URL,
+        "testpypi" => TESTPYPI_UPLOAD_URL,
+        url => url,
+    };
+    Url::parse(url).map_err(|e| anyhow!("Invalid upload URL '{}': {}", url, e))
+}
+
+/// A built sdist or wheel and the core metadata the upload API wants alongside it
+#[derive(Debug, Clone, Serialize)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub filename: String,
+    pub name: String,
+    pub version: String,
+    /// `sdist` or `bdist_wheel`
+    pub filetype: &'static str,
+    /// `source` for an sdist, the wheel's Python tag otherwise
+    pub pyversion: String,
+    pub sha256: String,
+    pub size: u64,
+    #[serde(skip)]
+    headers: Vec<(String, String)>,
+    #[serde(skip)]
+    description: String,
+}
+
+impl Artifact {
+    /// Read `path` and its METADATA (wheel) or PKG-INFO (sdist), checking they agree with
+    /// the filename
+    pub fn inspect(path: &Path) -> Result<Self> {
+        let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let (filetype, pyversion, file_name, file_version, metadata) = if let Some(stem) = filename.strip_suffix(".whl") {
+            let parts: Vec<&str> = stem.split('-').collect();
+            if parts.len() < 5 {
+                return Err(anyhow!("{} is not a valid wheel filename", filename));
+            }
+            ("bdist_wheel", parts[parts.len() - 3].to_string(), parts[0], parts[1], wheel_metadata(&bytes)?)
+        } else if let Some(stem) = filename.strip_suffix(".tar.gz").or_else(|| filename.strip_suffix(".zip")) {
+            let (name, version) = stem.rsplit_once('-')
+                .ok_or_else(|| anyhow!("{} is not a valid sdist filename", filename))?;
+            ("sdist", "source".to_string(), name, version, sdist_metadata(&filename, &bytes)?)
+        } else {
+            return Err(anyhow!("{} is neither a wheel nor an sdist", filename));
+        };
+
+        let headers = metadata_headers(&metadata);
+        let header = |key: &str| headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.clone())
+            .filter(|value| !value.is_empty());
+        let name = header("Name").ok_or_else(|| anyhow!("{} has no Name in its metadata", filename))?;
+        let version = header("Version").ok_or_else(|| anyhow!("{} has no Version in its metadata", filename))?;
+        if header("Metadata-Version").is_none() {
+            return Err(anyhow!("{} has no Metadata-Version in its metadata", filename));
+        }
+        if canonical_name(file_name) != canonical_name(&name) {
+            return Err(anyhow!("{} is named for '{}' but its metadata says '{}'", filename, file_name, name));
+        }
+        if file_version != version {
+            return Err(anyhow!("{} is named for version {} but its metadata says {}", filename, file_version, version));
+        }
+
+        let description = header("Description").unwrap_or_else(|| metadata_body(&metadata));
+        Ok(Self {
+            path: path.to_path_buf(),
+            filename,
+            name,
+            version,
+            filetype,
+            pyversion,
+            sha256: snakegg::native::hash::compute_sha256_hex(&bytes),
+            size: bytes.len() as u64,
+            headers,
+            description,
+        })
+    }
+
+    /// The upload API's form fields other than the file itself, in order
+    pub fn form_fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            (":action".to_string(), "file_upload".to_string()),
+            ("protocol_version".to_string(), "1".to_string()),
+            ("name".to_string(), self.name.clone()),
+            ("version".to_string(), self.version.clone()),
+            ("filetype".to_string(), self.filetype.to_string()),
+            ("pyversion".to_string(), self.pyversion.clone()),
+            ("sha256_digest".to_string(), self.sha256.clone()),
+        ];
+        for (key, value) in &self.headers {
+            if ["Name", "Version", "Description"].iter().any(|skip| key.eq_ignore_ascii_case(skip)) {
+                continue;
+            }
+            let field = MULTI_VALUED.iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(key))
+                .map(|(_, field)| field.to_string())
+                .unwrap_or_else(|| key.to_ascii_lowercase().replace('-', "_"));
+            fields.push((field, value.clone()));
+        }
+        if !self.description.is_empty() {
+            fields.push(("description".to_string(), self.description.clone()));
+        }
+        fields
+    }
+}
+
+/// The files of `dist_dir` there is something to upload from: wheels and sdists, sorted
+pub fn find_artifacts(dist_dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dist_dir)
+        .with_context(|| format!("No {} to publish from; run `snakepit build` first", dist_dir.display()))?;
+    let mut files: Vec<PathBuf> = entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.ends_with(".whl") || name.ends_with(".tar.gz") || name.ends_with(".zip")
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn wheel_metadata(bytes: &[u8]) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let name = archive.file_names()
+        .find(|name| name.ends_with(".dist-info/METADATA") && name.matches('/').count() == 1)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("The wheel has no .dist-info/METADATA"))?;
+    let mut metadata = String::new();
+    archive.by_name(&name)?.read_to_string(&mut metadata)?;
+    Ok(metadata)
+}
+
+fn sdist_metadata(filename: &str, bytes: &[u8]) -> Result<String> {
+    let is_pkg_info = |name: &str| name.trim_start_matches("./").matches('/').count() == 1 && name.ends_with("/PKG-INFO");
+    let mut metadata = String::new();
+    if filename.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+        let name = archive.file_names().find(|name| is_pkg_info(name)).map(str::to_string)
+            .ok_or_else(|| anyhow!("{} has no PKG-INFO", filename))?;
+        archive.by_name(&name)?.read_to_string(&mut metadata)?;
+        return Ok(metadata);
+    }
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bytes));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if is_pkg_info(&entry.path()?.to_string_lossy()) {
+            entry.read_to_string(&mut metadata)?;
+            return Ok(metadata);
+        }
+    }
+    Err(anyhow!("{} has no PKG-INFO", filename))
+}
+
+/// The description in a METADATA body (Metadata-Version 2.1+), after the headers
+fn metadata_body(metadata: &str) -> String {
+    let normalized = metadata.replace("\r\n", "\n");
+    normalized.split_once("\n\n").map(|(_, body)| body.trim_end().to_string()).unwrap_or_default()
+}
+
+/// Username and password for the upload API
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+    /// Where they came from, for messages
+    pub source: &'static str,
+}
+
+impl Credentials {
+    fn token(token: String, source: &'static str) -> Self {
+        Self { username: "__token__".to_string(), password: token, source }
+    }
+
+    /// The first of: `--token`, `SNAKEPIT_PUBLISH_TOKEN`, `SNAKEPIT_PUBLISH_USERNAME`/`_PASSWORD`,
+    /// a trusted-publisher token minted from CI's OIDC identity, and the `keyring` CLI
+    pub async fn find(client: &Client, upload_url: &Url, token: Option<&str>) -> Result<Self> {
+        if let Some(token) = token {
+            return Ok(Self::token(token.to_string(), "--token"));
+        }
+        if let Some(token) = env_value(TOKEN_ENV) {
+            return Ok(Self::token(token, TOKEN_ENV));
+        }
+        if let (Some(username), Some(password)) = (env_value(USERNAME_ENV), env_value(PASSWORD_ENV)) {
+            return Ok(Self { username, password, source: USERNAME_ENV });
+        }
+        if let Some(token) = trusted_publishing_token(client, upload_url).await? {
+            return Ok(Self::token(token, "trusted publishing"));
+        }
+        if let Some(token) = keyring_password(upload_url) {
+            return Ok(Self::token(token, "keyring"));
+        }
+        Err(anyhow!(
+            "No credentials for {}: pass --token, set {}, run in CI with trusted publishing, or store a token with `keyring set {} __token__`",
+            upload_url, TOKEN_ENV, upload_url
+        ))
+    }
+}
+
+fn env_value(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+/// The token `keyring get <url> __token__` prints, when the keyring CLI is installed and has one
+fn keyring_password(upload_url: &Url) -> Option<String> {
+    let output = std::process::Command::new("keyring")
+        .args(["get", upload_url.as_str(), "__token__"])
+        .output()
+        .ok()?;
+    let password = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !password.is_empty()).then_some(password)
+}
+
+/// The index a trusted publisher exchanges tokens with: pypi.org for upload.pypi.org,
+/// otherwise the upload URL's own origin
+fn oidc_base(upload_url: &Url) -> Result<Url> {
+    let mut base = upload_url.clone();
+    if upload_url.host_str() == Some("upload.pypi.org") {
+        base.set_host(Some("pypi.org"))?;
+    }
+    base.set_path("/");
+    base.set_query(None);
+    Ok(base)
+}
+
+#[derive(Deserialize)]
+struct OidcAudience {
+    audience: String,
+}
+
+#[derive(Deserialize)]
+struct OidcIdToken {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct MintedToken {
+    token: String,
+}
+
+/// Trusted publishing: trade the GitHub Actions OIDC identity for a short-lived upload
+/// token. `None` outside a workflow with `id-token: write`.
+async fn trusted_publishing_token(client: &Client, upload_url: &Url) -> Result<Option<String>> {
+    let (Some(request_url), Some(request_token)) = (env_value("ACTIONS_ID_TOKEN_REQUEST_URL"), env_value("ACTIONS_ID_TOKEN_REQUEST_TOKEN")) else {
+        return Ok(None);
+    };
+    let base = oidc_base(upload_url)?;
+    let audience: OidcAudience = client.get(base.join("_/oidc/audience")?)
+        .send().await?
+        .error_for_status()
+        .context("The index doesn't support trusted publishing")?
+        .json().await?;
+
+    let mut id_token_url = Url::parse(&request_url)?;
+    id_token_url.query_pairs_mut().append_pair("audience", &audience.audience);
+    let id_token: OidcIdToken = client.get(id_token_url)
+        .bearer_auth(request_token)
+        .send().await?
+        .error_for_status()
+        .context("Failed to get an OIDC token from GitHub Actions")?
+        .json().await?;
+
+    let response = client.post(base.join("_/oidc/mint-token")?)
+        .json(&serde_json::json!({ "token": id_token.value }))
+        .send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("The index refused the trusted publisher ({}): {}", status, body.trim()));
+    }
+    let minted: MintedToken = response.json().await?;
+    Ok(Some(minted.token))
+}
+
+/// How an upload went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UploadOutcome {
+    Uploaded,
+    /// The index already has this file and `--skip-existing` was given
+    Skipped,
+}
+
+/// What `snakepit publish` did, for `--json`
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishReport {
+    pub url: String,
+    pub dry_run: bool,
+    pub files: Vec<PublishedFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedFile {
+    #[serde(flatten)]
+    pub artifact: Artifact,
+    /// `None` on a dry run
+    pub outcome: Option<UploadOutcome>,
+}
+
+pub struct Uploader {
+    client: Client,
+    url: Url,
+    credentials: Credentials,
+    skip_existing: bool,
+}
+
+impl Uploader {
+    pub fn new(client: Client, url: Url, credentials: Credentials) -> Self {
+        Self { client, url, credentials, skip_existing: false }
+    }
+
+    pub fn with_skip_existing(mut self, skip_existing: bool) -> Self {
+        self.skip_existing = skip_existing;
+        self
+    }
+
+    pub async fn upload(&self, artifact: &Artifact) -> Result<UploadOutcome> {
+        let mut form = Form::new();
+        for (field, value) in artifact.form_fields() {
+            form = form.text(field, value);
+        }
+        let contents = std::fs::read(&artifact.path)?;
+        form = form.part("content", Part::bytes(contents)
+            .file_name(artifact.filename.clone())
+            .mime_str("application/octet-stream")?);
+
+        let response = self.client.post(self.url.clone())
+            .basic_auth(&self.credentials.username, Some(&self.credentials.password))
+            .multipart(form)
+            .send().await
+            .with_context(|| format!("Failed to upload {}", artifact.filename))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(UploadOutcome::Uploaded);
+        }
+        let body = response.text().await.unwrap_or_default();
+        if self.skip_existing && is_existing_file(status, &body) {
+            return Ok(UploadOutcome::Skipped);
+        }
+        Err(match status {
+            StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => anyhow!(
+                "{} rejected the credentials from {} for {} ({})", self.url, self.credentials.source, artifact.filename, status
+            ),
+            _ => anyhow!("Uploading {} failed ({}): {}", artifact.filename, status, first_line(&body)),
+        })
+    }
+}
+
+/// Warehouse answers a re-upload with 400 "File already exists"; other indexes use 409
+fn is_existing_file(status: StatusCode, body: &str) -> bool {
+    status == StatusCode::CONFLICT
+        || (status == StatusCode::BAD_REQUEST && body.to_ascii_lowercase().contains("already exist"))
+}
+
+fn first_line(body: &str) -> &str {
+    body.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("no details")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const METADATA: &str = "Metadata-Version: 2.1\nName: Demo_Pkg\nVersion: 1.2.0\nSummary: A demo\n\
+        Requires-Python: >=3.9\nClassifier: Programming Language :: Python\nClassifier: License :: OSI Approved :: MIT License\n\
+        Requires-Dist: requests>=2\nDescription-Content-Type: text/markdown\n\n# Demo\n\nLong text.\n";
+
+    fn write_wheel(path: &Path, metadata: &str) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        zip.start_file("demo_pkg/__init__.py", zip::write::FileOptions::default()).unwrap();
+        zip.start_file("demo_pkg-1.2.0.dist-info/METADATA", zip::write::FileOptions::default()).unwrap();
+        zip.write_all(metadata.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_inspects_wheel_and_builds_form() {
+        let dir = std::env::temp_dir().join(format!("snakepit-publish-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wheel = dir.join("demo_pkg-1.2.0-py3-none-any.whl");
+        write_wheel(&wheel, METADATA);
+
+        let artifact = Artifact::inspect(&wheel).unwrap();
+        assert_eq!(artifact.filetype, "bdist_wheel");
+        assert_eq!(artifact.pyversion, "py3");
+        assert_eq!(artifact.sha256, snakegg::native::hash::compute_sha256_hex(&std::fs::read(&wheel).unwrap()));
+        let fields = artifact.form_fields();
+        let values = |key: &str| fields.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect::<Vec<_>>();
+        assert_eq!(values("name"), vec!["Demo_Pkg"]);
+        assert_eq!(values("classifiers").len(), 2);
+        assert_eq!(values("requires_python"), vec![">=3.9"]);
+        assert_eq!(values("description"), vec!["# Demo\n\nLong text."]);
+        assert_eq!(values("description_content_type"), vec!["text/markdown"]);
+
+        let mismatched = dir.join("demo_pkg-1.3.0-py3-none-any.whl");
+        write_wheel(&mismatched, METADATA);
+        assert!(Artifact::inspect(&mismatched).unwrap_err().to_string().contains("1.3.0"));
+        assert_eq!(find_artifacts(&dir).unwrap().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_upload_urls() {
+        assert_eq!(upload_url("testpypi").unwrap().as_str(), TESTPYPI_UPLOAD_URL);
+        assert_eq!(oidc_base(&upload_url("pypi").unwrap()).unwrap().as_str(), "https://pypi.org/");
+        assert_eq!(oidc_base(&upload_url("testpypi").unwrap()).unwrap().as_str(), "https://test.pypi.org/");
+        assert!(upload_url("not a url").is_err());
+        assert!(is_existing_file(StatusCode::BAD_REQUEST, "400 File already exists. See https://pypi.org/help/#file-name-reuse"));
+        assert!(!is_existing_file(StatusCode::BAD_REQUEST, "Invalid value for classifiers"));
+    }
+}
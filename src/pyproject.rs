@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, Item, Value};
+use crate::manifest::{canonical_name, Requirement};
+
+/// A requirement list in pyproject.toml that `add` and `remove` edit
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencyList {
+    /// `[project] dependencies`
+    Main,
+    /// Whichever dev list the project already uses: `[project.optional-dependencies] dev`,
+    /// `[tool.snakepit] dev-dependencies` or (the default) `[dependency-groups] dev`
+    Dev,
+    /// Any other PEP 735 group in `[dependency-groups]`
+    Group(String),
+}
+
+impl DependencyList {
+    pub fn from_args(dev: bool, group: Option<&str>) -> Self {
+        match group.map(canonical_name) {
+            Some(group) if group == "dev" => DependencyList::Dev,
+            Some(group) => DependencyList::Group(group),
+            None if dev => DependencyList::Dev,
+            None => DependencyList::Main,
+        }
+    }
+}
+
+impl std::fmt::Display for DependencyList {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DependencyList::Main => write!(f, "dependencies"),
+            DependencyList::Dev => write!(f, "dev dependencies"),
+            DependencyList::Group(group) => write!(f, "dependency group '{}'", group),
+        }
+    }
+}
+
+/// Where each dev list lives, in the order an existing one is preferred
+const DEV_LISTS: [&[&str]; 4] = [
+    &["project", "optional-dependencies", "dev"],
+    &["tool", "snakepit", "dev-dependencies"],
+    &["tool", "snakepit", "dev_dependencies"],
+    &["dependency-groups", "dev"],
+];
+
+/// Edits the requirement arrays of a pyproject.toml, keeping its comments and layout
+pub struct PyprojectEditor {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl PyprojectEditor {
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let document = content.parse::<DocumentMut>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Self { path: path.to_path_buf(), document })
+    }
+
+    /// Add `requirement` to `list`, replacing an entry for the same package; returns the replaced one
+    pub fn add(&mut self, requirement: &Requirement, list: &DependencyList) -> Result<Option<String>> {
+        let location = self.location(list);
+        let array = self.array_mut(&location)?;
+        let value = Value::from(requirement.to_string());
+
+        if let Some(index) = position(array, &requirement.name) {
+            let previous = array.get(index).and_then(|v| v.as_str()).map(str::to_string);
+            array.replace(index, value);
+            return Ok(previous);
+        }
+
+        // Keep a sorted list sorted; anything else gets the new entry at the end
+        let names: Vec<String> = array.iter().filter_map(entry_name).collect();
+        let index = if names.windows(2).all(|pair| pair[0] <= pair[1]) {
+            names.iter().take_while(|name| **name < requirement.name).count()
+        } else {
+            array.len()
+        };
+        match multiline_indent(array) {
+            Some(indent) => {
+                let mut value = value;
+                value.decor_mut().set_prefix(format!("\n{}", indent));
+                value.decor_mut().set_suffix("");
+                array.insert_formatted(index, value);
+            }
+            None => {
+                array.insert(index, value);
+                array.fmt();
+            }
+        }
+        Ok(None)
+    }
+
+    /// Remove `package` from `list`; returns the removed entry, `None` if it wasn't listed
+    pub fn remove(&mut self, package: &str, list: &DependencyList) -> Option<String> {
+        let location = self.location(list);
+        let array = self.array_mut(&location).ok()?;
+        let index = position(array, &canonical_name(package))?;
+        let multiline = multiline_indent(array).is_some();
+
+        let removed = array.remove(index);
+        if !multiline {
+            array.fmt();
+        }
+        removed.as_str().map(str::to_string)
+    }
+
+    /// The entry for `package` in `list`, if there is one
+    pub fn get(&self, package: &str, list: &DependencyList) -> Option<String> {
+        let array = lookup(self.document.as_item(), &self.location(list))?.as_array()?;
+        let index = position(array, &canonical_name(package))?;
+        array.get(index).and_then(|v| v.as_str()).map(str::to_string)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, self.document.to_string())
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    fn location(&self, list: &DependencyList) -> Vec<String> {
+        let location: &[&str] = match list {
+            DependencyList::Main => &["project", "dependencies"],
+            DependencyList::Group(group) => return vec!["dependency-groups".to_string(), group.clone()],
+            DependencyList::Dev => DEV_LISTS.iter()
+                .find(|location| lookup(self.document.as_item(), location).is_some())
+                .unwrap_or(&DEV_LISTS[3]),
+        };
+        location.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// The array at `location`, created (with any missing tables) when absent
+    fn array_mut(&mut self, location: &[String]) -> Result<&mut Array> {
+        let (last, tables) = location.split_last().expect("locations are never empty");
+        let mut table: &mut dyn toml_edit::TableLike = self.document.as_table_mut();
+        for key in tables {
+            table = table.entry(key)
+                .or_insert(toml_edit::table())
+                .as_table_like_mut()
+                .ok_or_else(|| anyhow!("`{}` in pyproject.toml isn't a table", key))?;
+        }
+        table.entry(last)
+            .or_insert(Item::Value(Value::Array(Array::new())))
+            .as_array_mut()
+            .ok_or_else(|| anyhow!("`{}` in pyproject.toml isn't an array", location.join(".")))
+    }
+}
+
+fn lookup<'a, S: AsRef<str>>(item: &'a Item, location: &[S]) -> Option<&'a Item> {
+    location.iter().try_fold(item, |item, key| item.get(key.as_ref()))
+}
+
+/// The package an array entry requires; PEP 735 `{include-group = ...}` tables have none
+fn entry_name(value: &Value) -> Option<String> {
+    value.as_str().and_then(Requirement::parse).map(|requirement| requirement.name)
+}
+
+fn position(array: &Array, name: &str) -> Option<usize> {
+    array.iter().position(|value| entry_name(value).as_deref() == Some(name))
+}
+
+/// The indentation of an array written one entry per line; `None` for an inline one
+fn multiline_indent(array: &Array) -> Option<String> {
+    let prefix = array.iter().last()?.decor().prefix()?.as_str()?;
+    prefix.rfind('\n').map(|newline| prefix[newline + 1..].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(content: &str) -> PyprojectEditor {
+        PyprojectEditor { path: PathBuf::from("pyproject.toml"), document: content.parse().unwrap() }
+    }
+
+    fn requirement(spec: &str) -> Requirement {
+        Requirement::parse(spec).unwrap()
+    }
+
+    #[test]
+    fn test_adds_and_removes_keeping_layout() {
+        let mut pyproject = editor(r#"[project]
+name = "demo"
+dependencies = [
+    # web
+    "flask>=3",
+    "requests>=2.31",
+]
+
+[project.optional-dependencies]
+dev = ["pytest"]
+"#);
+        assert_eq!(pyproject.add(&requirement("Click>=8"), &DependencyList::Main).unwrap(), None);
+        assert_eq!(pyproject.add(&requirement("requests>=2.32"), &DependencyList::Main).unwrap(), Some("requests>=2.31".to_string()));
+        assert_eq!(pyproject.add(&requirement("ruff"), &DependencyList::Dev).unwrap(), None);
+        assert_eq!(pyproject.add(&requirement("mkdocs"), &DependencyList::from_args(false, Some("docs"))).unwrap(), None);
+        assert_eq!(pyproject.get("REQUESTS", &DependencyList::Main).as_deref(), Some("requests>=2.32"));
+
+        assert_eq!(pyproject.document.to_string(), r#"[project]
+name = "demo"
+dependencies = [
+    "click>=8",
+    # web
+    "flask>=3",
+    "requests>=2.32",
+]
+
+[project.optional-dependencies]
+dev = ["pytest", "ruff"]
+
+[dependency-groups]
+docs = ["mkdocs"]
+"#);
+
+        assert_eq!(pyproject.remove("pytest", &DependencyList::Dev).as_deref(), Some("pytest"));
+        assert_eq!(pyproject.remove("flask", &DependencyList::Main).as_deref(), Some("flask>=3"));
+        assert_eq!(pyproject.remove("flask", &DependencyList::Main), None);
+        assert!(pyproject.document.to_string().contains("dependencies = [\n    \"click>=8\",\n    \"requests>=2.32\",\n]"));
+        assert!(pyproject.document.to_string().contains("dev = [\"ruff\"]"));
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use snakegg::native::dirs;
+use crate::config::SnakepitConfig;
+
+/// Requests per second across every subsystem when `pypi_rate_limit` is unset
+pub const DEFAULT_RATE: f64 = 10.0;
+
+/// Requests in flight at once when `pypi_max_concurrency` is unset
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+
+/// Which part of snakepit a package index request is made for, so `snakepit stats` can attribute it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    Resolver,
+    Installer,
+    Download,
+}
+
+impl Subsystem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Subsystem::Resolver => "resolver",
+            Subsystem::Installer => "installer",
+            Subsystem::Download => "download",
+        }
+    }
+}
+
+/// Index traffic attributed to one subsystem
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubsystemStats {
+    pub requests: u64,
+    /// Requests that had to wait for the budget
+    pub throttled: u64,
+    pub waited_ms: u64,
+}
+
+impl SubsystemStats {
+    fn add(&mut self, other: &SubsystemStats) {
+        self.requests += other.requests;
+        self.throttled += other.throttled;
+        self.waited_ms += other.waited_ms;
+    }
+}
+
+/// A slot in the budget; the request counts against concurrency until this is dropped
+pub struct Permit<'a> {
+    _permit: SemaphorePermit<'a>,
+}
+
+/// Client-side budget every package index consumer draws from: at most
+/// `max_concurrent` requests in flight, started no faster than `rate` per second.
+pub struct RateLimiter {
+    semaphore: Semaphore,
+    /// Zero when the rate is unlimited
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+    stats: Mutex<BTreeMap<String, SubsystemStats>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, max_concurrent: usize) -> Self {
+        let interval = if rate > 0.0 { Duration::from_secs_f64(1.0 / rate) } else { Duration::ZERO };
+        Self {
+            semaphore: Semaphore::new(max_concurrent.max(1)),
+            interval,
+            next_slot: Mutex::new(Instant::now()),
+            stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Wait for a concurrency slot and the next start time, then count the request
+    pub async fn acquire(&self, subsystem: Subsystem) -> Permit<'_> {
+        let started = Instant::now();
+        let permit = self.semaphore.acquire().await.expect("rate limiter semaphore is never closed");
+
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+
+        let waited = started.elapsed();
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = stats.entry(subsystem.as_str().to_string()).or_default();
+        entry.requests += 1;
+        // Scheduling jitter isn't throttling
+        if waited >= Duration::from_millis(5) {
+            entry.throttled += 1;
+            entry.waited_ms += waited.as_millis() as u64;
+        }
+
+        Permit { _permit: permit }
+    }
+
+    /// Traffic counted by this process so far
+    pub fn stats(&self) -> BTreeMap<String, SubsystemStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Set up the shared budget from the config; only the first call has any effect
+pub fn configure(config: &SnakepitConfig) {
+    let _ = LIMITER.set(RateLimiter::new(
+        config.pypi_rate_limit.unwrap_or(DEFAULT_RATE),
+        config.pypi_max_concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+    ));
+}
+
+fn limiter() -> &'static RateLimiter {
+    LIMITER.get_or_init(|| RateLimiter::new(DEFAULT_RATE, DEFAULT_CONCURRENCY))
+}
+
+/// Take a slot in the process-wide budget before talking to a package index
+pub async fn acquire(subsystem: Subsystem) -> Permit<'static> {
+    limiter().acquire(subsystem).await
+}
+
+pub fn stats_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from(".snakepit"))
+        .join("snakepit")
+        .join("pypi-stats.json")
+}
+
+/// Totals recorded by every snakepit process since the last reset
+pub fn load_stats() -> BTreeMap<String, SubsystemStats> {
+    std::fs::read_to_string(stats_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Add this process's traffic to the recorded totals
+pub fn save_stats() -> Result<()> {
+    let Some(limiter) = LIMITER.get() else {
+        return Ok(());
+    };
+    let current = limiter.stats();
+    if current.is_empty() {
+        return Ok(());
+    }
+
+    let mut totals = load_stats();
+    for (subsystem, stats) in &current {
+        totals.entry(subsystem.clone()).or_default().add(stats);
+    }
+
+    let path = stats_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(&totals)?)?;
+    std::fs::rename(&tmp, &path)?;
+
+    // Saved once; a second call must not count it again
+    limiter.stats.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    Ok(())
+}
+
+pub fn reset_stats() -> Result<()> {
+    match std::fs::remove_file(stats_path()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Records this process's index traffic when dropped, however `main` exits
+pub struct StatsGuard;
+
+impl Drop for StatsGuard {
+    fn drop(&mut self) {
+        let _ = save_stats();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_are_spaced_and_attributed() {
+        let limiter = RateLimiter::new(50.0, 2);
+        let started = Instant::now();
+        for _ in 0..3 {
+            drop(limiter.acquire(Subsystem::Resolver).await);
+        }
+        drop(limiter.acquire(Subsystem::Download).await);
+
+        // Four requests at 50/s: the last starts no earlier than 60ms in
+        assert!(started.elapsed() >= Duration::from_millis(60));
+        let stats = limiter.stats();
+        assert_eq!(stats["resolver"].requests, 3);
+        assert_eq!(stats["download"].requests, 1);
+        assert!(stats["resolver"].throttled >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_is_capped() {
+        let limiter = RateLimiter::new(0.0, 1);
+        let held = limiter.acquire(Subsystem::Installer).await;
+        let blocked = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(Subsystem::Download)).await;
+        assert!(blocked.is_err());
+
+        drop(held);
+        let _next = limiter.acquire(Subsystem::Download).await;
+        assert_eq!(limiter.stats()["installer"].requests, 1);
+    }
+}
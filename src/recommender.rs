@@ -156,16 +156,15 @@ Be concise and specific. Focus on practical differences between packages."#,
         println!("\n{}", bold("💡 Based on your query, I recommend starting with:"));
         println!("   {}", bold(green(&recommendations[0].name)));
         
-        println!("\n{}", bold("Install now? [Y/n/1-3]"));
+        println!();
         println!("   {} Install {}", green("Y"), recommendations[0].name);
         println!("   {} Skip", red("n"));
         if recommendations.len() > 1 {
             println!("   {} Install a specific package (1-{})", cyan("1-3"), recommendations.len().min(3));
         }
-        
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
+
+        // Without a terminal this takes the default, the first recommendation
+        let input = crate::prompt::ask("Install now? [Y/n/1-3]")?.unwrap_or_default().to_lowercase();
 
         match input.as_str() {
             "" | "y" | "yes" => Ok(Some(recommendations[0].name.clone())),
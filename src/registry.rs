@@ -0,0 +1,249 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use snakegg::native::dirs;
+use crate::dependency::ProjectDependencies;
+
+/// The environment picked for this process by `--venv`/`--system` or detection;
+/// the inner `None` is the user site
+static CHOSEN: RwLock<Option<Option<PathBuf>>> = RwLock::new(None);
+
+/// A project snakepit has installed into, and the environment it installs into.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegisteredProject {
+    pub root: PathBuf,
+    /// `None` means the user/global site rather than a virtual environment
+    pub environment: Option<PathBuf>,
+}
+
+impl RegisteredProject {
+    /// Direct dependency names declared in this project's manifest
+    pub fn declared_packages(&self) -> Vec<String> {
+        let pyproject = self.root.join("pyproject.toml");
+        let requirements = self.root.join("requirements.txt");
+
+        let deps = if pyproject.exists() {
+            ProjectDependencies::from_pyproject_toml(&pyproject).ok()
+        } else if requirements.exists() {
+            ProjectDependencies::from_requirements_txt(&requirements).ok()
+        } else {
+            None
+        };
+
+        deps.map(|d| {
+            d.dependencies.iter()
+                .chain(d.dev_dependencies.iter())
+                .map(|dep| dep.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+}
+
+/// Registry of every project that shares environments managed by snakepit.
+///
+/// Used to reference-count packages across projects so removing a package from
+/// a shared environment doesn't break a sibling project that still declares it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProjectRegistry {
+    #[serde(skip)]
+    path: PathBuf,
+    pub projects: Vec<RegisteredProject>,
+}
+
+impl ProjectRegistry {
+    pub fn load() -> Result<Self> {
+        let path = Self::get_registry_path()?;
+
+        let mut registry = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str::<ProjectRegistry>(&content)?
+        } else {
+            ProjectRegistry::default()
+        };
+
+        registry.path = path;
+        Ok(registry)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    pub fn get_registry_path() -> Result<PathBuf> {
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        Ok(data_dir.join("snakepit").join("projects.json"))
+    }
+
+    /// The environment commands in this process install into
+    pub fn current_environment() -> Option<PathBuf> {
+        if let Some(chosen) = CHOSEN.read().unwrap_or_else(|e| e.into_inner()).clone() {
+            return chosen;
+        }
+        Self::detect_environment(&std::env::current_dir().ok()?, ".venv")
+    }
+
+    /// Install into `environment` (`None`: the user site) for the rest of this process
+    pub fn choose_environment(environment: Option<PathBuf>) {
+        *CHOSEN.write().unwrap_or_else(|e| e.into_inner()) = Some(environment);
+    }
+
+    /// The active virtualenv, else a venv called `name` in `dir` or the nearest parent with one
+    pub fn detect_environment(dir: &Path, name: &str) -> Option<PathBuf> {
+        std::env::var_os("VIRTUAL_ENV").map(PathBuf::from).or_else(|| {
+            dir.ancestors()
+                .map(|dir| dir.join(name))
+                .find(|venv| venv.join("pyvenv.cfg").exists())
+        })
+    }
+
+    /// Record (or update) a project root and the environment it uses
+    pub fn register(&mut self, root: &Path, environment: Option<PathBuf>) {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+
+        if let Some(existing) = self.projects.iter_mut().find(|p| p.root == root) {
+            existing.environment = environment;
+        } else {
+            self.projects.push(RegisteredProject { root, environment });
+        }
+    }
+
+    /// The environment `root` was last installed into, if it's registered with one
+    pub fn environment_of(&self, root: &Path) -> Option<PathBuf> {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        self.projects.iter()
+            .find(|p| p.root == root)
+            .and_then(|p| p.environment.clone())
+    }
+
+    /// Drop projects whose directories no longer exist
+    pub fn prune_missing(&mut self) -> usize {
+        let before = self.projects.len();
+        self.projects.retain(|p| p.root.exists());
+        before - self.projects.len()
+    }
+
+    pub fn projects_sharing(&self, environment: Option<&Path>) -> Vec<&RegisteredProject> {
+        self.projects.iter()
+            .filter(|p| p.environment.as_deref() == environment)
+            .collect()
+    }
+
+    /// Projects sharing `environment` whose manifests declare `package`.
+    ///
+    /// `exclude` is typically the project the command runs in, whose own
+    /// manifest is handled separately by the caller.
+    pub fn referencing_projects(&self, package: &str, environment: Option<&Path>, exclude: Option<&Path>) -> Vec<PathBuf> {
+        let wanted = Self::normalize(package);
+        let exclude = exclude.map(|e| e.canonicalize().unwrap_or_else(|_| e.to_path_buf()));
+
+        self.projects_sharing(environment)
+            .into_iter()
+            .filter(|p| exclude.as_ref() != Some(&p.root))
+            .filter(|p| p.declared_packages().iter().any(|name| Self::normalize(name) == wanted))
+            .map(|p| p.root.clone())
+            .collect()
+    }
+
+    /// Union of declared packages across every project sharing `environment`, as canonical
+    /// names; `uninstall --cascade` and `autoremove` never treat these as orphans
+    pub fn declared_in_environment(&self, environment: Option<&Path>) -> std::collections::HashSet<String> {
+        self.projects_sharing(environment)
+            .into_iter()
+            .flat_map(|p| p.declared_packages())
+            .map(|name| Self::normalize(&name))
+            .collect()
+    }
+
+    fn normalize(name: &str) -> String {
+        crate::manifest::canonical_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_deduplicates_roots() {
+        let mut registry = ProjectRegistry::default();
+        let root = std::env::temp_dir();
+        registry.register(&root, None);
+        registry.register(&root, Some(PathBuf::from("/tmp/venv")));
+
+        assert_eq!(registry.projects.len(), 1);
+        assert_eq!(registry.projects[0].environment, Some(PathBuf::from("/tmp/venv")));
+    }
+
+    #[test]
+    fn test_referencing_projects_counts_shared_environment_only() {
+        let base = std::env::temp_dir().join(format!("snakepit-registry-{}", std::process::id()));
+        let a = base.join("a");
+        let b = base.join("b");
+        std::fs::create_dir_all(&a).unwrap();
+        std::fs::create_dir_all(&b).unwrap();
+        std::fs::write(a.join("requirements.txt"), "requests>=2.0\n").unwrap();
+        std::fs::write(b.join("requirements.txt"), "Requests\nflask\n").unwrap();
+
+        let env = PathBuf::from("/tmp/shared-venv");
+        let mut registry = ProjectRegistry::default();
+        registry.register(&a, Some(env.clone()));
+        registry.register(&b, None);
+
+        let refs = registry.referencing_projects("requests", Some(&env), None);
+        assert_eq!(refs.len(), 1);
+
+        let refs = registry.referencing_projects("requests", Some(&env), Some(&a));
+        assert!(refs.is_empty());
+
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_declared_in_environment_unions_sharing_projects() {
+        let base = std::env::temp_dir().join(format!("snakepit-registry-union-{}", std::process::id()));
+        let (a, b, c) = (base.join("a"), base.join("b"), base.join("c"));
+        for (root, requirements) in [(&a, "requests\n"), (&b, "Flask_Login\nrequests\n"), (&c, "numpy\n")] {
+            std::fs::create_dir_all(root).unwrap();
+            std::fs::write(root.join("requirements.txt"), requirements).unwrap();
+        }
+
+        let env = PathBuf::from("/tmp/shared-venv");
+        let mut registry = ProjectRegistry::default();
+        registry.register(&a, Some(env.clone()));
+        registry.register(&b, Some(env.clone()));
+        registry.register(&c, None);
+
+        let mut declared: Vec<String> = registry.declared_in_environment(Some(&env)).into_iter().collect();
+        declared.sort();
+        assert_eq!(declared, vec!["flask-login", "requests"]);
+        assert_eq!(registry.declared_in_environment(None).into_iter().collect::<Vec<_>>(), vec!["numpy"]);
+
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_detects_project_venv_in_parents() {
+        if std::env::var_os("VIRTUAL_ENV").is_some() {
+            return; // an active venv always wins
+        }
+        let base = std::env::temp_dir().join(format!("snakepit-registry-venv-{}", std::process::id()));
+        let nested = base.join("src").join("pkg");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(ProjectRegistry::detect_environment(&nested, ".venv"), None);
+
+        std::fs::create_dir_all(base.join(".venv")).unwrap();
+        std::fs::write(base.join(".venv").join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        assert_eq!(ProjectRegistry::detect_environment(&nested, ".venv"), Some(base.join(".venv")));
+        assert_eq!(ProjectRegistry::detect_environment(&nested, "env"), None);
+
+        std::fs::remove_dir_all(&base).ok();
+    }
+}
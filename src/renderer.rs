@@ -0,0 +1,315 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Installer lifecycle events published on the [`EventBus`].
+///
+/// Serialized with an `event` tag, e.g. `{"event":"download_started","name":"requests","total_bytes":62574}`,
+/// which is also the wire format of the `ndjson` renderer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum InstallEvent {
+    PackageQueued { name: String, version: Option<String> },
+    DownloadStarted { name: String, total_bytes: Option<u64> },
+    DownloadFinished { name: String, bytes: u64 },
+    Unpacking { name: String },
+    InstallComplete { name: String },
+    InstallFailed { name: String, error: String },
+    AllDone { succeeded: u32, failed: u32 },
+}
+
+/// A UI that consumes installer events.
+///
+/// Renderers are shared across parallel install tasks, so they take `&self` and
+/// must be `Send + Sync`; use interior mutability for any state.
+pub trait Renderer: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn render(&self, event: &InstallEvent) -> Result<()>;
+
+    /// Called once when the command is done; flush buffers, close pipes
+    fn finish(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds a renderer from the optional argument after `name:` in a renderer spec
+pub type RendererFactory = Box<dyn Fn(Option<&str>) -> Result<Box<dyn Renderer>> + Send + Sync>;
+
+/// Named renderer factories, selected with `--renderer <name>[:<arg>]`
+pub struct RendererRegistry {
+    factories: HashMap<String, RendererFactory>,
+}
+
+impl RendererRegistry {
+    pub fn new() -> Self {
+        Self { factories: HashMap::new() }
+    }
+
+    /// Registry with the renderers that ship with snakepit
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register("classic", Box::new(|_| Ok(Box::new(ClassicRenderer) as Box<dyn Renderer>)));
+        registry.register("ndjson", Box::new(|_| Ok(Box::new(NdjsonRenderer::stdout()) as Box<dyn Renderer>)));
+        registry.register("exec", Box::new(|arg| {
+            let command = arg.ok_or_else(|| anyhow::anyhow!("The exec renderer needs a command, e.g. --renderer 'exec:my-dashboard --port 8080'"))?;
+            Ok(Box::new(ExecRenderer::spawn(command)?) as Box<dyn Renderer>)
+        }));
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, factory: RendererFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.factories.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Instantiate a renderer from a spec like `ndjson` or `exec:./gui.py`
+    pub fn create(&self, spec: &str) -> Result<Box<dyn Renderer>> {
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (spec, None),
+        };
+
+        let factory = self.factories.get(name).ok_or_else(|| {
+            anyhow::anyhow!("Unknown renderer '{}' (available: {})", name, self.names().join(", "))
+        })?;
+        factory(arg)
+    }
+}
+
+impl Default for RendererRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// Fan-out of installer events to every subscribed renderer.
+///
+/// Cheap to clone; clones share the same renderers.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    renderers: Vec<Arc<dyn Renderer>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bus with the renderer named by `spec`, or the classic renderer when unset
+    pub fn from_spec(spec: Option<&str>) -> Result<Self> {
+        let registry = RendererRegistry::with_builtins();
+        let renderer = registry.create(spec.unwrap_or("classic"))?;
+        Ok(Self::new().with_renderer(renderer))
+    }
+
+    pub fn with_renderer(mut self, renderer: Box<dyn Renderer>) -> Self {
+        self.renderers.push(Arc::from(renderer));
+        self
+    }
+
+    pub fn emit(&self, event: InstallEvent) {
+        for renderer in &self.renderers {
+            // A broken UI must never fail the install itself
+            let _ = renderer.render(&event);
+        }
+    }
+
+    pub fn finish(&self) -> Result<()> {
+        for renderer in &self.renderers {
+            renderer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.renderers.iter().map(|r| r.name())).finish()
+    }
+}
+
+/// The default terminal experience. Progress is already written to stderr by
+/// the installer itself, so this renderer deliberately draws nothing extra.
+pub struct ClassicRenderer;
+
+impl Renderer for ClassicRenderer {
+    fn name(&self) -> &str {
+        "classic"
+    }
+
+    fn render(&self, _event: &InstallEvent) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Streams one JSON object per event, each stamped with a millisecond `ts`
+pub struct NdjsonRenderer<W: Write + Send> {
+    out: Mutex<W>,
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    ts: u128,
+    #[serde(flatten)]
+    event: &'a InstallEvent,
+}
+
+fn write_ndjson(out: &mut dyn Write, event: &InstallEvent) -> Result<()> {
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let line = serde_json::to_string(&Envelope { ts, event })?;
+    writeln!(out, "{}", line)?;
+    out.flush()?;
+    Ok(())
+}
+
+impl NdjsonRenderer<std::io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl<W: Write + Send> NdjsonRenderer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out: Mutex::new(out) }
+    }
+
+    #[cfg(test)]
+    pub fn into_inner(self) -> W {
+        self.out.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<W: Write + Send> Renderer for NdjsonRenderer<W> {
+    fn name(&self) -> &str {
+        "ndjson"
+    }
+
+    fn render(&self, event: &InstallEvent) -> Result<()> {
+        let mut out = self.out.lock().map_err(|_| anyhow::anyhow!("Renderer output poisoned"))?;
+        write_ndjson(&mut *out, event)
+    }
+
+    fn finish(&self) -> Result<()> {
+        let mut out = self.out.lock().map_err(|_| anyhow::anyhow!("Renderer output poisoned"))?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Pipes the ndjson stream into an external program's stdin, so a GUI or web
+/// dashboard written in any language can act as the installer UI
+pub struct ExecRenderer {
+    stdin: Mutex<Option<ChildStdin>>,
+    child: Mutex<Option<Child>>,
+}
+
+impl ExecRenderer {
+    pub fn spawn(command: &str) -> Result<Self> {
+        let shell = if cfg!(target_os = "windows") { ("cmd", "/C") } else { ("sh", "-c") };
+        let mut child = Command::new(shell.0)
+            .arg(shell.1)
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start renderer '{}': {}", command, e))?;
+
+        let stdin = child.stdin.take();
+
+        Ok(Self {
+            stdin: Mutex::new(stdin),
+            child: Mutex::new(Some(child)),
+        })
+    }
+}
+
+impl Renderer for ExecRenderer {
+    fn name(&self) -> &str {
+        "exec"
+    }
+
+    fn render(&self, event: &InstallEvent) -> Result<()> {
+        let mut stdin = self.stdin.lock().map_err(|_| anyhow::anyhow!("Renderer output poisoned"))?;
+        match stdin.as_mut() {
+            Some(stdin) => write_ndjson(stdin, event),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(&self) -> Result<()> {
+        // Closing stdin is the renderer's signal that the stream is over
+        if let Ok(mut stdin) = self.stdin.lock() {
+            stdin.take();
+        }
+        let child = self.child.lock().ok().and_then(|mut c| c.take());
+        if let Some(mut child) = child {
+            child.wait()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recording(Mutex<Vec<InstallEvent>>);
+
+    impl Renderer for Recording {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn render(&self, event: &InstallEvent) -> Result<()> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ndjson_lines_are_tagged_and_parseable() {
+        let renderer = NdjsonRenderer::new(Vec::new());
+        renderer.render(&InstallEvent::PackageQueued { name: "requests".to_string(), version: None }).unwrap();
+        renderer.render(&InstallEvent::AllDone { succeeded: 1, failed: 0 }).unwrap();
+
+        let output = String::from_utf8(renderer.into_inner()).unwrap();
+        let lines: Vec<serde_json::Value> = output.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "package_queued");
+        assert_eq!(lines[0]["name"], "requests");
+        assert!(lines[0]["ts"].is_u64());
+        assert_eq!(lines[1]["event"], "all_done");
+    }
+
+    #[test]
+    fn test_registry_resolves_custom_renderers() {
+        let mut registry = RendererRegistry::with_builtins();
+        registry.register("recording", Box::new(|_| Ok(Box::new(Recording(Mutex::new(Vec::new()))) as Box<dyn Renderer>)));
+
+        assert_eq!(registry.create("recording").unwrap().name(), "recording");
+        assert_eq!(registry.create("ndjson").unwrap().name(), "ndjson");
+        assert!(registry.create("exec").is_err());
+        assert!(registry.create("nope").is_err());
+    }
+
+    #[test]
+    fn test_event_bus_fans_out() {
+        let recording = Arc::new(Recording(Mutex::new(Vec::new())));
+        let bus = EventBus { renderers: vec![recording.clone(), recording.clone()] };
+        bus.emit(InstallEvent::Unpacking { name: "flask".to_string() });
+
+        assert_eq!(recording.0.lock().unwrap().len(), 2);
+    }
+}
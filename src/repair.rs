@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+
+/// A file a distribution's RECORD lists that no longer matches it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamagedFile {
+    pub path: PathBuf,
+    pub missing: bool,
+}
+
+/// An installed distribution whose files don't match its RECORD
+#[derive(Debug, Clone)]
+pub struct DamagedPackage {
+    pub name: String,
+    pub version: String,
+    pub files: Vec<DamagedFile>,
+}
+
+/// Check every installed distribution's files against the hashes in its RECORD.
+///
+/// Entries without a hash (RECORD itself, bytecode) and hashes other than sha256
+/// can't be checked and are skipped.
+pub fn verify_site_packages(site_packages: &Path) -> Vec<DamagedPackage> {
+    let Ok(entries) = std::fs::read_dir(site_packages) else {
+        return Vec::new();
+    };
+
+    let mut damaged = Vec::new();
+    for entry in entries.flatten() {
+        let dir_name = entry.file_name().to_string_lossy().to_string();
+        let Some(stem) = dir_name.strip_suffix(".dist-info") else { continue };
+        let Some((name, version)) = stem.split_once('-') else { continue };
+        let Ok(record) = std::fs::read_to_string(entry.path().join("RECORD")) else { continue };
+
+        let files: Vec<DamagedFile> = record.lines()
+            .filter_map(parse_record_line)
+            .filter_map(|(file, expected)| {
+                let path = site_packages.join(file);
+                match std::fs::read(&path) {
+                    Ok(bytes) if record_hash(&bytes) == expected => None,
+                    Ok(_) => Some(DamagedFile { path, missing: false }),
+                    Err(_) => Some(DamagedFile { path, missing: true }),
+                }
+            })
+            .collect();
+        if !files.is_empty() {
+            damaged.push(DamagedPackage { name: canonical_name(name), version: version.to_string(), files });
+        }
+    }
+    damaged.sort_by(|a, b| a.name.cmp(&b.name));
+    damaged
+}
+
+/// `path,sha256=digest,size` to the path and digest; paths containing commas are quoted
+fn parse_record_line(line: &str) -> Option<(&str, &str)> {
+    let (file, rest) = match line.strip_prefix('"') {
+        Some(quoted) => {
+            let (file, rest) = quoted.split_once('"')?;
+            (file, rest.strip_prefix(',')?)
+        }
+        None => line.split_once(',')?,
+    };
+    let digest = rest.split(',').next()?.strip_prefix("sha256=")?;
+    (!file.is_empty() && !digest.is_empty()).then_some((file, digest))
+}
+
+/// The sha256 of `bytes` the way RECORD writes it: urlsafe base64 without padding
+fn record_hash(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let hex = snakegg::native::hash::compute_sha256_hex(bytes);
+    let digest: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect();
+
+    let mut encoded = String::new();
+    for chunk in digest.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |acc, (i, byte)| acc | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_modified_and_missing_files() {
+        let site = std::env::temp_dir().join(format!("snakepit-repair-{}", std::process::id()));
+        let dist_info = site.join("demo-1.0.dist-info");
+        std::fs::create_dir_all(&dist_info).unwrap();
+        std::fs::write(site.join("demo.py"), "hello").unwrap();
+        std::fs::write(site.join("intact.py"), "hello").unwrap();
+        // sha256("hello"), as pip writes it
+        let hash = "sha256=LPJNul-wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ";
+        assert_eq!(record_hash(b"hello"), hash.trim_start_matches("sha256="));
+        std::fs::write(dist_info.join("RECORD"), format!(
+            "demo.py,{hash},5\n\"intact.py\",{hash},5\ngone.py,{hash},5\ndemo-1.0.dist-info/RECORD,,\n"
+        )).unwrap();
+        std::fs::write(site.join("demo.py"), "tampered").unwrap();
+
+        let damaged = verify_site_packages(&site);
+        assert_eq!(damaged.len(), 1);
+        assert_eq!((damaged[0].name.as_str(), damaged[0].version.as_str()), ("demo", "1.0"));
+        assert_eq!(damaged[0].files, vec![
+            DamagedFile { path: site.join("demo.py"), missing: false },
+            DamagedFile { path: site.join("gone.py"), missing: true },
+        ]);
+
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
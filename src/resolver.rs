@@ -1,4 +1,12 @@
 use crate::dependency::{Dependency, ProjectDependencies};
+use crate::rate_limit::Subsystem;
+use crate::upstream::Upstream;
+use crate::constraints::Constraints;
+use crate::prerelease::{self, Prereleases};
+use crate::overrides::Overrides;
+use crate::cache::CacheValidators;
+use crate::manifest::canonical_name;
+use crate::logger;
 use anyhow::Result;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -25,23 +33,42 @@ impl DiskCache {
         Self { root }
     }
 
-    fn get(&self, package: &str) -> Option<PyPIPackageInfo> {
+    fn validators_path(&self, package: &str) -> PathBuf {
+        self.root.join(format!("{}.validators.json", package))
+    }
+
+    /// Cached metadata along with the validators to revalidate it with
+    fn get(&self, package: &str) -> Option<(PyPIPackageInfo, CacheValidators)> {
         let path = self.root.join(format!("{}.json", package));
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(path) {
-                if let Ok(info) = serde_json::from_str(&content) {
-                    return Some(info);
-                }
-            }
-        }
-        None
+        let content = fs::read_to_string(path).ok()?;
+        let info = serde_json::from_str(&content).ok()?;
+        Some((info, CacheValidators::load(&self.validators_path(package))))
     }
 
-    fn set(&self, package: &str, info: &PyPIPackageInfo) {
+    fn set(&self, package: &str, info: &PyPIPackageInfo, validators: &CacheValidators) {
         let path = self.root.join(format!("{}.json", package));
         if let Ok(content) = serde_json::to_string(info) {
             let _ = fs::write(path, content);
         }
+        validators.save(&self.validators_path(package));
+    }
+
+    /// Releases never change, so their dependencies are kept without validators
+    fn requires_dist_path(&self, package: &str, version: &str) -> PathBuf {
+        self.root.join("releases").join(format!("{}-{}.json", package, version))
+    }
+
+    fn get_requires_dist(&self, package: &str, version: &str) -> Option<Vec<String>> {
+        let content = fs::read_to_string(self.requires_dist_path(package, version)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn set_requires_dist(&self, package: &str, version: &str, requires_dist: &[String]) {
+        let path = self.requires_dist_path(package, version);
+        if let (Some(parent), Ok(content)) = (path.parent(), serde_json::to_string(requires_dist)) {
+            let _ = fs::create_dir_all(parent);
+            let _ = fs::write(path, content);
+        }
     }
 }
 
@@ -62,6 +89,12 @@ pub struct PyPIPackageInfo {
     pub releases: HashMap<String, Vec<PyPIRelease>>,
 }
 
+/// `/pypi/{name}/{version}/json`: the same `info`, describing that release
+#[derive(Debug, Clone, Deserialize)]
+struct PyPIReleaseInfo {
+    info: PyPIInfo,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PyPIInfo {
     pub name: String,
@@ -81,12 +114,145 @@ pub struct PyPIRelease {
     pub size: Option<u64>,
     pub upload_time: Option<String>,
     pub digests: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub yanked: bool,
+    #[serde(default)]
+    pub yanked_reason: Option<String>,
+    #[serde(default)]
+    pub requires_python: Option<String>,
+}
+
+impl PyPIPackageInfo {
+    /// PEP 592: a release counts as yanked once every one of its files is
+    pub fn is_yanked(&self, version: &str) -> bool {
+        self.releases
+            .get(version)
+            .is_some_and(|files| !files.is_empty() && files.iter().all(|f| f.yanked))
+    }
+
+    pub fn yank_reason(&self, version: &str) -> Option<String> {
+        self.releases
+            .get(version)?
+            .iter()
+            .find_map(|f| f.yanked_reason.clone())
+            .filter(|reason| !reason.trim().is_empty())
+    }
+
+    /// Newest stable release that hasn't been yanked, preferring PyPI's `info.version`
+    pub fn latest_unyanked(&self) -> Option<String> {
+        self.latest_release(false)
+    }
+
+    /// Newest unyanked release; pre-releases only when allowed or when nothing else was published
+    pub fn latest_release(&self, allow_prereleases: bool) -> Option<String> {
+        let info_version = &self.info.version;
+        if !allow_prereleases && self.releases.contains_key(info_version)
+            && !self.is_yanked(info_version) && !prerelease::is_prerelease(info_version) {
+            return Some(info_version.clone());
+        }
+
+        let candidates: Vec<(crate::pep440::Version, &String)> = self.releases
+            .keys()
+            .filter(|v| !self.is_yanked(v) && self.releases.get(*v).is_some_and(|files| !files.is_empty()))
+            .filter_map(|v| crate::pep440::Version::parse(v).ok().map(|parsed| (parsed, v)))
+            .collect();
+        let newest = |include_pre: bool| candidates.iter()
+            .filter(|(parsed, _)| include_pre || (parsed.pre.is_none() && parsed.dev.is_none()))
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, v)| v.to_string());
+        newest(allow_prereleases).or_else(|| newest(true))
+    }
+
+    /// Forget files uploaded after `cutoff` (unix seconds), and releases left with none.
+    /// Files whose index doesn't report an upload time are kept.
+    pub fn exclude_newer(&mut self, cutoff: u64) {
+        for files in self.releases.values_mut() {
+            files.retain(|file| file.upload_time.as_deref().and_then(upload_timestamp).is_none_or(|t| t <= cutoff));
+        }
+        self.releases.retain(|_, files| !files.is_empty());
+        if !self.releases.contains_key(&self.info.version) {
+            self.info.version = self.latest_unyanked().unwrap_or_default();
+        }
+    }
+
+    /// Whether any file of `version` accepts interpreter `python_version` (`3.11`) per its Requires-Python
+    pub fn supports_python(&self, version: &str, python_version: &str) -> bool {
+        self.releases.get(version).is_none_or(|files| {
+            files.is_empty() || files.iter().any(|file| match file.requires_python.as_deref().map(str::trim) {
+                Some(requires) if !requires.is_empty() => crate::markers::python_satisfies(requires, python_version),
+                _ => true,
+            })
+        })
+    }
+
+    /// Yanked versions are only installed when pinned exactly; say so loudly
+    pub fn warn_if_yanked(&self, version: &str) {
+        if self.is_yanked(version) {
+            let reason = self.yank_reason(version)
+                .map(|r| format!(" (reason: {})", r))
+                .unwrap_or_default();
+            logger::warn(format!(
+                "⚠️  {} {} has been yanked{}; installing it only because it is pinned",
+                self.info.name, version, reason
+            ));
+        }
+    }
+}
+
+/// Which end of each allowed range to pick from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Resolution {
+    /// Newest compatible versions
+    #[default]
+    Highest,
+    /// Oldest compatible versions, to prove declared lower bounds actually work
+    Lowest,
+}
+
+impl Resolution {
+    pub fn named(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "highest" => Ok(Self::Highest),
+            "lowest" => Ok(Self::Lowest),
+            other => Err(anyhow::anyhow!("Unknown resolution '{}' (expected highest or lowest)", other)),
+        }
+    }
+
+    /// The `resolution` setting; highest when unset
+    pub fn from_config(name: Option<&str>) -> Result<Self> {
+        name.map_or(Ok(Self::Highest), Self::named)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Highest => "highest",
+            Self::Lowest => "lowest",
+        }
+    }
 }
 
 pub struct DependencyResolver {
     client: Client,
     cache: DiskCache,
     mem_cache: Arc<Mutex<HashMap<String, PyPIPackageInfo>>>,
+    /// Requires-Dist by (canonical name, version)
+    requires_dist_cache: Arc<Mutex<HashMap<(String, String), Vec<String>>>>,
+    upstream: Upstream,
+    constraints: Constraints,
+    prereleases: Prereleases,
+    /// Interpreter (`3.11`) whose Requires-Python every selected release must accept
+    python_version: Option<String>,
+    /// Specifiers from snakepit.toml used instead of what a requirement asks for
+    overrides: Overrides,
+    resolution: Resolution,
+    /// Ignore files uploaded after this time (unix seconds)
+    exclude_newer: Option<u64>,
+}
+
+/// Unix seconds of an index upload time such as `2024-05-01T12:34:56.789012Z`
+fn upload_timestamp(upload_time: &str) -> Option<u64> {
+    let whole_seconds = upload_time.split('.').next()?.trim_end_matches('Z');
+    crate::daemon_log::parse_time(whole_seconds, 0).ok()
 }
 
 impl DependencyResolver {
@@ -95,9 +261,67 @@ impl DependencyResolver {
             client: Client::new(),
             cache: DiskCache::new(),
             mem_cache: Arc::new(Mutex::new(HashMap::new())),
+            requires_dist_cache: Arc::new(Mutex::new(HashMap::new())),
+            upstream: Upstream::new(),
+            constraints: Constraints::new(),
+            prereleases: Prereleases::new(),
+            python_version: None,
+            overrides: Overrides::new(),
+            resolution: Resolution::Highest,
+            exclude_newer: None,
         }
     }
 
+    /// Limit versions with `-c` constraints files; constrained packages aren't added by themselves
+    pub fn with_constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    pub fn with_upstream(mut self, upstream: Upstream) -> Self {
+        self.upstream = upstream.with_subsystem(Subsystem::Resolver);
+        self
+    }
+
+    /// Let the given packages (or all) resolve to pre-releases
+    pub fn with_prereleases(mut self, prereleases: Prereleases) -> Self {
+        self.prereleases = prereleases;
+        self
+    }
+
+    /// Replace the specifiers of overridden packages, wherever they're required
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Resolve as of `cutoff` (unix seconds): releases uploaded later don't exist
+    pub fn with_exclude_newer(mut self, cutoff: u64) -> Self {
+        self.exclude_newer = Some(cutoff);
+        self
+    }
+
+    /// Pick the oldest compatible versions instead of the newest
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Only pick releases whose Requires-Python accepts this interpreter version
+    pub fn with_python_version(mut self, python_version: &str) -> Self {
+        self.python_version = Some(python_version.to_string());
+        self
+    }
+
+    fn supports_python(&self, package_info: &PyPIPackageInfo, version: &str) -> bool {
+        self.python_version.as_deref().is_none_or(|python| package_info.supports_python(version, python))
+    }
+
+    /// Whether `dep` may resolve to a pre-release: opted in, or its specifier names one
+    fn allows_prereleases(&self, dep: &Dependency) -> bool {
+        self.prereleases.allows(&dep.name) || dep.version.as_deref().is_some_and(prerelease::is_prerelease)
+    }
+
     pub async fn resolve_dependencies(&mut self, project: &ProjectDependencies) -> Result<ResolvedDependencies> {
         let mut resolved = ResolvedDependencies::new();
         let mut visited = HashSet::new();
@@ -136,12 +360,7 @@ impl DependencyResolver {
             visited.insert(dep.name.clone());
 
             let package_info = self.fetch_package_info(&dep.name).await?;
-            
-            let version = if let Some(requested_version) = &dep.version {
-                Self::find_best_version_static(&package_info, requested_version, &dep.version_constraint)?
-            } else {
-                package_info.info.version.clone()
-            };
+            let version = self.select_version(&package_info, dep)?;
             
             let mut resolved_dep = ResolvedDependency {
                 name: dep.name.clone(),
@@ -154,9 +373,15 @@ impl DependencyResolver {
             // Resolve sub-dependencies
             if let Some(requires) = &package_info.info.requires_dist {
                 for req_str in requires {
-                    // Filter out extra markers for now (simplified)
+                    // Only follow extra-gated requirements when that extra was requested
                     if req_str.contains("extra ==") {
-                        continue;
+                        let env = crate::markers::TargetEnvironment::host().with_extras(&dep.extras);
+                        let applies = crate::markers::parse_requirement(req_str)
+                            .map(|spec| spec.marker.map_or(true, |m| m.evaluate(&env)))
+                            .unwrap_or(false);
+                        if !applies {
+                            continue;
+                        }
                     }
                     
                     if let Some(sub_dep) = Self::parse_requirement_string_static(req_str) {
@@ -174,6 +399,76 @@ impl DependencyResolver {
         })
     }
 
+    /// Version `install` should use for `dep`, honouring constraints files
+    pub async fn resolve_version(&self, dep: &Dependency) -> Result<String> {
+        let package_info = self.fetch_package_info(&dep.name).await?;
+        self.select_version(&package_info, dep)
+    }
+
+    fn select_version(&self, package_info: &PyPIPackageInfo, dep: &Dependency) -> Result<String> {
+        if self.overrides.get(&dep.name).is_some() || self.resolution == Resolution::Lowest {
+            let version = self.constrained_version(package_info, dep)?;
+            package_info.warn_if_yanked(&version);
+            return Ok(version);
+        }
+        let allow_prereleases = self.allows_prereleases(dep);
+        let version = if let Some(requested_version) = &dep.version {
+            Self::find_best_version_static(package_info, requested_version, &dep.version_constraint, allow_prereleases)?
+        } else {
+            package_info.latest_release(allow_prereleases)
+                .ok_or_else(|| anyhow::anyhow!("Every release of {} has been yanked", dep.name))?
+        };
+
+        let version = if self.constraints.allows(&dep.name, &version) && self.supports_python(package_info, &version) {
+            version
+        } else {
+            self.constrained_version(package_info, dep)?
+        };
+        package_info.warn_if_yanked(&version);
+        Ok(version)
+    }
+
+    /// Newest (or, resolving lowest, oldest) stable, unyanked release satisfying the
+    /// requirement (or its override), the constraints and Requires-Python
+    fn constrained_version(&self, package_info: &PyPIPackageInfo, dep: &Dependency) -> Result<String> {
+        let overridden = self.overrides.get(&dep.name);
+        let requirement = match (overridden, &dep.version_constraint, &dep.version) {
+            (Some(specs), _, _) => specs.to_vec(),
+            (None, Some(op), Some(version)) => crate::markers::parse_specifier_set(&format!("{}{}", op, version))?,
+            (None, None, Some(version)) => crate::markers::parse_specifier_set(&format!("=={}", version))?,
+            _ => Vec::new(),
+        };
+
+        package_info.releases.keys()
+            .filter(|v| !package_info.is_yanked(v) && package_info.releases.get(*v).is_some_and(|files| !files.is_empty()))
+            .filter(|v| self.constraints.allows(&dep.name, v) && requirement.iter().all(|spec| spec.matches(v)))
+            .filter(|v| self.supports_python(package_info, v))
+            .filter_map(|v| crate::pep440::Version::parse(v).ok().map(|parsed| (parsed, v)))
+            .filter(|(parsed, _)| self.allows_prereleases(dep) || (parsed.pre.is_none() && parsed.dev.is_none()))
+            .max_by(|a, b| match self.resolution {
+                Resolution::Highest => a.0.cmp(&b.0),
+                Resolution::Lowest => b.0.cmp(&a.0),
+            })
+            .map(|(_, v)| v.clone())
+            .ok_or_else(|| {
+                let mut limits = vec![match overridden {
+                    Some(specs) => format!("override {}", specs.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(",")),
+                    None => format!(
+                        "{}{}",
+                        dep.version_constraint.as_deref().unwrap_or(""),
+                        dep.version.as_deref().unwrap_or("(any)")
+                    ),
+                }];
+                if self.constraints.get(&dep.name).is_some() {
+                    limits.push(format!("constraint {}", self.constraints.describe(&dep.name)));
+                }
+                if let Some(python) = &self.python_version {
+                    limits.push(format!("Python {}", python));
+                }
+                anyhow::anyhow!("No version of {} satisfies {}", dep.name, limits.join(" and "))
+            })
+    }
+
     // Kept for backward compatibility if needed, but redirects to recursive
     async fn resolve_single_dependency(&self, dep: &Dependency) -> Result<ResolvedDependency> {
         let mut visited = HashSet::new();
@@ -181,6 +476,80 @@ impl DependencyResolver {
     }
 
     pub async fn fetch_package_info(&self, package_name: &str) -> Result<PyPIPackageInfo> {
+        let mut info = self.fetch_index_info(package_name).await?;
+        if let Some(cutoff) = self.exclude_newer {
+            info.exclude_newer(cutoff);
+        }
+        Ok(info)
+    }
+
+    /// Requires-Dist of one release. `info.requires_dist` in the project document is
+    /// only the newest release's, so older releases are looked up on their own.
+    pub async fn fetch_requires_dist(&self, package_name: &str, version: &str) -> Result<Vec<String>> {
+        let package_name = canonical_name(package_name);
+        let key = (package_name.clone(), version.to_string());
+        if let Some(requires_dist) = self.requires_dist_cache.lock().unwrap().get(&key) {
+            return Ok(requires_dist.clone());
+        }
+
+        let requires_dist = match self.cache.get_requires_dist(&package_name, version) {
+            Some(requires_dist) => requires_dist,
+            None => match self.fetch_release_requires_dist(&package_name, version).await? {
+                Some(requires_dist) => {
+                    self.cache.set_requires_dist(&package_name, version, &requires_dist);
+                    requires_dist
+                }
+                // The index serves no metadata for it; don't remember that on disk
+                None => Vec::new(),
+            },
+        };
+        self.requires_dist_cache.lock().unwrap().insert(key, requires_dist.clone());
+        Ok(requires_dist)
+    }
+
+    /// `None` when the index has no metadata for the release
+    async fn fetch_release_requires_dist(&self, package_name: &str, version: &str) -> Result<Option<Vec<String>>> {
+        let info = self.fetch_index_info(package_name).await?;
+        // Release keys are as uploaded, not necessarily normalized
+        let wanted = crate::pep440::Version::parse(version).ok();
+        let release = info.releases.keys()
+            .find(|v| *v == version || crate::pep440::Version::parse(v).ok().is_some_and(|v| Some(v) == wanted))
+            .cloned()
+            .unwrap_or_else(|| version.to_string());
+        if release == info.info.version && info.info.requires_dist.is_some() {
+            return Ok(info.info.requires_dist);
+        }
+
+        if self.upstream.index_url().is_some() {
+            return self.upstream.fetch_simple_requires_dist(&self.client, package_name, &release).await;
+        }
+        match self.upstream.fetch_release_json(&self.client, package_name, &release).await {
+            Ok(response) if response.status().is_success() => {
+                let release_info: PyPIReleaseInfo = response.json().await?;
+                Ok(Some(release_info.info.requires_dist.unwrap_or_default()))
+            }
+            Ok(response) => Err(anyhow::anyhow!("{} {} not found on PyPI ({})", package_name, release, response.status())),
+            Err(e) => self.upstream.fetch_simple_requires_dist(&self.client, package_name, &release).await
+                .map_err(|_| e),
+        }
+    }
+
+    /// Serve `info` and the given releases' Requires-Dist without asking the index
+    #[cfg(test)]
+    pub(crate) fn with_cached(self, info: PyPIPackageInfo, requires_dist: Vec<(&str, Vec<String>)>) -> Self {
+        let package_name = canonical_name(&info.info.name);
+        for (version, requires) in requires_dist {
+            self.requires_dist_cache.lock().unwrap().insert((package_name.clone(), version.to_string()), requires);
+        }
+        self.mem_cache.lock().unwrap().insert(package_name, info);
+        self
+    }
+
+    /// Everything the index knows about a package; caches hold it unfiltered
+    async fn fetch_index_info(&self, package_name: &str) -> Result<PyPIPackageInfo> {
+        // `Foo_Bar`, `foo-bar` and `foo.bar` are one project with one cache entry
+        let package_name = &canonical_name(package_name);
+
         // Check memory cache
         {
             let cache = self.mem_cache.lock().unwrap();
@@ -189,34 +558,68 @@ impl DependencyResolver {
             }
         }
 
-        // Check disk cache
-        if let Some(info) = self.cache.get(package_name) {
-            let mut cache = self.mem_cache.lock().unwrap();
-            cache.insert(package_name.to_string(), info.clone());
-            return Ok(info);
-        }
-
-        // Fetch from network
-        let url = format!("https://pypi.org/pypi/{}/json", package_name);
-        let response = self.client.get(&url).send().await?;
-        
-        if response.status().is_success() {
-            let package_info: PyPIPackageInfo = response.json().await?;
-            
-            // Update caches
-            self.cache.set(package_name, &package_info);
-            {
+        // Check disk cache: served as-is while fresh, revalidated with a conditional GET after that
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let cached = self.cache.get(package_name);
+        if let Some((info, validators)) = &cached {
+            if validators.is_fresh(now) {
                 let mut cache = self.mem_cache.lock().unwrap();
-                cache.insert(package_name.to_string(), package_info.clone());
+                cache.insert(package_name.to_string(), info.clone());
+                return Ok(info.clone());
             }
-            
-            Ok(package_info)
+        }
+
+        // Fetch from network: a configured simple index, or PyPI's JSON API (then mirrors, then the simple API)
+        let fetched = if self.upstream.index_url().is_some() {
+            self.upstream.fetch_simple(&self.client, package_name).await
+                .map(|info| (info, CacheValidators { checked_at: now, ..Default::default() }))
         } else {
-            Err(anyhow::anyhow!("Package {} not found on PyPI", package_name))
+            let headers = cached.as_ref().map(|(_, validators)| validators.conditional_headers()).unwrap_or_default();
+            match self.upstream.fetch_json(&self.client, package_name, headers).await {
+                Ok(response) if response.status() == reqwest::StatusCode::NOT_MODIFIED && cached.is_some() => {
+                    let (info, validators) = cached.clone().unwrap();
+                    Ok((info, CacheValidators { checked_at: now, ..validators }))
+                }
+                Ok(response) if response.status().is_success() => {
+                    let validators = CacheValidators::from_response(response.headers(), now);
+                    Ok((response.json().await?, validators))
+                }
+                Ok(_) => return Err(anyhow::anyhow!("Package {} not found on PyPI", package_name)),
+                Err(e) => self.upstream.fetch_simple(&self.client, package_name).await
+                    .map(|info| (info, CacheValidators { checked_at: now, ..Default::default() }))
+                    .map_err(|_| e),
+            }
+        };
+
+        let package_info = match (fetched, cached) {
+            (Ok((info, validators)), _) => {
+                self.cache.set(package_name, &info, &validators);
+                info
+            }
+            // Every source is down: stale metadata beats failing the resolution
+            (Err(_), Some((info, validators))) => {
+                logger::warn(format!(
+                    "⚠️  Package index unavailable; using cached metadata for {} from {} ago (may be stale)",
+                    package_name, crate::upstream::describe_age(now.saturating_sub(validators.checked_at))
+                ));
+                info
+            }
+            (Err(e), None) => return Err(e),
+        };
+
+        // Update memory cache
+        {
+            let mut cache = self.mem_cache.lock().unwrap();
+            cache.insert(package_name.to_string(), package_info.clone());
         }
+
+        Ok(package_info)
     }
 
-    fn find_best_version_static(package_info: &PyPIPackageInfo, requested_version: &str, constraint: &Option<String>) -> Result<String> {
+    fn find_best_version_static(package_info: &PyPIPackageInfo, requested_version: &str, constraint: &Option<String>, allow_prereleases: bool) -> Result<String> {
         let available_versions: Vec<&String> = package_info.releases.keys().collect();
         
         if let Some(constraint) = constraint {
@@ -227,6 +630,9 @@ impl DependencyResolver {
                     let mut best_version = None;
                     
                     for version_str in available_versions {
+                        if package_info.is_yanked(version_str) || (!allow_prereleases && prerelease::is_prerelease(version_str)) {
+                            continue;
+                        }
                         if let Ok(version) = Version::parse(version_str) {
                             if req.matches(&version) {
                                 if let Some(ref current_best) = best_version {
@@ -252,12 +658,12 @@ impl DependencyResolver {
                 }
                 _ => {
                     // Default to latest version
-                    return Ok(package_info.info.version.clone());
+                    return Ok(package_info.latest_release(allow_prereleases).unwrap_or_else(|| package_info.info.version.clone()));
                 }
             }
         } else {
             // No constraint, return latest
-            Ok(package_info.info.version.clone())
+            Ok(package_info.latest_release(allow_prereleases).unwrap_or_else(|| package_info.info.version.clone()))
         }
     }
 
@@ -268,7 +674,7 @@ impl DependencyResolver {
             return None;
         }
         
-        let name = parts[0].to_string();
+        let (name, extras) = crate::dependency::split_extras(parts[0].trim_end_matches(';'));
         let version = if parts.len() > 1 {
             Some(parts[1].to_string())
         } else {
@@ -281,6 +687,8 @@ impl DependencyResolver {
             version_constraint: None,
             is_dev: false,
             source: None,
+            extras,
+            hashes: Vec::new(),
         })
     }
 }
@@ -331,5 +739,161 @@ pub struct ResolvedDependency {
 mod tests {
     use super::*;
 
+    fn release(version: &str, yanked: bool, reason: Option<&str>) -> (String, Vec<PyPIRelease>) {
+        (version.to_string(), vec![PyPIRelease {
+            filename: format!("demo-{}-py3-none-any.whl", version),
+            url: String::new(),
+            size: None,
+            upload_time: None,
+            digests: None,
+            yanked,
+            yanked_reason: reason.map(|r| r.to_string()),
+            requires_python: None,
+        }])
+    }
+
+    fn package(latest: &str, releases: Vec<(String, Vec<PyPIRelease>)>) -> PyPIPackageInfo {
+        PyPIPackageInfo {
+            info: PyPIInfo {
+                name: "demo".to_string(),
+                version: latest.to_string(),
+                summary: None,
+                description: None,
+                author: None,
+                license: None,
+                home_page: None,
+                requires_dist: None,
+            },
+            releases: releases.into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_yanked_releases_are_skipped() {
+        let info = package("1.2.0", vec![
+            release("1.0.0", false, None),
+            release("1.1.0", false, None),
+            release("1.2.0", true, Some("broken wheel")),
+        ]);
+
+        assert!(info.is_yanked("1.2.0"));
+        assert_eq!(info.yank_reason("1.2.0"), Some("broken wheel".to_string()));
+        assert_eq!(info.latest_unyanked(), Some("1.1.0".to_string()));
+        assert_eq!(
+            DependencyResolver::find_best_version_static(&info, "1.0.0", &Some(">=".to_string()), false).unwrap(),
+            "1.1.0"
+        );
+        // An exact pin still gets the yanked version
+        assert_eq!(
+            DependencyResolver::find_best_version_static(&info, "1.2.0", &Some("==".to_string()), false).unwrap(),
+            "1.2.0"
+        );
+    }
+
+    #[test]
+    fn test_prereleases_need_opt_in() {
+        let info = package("2.0.0rc1", vec![
+            release("1.9.0", false, None),
+            release("2.0.0rc1", false, None),
+        ]);
+        assert_eq!(info.latest_unyanked(), Some("1.9.0".to_string()));
+        assert_eq!(info.latest_release(true), Some("2.0.0rc1".to_string()));
+
+        let dep = |version: Option<&str>| Dependency {
+            name: "demo".to_string(),
+            version: version.map(|v| v.to_string()),
+            version_constraint: version.map(|_| ">=".to_string()),
+            is_dev: false,
+            source: None,
+            extras: Vec::new(),
+            hashes: Vec::new(),
+        };
+        let resolver = DependencyResolver::new();
+        assert_eq!(resolver.select_version(&info, &dep(None)).unwrap(), "1.9.0");
+        // Naming a pre-release in the specifier opts in, as with pip
+        assert!(resolver.allows_prereleases(&dep(Some("2.0.0rc1"))));
+        let resolver = DependencyResolver::new().with_prereleases(Prereleases::new().with_package("demo"));
+        assert_eq!(resolver.select_version(&info, &dep(None)).unwrap(), "2.0.0rc1");
 
+        // Only pre-releases published: take the newest rather than failing
+        let only_pre = package("0.1.0b2", vec![release("0.1.0b1", false, None), release("0.1.0b2", false, None)]);
+        assert_eq!(only_pre.latest_unyanked(), Some("0.1.0b2".to_string()));
+    }
+
+    #[test]
+    fn test_requires_python_filters_versions() {
+        let mut info = package("2.0.0", vec![
+            release("1.0.0", false, None),
+            release("2.0.0", false, None),
+        ]);
+        for file in info.releases.get_mut("2.0.0").unwrap() {
+            file.requires_python = Some(">=3.10".to_string());
+        }
+        assert!(info.supports_python("2.0.0", "3.12"));
+        assert!(!info.supports_python("2.0.0", "3.9"));
+        assert!(info.supports_python("1.0.0", "3.9"));
+
+        let dep = |version: Option<&str>| Dependency {
+            name: "demo".to_string(),
+            version: version.map(|v| v.to_string()),
+            version_constraint: version.map(|_| "==".to_string()),
+            is_dev: false,
+            source: None,
+            extras: Vec::new(),
+            hashes: Vec::new(),
+        };
+        let py39 = DependencyResolver::new().with_python_version("3.9");
+        assert_eq!(py39.select_version(&info, &dep(None)).unwrap(), "1.0.0");
+        assert_eq!(DependencyResolver::new().with_python_version("3.11").select_version(&info, &dep(None)).unwrap(), "2.0.0");
+        let err = py39.select_version(&info, &dep(Some("2.0.0"))).unwrap_err().to_string();
+        assert!(err.contains("Python 3.9"), "{}", err);
+    }
+
+    #[test]
+    fn test_exclude_newer_drops_later_uploads() {
+        let mut info = package("2.0.0", vec![
+            release("1.0.0", false, None),
+            release("1.1.0", false, None),
+            release("2.0.0", false, None),
+        ]);
+        let uploaded = [("1.0.0", "2023-01-10T08:00:00"), ("1.1.0", "2024-05-31T23:59:59.123456Z"), ("2.0.0", "2024-06-02T00:00:00")];
+        for (version, time) in uploaded {
+            info.releases.get_mut(version).unwrap()[0].upload_time = Some(time.to_string());
+        }
+        let mut unknown = release("0.9.0", false, None);
+        unknown.1[0].upload_time = None;
+        info.releases.insert(unknown.0, unknown.1);
+
+        let cutoff = crate::daemon_log::parse_time("2024-06-01", 0).unwrap();
+        info.exclude_newer(cutoff);
+        assert!(!info.releases.contains_key("2.0.0"));
+        assert!(info.releases.contains_key("0.9.0"));
+        assert_eq!(info.info.version, "1.1.0");
+    }
+
+    #[test]
+    fn test_constraints_cap_selected_version() {
+        let info = package("2.1.0", vec![
+            release("1.9.0", false, None),
+            release("2.0.0", false, None),
+            release("2.1.0", false, None),
+        ]);
+        let mut constraints = Constraints::new();
+        constraints.add_line("demo<2.1").unwrap();
+        let resolver = DependencyResolver::new().with_constraints(constraints);
+
+        let dep = |constraint: Option<&str>, version: Option<&str>| Dependency {
+            name: "demo".to_string(),
+            version: version.map(|v| v.to_string()),
+            version_constraint: constraint.map(|c| c.to_string()),
+            is_dev: false,
+            source: None,
+            extras: Vec::new(),
+            hashes: Vec::new(),
+        };
+
+        assert_eq!(resolver.select_version(&info, &dep(None, None)).unwrap(), "2.0.0");
+        assert_eq!(resolver.select_version(&info, &dep(Some(">="), Some("1.0"))).unwrap(), "2.0.0");
+        assert!(resolver.select_version(&info, &dep(Some("=="), Some("2.1.0"))).is_err());
+    }
 }
@@ -2,6 +2,7 @@ use snakegg::charmer::SnakeCharmer;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::logger;
 
 #[derive(Debug, Clone)]
 pub struct Conflict {
@@ -38,7 +39,7 @@ impl AIResolver {
         // Check cache first
         let cache_key = format!("{}-{}-{}", conflict.package_name, conflict.constraint_a, conflict.constraint_b);
         if let Some(cached) = self.conflict_cache.get(&cache_key) {
-            println!("💾 Using cached resolution for {}", conflict.package_name);
+            logger::info(format!("💾 Using cached resolution for {}", conflict.package_name));
             return Ok(Some(cached.clone()));
         }
 
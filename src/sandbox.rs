@@ -1,16 +1,32 @@
 use anyhow::{Result, Context};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use serde::{Serialize, Deserialize};
 use crate::venv::VirtualEnvironmentManager;
+use crate::installer::{PackageInstaller, InstallerBackend};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SandboxMode {
+    /// Full `python -m venv` environment with pip available
+    #[default]
+    Full,
+    /// Symlinked interpreter plus an empty site-packages, good enough for import checks
+    Lightweight,
+}
 
 pub struct VenvSandbox {
     id: String,
     path: PathBuf,
     manager: VirtualEnvironmentManager,
+    mode: SandboxMode,
 }
 
 impl VenvSandbox {
     pub fn new(id: &str) -> Self {
+        Self::with_mode(id, SandboxMode::Full)
+    }
+
+    pub fn with_mode(id: &str, mode: SandboxMode) -> Self {
         let manager = VirtualEnvironmentManager::new();
         // Use a temporary directory for sandboxes
         let sandbox_dir = std::env::temp_dir().join("snakepit-sandbox");
@@ -23,16 +39,103 @@ impl VenvSandbox {
             id: id.to_string(),
             path,
             manager,
+            mode,
         }
     }
 
+    pub fn mode(&self) -> SandboxMode {
+        self.mode
+    }
+
     pub async fn create(&self) -> Result<PathBuf> {
-        // Create the virtual environment
-        self.manager.create_venv(&self.id, None).await?;
+        match self.mode {
+            SandboxMode::Full => {
+                // Create the virtual environment
+                self.manager.create_venv(&self.id, None).await?;
+            }
+            SandboxMode::Lightweight => {
+                self.create_lightweight()?;
+            }
+        }
         Ok(self.path.clone())
     }
 
+    /// Build a minimal venv-shaped prefix without invoking `python -m venv`.
+    ///
+    /// The layout is `bin/python` (a symlink to the base interpreter), an empty
+    /// `lib/pythonX.Y/site-packages` and a `pyvenv.cfg`, which is all CPython needs
+    /// to treat the prefix as an isolated environment. There is no pip in here.
+    fn create_lightweight(&self) -> Result<()> {
+        if self.path.exists() {
+            return Err(anyhow::anyhow!("Sandbox '{}' already exists", self.id));
+        }
+
+        let probe = Command::new("python3")
+            .arg("-c")
+            .arg("import os, sys; \
+                  print(os.path.realpath(getattr(sys, '_base_executable', sys.executable))); \
+                  print('%d.%d.%d' % sys.version_info[:3])")
+            .output()
+            .context("Failed to probe base interpreter")?;
+
+        if !probe.status.success() {
+            return Err(anyhow::anyhow!("Failed to probe base interpreter: {}", String::from_utf8_lossy(&probe.stderr)));
+        }
+
+        let stdout = String::from_utf8_lossy(&probe.stdout);
+        let mut lines = stdout.lines();
+        let executable = PathBuf::from(lines.next().unwrap_or_default().trim());
+        let full_version = lines.next().unwrap_or_default().trim().to_string();
+        let short_version = full_version.split('.').take(2).collect::<Vec<_>>().join(".");
+
+        if !executable.exists() || short_version.is_empty() {
+            return Err(anyhow::anyhow!("Could not determine base interpreter"));
+        }
+
+        let bin_dir = self.path.join("bin");
+        let site_packages = self.path
+            .join("lib")
+            .join(format!("python{}", short_version))
+            .join("site-packages");
+
+        std::fs::create_dir_all(&bin_dir)?;
+        std::fs::create_dir_all(&site_packages)?;
+
+        Self::link_interpreter(&executable, &bin_dir.join("python"))?;
+        Self::link_interpreter(&executable, &bin_dir.join("python3"))?;
+
+        let home = executable.parent().unwrap_or(Path::new("/usr/bin"));
+        let cfg = format!(
+            "home = {}\ninclude-system-site-packages = false\nversion = {}\n",
+            home.display(),
+            full_version
+        );
+        std::fs::write(self.path.join("pyvenv.cfg"), cfg)?;
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn link_interpreter(target: &Path, link: &Path) -> Result<()> {
+        std::os::unix::fs::symlink(target, link)?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn link_interpreter(_target: &Path, _link: &Path) -> Result<()> {
+        // Symlinks need elevated privileges on Windows, so callers fall back to a full venv
+        Err(anyhow::anyhow!("Lightweight sandboxes are not supported on this platform"))
+    }
+
     pub async fn install_package(&self, package: &str, version: Option<&str>) -> Result<()> {
+        if self.mode == SandboxMode::Lightweight {
+            // No pip here: unpack the wheel straight into the sandbox site-packages
+            let installer = PackageInstaller::new()
+                .with_backend(InstallerBackend::detect())
+                .with_venv(self.path.to_string_lossy().to_string());
+            return installer.install_package(package, version).await;
+        }
+
         let python_path = self.manager.activate_venv(&self.id).await?;
         let pip_path = if cfg!(target_os = "windows") {
             python_path.parent().unwrap().join("pip.exe")
@@ -59,6 +162,31 @@ impl VenvSandbox {
         Ok(())
     }
 
+    /// Install requirement specifiers (`setuptools>=61`, `wheel`) together, so pip resolves them as one set
+    pub async fn install_requirements(&self, requirements: &[String]) -> Result<()> {
+        if requirements.is_empty() {
+            return Ok(());
+        }
+        if self.mode == SandboxMode::Lightweight {
+            for requirement in requirements {
+                self.install_package(requirement, None).await?;
+            }
+            return Ok(());
+        }
+
+        let python_path = self.manager.activate_venv(&self.id).await?;
+        let output = Command::new(python_path)
+            .args(["-m", "pip", "install", "--quiet", "--disable-pip-version-check"])
+            .args(requirements)
+            .output()
+            .context("Failed to run pip in sandbox")?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Failed to install {} in sandbox: {}", requirements.join(", "), error));
+        }
+        Ok(())
+    }
+
     pub async fn run_script(&self, script_path: &Path) -> Result<(bool, String, String)> {
         let python_path = self.manager.activate_venv(&self.id).await?;
         
@@ -98,46 +226,50 @@ impl VenvSandbox {
     pub async fn find_installed_module(&self, package_name: &str) -> Result<String> {
         let path = self.manager.get_venv_path(&self.id);
         let site_packages = self.manager.get_site_packages_path(&path)?;
-        
-        if !site_packages.exists() {
-            return Ok(package_name.replace("-", "_"));
-        }
-
-        let normalized_name = package_name.replace("-", "_").to_lowercase();
-        let mut best_match = None;
-
-        for entry in std::fs::read_dir(&site_packages)? {
-            let entry = entry?;
-            let path = entry.path();
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                // Skip metadata directories
-                if name.ends_with(".dist-info") || name.ends_with(".egg-info") || name == "__pycache__" {
-                    continue;
-                }
-                
-                let name_lower = name.to_lowercase();
-                
-                // Exact match (ignoring case)
-                if name_lower == normalized_name {
-                    return Ok(name.trim_end_matches(".py").to_string());
-                }
-                
-                // Heuristic: if the package name contains the module name
-                if normalized_name.contains(&name_lower) || name_lower.contains(&normalized_name) {
-                    best_match = Some(name.trim_end_matches(".py").to_string());
-                }
-
-                // Special case for google packages
-                if normalized_name.starts_with("google") && name_lower == "google" {
-                    return Ok("google".to_string());
-                }
-            }
-        }
-
-        Ok(best_match.unwrap_or_else(|| package_name.replace("-", "_")))
+        find_module_in(&site_packages, package_name)
     }
 
     pub fn get_path(&self) -> &Path {
         &self.path
     }
 }
+
+/// Best guess at the importable module a distribution provides, from what's in `site_packages`
+pub fn find_module_in(site_packages: &Path, package_name: &str) -> Result<String> {
+    if !site_packages.exists() {
+        return Ok(package_name.replace("-", "_"));
+    }
+
+    let normalized_name = crate::manifest::canonical_name(package_name).replace('-', "_");
+    let mut best_match = None;
+
+    for entry in std::fs::read_dir(site_packages)? {
+        let entry = entry?;
+        let path = entry.path();
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            // Skip metadata directories
+            if name.ends_with(".dist-info") || name.ends_with(".egg-info") || name == "__pycache__" {
+                continue;
+            }
+            
+            let name_lower = name.to_lowercase();
+            
+            // Exact match (ignoring case)
+            if name_lower == normalized_name {
+                return Ok(name.trim_end_matches(".py").to_string());
+            }
+            
+            // Heuristic: if the package name contains the module name
+            if normalized_name.contains(&name_lower) || name_lower.contains(&normalized_name) {
+                best_match = Some(name.trim_end_matches(".py").to_string());
+            }
+
+            // Special case for google packages
+            if normalized_name.starts_with("google") && name_lower == "google" {
+                return Ok("google".to_string());
+            }
+        }
+    }
+
+    Ok(best_match.unwrap_or_else(|| package_name.replace("-", "_")))
+}
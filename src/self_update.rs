@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use crate::toolchain::{checksum_for, client, host_triple};
+
+/// Latest snakepit release; each one carries a `snakepit-<triple>` binary per platform and a `SHA256SUMS`
+const RELEASES_URL: &str = "https://api.github.com/repos/elci-group/snakepit/releases/latest";
+
+/// The version this binary was built as
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// The newest release and its binary for this machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub version: String,
+    pub filename: String,
+    pub url: String,
+    pub sums_url: String,
+}
+
+impl Update {
+    /// Whether this release is newer than the running binary
+    pub fn is_newer(&self) -> bool {
+        is_newer(&self.version, CURRENT_VERSION)
+    }
+}
+
+/// Replaces the running snakepit with the latest GitHub release
+pub struct SelfUpdater {
+    triple: String,
+}
+
+impl SelfUpdater {
+    pub fn new() -> Self {
+        Self { triple: host_triple() }
+    }
+
+    /// The latest release, with the binary built for this machine
+    pub async fn latest(&self) -> Result<Update> {
+        let release: Release = client()?.get(RELEASES_URL).send().await?
+            .error_for_status()
+            .context("Failed to fetch the snakepit release list")?
+            .json().await?;
+        let version = release.tag_name.trim_start_matches('v').to_string();
+        let filename = asset_name(&self.triple);
+        let url_of = |name: &str| release.assets.iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.clone());
+        let url = url_of(&filename)
+            .ok_or_else(|| anyhow!("snakepit {} has no build for {}", version, self.triple))?;
+        let sums_url = url_of("SHA256SUMS")
+            .ok_or_else(|| anyhow!("snakepit {} publishes no SHA256SUMS; refusing an unverifiable binary", version))?;
+        Ok(Update { version, filename, url, sums_url })
+    }
+
+    /// Download `update`, check it against `SHA256SUMS`, and swap it in for the executable.
+    ///
+    /// The new binary is written next to the old one and renamed over it, so the executable
+    /// is never half-written; on Windows, where a running binary can't be replaced, the old
+    /// one is moved aside to `snakepit.exe.old` first.
+    pub async fn install(&self, update: &Update) -> Result<PathBuf> {
+        let executable = std::env::current_exe()?.canonicalize()?;
+        let client = client()?;
+        let binary = client.get(&update.url).send().await?.error_for_status()?.bytes().await?;
+        let sums = client.get(&update.sums_url).send().await?.error_for_status()?.text().await?;
+        let expected = checksum_for(&sums, &update.filename)
+            .ok_or_else(|| anyhow!("{} isn't listed in SHA256SUMS", update.filename))?;
+        let actual = snakegg::native::hash::compute_sha256_hex(&binary);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(anyhow!("Checksum mismatch for {}: expected {}, got {}", update.filename, expected, actual));
+        }
+        replace_executable(&executable, &binary)?;
+        Ok(executable)
+    }
+}
+
+/// Write `binary` beside `executable`, then rename it into place
+fn replace_executable(executable: &Path, binary: &[u8]) -> Result<()> {
+    let dir = executable.parent().ok_or_else(|| anyhow!("{} has no parent directory", executable.display()))?;
+    let staged = dir.join(format!(".snakepit-update-{}", std::process::id()));
+    std::fs::write(&staged, binary)
+        .with_context(|| format!("Failed to write to {} (is it writable?)", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    let old = executable.with_extension("exe.old");
+    if cfg!(target_os = "windows") {
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(executable, &old)?;
+    }
+    if let Err(e) = std::fs::rename(&staged, executable) {
+        let _ = std::fs::remove_file(&staged);
+        if cfg!(target_os = "windows") {
+            let _ = std::fs::rename(&old, executable);
+        }
+        return Err(e).with_context(|| format!("Failed to replace {}", executable.display()));
+    }
+    Ok(())
+}
+
+/// `snakepit-<triple>`, with `.exe` on Windows
+fn asset_name(triple: &str) -> String {
+    if triple.contains("windows") {
+        format!("snakepit-{}.exe", triple)
+    } else {
+        format!("snakepit-{}", triple)
+    }
+}
+
+/// Semver comparison; a version that doesn't parse is never newer
+fn is_newer(candidate: &str, current: &str) -> bool {
+    match (semver::Version::parse(candidate), semver::Version::parse(current)) {
+        (Ok(candidate), Ok(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compares_release_versions() {
+        assert!(is_newer("0.3.0", "0.2.0"));
+        assert!(is_newer("0.2.1", "0.2.0"));
+        assert!(!is_newer("0.2.0", "0.2.0"));
+        assert!(!is_newer("0.3.0-rc.1", "0.3.0"));
+        assert!(!is_newer("nightly", "0.2.0"));
+        assert_eq!(asset_name("x86_64-unknown-linux-gnu"), "snakepit-x86_64-unknown-linux-gnu");
+        assert_eq!(asset_name("x86_64-pc-windows-msvc"), "snakepit-x86_64-pc-windows-msvc.exe");
+    }
+
+    #[test]
+    fn test_replaces_executable_in_place() {
+        let dir = std::env::temp_dir().join(format!("snakepit-self-update-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let executable = dir.join("snakepit");
+        std::fs::write(&executable, b"old").unwrap();
+
+        replace_executable(&executable, b"new").unwrap();
+        assert_eq!(std::fs::read(&executable).unwrap(), b"new");
+        assert!(!dir.join(format!(".snakepit-update-{}", std::process::id())).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use snakegg::native::dirs;
+
+/// systemd unit name, and the launchd label
+const SERVICE_NAME: &str = "snakepit";
+const LAUNCHD_LABEL: &str = "dev.snakepit.daemon";
+
+/// The per-user service manager that keeps the daemon running across logins and reboots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    /// A systemd user unit
+    Systemd,
+    /// A launchd agent (macOS)
+    Launchd,
+}
+
+impl ServiceManager {
+    pub fn detect() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(ServiceManager::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Ok(ServiceManager::Systemd)
+        } else {
+            Err(anyhow!("Installing the daemon as a service needs systemd (Linux) or launchd (macOS)"))
+        }
+    }
+
+    /// Where the unit file or plist goes
+    pub fn unit_path(&self) -> Result<PathBuf> {
+        match self {
+            ServiceManager::Systemd => {
+                let config_dir = dirs::config_dir()
+                    .ok_or_else(|| anyhow!("Could not find config directory"))?;
+                Ok(config_dir.join("systemd").join("user").join(format!("{}.service", SERVICE_NAME)))
+            }
+            ServiceManager::Launchd => {
+                let home = dirs::home_dir()
+                    .ok_or_else(|| anyhow!("Could not find home directory"))?;
+                Ok(home.join("Library").join("LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL)))
+            }
+        }
+    }
+
+    /// The unit running `<exe> daemon start` in the foreground; the service manager restarts
+    /// it if it crashes, and `output` collects what it prints
+    pub fn render(&self, exe: &Path, output: &Path) -> String {
+        match self {
+            ServiceManager::Systemd => format!(
+                "[Unit]\n\
+                 Description=Snakepit daemon (auto-installs missing Python modules)\n\
+                 \n\
+                 [Service]\n\
+                 ExecStart=\"{exe}\" daemon start\n\
+                 ExecReload=/bin/kill -HUP $MAINPID\n\
+                 Restart=on-failure\n\
+                 RestartSec=5\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=default.target\n",
+                // `%` starts a systemd specifier
+                exe = exe.display().to_string().replace('%', "%%"),
+            ),
+            ServiceManager::Launchd => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+                 <plist version=\"1.0\">\n\
+                 <dict>\n\
+                 \x20   <key>Label</key>\n\
+                 \x20   <string>{label}</string>\n\
+                 \x20   <key>ProgramArguments</key>\n\
+                 \x20   <array>\n\
+                 \x20       <string>{exe}</string>\n\
+                 \x20       <string>daemon</string>\n\
+                 \x20       <string>start</string>\n\
+                 \x20   </array>\n\
+                 \x20   <key>RunAtLoad</key>\n\
+                 \x20   <true/>\n\
+                 \x20   <key>KeepAlive</key>\n\
+                 \x20   <dict>\n\
+                 \x20       <key>SuccessfulExit</key>\n\
+                 \x20       <false/>\n\
+                 \x20   </dict>\n\
+                 \x20   <key>StandardOutPath</key>\n\
+                 \x20   <string>{output}</string>\n\
+                 \x20   <key>StandardErrorPath</key>\n\
+                 \x20   <string>{output}</string>\n\
+                 </dict>\n\
+                 </plist>\n",
+                label = LAUNCHD_LABEL,
+                exe = xml_escape(&exe.display().to_string()),
+                output = xml_escape(&output.display().to_string()),
+            ),
+        }
+    }
+
+    /// Write the unit for this snakepit binary, then enable and start it
+    pub fn install(&self, output: &Path) -> Result<PathBuf> {
+        let path = self.unit_path()?;
+        let exe = std::env::current_exe().context("Could not locate the snakepit binary")?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, self.render(&exe, output))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        match self {
+            ServiceManager::Systemd => {
+                run("systemctl", &["--user", "daemon-reload"])?;
+                run("systemctl", &["--user", "enable", "--now", &format!("{}.service", SERVICE_NAME)])?;
+            }
+            ServiceManager::Launchd => {
+                run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Stop and disable the service and delete its unit; `None` when none was installed
+    pub fn uninstall(&self) -> Result<Option<PathBuf>> {
+        let path = self.unit_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        match self {
+            ServiceManager::Systemd => {
+                run("systemctl", &["--user", "disable", "--now", &format!("{}.service", SERVICE_NAME)])?;
+                std::fs::remove_file(&path)?;
+                run("systemctl", &["--user", "daemon-reload"])?;
+            }
+            ServiceManager::Launchd => {
+                run("launchctl", &["unload", "-w", &path.to_string_lossy()])?;
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(Some(path))
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        return Err(anyhow!("{} {} failed: {}", program, args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_units() {
+        let exe = Path::new("/opt/snake & co/bin/snakepit");
+        let output = Path::new("/home/dev/.local/share/snakepit/daemon.out");
+
+        let unit = ServiceManager::Systemd.render(exe, output);
+        assert!(unit.contains("ExecStart=\"/opt/snake & co/bin/snakepit\" daemon start\n"));
+        assert!(unit.contains("ExecReload=/bin/kill -HUP $MAINPID\n"));
+        assert!(unit.ends_with("[Install]\nWantedBy=default.target\n"));
+
+        let plist = ServiceManager::Launchd.render(exe, output);
+        assert!(plist.contains("    <string>dev.snakepit.daemon</string>\n"));
+        assert!(plist.contains("        <string>/opt/snake &amp; co/bin/snakepit</string>\n        <string>daemon</string>\n"));
+        assert!(plist.contains("<key>StandardOutPath</key>\n    <string>/home/dev/.local/share/snakepit/daemon.out</string>"));
+    }
+}
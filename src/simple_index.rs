@@ -0,0 +1,407 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::{Client, Url};
+use std::collections::HashMap;
+use crate::rate_limit::{self, Subsystem};
+use crate::resolver::{PyPIInfo, PyPIPackageInfo, PyPIRelease};
+
+/// PEP 691 content negotiation: prefer JSON, accept the PEP 503 HTML page
+const ACCEPT_SIMPLE: &str = "application/vnd.pypi.simple.v1+json, application/vnd.pypi.simple.v1+html;q=0.2, text/html;q=0.01";
+
+const SIMPLE_JSON: &str = "application/vnd.pypi.simple.v1+json";
+
+lazy_static! {
+    static ref ANCHOR: Regex = Regex::new(r#"(?is)<a\s([^>]*)>(.*?)</a>"#).unwrap();
+    static ref ATTRIBUTE: Regex = Regex::new(r#"([a-zA-Z][a-zA-Z0-9_-]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?"#).unwrap();
+}
+
+/// One distribution file listed on a project page
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimpleFile {
+    pub filename: String,
+    pub url: String,
+    /// Algorithm name -> hex digest
+    pub hashes: HashMap<String, String>,
+    pub requires_python: Option<String>,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+    /// PEP 658: the core metadata is served separately at `{url}.metadata`
+    pub has_metadata: bool,
+    pub size: Option<u64>,
+    /// PEP 700 `upload-time` (JSON pages only), ISO 8601
+    pub upload_time: Option<String>,
+}
+
+impl SimpleFile {
+    /// Version encoded in a wheel or sdist filename
+    pub fn version(&self) -> Option<String> {
+        let name = &self.filename;
+        if let Some(stem) = name.strip_suffix(".whl") {
+            return stem.split('-').nth(1).map(|v| v.to_string());
+        }
+        let stem = [".tar.gz", ".tar.bz2", ".zip", ".tgz"]
+            .iter()
+            .find_map(|ext| name.strip_suffix(ext))?;
+        stem.rsplit_once('-').map(|(_, v)| v.to_string())
+    }
+}
+
+/// A project page from a PEP 503 / PEP 691 simple index
+#[derive(Debug, Clone)]
+pub struct SimpleProject {
+    pub name: String,
+    pub files: Vec<SimpleFile>,
+}
+
+impl SimpleProject {
+    /// Parse a PEP 691 JSON project page
+    pub fn from_json(body: &str, page_url: &Url) -> Result<Self> {
+        let doc: serde_json::Value = serde_json::from_str(body)?;
+        let name = doc["name"].as_str()
+            .ok_or_else(|| anyhow::anyhow!("Simple API response has no project name"))?
+            .to_string();
+
+        let files = doc["files"].as_array()
+            .map(|files| files.iter().filter_map(|f| {
+                let filename = f["filename"].as_str()?.to_string();
+                let url = page_url.join(f["url"].as_str()?).ok()?.to_string();
+                let hashes = f["hashes"].as_object()
+                    .map(|h| h.iter().filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string()))).collect())
+                    .unwrap_or_default();
+                // `yanked` is either a bool or the reason string
+                let (yanked, yanked_reason) = match &f["yanked"] {
+                    serde_json::Value::Bool(b) => (*b, None),
+                    serde_json::Value::String(reason) => (true, Some(reason.clone()).filter(|r| !r.is_empty())),
+                    _ => (false, None),
+                };
+                let metadata = if f.get("core-metadata").is_some() { &f["core-metadata"] } else { &f["dist-info-metadata"] };
+                let has_metadata = !matches!(metadata, serde_json::Value::Null | serde_json::Value::Bool(false));
+
+                Some(SimpleFile {
+                    filename,
+                    url,
+                    hashes,
+                    requires_python: f["requires-python"].as_str().map(|s| s.to_string()),
+                    yanked,
+                    yanked_reason,
+                    has_metadata,
+                    size: f["size"].as_u64(),
+                    upload_time: f["upload-time"].as_str().map(|s| s.to_string()),
+                })
+            }).collect())
+            .unwrap_or_default();
+
+        Ok(Self { name, files })
+    }
+
+    /// Parse a PEP 503 HTML project page
+    pub fn from_html(name: &str, body: &str, page_url: &Url) -> Result<Self> {
+        let mut files = Vec::new();
+
+        for anchor in ANCHOR.captures_iter(body) {
+            let mut attrs = HashMap::new();
+            for attr in ATTRIBUTE.captures_iter(&anchor[1]) {
+                let value = attr.get(2).or(attr.get(3)).or(attr.get(4)).map(|v| decode_entities(v.as_str()));
+                attrs.insert(attr[1].to_lowercase(), value);
+            }
+
+            let Some(Some(href)) = attrs.get("href") else { continue };
+            let Ok(mut url) = page_url.join(href) else { continue };
+
+            // Hashes travel in the URL fragment: #sha256=<hex>
+            let mut hashes = HashMap::new();
+            if let Some((algo, digest)) = url.fragment().and_then(|f| f.split_once('=')) {
+                hashes.insert(algo.to_string(), digest.to_string());
+            }
+            url.set_fragment(None);
+
+            let filename = decode_entities(anchor[2].trim());
+            let filename = if filename.is_empty() {
+                url.path_segments().and_then(|mut s| s.next_back()).unwrap_or_default().to_string()
+            } else {
+                filename
+            };
+
+            let yanked_reason = attrs.get("data-yanked").cloned();
+            let metadata = attrs.get("data-core-metadata").or(attrs.get("data-dist-info-metadata"));
+
+            files.push(SimpleFile {
+                filename,
+                url: url.to_string(),
+                hashes,
+                requires_python: attrs.get("data-requires-python").cloned().flatten(),
+                yanked: yanked_reason.is_some(),
+                yanked_reason: yanked_reason.flatten().filter(|r| !r.is_empty()),
+                has_metadata: metadata.is_some_and(|m| m.as_deref() != Some("false")),
+                size: None,
+                upload_time: None,
+            });
+        }
+
+        Ok(Self { name: name.to_string(), files })
+    }
+
+    /// Group files into the JSON-API shape the resolver and installer already understand.
+    ///
+    /// The simple API has no per-release metadata, so `requires_dist` has to be supplied
+    /// by the caller (see [`SimpleIndexClient::fetch_core_metadata`]).
+    pub fn into_package_info(self, requires_dist: Option<Vec<String>>) -> PyPIPackageInfo {
+        let mut releases: HashMap<String, Vec<PyPIRelease>> = HashMap::new();
+        for file in self.files {
+            let Some(version) = file.version() else { continue };
+            releases.entry(version).or_default().push(PyPIRelease {
+                filename: file.filename,
+                url: file.url,
+                size: file.size,
+                upload_time: file.upload_time,
+                digests: Some(file.hashes),
+                yanked: file.yanked,
+                yanked_reason: file.yanked_reason,
+                requires_python: file.requires_python,
+            });
+        }
+
+        let mut info = PyPIPackageInfo {
+            info: PyPIInfo {
+                name: self.name,
+                version: String::new(),
+                summary: None,
+                description: None,
+                author: None,
+                license: None,
+                home_page: None,
+                requires_dist,
+            },
+            releases,
+        };
+        info.info.version = info.latest_unyanked().unwrap_or_default();
+        info
+    }
+}
+
+/// Client for any PEP 503 / PEP 691 compliant index (PyPI, devpi, Artifactory, ...)
+pub struct SimpleIndexClient {
+    client: Client,
+    index_url: String,
+    subsystem: Subsystem,
+}
+
+impl SimpleIndexClient {
+    pub fn new(client: Client, index_url: &str) -> Self {
+        Self {
+            client,
+            index_url: index_url.trim_end_matches('/').to_string(),
+            subsystem: Subsystem::Resolver,
+        }
+    }
+
+    /// Who `snakepit stats` attributes this client's requests to
+    pub fn with_subsystem(mut self, subsystem: Subsystem) -> Self {
+        self.subsystem = subsystem;
+        self
+    }
+
+    pub fn index_url(&self) -> &str {
+        &self.index_url
+    }
+
+    pub async fn fetch_project(&self, package: &str) -> Result<SimpleProject> {
+        let name = crate::manifest::canonical_name(package);
+        let page_url = Url::parse(&format!("{}/{}/", self.index_url, name))?;
+
+        let response = {
+            let _permit = rate_limit::acquire(self.subsystem).await;
+            self.client.get(page_url.clone())
+                .header(ACCEPT, ACCEPT_SIMPLE)
+                .send()
+                .await?
+        };
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("{} returned {} for {}", self.index_url, response.status(), package));
+        }
+
+        // Redirects change the base that relative file URLs resolve against
+        let page_url = response.url().clone();
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        let body = response.text().await?;
+
+        if content_type.starts_with(SIMPLE_JSON) {
+            SimpleProject::from_json(&body, &page_url)
+        } else {
+            SimpleProject::from_html(&name, &body, &page_url)
+        }
+    }
+
+    /// `Requires-Dist` of a file, when the index serves PEP 658 metadata for it
+    pub async fn fetch_core_metadata(&self, file: &SimpleFile) -> Result<Option<Vec<String>>> {
+        if !file.has_metadata {
+            return Ok(None);
+        }
+        let response = {
+            let _permit = rate_limit::acquire(self.subsystem).await;
+            self.client.get(format!("{}.metadata", file.url)).send().await?
+        };
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+        Ok(Some(parse_requires_dist(&response.text().await?)))
+    }
+
+    /// Project page plus the dependencies of its newest release, shaped like the JSON API
+    pub async fn fetch_package_info(&self, package: &str) -> Result<PyPIPackageInfo> {
+        let project = self.fetch_project(package).await?;
+        if project.files.is_empty() {
+            return Err(anyhow::anyhow!("No files for {} on {}", package, self.index_url));
+        }
+
+        let latest = project.clone().into_package_info(None).info.version;
+        let requires_dist = self.release_requires_dist(&project, &latest).await.unwrap_or(None);
+
+        Ok(project.into_package_info(requires_dist))
+    }
+
+    /// `Requires-Dist` of one release, when the index serves PEP 658 metadata for it
+    pub async fn fetch_requires_dist(&self, package: &str, version: &str) -> Result<Option<Vec<String>>> {
+        let project = self.fetch_project(package).await?;
+        self.release_requires_dist(&project, version).await
+    }
+
+    /// From a wheel of `version` if there is one: an sdist's metadata may be incomplete
+    async fn release_requires_dist(&self, project: &SimpleProject, version: &str) -> Result<Option<Vec<String>>> {
+        let wanted = crate::pep440::Version::parse(version).ok();
+        let metadata_file = project.files.iter()
+            .filter(|f| f.has_metadata)
+            .filter(|f| match (f.version(), &wanted) {
+                (Some(v), Some(wanted)) => crate::pep440::Version::parse(&v).is_ok_and(|v| v == *wanted),
+                (v, _) => v.as_deref() == Some(version),
+            })
+            .max_by_key(|f| f.filename.ends_with(".whl"));
+        match metadata_file {
+            Some(file) => self.fetch_core_metadata(file).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Every project name on the index root page (several MB for PyPI)
+    pub async fn fetch_project_names(&self) -> Result<Vec<String>> {
+        let response = {
+            let _permit = rate_limit::acquire(self.subsystem).await;
+            self.client.get(format!("{}/", self.index_url))
+                .header(ACCEPT, ACCEPT_SIMPLE)
+                .send()
+                .await?
+        };
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("{} returned {} for the project list", self.index_url, response.status()));
+        }
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        parse_project_list(&response.text().await?, &content_type)
+    }
+}
+
+/// Project names from an index root page, PEP 691 JSON or PEP 503 HTML
+pub fn parse_project_list(body: &str, content_type: &str) -> Result<Vec<String>> {
+    if content_type.starts_with(SIMPLE_JSON) {
+        let root: serde_json::Value = serde_json::from_str(body)?;
+        return Ok(root["projects"].as_array()
+            .map(|projects| projects.iter().filter_map(|p| p["name"].as_str().map(str::to_string)).collect())
+            .unwrap_or_default());
+    }
+    Ok(ANCHOR.captures_iter(body)
+        .map(|anchor| decode_entities(anchor[2].trim()))
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+/// Pull `Requires-Dist` headers out of a METADATA document
+pub fn parse_requires_dist(metadata: &str) -> Vec<String> {
+    metadata.lines()
+        // Headers end at the first blank line; the description body follows
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| line.strip_prefix("Requires-Dist:"))
+        .map(|req| req.trim().to_string())
+        .collect()
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_list_parsing() {
+        let json = r#"{"meta": {"api-version": "1.0"}, "projects": [{"name": "requests"}, {"name": "Flask"}]}"#;
+        assert_eq!(parse_project_list(json, SIMPLE_JSON).unwrap(), vec!["requests", "Flask"]);
+
+        let html = "<html><body><a href=\"/simple/requests/\">requests</a>\n<a href=\"/simple/zope-interface/\">zope.interface</a></body></html>";
+        assert_eq!(parse_project_list(html, "text/html").unwrap(), vec!["requests", "zope.interface"]);
+    }
+
+    #[test]
+    fn test_html_page_parsing() {
+        let page = Url::parse("https://index.example.com/simple/demo/").unwrap();
+        let html = r#"<!DOCTYPE html><html><body>
+            <a href="../../files/demo-1.0-py3-none-any.whl#sha256=abc123" data-requires-python="&gt;=3.8" data-dist-info-metadata="sha256=def">demo-1.0-py3-none-any.whl</a>
+            <a href="https://cdn.example.com/demo-1.1.tar.gz" data-yanked="broken build">demo-1.1.tar.gz</a>
+        </body></html>"#;
+
+        let project = SimpleProject::from_html("demo", html, &page).unwrap();
+        assert_eq!(project.files.len(), 2);
+
+        let wheel = &project.files[0];
+        assert_eq!(wheel.url, "https://index.example.com/files/demo-1.0-py3-none-any.whl");
+        assert_eq!(wheel.hashes.get("sha256").map(|s| s.as_str()), Some("abc123"));
+        assert_eq!(wheel.requires_python.as_deref(), Some(">=3.8"));
+        assert!(wheel.has_metadata && !wheel.yanked);
+
+        let sdist = &project.files[1];
+        assert_eq!(sdist.version().as_deref(), Some("1.1"));
+        assert!(sdist.yanked);
+        assert_eq!(sdist.yanked_reason.as_deref(), Some("broken build"));
+
+        let info = project.into_package_info(None);
+        assert_eq!(info.info.version, "1.0");
+    }
+
+    #[test]
+    fn test_json_page_parsing() {
+        let page = Url::parse("https://pypi.org/simple/demo/").unwrap();
+        let json = r#"{
+            "meta": {"api-version": "1.1"},
+            "name": "demo",
+            "files": [
+                {"filename": "demo-2.0-py3-none-any.whl", "url": "https://files.example/demo-2.0-py3-none-any.whl",
+                 "hashes": {"sha256": "ff"}, "requires-python": ">=3.9", "core-metadata": {"sha256": "aa"}, "size": 1024},
+                {"filename": "demo-2.1.tar.gz", "url": "/files/demo-2.1.tar.gz", "hashes": {}, "yanked": "security issue"}
+            ]
+        }"#;
+
+        let project = SimpleProject::from_json(json, &page).unwrap();
+        assert_eq!(project.files[0].size, Some(1024));
+        assert!(project.files[0].has_metadata);
+        assert_eq!(project.files[1].url, "https://pypi.org/files/demo-2.1.tar.gz");
+        assert_eq!(project.files[1].yanked_reason.as_deref(), Some("security issue"));
+    }
+
+    #[test]
+    fn test_requires_dist_stops_at_body() {
+        let metadata = "Metadata-Version: 2.1\nName: demo\nRequires-Dist: idna (>=2.5)\nRequires-Dist: socks ; extra == \"socks\"\n\nRequires-Dist: not-a-header\n";
+        assert_eq!(parse_requires_dist(metadata), vec!["idna (>=2.5)", "socks ; extra == \"socks\""]);
+    }
+}
@@ -2,9 +2,9 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tokio::fs;
+use crate::logger;
 use crate::daemon::{ModuleError, DaemonConfig};
 use snakegg::native::dirs;
-use snakegg::native::style::{dim, green};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SnakeskinState {
@@ -38,7 +38,7 @@ impl Snakeskin {
     pub async fn shed(&self, state: &SnakeskinState) -> Result<()> {
         let json = serde_json::to_string_pretty(state)?;
         fs::write(&self.path, json).await?;
-        println!("{}", dim("🐍 Snakeskin shed (state saved)"));
+        logger::note("🐍 Snakeskin shed (state saved)");
         Ok(())
     }
 
@@ -49,7 +49,7 @@ impl Snakeskin {
 
         let json = fs::read_to_string(&self.path).await?;
         let state: SnakeskinState = serde_json::from_str(&json)?;
-        println!("{}", green("🐍 Snakeskin regrown (state restored)"));
+        logger::success("🐍 Snakeskin regrown (state restored)");
         Ok(Some(state))
     }
 }
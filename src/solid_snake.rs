@@ -3,6 +3,7 @@ use std::process::Command;
 use serde::{Serialize, Deserialize};
 
 use snakegg::native::which;
+use crate::logger;
 
 pub struct SolidSnakeEngine {
     adb_path: String,
@@ -42,17 +43,17 @@ pub struct PerformanceMetrics {
 
 impl SolidSnakeEngine {
     pub fn new() -> Result<Self> {
-        println!("🎮 Solid Snake Engine initializing...");
+        logger::info("🎮 Solid Snake Engine initializing...");
         
         // Find adb in PATH
         let adb_path = which::find_executable("adb")
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_else(|| {
-                println!("⚠️  ADB not found in PATH, using 'adb'");
+                logger::warn("⚠️  ADB not found in PATH, using 'adb'");
                 "adb".to_string()
             });
         
-        println!("📱 ADB path: {}", adb_path);
+        logger::info(format!("📱 ADB path: {}", adb_path));
         
         Ok(Self {
             adb_path,
@@ -61,7 +62,7 @@ impl SolidSnakeEngine {
     }
     
     pub async fn discover_devices(&mut self) -> Result<Vec<AndroidDevice>> {
-        println!("🔍 Discovering Android devices...");
+        logger::info("🔍 Discovering Android devices...");
         
         // List devices via ADB
         let output = Command::new(&self.adb_path)
@@ -87,7 +88,7 @@ impl SolidSnakeEngine {
                 match self.probe_device(&id).await {
                     Ok(device) => devices.push(device),
                     Err(e) => {
-                        println!("⚠️  Failed to probe device {}: {}", id, e);
+                        logger::warn(format!("⚠️  Failed to probe device {}: {}", id, e));
                     }
                 }
             }
@@ -96,24 +97,24 @@ impl SolidSnakeEngine {
         self.connected_devices = devices.clone();
         
         if devices.is_empty() {
-            println!("❌ No devices found");
-            println!("💡 Make sure:");
-            println!("   • USB debugging is enabled");
-            println!("   • Device is connected via USB or WiFi");
-            println!("   • ADB is installed and in PATH");
+            logger::error("❌ No devices found");
+            logger::info("💡 Make sure:");
+            logger::info("   • USB debugging is enabled");
+            logger::info("   • Device is connected via USB or WiFi");
+            logger::info("   • ADB is installed and in PATH");
         } else {
-            println!("✅ Found {} device(s)", devices.len());
+            logger::success(format!("✅ Found {} device(s)", devices.len()));
             for dev in &devices {
                 let conn_type = match &dev.connection_type {
                     ConnectionType::USB => "USB",
                     ConnectionType::WiFi(_) => "WiFi",
                     ConnectionType::Bluetooth(_) => "Bluetooth",
                 };
-                println!("   📱 {} ({}) - {}", dev.name, dev.id, conn_type);
+                logger::info(format!("   📱 {} ({}) - {}", dev.name, dev.id, conn_type));
                 if let Some(py_ver) = &dev.python_version {
-                    println!("      🐍 Python: {}", py_ver);
+                    logger::info(format!("      🐍 Python: {}", py_ver));
                 } else if !dev.termux_installed {
-                    println!("      ⚠️  Termux not installed");
+                    logger::info("      ⚠️  Termux not installed");
                 }
             }
         }
@@ -173,10 +174,10 @@ impl SolidSnakeEngine {
     }
     
     pub async fn connect_wifi(&self, ip_address: &str, port: u16) -> Result<()> {
-        println!("📡 Connecting to {}:{}...", ip_address, port);
+        logger::info(format!("📡 Connecting to {}:{}...", ip_address, port));
         
         // First, enable tcpip mode on USB-connected device
-        println!("   Enabling TCP/IP mode...");
+        logger::info("   Enabling TCP/IP mode...");
         let output = Command::new(&self.adb_path)
             .args(&["tcpip", &port.to_string()])
             .output()?;
@@ -187,11 +188,11 @@ impl SolidSnakeEngine {
         }
         
         // Wait for device to restart in TCP/IP mode
-        println!("   Waiting for device to restart...");
+        logger::info("   Waiting for device to restart...");
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
         
         // Connect via WiFi
-        println!("   Connecting...");
+        logger::info("   Connecting...");
         let output = Command::new(&self.adb_path)
             .args(&["connect", &format!("{}:{}", ip_address, port)])
             .output()?;
@@ -199,8 +200,8 @@ impl SolidSnakeEngine {
         let result = String::from_utf8_lossy(&output.stdout);
         
         if result.contains("connected") {
-            println!("✅ Connected via WiFi to {}:{}", ip_address, port);
-            println!("💡 You can now disconnect USB cable");
+            logger::success(format!("✅ Connected via WiFi to {}:{}", ip_address, port));
+            logger::info("💡 You can now disconnect USB cable");
             Ok(())
         } else {
             Err(anyhow::anyhow!("Failed to connect: {}", result))
@@ -208,14 +209,14 @@ impl SolidSnakeEngine {
     }
     
     pub async fn disconnect_wifi(&self, ip_address: &str, port: u16) -> Result<()> {
-        println!("🔌 Disconnecting from {}:{}...", ip_address, port);
+        logger::info(format!("🔌 Disconnecting from {}:{}...", ip_address, port));
         
         let output = Command::new(&self.adb_path)
             .args(&["disconnect", &format!("{}:{}", ip_address, port)])
             .output()?;
         
         if output.status.success() {
-            println!("✅ Disconnected");
+            logger::success("✅ Disconnected");
             Ok(())
         } else {
             let error = String::from_utf8_lossy(&output.stderr);
@@ -224,7 +225,7 @@ impl SolidSnakeEngine {
     }
     
     pub async fn install_package(&self, device_id: &str, package: &str) -> Result<()> {
-        println!("📦 Installing {} on device {}...", package, device_id);
+        logger::info(format!("📦 Installing {} on device {}...", package, device_id));
         
         // Check if Termux is installed
         let device = self.connected_devices.iter()
@@ -248,7 +249,7 @@ impl SolidSnakeEngine {
             .output()?;
         
         if output.status.success() {
-            println!("✅ Installed {}", package);
+            logger::success(format!("✅ Installed {}", package));
             Ok(())
         } else {
             let error = String::from_utf8_lossy(&output.stderr);
@@ -257,19 +258,19 @@ impl SolidSnakeEngine {
     }
     
     pub async fn run_tests(&self, device_id: &str, test_file: &str) -> Result<TestResults> {
-        println!("🧪 Running tests on device {}...", device_id);
+        logger::info(format!("🧪 Running tests on device {}...", device_id));
         
         let start = std::time::Instant::now();
         
         // Push test file to device
-        println!("   Pushing test file...");
+        logger::info("   Pushing test file...");
         let remote_path = "/data/local/tmp/test.py";
         Command::new(&self.adb_path)
             .args(&["-s", device_id, "push", test_file, remote_path])
             .output()?;
         
         // Run pytest
-        println!("   Running tests...");
+        logger::info("   Running tests...");
         let output = Command::new(&self.adb_path)
             .args(&[
                 "-s", device_id,
@@ -286,9 +287,9 @@ impl SolidSnakeEngine {
         let passed = output.status.success();
         
         if passed {
-            println!("✅ All tests passed ({} ms)", duration_ms);
+            logger::success(format!("✅ All tests passed ({} ms)", duration_ms));
         } else {
-            println!("❌ Some tests failed ({} ms)", duration_ms);
+            logger::error(format!("❌ Some tests failed ({} ms)", duration_ms));
         }
         
         Ok(TestResults {
@@ -300,7 +301,7 @@ impl SolidSnakeEngine {
     }
     
     pub async fn profile_performance(&self, device_id: &str, script: &str) -> Result<PerformanceMetrics> {
-        println!("⚡ Profiling performance on device {}...", device_id);
+        logger::info(format!("⚡ Profiling performance on device {}...", device_id));
         
         // Push script to device
         let remote_path = "/data/local/tmp/profile_script.py";
@@ -320,7 +321,7 @@ impl SolidSnakeEngine {
         let profile_output = String::from_utf8_lossy(&output.stdout).to_string();
         
         // Parse metrics (simplified - would need proper parsing)
-        println!("📊 Profile complete");
+        logger::info("📊 Profile complete");
         
         Ok(PerformanceMetrics {
             total_time: 0.0, // Would parse from output
@@ -330,8 +331,8 @@ impl SolidSnakeEngine {
     }
     
     pub async fn stream_logs(&self, device_id: &str) -> Result<()> {
-        println!("📜 Streaming logs from device {}...", device_id);
-        println!("   Press Ctrl+C to stop");
+        logger::info(format!("📜 Streaming logs from device {}...", device_id));
+        logger::info("   Press Ctrl+C to stop");
         
         // Stream logcat
         let mut child = Command::new(&self.adb_path)
@@ -1,13 +1,17 @@
-use crate::pep440::Version;
-use std::collections::{HashMap, HashSet};
-use std::fmt;
+use crate::manifest::canonical_name;
+use crate::pep440::{Operator, Specifier, SpecifierSet, Version};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::rc::Rc;
 use anyhow::Result;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
 // Represents a package name
 pub type PackageName = String;
 
+/// A subset of the versions known for one package
+pub type VersionSet = BTreeSet<Version>;
+
 // Represents a version constraint (simplified for now, will need full range support)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Constraint {
@@ -17,6 +21,8 @@ pub enum Constraint {
     Union(Vec<Constraint>),
     Intersection(Vec<Constraint>),
     Not(Box<Constraint>),
+    /// A PEP 440 specifier set, e.g. `>=1.4,!=1.5.*`
+    Specifiers(SpecifierSet),
 }
 
 impl Constraint {
@@ -28,6 +34,18 @@ impl Constraint {
             Constraint::Union(constraints) => constraints.iter().any(|c| c.allows(version)),
             Constraint::Intersection(constraints) => constraints.iter().all(|c| c.allows(version)),
             Constraint::Not(c) => !c.allows(version),
+            Constraint::Specifiers(specs) => specs.contains(version),
+        }
+    }
+
+    /// Whether this asks for exactly `version` (`==1.2.3`), the only way a yanked release is chosen (PEP 592)
+    pub fn pins(&self, version: &Version) -> bool {
+        match self {
+            Constraint::Exact(v) => v == version,
+            Constraint::Specifiers(specs) => specs.contains(version) && specs.iter()
+                .any(|spec| matches!(spec.operator, Operator::Equal | Operator::ArbitraryEqual) && !spec.wildcard),
+            Constraint::Intersection(constraints) => self.allows(version) && constraints.iter().any(|c| c.pins(version)),
+            _ => false,
         }
     }
 
@@ -59,35 +77,76 @@ impl Constraint {
     }
 }
 
-// A Term represents a statement about a package version: "Package A is selected and satisfies Constraint C"
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A statement about one package: "selected at one of `versions`" when positive,
+/// "not selected at any of `versions`" (which includes not selected at all) when negative.
+///
+/// `versions` is always a subset of the versions known for the package, so set
+/// operations are exact.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Term {
     pub package: PackageName,
-    pub constraint: Constraint,
-    pub positive: bool, // true = "is selected", false = "is NOT selected" (or satisfies constraint)
+    pub versions: VersionSet,
+    pub positive: bool,
 }
 
 impl Term {
-    pub fn new(package: PackageName, constraint: Constraint) -> Self {
-        Self { package, constraint, positive: true }
+    pub fn positive(package: &str, versions: VersionSet) -> Self {
+        Self { package: package.to_string(), versions, positive: true }
     }
-    
+
+    pub fn negative(package: &str, versions: VersionSet) -> Self {
+        Self { package: package.to_string(), versions, positive: false }
+    }
+
+    /// True whatever happens to the package
+    pub fn any(package: &str) -> Self {
+        Self::negative(package, VersionSet::new())
+    }
+
     pub fn negate(&self) -> Self {
         Self {
             package: self.package.clone(),
-            constraint: self.constraint.clone(),
+            versions: self.versions.clone(),
             positive: !self.positive,
         }
     }
-    
-    // Check relation with another term (subset, disjoint, overlap)
+
+    pub fn intersection(&self, other: &Term) -> Term {
+        debug_assert_eq!(self.package, other.package);
+        match (self.positive, other.positive) {
+            (true, true) => Term::positive(&self.package, self.versions.intersection(&other.versions).cloned().collect()),
+            (true, false) => Term::positive(&self.package, self.versions.difference(&other.versions).cloned().collect()),
+            (false, true) => Term::positive(&self.package, other.versions.difference(&self.versions).cloned().collect()),
+            (false, false) => Term::negative(&self.package, self.versions.union(&other.versions).cloned().collect()),
+        }
+    }
+
+    pub fn union(&self, other: &Term) -> Term {
+        self.negate().intersection(&other.negate()).negate()
+    }
+
+    /// Nothing satisfies it
+    pub fn is_empty(&self) -> bool {
+        self.positive && self.versions.is_empty()
+    }
+
+    pub fn is_any(&self) -> bool {
+        !self.positive && self.versions.is_empty()
+    }
+
     pub fn relation(&self, other: &Term) -> SetRelation {
         if self.package != other.package {
-            return SetRelation::Disjoint; 
+            return SetRelation::Disjoint;
+        }
+
+        let common = self.intersection(other);
+        if common == *self {
+            SetRelation::Subset
+        } else if common.is_empty() {
+            SetRelation::Disjoint
+        } else {
+            SetRelation::Overlapping
         }
-        
-        // Simplified relation logic
-        SetRelation::Overlapping
     }
 }
 
@@ -103,6 +162,7 @@ pub enum SetRelation {
 // Usually derived from dependencies: "A depends on B" -> { A, not B }
 #[derive(Debug, Clone)]
 pub struct Incompatibility {
+    /// At most one term per package
     pub terms: Vec<Term>,
     pub cause: IncompatibilityCause,
 }
@@ -112,22 +172,55 @@ pub enum IncompatibilityCause {
     Dependency(PackageName, PackageName), // A depends on B
     Root, // Root package requirement
     NoVersion, // No version matches constraint
-    Conflict, // Derived conflict
+    /// Learned during conflict resolution from an incompatibility and the cause of its satisfier
+    Derived(Rc<Incompatibility>, Rc<Incompatibility>),
+}
+
+impl Incompatibility {
+    /// Terms on the same package are merged: all of them holding means their intersection holds
+    pub fn new(terms: Vec<Term>, cause: IncompatibilityCause) -> Self {
+        let mut merged: Vec<Term> = Vec::new();
+        for term in terms {
+            match merged.iter_mut().find(|t| t.package == term.package) {
+                Some(existing) => *existing = existing.intersection(&term),
+                None => merged.push(term),
+            }
+        }
+        Self { terms: merged, cause }
+    }
+
+    pub fn get(&self, package: &str) -> Option<&Term> {
+        self.terms.iter().find(|t| t.package == package)
+    }
+
+    /// Satisfied no matter what is chosen, so there is no solution
+    fn is_terminal(&self, root: &str) -> bool {
+        let mut terms = self.terms.iter().filter(|t| !t.is_any());
+        match (terms.next(), terms.next()) {
+            (None, _) => true,
+            (Some(term), None) => term.package == root && term.positive,
+            _ => false,
+        }
+    }
+}
+
+/// How the partial solution relates to a term or an incompatibility
+#[derive(Debug, Clone, PartialEq)]
+enum Relation {
+    Satisfied,
+    Contradicted,
+    /// Every term but this one is satisfied
+    AlmostSatisfied(Term),
+    Inconclusive,
 }
 
-// An Assignment is a decision made by the solver
+// An Assignment is a decision made by the solver, or a term derived from an incompatibility
 #[derive(Debug, Clone)]
-pub enum Assignment {
-    Decision {
-        package: PackageName,
-        version: Version,
-        decision_level: usize,
-    },
-    Derivation {
-        term: Term,
-        cause: Rc<Incompatibility>, // The incompatibility that forced this derivation
-        decision_level: usize,
-    },
+pub struct Assignment {
+    pub term: Term,
+    pub decision_level: usize,
+    /// The incompatibility that forced this derivation; `None` for decisions
+    pub cause: Option<Rc<Incompatibility>>,
 }
 
 // The PartialSolution tracks the current state of assignments
@@ -144,383 +237,956 @@ impl PartialSolution {
         }
     }
 
-    pub fn assign(&mut self, assignment: Assignment) {
-        match &assignment {
-            Assignment::Decision { package, version, .. } => {
-                self.decisions.insert(package.clone(), version.clone());
-            }
-            Assignment::Derivation { .. } => {
-                // Derivations might narrow constraints, not necessarily pick a version yet
-            }
-        }
-        self.assignments.push(assignment);
+    pub fn decide(&mut self, package: &str, version: Version) {
+        self.decisions.insert(package.to_string(), version.clone());
+        self.assignments.push(Assignment {
+            term: Term::positive(package, VersionSet::from([version])),
+            decision_level: self.decision_level(),
+            cause: None,
+        });
+    }
+
+    pub fn derive(&mut self, term: Term, cause: Rc<Incompatibility>) {
+        self.assignments.push(Assignment {
+            term,
+            decision_level: self.decision_level(),
+            cause: Some(cause),
+        });
     }
-    
+
     pub fn decision_level(&self) -> usize {
-        // Count decisions
-        self.assignments.iter().filter(|a| matches!(a, Assignment::Decision { .. })).count()
+        self.decisions.len()
     }
-    pub fn satisfies(&self, term: &Term) -> bool {
-        if let Some(version) = self.decisions.get(&term.package) {
-            let allows = term.constraint.allows(version);
-            if term.positive { allows } else { !allows }
-        } else {
-            false
+
+    /// Everything the assignments say about `package`, combined
+    pub fn accumulated(&self, package: &str) -> Term {
+        self.assignments.iter()
+            .filter(|a| a.term.package == package)
+            .fold(Term::any(package), |acc, a| acc.intersection(&a.term))
+    }
+
+    fn relation(&self, term: &Term) -> Relation {
+        match self.accumulated(&term.package).relation(term) {
+            SetRelation::Subset => Relation::Satisfied,
+            SetRelation::Disjoint => Relation::Contradicted,
+            SetRelation::Overlapping => Relation::Inconclusive,
         }
     }
 
-    pub fn unsatisfies(&self, term: &Term) -> bool {
-        if let Some(version) = self.decisions.get(&term.package) {
-            let allows = term.constraint.allows(version);
-            if term.positive { !allows } else { allows }
-        } else {
-            false
+    /// Index of the assignment after which `term` first became satisfied
+    fn satisfier(&self, term: &Term) -> Option<usize> {
+        let mut accumulated = Term::any(&term.package);
+        for (i, assignment) in self.assignments.iter().enumerate() {
+            if assignment.term.package == term.package {
+                accumulated = accumulated.intersection(&assignment.term);
+                if accumulated.relation(term) == SetRelation::Subset {
+                    return Some(i);
+                }
+            }
         }
+        None
     }
-    
-    pub fn backtrack(&mut self, decision_level: usize) {
-        while self.decision_level() > decision_level {
-            if let Some(assignment) = self.assignments.pop() {
-                match assignment {
-                    Assignment::Decision { package, .. } => {
-                        self.decisions.remove(&package);
-                    }
-                    _ => {}
+
+    /// Earliest assignment before `satisfier` that, together with it, already satisfies `term`
+    fn previous_satisfier(&self, term: &Term, satisfier: usize) -> Option<usize> {
+        let satisfier_term = &self.assignments[satisfier].term;
+        if satisfier_term.relation(term) == SetRelation::Subset {
+            return None;
+        }
+
+        let mut accumulated = satisfier_term.clone();
+        for (i, assignment) in self.assignments[..satisfier].iter().enumerate() {
+            if assignment.term.package == term.package {
+                accumulated = accumulated.intersection(&assignment.term);
+                if accumulated.relation(term) == SetRelation::Subset {
+                    return Some(i);
                 }
             }
         }
+        None
+    }
+
+    /// Undo every assignment made after `decision_level`
+    pub fn backtrack(&mut self, decision_level: usize) {
+        self.assignments.retain(|a| a.decision_level <= decision_level);
+        self.decisions = self.assignments.iter()
+            .filter(|a| a.cause.is_none())
+            .filter_map(|a| a.term.versions.first().map(|v| (a.term.package.clone(), v.clone())))
+            .collect();
     }
 }
 
-use crate::resolver::DependencyResolver;
+use crate::resolver::{DependencyResolver, Resolution};
+use crate::markers::{TargetEnvironment, VersionSpecifier};
+use crate::prerelease::Prereleases;
+use crate::overrides::Overrides;
+use crate::constraints::Constraints;
+use crate::logger;
+
+type Dependencies = Vec<(PackageName, Constraint, Vec<String>)>;
 
-// The Solver driver
+// The Solver driver: PubGrub with unit propagation, conflict-driven learning and backjumping
 pub struct Solver {
     root: PackageName,
     root_version: Version,
     incompatibilities: Vec<Rc<Incompatibility>>,
     solution: PartialSolution,
     resolver: Arc<Mutex<DependencyResolver>>,
+    extras: HashMap<PackageName, HashSet<String>>,
+    /// Every version known for each package, ascending
+    universe: HashMap<PackageName, Vec<Version>>,
+    /// Yanked versions in the universe, with the reason given; only chosen when pinned
+    yanked: HashMap<PackageName, HashMap<Version, Option<String>>>,
+    /// Dependencies supplied up front; these packages are never looked up on the index
+    known: HashMap<(PackageName, Version), Dependencies>,
+    /// Versions to keep when still allowed (the current lock), instead of the newest
+    preferred: HashMap<PackageName, Version>,
+    /// Targets to resolve for; a dependency counts if its marker holds in any of them
+    environments: Vec<TargetEnvironment>,
+    /// Packages that may pick a pre-release over an older final release
+    prereleases: Prereleases,
+    /// Interpreters (`3.11`) whose Requires-Python every candidate release must accept
+    python_versions: Vec<String>,
+    /// Specifiers from snakepit.toml that replace every requirement on a package
+    overrides: Overrides,
+    /// `-c` constraint files: limits on versions that don't add requirements
+    constraints: Constraints,
+    resolution: Resolution,
 }
 
 impl Solver {
     pub fn new(root: PackageName, root_version: Version, resolver: Arc<Mutex<DependencyResolver>>) -> Self {
+        let root = canonical_name(&root);
         Self {
+            universe: HashMap::from([(root.clone(), vec![root_version.clone()])]),
+            yanked: HashMap::new(),
             root,
             root_version,
             incompatibilities: Vec::new(),
             solution: PartialSolution::new(),
             resolver,
+            extras: HashMap::new(),
+            known: HashMap::new(),
+            preferred: HashMap::new(),
+            environments: Vec::new(),
+            prereleases: Prereleases::new(),
+            python_versions: Vec::new(),
+            overrides: Overrides::new(),
+            constraints: Constraints::new(),
+            resolution: Resolution::Highest,
         }
     }
 
+    /// Request extras for a package, e.g. `socks` for `requests[socks]`
+    pub fn with_extras(mut self, package: &str, extras: &[String]) -> Self {
+        self.extras.entry(canonical_name(package))
+            .or_default()
+            .extend(extras.iter().map(|e| e.to_lowercase()));
+        self
+    }
+
+    /// Declare a version and its dependencies directly (the root project, local
+    /// packages); a package declared this way is never looked up on the index
+    pub fn with_package(mut self, package: &str, version: Version, dependencies: Vec<(PackageName, Constraint)>) -> Self {
+        let package = canonical_name(package);
+        let versions = self.universe.entry(package.clone()).or_default();
+        if !versions.contains(&version) {
+            versions.push(version.clone());
+            versions.sort();
+        }
+        let dependencies = dependencies.into_iter()
+            .map(|(name, constraint)| (name, constraint, Vec::new()))
+            .collect();
+        self.known.insert((package, version), dependencies);
+        self
+    }
+
+    /// Choose `version` of `package` whenever the constraints allow it, e.g. to keep
+    /// a locked version while other packages are upgraded around it
+    pub fn with_preferred(mut self, package: &str, version: Version) -> Self {
+        self.preferred.insert(canonical_name(package), version);
+        self
+    }
+
+    /// Resolve one set of versions that works on every target, e.g. linux and macos on
+    /// several Pythons; without targets, markers are evaluated for the host
+    pub fn with_environments(mut self, environments: Vec<TargetEnvironment>) -> Self {
+        self.environments = environments;
+        self
+    }
+
+    /// Drop releases whose Requires-Python rejects any of these interpreters
+    pub fn with_python_versions(mut self, python_versions: Vec<String>) -> Self {
+        self.python_versions = python_versions;
+        self
+    }
+
+    /// Pick the oldest allowed version of every package instead of the newest
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Only consider versions the constraint files allow
+    pub fn with_constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    pub fn with_prereleases(mut self, prereleases: Prereleases) -> Self {
+        self.prereleases = prereleases;
+        self
+    }
+
+    /// Extras requested for each package, by the user or by dependents
+    pub fn extras(&self) -> &HashMap<PackageName, HashSet<String>> {
+        &self.extras
+    }
+
     pub async fn solve(&mut self) -> Result<HashMap<PackageName, Version>> {
-        self.solution.assign(Assignment::Decision {
-            package: self.root.clone(),
-            version: self.root_version.clone(),
-            decision_level: 0,
-        });
+        // The root must be selected
+        let root_term = Term::negative(&self.root, VersionSet::from([self.root_version.clone()]));
+        self.incompatibilities.push(Rc::new(Incompatibility::new(vec![root_term], IncompatibilityCause::Root)));
 
+        let mut next = self.root.clone();
         loop {
-            if let Some(conflict) = self.propagate() {
-                if let Err(e) = self.resolve_conflict(conflict) {
-                    return Err(e); // Unsolvable
-                }
-                continue; // Retry propagation after backtracking
-            }
-            
-            if let Some(package) = self.choose_next_package() {
-                let version = self.fetch_best_version(&package).await?;
-                
-                // Add dependencies as incompatibilities
-                let deps = self.fetch_dependencies(&package, &version).await?;
-                for (dep_name, dep_constraint) in deps {
-                    let term1 = Term::new(package.clone(), Constraint::Exact(version.clone()));
-                    let term2 = Term::new(dep_name.clone(), dep_constraint).negate();
-                    
-                    self.incompatibilities.push(Rc::new(Incompatibility {
-                        terms: vec![term1, term2],
-                        cause: IncompatibilityCause::Dependency(package.clone(), dep_name),
-                    }));
-                }
-
-                self.solution.assign(Assignment::Decision {
-                    package,
-                    version,
-                    decision_level: self.solution.decision_level() + 1,
-                });
-            } else {
-                break; // Done
+            self.propagate(&next)?;
+            match self.choose_next_package() {
+                Some(package) => next = self.decide(package).await?,
+                None => break,
             }
         }
 
         Ok(self.solution.decisions.clone())
     }
 
-    fn resolve_conflict(&mut self, mut conflict: Rc<Incompatibility>) -> Result<()> {
-        if conflict.terms.iter().any(|t| t.package == self.root) && self.solution.decision_level() == 0 {
-            return Err(anyhow::anyhow!("Unsolvable conflict: {:?}", conflict));
-        }
+    /// Derive everything the incompatibilities force after `package` changed,
+    /// learning from and backjumping over any conflict found on the way
+    fn propagate(&mut self, package: &str) -> Result<()> {
+        let mut changed = vec![package.to_string()];
+        while let Some(package) = changed.pop() {
+            // Newest first: learned incompatibilities are the most specific
+            for i in (0..self.incompatibilities.len()).rev() {
+                let incompatibility = self.incompatibilities[i].clone();
+                if incompatibility.get(&package).is_none() {
+                    continue;
+                }
 
-        let current_level = self.solution.decision_level();
-        if current_level == 0 {
-             return Err(anyhow::anyhow!("Unsolvable conflict at root: {:?}", conflict));
+                match self.relation(&incompatibility) {
+                    Relation::Satisfied => {
+                        let root_cause = self.resolve_conflict(incompatibility)?;
+                        let Relation::AlmostSatisfied(term) = self.relation(&root_cause) else {
+                            return Err(anyhow::anyhow!("Solver bug: learned incompatibility is not almost satisfied after backjumping"));
+                        };
+                        changed.clear();
+                        changed.push(term.package.clone());
+                        self.solution.derive(term.negate(), root_cause);
+                        break;
+                    }
+                    Relation::AlmostSatisfied(term) => {
+                        if !changed.contains(&term.package) {
+                            changed.push(term.package.clone());
+                        }
+                        self.solution.derive(term.negate(), incompatibility);
+                    }
+                    Relation::Contradicted | Relation::Inconclusive => {}
+                }
+            }
         }
-        
-        let backtrack_level = current_level - 1;
-        self.solution.backtrack(backtrack_level);
-        
         Ok(())
     }
 
-    fn choose_next_package(&self) -> Option<PackageName> {
-        for assignment in &self.solution.assignments {
-            match assignment {
-                Assignment::Derivation { term, .. } => {
-                    if term.positive && !self.solution.decisions.contains_key(&term.package) {
-                        return Some(term.package.clone());
+    fn relation(&self, incompatibility: &Incompatibility) -> Relation {
+        let mut undecided = None;
+        for term in &incompatibility.terms {
+            match self.solution.relation(term) {
+                Relation::Satisfied => {}
+                Relation::Contradicted => return Relation::Contradicted,
+                _ if undecided.is_some() => return Relation::Inconclusive,
+                _ => undecided = Some(term.clone()),
+            }
+        }
+        match undecided {
+            Some(term) => Relation::AlmostSatisfied(term),
+            None => Relation::Satisfied,
+        }
+    }
+
+    /// Find the root cause of a satisfied incompatibility, learn it and backjump to
+    /// where it is no longer satisfied. Fails with an explanation when nothing can be chosen.
+    fn resolve_conflict(&mut self, conflict: Rc<Incompatibility>) -> Result<Rc<Incompatibility>> {
+        let mut incompatibility = conflict;
+        let mut learned = false;
+
+        loop {
+            if incompatibility.is_terminal(&self.root) {
+                return Err(anyhow::anyhow!("{}", self.explain(&incompatibility)));
+            }
+
+            // A dependency with no matching versions leaves a term that holds from the start
+            let satisfiers: Vec<(usize, &Term)> = incompatibility.terms.iter()
+                .filter(|term| !term.is_any())
+                .map(|term| self.solution.satisfier(term).map(|i| (i, term)))
+                .collect::<Option<_>>()
+                .ok_or_else(|| anyhow::anyhow!("Solver bug: conflict is not satisfied"))?;
+            let &(satisfier_index, satisfier_term) = satisfiers.iter()
+                .max_by_key(|(i, _)| *i)
+                .ok_or_else(|| anyhow::anyhow!("Solver bug: empty conflict"))?;
+            let satisfier = self.solution.assignments[satisfier_index].clone();
+
+            let mut previous_level = satisfiers.iter()
+                .filter(|(i, _)| *i != satisfier_index)
+                .map(|(i, _)| self.solution.assignments[*i].decision_level)
+                .max()
+                .unwrap_or(1)
+                .max(1);
+            if let Some(previous) = self.solution.previous_satisfier(satisfier_term, satisfier_index) {
+                previous_level = previous_level.max(self.solution.assignments[previous].decision_level);
+            }
+
+            match &satisfier.cause {
+                Some(cause) if previous_level == satisfier.decision_level => {
+                    // Resolve with the incompatibility that forced the satisfier
+                    let package = &satisfier.term.package;
+                    let mut terms: Vec<Term> = incompatibility.terms.iter()
+                        .chain(cause.terms.iter())
+                        .filter(|t| &t.package != package)
+                        .cloned()
+                        .collect();
+                    let own = incompatibility.get(package).cloned().unwrap_or_else(|| Term::any(package));
+                    let theirs = cause.get(package).cloned().unwrap_or_else(|| Term::any(package));
+                    let combined = own.union(&theirs);
+                    if !combined.is_any() {
+                        terms.push(combined);
+                    }
+                    incompatibility = Rc::new(Incompatibility::new(
+                        terms,
+                        IncompatibilityCause::Derived(incompatibility.clone(), cause.clone()),
+                    ));
+                    learned = true;
+                }
+                _ => {
+                    if learned {
+                        self.incompatibilities.push(incompatibility.clone());
                     }
+                    self.solution.backtrack(previous_level);
+                    return Ok(incompatibility);
                 }
-                _ => {}
             }
         }
-        None
     }
 
-    async fn fetch_best_version(&self, package: &str) -> Result<Version> {
-        let mut resolver = self.resolver.lock().unwrap();
-        let info = resolver.fetch_package_info(package).await?;
-        
-        // Find latest version (simplified)
-        // In real PubGrub, we'd pick the best version matching current constraints
-        // For now, just pick the latest one that parses
-        let mut best_version = None;
-        for v_str in info.releases.keys() {
-            if let Ok(v) = Version::parse(v_str) {
-                if best_version.as_ref().map_or(true, |best| &v > best) {
-                    best_version = Some(v);
-                }
+    /// The undecided package with the fewest versions left, which fails fastest
+    fn choose_next_package(&self) -> Option<PackageName> {
+        let mut candidates: Vec<PackageName> = self.solution.assignments.iter()
+            .filter(|a| a.term.positive && !self.solution.decisions.contains_key(&a.term.package))
+            .map(|a| a.term.package.clone())
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates.into_iter()
+            .min_by_key(|package| self.solution.accumulated(package).versions.len())
+    }
+
+    /// Pick a version of `package` and add its dependencies; returns the package to propagate from
+    async fn decide(&mut self, package: PackageName) -> Result<PackageName> {
+        let allowed = self.solution.accumulated(&package).versions;
+        // A preferred version if it's still allowed, else the newest (or oldest) final
+        // release; pre-releases only when opted in or when nothing else is allowed
+        let include_pre = self.prereleases.allows(&package);
+        let is_candidate = |v: &&Version| include_pre || (v.pre.is_none() && v.dev.is_none());
+        let version = self.preferred.get(&package).filter(|v| allowed.contains(*v))
+            .or_else(|| match self.resolution {
+                Resolution::Highest => allowed.iter().rev().find(is_candidate).or_else(|| allowed.last()),
+                Resolution::Lowest => allowed.iter().find(is_candidate).or_else(|| allowed.iter().next()),
+            })
+            .cloned();
+        let Some(version) = version else {
+            self.incompatibilities.push(Rc::new(Incompatibility::new(
+                vec![Term::positive(&package, allowed)],
+                IncompatibilityCause::NoVersion,
+            )));
+            return Ok(package);
+        };
+        if let Some(reason) = self.yanked.get(&package).and_then(|yanked| yanked.get(&version)) {
+            let reason = reason.as_ref().map(|r| format!(" (reason: {})", r)).unwrap_or_default();
+            logger::warn(format!(
+                "⚠️  {} {} has been yanked{}; installing it only because it is pinned",
+                package, version, reason
+            ));
+        }
+
+        let mut conflicting = false;
+        for (dep_name, dep_constraint, dep_extras) in self.dependencies(&package, &version).await? {
+            let dep_name = canonical_name(&dep_name);
+            if dep_name == package {
+                continue;
+            }
+            if !dep_extras.is_empty() {
+                self.extras.entry(dep_name.clone()).or_default().extend(dep_extras.iter().map(|e| e.to_lowercase()));
             }
+
+            let known_versions = self.versions(&dep_name).await?;
+            let yanked = self.yanked.get(&dep_name);
+            let dep_versions: VersionSet = known_versions
+                .into_iter()
+                .filter(|v| dep_constraint.allows(v))
+                .filter(|v| !yanked.is_some_and(|yanked| yanked.contains_key(v)) || dep_constraint.pins(v))
+                .collect();
+            let incompatibility = Incompatibility::new(
+                vec![
+                    Term::positive(&package, VersionSet::from([version.clone()])),
+                    Term::negative(&dep_name, dep_versions),
+                ],
+                IncompatibilityCause::Dependency(package.clone(), dep_name.clone()),
+            );
+            // A dependency the current assignments already rule out makes this version a dead end
+            conflicting |= incompatibility.terms.iter()
+                .filter(|t| t.package != package)
+                .all(|t| self.solution.relation(t) == Relation::Satisfied);
+            self.incompatibilities.push(Rc::new(incompatibility));
         }
-        
-        best_version.ok_or_else(|| anyhow::anyhow!("No valid versions found for {}", package))
+
+        if !conflicting {
+            self.solution.decide(&package, version);
+        }
+        Ok(package)
+    }
+
+    /// Every version of `package` the target interpreters can run, ascending
+    async fn versions(&mut self, package: &str) -> Result<Vec<Version>> {
+        if let Some(versions) = self.universe.get(package) {
+            return Ok(versions.clone());
+        }
+
+        let info = self.resolver.lock().await.fetch_package_info(package).await?;
+        let mut versions: Vec<Version> = info.releases.keys()
+            .filter(|v| self.python_versions.iter().all(|python| info.supports_python(v, python)))
+            .filter_map(|v| Version::parse(v).ok())
+            .collect();
+        versions.sort();
+        versions.dedup();
+        let yanked = info.releases.keys()
+            .filter(|v| info.is_yanked(v))
+            .filter_map(|v| Version::parse(v).ok().map(|parsed| (parsed, info.yank_reason(v))))
+            .collect();
+        self.yanked.insert(package.to_string(), yanked);
+        self.universe.insert(package.to_string(), versions.clone());
+        Ok(versions)
+    }
+
+    async fn dependencies(&self, package: &str, version: &Version) -> Result<Dependencies> {
+        let dependencies = match self.known.get(&(package.to_string(), version.clone())) {
+            Some(dependencies) => dependencies.clone(),
+            None if self.known.keys().any(|(p, _)| p == package) => Vec::new(),
+            None => self.fetch_dependencies(package, version).await?,
+        };
+        // Overrides replace what was asked for, wherever it was asked; constraints narrow it
+        Ok(dependencies.into_iter()
+            .map(|(name, constraint, extras)| match self.overrides.get(&name) {
+                Some(specs) => (name, requirement_constraint(specs), extras),
+                None => (name, constraint, extras),
+            })
+            .map(|(name, constraint, extras)| match self.constraints.get(&name) {
+                Some(specs) => (name, constraint.intersect(&requirement_constraint(specs)), extras),
+                None => (name, constraint, extras),
+            })
+            .collect())
     }
 
-    async fn fetch_dependencies(&self, package: &str, version: &Version) -> Result<Vec<(PackageName, Constraint)>> {
-        let mut resolver = self.resolver.lock().unwrap();
-        let info = resolver.fetch_package_info(package).await?;
+    async fn fetch_dependencies(&self, package: &str, version: &Version) -> Result<Vec<(PackageName, Constraint, Vec<String>)>> {
+        let requires = self.resolver.lock().await.fetch_requires_dist(package, &version.to_string()).await?;
+        let requested_extras: Vec<String> = self.extras.get(package)
+            .map(|e| e.iter().cloned().collect())
+            .unwrap_or_default();
         
         let mut deps = Vec::new();
-        if let Some(requires) = &info.info.requires_dist {
-            for req_str in requires {
-                // Use PEP 508 parser
-                if let Ok(spec) = crate::markers::parse_requirement(req_str) {
-                    // Skip if marker doesn't match (simple check)
-                    if let Some(marker) = &spec.marker {
-                        let target_env = crate::markers::TargetEnvironment::default();
-                        if !marker.evaluate(&target_env) {
-                            continue; // Skip this dependency
-                        }
-                    }
-                    
-                    // Convert version specs to Constraint
-                    let mut constraints = Vec::new();
-                    
-                    if spec.version_specs.is_empty() {
-                        constraints.push(Constraint::Any);
-                    } else {
-                        for vspec in &spec.version_specs {
-                            let c = match vspec.operator.as_str() {
-                                "==" => {
-                                    if let Ok(v) = Version::parse(&vspec.version) {
-                                        Constraint::Exact(v)
-                                    } else {
-                                        Constraint::Any
-                                    }
-                                },
-                                ">=" => {
-                                    if let Ok(min) = Version::parse(&vspec.version) {
-                                        let max = Version {
-                                            epoch: 9999,
-                                            release: vec![9999, 9999, 9999],
-                                            pre: None,
-                                            post: None,
-                                            dev: None,
-                                            local: None,
-                                        };
-                                        Constraint::Range(min, max)
-                                    } else {
-                                        Constraint::Any
-                                    }
-                                },
-                                "<=" => {
-                                    if let Ok(v) = Version::parse(&vspec.version) {
-                                        // <= v means < v OR == v
-                                        // < v is Range(MIN, v)
-                                        let min = Version {
-                                            epoch: 0,
-                                            release: vec![0],
-                                            pre: None,
-                                            post: None,
-                                            dev: None,
-                                            local: None,
-                                        };
-                                        Constraint::Union(vec![
-                                            Constraint::Range(min, v.clone()),
-                                            Constraint::Exact(v)
-                                        ])
-                                    } else {
-                                        Constraint::Any
-                                    }
-                                },
-                                ">" => {
-                                    if let Ok(v) = Version::parse(&vspec.version) {
-                                        // > v means >= v AND != v
-                                        // >= v is Range(v, MAX)
-                                        let max = Version {
-                                            epoch: 9999,
-                                            release: vec![9999, 9999, 9999],
-                                            pre: None,
-                                            post: None,
-                                            dev: None,
-                                            local: None,
-                                        };
-                                        Constraint::Intersection(vec![
-                                            Constraint::Range(v.clone(), max),
-                                            Constraint::Not(Box::new(Constraint::Exact(v)))
-                                        ])
-                                    } else {
-                                        Constraint::Any
-                                    }
-                                },
-                                "<" => {
-                                    if let Ok(v) = Version::parse(&vspec.version) {
-                                        let min = Version {
-                                            epoch: 0,
-                                            release: vec![0],
-                                            pre: None,
-                                            post: None,
-                                            dev: None,
-                                            local: None,
-                                        };
-                                        Constraint::Range(min, v)
-                                    } else {
-                                        Constraint::Any
-                                    }
-                                },
-                                "!=" => {
-                                    if let Ok(v) = Version::parse(&vspec.version) {
-                                        Constraint::Not(Box::new(Constraint::Exact(v)))
-                                    } else {
-                                        Constraint::Any
-                                    }
-                                },
-                                "~=" => {
-                                    // ~= 1.2.3 means >= 1.2.3 and == 1.2.*
-                                    // == 1.2.* means >= 1.2.0 and < 1.3.0
-                                    if let Ok(v) = Version::parse(&vspec.version) {
-                                        if v.release.len() < 2 {
-                                            Constraint::Any // Malformed for compatible release
-                                        } else {
-                                            // Remove last segment to get prefix
-                                            let mut prefix = v.release.clone();
-                                            prefix.pop();
-                                            // Increment last segment of prefix
-                                            if let Some(last) = prefix.last_mut() {
-                                                *last += 1;
-                                            }
-                                            let upper_bound = Version {
-                                                epoch: v.epoch,
-                                                release: prefix,
-                                                pre: None,
-                                                post: None,
-                                                dev: None,
-                                                local: None,
-                                            };
-                                            
-                                            let max = Version {
-                                                epoch: 9999,
-                                                release: vec![9999, 9999, 9999],
-                                                pre: None,
-                                                post: None,
-                                                dev: None,
-                                                local: None,
-                                            };
-                                            
-                                            // >= v AND < upper_bound
-                                            Constraint::Intersection(vec![
-                                                Constraint::Range(v, max),
-                                                Constraint::Range(Version { epoch: 0, release: vec![0], pre: None, post: None, dev: None, local: None }, upper_bound)
-                                            ])
-                                        }
-                                    } else {
-                                        Constraint::Any
-                                    }
-                                },
-                                _ => Constraint::Any,
-                            };
-                            constraints.push(c);
-                        }
+        for req_str in &requires {
+            // Use PEP 508 parser
+            if let Ok(spec) = crate::markers::parse_requirement(req_str) {
+                // Skip if the marker holds on none of the targets
+                if let Some(marker) = &spec.marker {
+                    if !marker.evaluate_any(&self.environments, &requested_extras) {
+                        continue; // Skip this dependency
                     }
-                    
-                    let final_constraint = if constraints.is_empty() {
-                        Constraint::Any
-                    } else if constraints.len() == 1 {
-                        constraints[0].clone()
-                    } else {
-                        Constraint::Intersection(constraints)
-                    };
-                    
-                    deps.push((spec.name, final_constraint));
                 }
+                
+                deps.push((canonical_name(&spec.name), requirement_constraint(&spec.version_specs), spec.extras));
             }
         }
         
         Ok(deps)
     }
 
-    fn propagate(&mut self) -> Option<Rc<Incompatibility>> {
-        let mut changed = true;
-        while changed {
-            changed = false;
-            for incompatibility in &self.incompatibilities {
-                let mut satisfied_count = 0;
-                let mut undecided_term = None;
-                let mut failed = false;
-
-                for term in &incompatibility.terms {
-                    if self.solution.satisfies(term) {
-                        satisfied_count += 1;
-                    } else if self.solution.unsatisfies(term) {
-                        failed = true;
-                        break;
-                    } else {
-                        if undecided_term.is_some() {
-                            failed = true; // More than one undecided
-                            break;
-                        }
-                        undecided_term = Some(term);
+    /// Human-readable derivation of why solving failed
+    fn explain(&self, incompatibility: &Incompatibility) -> String {
+        let report = Explanation::new(&self.root, &self.universe).build(incompatibility);
+        format!("No solution for {} {}:\n  {}", self.root, self.root_version, report.replace('\n', "\n  "))
+    }
+}
+
+/// The versions a PEP 508 specifier set such as `>=2.0,!=2.1` allows; clauses that
+/// don't parse are dropped rather than ruling out every version
+pub fn requirement_constraint(version_specs: &[VersionSpecifier]) -> Constraint {
+    let specifiers: Vec<Specifier> = version_specs.iter()
+        .filter_map(|spec| Specifier::new(&spec.operator, &spec.version).ok())
+        .collect();
+    if specifiers.is_empty() {
+        Constraint::Any
+    } else {
+        Constraint::Specifiers(SpecifierSet::from(specifiers))
+    }
+}
+
+/// PubGrub-style report over the derivation graph of the incompatibility that ended solving.
+///
+/// Each derived incompatibility becomes a "Because ..., ..." line built from its two causes,
+/// innermost first. Conclusions that more than one later line relies on are numbered so they
+/// can be referred back to instead of being derived twice.
+struct Explanation<'a> {
+    root: &'a str,
+    universe: &'a HashMap<PackageName, Vec<Version>>,
+    lines: Vec<String>,
+    /// Derived incompatibilities that are a cause of more than one other
+    shared: HashSet<*const Incompatibility>,
+    numbers: HashMap<*const Incompatibility, usize>,
+}
+
+impl<'a> Explanation<'a> {
+    fn new(root: &'a str, universe: &'a HashMap<PackageName, Vec<Version>>) -> Self {
+        Self {
+            root,
+            universe,
+            lines: Vec::new(),
+            shared: HashSet::new(),
+            numbers: HashMap::new(),
+        }
+    }
+
+    fn build(mut self, failure: &Incompatibility) -> String {
+        let mut references = HashMap::new();
+        count_references(failure, &mut references);
+        self.shared = references.into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(incompatibility, _)| incompatibility)
+            .collect();
+
+        if is_derived(failure) {
+            self.visit(failure);
+        } else {
+            // Failed straight from a fact, e.g. the project requires a version that doesn't exist
+            self.lines.push(format!("Because {}, version solving failed.", self.describe(failure)));
+        }
+        self.lines.join("\n")
+    }
+
+    /// Emit the lines that derive `current`, ending with the one that concludes it
+    fn visit(&mut self, current: &Incompatibility) {
+        let IncompatibilityCause::Derived(left, right) = &current.cause else {
+            return;
+        };
+        let conclusion = self.describe(current);
+
+        let line = match (is_derived(left), is_derived(right)) {
+            (true, true) => match (self.number(left), self.number(right)) {
+                (Some(l), Some(r)) => format!(
+                    "Because {} ({}) and {} ({}), {}.",
+                    self.describe(left), l, self.describe(right), r, conclusion
+                ),
+                (Some(n), None) => {
+                    self.visit(right);
+                    format!("And because {} ({}), {}.", self.describe(left), n, conclusion)
+                }
+                (None, Some(n)) => {
+                    self.visit(left);
+                    format!("And because {} ({}), {}.", self.describe(right), n, conclusion)
+                }
+                (None, None) => {
+                    // Derive the left side first and number it so the line after the right side can cite it
+                    self.visit(left);
+                    let n = self.number_last(left);
+                    self.visit(right);
+                    format!("And because {} ({}), {}.", self.describe(left), n, conclusion)
+                }
+            },
+            (true, false) | (false, true) => {
+                let (derived, external) = if is_derived(left) { (left, right) } else { (right, left) };
+                match self.number(derived) {
+                    Some(n) => format!(
+                        "Because {} and {} ({}), {}.",
+                        self.describe(external), self.describe(derived), n, conclusion
+                    ),
+                    None => {
+                        self.visit(derived);
+                        format!("And because {}, {}.", self.describe(external), conclusion)
                     }
                 }
+            }
+            (false, false) => format!(
+                "Because {} and {}, {}.",
+                self.describe(left), self.describe(right), conclusion
+            ),
+        };
 
-                if failed {
-                    continue;
+        self.lines.push(line);
+        if self.shared.contains(&(current as *const Incompatibility)) {
+            self.number_last(current);
+        }
+    }
+
+    fn number(&self, incompatibility: &Incompatibility) -> Option<usize> {
+        self.numbers.get(&(incompatibility as *const Incompatibility)).copied()
+    }
+
+    /// Number the line just emitted, which concludes `incompatibility`
+    fn number_last(&mut self, incompatibility: &Incompatibility) -> usize {
+        if let Some(n) = self.number(incompatibility) {
+            return n;
+        }
+        let n = self.numbers.len() + 1;
+        if let Some(line) = self.lines.last_mut() {
+            line.push_str(&format!(" ({})", n));
+        }
+        self.numbers.insert(incompatibility as *const Incompatibility, n);
+        n
+    }
+
+    fn describe(&self, incompatibility: &Incompatibility) -> String {
+        match &incompatibility.cause {
+            IncompatibilityCause::Root => format!("{} is the project being resolved", self.root),
+            IncompatibilityCause::NoVersion => match incompatibility.terms.first() {
+                Some(term) => format!("no versions of {} are available", self.term(term)),
+                None => "no versions are available".to_string(),
+            },
+            IncompatibilityCause::Dependency(package, dependency) => {
+                let dependency = incompatibility.get(dependency)
+                    .map(|t| self.term(t))
+                    .unwrap_or_else(|| dependency.clone());
+                match incompatibility.get(package) {
+                    _ if package == self.root => format!("your project requires {}", dependency),
+                    Some(from) => format!("{} depends on {}", self.term(from), dependency),
+                    None => format!("{} depends on {}", package, dependency),
+                }
+            }
+            IncompatibilityCause::Derived(..) => {
+                // The project is always selected, so a term saying so adds nothing but "your project"
+                let requires_root = incompatibility.get(self.root).is_some_and(|t| t.positive);
+                let terms: Vec<&Term> = incompatibility.terms.iter()
+                    .filter(|t| !t.is_any() && !(t.positive && t.package == self.root))
+                    .collect();
+                match terms.as_slice() {
+                    [] => "version solving failed".to_string(),
+                    [term] if term.positive => format!("{} is impossible", self.term(term)),
+                    [term] if requires_root => format!("your project requires {}", self.term(term)),
+                    [term] => format!("{} is required", self.term(term)),
+                    [a, b] if a.positive != b.positive => {
+                        let (from, to) = if a.positive { (a, b) } else { (b, a) };
+                        format!("{} depends on {}", self.term(from), self.term(to))
+                    }
+                    [a, b] if a.positive => format!("{} is incompatible with {}", self.term(a), self.term(b)),
+                    terms => {
+                        let parts: Vec<String> = terms.iter()
+                            .map(|t| format!("{}{}", if t.positive { "" } else { "not " }, self.term(t)))
+                            .collect();
+                        format!("{} are incompatible", parts.join(" and "))
+                    }
                 }
+            }
+        }
+    }
 
-                if let Some(term) = undecided_term {
-                    // One undecided, others satisfied -> Propagate negation
-                    self.solution.assign(Assignment::Derivation {
-                        term: term.negate(),
-                        cause: incompatibility.clone(),
-                        decision_level: self.solution.decision_level(),
-                    });
-                    changed = true;
-                } else if satisfied_count == incompatibility.terms.len() {
-                    // All satisfied -> Conflict
-                    return Some(incompatibility.clone());
+    /// A package and its versions as a specifier over the versions known for it, e.g.
+    /// `bar>=1.5,<2.0`; the package alone when every version is included
+    fn term(&self, term: &Term) -> String {
+        let all = self.universe.get(&term.package).map(Vec::as_slice).unwrap_or_default();
+        let versions: Vec<&Version> = term.versions.iter().collect();
+        let specifier = match versions.as_slice() {
+            [] => " (no matching versions)".to_string(),
+            _ if versions.len() == all.len() => String::new(),
+            [only] => format!("=={}", only),
+            [first, .., last] => {
+                let start = all.iter().position(|v| v == *first).unwrap_or(0);
+                let end = all.iter().position(|v| v == *last).unwrap_or(0);
+                if end + 1 - start == versions.len() {
+                    let lower = (start > 0).then(|| format!(">={}", first));
+                    let upper = all.get(end + 1).map(|next| format!("<{}", next));
+                    lower.into_iter().chain(upper).collect::<Vec<_>>().join(",")
+                } else {
+                    let shown: Vec<String> = versions.iter().take(4).map(|v| v.to_string()).collect();
+                    let more = if versions.len() > 4 { ", ..." } else { "" };
+                    format!(" in {{{}{}}}", shown.join(", "), more)
+                }
+            }
+        };
+        format!("{}{}", term.package, specifier)
+    }
+}
+
+fn is_derived(incompatibility: &Incompatibility) -> bool {
+    matches!(incompatibility.cause, IncompatibilityCause::Derived(..))
+}
+
+/// How many derivations each derived incompatibility under `incompatibility` feeds into
+fn count_references(incompatibility: &Incompatibility, counts: &mut HashMap<*const Incompatibility, usize>) {
+    if let IncompatibilityCause::Derived(left, right) = &incompatibility.cause {
+        for cause in [left, right] {
+            if is_derived(cause) {
+                let count = counts.entry(Rc::as_ptr(cause)).or_insert(0);
+                *count += 1;
+                if *count == 1 {
+                    count_references(cause, counts);
                 }
             }
         }
-        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    fn range(min: &str, max: &str) -> Constraint {
+        Constraint::Range(v(min), v(max))
+    }
+
+    fn solver() -> Solver {
+        Solver::new("root".to_string(), v("1.0.0"), Arc::new(Mutex::new(DependencyResolver::new())))
+    }
+
+    #[tokio::test]
+    async fn test_backjumps_past_conflicting_version() {
+        // foo 1.1.0 needs bar, but every bar needs foo 2, so foo 1.0.0 is the answer
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("foo".to_string(), range("1.0.0", "2.0.0"))])
+            .with_package("foo", v("1.0.0"), vec![])
+            .with_package("foo", v("1.1.0"), vec![("bar".to_string(), range("1.0.0", "2.0.0"))])
+            .with_package("foo", v("2.0.0"), vec![])
+            .with_package("bar", v("1.0.0"), vec![("foo".to_string(), range("2.0.0", "3.0.0"))]);
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("foo"), Some(&v("1.0.0")));
+        assert!(!solution.contains_key("bar"));
+    }
+
+    #[tokio::test]
+    async fn test_picks_newest_compatible_versions() {
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("Web".to_string(), Constraint::Any)])
+            .with_package("web", v("1.0.0"), vec![("db".to_string(), Constraint::Any)])
+            .with_package("web", v("2.0.0"), vec![("db".to_string(), range("1.0.0", "2.0.0"))])
+            .with_package("db", v("1.5.0"), vec![])
+            .with_package("db", v("2.0.0"), vec![])
+            .with_package("db", v("2.1.0rc1"), vec![]);
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("web"), Some(&v("2.0.0")));
+        assert_eq!(solution.get("db"), Some(&v("1.5.0")));
+    }
+
+    #[tokio::test]
+    async fn test_keeps_preferred_versions_while_allowed() {
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("web".to_string(), Constraint::Any), ("db".to_string(), Constraint::Any)])
+            .with_package("web", v("1.0.0"), vec![])
+            .with_package("web", v("2.0.0"), vec![])
+            .with_package("db", v("1.0.0"), vec![])
+            .with_package("db", v("2.0.0"), vec![])
+            .with_preferred("web", v("1.0.0"))
+            .with_preferred("db", v("3.0.0"));
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("web"), Some(&v("1.0.0")));
+        assert_eq!(solution.get("db"), Some(&v("2.0.0")));
+    }
+
+    #[tokio::test]
+    async fn test_overrides_replace_requirements() {
+        let overrides = Overrides::new().with_override("lib", ">=2").unwrap();
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("app".to_string(), Constraint::Any)])
+            .with_package("app", v("1.0.0"), vec![("lib".to_string(), range("1.0.0", "2.0.0"))])
+            .with_package("lib", v("1.5.0"), vec![])
+            .with_package("lib", v("2.1.0"), vec![])
+            .with_overrides(overrides);
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("lib"), Some(&v("2.1.0")));
+    }
+
+    #[tokio::test]
+    async fn test_lowest_resolution_picks_minimum_versions() {
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("web".to_string(), range("1.1.0", "3.0.0"))])
+            .with_package("web", v("1.0.0"), vec![])
+            .with_package("web", v("1.1.0"), vec![("db".to_string(), range("2.0.0", "9.0.0"))])
+            .with_package("web", v("2.0.0"), vec![])
+            .with_package("db", v("1.0.0"), vec![])
+            .with_package("db", v("2.0.0"), vec![])
+            .with_package("db", v("2.5.0"), vec![])
+            .with_resolution(Resolution::Lowest);
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("web"), Some(&v("1.1.0")));
+        assert_eq!(solution.get("db"), Some(&v("2.0.0")));
+    }
+
+    #[tokio::test]
+    async fn test_constraints_narrow_requirements() {
+        let mut constraints = Constraints::new();
+        constraints.add_line("lib<2").unwrap();
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("app".to_string(), Constraint::Any)])
+            .with_package("app", v("1.0.0"), vec![("lib".to_string(), Constraint::Any)])
+            .with_package("lib", v("1.5.0"), vec![])
+            .with_package("lib", v("2.1.0"), vec![])
+            .with_package("other", v("1.0.0"), vec![])
+            .with_constraints(constraints);
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("lib"), Some(&v("1.5.0")));
+        assert!(!solution.contains_key("other"));
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_requirements_resolve() {
+        let specs = crate::markers::parse_specifier_set("==1.2.*,!=1.2.3").unwrap();
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("lib".to_string(), requirement_constraint(&specs))])
+            .with_package("lib", v("1.2.2"), vec![])
+            .with_package("lib", v("1.2.3"), vec![])
+            .with_package("lib", v("1.20.0"), vec![]);
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("lib"), Some(&v("1.2.2")));
+    }
+
+    #[tokio::test]
+    async fn test_unsolvable_conflict_is_explained() {
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![
+                ("left".to_string(), Constraint::Any),
+                ("right".to_string(), Constraint::Any),
+            ])
+            .with_package("left", v("1.0.0"), vec![("shared".to_string(), range("2.0.0", "3.0.0"))])
+            .with_package("right", v("1.0.0"), vec![("shared".to_string(), range("1.0.0", "2.0.0"))])
+            .with_package("shared", v("1.0.0"), vec![])
+            .with_package("shared", v("2.0.0"), vec![]);
+
+        let error = solver.solve().await.unwrap_err().to_string();
+        assert!(error.starts_with("No solution for root 1.0.0"), "{}", error);
+        assert!(error.contains("Because left depends on shared==2.0.0 and right depends on shared==1.0.0, left is incompatible with right."), "{}", error);
+        assert!(error.contains("And because your project requires left, right is impossible."), "{}", error);
+        assert!(error.ends_with("And because your project requires right, version solving failed."), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_explanation_reads_as_derivation() {
+        // foo>=2 needs an old bar, but the project itself needs a new one
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![
+                ("foo".to_string(), range("2.0.0", "3.0.0")),
+                ("bar".to_string(), range("1.5.0", "9.0.0")),
+            ])
+            .with_package("foo", v("1.0.0"), vec![])
+            .with_package("foo", v("2.0.0"), vec![("bar".to_string(), range("0.1.0", "1.0.0"))])
+            .with_package("foo", v("2.1.0"), vec![("bar".to_string(), range("0.1.0", "1.0.0"))])
+            .with_package("bar", v("0.8.0"), vec![])
+            .with_package("bar", v("0.9.0"), vec![])
+            .with_package("bar", v("1.5.0"), vec![])
+            .with_package("bar", v("1.6.0"), vec![]);
+
+        let error = solver.solve().await.unwrap_err().to_string();
+        let lines: Vec<&str> = error.lines().map(str::trim).collect();
+        assert_eq!(lines[1..], [
+            "Because foo==2.1.0 depends on bar<1.5.0 and foo==2.0.0 depends on bar<1.5.0, foo>=2.0.0 depends on bar<1.5.0.",
+            "And because your project requires foo>=2.0.0, your project requires bar<1.5.0.",
+            "And because your project requires bar>=1.5.0, version solving failed.",
+        ]);
+    }
+
+    fn indexed(name: &str, versions: &[&str], yanked: &[&str]) -> crate::resolver::PyPIPackageInfo {
+        use crate::resolver::{PyPIInfo, PyPIPackageInfo, PyPIRelease};
+        let release = |version: &str| (version.to_string(), vec![PyPIRelease {
+            filename: format!("{}-{}-py3-none-any.whl", name, version),
+            url: String::new(),
+            size: None,
+            upload_time: None,
+            digests: None,
+            yanked: yanked.contains(&version),
+            yanked_reason: yanked.contains(&version).then(|| "broken wheel".to_string()),
+            requires_python: None,
+        }]);
+        let latest = versions.last().copied().unwrap_or_default();
+        PyPIPackageInfo {
+            info: PyPIInfo {
+                name: name.to_string(),
+                version: latest.to_string(),
+                summary: None,
+                description: None,
+                author: None,
+                license: None,
+                home_page: None,
+                requires_dist: Some(vec!["lib>=2".to_string()]),
+            },
+            releases: versions.iter().map(|v| release(v)).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_older_releases_use_their_own_dependencies() {
+        // Only app 2.0 needs lib>=2 (which is also what the project document says);
+        // backtracking to app 1.0 must see that it doesn't
+        let resolver = DependencyResolver::new()
+            .with_cached(indexed("app", &["1.0.0", "2.0.0"], &[]), vec![
+                ("1.0.0", vec!["lib".to_string()]),
+                ("2.0.0", vec!["lib>=2".to_string()]),
+            ])
+            .with_cached(indexed("lib", &["1.0.0", "2.0.0"], &[]), vec![("1.0.0", vec![]), ("2.0.0", vec![])]);
+        let mut solver = Solver::new("root".to_string(), v("1.0.0"), Arc::new(Mutex::new(resolver)))
+            .with_package("root", v("1.0.0"), vec![
+                ("app".to_string(), Constraint::Any),
+                ("lib".to_string(), range("1.0.0", "2.0.0")),
+            ]);
+
+        let solution = solver.solve().await.unwrap();
+        assert_eq!(solution.get("app"), Some(&v("1.0.0")));
+        assert_eq!(solution.get("lib"), Some(&v("1.0.0")));
+    }
+
+    #[tokio::test]
+    async fn test_yanked_versions_need_a_pin() {
+        let solve = |requirement: Constraint| async move {
+            let resolver = DependencyResolver::new()
+                .with_cached(indexed("lib", &["1.0.0", "2.0.0"], &["2.0.0"]), vec![("1.0.0", vec![]), ("2.0.0", vec![])]);
+            Solver::new("root".to_string(), v("1.0.0"), Arc::new(Mutex::new(resolver)))
+                .with_package("root", v("1.0.0"), vec![("lib".to_string(), requirement)])
+                .solve().await
+        };
+
+        let solution = solve(Constraint::Any).await.unwrap();
+        assert_eq!(solution.get("lib"), Some(&v("1.0.0")));
+        let pinned = requirement_constraint(&crate::markers::parse_specifier_set("==2.0.0").unwrap());
+        let solution = solve(pinned).await.unwrap();
+        assert_eq!(solution.get("lib"), Some(&v("2.0.0")));
+        assert!(solve(range("2.0.0", "3.0.0")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_versions_are_explained() {
+        let mut solver = solver()
+            .with_package("root", v("1.0.0"), vec![("foo".to_string(), range("3.0.0", "4.0.0"))])
+            .with_package("foo", v("1.0.0"), vec![]);
+
+        let error = solver.solve().await.unwrap_err().to_string();
+        assert!(error.contains("Because your project requires foo (no matching versions), version solving failed."), "{}", error);
     }
 }
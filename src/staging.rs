@@ -0,0 +1,304 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::logger;
+use crate::manifest::canonical_name;
+use crate::provenance::transaction_id;
+
+const STAGING_DIR: &str = ".snakepit-staging";
+const JOURNAL: &str = "journal.jsonl";
+
+/// Site-packages directories already checked for interrupted installs by this process
+static RECOVERED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// One step of moving a staged install into place, logged before it happens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum JournalEntry {
+    /// A directory that didn't exist yet
+    Mkdir { path: PathBuf },
+    /// A file moved to `target`; whatever was there is parked at `backup` until commit
+    Move { target: PathBuf, backup: Option<PathBuf> },
+}
+
+/// A wheel extraction that only touches the environment once every file is on disk.
+///
+/// Files are extracted under `<site-packages>/.snakepit-staging/<package>-<transaction>/`
+/// and fsynced, then renamed into place. Each rename is journaled first, so a failure
+/// midway, or a crash picked up later by [`StagedInstall::recover`], can restore exactly
+/// what was there before.
+#[derive(Debug)]
+pub struct StagedInstall {
+    dir: PathBuf,
+    entries: Vec<JournalEntry>,
+    /// Set when a rollback failed and the journal must survive for the next recovery
+    keep: bool,
+}
+
+impl StagedInstall {
+    pub fn create(site_packages: &Path, package: &str) -> Result<Self> {
+        let dir = site_packages
+            .join(STAGING_DIR)
+            .join(format!("{}-{}", canonical_name(package), transaction_id()));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        std::fs::create_dir_all(dir.join("files"))?;
+        Ok(Self { dir, entries: Vec::new(), keep: false })
+    }
+
+    /// Where the `index`th archive entry is extracted before commit
+    pub fn staged_path(&self, index: usize) -> PathBuf {
+        self.dir.join("files").join(index.to_string())
+    }
+
+    /// Create `dirs` and move each staged file to its target.
+    ///
+    /// If any step fails, everything already done is undone before the error is returned.
+    pub fn commit(mut self, dirs: &[PathBuf], files: &[(PathBuf, PathBuf)]) -> Result<()> {
+        if let Err(e) = self.move_into_place(dirs, files) {
+            if let Err(rollback) = Self::undo(&self.entries) {
+                self.keep = true;
+                logger::warn(format!(
+                    "⚠️  Could not roll back a partial install ({}); the next install will retry from {}",
+                    rollback, self.dir.display()
+                ));
+            }
+            return Err(e);
+        }
+
+        // Without a journal the staging directory is just leftovers
+        std::fs::remove_file(self.dir.join(JOURNAL))?;
+        Ok(())
+    }
+
+    fn move_into_place(&mut self, dirs: &[PathBuf], files: &[(PathBuf, PathBuf)]) -> Result<()> {
+        let mut journal = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(JOURNAL))?;
+
+        for dir in dirs {
+            self.create_dirs(&mut journal, dir)?;
+        }
+
+        for (i, (staged, target)) in files.iter().enumerate() {
+            if let Some(parent) = target.parent() {
+                self.create_dirs(&mut journal, parent)?;
+            }
+
+            let backup = target.symlink_metadata().is_ok()
+                .then(|| self.dir.join("backup").join(i.to_string()));
+            self.log(&mut journal, JournalEntry::Move { target: target.clone(), backup: backup.clone() })?;
+
+            if let Some(backup) = &backup {
+                std::fs::create_dir_all(self.dir.join("backup"))?;
+                std::fs::rename(target, backup)?;
+            }
+            move_file(staged, target)
+                .map_err(|e| anyhow::anyhow!("Could not move {} into place: {}", target.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Create `dir` and any missing ancestors, journaling each one outermost first
+    fn create_dirs(&mut self, journal: &mut std::fs::File, dir: &Path) -> Result<()> {
+        let missing: Vec<&Path> = dir.ancestors().take_while(|d| !d.exists()).collect();
+        for path in missing.into_iter().rev() {
+            self.log(journal, JournalEntry::Mkdir { path: path.to_path_buf() })?;
+            std::fs::create_dir(path)?;
+        }
+        Ok(())
+    }
+
+    fn log(&mut self, journal: &mut std::fs::File, entry: JournalEntry) -> Result<()> {
+        writeln!(journal, "{}", serde_json::to_string(&entry)?)?;
+        journal.sync_all()?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Reverse journaled steps, newest first; safe to repeat after a crash mid-undo
+    fn undo(entries: &[JournalEntry]) -> Result<()> {
+        for entry in entries.iter().rev() {
+            match entry {
+                JournalEntry::Move { target, backup: Some(backup) } => {
+                    // A backup that was never written means the target was never touched
+                    if backup.symlink_metadata().is_ok() {
+                        remove_if_present(target)?;
+                        std::fs::rename(backup, target)?;
+                    }
+                }
+                JournalEntry::Move { target, backup: None } => remove_if_present(target)?,
+                // Another install may have put files here meanwhile; then the directory stays
+                JournalEntry::Mkdir { path } => { let _ = std::fs::remove_dir(path); }
+            }
+        }
+        Ok(())
+    }
+
+    /// Roll back installs into `site_packages` that were interrupted before they committed.
+    ///
+    /// Each directory is checked once per process; staging areas of installs still
+    /// running elsewhere are left alone. Returns how many installs were rolled back.
+    pub fn recover(site_packages: &Path) -> Result<usize> {
+        let mut recovered = RECOVERED.lock().unwrap_or_else(|e| e.into_inner());
+        if recovered.iter().any(|done| done == site_packages) {
+            return Ok(0);
+        }
+
+        let root = site_packages.join(STAGING_DIR);
+        let mut rolled_back = 0;
+        if let Ok(stages) = std::fs::read_dir(&root) {
+            for stage in stages.flatten() {
+                let name = stage.file_name().to_string_lossy().to_string();
+                if Self::owner_is_running(&name) {
+                    continue;
+                }
+
+                let journal = stage.path().join(JOURNAL);
+                if journal.exists() {
+                    let entries: Vec<JournalEntry> = std::fs::read_to_string(&journal)?
+                        .lines()
+                        // The last line may be torn if the crash happened while writing it
+                        .filter_map(|line| serde_json::from_str(line).ok())
+                        .collect();
+                    Self::undo(&entries)?;
+                    rolled_back += 1;
+                }
+                std::fs::remove_dir_all(stage.path())?;
+            }
+            let _ = std::fs::remove_dir(&root);
+        }
+
+        recovered.push(site_packages.to_path_buf());
+        Ok(rolled_back)
+    }
+
+    /// Whether the process behind a `<package>-<secs>-<pid>` staging directory is alive
+    fn owner_is_running(stage_name: &str) -> bool {
+        if stage_name.ends_with(transaction_id()) {
+            return true;
+        }
+        let Some(pid) = stage_name.rsplit('-').next().and_then(|pid| pid.parse::<u32>().ok()) else {
+            return false;
+        };
+        process_exists(pid)
+    }
+}
+
+impl Drop for StagedInstall {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = std::fs::remove_dir_all(&self.dir);
+            if let Some(root) = self.dir.parent() {
+                let _ = std::fs::remove_dir(root);
+            }
+        }
+    }
+}
+
+/// Rename, falling back to copy-and-delete when the target is on another filesystem
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::File::open(to)?.sync_all()?;
+    std::fs::remove_file(from)
+}
+
+fn remove_if_present(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(anyhow::anyhow!("Could not remove {}: {}", path.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn process_exists(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn process_exists(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage_file(staging: &StagedInstall, index: usize, content: &str) -> PathBuf {
+        let path = staging.staged_path(index);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_failed_commit_restores_previous_files() {
+        let site = std::env::temp_dir().join(format!("snakepit-staging-{}", std::process::id()));
+        std::fs::create_dir_all(site.join("demo")).unwrap();
+        std::fs::write(site.join("demo").join("__init__.py"), "old").unwrap();
+
+        let staging = StagedInstall::create(&site, "Demo").unwrap();
+        let files = vec![
+            (stage_file(&staging, 0, "new"), site.join("demo").join("__init__.py")),
+            (stage_file(&staging, 1, "helper"), site.join("demo").join("sub").join("helper.py")),
+            // Never staged, so moving it fails after the first two are in place
+            (staging.staged_path(2), site.join("demo").join("missing.py")),
+        ];
+        assert!(staging.commit(&[], &files).is_err());
+
+        assert_eq!(std::fs::read_to_string(site.join("demo").join("__init__.py")).unwrap(), "old");
+        assert!(!site.join("demo").join("sub").exists());
+        assert!(!site.join(STAGING_DIR).exists());
+
+        let staging = StagedInstall::create(&site, "demo").unwrap();
+        let files = vec![(stage_file(&staging, 0, "new"), site.join("demo").join("__init__.py"))];
+        staging.commit(&[site.join("demo-1.0.dist-info")], &files).unwrap();
+        assert_eq!(std::fs::read_to_string(site.join("demo").join("__init__.py")).unwrap(), "new");
+        assert!(site.join("demo-1.0.dist-info").is_dir());
+        assert!(!site.join(STAGING_DIR).exists());
+
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+
+    #[test]
+    fn test_recover_rolls_back_interrupted_install() {
+        let site = std::env::temp_dir().join(format!("snakepit-recover-{}", std::process::id()));
+        std::fs::create_dir_all(&site).unwrap();
+        std::fs::write(site.join("six.py"), "new").unwrap();
+
+        // A dead process got as far as replacing six.py and adding a package directory
+        let stage = site.join(STAGING_DIR).join("six-1-4294967").join("backup");
+        std::fs::create_dir_all(&stage).unwrap();
+        std::fs::write(stage.join("0"), "old").unwrap();
+        std::fs::create_dir_all(site.join("extra")).unwrap();
+        std::fs::write(site.join("extra").join("mod.py"), "").unwrap();
+        let journal = [
+            JournalEntry::Move { target: site.join("six.py"), backup: Some(stage.join("0")) },
+            JournalEntry::Mkdir { path: site.join("extra") },
+            JournalEntry::Move { target: site.join("extra").join("mod.py"), backup: None },
+        ];
+        let lines: Vec<String> = journal.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        std::fs::write(stage.parent().unwrap().join(JOURNAL), format!("{}\n{{\"op\":\"mo", lines.join("\n"))).unwrap();
+
+        assert_eq!(StagedInstall::recover(&site).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(site.join("six.py")).unwrap(), "old");
+        assert!(!site.join("extra").exists());
+        assert!(!site.join(STAGING_DIR).exists());
+        assert_eq!(StagedInstall::recover(&site).unwrap(), 0);
+
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
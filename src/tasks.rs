@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::config::{find_project_file, read_project_table};
+
+/// A `[scripts]` entry: a bare command, or a table that adds environment variables, tasks
+/// to run first and a sync beforehand
+///
+/// ```toml
+/// [tool.snakepit.scripts]
+/// fmt = "ruff format ."
+/// test = { cmd = "pytest -x", env = { DJANGO_SETTINGS_MODULE = "app.test" }, depends = ["fmt"], sync = true }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum TaskSpec {
+    Command(String),
+    Table {
+        cmd: String,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+        #[serde(default)]
+        depends: Vec<String>,
+        #[serde(default)]
+        sync: bool,
+        #[serde(default)]
+        help: Option<String>,
+    },
+}
+
+/// A project task `snakepit run NAME` (or just `snakepit NAME`) runs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Task {
+    pub command: String,
+    /// Set for this task on top of the project's `[env]`
+    pub env: BTreeMap<String, String>,
+    /// Tasks run, in order, before this one
+    pub depends: Vec<String>,
+    /// Sync the environment with the project's requirements before running
+    pub sync: bool,
+    pub help: Option<String>,
+}
+
+impl From<TaskSpec> for Task {
+    fn from(spec: TaskSpec) -> Self {
+        match spec {
+            TaskSpec::Command(command) => Task { command, ..Default::default() },
+            TaskSpec::Table { cmd, env, depends, sync, help } => Task { command: cmd, env, depends, sync, help },
+        }
+    }
+}
+
+impl Task {
+    /// The argv to spawn with `extra` arguments appended. Commands using `&&`, pipes or
+    /// redirections go through the shell; the rest are split into words, honouring quotes.
+    pub fn argv(&self, extra: &[String]) -> Result<Vec<String>> {
+        if needs_shell(&self.command) {
+            let mut script = self.command.clone();
+            for arg in extra {
+                script.push(' ');
+                script.push_str(&shell_quote(arg));
+            }
+            return Ok(if cfg!(windows) {
+                vec!["cmd".to_string(), "/C".to_string(), script]
+            } else {
+                vec!["sh".to_string(), "-c".to_string(), script]
+            });
+        }
+        let mut argv = split_words(&self.command)?;
+        argv.extend(extra.iter().cloned());
+        if argv.is_empty() {
+            return Err(anyhow!("Empty command"));
+        }
+        Ok(argv)
+    }
+}
+
+/// The project's tasks and the variables every one of them gets
+#[derive(Debug, Clone, Default)]
+pub struct ProjectTasks {
+    pub tasks: BTreeMap<String, Task>,
+    /// `[env]` of the project file
+    pub env: BTreeMap<String, String>,
+}
+
+impl ProjectTasks {
+    /// The `scripts` and `env` tables of the nearest project file (snakepit.toml or
+    /// `[tool.snakepit]`); empty when there's none
+    pub fn load(dir: &Path) -> Result<Self> {
+        let Some(mut project) = find_project_file(dir).map(|path| read_project_table(&path)).transpose()?.flatten() else {
+            return Ok(Self::default());
+        };
+        let tasks: BTreeMap<String, TaskSpec> = match project.remove("scripts") {
+            Some(scripts) => scripts.try_into().map_err(|e| anyhow!("Invalid [scripts]: {}", e))?,
+            None => BTreeMap::new(),
+        };
+        let env = match project.remove("env") {
+            Some(env) => env.try_into().map_err(|e| anyhow!("Invalid [env]: {}", e))?,
+            None => BTreeMap::new(),
+        };
+        Ok(Self {
+            tasks: tasks.into_iter().map(|(name, spec)| (name, spec.into())).collect(),
+            env,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Task> {
+        self.tasks.get(name)
+    }
+
+    /// `name` and everything it depends on, dependencies first, each task once
+    pub fn plan(&self, name: &str) -> Result<Vec<String>> {
+        let mut order = Vec::new();
+        self.visit(name, &mut Vec::new(), &mut order)?;
+        Ok(order)
+    }
+
+    fn visit(&self, name: &str, stack: &mut Vec<String>, order: &mut Vec<String>) -> Result<()> {
+        if order.iter().any(|done| done == name) {
+            return Ok(());
+        }
+        if stack.iter().any(|pending| pending == name) {
+            stack.push(name.to_string());
+            return Err(anyhow!("Task dependencies form a cycle: {}", stack.join(" -> ")));
+        }
+        let task = self.get(name)
+            .ok_or_else(|| match stack.last() {
+                Some(parent) => anyhow!("Task '{}' depends on '{}', which isn't defined", parent, name),
+                None => anyhow!("No task named '{}'", name),
+            })?;
+        stack.push(name.to_string());
+        for dependency in &task.depends {
+            self.visit(dependency, stack, order)?;
+        }
+        stack.pop();
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    /// Variables to set for `task`: the project's `[env]`, then the task's own
+    pub fn env_for(&self, task: &Task) -> BTreeMap<String, String> {
+        let mut env = self.env.clone();
+        env.extend(task.env.iter().map(|(key, value)| (key.clone(), value.clone())));
+        env
+    }
+}
+
+fn needs_shell(command: &str) -> bool {
+    ["&&", "||", "|", ";", ">", "<", "$(", "`"].iter().any(|operator| command.contains(operator))
+}
+
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// `command` split on whitespace, keeping single- and double-quoted runs together
+fn split_words(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => word.extend(chars.next()),
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow!("Unterminated quote in '{}'", command));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_from_pyproject() {
+        let dir = std::env::temp_dir().join(format!("snakepit-tasks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("pyproject.toml"), r#"
+[tool.snakepit.env]
+PYTHONDONTWRITEBYTECODE = "1"
+MODE = "dev"
+
+[tool.snakepit.scripts]
+fmt = "ruff format ."
+lint = { cmd = "ruff check .", depends = ["fmt"] }
+test = { cmd = "pytest -x -k 'not slow'", env = { MODE = "test" }, depends = ["lint", "fmt"], sync = true }
+loop = { cmd = "true", depends = ["again"] }
+again = { cmd = "true", depends = ["loop"] }
+"#).unwrap();
+
+        let project = ProjectTasks::load(&dir).unwrap();
+        assert_eq!(project.plan("test").unwrap(), vec!["fmt", "lint", "test"]);
+        let test = project.get("test").unwrap();
+        assert!(test.sync);
+        assert_eq!(test.argv(&["tests/unit".to_string()]).unwrap(), vec!["pytest", "-x", "-k", "not slow", "tests/unit"]);
+        let env = project.env_for(test);
+        assert_eq!(env["MODE"], "test");
+        assert_eq!(env["PYTHONDONTWRITEBYTECODE"], "1");
+        assert!(project.plan("loop").unwrap_err().to_string().contains("loop -> again -> loop"));
+        assert!(project.plan("missing").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shell_commands_keep_their_operators() {
+        let task = Task { command: "black . && mypy .".to_string(), ..Default::default() };
+        let argv = task.argv(&["it's".to_string()]).unwrap();
+        assert_eq!(argv.last().unwrap(), "black . && mypy . 'it'\\''s'");
+        assert!(split_words("echo 'unterminated").is_err());
+    }
+}
@@ -0,0 +1,630 @@
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use snakegg::native::dirs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use crate::manifest::canonical_name;
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*([a-z_]+)\s*\}\}").unwrap();
+}
+
+/// PEP 517 backends `init` can set up, with the `[build-system]` each needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildBackend {
+    Hatchling,
+    Setuptools,
+    Flit,
+    Pdm,
+}
+
+impl BuildBackend {
+    pub const ALL: [BuildBackend; 4] = [BuildBackend::Hatchling, BuildBackend::Setuptools, BuildBackend::Flit, BuildBackend::Pdm];
+
+    /// Versions new enough for PEP 639 `license` expressions
+    fn requires(self) -> &'static str {
+        match self {
+            BuildBackend::Hatchling => "hatchling>=1.27",
+            BuildBackend::Setuptools => "setuptools>=77",
+            BuildBackend::Flit => "flit_core>=3.12,<4",
+            BuildBackend::Pdm => "pdm-backend>=2.4",
+        }
+    }
+
+    fn module(self) -> &'static str {
+        match self {
+            BuildBackend::Hatchling => "hatchling.build",
+            BuildBackend::Setuptools => "setuptools.build_meta",
+            BuildBackend::Flit => "flit_core.buildapi",
+            BuildBackend::Pdm => "pdm.backend",
+        }
+    }
+}
+
+/// Where the package lives: `src/<package>` or `<package>` next to pyproject.toml
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Src,
+    Flat,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 2] = [Layout::Src, Layout::Flat];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestFramework {
+    Pytest,
+    Unittest,
+    None,
+}
+
+impl TestFramework {
+    pub const ALL: [TestFramework; 3] = [TestFramework::Pytest, TestFramework::Unittest, TestFramework::None];
+}
+
+impl std::str::FromStr for BuildBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "hatchling" | "hatch" => Ok(BuildBackend::Hatchling),
+            "setuptools" => Ok(BuildBackend::Setuptools),
+            "flit" | "flit-core" | "flit_core" => Ok(BuildBackend::Flit),
+            "pdm" | "pdm-backend" => Ok(BuildBackend::Pdm),
+            other => Err(anyhow!("Unknown build backend '{}' (expected hatchling, setuptools, flit or pdm)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for BuildBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BuildBackend::Hatchling => write!(f, "hatchling"),
+            BuildBackend::Setuptools => write!(f, "setuptools"),
+            BuildBackend::Flit => write!(f, "flit"),
+            BuildBackend::Pdm => write!(f, "pdm"),
+        }
+    }
+}
+
+impl std::str::FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "src" => Ok(Layout::Src),
+            "flat" => Ok(Layout::Flat),
+            other => Err(anyhow!("Unknown layout '{}' (expected src or flat)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Layout::Src => write!(f, "src"),
+            Layout::Flat => write!(f, "flat"),
+        }
+    }
+}
+
+impl std::str::FromStr for TestFramework {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "pytest" => Ok(TestFramework::Pytest),
+            "unittest" => Ok(TestFramework::Unittest),
+            "none" => Ok(TestFramework::None),
+            other => Err(anyhow!("Unknown test framework '{}' (expected pytest, unittest or none)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for TestFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TestFramework::Pytest => write!(f, "pytest"),
+            TestFramework::Unittest => write!(f, "unittest"),
+            TestFramework::None => write!(f, "none"),
+        }
+    }
+}
+
+/// The licenses `init` offers; any other SPDX expression is accepted too, without a LICENSE file
+pub const LICENSES: &[&str] = &["MIT", "Apache-2.0", "BSD-3-Clause", "GPL-3.0-or-later", "none"];
+
+/// The wizard's answers, which every template renders from
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitOptions {
+    pub name: String,
+    /// Importable name of the package, e.g. `my_project` for `my-project`
+    pub package: String,
+    pub description: String,
+    pub python_version: String,
+    pub backend: BuildBackend,
+    pub layout: Layout,
+    /// SPDX expression; `None` for no license
+    pub license: Option<String>,
+    pub test_framework: TestFramework,
+    /// For the LICENSE copyright line
+    pub author: Option<String>,
+}
+
+impl InitOptions {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            package: canonical_name(name).replace('-', "_"),
+            description: String::new(),
+            python_version: "3.9".to_string(),
+            backend: BuildBackend::Hatchling,
+            layout: Layout::Src,
+            license: None,
+            test_framework: TestFramework::Pytest,
+            author: None,
+        }
+    }
+
+    /// Directory of the package, relative to the project
+    pub fn package_dir(&self) -> PathBuf {
+        match self.layout {
+            Layout::Src => Path::new("src").join(&self.package),
+            Layout::Flat => PathBuf::from(&self.package),
+        }
+    }
+
+    /// `{{ key }}` in a template's paths and text files: name, package, description,
+    /// python_version, license and author. Unknown keys are left as they are.
+    pub fn render(&self, text: &str) -> String {
+        PLACEHOLDER.replace_all(text, |captures: &regex::Captures| {
+            match &captures[1] {
+                "name" => self.name.clone(),
+                "package" => self.package.clone(),
+                "description" => self.description.clone(),
+                "python_version" => self.python_version.clone(),
+                "license" => self.license.clone().unwrap_or_default(),
+                "author" => self.author.clone().unwrap_or_default(),
+                _ => captures[0].to_string(),
+            }
+        }).into_owned()
+    }
+}
+
+/// A file a template writes, relative to the project directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateFile {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+impl TemplateFile {
+    fn text(path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        Self { path: path.into(), contents: contents.into().into_bytes() }
+    }
+}
+
+/// Something `snakepit init --template` can start a project from
+pub trait ProjectTemplate: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    /// The project's files. Templates that leave out pyproject.toml get one generated from
+    /// the options.
+    fn files(&self, options: &InitOptions) -> Result<Vec<TemplateFile>>;
+}
+
+/// A library: the package, its tests, README, LICENSE and pyproject.toml
+pub struct LibraryTemplate;
+
+impl ProjectTemplate for LibraryTemplate {
+    fn name(&self) -> &str {
+        "lib"
+    }
+
+    fn description(&self) -> &str {
+        "An importable package"
+    }
+
+    fn files(&self, options: &InitOptions) -> Result<Vec<TemplateFile>> {
+        let mut files = base_files(options);
+        files.push(TemplateFile::text("pyproject.toml", pyproject_toml(options, None)));
+        Ok(files)
+    }
+}
+
+/// A command-line application: a library whose `main` is installed as a command
+pub struct ApplicationTemplate;
+
+impl ProjectTemplate for ApplicationTemplate {
+    fn name(&self) -> &str {
+        "app"
+    }
+
+    fn description(&self) -> &str {
+        "A package with a command-line entry point"
+    }
+
+    fn files(&self, options: &InitOptions) -> Result<Vec<TemplateFile>> {
+        let mut files = base_files(options);
+        files.push(TemplateFile::text(options.package_dir().join("__main__.py"), format!(
+            "def main() -> None:\n    print(\"Hello from {}!\")\n\n\nif __name__ == \"__main__\":\n    main()\n",
+            options.name,
+        )));
+        let entry_point = format!("{}.__main__:main", options.package);
+        files.push(TemplateFile::text("pyproject.toml", pyproject_toml(options, Some(&entry_point))));
+        Ok(files)
+    }
+}
+
+/// A directory of files to copy, with `{{ placeholders }}` rendered in paths and text files
+pub struct DirectoryTemplate {
+    name: String,
+    description: String,
+    root: PathBuf,
+}
+
+impl DirectoryTemplate {
+    pub fn new(name: &str, root: &Path) -> Self {
+        Self {
+            name: name.to_string(),
+            description: format!("Files from {}", root.display()),
+            root: root.to_path_buf(),
+        }
+    }
+}
+
+impl ProjectTemplate for DirectoryTemplate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn files(&self, options: &InitOptions) -> Result<Vec<TemplateFile>> {
+        let mut files = Vec::new();
+        collect_files(&self.root, &self.root, options, &mut files)?;
+        if files.is_empty() {
+            return Err(anyhow!("Template {} has no files", self.root.display()));
+        }
+        Ok(files)
+    }
+}
+
+fn collect_files(root: &Path, dir: &Path, options: &InitOptions, files: &mut Vec<TemplateFile>) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read template {}", dir.display()))?
+        .flatten()
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(root, &path, options, files)?;
+            continue;
+        }
+        let relative = path.strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+        let bytes = std::fs::read(&path)?;
+        // Binary files (images, fonts) are copied as they are
+        let contents = match String::from_utf8(bytes) {
+            Ok(text) => options.render(&text).into_bytes(),
+            Err(binary) => binary.into_bytes(),
+        };
+        files.push(TemplateFile { path: PathBuf::from(options.render(&relative)), contents });
+    }
+    Ok(())
+}
+
+/// A template in a git repository, cloned fresh for each project. `url#ref` picks a
+/// branch or tag; `gh:owner/repo` is short for the GitHub repository.
+pub struct GitTemplate {
+    url: String,
+    reference: Option<String>,
+}
+
+impl GitTemplate {
+    pub fn new(spec: &str) -> Self {
+        let spec = spec.strip_prefix("git+").unwrap_or(spec);
+        let (url, reference) = match spec.rsplit_once('#') {
+            Some((url, reference)) => (url, Some(reference.to_string())),
+            None => (spec, None),
+        };
+        let url = match url.strip_prefix("gh:") {
+            Some(repo) => format!("https://github.com/{}.git", repo),
+            None => url.to_string(),
+        };
+        Self { url, reference }
+    }
+
+    fn is_git_spec(spec: &str) -> bool {
+        spec.starts_with("gh:") || spec.starts_with("git+") || spec.starts_with("git@")
+            || spec.starts_with("https://") || spec.starts_with("ssh://")
+            || spec.split('#').next().unwrap_or(spec).ends_with(".git")
+    }
+}
+
+impl ProjectTemplate for GitTemplate {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn description(&self) -> &str {
+        "A git-hosted template"
+    }
+
+    fn files(&self, options: &InitOptions) -> Result<Vec<TemplateFile>> {
+        let checkout = std::env::temp_dir().join(format!("snakepit-template-{}", std::process::id()));
+        if checkout.exists() {
+            std::fs::remove_dir_all(&checkout)?;
+        }
+        let mut clone = std::process::Command::new("git");
+        clone.args(["clone", "--quiet", "--depth", "1"]);
+        if let Some(reference) = &self.reference {
+            clone.args(["--branch", reference]);
+        }
+        let output = clone.arg(&self.url).arg(&checkout)
+            .output()
+            .context("Failed to run git; it's needed for git-hosted templates")?;
+        if !output.status.success() {
+            return Err(anyhow!("Failed to clone template {}: {}", self.url, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        let files = DirectoryTemplate::new(&self.url, &checkout).files(options);
+        let _ = std::fs::remove_dir_all(&checkout);
+        files
+    }
+}
+
+fn registry() -> &'static RwLock<Vec<Arc<dyn ProjectTemplate>>> {
+    static REGISTRY: OnceLock<RwLock<Vec<Arc<dyn ProjectTemplate>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(vec![
+        Arc::new(LibraryTemplate),
+        Arc::new(ApplicationTemplate),
+    ]))
+}
+
+/// Make another template available by name; it takes precedence over a built-in one of the same name
+pub fn register(template: impl ProjectTemplate + 'static) {
+    registry().write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(0, Arc::new(template));
+}
+
+/// Where `init` looks for the user's own templates, one directory each
+pub fn user_template_dir() -> PathBuf {
+    std::env::var_os("SNAKEPIT_TEMPLATE_DIR").map(PathBuf::from).unwrap_or_else(|| {
+        dirs::data_dir().unwrap_or_else(|| PathBuf::from(".snakepit")).join("snakepit").join("templates")
+    })
+}
+
+/// The template `spec` names: a registered template, one in the user template directory,
+/// a git URL (`gh:owner/repo`, `https://…/repo.git#v2`) or a local directory
+pub fn template_for(spec: &str) -> Result<Arc<dyn ProjectTemplate>> {
+    if let Some(template) = registry().read().unwrap_or_else(|e| e.into_inner()).iter().find(|t| t.name() == spec) {
+        return Ok(template.clone());
+    }
+    let user_template = user_template_dir().join(spec);
+    if !spec.contains(['/', '\\']) && user_template.is_dir() {
+        return Ok(Arc::new(DirectoryTemplate::new(spec, &user_template)));
+    }
+    if GitTemplate::is_git_spec(spec) {
+        return Ok(Arc::new(GitTemplate::new(spec)));
+    }
+    let path = Path::new(spec);
+    if path.is_dir() {
+        return Ok(Arc::new(DirectoryTemplate::new(spec, path)));
+    }
+    let known: Vec<String> = available().into_iter().map(|(name, _)| name).collect();
+    Err(anyhow!("No template '{}' (available: {}; or a git URL or directory)", spec, known.join(", ")))
+}
+
+/// Name and description of every registered and user template
+pub fn available() -> Vec<(String, String)> {
+    let mut templates: Vec<(String, String)> = registry().read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|template| (template.name().to_string(), template.description().to_string()))
+        .collect();
+    if let Ok(entries) = std::fs::read_dir(user_template_dir()) {
+        let mut user: Vec<(String, String)> = entries.flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                (name, format!("Files from {}", entry.path().display()))
+            })
+            .filter(|(name, _)| !templates.iter().any(|(known, _)| known == name))
+            .collect();
+        user.sort();
+        templates.extend(user);
+    }
+    templates
+}
+
+/// Render `template` for `options`, adding the generated pyproject.toml when the template has none
+pub fn render(template: &dyn ProjectTemplate, options: &InitOptions) -> Result<Vec<TemplateFile>> {
+    let mut files = template.files(options)?;
+    if !files.iter().any(|file| file.path == Path::new("pyproject.toml")) {
+        files.push(TemplateFile::text("pyproject.toml", pyproject_toml(options, None)));
+    }
+    Ok(files)
+}
+
+/// What every built-in template starts from: the package, tests, README, .gitignore and LICENSE
+fn base_files(options: &InitOptions) -> Vec<TemplateFile> {
+    let package_dir = options.package_dir();
+    let docstring = if options.description.is_empty() { options.name.clone() } else { options.description.clone() };
+    let mut files = vec![
+        TemplateFile::text("README.md", format!("# {}\n\n{}\n", options.name, options.description).replace("\n\n\n", "\n")),
+        TemplateFile::text(".gitignore", "__pycache__/\n*.py[cod]\n.venv/\nvenv/\nbuild/\ndist/\n*.egg-info/\n.pytest_cache/\n"),
+        TemplateFile::text(package_dir.join("__init__.py"), format!("\"\"\"{}\"\"\"\n\n__version__ = \"0.1.0\"\n", docstring)),
+    ];
+    let test_file = Path::new("tests").join(format!("test_{}.py", options.package));
+    match options.test_framework {
+        TestFramework::Pytest => files.push(TemplateFile::text(test_file, format!(
+            "import {package}\n\n\ndef test_version():\n    assert {package}.__version__ == \"0.1.0\"\n",
+            package = options.package,
+        ))),
+        TestFramework::Unittest => {
+            files.push(TemplateFile::text("tests/__init__.py", ""));
+            files.push(TemplateFile::text(test_file, format!(
+                "import unittest\n\nimport {package}\n\n\nclass TestVersion(unittest.TestCase):\n    def test_version(self):\n        self.assertEqual({package}.__version__, \"0.1.0\")\n\n\nif __name__ == \"__main__\":\n    unittest.main()\n",
+                package = options.package,
+            )));
+        }
+        TestFramework::None => {}
+    }
+    if let Some(text) = options.license.as_deref().and_then(|license| license_text(license, options)) {
+        files.push(TemplateFile::text("LICENSE", text));
+    }
+    files
+}
+
+fn license_text(license: &str, options: &InitOptions) -> Option<String> {
+    let year = chrono::Utc::now().format("%Y");
+    let holder = options.author.clone().unwrap_or_else(|| format!("The {} authors", options.name));
+    match license {
+        "MIT" => Some(format!("MIT License\n\nCopyright (c) {} {}\n\n{}", year, holder, MIT_TEXT)),
+        "BSD-3-Clause" => Some(format!("BSD 3-Clause License\n\nCopyright (c) {}, {}\n\n{}", year, holder, BSD_3_CLAUSE_TEXT)),
+        _ => None,
+    }
+}
+
+/// The generated pyproject.toml; `script` is the `module:function` of an app's command
+pub fn pyproject_toml(options: &InitOptions, script: Option<&str>) -> String {
+    let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+    let mut out = String::from("[project]\n");
+    out.push_str(&format!("name = {}\n", quote(&options.name)));
+    out.push_str("version = \"0.1.0\"\n");
+    out.push_str(&format!("description = {}\n", quote(&options.description)));
+    out.push_str("readme = \"README.md\"\n");
+    out.push_str(&format!("requires-python = {}\n", quote(&format!(">={}", options.python_version))));
+    if let Some(license) = &options.license {
+        out.push_str(&format!("license = {}\n", quote(license)));
+        if license_text(license, options).is_some() {
+            out.push_str("license-files = [\"LICENSE\"]\n");
+        }
+    }
+    out.push_str("dependencies = []\n");
+    if let Some(script) = script {
+        out.push_str(&format!("\n[project.scripts]\n{} = {}\n", quote(&options.name), quote(script)));
+    }
+    if options.test_framework == TestFramework::Pytest {
+        out.push_str("\n[dependency-groups]\ndev = [\"pytest>=8\"]\n");
+    }
+    out.push_str(&format!(
+        "\n[build-system]\nrequires = [{}]\nbuild-backend = {}\n",
+        quote(options.backend.requires()), quote(options.backend.module()),
+    ));
+    let test_command = match options.test_framework {
+        TestFramework::Pytest => Some("pytest"),
+        TestFramework::Unittest => Some("python -m unittest discover -s tests"),
+        TestFramework::None => None,
+    };
+    if let Some(command) = test_command {
+        out.push_str(&format!("\n[tool.snakepit.scripts]\ntest = {}\n", quote(command)));
+    }
+    out
+}
+
+const MIT_TEXT: &str = "Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the \"Software\"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED \"AS IS\", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+";
+
+const BSD_3_CLAUSE_TEXT: &str = "Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice, this
+   list of conditions and the following disclaimer.
+
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+3. Neither the name of the copyright holder nor the names of its
+   contributors may be used to endorse or promote products derived from
+   this software without specific prior written permission.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS \"AS IS\"
+AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::BuildSystem;
+
+    #[test]
+    fn test_app_template_with_src_layout() {
+        let mut options = InitOptions::new("My-Tool");
+        options.backend = BuildBackend::Flit;
+        options.license = Some("MIT".to_string());
+        options.author = Some("Ada".to_string());
+        let files = render(template_for("app").unwrap().as_ref(), &options).unwrap();
+        let paths: Vec<String> = files.iter().map(|file| file.path.to_string_lossy().replace('\\', "/")).collect();
+        assert!(paths.contains(&"src/my_tool/__init__.py".to_string()));
+        assert!(paths.contains(&"src/my_tool/__main__.py".to_string()));
+        assert!(paths.contains(&"tests/test_my_tool.py".to_string()));
+        assert!(paths.contains(&"LICENSE".to_string()));
+
+        let pyproject = files.iter().find(|file| file.path == Path::new("pyproject.toml")).unwrap();
+        let pyproject = String::from_utf8(pyproject.contents.clone()).unwrap();
+        let document: toml::Table = toml::from_str(&pyproject).unwrap();
+        assert_eq!(document["project"]["scripts"]["My-Tool"].as_str(), Some("my_tool.__main__:main"));
+        assert_eq!(document["project"]["license"].as_str(), Some("MIT"));
+        assert_eq!(BuildSystem::parse(&pyproject).unwrap().backend, "flit_core.buildapi");
+    }
+
+    #[test]
+    fn test_directory_template_renders_placeholders() {
+        let root = std::env::temp_dir().join(format!("snakepit-template-dir-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("{{package}}")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git").join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        std::fs::write(root.join("{{package}}").join("__init__.py"), "NAME = \"{{ name }}\"  # {{ unknown }}\n").unwrap();
+
+        let mut options = InitOptions::new("data-kit");
+        options.layout = Layout::Flat;
+        options.test_framework = TestFramework::None;
+        let files = render(&DirectoryTemplate::new("local", &root), &options).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, Path::new("data_kit").join("__init__.py"));
+        assert_eq!(files[0].contents, b"NAME = \"data-kit\"  # {{ unknown }}\n");
+        assert!(!String::from_utf8(files[1].contents.clone()).unwrap().contains("dependency-groups"));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(GitTemplate::is_git_spec("gh:acme/python-template"));
+        assert!(GitTemplate::is_git_spec("https://example.com/acme/template.git#v2"));
+        assert_eq!(GitTemplate::new("gh:acme/python-template#v2").url, "https://github.com/acme/python-template.git");
+        assert!(!GitTemplate::is_git_spec("lib"));
+        assert_eq!("Src".parse::<Layout>().unwrap(), Layout::Src);
+        assert!("poetry".parse::<BuildBackend>().is_err());
+    }
+}
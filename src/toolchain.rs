@@ -0,0 +1,333 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::dependency::ProjectDependencies;
+use crate::pep440::{SpecifierSet, Version};
+
+/// Latest release of python-build-standalone, whose `install_only` archives we unpack as-is
+const RELEASES_URL: &str = "https://api.github.com/repos/astral-sh/python-build-standalone/releases/latest";
+
+/// Where a project pins its interpreter version; pyenv and uv read the same file
+pub const PIN_FILE: &str = ".python-version";
+
+/// The interpreter a project asks for
+#[derive(Debug, Clone, PartialEq)]
+pub enum PythonRequest {
+    /// `3.12` (any 3.12.x) or `3.12.4`, from `.python-version`, the command line or config
+    Version(String),
+    /// `requires-python` from pyproject.toml
+    Specifiers(SpecifierSet),
+}
+
+impl PythonRequest {
+    /// `.python-version`, else pyproject.toml's `requires-python`, else `configured`
+    /// (the `python_version` setting); the nearest file up from `dir` wins
+    pub fn for_project(dir: &Path, configured: Option<&str>) -> Option<Self> {
+        if let Some(version) = pinned_version(dir) {
+            return Some(Self::Version(version));
+        }
+        let requires_python = dir.ancestors()
+            .map(|dir| dir.join("pyproject.toml"))
+            .find(|path| path.exists())
+            .and_then(|path| ProjectDependencies::from_pyproject_toml(path).ok())
+            .and_then(|deps| deps.python_version)
+            .and_then(|spec| SpecifierSet::parse(&spec).ok())
+            .filter(|set| !set.is_empty());
+        requires_python.map(Self::Specifiers)
+            .or_else(|| configured.map(|version| Self::Version(version.trim().to_string())))
+    }
+
+    pub fn allows(&self, version: &str) -> bool {
+        match self {
+            Self::Version(request) => matches_request(version, request),
+            Self::Specifiers(set) => Version::parse(version).is_ok_and(|v| set.contains(&v)),
+        }
+    }
+}
+
+impl fmt::Display for PythonRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Version(version) => write!(f, "{}", version),
+            Self::Specifiers(set) => write!(f, "{}", set),
+        }
+    }
+}
+
+/// A CPython build unpacked under `~/.snakepit/pythons`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledPython {
+    pub version: String,
+    pub path: PathBuf,
+}
+
+impl InstalledPython {
+    pub fn interpreter(&self) -> PathBuf {
+        if cfg!(target_os = "windows") {
+            self.path.join("python").join("python.exe")
+        } else {
+            self.path.join("python").join("bin").join("python3")
+        }
+    }
+}
+
+/// A build the release offers for this machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Download {
+    pub version: String,
+    pub filename: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Standalone CPython builds snakepit downloads and manages, so any version can back a venv
+pub struct ToolchainManager {
+    root: PathBuf,
+    triple: String,
+}
+
+impl ToolchainManager {
+    pub fn new() -> Self {
+        let root = snakegg::native::dirs::home_dir()
+            .map(|home| home.join(".snakepit"))
+            .unwrap_or_else(|| PathBuf::from(".snakepit"))
+            .join("pythons");
+        Self { root, triple: host_triple() }
+    }
+
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
+
+    pub fn with_triple(mut self, triple: &str) -> Self {
+        self.triple = triple.to_string();
+        self
+    }
+
+    /// Installed builds for this machine, newest first
+    pub fn installed(&self) -> Result<Vec<InstalledPython>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+        let suffix = format!("-{}", self.triple);
+        let mut pythons = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let Some(version) = name.strip_prefix("cpython-").and_then(|rest| rest.strip_suffix(&suffix)) else {
+                continue;
+            };
+            pythons.push(InstalledPython { version: version.to_string(), path });
+        }
+        sort_newest_first(&mut pythons, |python| &python.version);
+        Ok(pythons)
+    }
+
+    /// The newest installed build matching `request` (`3.12` or `3.12.4`)
+    pub fn find(&self, request: &str) -> Result<Option<InstalledPython>> {
+        Ok(self.installed()?.into_iter().find(|python| matches_request(&python.version, request)))
+    }
+
+    /// The newest interpreter already on this machine that fits `request`: a managed build,
+    /// or `python3.X` on PATH (judged by its minor version alone)
+    pub fn find_interpreter(&self, request: &PythonRequest) -> Option<PathBuf> {
+        let mut candidates: Vec<(String, PathBuf)> = self.installed().unwrap_or_default().into_iter()
+            .filter(|python| request.allows(&python.version))
+            .map(|python| (python.version.clone(), python.interpreter()))
+            .collect();
+        for minor in (8..=14).rev() {
+            let version = format!("3.{}", minor);
+            if request.allows(&version) {
+                candidates.extend(snakegg::native::which::find_executable(&format!("python{}", version)).map(|path| (version, path)));
+            }
+        }
+        // Stable, so a managed 3.12.4 stays ahead of a system `python3.12` it ties with
+        sort_newest_first(&mut candidates, |(version, _)| version);
+        candidates.into_iter().next().map(|(_, path)| path)
+    }
+
+    /// Builds the latest release offers for this machine, newest first
+    pub async fn available(&self) -> Result<Vec<Download>> {
+        let release: Release = client()?.get(RELEASES_URL).send().await?
+            .error_for_status()
+            .context("Failed to fetch the python-build-standalone release list")?
+            .json().await?;
+        let mut downloads: Vec<Download> = release.assets.into_iter()
+            .filter_map(|asset| {
+                let (version, triple) = parse_asset_name(&asset.name)?;
+                (triple == self.triple).then_some(Download { version, filename: asset.name, url: asset.browser_download_url })
+            })
+            .collect();
+        sort_newest_first(&mut downloads, |download| &download.version);
+        Ok(downloads)
+    }
+
+    /// Download, verify and unpack the newest build matching `request`; a no-op when one is installed
+    pub async fn install(&self, request: &PythonRequest) -> Result<InstalledPython> {
+        if let Some(installed) = self.installed()?.into_iter().find(|python| request.allows(&python.version)) {
+            return Ok(installed);
+        }
+        let download = self.available().await?.into_iter()
+            .find(|download| request.allows(&download.version))
+            .ok_or_else(|| anyhow!("No standalone build of Python {} for {}", request, self.triple))?;
+
+        let client = client()?;
+        let archive = client.get(&download.url).send().await?.error_for_status()?.bytes().await?;
+        let sums_url = format!("{}/SHA256SUMS", download.url.rsplit_once('/').map(|(base, _)| base).unwrap_or_default());
+        let sums = client.get(&sums_url).send().await?.error_for_status()?.text().await?;
+        let expected = checksum_for(&sums, &download.filename)
+            .ok_or_else(|| anyhow!("{} isn't listed in SHA256SUMS", download.filename))?;
+        let actual = snakegg::native::hash::compute_sha256_hex(&archive);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(anyhow!("Checksum mismatch for {}: expected {}, got {}", download.filename, expected, actual));
+        }
+
+        // Unpack next to the final location so a failed or interrupted install leaves nothing behind
+        let target = self.root.join(format!("cpython-{}-{}", download.version, self.triple));
+        let staging = self.root.join(format!(".staging-{}", std::process::id()));
+        std::fs::create_dir_all(&staging)?;
+        let unpacked = tar::Archive::new(flate2::read::GzDecoder::new(&archive[..])).unpack(&staging)
+            .with_context(|| format!("Failed to unpack {}", download.filename));
+        if let Err(e) = unpacked.and_then(|_| Ok(std::fs::rename(&staging, &target)?)) {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        Ok(InstalledPython { version: download.version, path: target })
+    }
+
+    /// Remove the newest installed build matching `request`
+    pub fn uninstall(&self, request: &str) -> Result<InstalledPython> {
+        let installed = self.find(request)?
+            .ok_or_else(|| anyhow!("Python {} is not installed", request))?;
+        std::fs::remove_dir_all(&installed.path)?;
+        Ok(installed)
+    }
+}
+
+/// The version pinned for `dir` or the nearest parent with a `.python-version`
+pub fn pinned_version(dir: &Path) -> Option<String> {
+    dir.ancestors()
+        .map(|dir| dir.join(PIN_FILE))
+        .find(|path| path.exists())
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| content.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#')).map(String::from))
+}
+
+pub fn pin_version(dir: &Path, version: &str) -> Result<PathBuf> {
+    let path = dir.join(PIN_FILE);
+    std::fs::write(&path, format!("{}\n", version))?;
+    Ok(path)
+}
+
+/// `3.12` matches every 3.12.x; `3.12.4` only itself
+pub fn matches_request(version: &str, request: &str) -> bool {
+    let request = request.trim().trim_start_matches("cpython-").trim_start_matches("python");
+    version == request || version.starts_with(&format!("{}.", request))
+}
+
+/// `(version, triple)` of an `install_only` archive such as
+/// `cpython-3.12.4+20240726-x86_64-unknown-linux-gnu-install_only.tar.gz`
+fn parse_asset_name(name: &str) -> Option<(String, String)> {
+    let rest = name.strip_prefix("cpython-")?.strip_suffix("-install_only.tar.gz")?;
+    let (version, rest) = rest.split_once('+')?;
+    let (_tag, triple) = rest.split_once('-')?;
+    Some((version.to_string(), triple.to_string()))
+}
+
+/// The digest `SHA256SUMS` lists for `filename`
+pub(crate) fn checksum_for<'a>(sums: &'a str, filename: &str) -> Option<&'a str> {
+    sums.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*') == filename)
+        .map(|(digest, _)| digest)
+}
+
+/// The python-build-standalone target triple of this machine
+pub(crate) fn host_triple() -> String {
+    let arch = std::env::consts::ARCH;
+    match std::env::consts::OS {
+        "macos" => format!("{}-apple-darwin", arch),
+        "windows" => format!("{}-pc-windows-msvc", arch),
+        _ => format!("{}-unknown-linux-gnu", arch),
+    }
+}
+
+fn sort_newest_first<T>(items: &mut [T], version: impl Fn(&T) -> &String) {
+    items.sort_by(|a, b| match (Version::parse(version(a)), Version::parse(version(b))) {
+        (Ok(a), Ok(b)) => b.cmp(&a),
+        _ => version(b).cmp(version(a)),
+    });
+}
+
+pub(crate) fn client() -> Result<reqwest::Client> {
+    // GitHub's API rejects requests without a User-Agent
+    Ok(reqwest::Client::builder()
+        .user_agent(concat!("snakepit/", env!("CARGO_PKG_VERSION")))
+        .build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_release_assets() {
+        assert_eq!(
+            parse_asset_name("cpython-3.12.4+20240726-x86_64-unknown-linux-gnu-install_only.tar.gz"),
+            Some(("3.12.4".to_string(), "x86_64-unknown-linux-gnu".to_string())),
+        );
+        assert_eq!(parse_asset_name("cpython-3.12.4+20240726-x86_64-unknown-linux-gnu-install_only_stripped.tar.gz"), None);
+        assert_eq!(parse_asset_name("cpython-3.13.0+20241016-x86_64-unknown-linux-gnu-freethreaded+pgo-full.tar.zst"), None);
+
+        let sums = "aa11  cpython-3.12.4+20240726-x86_64-unknown-linux-gnu-install_only.tar.gz\nbb22 *cpython-3.11.9+20240726-x86_64-unknown-linux-gnu-install_only.tar.gz\n";
+        assert_eq!(checksum_for(sums, "cpython-3.11.9+20240726-x86_64-unknown-linux-gnu-install_only.tar.gz"), Some("bb22"));
+        assert_eq!(checksum_for(sums, "cpython-3.10.0.tar.gz"), None);
+    }
+
+    #[test]
+    fn test_installed_builds_and_pins() {
+        let root = std::env::temp_dir().join(format!("snakepit-toolchain-{}", std::process::id()));
+        for name in ["cpython-3.12.4-x86_64-unknown-linux-gnu", "cpython-3.12.10-x86_64-unknown-linux-gnu", "cpython-3.11.9-aarch64-apple-darwin"] {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+        }
+        let manager = ToolchainManager::new().with_root(root.clone()).with_triple("x86_64-unknown-linux-gnu");
+
+        let versions: Vec<String> = manager.installed().unwrap().into_iter().map(|p| p.version).collect();
+        assert_eq!(versions, vec!["3.12.10", "3.12.4"]);
+        assert_eq!(manager.find("3.12").unwrap().unwrap().version, "3.12.10");
+        assert_eq!(manager.find("3.12.4").unwrap().unwrap().version, "3.12.4");
+        assert!(manager.find("3.1").unwrap().is_none());
+        assert!(manager.find("3.11").unwrap().is_none());
+
+        let project = root.join("project").join("src");
+        std::fs::create_dir_all(&project).unwrap();
+        pin_version(&root.join("project"), "3.12").unwrap();
+        assert_eq!(pinned_version(&project).as_deref(), Some("3.12"));
+
+        let request = PythonRequest::Specifiers(SpecifierSet::parse(">=3.10,<3.12.5").unwrap());
+        assert!(request.allows("3.12.4"));
+        assert!(!request.allows("3.12.10"));
+        assert_eq!(PythonRequest::for_project(&project, None), Some(PythonRequest::Version("3.12".to_string())));
+        std::fs::remove_file(root.join("project").join(PIN_FILE)).unwrap();
+        std::fs::write(root.join("project").join("pyproject.toml"), "[project]\nname = \"demo\"\nrequires-python = \">=3.11\"\n").unwrap();
+        assert_eq!(PythonRequest::for_project(&project, Some("3.9")).unwrap().to_string(), ">=3.11");
+        std::fs::remove_file(root.join("project").join("pyproject.toml")).unwrap();
+        assert_eq!(PythonRequest::for_project(&project, Some("3.9")), Some(PythonRequest::Version("3.9".to_string())));
+
+        manager.uninstall("3.12.4").unwrap();
+        assert_eq!(manager.installed().unwrap().len(), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use snakegg::native::dirs;
+use std::path::{Path, PathBuf};
+use crate::manifest::canonical_name;
+
+/// What `tool install` recorded about a tool, kept in its environment
+pub const RECEIPT_FILE: &str = "snakepit-tool.json";
+
+/// A command-line tool installed into its own environment by `snakepit tool install`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolReceipt {
+    /// The package as published
+    pub name: String,
+    /// What was asked for, e.g. `ruff` or `black==24.2.0`; upgrades reinstall from it
+    pub requirement: String,
+    pub version: String,
+    /// Commands linked into the bin directory
+    pub commands: Vec<String>,
+    /// Interpreter the environment was built with, when one was asked for
+    #[serde(default)]
+    pub python: Option<String>,
+    pub installed_at: String,
+}
+
+/// Tool environments under `<data dir>/snakepit/tools/<name>`, with their commands linked
+/// into `~/.local/bin` (or `SNAKEPIT_TOOL_BIN_DIR`) so they're on PATH without activating anything
+#[derive(Debug, Clone)]
+pub struct ToolManager {
+    root: PathBuf,
+    bin_dir: PathBuf,
+}
+
+impl ToolManager {
+    pub fn new() -> Self {
+        let root = std::env::var_os("SNAKEPIT_TOOL_DIR").map(PathBuf::from).unwrap_or_else(|| {
+            dirs::data_dir().unwrap_or_else(|| PathBuf::from(".snakepit")).join("snakepit").join("tools")
+        });
+        let bin_dir = std::env::var_os("SNAKEPIT_TOOL_BIN_DIR").map(PathBuf::from).unwrap_or_else(|| {
+            dirs::home_dir().unwrap_or_default().join(".local").join("bin")
+        });
+        Self { root, bin_dir }
+    }
+
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
+
+    pub fn with_bin_dir(mut self, bin_dir: PathBuf) -> Self {
+        self.bin_dir = bin_dir;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn bin_dir(&self) -> &Path {
+        &self.bin_dir
+    }
+
+    /// The environment `package` gets, whether or not it's installed
+    pub fn tool_dir(&self, package: &str) -> PathBuf {
+        self.root.join(canonical_name(package))
+    }
+
+    pub fn receipt(&self, package: &str) -> Option<ToolReceipt> {
+        read_receipt(&self.tool_dir(package))
+    }
+
+    pub fn save_receipt(&self, receipt: &ToolReceipt) -> Result<()> {
+        write_receipt(&self.tool_dir(&receipt.name), receipt)
+    }
+
+    /// Every installed tool, by name
+    pub fn list(&self) -> Vec<ToolReceipt> {
+        let Ok(entries) = std::fs::read_dir(&self.root) else { return Vec::new() };
+        let mut tools: Vec<ToolReceipt> = entries.flatten()
+            .filter_map(|entry| self.receipt(&entry.file_name().to_string_lossy()))
+            .collect();
+        tools.sort_by_key(|tool| canonical_name(&tool.name));
+        tools
+    }
+
+    /// The tool providing `command`, by package name or by one of its commands
+    pub fn find(&self, command: &str) -> Option<ToolReceipt> {
+        self.receipt(command).or_else(|| self.list().into_iter().find(|tool| tool.commands.iter().any(|c| c == command)))
+    }
+
+    /// Link `commands` from the tool's environment into the bin directory. A file there
+    /// that belongs to something else is left alone and reported.
+    pub fn link(&self, package: &str, commands: &[String]) -> Result<Vec<String>> {
+        std::fs::create_dir_all(&self.bin_dir)
+            .with_context(|| format!("Failed to create {}", self.bin_dir.display()))?;
+        let scripts = crate::venv::bin_dir(&self.tool_dir(package));
+        let mut conflicts = Vec::new();
+        for command in commands {
+            let Some(source) = script_path(&scripts, command) else { continue };
+            let target = self.bin_dir.join(source.file_name().unwrap_or_default());
+            if target.symlink_metadata().is_ok() {
+                if !self.owns(&target) {
+                    conflicts.push(command.clone());
+                    continue;
+                }
+                std::fs::remove_file(&target)?;
+            }
+            link_file(&source, &target)
+                .with_context(|| format!("Failed to link {}", target.display()))?;
+        }
+        Ok(conflicts)
+    }
+
+    /// Remove the tool's links from the bin directory, then its environment
+    pub fn uninstall(&self, package: &str) -> Result<ToolReceipt> {
+        let receipt = self.receipt(package)
+            .ok_or_else(|| anyhow!("{} is not installed as a tool (see `snakepit tool list`)", package))?;
+        let scripts = crate::venv::bin_dir(&self.tool_dir(package));
+        for command in &receipt.commands {
+            let Some(source) = script_path(&scripts, command) else { continue };
+            let target = self.bin_dir.join(source.file_name().unwrap_or_default());
+            if self.owns(&target) {
+                std::fs::remove_file(&target)?;
+            }
+        }
+        std::fs::remove_dir_all(self.tool_dir(package))?;
+        Ok(receipt)
+    }
+
+    /// Whether `link` is one of ours: a symlink into a tool environment, or (on Windows,
+    /// where launchers are copied) a file with the same name in one
+    fn owns(&self, link: &Path) -> bool {
+        match std::fs::read_link(link) {
+            Ok(target) => target.starts_with(&self.root),
+            Err(_) => cfg!(windows) && std::fs::read_dir(&self.root).is_ok_and(|entries| entries.flatten().any(|tool| {
+                script_path(&crate::venv::bin_dir(&tool.path()), &link.file_name().unwrap_or_default().to_string_lossy()).is_some()
+            })),
+        }
+    }
+}
+
+impl Default for ToolManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The receipt in the tool environment `venv`; `None` for one that never finished building
+pub fn read_receipt(venv: &Path) -> Option<ToolReceipt> {
+    let content = std::fs::read_to_string(venv.join(RECEIPT_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn write_receipt(venv: &Path, receipt: &ToolReceipt) -> Result<()> {
+    let path = venv.join(RECEIPT_FILE);
+    std::fs::write(&path, serde_json::to_string_pretty(receipt)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The cached environment `snakepit x` runs `requirement` from, named by a hash of the
+/// requirement and interpreter so every distinct request gets its own
+pub fn ephemeral_dir(cache_dir: &Path, requirement: &str, python: Option<&str>) -> PathBuf {
+    let key = format!("{}\n{}", requirement.trim(), python.unwrap_or_default());
+    let hash = snakegg::native::hash::compute_sha256_hex(key.as_bytes());
+    cache_dir.join("tools").join(&hash[..16])
+}
+
+/// The command to run from `receipt`: `wanted` if the tool has it, else the one named
+/// after the package, else its first
+pub fn pick_command(receipt: &ToolReceipt, wanted: Option<&str>) -> Result<String> {
+    if let Some(wanted) = wanted {
+        return receipt.commands.iter()
+            .find(|command| *command == wanted)
+            .cloned()
+            .ok_or_else(|| anyhow!("{} has no command '{}' (it has {})", receipt.name, wanted, receipt.commands.join(", ")));
+    }
+    let name = canonical_name(&receipt.name);
+    receipt.commands.iter()
+        .find(|command| canonical_name(command) == name)
+        .or_else(|| receipt.commands.first())
+        .cloned()
+        .ok_or_else(|| anyhow!("{} has no commands", receipt.name))
+}
+
+/// Names of the `console_scripts` and `gui_scripts` of the distribution whose dist-info is `dist_info`
+pub fn entry_point_commands(dist_info: &Path) -> Vec<String> {
+    let Ok(content) = std::fs::read_to_string(dist_info.join("entry_points.txt")) else { return Vec::new() };
+    let mut section = String::new();
+    let mut commands = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            section = name.trim().to_string();
+        } else if matches!(section.as_str(), "console_scripts" | "gui_scripts") {
+            if let Some((name, _)) = line.split_once('=') {
+                commands.push(name.trim().to_string());
+            }
+        }
+    }
+    commands
+}
+
+/// The dist-info of `package` in `site_packages`
+pub fn find_dist_info(site_packages: &Path, package: &str) -> Option<PathBuf> {
+    let wanted = canonical_name(package);
+    std::fs::read_dir(site_packages).ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name().unwrap_or_default().to_string_lossy()
+                .strip_suffix(".dist-info")
+                .and_then(|stem| stem.split_once('-'))
+                .is_some_and(|(name, _)| canonical_name(name) == wanted)
+        })
+}
+
+/// `command` in a venv's scripts directory, with the `.exe` Windows launchers have
+fn script_path(scripts: &Path, command: &str) -> Option<PathBuf> {
+    [command.to_string(), format!("{}.exe", command)]
+        .into_iter()
+        .map(|name| scripts.join(name))
+        .find(|path| path.exists())
+}
+
+#[cfg(unix)]
+fn link_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(not(unix))]
+fn link_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::fs::copy(source, target).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_points() {
+        let dist_info = std::env::temp_dir().join(format!("snakepit-tool-ep-{}", std::process::id()));
+        std::fs::create_dir_all(&dist_info).unwrap();
+        std::fs::write(dist_info.join("entry_points.txt"), "[console_scripts]\nblack = black:patched_main\nblackd = blackd:patched_main [d]\n\n[gui_scripts]\nblack-gui = black.gui:main\n\n[black.plugins]\nother = x:y\n").unwrap();
+        assert_eq!(entry_point_commands(&dist_info), vec!["black", "blackd", "black-gui"]);
+        std::fs::remove_dir_all(&dist_info).unwrap();
+    }
+
+    #[test]
+    fn test_picks_the_command_named_after_the_package() {
+        let receipt = ToolReceipt {
+            name: "HTTPie".to_string(),
+            requirement: "httpie".to_string(),
+            version: "3.2.2".to_string(),
+            commands: vec!["http".to_string(), "https".to_string(), "httpie".to_string()],
+            python: None,
+            installed_at: String::new(),
+        };
+        assert_eq!(pick_command(&receipt, None).unwrap(), "httpie");
+        assert_eq!(pick_command(&receipt, Some("https")).unwrap(), "https");
+        assert!(pick_command(&receipt, Some("curl")).is_err());
+        let cache = Path::new("/cache");
+        assert_eq!(ephemeral_dir(cache, "black", None), ephemeral_dir(cache, " black ", None));
+        assert_ne!(ephemeral_dir(cache, "black", None), ephemeral_dir(cache, "black==24.2.0", None));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_links_commands_and_leaves_foreign_files() {
+        let base = std::env::temp_dir().join(format!("snakepit-tools-{}", std::process::id()));
+        let tools = ToolManager::new().with_root(base.join("tools")).with_bin_dir(base.join("bin"));
+        let scripts = crate::venv::bin_dir(&tools.tool_dir("Black"));
+        std::fs::create_dir_all(&scripts).unwrap();
+        std::fs::write(scripts.join("black"), "#!/bin/sh\n").unwrap();
+        std::fs::write(scripts.join("blackd"), "#!/bin/sh\n").unwrap();
+        std::fs::create_dir_all(tools.bin_dir()).unwrap();
+        std::fs::write(tools.bin_dir().join("blackd"), "someone else's").unwrap();
+
+        let commands = vec!["black".to_string(), "blackd".to_string()];
+        assert_eq!(tools.link("Black", &commands).unwrap(), vec!["blackd"]);
+        assert_eq!(std::fs::read_link(tools.bin_dir().join("black")).unwrap(), scripts.join("black"));
+        tools.save_receipt(&ToolReceipt {
+            name: "Black".to_string(),
+            requirement: "black".to_string(),
+            version: "24.2.0".to_string(),
+            commands,
+            python: None,
+            installed_at: "2024-03-01T00:00:00Z".to_string(),
+        }).unwrap();
+        assert_eq!(tools.find("blackd").map(|tool| tool.version), Some("24.2.0".to_string()));
+        assert_eq!(tools.list().len(), 1);
+
+        tools.uninstall("black").unwrap();
+        assert!(tools.bin_dir().join("black").symlink_metadata().is_err());
+        assert!(tools.bin_dir().join("blackd").exists());
+        assert!(tools.list().is_empty());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
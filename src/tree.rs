@@ -0,0 +1,534 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use crate::lockfile::Lockfile;
+use crate::manifest::canonical_name;
+use crate::markers::{parse_requirement, TargetEnvironment};
+use crate::simple_index::parse_requires_dist;
+
+/// A package in the graph, keyed by its canonical name
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub version: String,
+    pub requires: Vec<Edge>,
+}
+
+/// One requirement of a package
+#[derive(Debug, Clone)]
+pub struct Edge {
+    /// Canonical name of the required package
+    pub package: String,
+    /// Version specifiers as written, e.g. `>=2.5,<4`; empty for any version
+    pub specifier: String,
+}
+
+/// A package in the tree `--json` prints; the same shape `render` draws
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TreeEntry {
+    pub name: String,
+    /// `null` when the package isn't in the graph
+    pub version: Option<String>,
+    /// What the parent asked for, e.g. `>=2.5,<4` (empty for any version); `null` at the top
+    pub required: Option<String>,
+    pub missing: bool,
+    /// Expanded earlier in the tree; `dependencies` is left empty here
+    pub repeated: bool,
+    pub dependencies: Vec<TreeEntry>,
+}
+
+/// Who requires whom, from installed dist-info metadata or the lockfile the resolver wrote
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    nodes: BTreeMap<String, Node>,
+}
+
+impl DependencyGraph {
+    /// Read `METADATA` from every `.dist-info` in site-packages.
+    ///
+    /// Requirements whose marker doesn't hold here (including extras nobody can be
+    /// shown to have asked for) are left out, as they weren't installed for this environment.
+    pub fn from_site_packages(site_packages: &Path) -> Result<Self> {
+        let env = TargetEnvironment::host();
+        let mut graph = Self::default();
+        for entry in std::fs::read_dir(site_packages)?.flatten() {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            if !dir_name.ends_with(".dist-info") {
+                continue;
+            }
+            let Ok(metadata) = std::fs::read_to_string(entry.path().join("METADATA")) else { continue };
+            let header = |key: &str| metadata.lines()
+                .take_while(|line| !line.trim().is_empty())
+                .find_map(|line| line.strip_prefix(key))
+                .map(|value| value.trim().to_string());
+            let (Some(name), Some(version)) = (header("Name:"), header("Version:")) else { continue };
+
+            let requires = parse_requires_dist(&metadata).iter()
+                .filter_map(|req| parse_requirement(req).ok())
+                .filter(|spec| spec.marker.as_ref().is_none_or(|marker| marker.evaluate(&env)))
+                .map(|spec| Edge {
+                    package: canonical_name(&spec.name),
+                    specifier: spec.version_specs.iter()
+                        .map(|v| format!("{}{}", v.operator, v.version))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                })
+                .collect();
+            graph.insert(Node { name, version, requires });
+        }
+        Ok(graph)
+    }
+
+    /// The graph `lock` resolved; each locked dependency is pinned, so that's its specifier
+    pub fn from_lockfile(lockfile: &Lockfile) -> Self {
+        let mut graph = Self::default();
+        for package in &lockfile.packages {
+            let requires = package.dependencies.iter()
+                .filter_map(|dep| parse_requirement(dep).ok())
+                .map(|spec| Edge {
+                    package: canonical_name(&spec.name),
+                    specifier: spec.version_specs.iter()
+                        .map(|v| format!("{}{}", v.operator, v.version))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                })
+                .collect();
+            graph.insert(Node { name: package.name.clone(), version: package.version.clone(), requires });
+        }
+        graph
+    }
+
+    pub fn insert(&mut self, node: Node) {
+        self.nodes.insert(canonical_name(&node.name), node);
+    }
+
+    pub fn get(&self, package: &str) -> Option<&Node> {
+        self.nodes.get(&canonical_name(package))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Every package, by canonical name
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.values()
+    }
+
+    /// Packages nothing else in the graph requires
+    pub fn roots(&self) -> Vec<String> {
+        let required: HashSet<&str> = self.nodes.values()
+            .flat_map(|node| node.requires.iter().map(|edge| edge.package.as_str()))
+            .collect();
+        self.nodes.keys().filter(|name| !required.contains(name.as_str())).cloned().collect()
+    }
+
+    /// Every dependency chain from one of `roots` down to `target`, shortest first.
+    ///
+    /// Each chain lists canonical names from the root to the target; a package never
+    /// appears twice in one chain, so cycles don't repeat. Stops after `limit` chains.
+    pub fn chains_to(&self, roots: &[String], target: &str, limit: usize) -> Vec<Vec<String>> {
+        let target = canonical_name(target);
+        let mut chains = Vec::new();
+        for root in roots {
+            let root = canonical_name(root);
+            if self.nodes.contains_key(&root) {
+                self.walk(&mut vec![root], &target, limit, &mut chains);
+            }
+        }
+        chains.sort_by_key(|chain| chain.len());
+        chains.dedup();
+        chains
+    }
+
+    fn walk(&self, path: &mut Vec<String>, target: &str, limit: usize, chains: &mut Vec<Vec<String>>) {
+        if chains.len() >= limit {
+            return;
+        }
+        let current = path.last().cloned().unwrap_or_default();
+        if current == target {
+            chains.push(path.clone());
+            return;
+        }
+        let Some(node) = self.nodes.get(&current) else { return };
+        for edge in &node.requires {
+            if path.contains(&edge.package) {
+                continue;
+            }
+            path.push(edge.package.clone());
+            self.walk(path, target, limit, chains);
+            path.pop();
+        }
+    }
+
+    /// Packages that require `package`, with the specifier each asks for
+    pub fn dependents(&self, package: &str) -> Vec<(&Node, &str)> {
+        self.nodes.values()
+            .flat_map(|node| node.requires.iter()
+                .filter(move |edge| edge.package == package)
+                .map(move |edge| (node, edge.specifier.as_str())))
+            .collect()
+    }
+
+    /// Canonical names of `from` and everything they require, directly or not
+    pub fn reachable(&self, from: &[String]) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<String> = from.iter().map(|name| canonical_name(name)).collect();
+        while let Some(name) = stack.pop() {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            if let Some(node) = self.nodes.get(&name) {
+                stack.extend(node.requires.iter().map(|edge| edge.package.clone()));
+            }
+        }
+        seen
+    }
+
+    /// Installed packages nothing in `roots` leads to, sorted
+    pub fn unreachable_from(&self, roots: &[String]) -> Vec<String> {
+        let reachable = self.reachable(roots);
+        self.nodes.keys().filter(|name| !reachable.contains(*name)).cloned().collect()
+    }
+
+    /// Dependencies of `removed` that nothing else would still need once they're gone:
+    /// packages only `removed` (or other such packages) lead to, leaving out `keep`. Sorted.
+    pub fn orphaned_by(&self, removed: &[String], keep: &[String]) -> Vec<String> {
+        let removed: HashSet<String> = removed.iter().map(|name| canonical_name(name)).collect();
+        let removed_list: Vec<String> = removed.iter().cloned().collect();
+        let candidates: HashSet<String> = self.reachable(&removed_list).into_iter()
+            .filter(|name| !removed.contains(name) && self.nodes.contains_key(name))
+            .collect();
+        // Whatever the rest of the environment (and `keep`) still leads to stays
+        let survivors: Vec<String> = self.nodes.keys()
+            .filter(|name| !removed.contains(*name) && !candidates.contains(*name))
+            .cloned()
+            .chain(keep.iter().map(|name| canonical_name(name)))
+            .collect();
+        let needed = self.reachable(&survivors);
+        let mut orphans: Vec<String> = candidates.into_iter().filter(|name| !needed.contains(name)).collect();
+        orphans.sort();
+        orphans
+    }
+
+    /// Indented tree under each root, down to `depth` levels below it.
+    ///
+    /// A package whose subtree was already printed is marked `(*)` instead of being
+    /// expanded again, which also keeps dependency cycles finite.
+    pub fn render(&self, roots: &[String], depth: Option<usize>) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut shown = HashSet::new();
+        for root in roots {
+            let package = canonical_name(root);
+            lines.push(self.label(&package, None));
+            if let Some(node) = self.nodes.get(&package) {
+                shown.insert(package);
+                let children: Vec<(String, Option<&str>)> = node.requires.iter()
+                    .map(|edge| (edge.package.clone(), Some(edge.specifier.as_str())))
+                    .collect();
+                self.render_children(&children, "", depth, 1, &mut shown, &mut lines, false);
+            }
+        }
+        lines
+    }
+
+    /// What requires `package`, transitively, as a tree growing towards the roots
+    pub fn render_inverted(&self, package: &str, depth: Option<usize>) -> Vec<String> {
+        let package = canonical_name(package);
+        let mut lines = vec![self.label(&package, None)];
+        let mut shown = HashSet::from([package.clone()]);
+        let parents: Vec<(String, Option<&str>)> = self.dependents(&package).into_iter()
+            .map(|(node, specifier)| (canonical_name(&node.name), Some(specifier)))
+            .collect();
+        self.render_children(&parents, "", depth, 1, &mut shown, &mut lines, true);
+        lines
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_children(
+        &self,
+        children: &[(String, Option<&str>)],
+        prefix: &str,
+        depth: Option<usize>,
+        level: usize,
+        shown: &mut HashSet<String>,
+        lines: &mut Vec<String>,
+        inverted: bool,
+    ) {
+        if depth.is_some_and(|max| level > max) {
+            return;
+        }
+        for (i, (package, specifier)) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            let label = self.label(package, *specifier);
+
+            let next: Vec<(String, Option<&str>)> = if inverted {
+                self.dependents(package).into_iter()
+                    .map(|(node, specifier)| (canonical_name(&node.name), Some(specifier)))
+                    .collect()
+            } else {
+                self.nodes.get(package)
+                    .map(|node| node.requires.iter().map(|e| (e.package.clone(), Some(e.specifier.as_str()))).collect())
+                    .unwrap_or_default()
+            };
+
+            if !next.is_empty() && !shown.insert(package.clone()) {
+                lines.push(format!("{}{}{} (*)", prefix, branch, label));
+                continue;
+            }
+            lines.push(format!("{}{}{}", prefix, branch, label));
+            self.render_children(&next, &format!("{}{}", prefix, indent), depth, level + 1, shown, lines, inverted);
+        }
+    }
+
+    /// `render` as data: one entry per root, each with its dependencies nested below it
+    pub fn entries(&self, roots: &[String], depth: Option<usize>) -> Vec<TreeEntry> {
+        let mut shown = HashSet::new();
+        roots.iter()
+            .map(|root| {
+                let package = canonical_name(root);
+                let mut entry = self.entry(&package, None, false);
+                if self.nodes.contains_key(&package) {
+                    shown.insert(package.clone());
+                    entry.dependencies = self.child_entries(&self.next(&package, false), depth, 1, &mut shown, false);
+                }
+                entry
+            })
+            .collect()
+    }
+
+    /// `render_inverted` as data: `package`, with what requires it nested below
+    pub fn inverted_entry(&self, package: &str, depth: Option<usize>) -> TreeEntry {
+        let package = canonical_name(package);
+        let mut shown = HashSet::from([package.clone()]);
+        let mut entry = self.entry(&package, None, false);
+        entry.dependencies = self.child_entries(&self.next(&package, true), depth, 1, &mut shown, true);
+        entry
+    }
+
+    fn child_entries(
+        &self,
+        children: &[(String, String)],
+        depth: Option<usize>,
+        level: usize,
+        shown: &mut HashSet<String>,
+        inverted: bool,
+    ) -> Vec<TreeEntry> {
+        if depth.is_some_and(|max| level > max) {
+            return Vec::new();
+        }
+        children.iter()
+            .map(|(package, specifier)| {
+                let next = self.next(package, inverted);
+                if !next.is_empty() && !shown.insert(package.clone()) {
+                    return self.entry(package, Some(specifier), true);
+                }
+                let mut entry = self.entry(package, Some(specifier), false);
+                entry.dependencies = self.child_entries(&next, depth, level + 1, shown, inverted);
+                entry
+            })
+            .collect()
+    }
+
+    /// The packages below `package`, each with its specifier: what it requires, or what requires it
+    fn next(&self, package: &str, inverted: bool) -> Vec<(String, String)> {
+        if inverted {
+            self.dependents(package).into_iter()
+                .map(|(node, specifier)| (canonical_name(&node.name), specifier.to_string()))
+                .collect()
+        } else {
+            self.nodes.get(package)
+                .map(|node| node.requires.iter().map(|e| (e.package.clone(), e.specifier.clone())).collect())
+                .unwrap_or_default()
+        }
+    }
+
+    fn entry(&self, package: &str, specifier: Option<&str>, repeated: bool) -> TreeEntry {
+        let node = self.nodes.get(package);
+        TreeEntry {
+            name: node.map(|node| node.name.clone()).unwrap_or_else(|| package.to_string()),
+            version: node.map(|node| node.version.clone()),
+            required: specifier.map(str::to_string),
+            missing: node.is_none(),
+            repeated,
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// `name version`, plus what the parent asked for and whether it's missing
+    fn label(&self, package: &str, specifier: Option<&str>) -> String {
+        let mut label = match self.nodes.get(package) {
+            Some(node) => format!("{} {}", node.name, node.version),
+            None => package.to_string(),
+        };
+        match specifier {
+            Some("") => label.push_str(" [required: any]"),
+            Some(specifier) => label.push_str(&format!(" [required: {}]", specifier)),
+            None => {}
+        }
+        if !self.nodes.contains_key(package) {
+            label.push_str(" (missing)");
+        }
+        label
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, version: &str, requires: &[(&str, &str)]) -> Node {
+        Node {
+            name: name.to_string(),
+            version: version.to_string(),
+            requires: requires.iter()
+                .map(|(package, specifier)| Edge { package: package.to_string(), specifier: specifier.to_string() })
+                .collect(),
+        }
+    }
+
+    fn graph() -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        graph.insert(node("Flask", "3.0.0", &[("werkzeug", ">=3.0"), ("jinja2", ">=3.1.2"), ("click", ">=8.1.3")]));
+        graph.insert(node("Werkzeug", "3.0.1", &[("markupsafe", ">=2.1.1")]));
+        graph.insert(node("Jinja2", "3.1.2", &[("markupsafe", ">=2.0")]));
+        graph.insert(node("MarkupSafe", "2.1.3", &[]));
+        graph.insert(node("click", "8.1.7", &[("colorama", "")]));
+        graph
+    }
+
+    #[test]
+    fn test_renders_tree_with_dedup_and_missing() {
+        let graph = graph();
+        assert_eq!(graph.roots(), vec!["flask"]);
+        assert_eq!(graph.render(&graph.roots(), None), vec![
+            "Flask 3.0.0",
+            "├── Werkzeug 3.0.1 [required: >=3.0]",
+            "│   └── MarkupSafe 2.1.3 [required: >=2.1.1]",
+            "├── Jinja2 3.1.2 [required: >=3.1.2]",
+            "│   └── MarkupSafe 2.1.3 [required: >=2.0]",
+            "└── click 8.1.7 [required: >=8.1.3]",
+            "    └── colorama [required: any] (missing)",
+        ]);
+        assert_eq!(graph.render(&["flask".to_string()], Some(1)).len(), 4);
+    }
+
+    #[test]
+    fn test_inverted_tree_shows_dependents() {
+        let mut graph = graph();
+        graph.insert(node("a", "1", &[("b", "")]));
+        graph.insert(node("b", "1", &[("a", ""), ("markupsafe", "")]));
+        assert_eq!(graph.render_inverted("MarkupSafe", None), vec![
+            "MarkupSafe 2.1.3",
+            "├── b 1 [required: any]",
+            "│   └── a 1 [required: any]",
+            "│       └── b 1 [required: any] (*)",
+            "├── Jinja2 3.1.2 [required: >=2.0]",
+            "│   └── Flask 3.0.0 [required: >=3.1.2]",
+            "└── Werkzeug 3.0.1 [required: >=2.1.1]",
+            "    └── Flask 3.0.0 [required: >=3.0]",
+        ]);
+    }
+
+    #[test]
+    fn test_orphans_and_unreachable_packages() {
+        let mut graph = graph();
+        graph.insert(node("rich", "13.7.0", &[("markupsafe", ""), ("a", "")]));
+        graph.insert(node("a", "1", &[("b", "")]));
+        graph.insert(node("b", "1", &[("a", "")]));
+
+        // MarkupSafe stays: Werkzeug and Jinja2 still need it
+        assert_eq!(graph.orphaned_by(&["rich".to_string()], &[]), vec!["a", "b"]);
+        assert_eq!(graph.orphaned_by(&["flask".to_string()], &["click".to_string()]), vec!["jinja2", "werkzeug"]);
+        assert_eq!(graph.orphaned_by(&["Flask".to_string(), "rich".to_string()], &[]), vec!["a", "b", "click", "jinja2", "markupsafe", "werkzeug"]);
+        assert_eq!(graph.unreachable_from(&["Flask".to_string()]), vec!["a", "b", "rich"]);
+    }
+
+    #[test]
+    fn test_packages_sharing_projects_declare_are_kept() {
+        use crate::registry::ProjectRegistry;
+
+        let base = std::env::temp_dir().join(format!("snakepit-tree-shared-{}", std::process::id()));
+        let (web, worker) = (base.join("web"), base.join("worker"));
+        std::fs::create_dir_all(&web).unwrap();
+        std::fs::create_dir_all(&worker).unwrap();
+        std::fs::write(web.join("requirements.txt"), "jinja2\n").unwrap();
+        std::fs::write(worker.join("requirements.txt"), "Click\n").unwrap();
+        let env = base.join(".venv");
+        let mut registry = ProjectRegistry::default();
+        registry.register(&web, Some(env.clone()));
+        registry.register(&worker, Some(env.clone()));
+
+        // Removing Flask would orphan click and Jinja2, but the projects sharing the venv declare them
+        let graph = graph();
+        let keep: Vec<String> = registry.declared_in_environment(Some(&env)).into_iter().collect();
+        assert_eq!(graph.orphaned_by(&["flask".to_string()], &[]), vec!["click", "jinja2", "markupsafe", "werkzeug"]);
+        assert_eq!(graph.orphaned_by(&["flask".to_string()], &keep), vec!["werkzeug"]);
+        assert_eq!(graph.unreachable_from(&keep), vec!["flask", "werkzeug"]);
+        assert!(registry.declared_in_environment(None).is_empty());
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_entries_mirror_rendered_tree() {
+        let graph = graph();
+        let entries = graph.entries(&graph.roots(), None);
+        assert_eq!(entries.len(), 1);
+        let flask = &entries[0];
+        assert_eq!((flask.name.as_str(), flask.version.as_deref(), flask.required.as_deref()), ("Flask", Some("3.0.0"), None));
+        let names: Vec<&str> = flask.dependencies.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Werkzeug", "Jinja2", "click"]);
+
+        let colorama = &flask.dependencies[2].dependencies[0];
+        assert!(colorama.missing);
+        assert_eq!((colorama.version.as_deref(), colorama.required.as_deref()), (None, Some("")));
+        assert!(graph.entries(&graph.roots(), Some(1))[0].dependencies.iter().all(|e| e.dependencies.is_empty()));
+
+        let inverted = graph.inverted_entry("markupsafe", None);
+        let dependents: Vec<&str> = inverted.dependencies.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(dependents, vec!["Jinja2", "Werkzeug"]);
+        assert_eq!(inverted.dependencies[1].dependencies[0].name, "Flask");
+
+        let mut cyclic = graph;
+        cyclic.insert(node("a", "1", &[("b", "")]));
+        cyclic.insert(node("b", "1", &[("a", "")]));
+        let a = &cyclic.entries(&["a".to_string()], None)[0];
+        let again = &a.dependencies[0].dependencies[0];
+        assert_eq!(again.name, "a");
+        assert!(again.repeated && again.dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_chains_lead_from_roots_to_target() {
+        let mut graph = graph();
+        graph.insert(node("pytest", "8.0.0", &[("pluggy", "")]));
+        graph.insert(node("pluggy", "1.4.0", &[("pytest", "")]));
+
+        let roots = vec!["Flask".to_string(), "pytest".to_string()];
+        assert_eq!(graph.chains_to(&roots, "markupsafe", 10), vec![
+            vec!["flask", "werkzeug", "markupsafe"],
+            vec!["flask", "jinja2", "markupsafe"],
+        ]);
+        assert_eq!(graph.chains_to(&roots, "flask", 10), vec![vec!["flask"]]);
+        assert_eq!(graph.chains_to(&roots, "pluggy", 10), vec![vec!["pytest", "pluggy"]]);
+        assert_eq!(graph.chains_to(&roots, "markupsafe", 1).len(), 1);
+        assert!(graph.chains_to(&["pytest".to_string()], "markupsafe", 10).is_empty());
+    }
+
+    #[test]
+    fn test_reads_installed_metadata() {
+        let site = std::env::temp_dir().join(format!("snakepit-tree-{}", std::process::id()));
+        let dist = site.join("requests-2.31.0.dist-info");
+        std::fs::create_dir_all(&dist).unwrap();
+        std::fs::write(dist.join("METADATA"), "Metadata-Version: 2.1\nName: requests\nVersion: 2.31.0\nRequires-Dist: idna (<4,>=2.5)\nRequires-Dist: PySocks (!=1.5.7,>=1.5.6) ; extra == \"socks\"\n\nBody\n").unwrap();
+
+        let graph = DependencyGraph::from_site_packages(&site).unwrap();
+        let requests = graph.get("requests").unwrap();
+        assert_eq!(requests.version, "2.31.0");
+        assert_eq!(requests.requires.len(), 1);
+        assert_eq!(requests.requires[0].package, "idna");
+        let _ = std::fs::remove_dir_all(&site);
+    }
+}
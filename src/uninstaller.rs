@@ -2,11 +2,21 @@ use anyhow::{Result, Context};
 use serde::{Serialize, Deserialize};
 use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet};
-use snakegg::native::style::{red, green, yellow, blue, cyan, magenta, bold, dim};
+use std::time::{Duration, SystemTime};
+use snakegg::native::style::{blue, magenta};
 use snakegg::native::dirs;
 use snakegg::native::datetime::DateTime;
 use snakegg::charmer::SnakeCharmer;
+use crate::cache::{parse_age, parse_size};
+use crate::config::SnakepitConfig;
+use crate::freeze::{installed_distributions, InstalledDistribution};
+use crate::logger;
+use crate::manifest::canonical_name;
 use crate::installer::{PackageInstaller, InstallerBackend};
+use crate::registry::ProjectRegistry;
+
+/// Risk score above which `uninstall` asks first, when `uninstall_risk_threshold` isn't set
+pub const DEFAULT_RISK_THRESHOLD: u8 = 50;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImpactReport {
@@ -15,6 +25,36 @@ pub struct ImpactReport {
     pub risk_score: u8, // 0-100
     pub ai_analysis: Option<String>,
     pub breaking_changes: bool,
+    /// Other registered projects sharing this environment that still declare the package
+    #[serde(default)]
+    pub referencing_projects: Vec<PathBuf>,
+}
+
+/// Whether an uninstall goes ahead, given what `analyze_impact` and the backend found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UninstallGate {
+    /// Nothing to warn about
+    Clear,
+    /// A dry run only shows the plan and why it's risky, so it needs neither --force nor a yes
+    Preview,
+    /// --force overrides the warnings
+    Forced,
+    /// Risky: ask first
+    Confirm,
+    /// Protected: only --force removes it
+    Refuse,
+}
+
+impl UninstallGate {
+    pub fn decide(protected: bool, risky: bool, dry_run: bool, force: bool) -> Self {
+        match (protected || risky, dry_run, force) {
+            (false, _, _) => UninstallGate::Clear,
+            (true, true, _) => UninstallGate::Preview,
+            (true, false, true) => UninstallGate::Forced,
+            (true, false, false) if protected => UninstallGate::Refuse,
+            (true, false, false) => UninstallGate::Confirm,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,12 +64,436 @@ pub struct Snapshot {
     pub package: String,
     pub version: String,
     pub files_path: PathBuf,
+    /// Directory the archived paths are relative to (normally site-packages)
+    #[serde(default)]
+    pub source_dir: Option<PathBuf>,
+    /// `sys.prefix` of the environment the package was snapshotted from
+    #[serde(default)]
+    pub environment: Option<PathBuf>,
+    #[serde(default)]
+    pub interpreter: Option<PathBuf>,
+    /// The directory that was archived
+    #[serde(default)]
+    pub original_path: Option<PathBuf>,
+    /// Unix seconds when it was taken
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// Files in the archive
+    #[serde(default)]
+    pub file_count: Option<usize>,
+    /// Bytes the archive takes on disk
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl Snapshot {
+    /// Manifest written next to the archive (`<package>_<id>.json`)
+    fn manifest_path(archive: &Path) -> PathBuf {
+        archive.with_extension("json")
+    }
+
+    fn load(archive: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(Self::manifest_path(archive)).ok()?;
+        let mut snapshot: Snapshot = serde_json::from_str(&content).ok()?;
+        snapshot.files_path = archive.to_path_buf();
+        Some(snapshot)
+    }
+
+    /// A snapshot from before manifests, known only by its `<package>_<id>.zip` name
+    fn from_archive_name(archive: &Path) -> Option<Self> {
+        let name = archive.file_stem()?.to_string_lossy();
+        let (package, id) = name.rsplit_once('_')?;
+        Some(Snapshot {
+            id: id.to_string(),
+            timestamp: "unknown".to_string(),
+            package: package.to_string(),
+            version: "unknown".to_string(),
+            files_path: archive.to_path_buf(),
+            source_dir: None,
+            environment: None,
+            interpreter: None,
+            original_path: None,
+            created_at: None,
+            file_count: None,
+            size: None,
+        })
+    }
+
+    /// Take what older manifests don't record from the archive itself
+    fn fill_in(mut self) -> Self {
+        let metadata = std::fs::metadata(&self.files_path).ok();
+        if self.created_at.is_none() {
+            self.created_at = metadata.as_ref()
+                .and_then(|meta| meta.modified().ok())
+                .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|age| age.as_secs());
+        }
+        if self.size.is_none() {
+            self.size = metadata.map(|meta| meta.len());
+        }
+        if self.file_count.is_none() {
+            self.file_count = std::fs::File::open(&self.files_path).ok()
+                .and_then(|file| zip::ZipArchive::new(file).ok())
+                .map(|mut archive| (0..archive.len())
+                    .filter(|&i| archive.by_index(i).is_ok_and(|file| !file.is_dir()))
+                    .count());
+        }
+        self
+    }
+
+    fn save(&self) -> Result<()> {
+        std::fs::write(Self::manifest_path(&self.files_path), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// `index.json` in the snapshots directory: every snapshot's manifest in one file, so
+/// listing, restoring and pruning don't open each one
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SnapshotIndex {
+    snapshots: Vec<Snapshot>,
+}
+
+/// One distribution recorded by an environment snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentPackage {
+    pub name: String,
+    pub version: String,
+    /// What reinstalls exactly this: `name==version`, `name @ url` or `-e url`
+    pub requirement: String,
+    /// sha256 of its RECORD, which holds the hash of every file it installed
+    #[serde(default)]
+    pub record_sha256: Option<String>,
+}
+
+impl EnvironmentPackage {
+    pub fn from_installed(dist: &InstalledDistribution) -> Self {
+        Self {
+            name: dist.name.clone(),
+            version: dist.version.clone(),
+            requirement: dist.requirement(),
+            record_sha256: std::fs::read(dist.dist_info.join("RECORD")).ok()
+                .map(|record| snakegg::native::hash::compute_sha256_hex(&record)),
+        }
+    }
+
+    /// Whether the package comes from the index, so `requirement` can be installed by version
+    pub fn is_from_index(&self) -> bool {
+        self.requirement == format!("{}=={}", self.name, self.version)
+    }
+
+    /// Same version, and the same files when both RECORD hashes are known
+    fn matches(&self, other: &EnvironmentPackage) -> bool {
+        self.version == other.version && match (&self.record_sha256, &other.record_sha256) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        }
+    }
+}
+
+/// `snapshot create --env`: every package in an environment, and optionally all of its files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentSnapshot {
+    pub id: String,
+    pub timestamp: String,
+    /// The venv it was taken from; `None` for the user site-packages
+    pub environment: Option<PathBuf>,
+    pub site_packages: PathBuf,
+    pub packages: Vec<EnvironmentPackage>,
+    /// Archive of the whole site-packages, with `--files`
+    #[serde(default)]
+    pub files_path: Option<PathBuf>,
+    /// Unix seconds when it was taken
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// Bytes its archive takes on disk; nothing without one
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// A snapshot of either kind, as `snapshot delete` and retention see them
+#[derive(Debug, Clone)]
+pub enum StoredSnapshot {
+    Package(Snapshot),
+    Environment(EnvironmentSnapshot),
+}
+
+impl StoredSnapshot {
+    pub fn id(&self) -> &str {
+        match self {
+            StoredSnapshot::Package(snapshot) => &snapshot.id,
+            StoredSnapshot::Environment(snapshot) => &snapshot.id,
+        }
+    }
+
+    fn created_at(&self) -> Option<u64> {
+        match self {
+            StoredSnapshot::Package(snapshot) => snapshot.created_at,
+            StoredSnapshot::Environment(snapshot) => snapshot.created_at,
+        }
+    }
+
+    fn size(&self) -> u64 {
+        let size = match self {
+            StoredSnapshot::Package(snapshot) => snapshot.size,
+            StoredSnapshot::Environment(snapshot) => snapshot.size,
+        };
+        size.unwrap_or_default()
+    }
+}
+
+/// What rolling an environment back to a snapshot takes
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EnvironmentRollback {
+    /// Installed now but absent from the snapshot, or different there
+    pub remove: Vec<String>,
+    /// In the snapshot but absent now, or different now
+    pub install: Vec<EnvironmentPackage>,
+}
+
+impl EnvironmentRollback {
+    pub fn between(snapshot: &[EnvironmentPackage], current: &[EnvironmentPackage]) -> Self {
+        let find = |packages: &[EnvironmentPackage], name: &str| packages.iter()
+            .find(|package| canonical_name(&package.name) == canonical_name(name))
+            .cloned();
+        let remove = current.iter()
+            .filter(|package| !find(snapshot, &package.name).is_some_and(|wanted| wanted.matches(package)))
+            .map(|package| package.name.clone())
+            .collect();
+        let install = snapshot.iter()
+            .filter(|package| !find(current, &package.name).is_some_and(|installed| installed.matches(package)))
+            .cloned()
+            .collect();
+        Self { remove, install }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remove.is_empty() && self.install.is_empty()
+    }
+}
+
+/// A file inside a snapshot, relative to the directory it was taken from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// What a restore would do to one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChange {
+    /// Missing now; the restore puts it back
+    Added,
+    /// Different now; the restore overwrites it
+    Modified,
+    Unchanged,
+    /// Installed now but not in the snapshot; the restore leaves it alone
+    Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub change: FileChange,
+    /// Size in the snapshot; `None` for extra files
+    pub snapshot_size: Option<u64>,
+    /// Size on disk now; `None` for added files
+    pub installed_size: Option<u64>,
+}
+
+/// `snapshot diff`: a snapshot against what's installed where it would be restored
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDiff {
+    pub id: String,
+    pub package: String,
+    pub snapshot_version: String,
+    /// `None` when the package isn't installed there anymore
+    pub installed_version: Option<String>,
+    pub target: PathBuf,
+    pub files: Vec<FileDiff>,
+}
+
+impl SnapshotDiff {
+    pub fn count(&self, change: FileChange) -> usize {
+        self.files.iter().filter(|file| file.change == change).count()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Zip everything under `dir`, naming entries relative to `relative_to`; returns the number of files
+fn archive_directory(dir: &Path, relative_to: &Path, archive: &Path) -> Result<usize> {
+    use std::io::{Read, Write};
+
+    let file = std::fs::File::create(archive)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut buffer = Vec::new();
+    let mut count = 0;
+
+    // Simple recursive walker
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = path.strip_prefix(relative_to)?.to_string_lossy();
+                if path.is_dir() {
+                    zip.add_directory(name, options)?;
+                    stack.push(path.clone());
+                } else {
+                    zip.start_file(name, options)?;
+                    std::fs::File::open(&path)?.read_to_end(&mut buffer)?;
+                    zip.write_all(&buffer)?;
+                    buffer.clear();
+                    count += 1;
+                }
+            }
+        }
+    }
+    zip.finish()?;
+    Ok(count)
+}
+
+/// Extract `archive` into `target_dir`, overwriting what's there
+fn unpack_archive(archive: &Path, target_dir: &Path) -> Result<()> {
+    let file = std::fs::File::open(archive)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let outpath = match file.enclosed_name() {
+            Some(path) => target_dir.join(path),
+            None => continue,
+        };
+
+        if file.name().ends_with('/') {
+            std::fs::create_dir_all(&outpath)?;
+        } else {
+            if let Some(p) = outpath.parent() {
+                if !p.exists() {
+                    std::fs::create_dir_all(p)?;
+                }
+            }
+            let mut outfile = std::fs::File::create(&outpath)?;
+            std::io::copy(&mut file, &mut outfile)?;
+        }
+    }
+    Ok(())
+}
+
+/// Compare each file in `archive` with its counterpart under `target_dir`, then list the
+/// `installed_files` (relative to `target_dir`) the archive doesn't have
+fn diff_archive(archive: &Path, target_dir: &Path, installed_files: &[String]) -> Result<Vec<FileDiff>> {
+    use std::io::Read;
+
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+    let mut files = Vec::new();
+    let mut in_snapshot = HashSet::new();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(relative) = file.enclosed_name().map(|path| path.to_path_buf()) else { continue };
+        let path = file.name().to_string();
+        in_snapshot.insert(path.clone());
+
+        let on_disk = target_dir.join(&relative);
+        let installed_size = std::fs::metadata(&on_disk).ok().map(|meta| meta.len());
+        let change = match installed_size {
+            None => FileChange::Added,
+            Some(size) if size != file.size() => FileChange::Modified,
+            Some(_) => {
+                let mut archived = Vec::new();
+                file.read_to_end(&mut archived)?;
+                if std::fs::read(&on_disk)? == archived { FileChange::Unchanged } else { FileChange::Modified }
+            }
+        };
+        files.push(FileDiff { path, change, snapshot_size: Some(file.size()), installed_size });
+    }
+    for path in installed_files {
+        if in_snapshot.contains(path) {
+            continue;
+        }
+        let installed_size = std::fs::metadata(target_dir.join(path)).ok().map(|meta| meta.len());
+        files.push(FileDiff { path: path.clone(), change: FileChange::Extra, snapshot_size: None, installed_size });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// How many snapshots to keep, for how long, and in how much space; a limit that's
+/// `None` doesn't apply. The newest snapshot is always kept.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_count: Option<usize>,
+    pub max_age: Option<Duration>,
+    pub max_size: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// `snapshot_max_count`, `snapshot_max_age` and `snapshot_max_size`
+    pub fn from_config(config: &SnakepitConfig) -> Result<Self> {
+        Self::parse(config.snapshot_max_count, config.snapshot_max_age.as_deref(), config.snapshot_max_size.as_deref())
+    }
+
+    pub fn parse(max_count: Option<usize>, max_age: Option<&str>, max_size: Option<&str>) -> Result<Self> {
+        let max_age = match max_age {
+            Some(age) => Some(parse_age(age)
+                .ok_or_else(|| anyhow::anyhow!("Invalid age '{}' (expected e.g. 12h, 7d, 2w)", age))?),
+            None => None,
+        };
+        let max_size = match max_size {
+            Some(size) => Some(parse_size(size)
+                .ok_or_else(|| anyhow::anyhow!("Invalid size '{}' (expected e.g. 500MB, 2GB)", size))?),
+            None => None,
+        };
+        Ok(Self { max_count, max_age, max_size })
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_count.is_none() && self.max_age.is_none() && self.max_size.is_none()
+    }
+
+    /// Which of `snapshots`, given as `(age, size)` newest first, fall outside the policy
+    fn expired(&self, snapshots: &[(Duration, u64)]) -> Vec<usize> {
+        let mut total = 0;
+        let mut kept = 0;
+        let mut expired = Vec::new();
+        for (i, (age, size)) in snapshots.iter().enumerate() {
+            total += size;
+            if i == 0 {
+                kept += 1;
+                continue;
+            }
+            let too_many = self.max_count.is_some_and(|max| kept >= max);
+            let too_old = self.max_age.is_some_and(|max| *age > max);
+            let too_big = self.max_size.is_some_and(|max| total > max);
+            if too_many || too_old || too_big {
+                expired.push(i);
+                total -= size;
+            } else {
+                kept += 1;
+            }
+        }
+        expired
+    }
 }
 
 pub struct Uninstaller {
     installer: PackageInstaller,
+    backend: InstallerBackend,
     charmer: Option<SnakeCharmer>,
     snapshots_dir: PathBuf,
+    retention: RetentionPolicy,
 }
 
 impl Uninstaller {
@@ -43,28 +507,59 @@ impl Uninstaller {
 
         Ok(Self {
             installer: PackageInstaller::new(),
+            backend: InstallerBackend::detect(),
             charmer: SnakeCharmer::new().ok(),
             snapshots_dir,
+            retention: RetentionPolicy::default(),
         })
     }
 
+    pub fn with_backend(mut self, backend: InstallerBackend) -> Self {
+        self.installer = PackageInstaller::new().with_backend(backend.clone());
+        self.backend = backend;
+        self
+    }
+
+    /// Prune older snapshots to `retention` after each new one
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Why removing `package` could leave the environment unable to install anything, if it could
+    pub fn protection_reason(&self, package: &str) -> Option<String> {
+        self.backend.is_protected(package).then(|| format!(
+            "'{}' is infrastructure the {} backend relies on; removing it can break this environment beyond what snakepit can repair",
+            package, self.backend.name()
+        ))
+    }
+
+    /// Canonical names of the packages the backend itself needs
+    pub fn protected_packages(&self) -> Vec<String> {
+        self.backend.protected_packages().iter().map(|name| name.to_string()).collect()
+    }
+
     pub async fn analyze_impact(&self, package: &str) -> Result<ImpactReport> {
-        println!("{}", dim(format!("🔍 Analyzing impact of removing '{}'...", package)));
+        logger::note(format!("🔍 Analyzing impact of removing '{}'...", package));
 
         // 1. Find dependents (packages that depend on this one)
         let dependents = self.find_dependents(package).await?;
+
+        // 1.5. Find sibling projects that share this environment and still declare it
+        let referencing_projects = self.find_referencing_projects(package);
         
         let mut report = ImpactReport {
             package: package.to_string(),
             dependents: dependents.clone(),
-            risk_score: if dependents.is_empty() { 10 } else { 80 },
+            risk_score: if dependents.is_empty() && referencing_projects.is_empty() { 10 } else { 80 },
             ai_analysis: None,
-            breaking_changes: !dependents.is_empty(),
+            breaking_changes: !dependents.is_empty() || !referencing_projects.is_empty(),
+            referencing_projects,
         };
 
         // 2. AI Analysis
         if let Some(charmer) = &self.charmer {
-            println!("{}", magenta("🧠 Consulting Snake Charmer for risk prediction..."));
+            logger::info(magenta("🧠 Consulting Snake Charmer for risk prediction..."));
             if let Ok(analysis) = charmer.analyze_uninstall_risk(package, &dependents).await {
                 report.ai_analysis = Some(analysis);
             }
@@ -73,21 +568,36 @@ impl Uninstaller {
         Ok(report)
     }
 
+    fn find_referencing_projects(&self, package: &str) -> Vec<PathBuf> {
+        let registry = match ProjectRegistry::load() {
+            Ok(registry) => registry,
+            Err(_) => return Vec::new(),
+        };
+
+        let environment = ProjectRegistry::current_environment();
+        let current_project = std::env::current_dir().ok();
+
+        registry.referencing_projects(package, environment.as_deref(), current_project.as_deref())
+    }
+
     async fn find_dependents(&self, package: &str) -> Result<Vec<String>> {
         // Use Python's importlib.metadata to find reverse dependencies
         // This is robust and works across venvs
+        // Requirement names are compared PEP 503-normalized: `Foo_Bar>=1` depends on foo-bar
         let script = format!(
-            "import importlib.metadata; \
-            package = '{}'; \
-            dependents = []; \
-            for dist in importlib.metadata.distributions(): \
-                try: \
-                    requires = dist.requires or []; \
-                    if any(package == r.split(' ')[0] for r in requires): \
-                        dependents.append(dist.metadata['Name']); \
-                except: pass; \
+            "import importlib.metadata, re\n\
+            normalize = lambda name: re.sub(r'[-_.]+', '-', name).lower()\n\
+            package = '{}'\n\
+            dependents = []\n\
+            for dist in importlib.metadata.distributions():\n\
+            \x20   try:\n\
+            \x20       names = [re.match(r'[A-Za-z0-9._-]+', r) for r in dist.requires or []]\n\
+            \x20       if any(m and normalize(m.group(0)) == package for m in names):\n\
+            \x20           dependents.append(dist.metadata['Name'])\n\
+            \x20   except Exception:\n\
+            \x20       pass\n\
             print(','.join(dependents))",
-            package
+            crate::manifest::canonical_name(package)
         );
 
         let output = std::process::Command::new("python3")
@@ -110,16 +620,20 @@ impl Uninstaller {
     }
 
     pub async fn create_snapshot(&self, package: &str) -> Result<Snapshot> {
-        println!("{}", blue(format!("📸 Creating snapshot of '{}'...", package)));
+        logger::info(blue(format!("📸 Creating snapshot of '{}'...", package)));
         
-        // 1. Find package location
+        // 1. Find package location, plus the environment it lives in so restore can go back there
         let script = format!(
-            "import importlib.metadata; \
-            try: \
-                files = importlib.metadata.files('{}'); \
-                if files: \
-                    print(files[0].locate().parent); \
-            except: pass",
+            "import importlib.metadata, sys\n\
+            try:\n\
+            \x20   dist = importlib.metadata.distribution('{}')\n\
+            \x20   if dist.files:\n\
+            \x20       print(dist.files[0].locate().parent)\n\
+            \x20       print(dist.version)\n\
+            \x20       print(sys.prefix)\n\
+            \x20       print(sys.executable)\n\
+            except Exception:\n\
+            \x20   pass",
             package
         );
 
@@ -127,150 +641,665 @@ impl Uninstaller {
             .arg("-c")
             .arg(script)
             .output()?;
-            
-        let location = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let mut lines = stdout.lines().map(str::trim);
+        let location = lines.next().unwrap_or_default();
         if location.is_empty() {
             return Err(anyhow::anyhow!("Could not locate package '{}'", package));
         }
-        let package_path = PathBuf::from(&location);
+        let package_path = PathBuf::from(location);
+        let version = lines.next().unwrap_or("unknown").to_string();
+        let environment = lines.next().filter(|l| !l.is_empty()).map(PathBuf::from);
+        let interpreter = lines.next().filter(|l| !l.is_empty()).map(PathBuf::from);
 
         // 2. Zip the package directory
+        let mut index = self.read_index()?;
         let id = snakegg::native::id::new();
         let timestamp = DateTime::now().to_string();
         let snapshot_path = self.snapshots_dir.join(format!("{}_{}.zip", package, id));
-        
-        let file = std::fs::File::create(&snapshot_path)?;
-        let mut zip = zip::ZipWriter::new(file);
-        let options = zip::write::FileOptions::default()
-            .compression_method(zip::CompressionMethod::Deflated);
-
-        // Recursively add files
         let prefix = package_path.parent().unwrap_or(&package_path);
-        let mut buffer = Vec::new();
-        
-        // Simple recursive walker
-        let mut stack = vec![package_path.clone()];
-        while let Some(dir) = stack.pop() {
-            if let Ok(entries) = std::fs::read_dir(&dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        stack.push(path.clone());
-                        let name = path.strip_prefix(prefix)?.to_string_lossy();
-                        zip.add_directory(name, options)?;
-                    } else {
-                        let name = path.strip_prefix(prefix)?.to_string_lossy();
-                        zip.start_file(name, options)?;
-                        use std::io::Read;
-                        let mut f = std::fs::File::open(&path)?;
-                        f.read_to_end(&mut buffer)?;
-                        use std::io::Write;
-                        zip.write_all(&buffer)?;
-                        buffer.clear();
-                    }
-                }
-            }
-        }
-        zip.finish()?;
-        
-        Ok(Snapshot {
+        let file_count = archive_directory(&package_path, prefix, &snapshot_path)?;
+
+        let snapshot = Snapshot {
             id,
             timestamp,
             package: package.to_string(),
-            version: "unknown".to_string(), 
+            version,
+            source_dir: Some(prefix.to_path_buf()),
+            environment,
+            interpreter,
+            original_path: Some(package_path.clone()),
+            created_at: Some(unix_now()),
+            file_count: Some(file_count),
+            size: Some(std::fs::metadata(&snapshot_path)?.len()),
             files_path: snapshot_path,
-        })
+        };
+        snapshot.save()?;
+        index.push(snapshot.clone());
+        self.write_index(&index)?;
+
+        self.apply_retention();
+        Ok(snapshot)
     }
 
-    pub async fn restore_snapshot(&self, snapshot_id: &str) -> Result<()> {
-        println!("{}", green(format!("⏪ Restoring snapshot '{}'...", snapshot_id)));
-        
-        // Find the snapshot file
-        let mut snapshot_path = None;
-        if let Ok(entries) = std::fs::read_dir(&self.snapshots_dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    if name.contains(snapshot_id) && name.ends_with(".zip") {
-                        snapshot_path = Some(path);
-                        break;
-                    }
-                }
-            }
+    /// Prune to the configured retention after a new snapshot; failing to is only a warning
+    fn apply_retention(&self) {
+        if self.retention.is_unlimited() {
+            return;
+        }
+        match self.prune_snapshots(&self.retention) {
+            Ok((0, _)) => {}
+            Ok((count, freed)) => logger::note(format!("Pruned {} old snapshots ({})", count, crate::cache::format_size(freed))),
+            Err(e) => logger::warn(format!("⚠️  Failed to prune old snapshots: {}", e)),
         }
+    }
 
-        let snapshot_path = snapshot_path.ok_or_else(|| anyhow::anyhow!("Snapshot '{}' not found", snapshot_id))?;
-        
-        // Determine restore location (site-packages)
-        // We assume the zip structure preserves the relative path from site-packages
-        // But we need to find site-packages first.
-        // We can use python to find it.
-        let output = std::process::Command::new("python3")
-            .arg("-c")
-            .arg("import site; print(site.getsitepackages()[0])")
-            .output()?;
-        let site_packages = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if site_packages.is_empty() {
-             return Err(anyhow::anyhow!("Could not locate site-packages"));
+    /// Delete the snapshots, package and environment alike, that fall outside `policy`,
+    /// oldest first, returning how many went and the bytes freed
+    pub fn prune_snapshots(&self, policy: &RetentionPolicy) -> Result<(usize, u64)> {
+        let now = unix_now();
+        let mut snapshots: Vec<StoredSnapshot> = self.read_index()?.into_iter()
+            .map(StoredSnapshot::Package)
+            .chain(self.list_environment_snapshots()?.into_iter().map(StoredSnapshot::Environment))
+            .collect();
+        snapshots.sort_by(|a, b| b.created_at().cmp(&a.created_at()).then_with(|| b.id().cmp(a.id())));
+
+        let ages: Vec<(Duration, u64)> = snapshots.iter()
+            .map(|snapshot| (
+                Duration::from_secs(now.saturating_sub(snapshot.created_at().unwrap_or(now))),
+                snapshot.size(),
+            ))
+            .collect();
+        let expired = policy.expired(&ages);
+        if expired.is_empty() {
+            return Ok((0, 0));
+        }
+        let mut freed = 0;
+        for &i in &expired {
+            self.remove_stored(&snapshots[i])?;
+            freed += snapshots[i].size();
         }
-        let target_dir = PathBuf::from(site_packages);
+        let kept: Vec<Snapshot> = snapshots.into_iter()
+            .enumerate()
+            .filter(|(i, _)| !expired.contains(i))
+            .filter_map(|(_, snapshot)| match snapshot {
+                StoredSnapshot::Package(snapshot) => Some(snapshot),
+                StoredSnapshot::Environment(_) => None,
+            })
+            .collect();
+        self.write_index(&kept)?;
+        Ok((expired.len(), freed))
+    }
 
-        // Unzip
-        let file = std::fs::File::open(&snapshot_path)?;
-        let mut archive = zip::ZipArchive::new(file)?;
+    /// The package snapshot with this ID, or this archive name (`<package>_<id>`)
+    pub async fn find_snapshot(&self, snapshot_id: &str) -> Result<Snapshot> {
+        match self.find_stored(snapshot_id)? {
+            StoredSnapshot::Package(snapshot) => Ok(snapshot),
+            StoredSnapshot::Environment(_) => Err(anyhow::anyhow!(
+                "'{}' is an environment snapshot; pass --env, or see `snakepit snapshot list --env`", snapshot_id
+            )),
+        }
+    }
 
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i)?;
-            let outpath = match file.enclosed_name() {
-                Some(path) => target_dir.join(path),
-                None => continue,
-            };
+    /// The package or environment snapshot with this ID
+    pub fn find_stored(&self, snapshot_id: &str) -> Result<StoredSnapshot> {
+        if let Some(snapshot) = self.read_index()?
+            .into_iter()
+            .find(|snapshot| snapshot.id == snapshot_id || format!("{}_{}", snapshot.package, snapshot.id) == snapshot_id)
+        {
+            return Ok(StoredSnapshot::Package(snapshot));
+        }
+        self.list_environment_snapshots()?
+            .into_iter()
+            .find(|snapshot| snapshot.id == snapshot_id)
+            .map(StoredSnapshot::Environment)
+            .ok_or_else(|| anyhow::anyhow!("Snapshot '{}' not found", snapshot_id))
+    }
 
-            if file.name().ends_with('/') {
-                std::fs::create_dir_all(&outpath)?;
-            } else {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        std::fs::create_dir_all(p)?;
+    /// Delete the package or environment snapshot with this ID
+    pub async fn delete_snapshot(&self, snapshot_id: &str) -> Result<StoredSnapshot> {
+        let snapshot = self.find_stored(snapshot_id)?;
+        self.remove_stored(&snapshot)?;
+        if let StoredSnapshot::Package(deleted) = &snapshot {
+            let mut index = self.read_index()?;
+            index.retain(|indexed| indexed.id != deleted.id);
+            self.write_index(&index)?;
+        }
+        Ok(snapshot)
+    }
+
+    /// A snapshot's files; a package snapshot's index entry is left to the caller
+    fn remove_stored(&self, snapshot: &StoredSnapshot) -> Result<()> {
+        match snapshot {
+            StoredSnapshot::Package(snapshot) => Self::remove_snapshot_files(&snapshot.files_path),
+            StoredSnapshot::Environment(snapshot) => {
+                let manifest = self.environment_snapshots_dir().join(format!("{}.json", snapshot.id));
+                for file in snapshot.files_path.iter().chain([&manifest]) {
+                    if file.exists() {
+                        std::fs::remove_file(file)
+                            .with_context(|| format!("Failed to remove {}", file.display()))?;
                     }
                 }
-                let mut outfile = std::fs::File::create(&outpath)?;
-                std::io::copy(&mut file, &mut outfile)?;
+                Ok(())
             }
         }
+    }
 
-        println!("{}", green("✓ Restore complete"));
+    /// Every snapshot, oldest first, from the index. The index is rebuilt from the
+    /// manifests when it's missing, unreadable or doesn't match the archives on disk.
+    fn read_index(&self) -> Result<Vec<Snapshot>> {
+        let archives: HashSet<PathBuf> = std::fs::read_dir(&self.snapshots_dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "zip"))
+            .collect();
+        let index = std::fs::read_to_string(self.index_path()).ok()
+            .and_then(|content| serde_json::from_str::<SnapshotIndex>(&content).ok());
+        if let Some(index) = index {
+            let indexed: HashSet<PathBuf> = index.snapshots.iter().map(|snapshot| snapshot.files_path.clone()).collect();
+            if indexed == archives {
+                return Ok(index.snapshots);
+            }
+        }
+
+        let snapshots: Vec<Snapshot> = archives.iter()
+            .filter_map(|archive| Snapshot::load(archive).or_else(|| Snapshot::from_archive_name(archive)))
+            .map(Snapshot::fill_in)
+            .collect();
+        self.write_index(&snapshots)?;
+        Ok(self.sorted(snapshots))
+    }
+
+    fn write_index(&self, snapshots: &[Snapshot]) -> Result<()> {
+        let index = SnapshotIndex { snapshots: self.sorted(snapshots.to_vec()) };
+        std::fs::write(self.index_path(), serde_json::to_string_pretty(&index)?)?;
         Ok(())
     }
 
-    pub async fn list_snapshots(&self) -> Result<Vec<Snapshot>> {
+    fn sorted(&self, mut snapshots: Vec<Snapshot>) -> Vec<Snapshot> {
+        snapshots.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        snapshots
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.snapshots_dir.join("index.json")
+    }
+
+    /// Every file in the snapshot's archive, with its uncompressed size
+    pub fn snapshot_contents(&self, snapshot: &Snapshot) -> Result<Vec<SnapshotFile>> {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&snapshot.files_path)?)?;
+        let mut files = Vec::new();
+        for i in 0..archive.len() {
+            let file = archive.by_index(i)?;
+            if !file.is_dir() {
+                files.push(SnapshotFile { path: file.name().to_string(), size: file.size() });
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// What restoring `snapshot` into `target` (or where it came from) would change
+    pub fn diff_snapshot(&self, snapshot: &Snapshot, target: Option<&Path>) -> Result<SnapshotDiff> {
+        let target_dir = Self::restore_dir(Some(snapshot), &snapshot.id, target)?;
+        let installed = crate::package_info::PackageInfo::installed(&target_dir, &snapshot.package)?;
+        let installed_files = installed.as_ref().map(|info| info.files.clone()).unwrap_or_default();
+        Ok(SnapshotDiff {
+            id: snapshot.id.clone(),
+            package: snapshot.package.clone(),
+            snapshot_version: snapshot.version.clone(),
+            installed_version: installed.map(|info| info.version),
+            target: target_dir.clone(),
+            files: diff_archive(&snapshot.files_path, &target_dir, &installed_files)?,
+        })
+    }
+
+    /// Record every package in `site_packages`, plus an archive of all its files when
+    /// `with_files`, so the whole environment can be rolled back
+    pub fn create_environment_snapshot(&self, site_packages: &Path, environment: Option<PathBuf>, with_files: bool) -> Result<EnvironmentSnapshot> {
+        logger::info(blue(format!("📸 Creating snapshot of {}...", site_packages.display())));
+        let dir = self.environment_snapshots_dir();
+        std::fs::create_dir_all(&dir)?;
+
+        let id = snakegg::native::id::new();
+        let packages = installed_distributions(site_packages)?.iter()
+            .map(EnvironmentPackage::from_installed)
+            .collect();
+        let files_path = if with_files {
+            let archive = dir.join(format!("{}.zip", id));
+            archive_directory(site_packages, site_packages, &archive)?;
+            Some(archive)
+        } else {
+            None
+        };
+        let size = match &files_path {
+            Some(archive) => Some(std::fs::metadata(archive)?.len()),
+            None => Some(0),
+        };
+        let snapshot = EnvironmentSnapshot {
+            id,
+            timestamp: DateTime::now().to_string(),
+            environment,
+            site_packages: site_packages.to_path_buf(),
+            packages,
+            files_path,
+            created_at: Some(unix_now()),
+            size,
+        };
+        std::fs::write(dir.join(format!("{}.json", snapshot.id)), serde_json::to_string_pretty(&snapshot)?)?;
+        self.apply_retention();
+        Ok(snapshot)
+    }
+
+    pub fn list_environment_snapshots(&self) -> Result<Vec<EnvironmentSnapshot>> {
         let mut snapshots = Vec::new();
-        if let Ok(entries) = std::fs::read_dir(&self.snapshots_dir) {
+        if let Ok(entries) = std::fs::read_dir(self.environment_snapshots_dir()) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().map_or(false, |ext| ext == "zip") {
-                    let name = path.file_stem().unwrap().to_string_lossy();
-                    // name format: package_id
-                    let parts: Vec<&str> = name.split('_').collect();
-                    if parts.len() >= 2 {
-                        let package = parts[0..parts.len()-1].join("_");
-                        let id = parts.last().unwrap().to_string();
-                        
-                        snapshots.push(Snapshot {
-                            id,
-                            timestamp: "unknown".to_string(), // Metadata not stored in filename
-                            package,
-                            version: "unknown".to_string(),
-                            files_path: path,
-                        });
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    let content = std::fs::read_to_string(&path)?;
+                    let mut snapshot = serde_json::from_str::<EnvironmentSnapshot>(&content)
+                        .with_context(|| format!("Failed to parse {}", path.display()))?;
+                    // Older manifests don't record these; the files on disk do
+                    if snapshot.created_at.is_none() {
+                        snapshot.created_at = std::fs::metadata(&path).ok()
+                            .and_then(|meta| meta.modified().ok())
+                            .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+                            .map(|age| age.as_secs());
+                    }
+                    if snapshot.size.is_none() {
+                        snapshot.size = Some(snapshot.files_path.as_ref()
+                            .and_then(|archive| std::fs::metadata(archive).ok())
+                            .map_or(0, |meta| meta.len()));
                     }
+                    snapshots.push(snapshot);
                 }
             }
         }
+        snapshots.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
         Ok(snapshots)
     }
 
+    pub fn find_environment_snapshot(&self, snapshot_id: &str) -> Result<EnvironmentSnapshot> {
+        self.list_environment_snapshots()?
+            .into_iter()
+            .find(|snapshot| snapshot.id == snapshot_id)
+            .ok_or_else(|| anyhow::anyhow!("Environment snapshot '{}' not found", snapshot_id))
+    }
+
+    /// Roll the chosen environment back to `snapshot`: remove what it didn't have (or had
+    /// differently), then put back its files or, without an archive, reinstall its versions
+    pub async fn restore_environment(&self, snapshot: &EnvironmentSnapshot) -> Result<EnvironmentRollback> {
+        logger::success(format!("⏪ Restoring environment snapshot '{}'...", snapshot.id));
+        let installer = PackageInstaller::new().with_backend(self.backend.clone());
+        let site_packages = installer.site_packages()?;
+        let current: Vec<EnvironmentPackage> = installed_distributions(&site_packages)?.iter()
+            .map(EnvironmentPackage::from_installed)
+            .collect();
+        let mut rollback = EnvironmentRollback::between(&snapshot.packages, &current);
+        if rollback.is_empty() {
+            logger::success("✓ Environment already matches the snapshot");
+            return Ok(rollback);
+        }
+
+        // Removing the installer itself would leave nothing to put things back with
+        let (protected, removable): (Vec<String>, Vec<String>) = rollback.remove.drain(..)
+            .partition(|name| self.backend.is_protected(name) && !rollback.install.iter().any(|p| canonical_name(&p.name) == canonical_name(name)));
+        for name in &protected {
+            logger::warn(format!("⚠️  Keeping {}: the {} backend needs it", name, self.backend.name()));
+        }
+        rollback.remove = removable;
+        for name in &rollback.remove {
+            installer.uninstall_package(name).await
+                .with_context(|| format!("Failed to uninstall {}", name))?;
+            logger::note(format!("  - removed {}", name));
+        }
+
+        match &snapshot.files_path {
+            Some(archive) => {
+                logger::note(format!("Unpacking {} files into {}", snapshot.packages.len(), site_packages.display()));
+                unpack_archive(archive, &site_packages)?;
+            }
+            None => {
+                let (from_index, elsewhere): (Vec<&EnvironmentPackage>, Vec<&EnvironmentPackage>) = rollback.install.iter()
+                    .partition(|package| package.is_from_index());
+                let pinned: Vec<crate::resolver::ResolvedDependency> = from_index.iter()
+                    .map(|package| crate::resolver::ResolvedDependency {
+                        name: package.name.clone(),
+                        version: package.version.clone(),
+                        is_dev: false,
+                        dependencies: Vec::new(),
+                        source: None,
+                    })
+                    .collect();
+                installer.install_dependencies(&pinned).await?;
+                for package in elsewhere {
+                    logger::warn(format!("⚠️  {} wasn't installed from the index; reinstall it with: {}", package.name, package.requirement));
+                }
+            }
+        }
+        logger::success("✓ Environment restored");
+        Ok(rollback)
+    }
+
+    fn environment_snapshots_dir(&self) -> PathBuf {
+        self.snapshots_dir.join("environments")
+    }
+
+    /// The archive and its manifest
+    fn archive_files(archive: &Path) -> [PathBuf; 2] {
+        [archive.to_path_buf(), Snapshot::manifest_path(archive)]
+    }
+
+    fn remove_snapshot_files(archive: &Path) -> Result<()> {
+        for file in Self::archive_files(archive) {
+            if file.exists() {
+                std::fs::remove_file(&file)
+                    .with_context(|| format!("Failed to remove {}", file.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Unpack a snapshot back into the environment it was taken from.
+    ///
+    /// Restoring anywhere else, or restoring a snapshot that predates environment
+    /// tracking, needs an explicit `target` (a venv or a site-packages directory).
+    pub async fn restore_snapshot(&self, snapshot_id: &str, target: Option<&Path>) -> Result<()> {
+        logger::success(format!("⏪ Restoring snapshot '{}'...", snapshot_id));
+        let snapshot = self.find_snapshot(snapshot_id).await?;
+        let target_dir = Self::restore_dir(Some(&snapshot), snapshot_id, target)?;
+        logger::note(format!("Restoring {} {} ({} files) into {}",
+            snapshot.package, snapshot.version, snapshot.file_count.unwrap_or_default(), target_dir.display()));
+        unpack_archive(&snapshot.files_path, &target_dir)?;
+
+        logger::success("✓ Restore complete");
+        Ok(())
+    }
+
+    /// Where a snapshot's files go back to: `target` when given, otherwise the
+    /// directory it was taken from, as long as that still exists
+    fn restore_dir(snapshot: Option<&Snapshot>, snapshot_id: &str, target: Option<&Path>) -> Result<PathBuf> {
+        let source_dir = snapshot.and_then(|s| s.source_dir.clone());
+        let target_dir = match (target, &source_dir) {
+            (Some(target), _) => {
+                let target_dir = Self::resolve_target(target)?;
+                if let Some(source) = source_dir.as_ref().filter(|source| !Self::same_dir(source, &target_dir)) {
+                    logger::warn(format!(
+                        "⚠️  Snapshot was taken from {}; restoring into {} as requested",
+                        source.display(), target_dir.display()
+                    ));
+                }
+                target_dir
+            }
+            (None, Some(source)) if source.exists() => {
+                if let Some(interpreter) = snapshot.and_then(|s| s.interpreter.as_ref()) {
+                    if !interpreter.exists() {
+                        logger::warn(format!("⚠️  Original interpreter {} is gone; files are restored anyway", interpreter.display()));
+                    }
+                }
+                source.clone()
+            }
+            (None, Some(source)) => {
+                return Err(anyhow::anyhow!(
+                    "Snapshot '{}' came from {}, which no longer exists; pass --target <venv or site-packages> to restore elsewhere",
+                    snapshot_id, source.display()
+                ));
+            }
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "Snapshot '{}' doesn't record where it came from; pass --target <venv or site-packages>",
+                    snapshot_id
+                ));
+            }
+        };
+        Ok(target_dir)
+    }
+
+    /// A venv root maps to its site-packages; anything else is taken as the directory itself
+    fn resolve_target(target: &Path) -> Result<PathBuf> {
+        if target.join("pyvenv.cfg").exists() {
+            return PackageInstaller::new()
+                .with_venv(target.to_string_lossy().to_string())
+                .site_packages();
+        }
+        if !target.is_dir() {
+            return Err(anyhow::anyhow!("Restore target {} is not a directory", target.display()));
+        }
+        Ok(target.to_path_buf())
+    }
+
+    fn same_dir(a: &Path, b: &Path) -> bool {
+        match (a.canonicalize(), b.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => a == b,
+        }
+    }
+
+    /// Every snapshot, oldest first
+    pub async fn list_snapshots(&self) -> Result<Vec<Snapshot>> {
+        self.read_index()
+    }
+
     pub async fn uninstall(&self, package: &str) -> Result<()> {
         self.installer.uninstall_package(package).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_keeps_the_newest_snapshots() {
+        let day = Duration::from_secs(24 * 3600);
+        let snapshots = [(Duration::ZERO, 100), (day, 100), (2 * day, 100), (10 * day, 100)];
+
+        let by_count = RetentionPolicy { max_count: Some(2), ..Default::default() };
+        assert_eq!(by_count.expired(&snapshots), vec![2, 3]);
+
+        let by_age = RetentionPolicy::parse(None, Some("7d"), None).unwrap();
+        assert_eq!(by_age.expired(&snapshots), vec![3]);
+
+        let by_size = RetentionPolicy { max_size: Some(250), ..Default::default() };
+        assert_eq!(by_size.expired(&snapshots), vec![2, 3]);
+
+        // Only the snapshots kept count towards max_count
+        let mixed = [(Duration::ZERO, 100), (10 * day, 100), (day, 100), (2 * day, 100)];
+        let count_and_age = RetentionPolicy::parse(Some(2), Some("7d"), None).unwrap();
+        assert_eq!(count_and_age.expired(&mixed), vec![1, 3]);
+
+        // The newest survives even a limit it breaks on its own
+        let strict = RetentionPolicy { max_count: Some(0), max_age: Some(Duration::ZERO), max_size: Some(1) };
+        assert_eq!(strict.expired(&snapshots), vec![1, 2, 3]);
+
+        assert!(RetentionPolicy::default().is_unlimited());
+        assert!(RetentionPolicy::parse(None, Some("soon"), None).is_err());
+    }
+
+    #[test]
+    fn test_dry_run_previews_risky_uninstalls() {
+        use UninstallGate::*;
+        // A dry run shows the plan for a risky or protected package without --force or a yes
+        assert_eq!(UninstallGate::decide(false, true, true, false), Preview);
+        assert_eq!(UninstallGate::decide(true, true, true, false), Preview);
+        assert_eq!(UninstallGate::decide(false, true, false, false), Confirm);
+        assert_eq!(UninstallGate::decide(true, false, false, false), Refuse);
+        assert_eq!(UninstallGate::decide(true, true, false, true), Forced);
+        assert_eq!(UninstallGate::decide(false, false, true, false), Clear);
+        assert_eq!(UninstallGate::decide(false, false, false, true), Clear);
+    }
+
+    #[test]
+    fn test_index_follows_the_archives() {
+        let dir = std::env::temp_dir().join(format!("snakepit-snapshot-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("site/demo")).unwrap();
+        std::fs::write(dir.join("site/demo/__init__.py"), "x = 1").unwrap();
+        std::fs::write(dir.join("site/demo/core.py"), "y = 2").unwrap();
+        let snapshots_dir = dir.join("snapshots");
+        std::fs::create_dir_all(&snapshots_dir).unwrap();
+        let uninstaller = Uninstaller {
+            installer: PackageInstaller::new(),
+            backend: InstallerBackend::detect(),
+            charmer: None,
+            snapshots_dir: snapshots_dir.clone(),
+            retention: RetentionPolicy::default(),
+        };
+
+        // One snapshot with a manifest, one from before manifests
+        let archive = |name: &str| {
+            let path = snapshots_dir.join(name);
+            archive_directory(&dir.join("site/demo"), &dir.join("site"), &path).unwrap();
+            path
+        };
+        let mut snapshot = Snapshot::from_archive_name(&archive("demo_aaaa.zip")).unwrap();
+        snapshot.version = "1.0".to_string();
+        snapshot.created_at = Some(100);
+        snapshot.save().unwrap();
+        archive("old-pkg_bbbb.zip");
+
+        let snapshots = uninstaller.read_index().unwrap();
+        assert!(snapshots_dir.join("index.json").exists());
+        let ids: Vec<&str> = snapshots.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["aaaa", "bbbb"]);
+        assert_eq!(snapshots[0].version, "1.0");
+        assert_eq!(snapshots[1].package, "old-pkg");
+        assert!(snapshots.iter().all(|s| s.file_count == Some(2) && s.size.is_some()));
+
+        // An archive removed behind the index's back drops out of it
+        std::fs::remove_file(snapshots_dir.join("old-pkg_bbbb.zip")).unwrap();
+        assert_eq!(uninstaller.read_index().unwrap().len(), 1);
+
+        let policy = RetentionPolicy { max_count: Some(0), ..Default::default() };
+        assert_eq!(uninstaller.prune_snapshots(&policy).unwrap().0, 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prune_covers_environment_snapshots() {
+        let dir = std::env::temp_dir().join(format!("snakepit-snapshot-prune-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("site/demo")).unwrap();
+        std::fs::write(dir.join("site/demo/__init__.py"), "x = 1").unwrap();
+        let snapshots_dir = dir.join("snapshots");
+        std::fs::create_dir_all(snapshots_dir.join("environments")).unwrap();
+        let uninstaller = Uninstaller {
+            installer: PackageInstaller::new(),
+            backend: InstallerBackend::detect(),
+            charmer: None,
+            snapshots_dir: snapshots_dir.clone(),
+            retention: RetentionPolicy::default(),
+        };
+
+        let package = |id: &str, created_at: u64| {
+            let archive = snapshots_dir.join(format!("demo_{}.zip", id));
+            archive_directory(&dir.join("site/demo"), &dir.join("site"), &archive).unwrap();
+            let mut snapshot = Snapshot::from_archive_name(&archive).unwrap().fill_in();
+            snapshot.created_at = Some(created_at);
+            snapshot.save().unwrap();
+        };
+        let environment = |id: &str, created_at: u64| {
+            let archive = snapshots_dir.join("environments").join(format!("{}.zip", id));
+            archive_directory(&dir.join("site"), &dir.join("site"), &archive).unwrap();
+            let snapshot = EnvironmentSnapshot {
+                id: id.to_string(),
+                timestamp: created_at.to_string(),
+                environment: None,
+                site_packages: dir.join("site"),
+                packages: Vec::new(),
+                size: Some(std::fs::metadata(&archive).unwrap().len()),
+                files_path: Some(archive),
+                created_at: Some(created_at),
+            };
+            std::fs::write(snapshots_dir.join("environments").join(format!("{}.json", id)), serde_json::to_string(&snapshot).unwrap()).unwrap();
+        };
+        package("p1", 100);
+        environment("e1", 200);
+        package("p2", 300);
+        environment("e2", 400);
+
+        // One budget across both kinds: the two newest stay, whatever they are
+        let policy = RetentionPolicy { max_count: Some(2), ..Default::default() };
+        let (count, freed) = uninstaller.prune_snapshots(&policy).unwrap();
+        assert_eq!(count, 2);
+        assert!(freed > 0);
+        let packages: Vec<String> = uninstaller.read_index().unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(packages, vec!["p2"]);
+        let environments: Vec<String> = uninstaller.list_environment_snapshots().unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(environments, vec!["e2"]);
+        assert!(!snapshots_dir.join("environments/e1.zip").exists());
+        assert!(!snapshots_dir.join("demo_p1.zip").exists());
+
+        // Environment IDs resolve for delete, and point package-only commands at --env
+        assert!(matches!(uninstaller.find_stored("e2").unwrap(), StoredSnapshot::Environment(_)));
+        assert!(uninstaller.find_snapshot("e2").await.unwrap_err().to_string().contains("--env"));
+        let deleted = uninstaller.delete_snapshot("e2").await.unwrap();
+        assert_eq!(deleted.id(), "e2");
+        assert!(uninstaller.list_environment_snapshots().unwrap().is_empty());
+        assert!(!snapshots_dir.join("environments/e2.zip").exists());
+        assert!(uninstaller.find_stored("e2").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_environment_rollback() {
+        let package = |name: &str, version: &str, record: Option<&str>| EnvironmentPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            requirement: format!("{}=={}", name, version),
+            record_sha256: record.map(str::to_string),
+        };
+        let snapshot = vec![
+            package("requests", "2.31.0", Some("aa")),
+            package("urllib3", "1.26.18", None),
+            package("idna", "3.6", Some("bb")),
+            package("six", "1.16.0", Some("cc")),
+        ];
+        let current = vec![
+            package("Requests", "2.31.0", Some("aa")),
+            package("urllib3", "2.2.1", None),
+            package("idna", "3.6", Some("edited")),
+            package("certifi", "2024.2.2", None),
+        ];
+
+        let rollback = EnvironmentRollback::between(&snapshot, &current);
+        assert_eq!(rollback.remove, vec!["urllib3", "idna", "certifi"]);
+        let install: Vec<&str> = rollback.install.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(install, vec!["urllib3", "idna", "six"]);
+        assert!(EnvironmentRollback::between(&snapshot, &snapshot).is_empty());
+        assert!(package("a", "1", None).is_from_index());
+    }
+
+    #[test]
+    fn test_diff_archive_against_installed_files() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("snakepit-snapshot-diff-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let site = dir.join("site-packages");
+        std::fs::create_dir_all(site.join("demo")).unwrap();
+        std::fs::write(site.join("demo/same.py"), "same").unwrap();
+        std::fs::write(site.join("demo/changed.py"), "new contents").unwrap();
+        std::fs::write(site.join("demo/new.py"), "added since").unwrap();
+
+        let archive = dir.join("demo_1.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        let options = zip::write::FileOptions::default();
+        for (name, contents) in [("demo/same.py", "same"), ("demo/changed.py", "old contents"), ("demo/gone.py", "deleted")] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let installed = ["demo/same.py", "demo/changed.py", "demo/new.py"].map(String::from);
+        let files = diff_archive(&archive, &site, &installed).unwrap();
+        let change = |path: &str| files.iter().find(|file| file.path == path).map(|file| file.change);
+        assert_eq!(change("demo/same.py"), Some(FileChange::Unchanged));
+        assert_eq!(change("demo/changed.py"), Some(FileChange::Modified));
+        assert_eq!(change("demo/gone.py"), Some(FileChange::Added));
+        assert_eq!(change("demo/new.py"), Some(FileChange::Extra));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+use anyhow::Result;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Response, StatusCode};
+use std::time::Duration;
+use crate::logger;
+use crate::config::SnakepitConfig;
+use crate::rate_limit::{self, Subsystem};
+use crate::resolver::PyPIPackageInfo;
+use crate::simple_index::SimpleIndexClient;
+
+/// The canonical PyPI JSON API
+pub const PYPI_JSON_API: &str = "https://pypi.org/pypi";
+
+/// PyPI's PEP 503 / PEP 691 simple index
+pub const PYPI_SIMPLE_INDEX: &str = "https://pypi.org/simple";
+
+/// Never sleep longer than this for a single Retry-After, however much the server asks for
+const MAX_RETRY_WAIT_SECS: u64 = 30;
+
+/// Where package metadata is fetched from, and how hard to try before falling back.
+///
+/// PyPI is always tried first; configured mirrors are only used once it is
+/// rate-limiting (429) or failing (5xx, connection errors) past the retry budget.
+/// A configured `index_url` replaces PyPI entirely and is spoken to via the simple API.
+/// Every request draws from the shared [`rate_limit`] budget, attributed to `subsystem`.
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    mirrors: Vec<String>,
+    retries: u32,
+    index_url: Option<String>,
+    subsystem: Subsystem,
+}
+
+impl Upstream {
+    pub fn new() -> Self {
+        Self {
+            mirrors: Vec::new(),
+            retries: 3,
+            index_url: None,
+            subsystem: Subsystem::Resolver,
+        }
+    }
+
+    pub fn from_config(config: &SnakepitConfig) -> Self {
+        let upstream = Self::new()
+            .with_mirrors(config.mirrors.clone().unwrap_or_default())
+            .with_retries(config.retries.unwrap_or(3));
+        match &config.index_url {
+            Some(index_url) => upstream.with_index_url(index_url),
+            None => upstream,
+        }
+    }
+
+    /// Use a private or self-hosted simple index instead of PyPI
+    pub fn with_index_url(mut self, index_url: &str) -> Self {
+        self.index_url = Some(index_url.to_string());
+        self
+    }
+
+    pub fn index_url(&self) -> Option<&str> {
+        self.index_url.as_deref()
+    }
+
+    pub fn with_mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Who `snakepit stats` attributes this upstream's requests to
+    pub fn with_subsystem(mut self, subsystem: Subsystem) -> Self {
+        self.subsystem = subsystem;
+        self
+    }
+
+    /// JSON API URLs for `package`, PyPI first and then each mirror
+    pub fn json_urls(&self, package: &str) -> Vec<String> {
+        self.json_api_bases()
+            .map(|base| format!("{}/{}/json", base, package))
+            .collect()
+    }
+
+    /// JSON API URLs for one release of `package`, whose `info` describes that release
+    pub fn release_json_urls(&self, package: &str, version: &str) -> Vec<String> {
+        self.json_api_bases()
+            .map(|base| format!("{}/{}/{}/json", base, package, version))
+            .collect()
+    }
+
+    fn json_api_bases(&self) -> impl Iterator<Item = String> + '_ {
+        std::iter::once(PYPI_JSON_API.to_string())
+            .chain(self.mirrors.iter().map(|m| Self::json_api_base(m)))
+    }
+
+    /// Mirrors are usually configured by their simple index URL; they serve the JSON API next to it
+    fn json_api_base(mirror: &str) -> String {
+        let base = mirror.trim_end_matches('/');
+        let base = base.strip_suffix("/simple").unwrap_or(base);
+        if base.ends_with("/pypi") {
+            base.to_string()
+        } else {
+            format!("{}/pypi", base)
+        }
+    }
+
+    /// GET the metadata document for `package`, walking PyPI and then the mirrors.
+    ///
+    /// Returns the first response that isn't a throttle or server error, so callers
+    /// still see 404s and 304s. Errors only when every source is unavailable.
+    pub async fn fetch_json(&self, client: &Client, package: &str, headers: HeaderMap) -> Result<Response> {
+        self.fetch_first(client, self.json_urls(package), headers, package).await
+    }
+
+    /// GET the metadata document for `package` at `version`, like [`Self::fetch_json`]
+    pub async fn fetch_release_json(&self, client: &Client, package: &str, version: &str) -> Result<Response> {
+        let what = format!("{} {}", package, version);
+        self.fetch_first(client, self.release_json_urls(package, version), HeaderMap::new(), &what).await
+    }
+
+    async fn fetch_first(&self, client: &Client, urls: Vec<String>, headers: HeaderMap, package: &str) -> Result<Response> {
+        let mut failures = Vec::new();
+
+        for (i, url) in urls.into_iter().enumerate() {
+            if i > 0 {
+                logger::note(format!("🪞 Trying mirror {}", url));
+            }
+            match self.get_with_retry(client, &url, &headers).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    logger::warn(format!("⚠️  {}", e));
+                    failures.push(e.to_string());
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("Package index unavailable for {} ({})", package, failures.join("; ")))
+    }
+
+    /// Simple index URLs to try: the configured index alone, or PyPI's followed by the mirrors
+    pub fn simple_index_urls(&self) -> Vec<String> {
+        if let Some(index_url) = &self.index_url {
+            return vec![index_url.trim_end_matches('/').to_string()];
+        }
+        std::iter::once(PYPI_SIMPLE_INDEX.to_string())
+            .chain(self.mirrors.iter().map(|m| {
+                let base = m.trim_end_matches('/');
+                let base = base.strip_suffix("/pypi").unwrap_or(base);
+                if base.ends_with("/simple") { base.to_string() } else { format!("{}/simple", base) }
+            }))
+            .collect()
+    }
+
+    /// Metadata via the PEP 503 / PEP 691 simple API, for indexes without PyPI's JSON API
+    pub async fn fetch_simple(&self, client: &Client, package: &str) -> Result<PyPIPackageInfo> {
+        let mut failures = Vec::new();
+
+        for index_url in self.simple_index_urls() {
+            logger::note(format!("📇 Querying simple index {} for {}", index_url, package));
+            let index = SimpleIndexClient::new(client.clone(), &index_url).with_subsystem(self.subsystem);
+            match index.fetch_package_info(package).await {
+                Ok(info) => return Ok(info),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        Err(anyhow::anyhow!("No simple index could serve {} ({})", package, failures.join("; ")))
+    }
+
+    /// `Requires-Dist` of `package` at `version` via the simple API; `None` when no index serves PEP 658 metadata for it
+    pub async fn fetch_simple_requires_dist(&self, client: &Client, package: &str, version: &str) -> Result<Option<Vec<String>>> {
+        let mut failures = Vec::new();
+
+        for index_url in self.simple_index_urls() {
+            let index = SimpleIndexClient::new(client.clone(), &index_url).with_subsystem(self.subsystem);
+            match index.fetch_requires_dist(package, version).await {
+                Ok(requires_dist) => return Ok(requires_dist),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        Err(anyhow::anyhow!("No simple index could serve {} {} ({})", package, version, failures.join("; ")))
+    }
+
+    async fn get_with_retry(&self, client: &Client, url: &str, headers: &HeaderMap) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let permit = rate_limit::acquire(self.subsystem).await;
+            let outcome = client.get(url).headers(headers.clone()).send().await;
+            drop(permit);
+
+            let (reason, retry_after) = match outcome {
+                Ok(response) if !is_degraded(response.status()) => return Ok(response),
+                Ok(response) => (
+                    format!("{} returned {}", url, response.status()),
+                    retry_after(response.headers()),
+                ),
+                Err(e) => (format!("{} unreachable: {}", url, e), None),
+            };
+
+            if attempt >= self.retries {
+                return Err(anyhow::anyhow!("{} (gave up after {} attempts)", reason, attempt + 1));
+            }
+
+            let wait = retry_after.unwrap_or_else(|| backoff(attempt));
+            logger::note(format!("⏳ {}; retrying in {:.1}s", reason, wait.as_secs_f64()));
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for Upstream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Throttling and server-side failures are worth retrying elsewhere; 4xx client errors are not
+pub fn is_degraded(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Delay-seconds form of Retry-After, capped at [`MAX_RETRY_WAIT_SECS`]
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs: u64 = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(secs.min(MAX_RETRY_WAIT_SECS)))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_millis(500u64.saturating_mul(1 << attempt.min(6)))
+        .min(Duration::from_secs(MAX_RETRY_WAIT_SECS))
+}
+
+/// Human-readable age of a cached document, for staleness warnings
+pub fn describe_age(secs: u64) -> String {
+    match secs {
+        s if s < 120 => format!("{}s", s),
+        s if s < 2 * 3600 => format!("{}m", s / 60),
+        s if s < 2 * 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_mirror_urls_point_at_json_api() {
+        let upstream = Upstream::new().with_mirrors(vec![
+            "https://pypi.tuna.tsinghua.edu.cn/simple/".to_string(),
+            "https://mirror.example.com/pypi".to_string(),
+        ]);
+
+        assert_eq!(upstream.json_urls("flask"), vec![
+            "https://pypi.org/pypi/flask/json",
+            "https://pypi.tuna.tsinghua.edu.cn/pypi/flask/json",
+            "https://mirror.example.com/pypi/flask/json",
+        ]);
+        assert_eq!(upstream.simple_index_urls(), vec![
+            "https://pypi.org/simple",
+            "https://pypi.tuna.tsinghua.edu.cn/simple",
+            "https://mirror.example.com/simple",
+        ]);
+
+        assert_eq!(upstream.release_json_urls("flask", "2.0.1")[1], "https://pypi.tuna.tsinghua.edu.cn/pypi/flask/2.0.1/json");
+
+        let private = upstream.with_index_url("https://devpi.internal/root/prod/+simple/");
+        assert_eq!(private.simple_index_urls(), vec!["https://devpi.internal/root/prod/+simple"]);
+    }
+
+    #[test]
+    fn test_retry_after_is_capped() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3600"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(MAX_RETRY_WAIT_SECS)));
+
+        assert!(is_degraded(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_degraded(StatusCode::BAD_GATEWAY));
+        assert!(!is_degraded(StatusCode::NOT_FOUND));
+    }
+}
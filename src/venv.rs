@@ -1,9 +1,11 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use snakegg::native::style::{red, green, yellow, blue, cyan, bold, dim};
+use snakegg::native::style::{red, green};
 use snakegg::native::progress::ProgressBar;
 use snakegg::native::which;
+use crate::logger;
 
 #[derive(Debug, Clone)]
 pub enum VenvBackend {
@@ -26,11 +28,63 @@ impl VenvBackend {
         }
     }
 
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Venv => "venv",
+            Self::Virtualenv => "virtualenv",
+            Self::Conda => "conda",
+            Self::Poetry => "poetry",
+        }
+    }
+}
+
+/// Recorded into every environment snakepit creates, for `venv info`
+pub const METADATA_FILE: &str = "snakepit-venv.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenvMetadata {
+    /// Backend that created it (`venv`, `virtualenv`, `conda`, `poetry`)
+    pub backend: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Interpreter it was created from, when snakepit chose one
+    #[serde(default)]
+    pub interpreter: Option<String>,
+    pub snakepit_version: String,
+}
+
+impl VenvMetadata {
+    pub fn load(venv_path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(venv_path.join(METADATA_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, venv_path: &Path) -> Result<()> {
+        std::fs::write(venv_path.join(METADATA_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// What `venv info` reports about an environment
+#[derive(Debug, Clone)]
+pub struct VenvInfo {
+    pub path: PathBuf,
+    pub interpreter: Option<PathBuf>,
+    pub python_version: Option<String>,
+    pub site_packages: Option<PathBuf>,
+    pub site_packages_size: u64,
+    pub package_count: usize,
+    /// From the recorded metadata, else when `pyvenv.cfg` was written
+    pub created_at: Option<u64>,
+    /// `None` for environments snakepit didn't create
+    pub metadata: Option<VenvMetadata>,
 }
 
 pub struct VirtualEnvironmentManager {
     backend: VenvBackend,
     base_path: PathBuf,
+    /// Interpreter to build venvs from instead of looking up `python<version>`
+    interpreter: Option<PathBuf>,
 }
 
 impl VirtualEnvironmentManager {
@@ -38,6 +92,7 @@ impl VirtualEnvironmentManager {
         Self {
             backend: VenvBackend::detect(),
             base_path: Self::get_default_venv_path(),
+            interpreter: None,
         }
     }
 
@@ -51,6 +106,11 @@ impl VirtualEnvironmentManager {
         self
     }
 
+    pub fn with_interpreter(mut self, interpreter: PathBuf) -> Self {
+        self.interpreter = Some(interpreter);
+        self
+    }
+
     fn get_default_venv_path() -> PathBuf {
         if let Some(home) = snakegg::native::dirs::home_dir() {
             home.join(".snakepit").join("venvs")
@@ -81,9 +141,56 @@ impl VirtualEnvironmentManager {
             green(format!("Created virtual environment '{}'", name))
         ));
 
+        if result.is_ok() {
+            let metadata = VenvMetadata {
+                backend: self.backend.name().to_string(),
+                created_at: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                interpreter: self.interpreter.as_ref().map(|p| p.to_string_lossy().to_string()),
+                snakepit_version: env!("CARGO_PKG_VERSION").to_string(),
+            };
+            if let Err(e) = metadata.save(&venv_path) {
+                logger::warn(format!("⚠️  Could not record venv metadata: {}", e));
+            }
+        }
+
         result
     }
 
+    /// Interpreter, size and origin of the environment at `venv_path`
+    pub fn info(&self, venv_path: &Path) -> Result<VenvInfo> {
+        if !venv_path.exists() {
+            return Err(anyhow::anyhow!("{} does not exist", venv_path.display()));
+        }
+        let config = read_pyvenv_cfg(venv_path);
+        let python_version = config.get("version")
+            .or_else(|| config.get("version_info"))
+            .cloned();
+        let site_packages = self.get_site_packages_path(venv_path).ok().filter(|p| p.exists());
+        let (site_packages_size, package_count) = match &site_packages {
+            Some(site) => (dir_size(site), count_packages(site)),
+            None => (0, 0),
+        };
+        let metadata = VenvMetadata::load(venv_path);
+        let created_at = metadata.as_ref().map(|m| m.created_at).or_else(|| {
+            let modified = std::fs::metadata(venv_path.join("pyvenv.cfg")).and_then(|m| m.modified()).ok()?;
+            modified.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs())
+        });
+
+        Ok(VenvInfo {
+            path: venv_path.to_path_buf(),
+            interpreter: self.get_python_path(venv_path).ok().filter(|p| p.exists()),
+            python_version,
+            site_packages,
+            site_packages_size,
+            package_count,
+            created_at,
+            metadata,
+        })
+    }
+
     pub async fn activate_venv(&self, name: &str) -> Result<PathBuf> {
         let venv_path = self.base_path.join(name);
         
@@ -91,12 +198,7 @@ impl VirtualEnvironmentManager {
             return Err(anyhow::anyhow!("Virtual environment '{}' does not exist", name));
         }
 
-        let python_path = self.get_python_path(&venv_path)?;
-        
-        println!("{}", green(format!("Virtual environment '{}' activated", name)));
-        println!("Python path: {}", python_path.display());
-        
-        Ok(python_path)
+        self.get_python_path(&venv_path)
     }
 
     pub async fn delete_venv(&self, name: &str) -> Result<()> {
@@ -194,10 +296,10 @@ impl VirtualEnvironmentManager {
     }
 
     async fn create_with_venv(&self, venv_path: &Path, python_version: Option<&str>) -> Result<PathBuf> {
-        let mut cmd = Command::new("python3");
+        let mut cmd = Command::new(self.interpreter.as_deref().unwrap_or(Path::new("python3")));
         cmd.arg("-m").arg("venv");
         
-        if let Some(version) = python_version {
+        if let (None, Some(version)) = (&self.interpreter, python_version) {
             // Try to use specific Python version
             let python_cmd = format!("python{}", version);
             if Command::new(&python_cmd).arg("--version").status().is_ok() {
@@ -221,7 +323,9 @@ impl VirtualEnvironmentManager {
     async fn create_with_virtualenv(&self, venv_path: &Path, python_version: Option<&str>) -> Result<PathBuf> {
         let mut cmd = Command::new("virtualenv");
         
-        if let Some(version) = python_version {
+        if let Some(interpreter) = &self.interpreter {
+            cmd.arg("-p").arg(interpreter);
+        } else if let Some(version) = python_version {
             cmd.arg("-p").arg(&format!("python{}", version));
         }
         
@@ -278,10 +382,177 @@ impl VirtualEnvironmentManager {
     }
 }
 
+/// `key = value` pairs of the environment's `pyvenv.cfg`
+fn read_pyvenv_cfg(venv_path: &Path) -> std::collections::HashMap<String, String> {
+    std::fs::read_to_string(venv_path.join("pyvenv.cfg"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries.flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Installed distributions: one `.dist-info` (or legacy `.egg-info`) each
+fn count_packages(site_packages: &Path) -> usize {
+    std::fs::read_dir(site_packages)
+        .map(|entries| entries.flatten()
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.ends_with(".dist-info") || name.ends_with(".egg-info")
+            })
+            .count())
+        .unwrap_or(0)
+}
+
+/// Where `snakepit run` creates a project's environment when it doesn't have one
+pub const PROJECT_VENV: &str = ".venv";
+
+/// The directory holding an environment's executables
+pub fn bin_dir(venv_path: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        venv_path.join("Scripts")
+    } else {
+        venv_path.join("bin")
+    }
+}
+
+/// `path` (a `PATH` value) with the environment's executables searched first, as `activate` does
+pub fn activated_path(venv_path: &Path, path: Option<std::ffi::OsString>) -> Result<std::ffi::OsString> {
+    let rest: Vec<PathBuf> = path.map(|p| std::env::split_paths(&p).collect()).unwrap_or_default();
+    Ok(std::env::join_paths(std::iter::once(bin_dir(venv_path)).chain(rest))?)
+}
+
+/// Shells `venv activate --print-script` writes activation snippets for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// bash, zsh, sh and friends
+    Posix,
+    Fish,
+    PowerShell,
+    Cmd,
+}
+
+impl Shell {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "bash" | "zsh" | "sh" | "dash" | "ksh" | "posix" => Ok(Self::Posix),
+            "fish" => Ok(Self::Fish),
+            "powershell" | "pwsh" => Ok(Self::PowerShell),
+            "cmd" => Ok(Self::Cmd),
+            other => Err(anyhow::anyhow!("Unknown shell '{}' (expected bash, zsh, fish, powershell or cmd)", other)),
+        }
+    }
+
+    /// The user's shell from `$SHELL`, or the platform default
+    pub fn detect() -> Self {
+        let name = login_shell().file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        Self::parse(&name).unwrap_or(if cfg!(target_os = "windows") { Self::PowerShell } else { Self::Posix })
+    }
+
+    /// A snippet that activates `venv_path` when evaluated by this shell
+    pub fn activation_script(&self, venv_path: &Path) -> String {
+        let venv = venv_path.to_string_lossy();
+        let bin = bin_dir(venv_path);
+        let bin = bin.to_string_lossy();
+        match self {
+            Self::Posix => {
+                let quote = |s: &str| format!("'{}'", s.replace('\'', "'\\''"));
+                format!("export VIRTUAL_ENV={}\nexport PATH={}:\"$PATH\"\nunset PYTHONHOME\nhash -r 2>/dev/null\n", quote(&venv), quote(&bin))
+            }
+            Self::Fish => {
+                let quote = |s: &str| format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+                format!("set -gx VIRTUAL_ENV {}\nset -gx PATH {} $PATH\nset -e PYTHONHOME\n", quote(&venv), quote(&bin))
+            }
+            Self::PowerShell => {
+                let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+                format!(
+                    "$env:VIRTUAL_ENV = {}\n$env:PATH = {} + [IO.Path]::PathSeparator + $env:PATH\nRemove-Item Env:PYTHONHOME -ErrorAction SilentlyContinue\n",
+                    quote(&venv), quote(&bin)
+                )
+            }
+            Self::Cmd => format!("set \"VIRTUAL_ENV={}\"\r\nset \"PATH={};%PATH%\"\r\nset PYTHONHOME=\r\n", venv, bin),
+        }
+    }
+}
+
+/// The program `snakepit shell` starts: `$SHELL`, or `%COMSPEC%` on Windows
+pub fn login_shell() -> PathBuf {
+    let var = if cfg!(target_os = "windows") { "COMSPEC" } else { "SHELL" };
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(if cfg!(target_os = "windows") { "cmd.exe" } else { "/bin/sh" }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_activated_path_puts_venv_first() {
+        let venv = Path::new("/work/demo/.venv");
+        let system = std::env::join_paths(["/usr/local/bin", "/usr/bin"]).unwrap();
+        let path = activated_path(venv, Some(system)).unwrap();
+        let entries: Vec<PathBuf> = std::env::split_paths(&path).collect();
+        assert_eq!(entries[0], bin_dir(venv));
+        assert_eq!(entries.len(), 3);
+        assert_eq!(std::env::split_paths(&activated_path(venv, None).unwrap()).count(), 1);
+    }
+
+    #[test]
+    fn test_info_reads_recorded_metadata() {
+        let venv = std::env::temp_dir().join(format!("snakepit-venv-info-{}", std::process::id()));
+        let site = if cfg!(target_os = "windows") {
+            venv.join("Lib").join("site-packages")
+        } else {
+            venv.join("lib").join("python3.12").join("site-packages")
+        };
+        std::fs::create_dir_all(site.join("six-1.16.0.dist-info")).unwrap();
+        std::fs::write(site.join("six.py"), vec![b'x'; 100]).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\nversion = 3.12.4\n").unwrap();
+
+        let manager = VirtualEnvironmentManager::new().with_backend(VenvBackend::Venv);
+        let info = manager.info(&venv).unwrap();
+        assert_eq!(info.python_version.as_deref(), Some("3.12.4"));
+        assert_eq!(info.package_count, 1);
+        assert_eq!(info.site_packages_size, 100);
+        assert!(info.metadata.is_none());
+        assert!(info.created_at.is_some());
+
+        let metadata = VenvMetadata { backend: "venv".to_string(), created_at: 42, interpreter: None, snakepit_version: "0.2.0".to_string() };
+        metadata.save(&venv).unwrap();
+        let info = manager.info(&venv).unwrap();
+        assert_eq!(info.created_at, Some(42));
+        assert_eq!(info.metadata, Some(metadata));
+
+        std::fs::remove_dir_all(&venv).unwrap();
+    }
+
+    #[test]
+    fn test_activation_scripts_quote_paths() {
+        let venv = Path::new("/home/o'neil/.venv");
+        let posix = Shell::parse("zsh").unwrap().activation_script(venv);
+        assert!(posix.starts_with("export VIRTUAL_ENV='/home/o'\\''neil/.venv'\n"));
+        assert!(posix.contains("unset PYTHONHOME"));
+
+        let fish = Shell::Fish.activation_script(venv);
+        assert!(fish.starts_with("set -gx VIRTUAL_ENV '/home/o\\'neil/.venv'\n"));
+        assert!(Shell::PowerShell.activation_script(venv).contains("$env:VIRTUAL_ENV = '/home/o''neil/.venv'"));
+        assert!(Shell::parse("tcsh").is_err());
+    }
+
     #[test]
     fn test_backend_detection() {
         let backend = VenvBackend::detect();
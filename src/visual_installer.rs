@@ -2,7 +2,8 @@ use anyhow::Result;
 use std::process::{Command, Stdio};
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
-use snakegg::native::style::{red, green, yellow, blue, cyan, bold, dim};
+use snakegg::native::style::{blue, cyan};
+use crate::logger;
 
 /// Visual installer that uses the snake game GUI
 pub struct VisualInstaller {
@@ -43,7 +44,7 @@ impl VisualInstaller {
             package.to_string()
         };
 
-        println!("{}", cyan("🐍 Launching visual installer..."));
+        logger::info(cyan("🐍 Launching visual installer..."));
 
         let mut cmd = Command::new(&self.vip_path);
         cmd.arg("install")
@@ -59,7 +60,7 @@ impl VisualInstaller {
             let reader = BufReader::new(stdout);
             for line in reader.lines() {
                 if let Ok(line) = line {
-                    println!("{}", line);
+                    eprintln!("{}", line);
                 }
             }
         }
@@ -67,7 +68,7 @@ impl VisualInstaller {
         let status = child.wait()?;
 
         if status.success() {
-            println!("{}", green("✓ Package installed successfully!"));
+            logger::success("✓ Package installed successfully!");
             Ok(())
         } else {
             Err(anyhow::anyhow!("Installation failed with exit code: {}", status.code().unwrap_or(-1)))
@@ -82,14 +83,14 @@ impl VisualInstaller {
             package.to_string()
         };
 
-        println!("{}", blue("Installing package (classic mode)..."));
+        logger::info(blue("Installing package (classic mode)..."));
 
         let output = Command::new("python3")
             .args(&["-m", "pip", "install", &package_spec])
             .output()?;
 
         if output.status.success() {
-            println!("{}", green("✓ Package installed successfully!"));
+            logger::success("✓ Package installed successfully!");
             Ok(())
         } else {
             let error_msg = String::from_utf8_lossy(&output.stderr);
@@ -105,7 +106,7 @@ impl VisualInstaller {
 
         if self.use_gui && packages.len() > 1 {
             // Use VIP for batch installation
-            println!("{}", cyan(format!("🐍 Installing {} packages with visualization...", packages.len())));
+            logger::info(cyan(format!("🐍 Installing {} packages with visualization...", packages.len())));
 
             let mut cmd = Command::new(&self.vip_path);
             cmd.arg("install");
@@ -119,7 +120,7 @@ impl VisualInstaller {
             let status = cmd.status()?;
 
             if status.success() {
-                println!("{}", green("✓ All packages installed successfully!"));
+                logger::success("✓ All packages installed successfully!");
                 Ok(())
             } else {
                 Err(anyhow::anyhow!("Batch installation failed"))
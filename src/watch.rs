@@ -0,0 +1,138 @@
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How long the tree must stay quiet before `run --watch` reruns: an editor's save or a
+/// `git checkout` touches many files at once
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Paths `run --watch` never reacts to: environments, caches, build output and VCS metadata
+pub const DEFAULT_IGNORES: &[&str] = &[
+    ".git", ".hg", ".svn", ".venv", "venv", "env", "__pycache__", "*.pyc", "*.pyo",
+    ".pytest_cache", ".mypy_cache", ".ruff_cache", ".tox", ".nox", "node_modules",
+    "build", "dist", "*.egg-info", "target", "*.swp", "*~", ".#*",
+];
+
+/// Names to ignore, matched against every component of a path relative to the root.
+/// `*` matches any run of characters and `?` one character.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreSet {
+    patterns: Vec<Regex>,
+}
+
+impl IgnoreSet {
+    pub fn new(patterns: impl IntoIterator<Item = String>) -> Self {
+        // Escaped globs always compile; one too large for the regex engine is skipped
+        let patterns = patterns.into_iter()
+            .filter_map(|pattern| crate::module_policy::glob_regex(&pattern).ok())
+            .collect();
+        Self { patterns }
+    }
+
+    /// The defaults, the simple entries of `root/.gitignore` (no negations), and `extra`
+    pub fn for_project(root: &Path, extra: &[String]) -> Self {
+        let gitignore = std::fs::read_to_string(root.join(".gitignore")).unwrap_or_default();
+        let from_git = gitignore.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+            .map(|line| line.trim_matches('/').to_string())
+            .filter(|line| !line.is_empty() && !line.contains('/'));
+        Self::new(DEFAULT_IGNORES.iter().map(|pattern| pattern.to_string())
+            .chain(from_git)
+            .chain(extra.iter().cloned()))
+    }
+
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        relative.components().any(|component| {
+            let name = component.as_os_str().to_string_lossy();
+            self.patterns.iter().any(|pattern| pattern.is_match(&name))
+        })
+    }
+}
+
+/// Reports changed files under a project root, skipping ignored ones
+pub struct ProjectWatcher {
+    // Dropping the watcher stops the events
+    _watcher: RecommendedWatcher,
+    changes: mpsc::UnboundedReceiver<PathBuf>,
+}
+
+impl ProjectWatcher {
+    pub fn new(root: &Path, ignore: IgnoreSet) -> Result<Self> {
+        let (sender, changes) = mpsc::unbounded_channel();
+        // Events carry resolved paths (/private/var on macOS for /var)
+        let root = root.canonicalize()?;
+        let base = root.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else { return };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                return;
+            }
+            for path in event.paths {
+                let relative = path.strip_prefix(&base).unwrap_or(&path);
+                if !ignore.is_ignored(relative) {
+                    let _ = sender.send(path);
+                }
+            }
+        })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+        Ok(Self { _watcher: watcher, changes })
+    }
+
+    /// Wait for a change, then for `debounce` of quiet; the changed paths, first change first
+    pub async fn next_batch(&mut self, debounce: Duration) -> Vec<PathBuf> {
+        let Some(first) = self.changes.recv().await else { return Vec::new() };
+        let mut batch = vec![first];
+        while let Ok(Some(path)) = tokio::time::timeout(debounce, self.changes.recv()).await {
+            if !batch.contains(&path) {
+                batch.push(path);
+            }
+        }
+        batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignores_defaults_gitignore_and_extra_patterns() {
+        let root = std::env::temp_dir().join(format!("snakepit-watch-ignore-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(".gitignore"), "# comment\n/coverage/\n*.log\n!keep.log\ndocs/_build\n").unwrap();
+
+        let ignore = IgnoreSet::for_project(&root, &["fixtures".to_string()]);
+        assert!(ignore.is_ignored(Path::new(".venv/lib/python3.12/site.py")));
+        assert!(ignore.is_ignored(Path::new("pkg/__pycache__/mod.cpython-312.pyc")));
+        assert!(ignore.is_ignored(Path::new("coverage/index.html")));
+        assert!(ignore.is_ignored(Path::new("debug.log")));
+        assert!(ignore.is_ignored(Path::new("tests/fixtures/data.json")));
+        assert!(!ignore.is_ignored(Path::new("src/app.py")));
+        assert!(!ignore.is_ignored(Path::new("environment.py")));
+        assert!(ignore.is_ignored(Path::new("pkg/demo.egg-info/PKG-INFO")));
+        assert!(!ignore.is_ignored(Path::new("pkg/demo_egg-info/PKG-INFO")));
+
+        // Runs of `*` against a long name that almost matches stay linear
+        let stars = IgnoreSet::new(["*a*a*a*a*a*a*a*a*a*b".to_string()]);
+        assert!(!stars.is_ignored(Path::new(&"a".repeat(200))));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batches_changes_until_quiet() {
+        let root = std::env::temp_dir().join(format!("snakepit-watch-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("__pycache__")).unwrap();
+        let mut watcher = ProjectWatcher::new(&root, IgnoreSet::for_project(&root, &[])).unwrap();
+
+        std::fs::write(root.join("__pycache__").join("app.pyc"), "ignored").unwrap();
+        std::fs::write(root.join("app.py"), "print('hi')\n").unwrap();
+        let batch = tokio::time::timeout(Duration::from_secs(5), watcher.next_batch(Duration::from_millis(100))).await.unwrap();
+        assert!(batch.iter().any(|path| path.ends_with("app.py")));
+        assert!(!batch.iter().any(|path| path.ends_with("app.pyc")));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
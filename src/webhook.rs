@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::time::Duration;
+use crate::logger;
+use crate::daemon_history::{DaemonEvent, EventKind};
+
+/// A slow endpoint mustn't pile up requests in the daemon
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The body POSTed for each event. `text` makes it a valid Slack (and Mattermost, Discord
+/// `/slack`) incoming-webhook message; logging stacks get the event's fields alongside it.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    pub text: String,
+    pub host: Option<String>,
+    #[serde(flatten)]
+    pub event: &'a DaemonEvent,
+}
+
+impl<'a> WebhookPayload<'a> {
+    pub fn new(event: &'a DaemonEvent, host: Option<String>) -> Self {
+        let mut text = match event.kind {
+            EventKind::Detected => format!(":mag: Missing module `{}` detected in {}", event.module, event.origin),
+            EventKind::Installed => format!(":white_check_mark: Installed `{}`", event.module),
+            EventKind::Failed => format!(":x: Failed to install `{}`", event.module),
+            _ => format!("`{}` {}", event.module, event.kind),
+        };
+        if let Some(host) = &host {
+            text.push_str(&format!(" on {}", host));
+        }
+        if let Some(detail) = &event.detail {
+            text.push_str(&format!(": {}", detail));
+        }
+        Self { text, host, event }
+    }
+}
+
+/// Whether `kind` is announced: detections and install outcomes, not the steps in between
+pub fn announces(kind: EventKind) -> bool {
+    matches!(kind, EventKind::Detected | EventKind::Installed | EventKind::Failed)
+}
+
+pub fn validate_url(url: &str) -> Result<()> {
+    if url.starts_with("https://") || url.starts_with("http://") {
+        Ok(())
+    } else {
+        Err(anyhow!("Webhook URL must start with http:// or https://"))
+    }
+}
+
+/// POST `event` to `url` in the background; a failing endpoint only gets a line in the output
+pub fn post(url: &str, event: &DaemonEvent) {
+    if !announces(event.kind) {
+        return;
+    }
+    let url = url.to_string();
+    let host = sysinfo::System::host_name();
+    let body = match serde_json::to_value(WebhookPayload::new(event, host)) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+    tokio::spawn(async move {
+        let sent = async {
+            reqwest::Client::builder()
+                .timeout(TIMEOUT)
+                .user_agent(concat!("snakepit/", env!("CARGO_PKG_VERSION")))
+                .build()?
+                .post(&url)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, reqwest::Error>(())
+        };
+        if let Err(e) = sent.await {
+            logger::note(format!("Webhook delivery failed: {}", e));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_is_slack_compatible() {
+        let event = DaemonEvent::new(EventKind::Failed, "yaml", "process 9")
+            .with_pid(Some(9))
+            .with_detail("no matching distribution");
+        let body = serde_json::to_value(WebhookPayload::new(&event, Some("build-01".to_string()))).unwrap();
+        assert_eq!(body["text"], ":x: Failed to install `yaml` on build-01: no matching distribution");
+        assert_eq!(body["kind"], "failed");
+        assert_eq!(body["pid"], 9);
+        assert_eq!(body["host"], "build-01");
+
+        assert!(announces(EventKind::Installed));
+        assert!(!announces(EventKind::Installing));
+        assert!(validate_url("ftp://example.com").is_err());
+    }
+}
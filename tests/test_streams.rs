@@ -0,0 +1,85 @@
+//! Integration tests for standard stream discipline: diagnostics go to stderr,
+//! data (lists, details, config values) goes to stdout
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn scratch_home(name: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("snakepit-streams-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&home);
+    std::fs::create_dir_all(&home).unwrap();
+    home
+}
+
+fn snakepit(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_snakepit"))
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_DATA_HOME", home.join(".local").join("share"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env_remove("VIRTUAL_ENV")
+        .current_dir(home)
+        .output()
+        .expect("failed to run snakepit")
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_venv_list_writes_nothing_to_stdout() {
+        let home = scratch_home("venv-empty");
+        let output = snakepit(&home, &["venv", "list"]);
+
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("No virtual environments found"));
+    }
+
+    #[test]
+    fn test_venv_list_data_on_stdout_header_on_stderr() {
+        let home = scratch_home("venv-list");
+        std::fs::create_dir_all(home.join(".snakepit").join("venvs").join("demo")).unwrap();
+
+        let output = snakepit(&home, &["venv", "list"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("demo"));
+        assert!(!stdout.contains("Available virtual environments"));
+        assert!(stderr.contains("Available virtual environments"));
+    }
+
+    #[test]
+    fn test_sync_error_goes_to_stderr() {
+        let home = scratch_home("sync");
+        let output = snakepit(&home, &["sync"]);
+
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("No dependency file found"));
+    }
+
+    #[test]
+    fn test_daemon_config_show_values_on_stdout() {
+        let home = scratch_home("daemon-config");
+        let output = snakepit(&home, &["daemon", "config", "show"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("Auto-install"));
+        assert!(!stdout.contains("Daemon Configuration"));
+    }
+
+    #[test]
+    fn test_empty_snapshot_list_writes_nothing_to_stdout() {
+        let home = scratch_home("snapshots");
+        let output = snakepit(&home, &["snapshot", "list"]);
+
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+}